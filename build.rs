@@ -0,0 +1,17 @@
+//! Generates the gRPC service of `proto/iec104.proto` for the `grpc` feature.
+
+fn main() {
+    println!("cargo:rerun-if-changed=build.rs");
+    #[cfg(feature = "grpc")]
+    grpc();
+}
+
+#[cfg(feature = "grpc")]
+fn grpc() {
+    println!("cargo:rerun-if-changed=proto/iec104.proto");
+    let files = protox::compile(["proto/iec104.proto"], ["proto"]).expect("invalid proto file");
+    tonic_prost_build::configure()
+        .build_client(false)
+        .compile_fds(files)
+        .expect("gRPC code generation failed");
+}
@@ -0,0 +1,455 @@
+//! Human-readable rendering of APDUs for commissioning logs and support.
+//!
+//! [`render_apdu`] lays out a frame over several lines: the APCI with its
+//! sequence numbers, then for I-frames the ASDU header and one line per
+//! information object with its decoded value, quality and time tag.
+//! [`render_frame`] does the same for raw bytes, e.g. from a capture or a
+//! [`TappedFrame`], and adds a hex dump.
+//!
+//! ```text
+//! I-frame  send=5 recv=3
+//!   Type   M_ME_TF_1 (36), 2 objects
+//!   COT    Spontaneous (3)
+//!   CA     1
+//!   IOA 100  Float 1.5  quality=Good  time=2024-02-29T13:45:30.250
+//!   IOA 101  Float -2  quality=OV  time=2024-02-29T13:45:30.250
+//! ```
+//!
+//! For interop debugging, [`log_frames`] prints every frame of a client's
+//! [`tap`](crate::client::Iec104Client::tap) on one line, direction first,
+//! as [`render_tapped`] does:
+//!
+//! ```text
+//! >> 68 04 07 00 00 00  U(STARTDT act)
+//! << 68 04 0B 00 00 00  U(STARTDT con)
+//! >> 68 04 01 00 0A 00  S(R=5)
+//! ```
+//!
+//! The layout is meant for people and may change between releases; use
+//! [`crate::json`] or the typed API for anything that is parsed again.
+
+use std::fmt::Write;
+
+use tokio::sync::broadcast;
+
+use crate::client::{FrameDirection, TappedFrame};
+use crate::codec::{decode_apdu, Apdu};
+use crate::error::Result;
+use crate::parser::parse_asdu;
+use crate::types::{Apci, Asdu, DataPoint, DataValue, InfoObject, Timestamp, TypeId};
+
+/// Render an APDU as multi-line text.
+pub fn render_apdu(apdu: &Apdu) -> String {
+    let mut out = String::new();
+    match apdu.apci {
+        Apci::IFrame { send_seq, recv_seq } => {
+            let _ = writeln!(out, "I-frame  send={} recv={}", send_seq, recv_seq);
+        }
+        Apci::SFrame { recv_seq } => {
+            let _ = writeln!(out, "S-frame  recv={}", recv_seq);
+        }
+        Apci::UFrame { .. } => {
+            let _ = writeln!(out, "U-frame  {}", apdu.apci);
+        }
+    }
+    if let Some(asdu) = &apdu.asdu {
+        render_asdu(&mut out, asdu);
+    }
+    out
+}
+
+/// Decode a frame (start byte included) and render it, preceded by the
+/// bytes in hex.
+pub fn render_frame(frame: &[u8]) -> Result<String> {
+    let (apdu, _) = decode_apdu(frame)?;
+    let mut out = String::new();
+    let _ = writeln!(out, "Frame    {}", hex(frame));
+    out.push_str(&render_apdu(&apdu));
+    Ok(out)
+}
+
+/// Describe an APDU on one line: the APCI, then for I-frames the type,
+/// cause, common address and the first IOA.
+pub fn summarize_apdu(apdu: &Apdu) -> String {
+    let mut out = apdu.apci.to_string();
+    if let Some(asdu) = &apdu.asdu {
+        let header = &asdu.header;
+        let _ = write!(out, " {} {}", header.type_id, header.cot);
+        if header.negative {
+            out.push_str(" negative");
+        }
+        if header.test {
+            out.push_str(" test");
+        }
+        let _ = write!(out, " CA={}", header.common_address);
+        if let Some(ioa) = crate::command::first_ioa(asdu) {
+            let _ = write!(out, " IOA={}", ioa);
+        }
+        if header.vsq.count > 1 {
+            let _ = write!(out, " (+{} more)", header.vsq.count - 1);
+        }
+    }
+    out
+}
+
+/// Describe a data point on one line, as [`render_apdu`] lists the points
+/// of an ASDU: `IOA 100  Float 1.5  quality=Good  time=...`.
+pub fn render_point(point: &DataPoint) -> String {
+    let mut out = String::new();
+    write_point(&mut out, point);
+    out
+}
+
+/// Describe a value with its kind, e.g. `Float 1.5` or `Double On`.
+pub fn render_value(value: &DataValue) -> String {
+    let mut out = String::new();
+    write_value(&mut out, value);
+    out
+}
+
+/// Format a time tag as ISO 8601, or `xx:mm:ss.mmm` for CP24Time2a, followed
+/// by `SU` and `IV` when those flags are set.
+pub fn render_timestamp(timestamp: &Timestamp) -> String {
+    let mut out = String::new();
+    write_timestamp(&mut out, timestamp);
+    out
+}
+
+/// Render a tapped frame as `>>` (sent) or `<<` (received), its bytes in
+/// hex and [`summarize_apdu`].
+pub fn render_tapped(frame: &TappedFrame) -> String {
+    let arrow = match frame.direction {
+        FrameDirection::Sent => ">>",
+        FrameDirection::Received => "<<",
+    };
+    format!(
+        "{} {}  {}",
+        arrow,
+        hex(&frame.bytes),
+        summarize_apdu(&frame.apdu)
+    )
+}
+
+/// Pass every frame from `tap` to `write` as rendered by [`render_tapped`],
+/// until the client is dropped.
+///
+/// Frames lost because `write` kept up too slowly are reported as a line of
+/// their own.
+///
+/// ```rust,ignore
+/// tokio::spawn(analyze::log_frames(client.tap(), |line| eprintln!("{}", line)));
+/// ```
+pub async fn log_frames(mut tap: broadcast::Receiver<TappedFrame>, mut write: impl FnMut(&str)) {
+    loop {
+        match tap.recv().await {
+            Ok(frame) => write(&render_tapped(&frame)),
+            Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                write(&format!("-- {} frame(s) not logged", skipped));
+            }
+            Err(broadcast::error::RecvError::Closed) => return,
+        }
+    }
+}
+
+fn render_asdu(out: &mut String, asdu: &Asdu) {
+    let header = &asdu.header;
+    let type_id = header.type_id;
+    let count = header.vsq.count;
+    let _ = write!(
+        out,
+        "  Type   {} ({}), {} object{}",
+        type_id,
+        type_id.as_u8(),
+        count,
+        if count == 1 { "" } else { "s" }
+    );
+    if header.vsq.sequence {
+        out.push_str(", sequence");
+    }
+    out.push('\n');
+
+    let _ = write!(out, "  COT    {} ({})", header.cot, header.cot.as_u8());
+    if header.negative {
+        out.push_str(", negative");
+    }
+    if header.test {
+        out.push_str(", test");
+    }
+    if header.originator != 0 {
+        let _ = write!(out, ", originator {}", header.originator);
+    }
+    out.push('\n');
+    let _ = writeln!(out, "  CA     {}", header.common_address);
+
+    // Objects built locally are only parsed once encoded
+    let encoded;
+    let asdu = if asdu.raw_data.is_empty() && !asdu.objects.is_empty() {
+        match Asdu::parse_bytes(asdu.encode().freeze()) {
+            Ok(parsed) => {
+                encoded = parsed;
+                &encoded
+            }
+            Err(err) => return render_undecoded(out, asdu, &err.to_string()),
+        }
+    } else {
+        asdu
+    };
+
+    if let TypeId::Other(_) = type_id {
+        return render_undecoded(out, asdu, "type not known to this crate");
+    }
+    match parse_asdu(asdu) {
+        Ok(points) if !points.is_empty() => {
+            for point in &points {
+                out.push_str("  ");
+                write_point(out, point);
+                out.push('\n');
+            }
+        }
+        Ok(_) => match InfoObject::parse_asdu(asdu) {
+            Ok(objects) => {
+                for (ioa, object) in objects {
+                    let _ = writeln!(out, "  IOA {}  {:?}", ioa, object);
+                }
+            }
+            Err(err) => render_undecoded(out, asdu, &err.to_string()),
+        },
+        Err(err) => render_undecoded(out, asdu, &err.to_string()),
+    }
+}
+
+fn write_point(out: &mut String, point: &DataPoint) {
+    let _ = write!(out, "IOA {}  ", point.ioa);
+    write_value(out, &point.value);
+    let _ = write!(out, "  quality={}", point.quality);
+    if let Some(timestamp) = &point.timestamp {
+        out.push_str("  time=");
+        write_timestamp(out, timestamp);
+    }
+}
+
+fn write_value(out: &mut String, value: &DataValue) {
+    match *value {
+        DataValue::Single(value) => {
+            let _ = write!(out, "Single {}", if value { "ON" } else { "OFF" });
+        }
+        DataValue::Double(value) => {
+            let _ = write!(out, "Double {:?}", value);
+        }
+        DataValue::Normalized(value) => {
+            let _ = write!(out, "Normalized {}", value);
+        }
+        DataValue::Scaled(value) => {
+            let _ = write!(out, "Scaled {}", value);
+        }
+        DataValue::Float(value) => {
+            let _ = write!(out, "Float {}", value);
+        }
+        DataValue::Counter(value) => {
+            let _ = write!(out, "Counter {}", value);
+        }
+        DataValue::Bitstring(value) => {
+            let _ = write!(out, "Bitstring 0x{:08X}", value);
+        }
+        DataValue::StepPosition { value, transient } => {
+            let _ = write!(out, "StepPosition {}", value);
+            if transient {
+                out.push_str(" (transient)");
+            }
+        }
+        DataValue::PackedSinglePoint { status, changes } => {
+            let _ = write!(
+                out,
+                "PackedSinglePoint status=0x{:04X} changes=0x{:04X}",
+                status, changes
+            );
+        }
+        DataValue::BinaryCounter {
+            value,
+            sequence,
+            carry,
+            adjusted,
+            invalid,
+        } => {
+            let _ = write!(out, "BinaryCounter {} seq={}", value, sequence);
+            for (set, flag) in [(carry, "CY"), (adjusted, "CA"), (invalid, "IV")] {
+                if set {
+                    let _ = write!(out, " {}", flag);
+                }
+            }
+        }
+        DataValue::Parameter { value, qpm } => {
+            let _ = write!(out, "Parameter {:?} {}", value, qpm);
+        }
+    }
+}
+
+fn write_timestamp(out: &mut String, timestamp: &Timestamp) {
+    match timestamp {
+        Timestamp::Full(time) => {
+            out.push_str(&time.to_iso8601());
+            if time.summer_time {
+                out.push_str(" SU");
+            }
+            if time.invalid {
+                out.push_str(" IV");
+            }
+        }
+        Timestamp::Partial(time) => {
+            let _ = write!(
+                out,
+                "xx:{:02}:{:02}.{:03}",
+                time.minutes,
+                time.milliseconds / 1000,
+                time.milliseconds % 1000
+            );
+            if time.invalid {
+                out.push_str(" IV");
+            }
+        }
+    }
+}
+
+fn render_undecoded(out: &mut String, asdu: &Asdu, reason: &str) {
+    let _ = writeln!(out, "  Objects not decoded: {}", reason);
+    if !asdu.raw_data.is_empty() {
+        let _ = writeln!(out, "  Data   {}", hex(&asdu.raw_data));
+    }
+}
+
+fn hex(bytes: &[u8]) -> String {
+    let octets: Vec<String> = bytes.iter().map(|b| format!("{:02X}", b)).collect();
+    octets.join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::codec::encode_apdu;
+    use crate::encoder::AsduBuilder;
+    use crate::types::{Cot, Cp56Time2a, Quality, UFunction};
+
+    #[test]
+    fn test_render_measured_values() {
+        let time = Cp56Time2a::from_bytes(&[0x2A, 0x76, 45, 13, 0x9D, 2, 24]).unwrap();
+        let asdu = AsduBuilder::new(TypeId::MeasuredFloatTime56)
+            .ca(1)
+            .add(100, DataValue::Float(1.5), Quality::Good)
+            .with_time(time)
+            .add(
+                101,
+                DataValue::Float(-2.0),
+                Quality::Good.set_overflow(true),
+            )
+            .with_time(time)
+            .build()
+            .unwrap();
+
+        let text = render_apdu(&Apdu::i_frame(5, 3, asdu));
+        assert_eq!(
+            text,
+            "I-frame  send=5 recv=3\n\
+             \x20 Type   M_ME_TF_1 (36), 2 objects\n\
+             \x20 COT    Spontaneous (3)\n\
+             \x20 CA     1\n\
+             \x20 IOA 100  Float 1.5  quality=Good  time=2024-02-29T13:45:30.250\n\
+             \x20 IOA 101  Float -2  quality=OV  time=2024-02-29T13:45:30.250\n"
+        );
+    }
+
+    #[test]
+    fn test_render_frame() {
+        let mut asdu = Asdu::interrogation_command(7, 20);
+        asdu.header.cot = Cot::ActivationConfirm;
+        asdu.header.negative = true;
+        let frame = encode_apdu(&Apdu::i_frame(0, 1, asdu)).unwrap();
+
+        let text = render_frame(&frame).unwrap();
+        let lines: Vec<&str> = text.lines().collect();
+        assert!(lines[0].starts_with("Frame    68 0E 00 00 02 00 64 01 47 00 07 00"));
+        assert_eq!(lines[1], "I-frame  send=0 recv=1");
+        assert_eq!(lines[2], "  Type   C_IC_NA_1 (100), 1 object");
+        assert_eq!(lines[3], "  COT    ActivationConfirm (7), negative");
+        assert!(lines[5].starts_with("  IOA 0  "));
+
+        let frame = encode_apdu(&Apdu::u_frame(UFunction::TestFrAct)).unwrap();
+        assert_eq!(
+            render_frame(&frame).unwrap().lines().nth(1),
+            Some("U-frame  U(TESTFR act)")
+        );
+        assert!(render_frame(&[0x68, 0x04]).is_err());
+    }
+
+    #[test]
+    fn test_render_tapped() {
+        let asdu = AsduBuilder::new(TypeId::SinglePoint)
+            .ca(1)
+            .add(100, DataValue::Single(true), Quality::Good)
+            .add(101, DataValue::Single(false), Quality::Good)
+            .build()
+            .unwrap();
+        let apdu = Apdu::i_frame(0, 0, asdu);
+        let frame = TappedFrame {
+            direction: FrameDirection::Received,
+            bytes: encode_apdu(&apdu).unwrap().freeze(),
+            apdu,
+        };
+        assert_eq!(
+            render_tapped(&frame),
+            "<< 68 12 00 00 00 00 01 02 03 00 01 00 64 00 00 01 65 00 00 00  \
+             I(S=0, R=0) M_SP_NA_1 Spontaneous CA=1 IOA=100 (+1 more)"
+        );
+
+        let apdu = Apdu::u_frame(UFunction::StartDtAct);
+        let frame = TappedFrame {
+            direction: FrameDirection::Sent,
+            bytes: encode_apdu(&apdu).unwrap().freeze(),
+            apdu,
+        };
+        assert_eq!(
+            render_tapped(&frame),
+            ">> 68 04 07 00 00 00  U(STARTDT act)"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_log_frames() {
+        let (tx, rx) = broadcast::channel(2);
+        for function in [
+            UFunction::TestFrAct,
+            UFunction::TestFrCon,
+            UFunction::StartDtAct,
+        ] {
+            let apdu = Apdu::u_frame(function);
+            let _ = tx.send(TappedFrame {
+                direction: FrameDirection::Sent,
+                bytes: encode_apdu(&apdu).unwrap().freeze(),
+                apdu,
+            });
+        }
+        drop(tx);
+
+        let mut lines = Vec::new();
+        log_frames(rx, |line| lines.push(line.to_string())).await;
+        assert_eq!(
+            lines,
+            [
+                "-- 1 frame(s) not logged",
+                ">> 68 04 83 00 00 00  U(TESTFR con)",
+                ">> 68 04 07 00 00 00  U(STARTDT act)"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_render_private_type() {
+        let data = [
+            0xC8, 0x01, 0x03, 0x00, 0x07, 0x00, 0x01, 0x00, 0x00, 0xDE, 0xAD,
+        ];
+        let asdu = Asdu::parse(&data).unwrap();
+        let text = render_apdu(&Apdu::i_frame(1, 1, asdu));
+        assert!(text.contains("  Type   TypeId(200) (200), 1 object\n"));
+        assert!(text.contains("  Objects not decoded: type not known to this crate\n"));
+        assert!(text.contains("  Data   01 00 00 DE AD\n"));
+    }
+}
@@ -0,0 +1,313 @@
+//! Command line client for checking IEC 60870-5-104 stations.
+//!
+//! Built with the `cli` feature:
+//!
+//! ```text
+//! cargo install voltage_iec104 --features cli
+//! iec104-cli 192.168.1.10:2404 --ca 1 interrogate
+//! iec104-cli 192.168.1.10:2404 --ca 1 single 3000 on --select
+//! iec104-cli 192.168.1.10:2404 --ca 1 --log-frames monitor
+//! ```
+//!
+//! Every command connects, starts data transfer, does its work and closes
+//! the connection again.
+
+use std::process::ExitCode;
+use std::time::Duration;
+
+use clap::{Parser, Subcommand, ValueEnum};
+use tokio::sync::mpsc;
+
+use voltage_iec104::analyze::{log_frames, render_point};
+use voltage_iec104::{
+    ClientConfig, ClientHandle, Command, CommandCompletion, CommandQualifier, DataPoint,
+    DoubleCommandState, Iec104Client, Iec104Error, Iec104Event, PulseDuration, Qos, Result,
+    SequencedEvent,
+};
+
+#[derive(Debug, Parser)]
+#[command(
+    name = "iec104-cli",
+    version,
+    about = "IEC 60870-5-104 client for field checks"
+)]
+struct Cli {
+    /// Station address, host:port
+    address: String,
+
+    /// Common address of the ASDU
+    #[arg(long, default_value_t = 1)]
+    ca: u16,
+
+    /// Seconds to wait for connection, confirmations and responses
+    #[arg(long, default_value_t = 10)]
+    timeout: u64,
+
+    /// Print every frame sent and received to stderr
+    #[arg(long)]
+    log_frames: bool,
+
+    #[command(subcommand)]
+    command: Action,
+}
+
+#[derive(Debug, Subcommand)]
+enum Action {
+    /// Connect, start data transfer and show the session parameters
+    Check,
+    /// Run a general interrogation and print the points
+    Interrogate,
+    /// Read one information object
+    Read {
+        /// Information object address
+        ioa: u32,
+    },
+    /// Send a single command (C_SC_NA_1)
+    Single {
+        /// Information object address
+        ioa: u32,
+        /// Value to command
+        state: Switch,
+        /// Select before execute
+        #[arg(long)]
+        select: bool,
+    },
+    /// Send a double command (C_DC_NA_1)
+    Double {
+        /// Information object address
+        ioa: u32,
+        /// Value to command
+        state: Switch,
+        /// Select before execute
+        #[arg(long)]
+        select: bool,
+    },
+    /// Send a setpoint command
+    Setpoint {
+        /// Information object address
+        ioa: u32,
+        /// Value to command
+        #[arg(allow_negative_numbers = true)]
+        value: f32,
+        /// Setpoint type
+        #[arg(long, value_enum, default_value_t = SetpointKind::Float)]
+        kind: SetpointKind,
+        /// Qualifier of set-point command (QL, 0-127)
+        #[arg(long, default_value_t = 0, value_parser = clap::value_parser!(u8).range(0..=127))]
+        ql: u8,
+        /// Select before execute
+        #[arg(long)]
+        select: bool,
+    },
+    /// Synchronize the station clock to this machine (C_CS_NA_1)
+    ClockSync,
+    /// Print data points as they arrive until the connection closes or Ctrl-C
+    Monitor {
+        /// Skip the general interrogation after connecting
+        #[arg(long)]
+        no_interrogation: bool,
+    },
+}
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+enum Switch {
+    On,
+    Off,
+}
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+enum SetpointKind {
+    Float,
+    Scaled,
+    Normalized,
+}
+
+#[tokio::main(flavor = "current_thread")]
+async fn main() -> ExitCode {
+    let cli = Cli::parse();
+    match run(cli).await {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(err) => {
+            eprintln!("error: {}", err);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+async fn run(cli: Cli) -> Result<()> {
+    let timeout = Duration::from_secs(cli.timeout);
+    let config = ClientConfig::new(cli.address.as_str()).connect_timeout(timeout);
+    let mut client = Iec104Client::new(config);
+    if cli.log_frames {
+        tokio::spawn(log_frames(client.tap(), |line| eprintln!("{}", line)));
+    }
+    // Only a monitor reads events; an unread queue would stall the link
+    let events = match cli.command {
+        Action::Monitor { .. } => client.subscribe(),
+        _ => None,
+    };
+
+    client.connect().await?;
+    client.start_dt().await?;
+    if let Action::Check = cli.command {
+        if let Some(session) = client.session_info() {
+            println!(
+                "Connected  {} -> {}",
+                session.local_address, session.peer_address
+            );
+            println!("k={} w={}", session.k, session.w);
+            println!(
+                "t1={:?} t2={:?} t3={:?}",
+                session.t1_timeout, session.t2_timeout, session.t3_timeout
+            );
+        }
+    }
+    let (handle, task) = client.spawn();
+
+    let result = act(&handle, cli.command, cli.ca, events, timeout).await;
+
+    // A failed command still closes the connection cleanly
+    let closed = handle.shutdown(timeout).await;
+    drop(handle);
+    let _ = task.await;
+    result.and(closed.or_else(ignore_closed))
+}
+
+/// Carry out `action` on a connection with data transfer started.
+async fn act(
+    handle: &ClientHandle,
+    action: Action,
+    ca: u16,
+    events: Option<mpsc::Receiver<SequencedEvent>>,
+    timeout: Duration,
+) -> Result<()> {
+    match action {
+        Action::Check => Ok(()),
+        Action::Interrogate => handle
+            .general_interrogation_snapshot(ca, timeout)
+            .await
+            .map(|points| print_points(&points)),
+        Action::Read { ioa } => handle
+            .read(ca, ioa, timeout)
+            .await
+            .map(|point| print_points(&[point])),
+        Action::Single { ioa, state, select } => {
+            let value = matches!(state, Switch::On);
+            execute(handle, ca, Command::Single { ioa, value }, select, timeout).await
+        }
+        Action::Double { ioa, state, select } => {
+            let value = match state {
+                Switch::On => DoubleCommandState::On,
+                Switch::Off => DoubleCommandState::Off,
+            };
+            execute(handle, ca, Command::Double { ioa, value }, select, timeout).await
+        }
+        Action::Setpoint {
+            ioa,
+            value,
+            kind,
+            ql,
+            select,
+        } => {
+            let qos = Qos::new(ql, false);
+            let command = match kind {
+                SetpointKind::Float => Command::SetpointFloat {
+                    ioa,
+                    value,
+                    qos,
+                },
+                SetpointKind::Scaled => Command::SetpointScaled {
+                    ioa,
+                    value: scaled(value)?,
+                    qos,
+                },
+                SetpointKind::Normalized => Command::SetpointNormalized {
+                    ioa,
+                    value,
+                    qos,
+                },
+            };
+            execute(handle, ca, command, select, timeout).await
+        }
+        Action::ClockSync => {
+            let completion = handle.clock_sync_now(ca).await?;
+            confirm(completion, timeout).await
+        }
+        Action::Monitor { no_interrogation } => {
+            if !no_interrogation {
+                handle.general_interrogation(ca).await?;
+            }
+            let Some(mut events) = events else {
+                return Err(Iec104Error::protocol_static("Event stream already taken"));
+            };
+            let ctrl_c = tokio::signal::ctrl_c();
+            tokio::pin!(ctrl_c);
+            loop {
+                tokio::select! {
+                    event = events.recv() => match event.map(|sequenced| sequenced.event) {
+                        Some(Iec104Event::DataUpdate(points)) => print_points(&points),
+                        Some(Iec104Event::Disconnected) | None => break Ok(()),
+                        Some(_) => {}
+                    },
+                    _ = &mut ctrl_c => break Ok(()),
+                }
+            }
+        }
+    }
+}
+
+/// Send a command, directly or with select before execute, and wait for the
+/// confirmation.
+async fn execute(
+    handle: &ClientHandle,
+    ca: u16,
+    command: Command,
+    select: bool,
+    timeout: Duration,
+) -> Result<()> {
+    command.validate()?;
+    if select {
+        handle
+            .select_then_execute(ca, command, PulseDuration::Unspecified, timeout)
+            .await?;
+        println!("{} IOA {} confirmed", command.type_id(), command.ioa());
+        return Ok(());
+    }
+    let completion = handle
+        .command(ca, command, CommandQualifier::EXECUTE)
+        .await?;
+    confirm(completion, timeout).await
+}
+
+async fn confirm(completion: CommandCompletion, timeout: Duration) -> Result<()> {
+    let type_id = completion.type_id();
+    let ioa = completion.ioa();
+    tokio::time::timeout(timeout, completion.confirmed())
+        .await
+        .unwrap_or(Err(Iec104Error::CommandTimeout { type_id, ioa }))?;
+    println!("{} IOA {} confirmed", type_id, ioa);
+    Ok(())
+}
+
+fn scaled(value: f32) -> Result<i16> {
+    if value.fract() != 0.0 || value < f32::from(i16::MIN) || value > f32::from(i16::MAX) {
+        return Err(Iec104Error::invalid_asdu_static(
+            "Scaled setpoint must be an integer in -32768..=32767",
+        ));
+    }
+    Ok(value as i16)
+}
+
+/// The station closing the connection first is not an error here.
+fn ignore_closed(err: Iec104Error) -> Result<()> {
+    match err {
+        Iec104Error::ChannelClosed | Iec104Error::Connection(_) | Iec104Error::Io(_) => Ok(()),
+        err => Err(err),
+    }
+}
+
+fn print_points(points: &[DataPoint]) {
+    for point in points {
+        println!("{}", render_point(point));
+    }
+}
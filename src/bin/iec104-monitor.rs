@@ -0,0 +1,305 @@
+//! Terminal monitor of an IEC 60870-5-104 station.
+//!
+//! Built with the `tui` feature:
+//!
+//! ```text
+//! cargo install voltage_iec104 --features tui
+//! iec104-monitor 192.168.1.10:2404 --ca 1
+//! ```
+//!
+//! Shows the connection state and sequence windows, every point received
+//! sorted by IOA and a log of the other events. `g` runs a general
+//! interrogation, the arrow keys scroll the table and `q` quits.
+
+use std::collections::{BTreeMap, VecDeque};
+use std::process::ExitCode;
+use std::time::{Duration, Instant};
+
+use clap::Parser;
+use ratatui::crossterm::event::{self, Event, KeyCode, KeyEventKind, KeyModifiers};
+use ratatui::layout::{Constraint, Layout};
+use ratatui::style::{Modifier, Style};
+use ratatui::text::Line;
+use ratatui::widgets::{Block, List, Paragraph, Row, Table, TableState};
+use ratatui::{DefaultTerminal, Frame};
+use tokio::sync::mpsc;
+
+use voltage_iec104::analyze::{render_timestamp, render_value};
+use voltage_iec104::{
+    ClientConfig, ClientHandle, ConnectionState, DataPoint, Iec104Client, Iec104Event, LinkStats,
+    Result, SequencedEvent, SessionInfo,
+};
+
+/// Lines kept in the event log.
+const LOG_LINES: usize = 500;
+
+/// Interval between refreshes of the link status and the screen.
+const REFRESH: Duration = Duration::from_millis(200);
+
+#[derive(Debug, Parser)]
+#[command(
+    name = "iec104-monitor",
+    version,
+    about = "Live terminal monitor of an IEC 104 station"
+)]
+struct Cli {
+    /// Station address, host:port
+    address: String,
+
+    /// Common address for the general interrogation
+    #[arg(long, default_value_t = 1)]
+    ca: u16,
+
+    /// Seconds to wait for the connection
+    #[arg(long, default_value_t = 10)]
+    timeout: u64,
+
+    /// Skip the general interrogation after connecting
+    #[arg(long)]
+    no_interrogation: bool,
+}
+
+/// Everything shown on screen.
+struct Monitor {
+    address: String,
+    started: Instant,
+    state: ConnectionState,
+    session: Option<SessionInfo>,
+    stats: LinkStats,
+    points: BTreeMap<u32, (DataPoint, u64)>,
+    log: VecDeque<String>,
+    table: TableState,
+}
+
+impl Monitor {
+    fn new(address: String) -> Self {
+        Self {
+            address,
+            started: Instant::now(),
+            state: ConnectionState::Disconnected,
+            session: None,
+            stats: LinkStats::default(),
+            points: BTreeMap::new(),
+            log: VecDeque::new(),
+            table: TableState::default(),
+        }
+    }
+
+    fn apply(&mut self, event: Iec104Event) {
+        let line = match event {
+            Iec104Event::DataUpdate(points) => {
+                for point in points {
+                    let updates = self.points.get(&point.ioa).map_or(0, |(_, n)| *n);
+                    self.points.insert(point.ioa, (point, updates + 1));
+                }
+                return;
+            }
+            Iec104Event::SessionEstablished(session) => {
+                let line = format!(
+                    "session {} -> {}, k={} w={}",
+                    session.local_address, session.peer_address, session.k, session.w
+                );
+                self.session = Some(session);
+                line
+            }
+            Iec104Event::AsduReceived(asdu) => format!(
+                "{} {} CA={}",
+                asdu.header.type_id, asdu.header.cot, asdu.header.common_address
+            ),
+            Iec104Event::CommandConfirm { ioa, success } => format!(
+                "command IOA {} {}",
+                ioa,
+                if success { "confirmed" } else { "rejected" }
+            ),
+            Iec104Event::Error(message) => format!("error: {}", message),
+            event => format!("{:?}", event),
+        };
+        self.log(line);
+    }
+
+    fn log(&mut self, line: String) {
+        if self.log.len() == LOG_LINES {
+            self.log.pop_front();
+        }
+        let elapsed = self.started.elapsed().as_secs_f64();
+        self.log.push_back(format!("{:>9.1}s  {}", elapsed, line));
+    }
+
+    fn scroll(&mut self, down: bool) {
+        let last = self.points.len().saturating_sub(1);
+        let row = match (self.table.selected(), down) {
+            (None, _) => 0,
+            (Some(row), true) => (row + 1).min(last),
+            (Some(row), false) => row.saturating_sub(1),
+        };
+        self.table.select(Some(row));
+    }
+
+    fn draw(&mut self, frame: &mut Frame) {
+        let [status, table, log] = Layout::vertical([
+            Constraint::Length(5),
+            Constraint::Min(5),
+            Constraint::Length(10),
+        ])
+        .areas(frame.area());
+
+        let stats = &self.stats;
+        let (k, w) = self.session.as_ref().map_or(("-".into(), "-".into()), |s| {
+            (s.k.to_string(), s.w.to_string())
+        });
+        let lines = vec![
+            Line::from(format!("{}  {:?}", self.address, self.state)),
+            Line::from(format!(
+                "send window {}/{}  receive window {}/{}  sequence errors {}",
+                stats.unacknowledged_sends,
+                k,
+                stats.unacknowledged_receives,
+                w,
+                stats.sequence_errors
+            )),
+            Line::from(format!(
+                "I {}/{}  S {}/{}  U {}/{}  (sent/received)  bytes {}/{}",
+                stats.i_frames_sent,
+                stats.i_frames_received,
+                stats.s_frames_sent,
+                stats.s_frames_received,
+                stats.u_frames_sent,
+                stats.u_frames_received,
+                stats.bytes_sent,
+                stats.bytes_received
+            )),
+        ];
+        frame.render_widget(
+            Paragraph::new(lines).block(Block::bordered().title(" Link ")),
+            status,
+        );
+
+        let rows = self.points.values().map(|(point, updates)| {
+            Row::new([
+                point.ioa.to_string(),
+                render_value(&point.value),
+                point.quality.to_string(),
+                point
+                    .timestamp
+                    .as_ref()
+                    .map_or_else(String::new, render_timestamp),
+                updates.to_string(),
+            ])
+        });
+        let widths = [
+            Constraint::Length(8),
+            Constraint::Min(20),
+            Constraint::Length(16),
+            Constraint::Length(26),
+            Constraint::Length(8),
+        ];
+        let points = Table::new(rows, widths)
+            .header(
+                Row::new(["IOA", "Value", "Quality", "Time", "Updates"])
+                    .style(Style::new().add_modifier(Modifier::BOLD)),
+            )
+            .row_highlight_style(Style::new().add_modifier(Modifier::REVERSED))
+            .block(Block::bordered().title(format!(" Points ({}) ", self.points.len())));
+        frame.render_stateful_widget(points, table, &mut self.table);
+
+        let shown = usize::from(log.height.saturating_sub(2));
+        let lines = self.log.iter().skip(self.log.len().saturating_sub(shown));
+        frame.render_widget(
+            List::new(lines.map(String::as_str))
+                .block(Block::bordered().title(" Events  (g interrogate, q quit) ")),
+            log,
+        );
+    }
+}
+
+#[tokio::main(flavor = "current_thread")]
+async fn main() -> ExitCode {
+    let cli = Cli::parse();
+    let timeout = Duration::from_secs(cli.timeout);
+    let config = ClientConfig::new(cli.address.as_str()).connect_timeout(timeout);
+    let mut client = Iec104Client::new(config);
+    let events = client.subscribe().expect("event stream of a new client");
+    let mut monitor = Monitor::new(cli.address);
+
+    // Connection errors are reported before the screen is taken over
+    let started = async {
+        client.connect().await?;
+        client.start_dt().await
+    };
+    if let Err(err) = started.await {
+        eprintln!("error: {}", err);
+        return ExitCode::FAILURE;
+    }
+    let (handle, task) = client.spawn();
+    if !cli.no_interrogation {
+        if let Err(err) = handle.general_interrogation(cli.ca).await {
+            monitor.log(format!("interrogation not sent: {}", err));
+        }
+    }
+
+    let mut terminal = ratatui::init();
+    let result = run(&mut terminal, &mut monitor, &handle, events, cli.ca).await;
+    ratatui::restore();
+
+    let _ = handle.shutdown(timeout).await;
+    drop(handle);
+    let _ = task.await;
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(err) => {
+            eprintln!("error: {}", err);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+/// Draw and handle keys until the user quits.
+async fn run(
+    terminal: &mut DefaultTerminal,
+    monitor: &mut Monitor,
+    handle: &ClientHandle,
+    mut events: mpsc::Receiver<SequencedEvent>,
+    ca: u16,
+) -> Result<()> {
+    let mut refresh = tokio::time::interval(REFRESH);
+    loop {
+        tokio::select! {
+            Some(event) = events.recv() => monitor.apply(event.event),
+            _ = refresh.tick() => {
+                // Both fail once the client task has ended, which the
+                // state shown already tells
+                if let Ok(state) = handle.state().await {
+                    monitor.state = state;
+                } else {
+                    monitor.state = ConnectionState::Disconnected;
+                }
+                if let Ok(stats) = handle.stats().await {
+                    monitor.stats = stats;
+                }
+                terminal.draw(|frame| monitor.draw(frame))?;
+
+                while event::poll(Duration::ZERO)? {
+                    let Event::Key(key) = event::read()? else {
+                        continue;
+                    };
+                    if key.kind != KeyEventKind::Press {
+                        continue;
+                    }
+                    match key.code {
+                        KeyCode::Char('q') | KeyCode::Esc => return Ok(()),
+                        KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                            return Ok(());
+                        }
+                        KeyCode::Char('g') => match handle.general_interrogation(ca).await {
+                            Ok(_) => monitor.log("interrogation sent".into()),
+                            Err(err) => monitor.log(format!("interrogation not sent: {}", err)),
+                        },
+                        KeyCode::Down => monitor.scroll(true),
+                        KeyCode::Up => monitor.scroll(false),
+                        _ => {}
+                    }
+                }
+            }
+        }
+    }
+}
@@ -2,19 +2,36 @@
 //!
 //! This module provides an asynchronous client for connecting to IEC 104 servers.
 
-use std::time::Duration;
-
-use bytes::Bytes;
-use tokio::net::TcpStream;
-use tokio::sync::mpsc;
-use tokio::time::{timeout, Instant};
+use std::collections::{HashMap, VecDeque};
+use std::net::SocketAddr;
+use std::time::{Duration, SystemTime};
+
+use tokio::net::{lookup_host, TcpSocket, TcpStream};
+use tokio::sync::{broadcast, mpsc, oneshot, watch};
+use tokio::task::JoinHandle;
+use tokio::time::{sleep_until, timeout, Instant};
 use tokio_util::codec::Framed;
 
+use bytes::Bytes;
 use futures::{SinkExt, StreamExt};
 
-use crate::codec::{Apdu, Iec104Codec};
+use crate::codec::{encode_apdu, Apdu, Iec104Codec};
+use crate::command::{
+    Command, CommandCompletion, PendingCommands, RetryPolicy, StepCommand,
+};
 use crate::error::{Iec104Error, Result};
-use crate::types::{Asdu, AsduHeader, Cot, Cp56Time2a, InformationObject, Ioa, TypeId, UFunction};
+use crate::file_transfer::{FileCapture, FileInfo, SECTION_RETRIES};
+use crate::filter::{Deadband, DeadbandFilter, EventFilter};
+use crate::handle::{ClientHandle, Request};
+#[cfg(feature = "tls")]
+use crate::tls::TlsConfig;
+use crate::transport::{AsyncStream, Transport};
+use crate::types::{
+    file_checksum, AckAction, Asdu, AsduHeader, CallAction, Coi, CommandQualifier, Cot, Cp56Time2a,
+    DataPoint, DoubleCommandState, FileError, FileObject, LastSectionQualifier,
+    ParameterActivationQualifier, ParameterValue, PulseDuration, Qcc, Qos, Qpm, ResetProcessQualifier,
+    Timestamp, UFunction,
+};
 
 /// Default IEC 104 port.
 pub const DEFAULT_PORT: u16 = 2404;
@@ -34,11 +51,17 @@ pub const DEFAULT_K: u16 = 12;
 /// Default W parameter (max unconfirmed receives before sending S-frame).
 pub const DEFAULT_W: u16 = 8;
 
+/// Events buffered per broadcast subscriber before it starts lagging.
+pub const EVENT_BROADCAST_CAPACITY: usize = 1024;
+
 /// Client configuration.
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct ClientConfig {
     /// Server address (host:port)
     pub address: String,
+    /// Source address of the TCP connection (None = chosen by the OS)
+    pub local_address: Option<SocketAddr>,
     /// Connection timeout
     pub connect_timeout: Duration,
     /// T1 timeout: time to wait for send confirmation
@@ -51,6 +74,31 @@ pub struct ClientConfig {
     pub k: u16,
     /// W parameter: max unconfirmed receives before sending S-frame
     pub w: u16,
+    /// Originator address stamped into every outgoing ASDU (0 = not used)
+    pub originator_address: u8,
+    /// Offset from UTC in seconds used by [`Iec104Client::clock_sync_now`]
+    pub utc_offset: i32,
+    /// How long a send waits for the K window to open (None = fail at once)
+    pub send_window_timeout: Option<Duration>,
+    /// Keep the latest value of every received point for [`Iec104Client::value`]
+    pub point_cache: bool,
+    /// Confirmation timeout and retries of commands (None = wait forever)
+    pub command_retry: Option<RetryPolicy>,
+    /// Complete CP24Time2a time tags against the latest full time received
+    pub complete_partial_timestamps: bool,
+    /// Send a general interrogation when a station reports end of initialization
+    pub interrogate_on_init: bool,
+    /// Deliver ASDUs of unknown type instead of dropping the connection
+    pub lenient_parsing: bool,
+    /// Deadband of measured values without their own
+    pub deadband: Option<Deadband>,
+    /// Deadbands of individual measured values, by IOA
+    pub point_deadbands: HashMap<u32, Deadband>,
+    /// TLS settings; plain TCP when None (not serialized: certificates and
+    /// keys are loaded separately)
+    #[cfg(feature = "tls")]
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub tls: Option<TlsConfig>,
 }
 
 impl ClientConfig {
@@ -58,15 +106,40 @@ impl ClientConfig {
     pub fn new(address: impl Into<String>) -> Self {
         Self {
             address: address.into(),
+            local_address: None,
             connect_timeout: Duration::from_secs(10),
             t1_timeout: Duration::from_secs(DEFAULT_T1_TIMEOUT),
             t2_timeout: Duration::from_secs(DEFAULT_T2_TIMEOUT),
             t3_timeout: Duration::from_secs(DEFAULT_T3_TIMEOUT),
             k: DEFAULT_K,
             w: DEFAULT_W,
+            originator_address: 0,
+            utc_offset: 0,
+            send_window_timeout: None,
+            point_cache: false,
+            command_retry: None,
+            complete_partial_timestamps: false,
+            interrogate_on_init: false,
+            lenient_parsing: false,
+            deadband: None,
+            point_deadbands: HashMap::new(),
+            #[cfg(feature = "tls")]
+            tls: None,
         }
     }
 
+    /// Bind the TCP connection to this source address before connecting.
+    ///
+    /// Use port 0 to pick the IP, and with it the interface, of a
+    /// multi-homed host while the OS chooses the port. A fixed port is
+    /// bound with `SO_REUSEADDR`, so reconnecting does not wait for the
+    /// previous connection to leave TIME_WAIT. Only server addresses of the
+    /// same IP version are tried.
+    pub fn local_address(mut self, address: SocketAddr) -> Self {
+        self.local_address = Some(address);
+        self
+    }
+
     /// Set connection timeout.
     pub fn connect_timeout(mut self, timeout: Duration) -> Self {
         self.connect_timeout = timeout;
@@ -90,6 +163,106 @@ impl ClientConfig {
         self.t3_timeout = timeout;
         self
     }
+
+    /// Set the originator address.
+    ///
+    /// Needed when several masters share a controlled station: confirmations
+    /// mirrored to another originator are not matched against our commands.
+    pub fn originator_address(mut self, originator: u8) -> Self {
+        self.originator_address = originator;
+        self
+    }
+
+    /// Set the offset from UTC (seconds) for clock synchronization.
+    pub fn utc_offset(mut self, seconds: i32) -> Self {
+        self.utc_offset = seconds;
+        self
+    }
+
+    /// Wait up to `timeout` for acknowledgments when K I-frames are
+    /// unacknowledged, instead of failing with
+    /// [`Iec104Error::TooManyUnconfirmed`] immediately.
+    pub fn send_window_timeout(mut self, timeout: Duration) -> Self {
+        self.send_window_timeout = Some(timeout);
+        self
+    }
+
+    /// Re-send commands not confirmed within `policy.timeout`, then fail
+    /// them with [`Iec104Error::CommandTimeout`].
+    ///
+    /// A retry repeats the original activation; it is skipped (and the
+    /// command fails) when the K window has no room for it.
+    pub fn command_retry(mut self, policy: RetryPolicy) -> Self {
+        self.command_retry = Some(policy);
+        self
+    }
+
+    /// Enable or disable the point cache.
+    pub fn point_cache(mut self, enabled: bool) -> Self {
+        self.point_cache = enabled;
+        self
+    }
+
+    /// Turn CP24Time2a (minutes and milliseconds) time tags into full
+    /// timestamps.
+    ///
+    /// Each partial tag is completed against the latest full time received
+    /// from the same common address, taken from CP56Time2a-tagged points and
+    /// clock synchronization. Tags arriving before any full time stay
+    /// [`Timestamp::Partial`].
+    pub fn complete_partial_timestamps(mut self, enabled: bool) -> Self {
+        self.complete_partial_timestamps = enabled;
+        self
+    }
+
+    /// Interrogate a station again after it reports end of initialization.
+    ///
+    /// A restarted station has lost nothing it will report spontaneously, so
+    /// the image held by the master is stale until the next general
+    /// interrogation. When enabled, one is sent to the common address of
+    /// every [`Iec104Event::EndOfInitialization`], right after the event.
+    pub fn interrogate_on_init(mut self, enabled: bool) -> Self {
+        self.interrogate_on_init = enabled;
+        self
+    }
+
+    /// Accept ASDUs whose type identification this crate does not know.
+    ///
+    /// By default such an ASDU is a decoding error that closes the
+    /// connection, unless its type is in the private range (128-255), which
+    /// is always accepted. When enabled, it is delivered as
+    /// [`Iec104Event::AsduReceived`] with [`TypeId::Other`] and the raw
+    /// payload intact.
+    ///
+    /// [`TypeId::Other`]: crate::types::TypeId::Other
+    pub fn lenient_parsing(mut self, enabled: bool) -> Self {
+        self.lenient_parsing = enabled;
+        self
+    }
+
+    /// Suppress measured value updates within `deadband` of the last
+    /// reported value.
+    ///
+    /// Applies to normalized, scaled and floating point measurements except
+    /// interrogation responses. Quality changes are always reported; the
+    /// point cache still sees every value.
+    pub fn deadband(mut self, deadband: Deadband) -> Self {
+        self.deadband = Some(deadband);
+        self
+    }
+
+    /// Set the deadband of one IOA, overriding [`deadband`](Self::deadband).
+    pub fn point_deadband(mut self, ioa: u32, deadband: Deadband) -> Self {
+        self.point_deadbands.insert(ioa, deadband);
+        self
+    }
+
+    /// Protect the connection with TLS.
+    #[cfg(feature = "tls")]
+    pub fn tls(mut self, tls: TlsConfig) -> Self {
+        self.tls = Some(tls);
+        self
+    }
 }
 
 /// Connection state.
@@ -105,6 +278,152 @@ pub enum ConnectionState {
     Stopping,
 }
 
+/// Role of the local end of a session.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ProtocolRole {
+    /// Controlling station (master, client)
+    Controlling,
+    /// Controlled station (outstation, server)
+    Controlled,
+}
+
+/// Session characteristics in effect once data transfer has started.
+///
+/// IEC 104 does not negotiate link parameters on the wire, so K, W and the
+/// timeouts are the locally configured values both ends are assumed to share.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SessionInfo {
+    /// Local protocol role
+    pub role: ProtocolRole,
+    /// Remote socket address (unspecified over a supplied stream)
+    pub peer_address: SocketAddr,
+    /// Local socket address (unspecified over a supplied stream)
+    pub local_address: SocketAddr,
+    /// K parameter: max unconfirmed I-frames
+    pub k: u16,
+    /// W parameter: max unconfirmed receives before sending S-frame
+    pub w: u16,
+    /// T1 timeout
+    pub t1_timeout: Duration,
+    /// T2 timeout
+    pub t2_timeout: Duration,
+    /// T3 timeout
+    pub t3_timeout: Duration,
+}
+
+/// Link counters, accumulated over the lifetime of a client.
+///
+/// The window fields describe the current connection at the time
+/// [`Iec104Client::stats`] was called.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct LinkStats {
+    /// I-frames sent
+    pub i_frames_sent: u64,
+    /// I-frames received
+    pub i_frames_received: u64,
+    /// S-frames sent
+    pub s_frames_sent: u64,
+    /// S-frames received
+    pub s_frames_received: u64,
+    /// U-frames sent
+    pub u_frames_sent: u64,
+    /// U-frames received
+    pub u_frames_received: u64,
+    /// APDU bytes sent
+    pub bytes_sent: u64,
+    /// APDU bytes received
+    pub bytes_received: u64,
+    /// I-frames received with an unexpected send sequence number
+    pub sequence_errors: u64,
+    /// TESTFR act sent on T3 expiry
+    pub test_frames_sent: u64,
+    /// TESTFR con received
+    pub test_frames_confirmed: u64,
+    /// Connections closed because an I-frame was not acknowledged within T1
+    pub t1_timeouts: u64,
+    /// Connections closed because TESTFR act was not confirmed within T1
+    pub test_frame_timeouts: u64,
+    /// I-frames sent but not yet acknowledged (at most K)
+    pub unacknowledged_sends: u16,
+    /// I-frames received but not yet acknowledged (at most W)
+    pub unacknowledged_receives: u16,
+}
+
+impl LinkStats {
+    fn count_sent(&mut self, apdu: &Apdu) {
+        *self.frame_counter(apdu, true) += 1;
+        self.bytes_sent += apdu_len(apdu);
+    }
+
+    fn count_received(&mut self, apdu: &Apdu) {
+        *self.frame_counter(apdu, false) += 1;
+        self.bytes_received += apdu_len(apdu);
+    }
+
+    fn frame_counter(&mut self, apdu: &Apdu, sent: bool) -> &mut u64 {
+        match (&apdu.apci, sent) {
+            (crate::types::Apci::IFrame { .. }, true) => &mut self.i_frames_sent,
+            (crate::types::Apci::IFrame { .. }, false) => &mut self.i_frames_received,
+            (crate::types::Apci::SFrame { .. }, true) => &mut self.s_frames_sent,
+            (crate::types::Apci::SFrame { .. }, false) => &mut self.s_frames_received,
+            (crate::types::Apci::UFrame { .. }, true) => &mut self.u_frames_sent,
+            (crate::types::Apci::UFrame { .. }, false) => &mut self.u_frames_received,
+        }
+    }
+}
+
+/// Size of an APDU on the wire.
+fn apdu_len(apdu: &Apdu) -> u64 {
+    6 + apdu.asdu.as_ref().map_or(0, |asdu| asdu.encoded_len()) as u64
+}
+
+/// Check that a test command confirmation mirrors the information elements
+/// of the request.
+pub(crate) fn check_test_mirror(expected: &[u8], confirmation: &Asdu) -> Result<()> {
+    // IOA followed by the mirrored information elements
+    let mirrored = confirmation.raw_data.get(3..3 + expected.len());
+    if mirrored != Some(expected) {
+        return Err(Iec104Error::protocol_static(
+            "Test command confirmation does not mirror the request",
+        ));
+    }
+    Ok(())
+}
+
+/// Direction of a frame seen by a [`tap`](Iec104Client::tap).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FrameDirection {
+    /// Sent to the server
+    Sent,
+    /// Received from the server
+    Received,
+}
+
+/// APDU copied to a frame tap, with its wire encoding.
+#[derive(Debug, Clone)]
+pub struct TappedFrame {
+    /// Direction of the frame
+    pub direction: FrameDirection,
+    /// Decoded frame
+    pub apdu: Apdu,
+    /// Frame as it appears on the wire, start byte included
+    pub bytes: Bytes,
+}
+
+/// Copy `apdu` to the tap subscribers, if there are any.
+fn tap_frame(tap: &broadcast::Sender<TappedFrame>, apdu: &Apdu, direction: FrameDirection) {
+    if tap.receiver_count() == 0 {
+        return;
+    }
+    if let Ok(bytes) = encode_apdu(apdu) {
+        let _ = tap.send(TappedFrame {
+            direction,
+            apdu: apdu.clone(),
+            bytes: bytes.freeze(),
+        });
+    }
+}
+
 /// Events emitted by the client.
 #[derive(Debug, Clone)]
 pub enum Iec104Event {
@@ -114,6 +433,8 @@ pub enum Iec104Event {
     Disconnected,
     /// Data transfer started
     DataTransferStarted,
+    /// Session parameters in effect after data transfer started
+    SessionEstablished(SessionInfo),
     /// Data transfer stopped
     DataTransferStopped,
     /// Data update with parsed data points
@@ -127,46 +448,233 @@ pub enum Iec104Event {
         /// Whether the command was successful
         success: bool,
     },
+    /// Reset process command issued (audit record, emitted before sending)
+    ResetProcessIssued {
+        /// Common address of the station being reset
+        common_address: u16,
+        /// Reset qualifier
+        qualifier: ResetProcessQualifier,
+    },
+    /// Remote station reported end of initialization (M_EI_NA_1)
+    EndOfInitialization {
+        /// Common address of the station
+        common_address: u16,
+        /// Cause of initialization
+        coi: Coi,
+    },
+    /// U-frame received that answers no pending request, such as a
+    /// duplicated STARTDT con
+    ///
+    /// A STARTDT con while data transfer is stopped, or a STOPDT con while it
+    /// is active, also changes the state (with the usual events). STARTDT and
+    /// STOPDT act are never confirmed, as only the controlling station may
+    /// start or stop data transfer.
+    UnsolicitedUFrame(UFunction),
+    /// Connection lost with I-frames the peer never acknowledged (oldest first)
+    Unacknowledged(Vec<Asdu>),
     /// Interrogation terminated
     InterrogationComplete {
         /// Common address
         common_address: u16,
     },
+    /// Counter interrogation terminated
+    CounterInterrogationComplete {
+        /// Common address
+        common_address: u16,
+        /// Qualifier of the terminated request
+        qcc: Qcc,
+    },
+    /// A file section was received and verified during
+    /// [`download_file`](Iec104Client::download_file)
+    FileProgress {
+        /// Common address
+        common_address: u16,
+        /// Name of file (NOF)
+        file: u16,
+        /// Octets received so far
+        received: u32,
+        /// Length of the file announced by the station
+        total: u32,
+    },
     /// Error occurred
     Error(String),
 }
 
+/// An event together with its position in the client's event stream.
+///
+/// Sequence numbers start at 0 for each client and increase by one per
+/// event, so a gap or a decrease indicates a lost or reordered event.
+#[derive(Debug, Clone)]
+pub struct SequencedEvent {
+    /// Position in the event stream
+    pub seq: u64,
+    /// The event
+    pub event: Iec104Event,
+}
+
+/// An I-frame sent but not yet acknowledged by the peer.
+struct InFlight {
+    sent_at: Instant,
+    asdu: Asdu,
+}
+
+/// Points captured from received data while a request waits for its reply.
+struct Collector {
+    cot: Cot,
+    common_address: u16,
+    ioa: Option<u32>,
+    points: Vec<DataPoint>,
+}
+
+impl Collector {
+    fn new(cot: Cot, common_address: u16, ioa: Option<u32>) -> Self {
+        Self {
+            cot,
+            common_address,
+            ioa,
+            points: Vec::new(),
+        }
+    }
+
+    fn collect(&mut self, header: &AsduHeader, update: &[DataPoint]) {
+        if header.cot != self.cot || header.common_address != self.common_address {
+            return;
+        }
+        let wanted = update.iter().filter(|p| self.ioa.is_none_or(|ioa| p.ioa == ioa));
+        self.points.extend(wanted.cloned());
+    }
+}
+
+/// What a link waiting in [`Iec104Client::next_input`] woke up for.
+pub(crate) enum LinkInput {
+    /// A frame, or the end of the stream
+    Frame(Option<Result<Apdu>>),
+    /// A protocol timer is due
+    Timer,
+}
+
+/// Points collected for a request issued through a [`ClientHandle`].
+///
+/// The background task keeps processing frames and requests meanwhile and
+/// hands the points over once the request completes.
+struct Watch {
+    collector: Collector,
+    completion: CommandCompletion,
+    confirmed: bool,
+    /// Complete with the first point (reads) instead of at the termination
+    first_point: bool,
+    reply: oneshot::Sender<Result<Vec<DataPoint>>>,
+}
+
+impl Watch {
+    /// The result, once the request has completed.
+    fn outcome(&mut self) -> Option<Result<Vec<DataPoint>>> {
+        if self.first_point {
+            if let Some(point) = self.collector.points.pop() {
+                return Some(Ok(vec![point]));
+            }
+        }
+        if !self.confirmed {
+            match self.completion.try_confirmed()? {
+                Ok(_) => self.confirmed = true,
+                Err(e) => return Some(Err(e)),
+            }
+        }
+        if self.first_point {
+            return None;
+        }
+        let terminated = self.completion.try_terminated()?;
+        Some(terminated.map(|_| std::mem::take(&mut self.collector.points)))
+    }
+}
+
 /// IEC 60870-5-104 client.
+///
+/// All events, whether produced by connection management or by received
+/// frames, pass through a single queue in the order they occur on the wire.
+///
+/// Commands return a [`CommandCompletion`] that resolves when the matching
+/// confirmation arrives, while frames keep being processed by `poll()`.
 pub struct Iec104Client {
     config: ClientConfig,
     state: ConnectionState,
+    state_tx: watch::Sender<ConnectionState>,
     send_seq: u16,
     recv_seq: u16,
     unconfirmed_sends: u16,
     unconfirmed_recvs: u16,
-    event_tx: mpsc::Sender<Iec104Event>,
-    event_rx: Option<mpsc::Receiver<Iec104Event>>,
-    framed: Option<Framed<TcpStream, Iec104Codec>>,
+    /// When the oldest unacknowledged received I-frame arrived (T2 start)
+    first_unacked_recv: Option<Instant>,
+    in_flight: VecDeque<InFlight>,
+    unacknowledged: Vec<Asdu>,
+    event_tx: mpsc::Sender<SequencedEvent>,
+    event_rx: Option<mpsc::Receiver<SequencedEvent>>,
+    broadcast_tx: broadcast::Sender<SequencedEvent>,
+    filtered: Vec<(EventFilter, mpsc::Sender<SequencedEvent>)>,
+    event_seq: u64,
+    framed: Option<Framed<Transport, Iec104Codec>>,
+    session: Option<SessionInfo>,
+    pending: PendingCommands,
+    collector: Option<Collector>,
+    watches: Vec<Watch>,
+    file_capture: Option<FileCapture>,
+    /// Latest point per (common address, IOA), when enabled
+    points: HashMap<(u16, u32), DataPoint>,
+    deadbands: DeadbandFilter,
+    /// Latest full time received per common address
+    station_clocks: HashMap<u16, Cp56Time2a>,
+    test_sequence: u16,
+    stats: LinkStats,
+    tap: broadcast::Sender<TappedFrame>,
+    /// When TESTFR act was sent without anything received since
+    test_frame_sent: Option<Instant>,
     last_recv_time: Instant,
     last_send_time: Instant,
+    /// Whether a connection was opened before, to count reconnects
+    #[cfg(feature = "metrics")]
+    connected_before: bool,
 }
 
 impl Iec104Client {
     /// Create a new IEC 104 client.
     pub fn new(config: ClientConfig) -> Self {
         let (event_tx, event_rx) = mpsc::channel(100);
+        let pending = PendingCommands::with_policy(config.command_retry);
+        #[cfg(feature = "metrics")]
+        let pending = pending.peer(&config.address);
         Self {
             config,
             state: ConnectionState::Disconnected,
+            state_tx: watch::Sender::new(ConnectionState::Disconnected),
             send_seq: 0,
             recv_seq: 0,
             unconfirmed_sends: 0,
             unconfirmed_recvs: 0,
+            first_unacked_recv: None,
+            in_flight: VecDeque::new(),
+            unacknowledged: Vec::new(),
             event_tx,
             event_rx: Some(event_rx),
+            broadcast_tx: broadcast::channel(EVENT_BROADCAST_CAPACITY).0,
+            filtered: Vec::new(),
+            event_seq: 0,
             framed: None,
+            session: None,
+            pending,
+            collector: None,
+            watches: Vec::new(),
+            file_capture: None,
+            points: HashMap::new(),
+            deadbands: DeadbandFilter::default(),
+            station_clocks: HashMap::new(),
+            test_sequence: 0,
+            stats: LinkStats::default(),
+            tap: broadcast::channel(EVENT_BROADCAST_CAPACITY).0,
+            test_frame_sent: None,
             last_recv_time: Instant::now(),
             last_send_time: Instant::now(),
+            #[cfg(feature = "metrics")]
+            connected_before: false,
         }
     }
 
@@ -175,44 +683,138 @@ impl Iec104Client {
         self.state
     }
 
+    /// Latest value received for a point.
+    ///
+    /// Requires [`ClientConfig::point_cache`]; values survive reconnection.
+    pub fn value(&self, common_address: u16, ioa: u32) -> Option<&DataPoint> {
+        self.points.get(&(common_address, ioa))
+    }
+
+    /// Latest value of every received point, keyed by (common address, IOA).
+    ///
+    /// Requires [`ClientConfig::point_cache`].
+    pub fn values(&self) -> &HashMap<(u16, u32), DataPoint> {
+        &self.points
+    }
+
+    /// Get the link counters.
+    pub fn stats(&self) -> LinkStats {
+        LinkStats {
+            unacknowledged_sends: self.unconfirmed_sends,
+            unacknowledged_receives: self.unconfirmed_recvs,
+            ..self.stats
+        }
+    }
+
+    /// Watch connection state transitions.
+    ///
+    /// Any number of receivers can be created; each sees the latest state
+    /// and can await changes without consuming events.
+    pub fn watch_state(&self) -> watch::Receiver<ConnectionState> {
+        self.state_tx.subscribe()
+    }
+
+    /// Get the session parameters, available once data transfer has started.
+    pub fn session_info(&self) -> Option<&SessionInfo> {
+        self.session.as_ref()
+    }
+
     /// Subscribe to events.
     ///
-    /// This can only be called once. Returns None if already subscribed.
-    pub fn subscribe(&mut self) -> Option<mpsc::Receiver<Iec104Event>> {
+    /// Events are delivered in wire order, each tagged with a sequence number.
+    /// Until a subscriber exists, at most 100 events are buffered and later
+    /// ones are dropped (visible as a gap in the sequence numbers).
+    ///
+    /// This can only be called once. Returns None if already subscribed;
+    /// use [`subscribe_broadcast`](Self::subscribe_broadcast) for more consumers.
+    pub fn subscribe(&mut self) -> Option<mpsc::Receiver<SequencedEvent>> {
         self.event_rx.take()
     }
 
+    /// Subscribe to events alongside any other subscribers.
+    ///
+    /// Can be called any number of times; each receiver gets every event
+    /// emitted after it was created, with the same sequence numbers as
+    /// [`subscribe`](Self::subscribe). A receiver more than
+    /// [`EVENT_BROADCAST_CAPACITY`] events behind skips the oldest ones and
+    /// is told how many with [`broadcast::error::RecvError::Lagged`]; it
+    /// never slows down the connection.
+    pub fn subscribe_broadcast(&self) -> broadcast::Receiver<SequencedEvent> {
+        self.broadcast_tx.subscribe()
+    }
+
+    /// Copy every APDU sent or received to the returned receiver.
+    ///
+    /// Meant for protocol logging and capture: frames are delivered with
+    /// their wire bytes, in the order they crossed the link. Frames are
+    /// only encoded for the tap while a receiver exists. Like
+    /// [`subscribe_broadcast`](Self::subscribe_broadcast), a receiver that
+    /// falls [`EVENT_BROADCAST_CAPACITY`] frames behind loses the oldest.
+    pub fn tap(&self) -> broadcast::Receiver<TappedFrame> {
+        self.tap.subscribe()
+    }
+
+    /// Subscribe to the events selected by `filter`.
+    ///
+    /// The filter runs before anything is queued, so unwanted events cost
+    /// the subscriber nothing. Sequence numbers are those of the full
+    /// stream; gaps are expected. Like [`subscribe`](Self::subscribe), a
+    /// full queue (100 events) delays the connection until it drains.
+    pub fn subscribe_filtered(&mut self, filter: EventFilter) -> mpsc::Receiver<SequencedEvent> {
+        let (tx, rx) = mpsc::channel(100);
+        self.filtered.push((filter, tx));
+        rx
+    }
+
     /// Connect to the server.
+    #[cfg_attr(
+        feature = "tracing-support",
+        tracing::instrument(skip_all, fields(peer = %self.config.address))
+    )]
     pub async fn connect(&mut self) -> Result<()> {
         if self.state != ConnectionState::Disconnected {
             return Err(Iec104Error::Connection(std::borrow::Cow::Borrowed("Already connected")));
         }
 
-        let stream = timeout(
-            self.config.connect_timeout,
-            TcpStream::connect(&self.config.address),
-        )
-        .await
-        .map_err(|_| Iec104Error::ConnectionTimeout)?
-        .map_err(Iec104Error::Io)?;
-
-        // Disable Nagle's algorithm for low latency
-        stream.set_nodelay(true).ok();
-
-        self.framed = Some(Framed::new(stream, Iec104Codec::new()));
-        self.state = ConnectionState::Connected;
-        self.send_seq = 0;
-        self.recv_seq = 0;
-        self.unconfirmed_sends = 0;
-        self.unconfirmed_recvs = 0;
-        self.last_recv_time = Instant::now();
-        self.last_send_time = Instant::now();
+        let transport = timeout(self.config.connect_timeout, self.open_transport())
+            .await
+            .map_err(|_| Iec104Error::ConnectionTimeout)??;
+        self.attach(transport).await;
+        Ok(())
+    }
 
-        self.emit_event(Iec104Event::Connected).await;
+    /// Connect over a byte stream set up by the caller instead of opening
+    /// a TCP connection to the configured address.
+    ///
+    /// The stream is used as it is: [`ClientConfig::tls`] and the connect
+    /// timeout do not apply, and [`SessionInfo`] reports unspecified socket
+    /// addresses (`0.0.0.0:0`). Everything else, from STARTDT to commands,
+    /// works as over TCP.
+    ///
+    /// # Example
+    ///
+    /// ```rust,ignore
+    /// let stream = tunnel.open("rtu-17").await?;
+    /// client.connect_stream(stream).await?;
+    /// client.start_dt().await?;
+    /// ```
+    #[cfg_attr(
+        feature = "tracing-support",
+        tracing::instrument(skip_all, fields(peer = %self.config.address))
+    )]
+    pub async fn connect_stream(&mut self, stream: impl AsyncStream) -> Result<()> {
+        if self.state != ConnectionState::Disconnected {
+            return Err(Iec104Error::Connection(std::borrow::Cow::Borrowed("Already connected")));
+        }
+        self.attach(Transport::Stream(Box::new(stream))).await;
         Ok(())
     }
 
     /// Disconnect from the server.
+    #[cfg_attr(
+        feature = "tracing-support",
+        tracing::instrument(skip_all, fields(peer = %self.config.address))
+    )]
     pub async fn disconnect(&mut self) -> Result<()> {
         if self.state == ConnectionState::Disconnected {
             return Ok(());
@@ -223,13 +825,85 @@ impl Iec104Client {
             self.stop_dt().await.ok();
         }
 
-        self.framed = None;
-        self.state = ConnectionState::Disconnected;
-        self.emit_event(Iec104Event::Disconnected).await;
+        self.drop_connection().await;
+        Ok(())
+    }
+
+    /// Close the connection gracefully.
+    ///
+    /// Waits up to `timeout` for every sent I-frame to be acknowledged
+    /// (processing frames as [`poll`](Self::poll) does), acknowledges
+    /// everything received, sends STOPDT and shuts the socket down. No new
+    /// commands can be issued meanwhile. I-frames still unacknowledged
+    /// afterwards are reported as by a connection loss.
+    #[cfg_attr(
+        feature = "tracing-support",
+        tracing::instrument(skip_all, fields(peer = %self.config.address))
+    )]
+    pub async fn shutdown(&mut self, timeout: Duration) -> Result<()> {
+        if self.state == ConnectionState::Disconnected {
+            return Ok(());
+        }
+
+        let deadline = Instant::now() + timeout;
+        while !self.in_flight.is_empty() && Instant::now() < deadline {
+            if let Err(e) = self.poll().await {
+                if self.state == ConnectionState::Disconnected {
+                    return Err(e);
+                }
+            }
+        }
+
+        if self.unconfirmed_recvs > 0 {
+            self.send_s_frame().await.ok();
+        }
+        if self.state == ConnectionState::Active {
+            self.stop_dt().await.ok();
+        }
+        if let Some(framed) = self.framed.as_mut() {
+            // Flushes and shuts down the write half
+            framed.close().await.ok();
+        }
+
+        self.drop_connection().await;
         Ok(())
     }
 
+    /// ASDUs whose I-frames were never acknowledged before the last
+    /// connection closed, oldest first.
+    pub fn unacknowledged(&self) -> &[Asdu] {
+        &self.unacknowledged
+    }
+
+    /// Re-send the ASDUs returned by [`unacknowledged`](Self::unacknowledged)
+    /// on the current connection.
+    ///
+    /// Re-sent commands are not tracked for confirmation. Returns how many
+    /// ASDUs were sent; those not sent (e.g. because the K window filled up)
+    /// remain for a later call.
+    pub async fn resend_unacknowledged(&mut self) -> Result<usize> {
+        if self.state != ConnectionState::Active {
+            return Err(Iec104Error::NotConnected);
+        }
+
+        let mut sent = 0;
+        while sent < self.unacknowledged.len() {
+            let asdu = self.unacknowledged[sent].clone();
+            if let Err(e) = self.send_i_frame(asdu).await {
+                self.unacknowledged.drain(..sent);
+                return Err(e);
+            }
+            sent += 1;
+        }
+        self.unacknowledged.clear();
+        Ok(sent)
+    }
+
     /// Start data transfer (STARTDT act).
+    #[cfg_attr(
+        feature = "tracing-support",
+        tracing::instrument(skip_all, fields(peer = %self.config.address))
+    )]
     pub async fn start_dt(&mut self) -> Result<()> {
         if self.state != ConnectionState::Connected {
             return Err(Iec104Error::protocol_static("Not connected or already active"));
@@ -237,407 +911,3081 @@ impl Iec104Client {
 
         self.send_u_frame(UFunction::StartDtAct).await?;
 
-        // Wait for confirmation
-        let response = self.recv_frame_timeout(self.config.t1_timeout).await?;
-
-        match response.apci {
-            crate::types::Apci::UFrame { function: UFunction::StartDtCon } => {
-                self.state = ConnectionState::Active;
-                self.emit_event(Iec104Event::DataTransferStarted).await;
-                Ok(())
+        // Wait for confirmation; anything else is handled as usual
+        let deadline = Instant::now() + self.config.t1_timeout;
+        loop {
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            let response = self.recv_frame_timeout(remaining).await?;
+            match response.apci {
+                crate::types::Apci::UFrame { function: UFunction::StartDtCon } => break,
+                _ => {
+                    self.handle_apdu(response).await?;
+                }
             }
-            _ => Err(Iec104Error::protocol_static("Unexpected response to STARTDT")),
         }
+        #[cfg(feature = "tracing-support")]
+        tracing::info!("data transfer started");
+        self.data_transfer_started().await
     }
 
     /// Stop data transfer (STOPDT act).
+    ///
+    /// Follows the STOPDT procedure: new commands are refused, sent
+    /// I-frames are waited for until acknowledged and everything received is
+    /// acknowledged before STOPDT act goes out. Data the server sends before
+    /// confirming is processed and delivered as usual.
+    #[cfg_attr(
+        feature = "tracing-support",
+        tracing::instrument(skip_all, fields(peer = %self.config.address))
+    )]
     pub async fn stop_dt(&mut self) -> Result<()> {
         if self.state != ConnectionState::Active {
             return Err(Iec104Error::protocol_static("Data transfer not active"));
         }
 
-        self.state = ConnectionState::Stopping;
+        // No new commands from here on; sent I-frames must be acknowledged
+        // first, which T1 bounds as usual
+        self.set_state(ConnectionState::Stopping);
+        while !self.in_flight.is_empty() {
+            if let Err(e) = self.poll().await {
+                if self.state == ConnectionState::Disconnected {
+                    return Err(e);
+                }
+            }
+        }
+        if self.unconfirmed_recvs > 0 {
+            self.send_s_frame().await?;
+        }
         self.send_u_frame(UFunction::StopDtAct).await?;
 
-        // Wait for confirmation
-        let response = self.recv_frame_timeout(self.config.t1_timeout).await?;
-
-        match response.apci {
-            crate::types::Apci::UFrame { function: UFunction::StopDtCon } => {
-                self.state = ConnectionState::Connected;
-                self.emit_event(Iec104Event::DataTransferStopped).await;
-                Ok(())
+        // The server may still send pending data before confirming, and
+        // waits for it to be acknowledged
+        let deadline = Instant::now() + self.config.t1_timeout;
+        loop {
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            let response = self.recv_frame_timeout(remaining).await?;
+            match response.apci {
+                crate::types::Apci::UFrame { function: UFunction::StopDtCon } => break,
+                crate::types::Apci::IFrame { .. } => {
+                    self.handle_apdu(response).await?;
+                    self.send_s_frame().await?;
+                }
+                _ => {
+                    self.handle_apdu(response).await?;
+                }
             }
-            _ => Err(Iec104Error::protocol_static("Unexpected response to STOPDT")),
         }
+
+        #[cfg(feature = "tracing-support")]
+        tracing::info!("data transfer stopped");
+        self.data_transfer_stopped().await;
+        Ok(())
     }
 
     /// Send general interrogation command.
-    pub async fn general_interrogation(&mut self, common_address: u16) -> Result<()> {
+    pub async fn general_interrogation(
+        &mut self,
+        common_address: u16,
+    ) -> Result<CommandCompletion> {
         if self.state != ConnectionState::Active {
             return Err(Iec104Error::NotConnected);
         }
 
         // QOI = 20 (station interrogation)
         let asdu = Asdu::interrogation_command(common_address, 20);
-        self.send_i_frame(asdu).await
+        self.send_command(asdu).await
     }
 
-    /// Send counter interrogation command.
-    pub async fn counter_interrogation(&mut self, common_address: u16, group: u8) -> Result<()> {
+    /// Perform a general interrogation and return the points it reported.
+    ///
+    /// Collects the points received with COT=20 (interrogated by station)
+    /// from `common_address` until the activation termination. Returns
+    /// [`Iec104Error::CommandTimeout`] if the interrogation has not terminated
+    /// within `timeout`. Events are still emitted as usual.
+    pub async fn general_interrogation_snapshot(
+        &mut self,
+        common_address: u16,
+        timeout: Duration,
+    ) -> Result<Vec<DataPoint>> {
+        let deadline = Instant::now() + timeout;
+        let mut completion = self.general_interrogation(common_address).await?;
+
+        self.collector = Some(Collector::new(Cot::InterrogatedByStation, common_address, None));
+        let mut result = self.wait_completion(&mut completion, deadline, false).await;
+        if result.is_ok() {
+            result = self.wait_completion(&mut completion, deadline, true).await;
+        }
+        let collector = self.collector.take();
+        result.map(|_| collector.map(|c| c.points).unwrap_or_default())
+    }
+
+    /// Read a single information object (C_RD_NA_1).
+    ///
+    /// Resolves with the point returned with COT=5 (request). Returns
+    /// [`Iec104Error::CommandRejected`] if the station rejects the read, or
+    /// [`Iec104Error::CommandTimeout`] if no reply arrives within `timeout`.
+    pub async fn read(
+        &mut self,
+        common_address: u16,
+        ioa: u32,
+        timeout: Duration,
+    ) -> Result<DataPoint> {
         if self.state != ConnectionState::Active {
             return Err(Iec104Error::NotConnected);
         }
 
-        let mut asdu = Asdu::new(AsduHeader::new(
-            TypeId::CounterInterrogation,
-            1,
-            Cot::Activation,
-            common_address,
-        ));
-        asdu.objects.push(InformationObject {
-            ioa: Ioa::new(0),
-            data: Bytes::copy_from_slice(&[group]),
-        });
+        let deadline = Instant::now() + timeout;
+        let mut completion = self.send_command(Asdu::read_command(common_address, ioa)).await?;
+        self.collector = Some(Collector::new(Cot::Request, common_address, Some(ioa)));
 
-        self.send_i_frame(asdu).await
+        let mut confirmed = false;
+        let result = loop {
+            if let Some(point) = self.collector.as_mut().and_then(|c| c.points.pop()) {
+                break Ok(point);
+            }
+            // Reads are not confirmed, but some stations do
+            if !confirmed {
+                match completion.try_confirmed() {
+                    Some(Err(e)) => break Err(e),
+                    Some(Ok(_)) => confirmed = true,
+                    None => {}
+                }
+            }
+            if Instant::now() >= deadline {
+                break Err(Iec104Error::CommandTimeout {
+                    type_id: completion.type_id(),
+                    ioa,
+                });
+            }
+            if let Err(e) = self.poll().await {
+                break Err(e);
+            }
+        };
+        self.collector = None;
+        result
     }
 
-    /// Send clock synchronization command.
-    pub async fn clock_sync(&mut self, common_address: u16, time: Cp56Time2a) -> Result<()> {
+    /// Call the directory of a station (F_SC_NA_1 with the default qualifier).
+    ///
+    /// Collects the directory entries (F_DR_TA_1) up to the one flagged as
+    /// last file of the directory. `ioa` addresses the directory, usually 0.
+    /// Returns [`Iec104Error::CommandTimeout`] if the directory is not
+    /// complete within `timeout`.
+    pub async fn file_directory(
+        &mut self,
+        common_address: u16,
+        ioa: u32,
+        timeout: Duration,
+    ) -> Result<Vec<FileInfo>> {
         if self.state != ConnectionState::Active {
             return Err(Iec104Error::NotConnected);
         }
 
-        let asdu = Asdu::clock_sync_command(common_address, time);
-        self.send_i_frame(asdu).await
+        self.file_capture = Some(FileCapture::new(common_address, None));
+        let result = self.call_directory(common_address, ioa, Instant::now() + timeout).await;
+        self.file_capture = None;
+        result
     }
 
-    /// Send single command.
-    pub async fn single_command(
+    async fn call_directory(
         &mut self,
         common_address: u16,
         ioa: u32,
-        value: bool,
-        select: bool,
-    ) -> Result<()> {
-        if self.state != ConnectionState::Active {
-            return Err(Iec104Error::NotConnected);
+        deadline: Instant,
+    ) -> Result<Vec<FileInfo>> {
+        let call = FileObject::Call {
+            file: 0,
+            section: 0,
+            action: CallAction::Default,
+            error: FileError::None,
+        };
+        let asdu = Asdu::file_transfer(common_address, ioa, Cot::Request, &call);
+        self.send_i_frame(asdu).await?;
+
+        let mut entries = Vec::new();
+        loop {
+            let (entry_ioa, object) = self.next_file_object(ioa, deadline).await?;
+            if let FileObject::Directory {
+                file,
+                length,
+                status,
+                time,
+            } = object
+            {
+                entries.push(FileInfo {
+                    ioa: entry_ioa,
+                    file,
+                    length,
+                    status,
+                    time,
+                });
+                if status.last_file {
+                    return Ok(entries);
+                }
+            }
         }
+    }
 
-        let mut asdu = Asdu::new(AsduHeader::new(
-            TypeId::SingleCommand,
-            1,
-            Cot::Activation,
+    /// Download a file from a station.
+    ///
+    /// Selects and calls the file, then calls each section the station
+    /// announces, checks its checksum and acknowledges it. A section failing
+    /// its checksum is acknowledged negatively, so the station can send it
+    /// again. An [`Iec104Event::FileProgress`] is emitted per accepted
+    /// section.
+    ///
+    /// Returns [`Iec104Error::CommandTimeout`] if the station does not answer
+    /// a step within `timeout`, and [`Iec104Error::FileTransfer`] if the file
+    /// or a section is not ready or keeps failing its checksum.
+    pub async fn download_file(
+        &mut self,
+        common_address: u16,
+        ioa: u32,
+        file: u16,
+        timeout: Duration,
+    ) -> Result<Vec<u8>> {
+        if self.state != ConnectionState::Active {
+            return Err(Iec104Error::NotConnected);
+        }
+
+        self.file_capture = Some(FileCapture::new(common_address, Some(ioa)));
+        let result = self.transfer_file(common_address, ioa, file, timeout).await;
+        self.file_capture = None;
+        result
+    }
+
+    async fn transfer_file(
+        &mut self,
+        common_address: u16,
+        ioa: u32,
+        file: u16,
+        timeout: Duration,
+    ) -> Result<Vec<u8>> {
+        let failed = |reason: &'static str| Iec104Error::FileTransfer {
+            file,
+            reason: std::borrow::Cow::Borrowed(reason),
+        };
+        let call = |section: u8, action: CallAction| FileObject::Call {
+            file,
+            section,
+            action,
+            error: FileError::None,
+        };
+        let ack = |section: u8, action: AckAction, error: FileError| FileObject::Ack {
+            file,
+            section,
+            action,
+            error,
+        };
+
+        self.send_file_object(common_address, ioa, call(0, CallAction::SelectFile))
+            .await?;
+        let total = loop {
+            let (_, object) = self.next_file_object(ioa, Instant::now() + timeout).await?;
+            if let FileObject::FileReady {
+                file: ready,
+                length,
+                negative,
+            } = object
+            {
+                if ready != file {
+                    continue;
+                }
+                if negative {
+                    return Err(failed("file not ready"));
+                }
+                break length;
+            }
+        };
+        self.send_file_object(common_address, ioa, call(0, CallAction::RequestFile))
+            .await?;
+
+        let mut data = Vec::with_capacity(total as usize);
+        let mut section_data = Vec::new();
+        let mut retries = SECTION_RETRIES;
+        loop {
+            let (_, object) = self.next_file_object(ioa, Instant::now() + timeout).await?;
+            if object.file() != file {
+                continue;
+            }
+            match object {
+                FileObject::SectionReady {
+                    section, not_ready, ..
+                } => {
+                    if not_ready {
+                        return Err(failed("section not ready"));
+                    }
+                    section_data.clear();
+                    let request = call(section, CallAction::RequestSection);
+                    self.send_file_object(common_address, ioa, request).await?;
+                }
+                FileObject::Segment { data: segment, .. } => {
+                    section_data.extend_from_slice(&segment);
+                }
+                FileObject::LastSection {
+                    section,
+                    qualifier:
+                        LastSectionQualifier::SectionTransfer
+                        | LastSectionQualifier::SectionDeactivated,
+                    checksum,
+                    ..
+                } => {
+                    if file_checksum(&section_data) == checksum {
+                        data.append(&mut section_data);
+                        retries = SECTION_RETRIES;
+                        let accepted = ack(section, AckAction::SectionAccepted, FileError::None);
+                        self.send_file_object(common_address, ioa, accepted).await?;
+                        self.emit_event(Iec104Event::FileProgress {
+                            common_address,
+                            file,
+                            received: data.len() as u32,
+                            total,
+                        })
+                        .await;
+                    } else {
+                        section_data.clear();
+                        let rejected =
+                            ack(section, AckAction::SectionRejected, FileError::Checksum);
+                        self.send_file_object(common_address, ioa, rejected).await?;
+                        if retries == 0 {
+                            return Err(failed("section checksum mismatch"));
+                        }
+                        retries -= 1;
+                    }
+                }
+                FileObject::LastSection {
+                    qualifier:
+                        LastSectionQualifier::FileTransfer | LastSectionQualifier::FileDeactivated,
+                    checksum,
+                    ..
+                } => {
+                    if file_checksum(&data) == checksum {
+                        let accepted = ack(0, AckAction::FileAccepted, FileError::None);
+                        self.send_file_object(common_address, ioa, accepted).await?;
+                        return Ok(data);
+                    }
+                    let rejected = ack(0, AckAction::FileRejected, FileError::Checksum);
+                    self.send_file_object(common_address, ioa, rejected).await?;
+                    return Err(failed("file checksum mismatch"));
+                }
+                _ => {}
+            }
+        }
+    }
+
+    async fn send_file_object(
+        &mut self,
+        common_address: u16,
+        ioa: u32,
+        object: FileObject,
+    ) -> Result<()> {
+        let asdu = Asdu::file_transfer(common_address, ioa, Cot::FileTransfer, &object);
+        self.send_i_frame(asdu).await
+    }
+
+    /// Wait for the next captured file transfer object.
+    async fn next_file_object(&mut self, ioa: u32, deadline: Instant) -> Result<(u32, FileObject)> {
+        loop {
+            if let Some(capture) = self.file_capture.as_mut() {
+                if let Some(error) = capture.rejected.take() {
+                    return Err(error);
+                }
+                if let Some(object) = capture.objects.pop_front() {
+                    return Ok(object);
+                }
+            }
+            if Instant::now() >= deadline {
+                return Err(Iec104Error::CommandTimeout {
+                    type_id: crate::types::TypeId::FileCall,
+                    ioa,
+                });
+            }
+            self.poll().await?;
+        }
+    }
+
+    /// Send a test command (C_TS_NA_1) and verify the mirrored test pattern.
+    ///
+    /// Returns [`Iec104Error::CommandTimeout`] if no confirmation arrives
+    /// within `timeout`, and a protocol error if the confirmation does not
+    /// mirror the fixed test pattern.
+    pub async fn test_command(&mut self, common_address: u16, timeout: Duration) -> Result<()> {
+        let asdu = Asdu::test_command(common_address);
+        self.send_test_command(asdu, timeout).await
+    }
+
+    /// Send a test command with time tag (C_TS_TA_1) and verify the mirrored
+    /// test sequence counter and time tag.
+    ///
+    /// The test sequence counter is maintained by the client.
+    pub async fn test_command_time(
+        &mut self,
+        common_address: u16,
+        time: Cp56Time2a,
+        timeout: Duration,
+    ) -> Result<()> {
+        let asdu = self.next_test_command_time(common_address, time);
+        self.send_test_command(asdu, timeout).await
+    }
+
+    /// Send counter interrogation command.
+    ///
+    /// Use [`Qcc::general_read`] to read all counters without freezing them.
+    pub async fn counter_interrogation(
+        &mut self,
+        common_address: u16,
+        qcc: Qcc,
+    ) -> Result<CommandCompletion> {
+        if self.state != ConnectionState::Active {
+            return Err(Iec104Error::NotConnected);
+        }
+
+        let asdu = Asdu::counter_interrogation_command(common_address, qcc);
+        self.send_command(asdu).await
+    }
+
+    /// Send clock synchronization command.
+    pub async fn clock_sync(
+        &mut self,
+        common_address: u16,
+        time: Cp56Time2a,
+    ) -> Result<CommandCompletion> {
+        if self.state != ConnectionState::Active {
+            return Err(Iec104Error::NotConnected);
+        }
+
+        let asdu = Asdu::clock_sync_command(common_address, time);
+        self.send_command(asdu).await
+    }
+
+    /// Send an arbitrary ASDU in an I-frame.
+    ///
+    /// Sequence numbers, the K window and the configured originator address
+    /// are handled as for every other frame; the ASDU is otherwise sent as
+    /// given and not tracked for confirmation. Meant for types the client
+    /// does not wrap yet.
+    pub async fn send_asdu(&mut self, asdu: Asdu) -> Result<()> {
+        if self.state != ConnectionState::Active {
+            return Err(Iec104Error::NotConnected);
+        }
+        self.send_i_frame(asdu).await
+    }
+
+    /// Forward a command received from another master, keeping its
+    /// originator address, and register it for confirmation tracking.
+    ///
+    /// Meant for gateways, where an
+    /// [`OriginatorRouter`](crate::gateway::OriginatorRouter) stamps the
+    /// originator address of each downstream master on the commands it
+    /// forwards and routes the confirmations back by it.
+    pub async fn forward_command(&mut self, asdu: Asdu) -> Result<CommandCompletion> {
+        if self.state != ConnectionState::Active {
+            return Err(Iec104Error::NotConnected);
+        }
+        self.wait_send_window().await?;
+        self.transmit_i_frame(asdu.clone()).await?;
+        Ok(self.pending.register(&asdu))
+    }
+
+    /// Synchronize the station clock to the local system time.
+    ///
+    /// The timestamp is UTC shifted by [`ClientConfig::utc_offset`].
+    pub async fn clock_sync_now(&mut self, common_address: u16) -> Result<CommandCompletion> {
+        let time = Cp56Time2a::from_system_time(SystemTime::now(), self.config.utc_offset);
+        self.clock_sync(common_address, time).await
+    }
+
+    /// Send reset process command (C_RP_NA_1) with a non-destructive qualifier.
+    ///
+    /// [`ResetProcessQualifier::GeneralReset`] is refused; use
+    /// [`dangerous_reset_process`](Self::dangerous_reset_process) for it. When
+    /// the station has reinitialized it reports
+    /// [`Iec104Event::EndOfInitialization`].
+    pub async fn reset_process(
+        &mut self,
+        common_address: u16,
+        qualifier: ResetProcessQualifier,
+    ) -> Result<CommandCompletion> {
+        if qualifier == ResetProcessQualifier::GeneralReset {
+            return Err(Iec104Error::protocol_static(
+                "General reset of process requires dangerous_reset_process",
+            ));
+        }
+        if self.state != ConnectionState::Active {
+            return Err(Iec104Error::NotConnected);
+        }
+
+        let asdu = Asdu::reset_process_command(common_address, qualifier);
+        self.send_command(asdu).await
+    }
+
+    /// Send reset process command (C_RP_NA_1).
+    ///
+    /// Resetting a process restarts the remote station and interrupts its
+    /// service. An [`Iec104Event::ResetProcessIssued`] audit event is emitted
+    /// before the command is sent.
+    pub async fn dangerous_reset_process(
+        &mut self,
+        common_address: u16,
+        qualifier: ResetProcessQualifier,
+    ) -> Result<CommandCompletion> {
+        if self.state != ConnectionState::Active {
+            return Err(Iec104Error::NotConnected);
+        }
+
+        self.emit_event(Iec104Event::ResetProcessIssued {
             common_address,
+            qualifier,
+        })
+        .await;
+
+        let asdu = Asdu::reset_process_command(common_address, qualifier);
+        self.send_command(asdu).await
+    }
+
+    /// Load a parameter of measured values (P_ME_NA_1, P_ME_NB_1 or P_ME_NC_1,
+    /// following the value variant), such as a threshold or smoothing factor.
+    ///
+    /// The current value can be read back with [`read`](Self::read), which
+    /// yields a [`DataValue::Parameter`](crate::types::DataValue::Parameter).
+    pub async fn set_parameter(
+        &mut self,
+        common_address: u16,
+        ioa: u32,
+        value: ParameterValue,
+        qpm: Qpm,
+    ) -> Result<CommandCompletion> {
+        value.validate()?;
+        if self.state != ConnectionState::Active {
+            return Err(Iec104Error::NotConnected);
+        }
+
+        let asdu = Asdu::parameter_command(common_address, ioa, value, qpm);
+        self.send_command(asdu).await
+    }
+
+    /// Activate (`activate = true`) or deactivate loaded parameters or cyclic
+    /// transmission (P_AC_NA_1).
+    pub async fn activate_parameter(
+        &mut self,
+        common_address: u16,
+        ioa: u32,
+        qpa: ParameterActivationQualifier,
+        activate: bool,
+    ) -> Result<CommandCompletion> {
+        if self.state != ConnectionState::Active {
+            return Err(Iec104Error::NotConnected);
+        }
+
+        let asdu = Asdu::parameter_activation(common_address, ioa, qpa, activate);
+        self.send_command(asdu).await
+    }
+
+    /// Send single command.
+    pub async fn single_command(
+        &mut self,
+        common_address: u16,
+        ioa: u32,
+        value: bool,
+        qualifier: CommandQualifier,
+    ) -> Result<CommandCompletion> {
+        self.command(common_address, Command::Single { ioa, value }, qualifier).await
+    }
+
+    /// Send double command.
+    ///
+    /// Only OFF and ON can be commanded; use [`DoubleCommandState::from_u8`]
+    /// to convert a raw state, which rejects the encodings 0 and 3.
+    pub async fn double_command(
+        &mut self,
+        common_address: u16,
+        ioa: u32,
+        value: DoubleCommandState,
+        qualifier: CommandQualifier,
+    ) -> Result<CommandCompletion> {
+        self.command(common_address, Command::Double { ioa, value }, qualifier).await
+    }
+
+    /// Send regulating step command (e.g. transformer tap changer).
+    pub async fn regulating_step(
+        &mut self,
+        common_address: u16,
+        ioa: u32,
+        step: StepCommand,
+        qualifier: CommandQualifier,
+    ) -> Result<CommandCompletion> {
+        self.command(common_address, Command::RegulatingStep { ioa, step }, qualifier).await
+    }
+
+    /// Send setpoint command (normalized value, -1.0..=1.0) with the qualifier `qos`.
+    pub async fn setpoint_normalized(
+        &mut self,
+        common_address: u16,
+        ioa: u32,
+        value: f32,
+        qos: Qos,
+    ) -> Result<CommandCompletion> {
+        let qualifier = CommandQualifier::EXECUTE.with_select(qos.select);
+        let command = Command::SetpointNormalized { ioa, value, qos };
+        self.command(common_address, command, qualifier).await
+    }
+
+    /// Send setpoint command (scaled value) with the qualifier `qos`.
+    pub async fn setpoint_scaled(
+        &mut self,
+        common_address: u16,
+        ioa: u32,
+        value: i16,
+        qos: Qos,
+    ) -> Result<CommandCompletion> {
+        let qualifier = CommandQualifier::EXECUTE.with_select(qos.select);
+        let command = Command::SetpointScaled { ioa, value, qos };
+        self.command(common_address, command, qualifier).await
+    }
+
+    /// Send setpoint command (short floating point) with the qualifier `qos`.
+    pub async fn setpoint_float(
+        &mut self,
+        common_address: u16,
+        ioa: u32,
+        value: f32,
+        qos: Qos,
+    ) -> Result<CommandCompletion> {
+        let qualifier = CommandQualifier::EXECUTE.with_select(qos.select);
+        let command = Command::SetpointFloat { ioa, value, qos };
+        self.command(common_address, command, qualifier).await
+    }
+
+    /// Send a command to the process (select or execute).
+    ///
+    /// Setpoints carry their own [`Qos`] and only take the S/E bit of
+    /// `qualifier`.
+    pub async fn command(
+        &mut self,
+        common_address: u16,
+        command: Command,
+        qualifier: CommandQualifier,
+    ) -> Result<CommandCompletion> {
+        if self.state != ConnectionState::Active {
+            return Err(Iec104Error::NotConnected);
+        }
+
+        command.validate()?;
+        self.send_command(command.to_asdu(common_address, Cot::Activation, qualifier)).await
+    }
+
+    /// Select a command, then execute it once the selection is confirmed.
+    ///
+    /// `pulse` is the output duration of both steps (ignored for setpoints).
+    /// Each confirmation must arrive within `window`, otherwise
+    /// [`Iec104Error::CommandTimeout`] is returned. When the selection may be
+    /// in effect but the command is not executed, it is cancelled with a
+    /// deactivation (COT=8). Frames received meanwhile are processed as by
+    /// [`poll`](Self::poll).
+    pub async fn select_then_execute(
+        &mut self,
+        common_address: u16,
+        command: Command,
+        pulse: PulseDuration,
+        window: Duration,
+    ) -> Result<()> {
+        let qualifier = CommandQualifier::new(pulse, true);
+        let mut selected = self.command(common_address, command, qualifier).await?;
+        let deadline = Instant::now() + window;
+        match self.wait_completion(&mut selected, deadline, false).await {
+            Ok(_) => {}
+            Err(e @ Iec104Error::CommandTimeout { .. }) => {
+                self.deselect(common_address, command, qualifier).await;
+                return Err(e);
+            }
+            Err(e) => return Err(e),
+        }
+
+        let execute = qualifier.with_select(false);
+        let result = match self.command(common_address, command, execute).await {
+            Ok(mut executed) => {
+                let deadline = Instant::now() + window;
+                self.wait_completion(&mut executed, deadline, false).await
+            }
+            Err(e) => Err(e),
+        };
+        if let Err(e) = result {
+            if !e.is_connection_error() {
+                self.deselect(common_address, command, qualifier).await;
+            }
+            return Err(e);
+        }
+        Ok(())
+    }
+
+    /// Process incoming frames.
+    ///
+    /// This should be called in a loop to handle incoming data, unless
+    /// [`run`](Self::run) or [`spawn`](Self::spawn) drives the connection.
+    /// A returned event is also delivered to the subscriber, in order with
+    /// all other events. An I-frame received out of sequence closes the
+    /// connection and returns [`Iec104Error::SequenceMismatch`].
+    #[cfg_attr(
+        feature = "tracing-support",
+        tracing::instrument(level = "debug", skip_all, fields(peer = %self.config.address))
+    )]
+    pub async fn poll(&mut self) -> Result<Option<Iec104Event>> {
+        if self.state == ConnectionState::Disconnected {
+            return Err(Iec104Error::NotConnected);
+        }
+
+        self.service_timers().await?;
+
+        // Try to receive a frame with a short timeout
+        let framed = self.framed.as_mut().ok_or(Iec104Error::NotConnected)?;
+        match timeout(Duration::from_millis(100), framed.next()).await {
+            Ok(frame) => self.process_frame(frame).await,
+            Err(_) => Ok(None), // Timeout, no data
+        }
+    }
+
+    /// Drive the connection until it closes.
+    ///
+    /// Frames are processed as they arrive and the T1/T2/T3 timers fire at
+    /// their deadlines, so no polling interval is involved. Events reach the
+    /// subscriber as with [`poll`](Self::poll); errors that do not end the
+    /// connection are reported as [`Iec104Event::Error`].
+    ///
+    /// Returns the error that closed the connection.
+    #[cfg_attr(
+        feature = "tracing-support",
+        tracing::instrument(level = "debug", skip_all, fields(peer = %self.config.address))
+    )]
+    pub async fn run(&mut self) -> Result<()> {
+        loop {
+            let due = self.next_timer_deadline();
+            let framed = self.framed.as_mut().ok_or(Iec104Error::NotConnected)?;
+            tokio::select! {
+                frame = framed.next() => self.dispatch_frame(frame).await?,
+                _ = sleep_until(due) => self.run_timers().await?,
+            }
+        }
+    }
+
+    /// Spawn a background task that owns the connection.
+    ///
+    /// The task receives frames, runs the protocol timers and executes
+    /// requests issued through the returned [`ClientHandle`], which can be
+    /// cloned freely. Events keep flowing to the receiver obtained from
+    /// [`subscribe`](Self::subscribe) before spawning.
+    ///
+    /// Received I-frames are acknowledged at most T2 after the first of them
+    /// arrived, without anyone having to call [`poll`](Self::poll).
+    ///
+    /// The task ends when the connection closes, a connection error occurs,
+    /// or every handle has been dropped (in which case it disconnects).
+    pub fn spawn(self) -> (ClientHandle, JoinHandle<Result<()>>) {
+        let (request_tx, request_rx) = mpsc::channel(32);
+        let task = tokio::spawn(self.run_actor(request_rx));
+        (ClientHandle::new(request_tx), task)
+    }
+
+    // Internal methods
+
+    /// Send TESTFR act on T3 expiry and an S-frame on T2 expiry.
+    ///
+    /// When the oldest unacknowledged I-frame has waited longer than T1 the
+    /// connection is closed and [`Iec104Error::T1Timeout`] returned; when a
+    /// TESTFR act is not confirmed within T1, [`Iec104Error::T3Timeout`].
+    /// Overdue commands are retried per [`ClientConfig::command_retry`].
+    pub(crate) async fn service_timers(&mut self) -> Result<()> {
+        let now = Instant::now();
+        let frame_expired = self.frame_deadline().is_some_and(|due| now >= due);
+        let test_expired = self.test_deadline().is_some_and(|due| now >= due);
+        if frame_expired || test_expired {
+            #[cfg(feature = "tracing-support")]
+            tracing::warn!(
+                peer = %self.config.address,
+                unacknowledged = self.in_flight.len(),
+                "{} expired, closing connection",
+                if frame_expired { "T1" } else { "TESTFR T1" }
+            );
+            let error = if frame_expired {
+                self.stats.t1_timeouts += 1;
+                Iec104Error::T1Timeout
+            } else {
+                self.stats.test_frame_timeouts += 1;
+                Iec104Error::T3Timeout
+            };
+            self.emit_event(Iec104Event::Error(error.to_string())).await;
+            self.drop_connection().await;
+            return Err(error);
+        }
+
+        let slots = usize::from(self.config.k.saturating_sub(self.unconfirmed_sends));
+        for asdu in self.pending.expire(now, slots) {
+            #[cfg(feature = "tracing-support")]
+            tracing::debug!(
+                ca = asdu.header.common_address,
+                type_id = %asdu.header.type_id,
+                "command not confirmed, re-sending"
+            );
+            self.transmit_i_frame(asdu).await?;
+        }
+
+        let need_test_frame = self.idle_deadline().is_some_and(|due| now >= due);
+        let need_s_frame = self.ack_deadline().is_some_and(|due| now >= due);
+
+        // Check T3 timeout (need to send test frame)
+        if need_test_frame {
+            self.send_u_frame(UFunction::TestFrAct).await?;
+            self.stats.test_frames_sent += 1;
+            self.test_frame_sent = Some(Instant::now());
+        }
+
+        // Check T2 timeout (need to send S-frame)
+        if need_s_frame {
+            self.send_s_frame().await?;
+        }
+        Ok(())
+    }
+
+    /// When the oldest unacknowledged I-frame must be acknowledged (T1).
+    fn frame_deadline(&self) -> Option<Instant> {
+        self.in_flight.front().map(|frame| frame.sent_at + self.config.t1_timeout)
+    }
+
+    /// When an outstanding TESTFR act must be confirmed (T1).
+    fn test_deadline(&self) -> Option<Instant> {
+        self.test_frame_sent.map(|sent| sent + self.config.t1_timeout)
+    }
+
+    /// When received I-frames must be acknowledged at the latest (T2).
+    fn ack_deadline(&self) -> Option<Instant> {
+        self.first_unacked_recv.map(|received| received + self.config.t2_timeout)
+    }
+
+    /// When an idle link is tested (T3), unless a test is already outstanding.
+    fn idle_deadline(&self) -> Option<Instant> {
+        match self.test_frame_sent {
+            Some(_) => None,
+            None => Some(self.last_recv_time + self.config.t3_timeout),
+        }
+    }
+
+    /// The earliest moment [`service_timers`](Self::service_timers) has work.
+    fn next_timer_deadline(&self) -> Instant {
+        [
+            self.frame_deadline(),
+            self.test_deadline(),
+            self.ack_deadline(),
+            self.idle_deadline(),
+            self.pending.next_deadline(),
+        ]
+            .into_iter()
+            .flatten()
+            .min()
+            .unwrap_or_else(|| Instant::now() + self.config.t3_timeout)
+    }
+
+    /// Run the protocol timers, dropping the connection on failure.
+    async fn run_timers(&mut self) -> Result<()> {
+        let result = self.service_timers().await;
+        if result.is_err() && self.framed.is_some() {
+            self.drop_connection().await;
+        }
+        result
+    }
+
+    /// Wait for the next frame or the next due timer, whichever comes first.
+    ///
+    /// Cancel-safe, so several links can be driven from one `select!`; hand
+    /// the input to [`process_input`](Self::process_input).
+    pub(crate) async fn next_input(&mut self) -> LinkInput {
+        let due = self.next_timer_deadline();
+        let Some(framed) = self.framed.as_mut() else {
+            return LinkInput::Frame(None);
+        };
+        tokio::select! {
+            frame = framed.next() => LinkInput::Frame(frame),
+            _ = sleep_until(due) => LinkInput::Timer,
+        }
+    }
+
+    /// Handle an input of [`next_input`](Self::next_input) as
+    /// [`poll`](Self::poll) would.
+    pub(crate) async fn process_input(&mut self, input: LinkInput) -> Result<Option<Iec104Event>> {
+        match input {
+            LinkInput::Frame(frame) => self.process_frame(frame).await,
+            LinkInput::Timer => self.service_timers().await.map(|_| None),
+        }
+    }
+
+    /// Handle the result of reading the next frame from the stream.
+    pub(crate) async fn process_frame(
+        &mut self,
+        frame: Option<Result<Apdu>>,
+    ) -> Result<Option<Iec104Event>> {
+        match frame {
+            Some(Ok(apdu)) => {
+                self.last_recv_time = Instant::now();
+                self.test_frame_sent = None;
+                self.stats.count_received(&apdu);
+                tap_frame(&self.tap, &apdu, FrameDirection::Received);
+                #[cfg(feature = "metrics")]
+                crate::metrics::frame(&self.config.address, FrameDirection::Received, &apdu);
+                self.handle_apdu(apdu).await
+            }
+            Some(Err(e)) => {
+                #[cfg(feature = "metrics")]
+                crate::metrics::read_error(&self.config.address, &e);
+                Err(e)
+            }
+            None => {
+                // Connection closed
+                self.drop_connection().await;
+                Err(Iec104Error::Connection(std::borrow::Cow::Borrowed("Connection closed by peer")))
+            }
+        }
+    }
+
+    /// Forget the connection without any closing handshake.
+    ///
+    /// Unacknowledged I-frames are kept for [`resend_unacknowledged`](Self::resend_unacknowledged)
+    /// and reported before the disconnect.
+    async fn drop_connection(&mut self) {
+        #[cfg(feature = "tracing-support")]
+        tracing::info!(
+            peer = %self.config.address,
+            unacknowledged = self.in_flight.len(),
+            "connection dropped"
+        );
+        self.framed = None;
+        self.session = None;
+        self.pending.clear();
+        self.set_state(ConnectionState::Disconnected);
+        self.unconfirmed_sends = 0;
+        if !self.in_flight.is_empty() {
+            self.unacknowledged = self.in_flight.drain(..).map(|frame| frame.asdu).collect();
+            let lost = self.unacknowledged.clone();
+            self.emit_event(Iec104Event::Unacknowledged(lost)).await;
+        }
+        self.emit_event(Iec104Event::Disconnected).await;
+    }
+
+    /// Background task body behind [`spawn`](Self::spawn).
+    #[cfg_attr(
+        feature = "tracing-support",
+        tracing::instrument(level = "debug", skip_all, fields(peer = %self.config.address))
+    )]
+    async fn run_actor(mut self, mut requests: mpsc::Receiver<Request>) -> Result<()> {
+        loop {
+            let due = self.next_timer_deadline();
+            let Some(framed) = self.framed.as_mut() else {
+                return Ok(());
+            };
+
+            // Only cancel-safe futures are raced; the work happens in the handlers
+            tokio::select! {
+                frame = framed.next() => self.dispatch_frame(frame).await?,
+                request = requests.recv() => match request {
+                    Some(request) => request(&mut self).await,
+                    // Every handle dropped
+                    None => return self.disconnect().await,
+                },
+                _ = sleep_until(due) => self.run_timers().await?,
+            }
+            self.complete_watches();
+        }
+    }
+
+    /// Process a frame for [`run`](Self::run) and the background task.
+    ///
+    /// Only errors that end the connection are returned.
+    async fn dispatch_frame(&mut self, frame: Option<Result<Apdu>>) -> Result<()> {
+        match self.process_frame(frame).await {
+            Ok(_) => Ok(()),
+            Err(
+                e @ (Iec104Error::Io(_)
+                | Iec104Error::Connection(_)
+                | Iec104Error::SequenceMismatch { .. }),
+            ) => {
+                if self.framed.is_some() {
+                    self.drop_connection().await;
+                }
+                Err(e)
+            }
+            Err(e) => {
+                #[cfg(feature = "tracing-support")]
+                tracing::warn!(error = %e, "frame not processed");
+                self.emit_event(Iec104Event::Error(e.to_string())).await;
+                Ok(())
+            }
+        }
+    }
+
+    fn set_state(&mut self, state: ConnectionState) {
+        self.state = state;
+        self.state_tx.send_replace(state);
+        #[cfg(feature = "metrics")]
+        crate::metrics::connection_state(&self.config.address, state);
+    }
+
+    /// Start a fresh session over a newly opened transport.
+    async fn attach(&mut self, transport: Transport) {
+        let codec = Iec104Codec::new().lenient(self.config.lenient_parsing);
+        self.framed = Some(Framed::new(transport, codec));
+        #[cfg(feature = "metrics")]
+        {
+            if self.connected_before {
+                crate::metrics::reconnect(&self.config.address);
+            }
+            self.connected_before = true;
+        }
+        self.set_state(ConnectionState::Connected);
+        self.send_seq = 0;
+        self.recv_seq = 0;
+        self.unconfirmed_sends = 0;
+        self.unconfirmed_recvs = 0;
+        self.first_unacked_recv = None;
+        self.in_flight.clear();
+        self.test_frame_sent = None;
+        self.last_recv_time = Instant::now();
+        self.last_send_time = Instant::now();
+
+        #[cfg(feature = "tracing-support")]
+        tracing::info!("connected");
+        self.emit_event(Iec104Event::Connected).await;
+    }
+
+    async fn open_transport(&self) -> Result<Transport> {
+        let stream = match self.config.local_address {
+            Some(local) => self.connect_from(local).await?,
+            None => TcpStream::connect(&self.config.address)
+                .await
+                .map_err(Iec104Error::Io)?,
+        };
+
+        // Disable Nagle's algorithm for low latency
+        stream.set_nodelay(true).ok();
+
+        #[cfg(feature = "tls")]
+        if let Some(tls) = &self.config.tls {
+            let stream = tls.connect(&self.config.address, stream).await?;
+            return Ok(Transport::Tls(Box::new(stream)));
+        }
+        Ok(Transport::Tcp(stream))
+    }
+
+    /// Connect from `local`, trying each server address of its IP version.
+    async fn connect_from(&self, local: SocketAddr) -> Result<TcpStream> {
+        let mut last_error = None;
+        for peer in lookup_host(&self.config.address).await.map_err(Iec104Error::Io)? {
+            if peer.is_ipv4() != local.is_ipv4() {
+                continue;
+            }
+            let socket = if local.is_ipv4() { TcpSocket::new_v4() } else { TcpSocket::new_v6() }
+                .map_err(Iec104Error::Io)?;
+            if local.port() != 0 {
+                // The previous connection from this port may be in TIME_WAIT
+                socket.set_reuseaddr(true).map_err(Iec104Error::Io)?;
+            }
+            socket.bind(local).map_err(Iec104Error::Io)?;
+            match socket.connect(peer).await {
+                Ok(stream) => return Ok(stream),
+                Err(err) => last_error = Some(err),
+            }
+        }
+        Err(match last_error {
+            Some(err) => Iec104Error::Io(err),
+            None => Iec104Error::Connection(
+                format!("No address of {} matches local address {}", self.config.address, local)
+                    .into(),
+            ),
+        })
+    }
+
+    fn build_session_info(&self) -> Result<SessionInfo> {
+        let framed = self.framed.as_ref().ok_or(Iec104Error::NotConnected)?;
+        let transport = framed.get_ref();
+        Ok(SessionInfo {
+            role: ProtocolRole::Controlling,
+            peer_address: transport.peer_addr()?,
+            local_address: transport.local_addr()?,
+            k: self.config.k,
+            w: self.config.w,
+            t1_timeout: self.config.t1_timeout,
+            t2_timeout: self.config.t2_timeout,
+            t3_timeout: self.config.t3_timeout,
+        })
+    }
+
+    async fn data_transfer_started(&mut self) -> Result<()> {
+        self.set_state(ConnectionState::Active);
+        self.emit_event(Iec104Event::DataTransferStarted).await;
+
+        let session = self.build_session_info()?;
+        self.session = Some(session.clone());
+        self.emit_event(Iec104Event::SessionEstablished(session)).await;
+        Ok(())
+    }
+
+    async fn data_transfer_stopped(&mut self) {
+        self.set_state(ConnectionState::Connected);
+        self.emit_event(Iec104Event::DataTransferStopped).await;
+    }
+
+    async fn emit_event(&mut self, event: Iec104Event) {
+        self.emit(event, None).await;
+    }
+
+    /// Deliver an event to every subscriber; `header` is that of the ASDU
+    /// it was produced from, for filtered subscribers.
+    async fn emit(&mut self, event: Iec104Event, header: Option<&AsduHeader>) {
+        let seq = self.event_seq;
+        self.event_seq += 1;
+
+        self.filtered.retain(|(_, tx)| !tx.is_closed());
+        for (filter, tx) in &self.filtered {
+            if let Some(event) = filter.apply(&event, header) {
+                let _ = tx.send(SequencedEvent { seq, event }).await;
+            }
+        }
+
+        let event = SequencedEvent { seq, event };
+
+        // No broadcast receivers is not an error
+        let _ = self.broadcast_tx.send(event.clone());
+        if self.event_rx.is_some() {
+            // Nobody subscribed yet: buffer without blocking the protocol
+            if let Err(_dropped) = self.event_tx.try_send(event) {
+                #[cfg(feature = "metrics")]
+                crate::metrics::event_dropped(&self.config.address);
+            }
+        } else {
+            let _ = self.event_tx.send(event).await;
+        }
+    }
+
+    async fn send_u_frame(&mut self, function: UFunction) -> Result<()> {
+        let framed = self.framed.as_mut().ok_or(Iec104Error::NotConnected)?;
+        let apdu = Apdu::u_frame(function);
+        #[cfg(feature = "tracing-support")]
+        tracing::trace!(function = ?function, "U-frame sent");
+        self.stats.count_sent(&apdu);
+        tap_frame(&self.tap, &apdu, FrameDirection::Sent);
+        #[cfg(feature = "metrics")]
+        crate::metrics::frame(&self.config.address, FrameDirection::Sent, &apdu);
+        framed.send(apdu).await?;
+        self.last_send_time = Instant::now();
+        Ok(())
+    }
+
+    async fn send_s_frame(&mut self) -> Result<()> {
+        let framed = self.framed.as_mut().ok_or(Iec104Error::NotConnected)?;
+        let apdu = Apdu::s_frame(self.recv_seq);
+        #[cfg(feature = "tracing-support")]
+        tracing::trace!(recv_seq = self.recv_seq, "S-frame sent");
+        self.stats.count_sent(&apdu);
+        tap_frame(&self.tap, &apdu, FrameDirection::Sent);
+        #[cfg(feature = "metrics")]
+        crate::metrics::frame(&self.config.address, FrameDirection::Sent, &apdu);
+        framed.send(apdu).await?;
+        self.last_send_time = Instant::now();
+        self.unconfirmed_recvs = 0;
+        self.first_unacked_recv = None;
+        Ok(())
+    }
+
+    async fn send_test_command(&mut self, asdu: Asdu, timeout: Duration) -> Result<()> {
+        let deadline = Instant::now() + timeout;
+        let (mut completion, expected) = self.start_test_command(asdu).await?;
+        let confirmation = self.wait_completion(&mut completion, deadline, false).await?;
+        check_test_mirror(&expected, &confirmation)
+    }
+
+    /// Send a test command and return the information elements to be mirrored.
+    pub(crate) async fn start_test_command(
+        &mut self,
+        asdu: Asdu,
+    ) -> Result<(CommandCompletion, Bytes)> {
+        if self.state != ConnectionState::Active {
+            return Err(Iec104Error::NotConnected);
+        }
+
+        let expected = asdu.objects[0].data.clone();
+        Ok((self.send_command(asdu).await?, expected))
+    }
+
+    /// Build the next C_TS_TA_1, advancing the test sequence counter.
+    pub(crate) fn next_test_command_time(
+        &mut self,
+        common_address: u16,
+        time: Cp56Time2a,
+    ) -> Asdu {
+        let tsc = self.test_sequence;
+        self.test_sequence = self.test_sequence.wrapping_add(1);
+        Asdu::test_command_time(common_address, tsc, time)
+    }
+
+    /// Send a general interrogation whose points are handed to `reply` once
+    /// it terminates, without waiting for it.
+    pub(crate) async fn watch_interrogation(
+        &mut self,
+        common_address: u16,
+        reply: oneshot::Sender<Result<Vec<DataPoint>>>,
+    ) -> Result<()> {
+        let completion = self.general_interrogation(common_address).await?;
+        let collector = Collector::new(Cot::InterrogatedByStation, common_address, None);
+        self.watches.push(Watch {
+            collector,
+            completion,
+            confirmed: false,
+            first_point: false,
+            reply,
+        });
+        Ok(())
+    }
+
+    /// Send a read command whose point is handed to `reply` once it
+    /// arrives, without waiting for it.
+    pub(crate) async fn watch_read(
+        &mut self,
+        common_address: u16,
+        ioa: u32,
+        reply: oneshot::Sender<Result<Vec<DataPoint>>>,
+    ) -> Result<()> {
+        if self.state != ConnectionState::Active {
+            return Err(Iec104Error::NotConnected);
+        }
+
+        let completion = self.send_command(Asdu::read_command(common_address, ioa)).await?;
+        self.watches.push(Watch {
+            collector: Collector::new(Cot::Request, common_address, Some(ioa)),
+            completion,
+            confirmed: false,
+            first_point: true,
+            reply,
+        });
+        Ok(())
+    }
+
+    /// Hand over the results of completed watches, and drop those nobody
+    /// waits for anymore.
+    fn complete_watches(&mut self) {
+        let mut i = 0;
+        while i < self.watches.len() {
+            let watch = &mut self.watches[i];
+            if watch.reply.is_closed() {
+                self.watches.swap_remove(i);
+                continue;
+            }
+            match watch.outcome() {
+                Some(result) => {
+                    let _ = self.watches.swap_remove(i).reply.send(result);
+                }
+                None => i += 1,
+            }
+        }
+    }
+
+    /// Cancel a selection, ignoring failures.
+    pub(crate) async fn deselect(
+        &mut self,
+        common_address: u16,
+        command: Command,
+        qualifier: CommandQualifier,
+    ) {
+        let asdu = command.to_asdu(common_address, Cot::Deactivation, qualifier);
+        let _ = self.send_i_frame(asdu).await;
+    }
+
+    /// Process frames until a command is confirmed (or terminated) or `deadline` passes.
+    async fn wait_completion(
+        &mut self,
+        completion: &mut CommandCompletion,
+        deadline: Instant,
+        terminated: bool,
+    ) -> Result<Asdu> {
+        loop {
+            let outcome = if terminated {
+                completion.try_terminated()
+            } else {
+                completion.try_confirmed()
+            };
+            if let Some(result) = outcome {
+                return result;
+            }
+            if Instant::now() >= deadline {
+                return Err(Iec104Error::CommandTimeout {
+                    type_id: completion.type_id(),
+                    ioa: completion.ioa(),
+                });
+            }
+            self.poll().await?;
+        }
+    }
+
+    /// Process frames until fewer than K I-frames are unacknowledged.
+    async fn wait_send_window(&mut self) -> Result<()> {
+        if self.unconfirmed_sends < self.config.k {
+            return Ok(());
+        }
+        let Some(wait) = self.config.send_window_timeout else {
+            return Err(Iec104Error::TooManyUnconfirmed(self.config.k));
+        };
+
+        let deadline = Instant::now() + wait;
+        while self.unconfirmed_sends >= self.config.k {
+            if Instant::now() >= deadline {
+                return Err(Iec104Error::TooManyUnconfirmed(self.config.k));
+            }
+            self.poll().await?;
+        }
+        Ok(())
+    }
+
+    /// Send a command and register it for confirmation tracking.
+    #[cfg_attr(
+        feature = "tracing-support",
+        tracing::instrument(
+            level = "debug",
+            skip_all,
+            fields(
+                ca = asdu.header.common_address,
+                ioa = ?crate::command::first_ioa(&asdu),
+                type_id = %asdu.header.type_id,
+                cot = %asdu.header.cot,
+            )
+        )
+    )]
+    async fn send_command(&mut self, mut asdu: Asdu) -> Result<CommandCompletion> {
+        asdu.header.originator = self.config.originator_address;
+        // Only tracked once sent: a command that failed must not be retried
+        self.send_i_frame(asdu.clone()).await?;
+        Ok(self.pending.register(&asdu))
+    }
+
+    async fn send_i_frame(&mut self, mut asdu: Asdu) -> Result<()> {
+        asdu.header.originator = self.config.originator_address;
+        self.wait_send_window().await?;
+        self.transmit_i_frame(asdu).await
+    }
+
+    /// Send an I-frame without checking the K window, originator address as given.
+    async fn transmit_i_frame(&mut self, asdu: Asdu) -> Result<()> {
+        let framed = self.framed.as_mut().ok_or(Iec104Error::NotConnected)?;
+        let apdu = Apdu::i_frame(self.send_seq, self.recv_seq, asdu.clone());
+        #[cfg(feature = "tracing-support")]
+        tracing::debug!(
+            send_seq = self.send_seq,
+            recv_seq = self.recv_seq,
+            ca = asdu.header.common_address,
+            type_id = %asdu.header.type_id,
+            cot = %asdu.header.cot,
+            "I-frame sent"
+        );
+        self.stats.count_sent(&apdu);
+        tap_frame(&self.tap, &apdu, FrameDirection::Sent);
+        #[cfg(feature = "metrics")]
+        crate::metrics::frame(&self.config.address, FrameDirection::Sent, &apdu);
+        framed.send(apdu).await?;
+
+        self.send_seq = (self.send_seq + 1) & 0x7FFF;
+        self.unconfirmed_sends += 1;
+        self.last_send_time = Instant::now();
+        self.in_flight.push_back(InFlight {
+            sent_at: self.last_send_time,
+            asdu,
+        });
+        self.unconfirmed_recvs = 0; // Piggyback acknowledgment
+        self.first_unacked_recv = None;
+        Ok(())
+    }
+
+    async fn recv_frame_timeout(&mut self, timeout_duration: Duration) -> Result<Apdu> {
+        let framed = self.framed.as_mut().ok_or(Iec104Error::NotConnected)?;
+
+        match timeout(timeout_duration, framed.next()).await {
+            Ok(Some(Ok(apdu))) => {
+                self.last_recv_time = Instant::now();
+                self.stats.count_received(&apdu);
+                tap_frame(&self.tap, &apdu, FrameDirection::Received);
+                #[cfg(feature = "metrics")]
+                crate::metrics::frame(&self.config.address, FrameDirection::Received, &apdu);
+                Ok(apdu)
+            }
+            Ok(Some(Err(e))) => {
+                #[cfg(feature = "metrics")]
+                crate::metrics::read_error(&self.config.address, &e);
+                Err(e)
+            }
+            Ok(None) => Err(Iec104Error::Connection(std::borrow::Cow::Borrowed("Connection closed"))),
+            Err(_) => Err(Iec104Error::T1Timeout),
+        }
+    }
+
+    async fn handle_apdu(&mut self, apdu: Apdu) -> Result<Option<Iec104Event>> {
+        match &apdu.apci {
+            crate::types::Apci::IFrame { send_seq, recv_seq } => {
+                #[cfg(feature = "tracing-support")]
+                if let Some(asdu) = &apdu.asdu {
+                    tracing::debug!(
+                        send_seq,
+                        recv_seq,
+                        ca = asdu.header.common_address,
+                        type_id = %asdu.header.type_id,
+                        cot = %asdu.header.cot,
+                        objects = asdu.header.vsq.count,
+                        "I-frame received"
+                    );
+                }
+
+                // Update acknowledgment
+                self.acknowledge_up_to(*recv_seq);
+
+                // Validate sequence number
+                if *send_seq != self.recv_seq {
+                    self.stats.sequence_errors += 1;
+                    #[cfg(feature = "tracing-support")]
+                    tracing::warn!(
+                        expected = self.recv_seq,
+                        actual = *send_seq,
+                        "I-frame out of sequence"
+                    );
+                    #[cfg(feature = "metrics")]
+                    crate::metrics::sequence_error(&self.config.address);
+                    let error = Iec104Error::SequenceMismatch {
+                        expected: self.recv_seq,
+                        actual: *send_seq,
+                    };
+                    // The standard requires closing the connection on N(S) errors
+                    self.drop_connection().await;
+                    return Err(error);
+                }
+
+                self.recv_seq = (self.recv_seq + 1) & 0x7FFF;
+                self.unconfirmed_recvs += 1;
+                self.first_unacked_recv.get_or_insert_with(Instant::now);
+
+                // Send S-frame if W threshold reached
+                if self.unconfirmed_recvs >= self.config.w {
+                    self.send_s_frame().await?;
+                }
+
+                // Process ASDU
+                if let Some(asdu) = apdu.asdu {
+                    if let Some(capture) = self.file_capture.as_mut() {
+                        capture.collect(&asdu);
+                    }
+                    if self.pending.resolve(&asdu) {
+                        #[cfg(feature = "tracing-support")]
+                        tracing::debug!(
+                            ca = asdu.header.common_address,
+                            ioa = ?crate::command::first_ioa(&asdu),
+                            type_id = %asdu.header.type_id,
+                            cot = %asdu.header.cot,
+                            negative = asdu.header.negative,
+                            "command answered"
+                        );
+                    }
+                    let header = asdu.header.clone();
+                    if header.type_id == crate::types::TypeId::ClockSync {
+                        self.record_clock_sync(&asdu);
+                    }
+                    let mut event = self.process_asdu(asdu);
+                    if let Iec104Event::DataUpdate(update) = &mut event {
+                        if self.config.complete_partial_timestamps {
+                            self.complete_timestamps(header.common_address, update);
+                        }
+                        if let Some(collector) = self.collector.as_mut() {
+                            collector.collect(&header, update);
+                        }
+                        for watch in &mut self.watches {
+                            watch.collector.collect(&header, update);
+                        }
+                        if self.config.point_cache {
+                            for point in update.iter() {
+                                let key = (header.common_address, point.ioa);
+                                self.points.insert(key, point.clone());
+                            }
+                        }
+                        let config = &self.config;
+                        let deadbands =
+                            config.deadband.is_some() || !config.point_deadbands.is_empty();
+                        if deadbands && !header.cot.is_interrogation_response() {
+                            self.deadbands.retain(header.common_address, update, |ioa| {
+                                config.point_deadbands.get(&ioa).copied().or(config.deadband)
+                            });
+                            if update.is_empty() {
+                                return Ok(None);
+                            }
+                        }
+                    }
+                    self.emit(event.clone(), Some(&header)).await;
+                    if let Iec104Event::EndOfInitialization { common_address, .. } = event {
+                        if self.config.interrogate_on_init {
+                            self.interrogate_after_init(common_address).await?;
+                        }
+                    }
+                    return Ok(Some(event));
+                }
+            }
+
+            crate::types::Apci::SFrame { recv_seq } => {
+                #[cfg(feature = "tracing-support")]
+                tracing::trace!(recv_seq, "S-frame received");
+                self.acknowledge_up_to(*recv_seq);
+            }
+
+            crate::types::Apci::UFrame { function } => {
+                #[cfg(feature = "tracing-support")]
+                tracing::trace!(function = ?function, "U-frame received");
+                match function {
+                    UFunction::TestFrAct => {
+                        // Respond with TESTFR con
+                        self.send_u_frame(UFunction::TestFrCon).await?;
+                    }
+                    UFunction::TestFrCon => {
+                        self.stats.test_frames_confirmed += 1;
+                    }
+                    function => {
+                        // Confirmations awaited by start_dt/stop_dt never get here
+                        let event = Iec104Event::UnsolicitedUFrame(*function);
+                        self.emit_event(event.clone()).await;
+                        match (function, self.state) {
+                            (UFunction::StartDtCon, ConnectionState::Connected) => {
+                                self.data_transfer_started().await?;
+                            }
+                            (UFunction::StopDtCon, ConnectionState::Active) => {
+                                self.data_transfer_stopped().await;
+                            }
+                            _ => {}
+                        }
+                        return Ok(Some(event));
+                    }
+                }
+            }
+        }
+
+        Ok(None)
+    }
+
+    /// Send the general interrogation requested by
+    /// [`ClientConfig::interrogate_on_init`].
+    ///
+    /// Runs on the receive path, so it cannot wait for the K window; when
+    /// the window is full the interrogation is skipped and reported as an
+    /// error event.
+    async fn interrogate_after_init(&mut self, common_address: u16) -> Result<()> {
+        if self.unconfirmed_sends >= self.config.k {
+            let message = format!(
+                "Interrogation of station {} after initialization skipped: {}",
+                common_address,
+                Iec104Error::TooManyUnconfirmed(self.config.k)
+            );
+            self.emit_event(Iec104Event::Error(message)).await;
+            return Ok(());
+        }
+        let mut asdu = Asdu::interrogation_command(common_address, 20);
+        asdu.header.originator = self.config.originator_address;
+        self.transmit_i_frame(asdu.clone()).await?;
+        // Nobody waits for it, but it is still retried under the policy
+        self.pending.register(&asdu);
+        Ok(())
+    }
+
+    /// Remember the time carried by a clock synchronization ASDU.
+    fn record_clock_sync(&mut self, asdu: &Asdu) {
+        let time = asdu.raw_data.get(3..10).map(Cp56Time2a::from_bytes);
+        if let Some(Ok(time)) = time {
+            if !time.invalid {
+                self.station_clocks.insert(asdu.header.common_address, time);
+            }
+        }
+    }
+
+    /// Track full time tags and complete partial ones against the latest.
+    fn complete_timestamps(&mut self, common_address: u16, update: &mut [DataPoint]) {
+        for point in update {
+            match point.timestamp {
+                Some(Timestamp::Full(time)) if !time.invalid => {
+                    self.station_clocks.insert(common_address, time);
+                }
+                Some(Timestamp::Partial(time)) => {
+                    if let Some(clock) = self.station_clocks.get(&common_address) {
+                        point.timestamp = Some(Timestamp::Full(time.complete(clock)));
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+
+    fn acknowledge_up_to(&mut self, recv_seq: u16) {
+        // IEC 104 sequence numbers are 15-bit (0..32767).
+        const SEQ_MASK: u16 = 0x7FFF;
+
+        let oldest_unacked = self.send_seq.wrapping_sub(self.unconfirmed_sends) & SEQ_MASK;
+        let acked = recv_seq.wrapping_sub(oldest_unacked) & SEQ_MASK;
+
+        if acked <= self.unconfirmed_sends {
+            self.unconfirmed_sends -= acked;
+            self.in_flight.drain(..acked as usize);
+        }
+    }
+
+    /// Process received ASDU and convert to appropriate event.
+    fn process_asdu(&self, asdu: Asdu) -> Iec104Event {
+        use crate::types::TypeId;
+
+        // Check for special COT values
+        match asdu.header.cot {
+            // Command confirmation - extract IOA from first object if available
+            Cot::ActivationConfirm | Cot::DeactivationConfirm if asdu.raw_data.len() >= 3 => {
+                let ioa = asdu.raw_data[0] as u32
+                    | ((asdu.raw_data[1] as u32) << 8)
+                    | ((asdu.raw_data[2] as u32) << 16);
+                return Iec104Event::CommandConfirm {
+                    ioa,
+                    success: !asdu.header.negative,
+                };
+            }
+            // Interrogation complete
+            Cot::ActivationTermination if asdu.header.type_id == TypeId::InterrogationCommand => {
+                return Iec104Event::InterrogationComplete {
+                    common_address: asdu.header.common_address,
+                };
+            }
+            Cot::ActivationTermination
+                if asdu.header.type_id == TypeId::CounterInterrogation
+                    && asdu.raw_data.len() >= 4 =>
+            {
+                return Iec104Event::CounterInterrogationComplete {
+                    common_address: asdu.header.common_address,
+                    qcc: Qcc::from_u8(asdu.raw_data[3]),
+                };
+            }
+            _ => {}
+        }
+
+        // Station (re)initialized
+        if asdu.header.type_id == TypeId::EndOfInit && asdu.raw_data.len() >= 4 {
+            return Iec104Event::EndOfInitialization {
+                common_address: asdu.header.common_address,
+                coi: Coi::from_u8(asdu.raw_data[3]),
+            };
+        }
+
+        // Check for negative confirmation (error response)
+        if asdu.header.negative {
+            return Iec104Event::Error(format!(
+                "Negative confirmation for {} (COT={})",
+                asdu.header.type_id, asdu.header.cot
+            ));
+        }
+
+        // Try to parse data points
+        match crate::parser::parse_asdu(&asdu) {
+            Ok(points) if !points.is_empty() => Iec104Event::DataUpdate(points),
+            Ok(_) => {
+                // No data points (command types, etc.) - return raw ASDU
+                Iec104Event::AsduReceived(asdu)
+            }
+            Err(e) => {
+                // Parse error - return as error event
+                Iec104Event::Error(format!("ASDU parse error: {}", e))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::TypeId;
+    use bytes::Bytes;
+
+    #[test]
+    fn test_client_config() {
+        let config = ClientConfig::new("192.168.1.100:2404")
+            .connect_timeout(Duration::from_secs(5))
+            .t1_timeout(Duration::from_secs(10));
+
+        assert_eq!(config.address, "192.168.1.100:2404");
+        assert_eq!(config.connect_timeout, Duration::from_secs(5));
+        assert_eq!(config.t1_timeout, Duration::from_secs(10));
+        assert_eq!(config.t2_timeout, Duration::from_secs(DEFAULT_T2_TIMEOUT));
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_client_config_serde() {
+        let config = ClientConfig::new("192.168.1.100:2404")
+            .t3_timeout(Duration::from_millis(12_500))
+            .command_retry(RetryPolicy::new(Duration::from_secs(2), 3))
+            .deadband(Deadband::Percent(0.5))
+            .point_deadband(1001, Deadband::Absolute(2.0));
+
+        let json = serde_json::to_string(&config).unwrap();
+        let restored: ClientConfig = serde_json::from_str(&json).unwrap();
+        assert_eq!(restored.address, config.address);
+        assert_eq!(restored.t3_timeout, Duration::from_millis(12_500));
+        assert_eq!(restored.command_retry, config.command_retry);
+        assert_eq!(restored.deadband, Some(Deadband::Percent(0.5)));
+        assert_eq!(restored.point_deadbands, config.point_deadbands);
+    }
+
+    #[test]
+    fn test_client_initial_state() {
+        let config = ClientConfig::new("localhost:2404");
+        let client = Iec104Client::new(config);
+
+        assert_eq!(client.state(), ConnectionState::Disconnected);
+    }
+
+    #[tokio::test]
+    async fn test_session_info_after_start_dt() {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+        use tokio::net::TcpListener;
+
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            let (mut socket, _) = listener.accept().await.unwrap();
+            let mut buf = [0u8; 6];
+            socket.read_exact(&mut buf).await.unwrap();
+            socket.write_all(&[0x68, 0x04, 0x0B, 0x00, 0x00, 0x00]).await.unwrap();
+            // Keep the connection open until the client is done
+            let _ = socket.read(&mut buf).await;
+        });
+
+        let mut client = Iec104Client::new(ClientConfig::new(addr.to_string()));
+        let mut events = client.subscribe().unwrap();
+        assert!(client.session_info().is_none());
+
+        client.connect().await.unwrap();
+        client.start_dt().await.unwrap();
+
+        let info = client.session_info().unwrap().clone();
+        assert_eq!(info.role, ProtocolRole::Controlling);
+        assert_eq!(info.peer_address, addr);
+        assert_eq!(info.k, DEFAULT_K);
+        assert_eq!(info.w, DEFAULT_W);
+
+        assert!(matches!(events.recv().await.unwrap().event, Iec104Event::Connected));
+        assert!(matches!(
+            events.recv().await.unwrap().event,
+            Iec104Event::DataTransferStarted
+        ));
+        match events.recv().await.unwrap().event {
+            Iec104Event::SessionEstablished(event_info) => assert_eq!(event_info, info),
+            other => panic!("Expected SessionEstablished, got {:?}", other),
+        }
+
+        client.disconnect().await.unwrap();
+        assert!(client.session_info().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_local_address() {
+        use tokio::net::TcpListener;
+
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let source: SocketAddr = "127.0.0.2:0".parse().unwrap();
+        let config = ClientConfig::new(addr.to_string()).local_address(source);
+        let mut client = Iec104Client::new(config);
+        let (connected, accepted) = tokio::join!(client.connect(), listener.accept());
+        connected.unwrap();
+        assert_eq!(accepted.unwrap().1.ip(), source.ip());
+
+        // Reconnecting from a fixed port
+        let port = std::net::TcpListener::bind("127.0.0.2:0").unwrap().local_addr().unwrap().port();
+        let source = SocketAddr::new(source.ip(), port);
+        let config = ClientConfig::new(addr.to_string()).local_address(source);
+        let mut client = Iec104Client::new(config);
+        for _ in 0..2 {
+            client.connect().await.unwrap();
+            let (_station, peer) = listener.accept().await.unwrap();
+            assert_eq!(peer, source);
+            // Closed by the client first, which leaves it in TIME_WAIT
+            client.disconnect().await.unwrap();
+        }
+
+        // No IPv4 server address to reach from an IPv6 source
+        let source: SocketAddr = "[::1]:0".parse().unwrap();
+        let config = ClientConfig::new(addr.to_string()).local_address(source);
+        let mut client = Iec104Client::new(config);
+        assert!(matches!(client.connect().await, Err(Iec104Error::Connection(_))));
+    }
+
+    #[tokio::test]
+    async fn test_connect_stream() {
+        use crate::codec::Apdu;
+        use futures::{SinkExt, StreamExt};
+
+        let (client_end, station_end) = tokio::io::duplex(1024);
+        tokio::spawn(async move {
+            let mut station = Framed::new(station_end, Iec104Codec::new());
+            station.next().await.unwrap().unwrap();
+            station.send(Apdu::u_frame(UFunction::StartDtCon)).await.unwrap();
+            let header = AsduHeader::new(TypeId::SinglePoint, 1, Cot::Spontaneous, 1);
+            let mut asdu = Asdu::new(header);
+            asdu.raw_data = Bytes::from_static(&[100, 0, 0, 0x01]);
+            station.send(Apdu::i_frame(0, 0, asdu)).await.unwrap();
+            while station.next().await.is_some() {}
+        });
+
+        let mut client = Iec104Client::new(ClientConfig::new("rtu-17"));
+        let mut events = client.subscribe().unwrap();
+        client.connect_stream(client_end).await.unwrap();
+        assert!(client.connect_stream(tokio::io::empty()).await.is_err());
+        client.start_dt().await.unwrap();
+        let info = client.session_info().unwrap();
+        assert!(info.peer_address.ip().is_unspecified());
+        assert_eq!(info.local_address.port(), 0);
+
+        let points = loop {
+            match events.try_recv() {
+                Ok(SequencedEvent {
+                    event: Iec104Event::DataUpdate(points),
+                    ..
+                }) => break points,
+                Ok(_) => {}
+                Err(_) => {
+                    client.poll().await.unwrap();
+                }
+            }
+        };
+        assert_eq!(points[0].ioa, 100);
+        assert_eq!(points[0].value, crate::types::DataValue::Single(true));
+    }
+
+    #[tokio::test]
+    async fn test_events_delivered_in_wire_order() {
+        let (mut client, mut server) = crate::testing::pair().await.unwrap();
+        let mut events = client.subscribe().unwrap();
+        tokio::spawn(async move {
+            server.recv_apdu().await.unwrap();
+            server.send_apdu(Apdu::u_frame(UFunction::StartDtCon)).await.unwrap();
+
+            // Spontaneous single point, then an activation confirmation
+            let mut data = Asdu::new(AsduHeader::new(TypeId::SinglePoint, 1, Cot::Spontaneous, 1));
+            data.raw_data = Bytes::from_static(&[0x01, 0x00, 0x00, 0x01]);
+            let mut confirm =
+                Asdu::new(AsduHeader::new(TypeId::SingleCommand, 1, Cot::ActivationConfirm, 1));
+            confirm.raw_data = Bytes::from_static(&[0x02, 0x00, 0x00, 0x01]);
+            server.send_asdu(data).await.unwrap();
+            server.send_asdu(confirm).await.unwrap();
+            while server.recv_apdu().await.is_ok() {}
+        });
+
+        client.start_dt().await.unwrap();
+
+        let mut received = 0;
+        while received < 2 {
+            if client.poll().await.unwrap().is_some() {
+                received += 1;
+            }
+        }
+
+        let mut seqs = Vec::new();
+        let mut kinds = Vec::new();
+        for _ in 0..5 {
+            let event = events.recv().await.unwrap();
+            seqs.push(event.seq);
+            kinds.push(event.event);
+        }
+        assert_eq!(seqs, vec![0, 1, 2, 3, 4]);
+        assert!(matches!(kinds[3], Iec104Event::DataUpdate(_)));
+        assert!(matches!(kinds[4], Iec104Event::CommandConfirm { ioa: 2, success: true }));
+    }
+
+    #[tokio::test]
+    async fn test_select_then_execute() {
+        let (mut client, mut server) = crate::testing::pair().await.unwrap();
+        let (seen_tx, mut seen_rx) = mpsc::unbounded_channel();
+        tokio::spawn(async move {
+            while let Ok(asdu) = server.recv_asdu().await {
+                let (ioa, sco) = (asdu.raw_data[0], asdu.raw_data[3]);
+                seen_tx.send((ioa, asdu.header.cot, sco & 0x80 != 0)).unwrap();
+
+                // IOA 2 never confirms the execution
+                let select = sco & 0x80 != 0;
+                if asdu.header.cot == Cot::Activation && (select || ioa != 2) {
+                    server.respond(&asdu, Cot::ActivationConfirm).await.unwrap();
+                }
+            }
+        });
+
+        client.start_dt().await.unwrap();
+
+        let window = Duration::from_millis(300);
+        let pulse = PulseDuration::Unspecified;
+        client
+            .select_then_execute(1, Command::Single { ioa: 1, value: true }, pulse, window)
+            .await
+            .unwrap();
+        assert_eq!(seen_rx.recv().await.unwrap(), (1, Cot::Activation, true));
+        assert_eq!(seen_rx.recv().await.unwrap(), (1, Cot::Activation, false));
+
+        let result = client
+            .select_then_execute(1, Command::Single { ioa: 2, value: true }, pulse, window)
+            .await;
+        assert!(matches!(result, Err(Iec104Error::CommandTimeout { ioa: 2, .. })));
+        assert_eq!(seen_rx.recv().await.unwrap(), (2, Cot::Activation, true));
+        assert_eq!(seen_rx.recv().await.unwrap(), (2, Cot::Activation, false));
+        assert_eq!(seen_rx.recv().await.unwrap(), (2, Cot::Deactivation, true));
+    }
+
+    #[tokio::test]
+    async fn test_setpoint_qos() {
+        let (mut client, mut server) = crate::testing::pair().await.unwrap();
+        let station = tokio::spawn(async move {
+            let mut qualifiers = Vec::new();
+            while let Ok(asdu) = server.recv_asdu().await {
+                qualifiers.push((asdu.header.type_id, *asdu.raw_data.last().unwrap()));
+            }
+            qualifiers
+        });
+
+        client.start_dt().await.unwrap();
+        client.setpoint_normalized(1, 5, 0.5, Qos::new(5, true)).await.unwrap();
+        client.setpoint_scaled(1, 6, -2, Qos::new(64, false)).await.unwrap();
+        client.setpoint_float(1, 7, 1.5, Qos::SELECT).await.unwrap();
+        drop(client);
+        assert_eq!(
+            station.await.unwrap(),
+            vec![
+                (TypeId::SetpointNormalized, 0x85),
+                (TypeId::SetpointScaled, 0x40),
+                (TypeId::SetpointFloat, 0x80),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_forward_command_keeps_originator() {
+        let config = ClientConfig::new("test").originator_address(7);
+        let (mut client, mut server) = crate::testing::pair_with(config).await.unwrap();
+        let station = tokio::spawn(async move {
+            let asdu = server.recv_asdu().await?;
+            server.respond(&asdu, Cot::ActivationConfirm).await?;
+            while server.recv_apdu().await.is_ok() {}
+            Ok::<_, Iec104Error>(asdu.header.originator)
+        });
+
+        client.start_dt().await.unwrap();
+        let mut asdu = Asdu::read_command(1, 100);
+        asdu.header.originator = 3;
+        let mut completion = client.forward_command(asdu).await.unwrap();
+        let confirmation = loop {
+            if let Some(result) = completion.try_confirmed() {
+                break result.unwrap();
+            }
+            client.poll().await.unwrap();
+        };
+        assert_eq!(confirmation.header.originator, 3);
+
+        drop(client);
+        assert_eq!(station.await.unwrap().unwrap(), 3);
+    }
+
+    #[tokio::test]
+    async fn test_general_interrogation_snapshot() {
+        let (mut client, mut server) = crate::testing::pair().await.unwrap();
+        tokio::spawn(async move {
+            let gi = server.recv_asdu().await.unwrap();
+            let mut station = Asdu::new(AsduHeader::new(
+                TypeId::SinglePoint,
+                2,
+                Cot::InterrogatedByStation,
+                1,
+            ));
+            station.raw_data =
+                Bytes::from_static(&[0x01, 0x00, 0x00, 0x01, 0x02, 0x00, 0x00, 0x00]);
+            let mut spontaneous =
+                Asdu::new(AsduHeader::new(TypeId::SinglePoint, 1, Cot::Spontaneous, 1));
+            spontaneous.raw_data = Bytes::from_static(&[0x09, 0x00, 0x00, 0x01]);
+
+            let replies = [
+                gi.mirror(Cot::ActivationConfirm, false),
+                station,
+                spontaneous,
+                gi.mirror(Cot::ActivationTermination, false),
+            ];
+            for asdu in replies {
+                server.send_asdu(asdu).await.unwrap();
+            }
+            while server.recv_apdu().await.is_ok() {}
+        });
+
+        client.start_dt().await.unwrap();
+
+        let points = client
+            .general_interrogation_snapshot(1, Duration::from_secs(5))
+            .await
+            .unwrap();
+        let ioas: Vec<u32> = points.iter().map(|p| p.ioa).collect();
+        assert_eq!(ioas, vec![1, 2]);
+        assert!(client.collector.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_read() {
+        use crate::types::{DataValue, Ioa};
+
+        let (mut client, mut server) = crate::testing::pair().await.unwrap();
+        tokio::spawn(async move {
+            while let Ok(read) = server.recv_asdu().await {
+                let reply = match Ioa::try_from_slice(&read.raw_data).unwrap().value() {
+                    // Scaled value 300, good quality
+                    1 => {
+                        let mut reply =
+                            Asdu::new(AsduHeader::new(TypeId::MeasuredScaled, 1, Cot::Request, 1));
+                        reply.raw_data = Bytes::from_static(&[0x01, 0x00, 0x00, 0x2C, 0x01, 0x00]);
+                        reply
+                    }
+                    _ => read.mirror(Cot::UnknownIoa, true),
+                };
+                server.send_asdu(reply).await.unwrap();
+            }
+        });
+
+        client.start_dt().await.unwrap();
+
+        let point = client.read(1, 1, Duration::from_secs(5)).await.unwrap();
+        assert_eq!(point.ioa, 1);
+        assert_eq!(point.value, DataValue::Scaled(300));
+
+        let result = client.read(1, 9, Duration::from_secs(5)).await;
+        assert!(matches!(
+            result,
+            Err(Iec104Error::CommandRejected { type_id: TypeId::ReadCommand, ioa: 9, .. })
         ));
+    }
+
+    #[tokio::test]
+    async fn test_set_and_read_parameter() {
+        use crate::types::{DataValue, ParameterKind};
+
+        let (mut client, mut server) = crate::testing::pair().await.unwrap();
+        tokio::spawn(async move {
+            let mut stored = Bytes::new();
+            while let Ok(request) = server.recv_asdu().await {
+                let reply = match request.header.type_id {
+                    TypeId::ParameterScaled => {
+                        stored = request.raw_data.clone();
+                        request.mirror(Cot::ActivationConfirm, false)
+                    }
+                    TypeId::ReadCommand => {
+                        let mut reply =
+                            Asdu::new(AsduHeader::new(TypeId::ParameterScaled, 1, Cot::Request, 1));
+                        reply.raw_data = stored.clone();
+                        reply
+                    }
+                    _ => request.mirror(Cot::ActivationConfirm, false),
+                };
+                server.send_asdu(reply).await.unwrap();
+            }
+        });
+
+        let qpm = Qpm::new(ParameterKind::Threshold);
+        let result = client.set_parameter(1, 16, ParameterValue::Normalized(2.0), qpm).await;
+        assert!(matches!(result, Err(Iec104Error::InvalidAsdu(_))));
+
+        client.start_dt().await.unwrap();
+        let (handle, _task) = client.spawn();
+
+        handle
+            .set_parameter(1, 16, ParameterValue::Scaled(300), qpm)
+            .await
+            .unwrap()
+            .confirmed()
+            .await
+            .unwrap();
+        handle
+            .activate_parameter(1, 16, ParameterActivationQualifier::ObjectParameter, true)
+            .await
+            .unwrap()
+            .confirmed()
+            .await
+            .unwrap();
+
+        let point = handle.read(1, 16, Duration::from_secs(5)).await.unwrap();
+        assert_eq!(
+            point.value,
+            DataValue::Parameter {
+                value: ParameterValue::Scaled(300),
+                qpm
+            }
+        );
+    }
+
+    #[tokio::test]
+    async fn test_file_directory_and_download() {
+        use crate::types::FileStatus;
+
+        const SECTIONS: [&[u8]; 2] = [b"TRIG,1999,1\r\n", b"DATA"];
+
+        let (mut client, mut server) = crate::testing::pair().await.unwrap();
+        let mut events = client.subscribe().unwrap();
+        tokio::spawn(async move {
+            let time = Cp56Time2a::from_bytes(&[0x10, 0x27, 30, 12, 0x6F, 6, 24]).unwrap();
+            let total: usize = SECTIONS.iter().map(|s| s.len()).sum();
+            let mut corrupted = false;
+            while let Ok(request) = server.recv_asdu().await {
+                let (ioa, object) = FileObject::parse_asdu(&request).unwrap().remove(0);
+                let replies = match object {
+                    FileObject::Call {
+                        action: CallAction::Default,
+                        ..
+                    } => vec![
+                        FileObject::Directory {
+                            file: 2,
+                            length: 100,
+                            status: FileStatus::default(),
+                            time,
+                        },
+                        FileObject::Directory {
+                            file: 3,
+                            length: total as u32,
+                            status: FileStatus::from_u8(0x20),
+                            time,
+                        },
+                    ],
+                    FileObject::Call {
+                        file,
+                        action: CallAction::SelectFile,
+                        ..
+                    } => vec![FileObject::FileReady {
+                        file,
+                        length: total as u32,
+                        negative: false,
+                    }],
+                    FileObject::Call {
+                        file,
+                        action: CallAction::RequestFile,
+                        ..
+                    }
+                    | FileObject::Ack {
+                        file,
+                        section: 1,
+                        action: AckAction::SectionAccepted,
+                        ..
+                    } => {
+                        let section = if matches!(object, FileObject::Call { .. }) { 1 } else { 2 };
+                        vec![FileObject::SectionReady {
+                            file,
+                            section,
+                            length: SECTIONS[section as usize - 1].len() as u32,
+                            not_ready: false,
+                        }]
+                    }
+                    FileObject::Ack {
+                        file,
+                        section,
+                        action: AckAction::SectionRejected,
+                        error,
+                    } => {
+                        assert_eq!(error, FileError::Checksum);
+                        vec![FileObject::SectionReady {
+                            file,
+                            section,
+                            length: SECTIONS[section as usize - 1].len() as u32,
+                            not_ready: false,
+                        }]
+                    }
+                    FileObject::Call {
+                        file,
+                        section,
+                        action: CallAction::RequestSection,
+                        ..
+                    } => {
+                        let content = SECTIONS[section as usize - 1];
+                        let mut replies: Vec<FileObject> = content
+                            .chunks(8)
+                            .map(|chunk| FileObject::Segment {
+                                file,
+                                section,
+                                data: Bytes::copy_from_slice(chunk),
+                            })
+                            .collect();
+                        let mut checksum = file_checksum(content);
+                        if section == 2 && !corrupted {
+                            corrupted = true;
+                            checksum = checksum.wrapping_add(1);
+                        }
+                        replies.push(FileObject::LastSection {
+                            file,
+                            section,
+                            qualifier: LastSectionQualifier::SectionTransfer,
+                            checksum,
+                        });
+                        replies
+                    }
+                    FileObject::Ack {
+                        file,
+                        action: AckAction::SectionAccepted,
+                        ..
+                    } => vec![FileObject::LastSection {
+                        file,
+                        section: 0,
+                        qualifier: LastSectionQualifier::FileTransfer,
+                        checksum: file_checksum(&SECTIONS.concat()),
+                    }],
+                    _ => Vec::new(),
+                };
+                for reply in replies {
+                    let asdu = Asdu::file_transfer(1, ioa, request.header.cot, &reply);
+                    server.send_asdu(asdu).await.unwrap();
+                }
+            }
+        });
+
+        client.start_dt().await.unwrap();
+
+        let directory = client.file_directory(1, 0, Duration::from_secs(5)).await.unwrap();
+        assert_eq!(directory.len(), 2);
+        assert_eq!(directory[1].file, 3);
+        assert!(directory[1].status.last_file);
+
+        let data = client.download_file(1, 0x10, 3, Duration::from_secs(5)).await.unwrap();
+        assert_eq!(data, SECTIONS.concat());
+
+        let mut progress = Vec::new();
+        while let Ok(event) = events.try_recv() {
+            if let Iec104Event::FileProgress { received, total, .. } = event.event {
+                progress.push((received, total));
+            }
+        }
+        assert_eq!(progress, [(13, 17), (17, 17)]);
+    }
+
+    #[tokio::test]
+    async fn test_reset_process_and_end_of_init() {
+        use crate::types::InitCause;
+
+        let mut client = Iec104Client::new(ClientConfig::new("127.0.0.1:2404"));
+        let result = client.reset_process(1, ResetProcessQualifier::GeneralReset).await;
+        assert!(matches!(result, Err(Iec104Error::Protocol(_))));
+
+        let (mut client, mut server) = crate::testing::pair().await.unwrap();
+        let mut events = client.subscribe().unwrap();
+        tokio::spawn(async move {
+            let reset = server.recv_asdu().await.unwrap();
+            assert_eq!(reset.raw_data[3], 2);
+            server.respond(&reset, Cot::ActivationConfirm).await.unwrap();
+            let coi = Coi::new(InitCause::RemoteReset);
+            server.end_of_init(1, coi).await.unwrap();
+            while server.recv_apdu().await.is_ok() {}
+        });
+
+        client.start_dt().await.unwrap();
+        let (handle, _task) = client.spawn();
+
+        handle
+            .reset_process(1, ResetProcessQualifier::ResetEventBuffer)
+            .await
+            .unwrap()
+            .confirmed()
+            .await
+            .unwrap();
+
+        loop {
+            if let Iec104Event::EndOfInitialization { common_address, coi } =
+                events.recv().await.unwrap().event
+            {
+                assert_eq!(common_address, 1);
+                assert_eq!(coi.cause, InitCause::RemoteReset);
+                break;
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn test_interrogate_on_init() {
+        use crate::types::InitCause;
+
+        let config = ClientConfig::new("test").interrogate_on_init(true);
+        let (mut client, mut server) = crate::testing::pair_with(config).await.unwrap();
+        let server = tokio::spawn(async move {
+            server.recv_apdu().await.unwrap();
+            server.send_apdu(Apdu::u_frame(UFunction::StartDtCon)).await.unwrap();
+            let coi = Coi {
+                cause: InitCause::LocalPowerOn,
+                parameters_changed: true,
+            };
+            server.end_of_init(3, coi).await.unwrap();
+            server.recv_asdu().await.unwrap()
+        });
 
-        // SCO (Single Command Output):
-        // Bit 0: SCS (single command state) - 0=OFF, 1=ON
-        // Bit 7: S/E (select/execute) - 0=execute, 1=select
-        let sco = if value { 0x01 } else { 0x00 } | if select { 0x80 } else { 0x00 };
+        client.start_dt().await.unwrap();
 
-        asdu.objects.push(InformationObject {
-            ioa: Ioa::new(ioa),
-            data: Bytes::copy_from_slice(&[sco]),
+        let event = client.poll().await.unwrap();
+        assert!(matches!(
+            event,
+            Some(Iec104Event::EndOfInitialization {
+                common_address: 3,
+                coi: Coi {
+                    cause: InitCause::LocalPowerOn,
+                    parameters_changed: true
+                }
+            })
+        ));
+        let interrogation = server.await.unwrap();
+        assert_eq!(interrogation.header.type_id, TypeId::InterrogationCommand);
+        assert_eq!(interrogation.header.cot, Cot::Activation);
+        assert_eq!(interrogation.header.common_address, 3);
+    }
+
+    #[tokio::test]
+    async fn test_counter_interrogation_complete() {
+        use crate::types::{CounterFreeze, CounterGroup};
+
+        let qcc = Qcc::new(CounterGroup::Group(2), CounterFreeze::FreezeAndReset);
+        let (mut client, mut server) = crate::testing::pair().await.unwrap();
+        tokio::spawn(async move {
+            server.recv_apdu().await.unwrap();
+            server.send_apdu(Apdu::u_frame(UFunction::StartDtCon)).await.unwrap();
+            let termination = Asdu::counter_interrogation_command(4, qcc)
+                .mirror(Cot::ActivationTermination, false);
+            server.send_asdu(termination).await.unwrap();
+            while server.recv_apdu().await.is_ok() {}
         });
 
-        self.send_i_frame(asdu).await
-    }
+        client.start_dt().await.unwrap();
 
-    /// Send double command.
-    pub async fn double_command(
-        &mut self,
-        common_address: u16,
-        ioa: u32,
-        value: u8,
-        select: bool,
-    ) -> Result<()> {
-        if self.state != ConnectionState::Active {
-            return Err(Iec104Error::NotConnected);
+        match client.poll().await.unwrap() {
+            Some(Iec104Event::CounterInterrogationComplete {
+                common_address,
+                qcc: received,
+            }) => {
+                assert_eq!(common_address, 4);
+                assert_eq!(received, qcc);
+            }
+            other => panic!("unexpected event {:?}", other),
         }
+    }
 
-        let mut asdu = Asdu::new(AsduHeader::new(
-            TypeId::DoubleCommand,
-            1,
-            Cot::Activation,
-            common_address,
-        ));
+    #[tokio::test]
+    async fn test_lenient_parsing() {
+        let config = ClientConfig::new("test").lenient_parsing(true);
+        let (mut client, mut server) = crate::testing::pair_with(config).await.unwrap();
+        tokio::spawn(async move {
+            server.recv_apdu().await.unwrap();
+            server.send_apdu(Apdu::u_frame(UFunction::StartDtCon)).await.unwrap();
+            let mut vendor = Asdu::new(AsduHeader::new(TypeId::Other(200), 1, Cot::Spontaneous, 1));
+            vendor.raw_data = Bytes::from_static(&[0x01, 0x00, 0x00, 0xAA, 0xBB]);
+            server.send_asdu(vendor).await.unwrap();
+            let mut point = Asdu::new(AsduHeader::new(TypeId::SinglePoint, 1, Cot::Spontaneous, 1));
+            point.raw_data = Bytes::from_static(&[0x02, 0x00, 0x00, 0x01]);
+            server.send_asdu(point).await.unwrap();
+            while server.recv_apdu().await.is_ok() {}
+        });
+
+        client.start_dt().await.unwrap();
 
-        // DCO (Double Command Output):
-        // Bits 0-1: DCS (double command state) - 1=OFF, 2=ON
-        // Bit 7: S/E (select/execute) - 0=execute, 1=select
-        let dco = (value & 0x03) | if select { 0x80 } else { 0x00 };
+        match client.poll().await.unwrap() {
+            Some(Iec104Event::AsduReceived(asdu)) => {
+                assert_eq!(asdu.header.type_id, TypeId::Other(200));
+                assert_eq!(asdu.raw_data.as_ref(), &[0x01, 0x00, 0x00, 0xAA, 0xBB]);
+            }
+            other => panic!("unexpected event {:?}", other),
+        }
+        match client.poll().await.unwrap() {
+            Some(Iec104Event::DataUpdate(points)) => assert_eq!(points[0].ioa, 2),
+            other => panic!("unexpected event {:?}", other),
+        }
+    }
 
-        asdu.objects.push(InformationObject {
-            ioa: Ioa::new(ioa),
-            data: Bytes::copy_from_slice(&[dco]),
+    #[tokio::test]
+    async fn test_sequence_error_closes_connection() {
+        let (mut client, mut server) = crate::testing::pair().await.unwrap();
+        let mut events = client.subscribe().unwrap();
+        let station = tokio::spawn(async move {
+            server.recv_apdu().await.unwrap();
+            server.send_apdu(Apdu::u_frame(UFunction::StartDtCon)).await.unwrap();
+            // N(S) 1 where 0 is expected
+            let mut data = Asdu::new(AsduHeader::new(TypeId::SinglePoint, 1, Cot::Spontaneous, 1));
+            data.raw_data = Bytes::from_static(&[0x01, 0x00, 0x00, 0x01]);
+            server.send_apdu(Apdu::i_frame(1, 0, data)).await.unwrap();
+            while server.recv_apdu().await.is_ok() {}
         });
 
-        self.send_i_frame(asdu).await
+        client.start_dt().await.unwrap();
+        let result = tokio::time::timeout(Duration::from_secs(5), client.run()).await.unwrap();
+        assert!(matches!(
+            result,
+            Err(Iec104Error::SequenceMismatch { expected: 0, actual: 1 })
+        ));
+        assert_eq!(client.state(), ConnectionState::Disconnected);
+        tokio::time::timeout(Duration::from_secs(5), station).await.unwrap().unwrap();
+        let mut disconnected = false;
+        while let Ok(event) = events.try_recv() {
+            disconnected |= matches!(event.event, Iec104Event::Disconnected);
+        }
+        assert!(disconnected);
     }
 
-    /// Send setpoint command (short floating point).
-    pub async fn setpoint_float(
-        &mut self,
-        common_address: u16,
-        ioa: u32,
-        value: f32,
-        select: bool,
-    ) -> Result<()> {
-        if self.state != ConnectionState::Active {
-            return Err(Iec104Error::NotConnected);
-        }
+    #[tokio::test]
+    async fn test_t1_expiry_closes_connection() {
+        let config = ClientConfig::new("test").t1_timeout(Duration::from_millis(200));
+        let (mut client, mut server) = crate::testing::pair_with(config).await.unwrap();
+        let mut events = client.subscribe().unwrap();
+        tokio::spawn(async move {
+            server.recv_apdu().await.unwrap();
+            server.send_apdu(Apdu::u_frame(UFunction::StartDtCon)).await.unwrap();
+            // Never acknowledge anything
+            while server.recv_apdu().await.is_ok() {}
+        });
 
-        let mut asdu = Asdu::new(AsduHeader::new(
-            TypeId::SetpointFloat,
-            1,
-            Cot::Activation,
-            common_address,
-        ));
+        client.start_dt().await.unwrap();
+        let (handle, task) = client.spawn();
+
+        handle.general_interrogation(1).await.unwrap();
+        let result = tokio::time::timeout(Duration::from_secs(5), task).await.unwrap();
+        assert!(matches!(result.unwrap(), Err(Iec104Error::T1Timeout)));
+
+        let mut saw_error = false;
+        let mut lost = Vec::new();
+        while let Ok(event) = events.try_recv() {
+            match event.event {
+                Iec104Event::Error(_) => saw_error = true,
+                Iec104Event::Unacknowledged(asdus) => lost = asdus,
+                Iec104Event::Disconnected => {
+                    assert!(saw_error);
+                    assert_eq!(lost.len(), 1);
+                    assert_eq!(lost[0].header.type_id, TypeId::InterrogationCommand);
+                    return;
+                }
+                _ => {}
+            }
+        }
+        panic!("no disconnect after T1 expiry");
+    }
 
-        // Value (4 bytes) + QOS (1 byte)
-        let value_bytes = value.to_le_bytes();
-        let qos = if select { 0x80 } else { 0x00 };
-        let data = [value_bytes[0], value_bytes[1], value_bytes[2], value_bytes[3], qos];
+    #[tokio::test]
+    async fn test_broadcast_subscribers_share_stream() {
+        use tokio::net::TcpListener;
 
-        asdu.objects.push(InformationObject {
-            ioa: Ioa::new(ioa),
-            data: Bytes::copy_from_slice(&data),
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            let (_socket, _) = listener.accept().await.unwrap();
         });
 
-        self.send_i_frame(asdu).await
+        let mut client = Iec104Client::new(ClientConfig::new(addr.to_string()));
+        let mut events = client.subscribe().unwrap();
+        let mut logger = client.subscribe_broadcast();
+        let mut historian = client.subscribe_broadcast();
+        client.connect().await.unwrap();
+
+        let expected = events.recv().await.unwrap();
+        for receiver in [&mut logger, &mut historian] {
+            let event = receiver.recv().await.unwrap();
+            assert_eq!(event.seq, expected.seq);
+            assert!(matches!(event.event, Iec104Event::Connected));
+        }
     }
 
-    /// Process incoming frames.
-    ///
-    /// This should be called in a loop to handle incoming data.
-    pub async fn poll(&mut self) -> Result<Option<Iec104Event>> {
-        if self.state == ConnectionState::Disconnected {
-            return Err(Iec104Error::NotConnected);
-        }
+    #[tokio::test]
+    async fn test_link_stats() {
+        let (mut client, mut server) = crate::testing::pair().await.unwrap();
+        tokio::spawn(async move {
+            let gi = server.recv_asdu().await.unwrap();
+            server.respond(&gi, Cot::ActivationConfirm).await.unwrap();
+            while server.recv_apdu().await.is_ok() {}
+        });
 
-        // Check timeouts and determine actions needed
-        let elapsed_since_recv = self.last_recv_time.elapsed();
-        let need_test_frame = elapsed_since_recv > self.config.t3_timeout;
-        let need_s_frame = self.unconfirmed_recvs > 0 && elapsed_since_recv > self.config.t2_timeout;
+        client.start_dt().await.unwrap();
+        let completion = client.general_interrogation(1).await.unwrap();
+        assert_eq!(client.stats().unacknowledged_sends, 1);
+        while client.poll().await.unwrap().is_none() {}
+        completion.confirmed().await.unwrap();
+
+        let stats = client.stats();
+        assert_eq!((stats.u_frames_sent, stats.u_frames_received), (1, 1));
+        assert_eq!((stats.i_frames_sent, stats.i_frames_received), (1, 1));
+        assert_eq!(stats.s_frames_sent, 0);
+        // STARTDT (6) + C_IC_NA_1 (16) each way
+        assert_eq!((stats.bytes_sent, stats.bytes_received), (22, 22));
+        assert_eq!(stats.unacknowledged_sends, 0);
+        assert_eq!(stats.unacknowledged_receives, 1);
+        assert_eq!(stats.sequence_errors, 0);
+    }
 
-        // Check T3 timeout (need to send test frame)
-        if need_test_frame {
-            self.send_u_frame(UFunction::TestFrAct).await?;
+    #[tokio::test]
+    async fn test_stop_dt_waits_for_acknowledgments() {
+        fn data(ioa: u8) -> Asdu {
+            let mut asdu = Asdu::new(AsduHeader::new(TypeId::SinglePoint, 1, Cot::Spontaneous, 1));
+            asdu.raw_data = Bytes::copy_from_slice(&[ioa, 0, 0, 0x01]);
+            asdu
         }
 
-        // Check T2 timeout (need to send S-frame)
-        if need_s_frame {
-            self.send_s_frame().await?;
-        }
+        let (mut client, mut server) = crate::testing::pair().await.unwrap();
+        let mut events = client.subscribe().unwrap();
+        tokio::spawn(async move {
+            server.recv_apdu().await.unwrap();
+            server.send_apdu(Apdu::u_frame(UFunction::StartDtCon)).await.unwrap();
+            server.recv_apdu().await.unwrap();
+            // Leave the interrogation unacknowledged for a while
+            server.send_apdu(Apdu::i_frame(0, 0, data(1))).await.unwrap();
+            tokio::time::sleep(Duration::from_millis(50)).await;
+            server.send_apdu(Apdu::s_frame(1)).await.unwrap();
+
+            let ack = server.recv_apdu().await.unwrap();
+            assert_eq!(ack.apci, crate::types::Apci::SFrame { recv_seq: 1 });
+            let stop = server.recv_apdu().await.unwrap();
+            assert_eq!(stop, Apdu::u_frame(UFunction::StopDtAct));
+            // Pending data goes out before the confirmation, once acknowledged
+            server.send_apdu(Apdu::i_frame(1, 1, data(2))).await.unwrap();
+            let ack = server.recv_apdu().await.unwrap();
+            assert_eq!(ack.apci, crate::types::Apci::SFrame { recv_seq: 2 });
+            server.send_apdu(Apdu::u_frame(UFunction::StopDtCon)).await.unwrap();
+            while server.recv_apdu().await.is_ok() {}
+        });
 
-        // Try to receive a frame with a short timeout
-        let framed = self.framed.as_mut().ok_or(Iec104Error::NotConnected)?;
-        match timeout(Duration::from_millis(100), framed.next()).await {
-            Ok(Some(Ok(apdu))) => {
-                self.last_recv_time = Instant::now();
-                self.handle_apdu(apdu).await
+        client.start_dt().await.unwrap();
+        let _completion = client.general_interrogation(1).await.unwrap();
+        client.stop_dt().await.unwrap();
+        assert_eq!(client.state(), ConnectionState::Connected);
+        assert!(client.in_flight.is_empty());
+
+        let mut ioas = Vec::new();
+        while let Ok(event) = events.try_recv() {
+            match event.event {
+                Iec104Event::DataUpdate(points) => ioas.extend(points.iter().map(|p| p.ioa)),
+                Iec104Event::DataTransferStopped => break,
+                _ => {}
             }
-            Ok(Some(Err(e))) => Err(e),
-            Ok(None) => {
-                // Connection closed
-                self.state = ConnectionState::Disconnected;
-                Err(Iec104Error::Connection(std::borrow::Cow::Borrowed("Connection closed by peer")))
-            }
-            Err(_) => Ok(None), // Timeout, no data
         }
+        assert_eq!(ioas, vec![1, 2]);
     }
 
-    // Internal methods
+    #[tokio::test]
+    async fn test_unsolicited_u_frames() {
+        let (mut client, mut server) = crate::testing::pair().await.unwrap();
+        tokio::spawn(async move {
+            server.recv_apdu().await.unwrap();
+            // A stale confirmation ahead of the real one, then a duplicate
+            for function in [
+                UFunction::StopDtCon,
+                UFunction::StartDtCon,
+                UFunction::StartDtCon,
+                UFunction::StopDtCon,
+                UFunction::StartDtAct,
+            ] {
+                server.send_apdu(Apdu::u_frame(function)).await.unwrap();
+            }
+            while server.recv_apdu().await.is_ok() {}
+        });
 
-    async fn emit_event(&self, event: Iec104Event) {
-        let _ = self.event_tx.send(event).await;
-    }
+        client.start_dt().await.unwrap();
+        assert_eq!(client.state(), ConnectionState::Active);
 
-    async fn send_u_frame(&mut self, function: UFunction) -> Result<()> {
-        let framed = self.framed.as_mut().ok_or(Iec104Error::NotConnected)?;
-        let apdu = Apdu::u_frame(function);
-        framed.send(apdu).await?;
-        self.last_send_time = Instant::now();
-        Ok(())
+        let mut states = Vec::new();
+        while states.len() < 3 {
+            if let Some(Iec104Event::UnsolicitedUFrame(function)) = client.poll().await.unwrap() {
+                states.push((function, client.state()));
+            }
+        }
+        assert_eq!(
+            states,
+            vec![
+                (UFunction::StartDtCon, ConnectionState::Active),
+                (UFunction::StopDtCon, ConnectionState::Connected),
+                (UFunction::StartDtAct, ConnectionState::Connected),
+            ]
+        );
     }
 
-    async fn send_s_frame(&mut self) -> Result<()> {
-        let framed = self.framed.as_mut().ok_or(Iec104Error::NotConnected)?;
-        let apdu = Apdu::s_frame(self.recv_seq);
-        framed.send(apdu).await?;
-        self.last_send_time = Instant::now();
-        self.unconfirmed_recvs = 0;
-        Ok(())
-    }
+    #[tokio::test]
+    async fn test_frame_tap() {
+        let (mut client, mut server) = crate::testing::pair().await.unwrap();
+        let mut tap = client.tap();
+        tokio::spawn(async move {
+            server.recv_apdu().await.unwrap();
+            server.send_apdu(Apdu::u_frame(UFunction::StartDtCon)).await.unwrap();
+            while server.recv_apdu().await.is_ok() {}
+        });
 
-    async fn send_i_frame(&mut self, asdu: Asdu) -> Result<()> {
-        if self.unconfirmed_sends >= self.config.k {
-            return Err(Iec104Error::TooManyUnconfirmed(self.config.k));
-        }
+        client.start_dt().await.unwrap();
 
-        let framed = self.framed.as_mut().ok_or(Iec104Error::NotConnected)?;
-        let apdu = Apdu::i_frame(self.send_seq, self.recv_seq, asdu);
-        framed.send(apdu).await?;
+        let sent = tap.recv().await.unwrap();
+        assert_eq!(sent.direction, FrameDirection::Sent);
+        assert_eq!(sent.apdu, Apdu::u_frame(UFunction::StartDtAct));
+        assert_eq!(&sent.bytes[..], &[0x68, 0x04, 0x07, 0x00, 0x00, 0x00]);
 
-        self.send_seq = (self.send_seq + 1) & 0x7FFF;
-        self.unconfirmed_sends += 1;
-        self.last_send_time = Instant::now();
-        self.unconfirmed_recvs = 0; // Piggyback acknowledgment
-        Ok(())
+        let received = tap.recv().await.unwrap();
+        assert_eq!(received.direction, FrameDirection::Received);
+        assert_eq!(&received.bytes[..], &[0x68, 0x04, 0x0B, 0x00, 0x00, 0x00]);
     }
 
-    async fn recv_frame_timeout(&mut self, timeout_duration: Duration) -> Result<Apdu> {
-        let framed = self.framed.as_mut().ok_or(Iec104Error::NotConnected)?;
+    #[tokio::test]
+    async fn test_filtered_subscription() {
+        let (mut client, mut server) = crate::testing::pair().await.unwrap();
+        let filter = EventFilter::new().common_address(2).ioa_range(20..=29);
+        let mut station = client.subscribe_filtered(filter);
+        tokio::spawn(async move {
+            server.recv_apdu().await.unwrap();
+            server.send_apdu(Apdu::u_frame(UFunction::StartDtCon)).await.unwrap();
+            let points = [(1, 10u8), (2, 10), (2, 20)];
+            for (common_address, ioa) in points {
+                let mut asdu = Asdu::new(AsduHeader::new(
+                    TypeId::SinglePoint,
+                    1,
+                    Cot::Spontaneous,
+                    common_address,
+                ));
+                asdu.raw_data = Bytes::copy_from_slice(&[ioa, 0, 0, 0x01]);
+                server.send_asdu(asdu).await.unwrap();
+            }
+            while server.recv_apdu().await.is_ok() {}
+        });
 
-        match timeout(timeout_duration, framed.next()).await {
-            Ok(Some(Ok(apdu))) => {
-                self.last_recv_time = Instant::now();
-                Ok(apdu)
+        client.start_dt().await.unwrap();
+        let (_handle, _task) = client.spawn();
+
+        loop {
+            let event = station.recv().await.unwrap();
+            if let Iec104Event::DataUpdate(points) = event.event {
+                assert_eq!(points.len(), 1);
+                assert_eq!(points[0].ioa, 20);
+                break;
             }
-            Ok(Some(Err(e))) => Err(e),
-            Ok(None) => Err(Iec104Error::Connection(std::borrow::Cow::Borrowed("Connection closed"))),
-            Err(_) => Err(Iec104Error::T1Timeout),
         }
     }
 
-    async fn handle_apdu(&mut self, apdu: Apdu) -> Result<Option<Iec104Event>> {
-        match &apdu.apci {
-            crate::types::Apci::IFrame { send_seq, recv_seq } => {
-                // Update acknowledgment
-                self.acknowledge_up_to(*recv_seq);
+    #[tokio::test]
+    async fn test_point_cache_keeps_latest_value() {
+        let config = ClientConfig::new("test").point_cache(true);
+        let (mut client, mut server) = crate::testing::pair_with(config).await.unwrap();
+        tokio::spawn(async move {
+            server.recv_apdu().await.unwrap();
+            server.send_apdu(Apdu::u_frame(UFunction::StartDtCon)).await.unwrap();
+            for siq in [0x01, 0x00] {
+                let mut asdu =
+                    Asdu::new(AsduHeader::new(TypeId::SinglePoint, 1, Cot::Spontaneous, 1));
+                asdu.raw_data = Bytes::copy_from_slice(&[7, 0, 0, siq]);
+                server.send_asdu(asdu).await.unwrap();
+            }
+            while server.recv_apdu().await.is_ok() {}
+        });
 
-                // Validate sequence number
-                if *send_seq != self.recv_seq {
-                    return Err(Iec104Error::SequenceMismatch {
-                        expected: self.recv_seq,
-                        actual: *send_seq,
-                    });
-                }
+        client.start_dt().await.unwrap();
+        assert!(client.value(1, 7).is_none());
 
-                self.recv_seq = (self.recv_seq + 1) & 0x7FFF;
-                self.unconfirmed_recvs += 1;
+        let mut updates = 0;
+        while updates < 2 {
+            if let Some(Iec104Event::DataUpdate(_)) = client.poll().await.unwrap() {
+                updates += 1;
+            }
+        }
+        let point = client.value(1, 7).unwrap();
+        assert_eq!(point.value, crate::types::DataValue::Single(false));
+        assert!(client.value(2, 7).is_none());
+        assert_eq!(client.values().len(), 1);
+    }
 
-                // Send S-frame if W threshold reached
-                if self.unconfirmed_recvs >= self.config.w {
-                    self.send_s_frame().await?;
-                }
+    #[tokio::test]
+    async fn test_deadband_suppresses_updates() {
+        let config = ClientConfig::new("test")
+            .point_cache(true)
+            .point_deadband(5, crate::filter::Deadband::Absolute(5.0));
+        let (mut client, mut server) = crate::testing::pair_with(config).await.unwrap();
+        tokio::spawn(async move {
+            server.recv_apdu().await.unwrap();
+            server.send_apdu(Apdu::u_frame(UFunction::StartDtCon)).await.unwrap();
+            // Scaled values 100, 102 (suppressed), 110
+            for value in [100i16, 102, 110] {
+                let [lo, hi] = value.to_le_bytes();
+                let mut asdu =
+                    Asdu::new(AsduHeader::new(TypeId::MeasuredScaled, 1, Cot::Spontaneous, 1));
+                asdu.raw_data = Bytes::copy_from_slice(&[5, 0, 0, lo, hi, 0x00]);
+                server.send_asdu(asdu).await.unwrap();
+            }
+            while server.recv_apdu().await.is_ok() {}
+        });
 
-                // Process ASDU
-                if let Some(asdu) = apdu.asdu {
-                    return Ok(Some(self.process_asdu(asdu)));
-                }
+        client.start_dt().await.unwrap();
+
+        let mut values = Vec::new();
+        while values.len() < 2 {
+            if let Some(Iec104Event::DataUpdate(points)) = client.poll().await.unwrap() {
+                values.push(points[0].value.clone());
             }
+        }
+        use crate::types::DataValue;
+        assert_eq!(values, vec![DataValue::Scaled(100), DataValue::Scaled(110)]);
+        assert_eq!(client.stats().i_frames_received, 3);
+    }
 
-            crate::types::Apci::SFrame { recv_seq } => {
-                self.acknowledge_up_to(*recv_seq);
+    #[tokio::test]
+    async fn test_partial_timestamps_completed() {
+        let config = ClientConfig::new("test").complete_partial_timestamps(true);
+        let (mut client, mut server) = crate::testing::pair_with(config).await.unwrap();
+        tokio::spawn(async move {
+            server.recv_apdu().await.unwrap();
+            server.send_apdu(Apdu::u_frame(UFunction::StartDtCon)).await.unwrap();
+            // M_SP_TA_1 before any full time, M_SP_TB_1 at 23:58, M_SP_TA_1 at xx:01
+            let frames: [(TypeId, &[u8]); 3] = [
+                (TypeId::SinglePointTime24, &[7, 0, 0, 0x01, 0x00, 0x00, 0x01]),
+                (
+                    TypeId::SinglePointTime56,
+                    &[7, 0, 0, 0x00, 0x00, 0x00, 58, 23, 0x9D, 2, 24],
+                ),
+                (TypeId::SinglePointTime24, &[7, 0, 0, 0x01, 0x10, 0x27, 0x01]),
+            ];
+            for (type_id, data) in frames {
+                let mut asdu = Asdu::new(AsduHeader::new(type_id, 1, Cot::Spontaneous, 1));
+                asdu.raw_data = Bytes::copy_from_slice(data);
+                server.send_asdu(asdu).await.unwrap();
             }
+            while server.recv_apdu().await.is_ok() {}
+        });
 
-            crate::types::Apci::UFrame { function } => {
-                match function {
-                    UFunction::TestFrAct => {
-                        // Respond with TESTFR con
-                        self.send_u_frame(UFunction::TestFrCon).await?;
-                    }
-                    UFunction::TestFrCon => {
-                        // Test frame acknowledged
-                    }
-                    _ => {
-                        // Other U-frames handled elsewhere
-                    }
-                }
+        client.start_dt().await.unwrap();
+
+        let mut timestamps = Vec::new();
+        while timestamps.len() < 3 {
+            if let Some(Iec104Event::DataUpdate(points)) = client.poll().await.unwrap() {
+                timestamps.push(points[0].timestamp.unwrap());
             }
         }
-
-        Ok(None)
+        assert!(matches!(timestamps[0], Timestamp::Partial(_)));
+        let completed = timestamps[2].full().unwrap();
+        assert_eq!((completed.month, completed.day), (3, 1));
+        assert_eq!((completed.hours, completed.minutes), (0, 1));
+        assert_eq!(completed.milliseconds, 10_000);
     }
 
-    fn acknowledge_up_to(&mut self, recv_seq: u16) {
-        // IEC 104 sequence numbers are 15-bit (0..32767).
-        const SEQ_MASK: u16 = 0x7FFF;
+    #[tokio::test]
+    async fn test_shutdown_waits_for_acknowledgment() {
+        use crate::types::Apci;
+
+        let (mut client, mut server) = crate::testing::pair().await.unwrap();
+        let mut events = client.subscribe().unwrap();
+        let server = tokio::spawn(async move {
+            server.recv_apdu().await.unwrap();
+            server.send_apdu(Apdu::u_frame(UFunction::StartDtCon)).await.unwrap();
+
+            // Acknowledge the interrogation late
+            server.recv_apdu().await.unwrap().asdu.unwrap();
+            tokio::time::sleep(Duration::from_millis(200)).await;
+            server.send_apdu(Apdu::s_frame(1)).await.unwrap();
+
+            let stop = server.recv_apdu().await.unwrap();
+            assert!(matches!(
+                stop.apci,
+                Apci::UFrame {
+                    function: UFunction::StopDtAct
+                }
+            ));
+            server.send_apdu(Apdu::u_frame(UFunction::StopDtCon)).await.unwrap();
+            // Then the client closes its side
+            assert!(server.recv_apdu().await.is_err());
+        });
 
-        let oldest_unacked = self.send_seq.wrapping_sub(self.unconfirmed_sends) & SEQ_MASK;
-        let acked = recv_seq.wrapping_sub(oldest_unacked) & SEQ_MASK;
+        client.start_dt().await.unwrap();
+        let (handle, task) = client.spawn();
 
-        if acked <= self.unconfirmed_sends {
-            self.unconfirmed_sends -= acked;
+        let _ = handle.general_interrogation(1).await.unwrap();
+        handle.shutdown(Duration::from_secs(2)).await.unwrap();
+        task.await.unwrap().unwrap();
+        server.await.unwrap();
+
+        while let Ok(event) = events.try_recv() {
+            assert!(!matches!(event.event, Iec104Event::Unacknowledged(_)));
         }
     }
 
-    /// Process received ASDU and convert to appropriate event.
-    fn process_asdu(&self, asdu: Asdu) -> Iec104Event {
-        use crate::types::TypeId;
+    #[tokio::test]
+    async fn test_command_retry_after_missing_confirmation() {
+        let policy = RetryPolicy::new(Duration::from_millis(200), 1);
+        let config = ClientConfig::new("test").command_retry(policy);
+        let (mut client, mut server) = crate::testing::pair_with(config).await.unwrap();
+        tokio::spawn(async move {
+            server.recv_apdu().await.unwrap();
+            server.send_apdu(Apdu::u_frame(UFunction::StartDtCon)).await.unwrap();
+
+            // Acknowledge the first activation on the link, but never confirm it
+            server.recv_apdu().await.unwrap().asdu.unwrap();
+            server.send_apdu(Apdu::s_frame(1)).await.unwrap();
+            let retry = server.recv_apdu().await.unwrap().asdu.unwrap();
+            let confirm = retry.mirror(Cot::ActivationConfirm, false);
+            server.send_apdu(Apdu::i_frame(0, 2, confirm)).await.unwrap();
+            while server.recv_apdu().await.is_ok() {}
+        });
 
-        // Check for special COT values
-        match asdu.header.cot {
-            Cot::ActivationConfirm | Cot::DeactivationConfirm => {
-                // Command confirmation - extract IOA from first object if available
-                if !asdu.raw_data.is_empty() && asdu.raw_data.len() >= 3 {
-                    let ioa = asdu.raw_data[0] as u32
-                        | ((asdu.raw_data[1] as u32) << 8)
-                        | ((asdu.raw_data[2] as u32) << 16);
-                    return Iec104Event::CommandConfirm {
-                        ioa,
-                        success: !asdu.header.negative,
-                    };
-                }
-            }
-            Cot::ActivationTermination => {
-                // Interrogation complete
-                if asdu.header.type_id == TypeId::InterrogationCommand {
-                    return Iec104Event::InterrogationComplete {
-                        common_address: asdu.header.common_address,
-                    };
-                }
+        client.start_dt().await.unwrap();
+        let (handle, _task) = client.spawn();
+
+        let completion = handle
+            .single_command(1, 100, true, CommandQualifier::EXECUTE)
+            .await
+            .unwrap();
+        completion.confirmed().await.unwrap();
+        assert_eq!(handle.stats().await.unwrap().i_frames_sent, 2);
+    }
+
+    #[tokio::test]
+    async fn test_command_refused_by_full_window_is_not_retried() {
+        let mut config = ClientConfig::new("test")
+            .command_retry(RetryPolicy::new(Duration::from_millis(300), 1));
+        config.k = 1;
+        let (mut client, mut server) = crate::testing::pair_with(config).await.unwrap();
+        let (confirm_tx, confirm_rx) = tokio::sync::oneshot::channel::<()>();
+        let station = tokio::spawn(async move {
+            // Hold the window closed until the client has been refused
+            let first = server.recv_asdu().await.unwrap();
+            confirm_rx.await.unwrap();
+            server.respond(&first, Cot::ActivationConfirm).await.unwrap();
+            let mut ioas = vec![first.raw_data[0]];
+            while let Ok(asdu) = server.recv_asdu().await {
+                ioas.push(asdu.raw_data[0]);
             }
-            _ => {}
-        }
+            ioas
+        });
 
-        // Check for negative confirmation (error response)
-        if asdu.header.negative {
-            return Iec104Event::Error(format!(
-                "Negative confirmation for {} (COT={})",
-                asdu.header.type_id, asdu.header.cot
+        client.start_dt().await.unwrap();
+        let _completion = client
+            .single_command(1, 10, true, CommandQualifier::EXECUTE)
+            .await
+            .unwrap();
+        let result = client.single_command(1, 20, true, CommandQualifier::EXECUTE).await;
+        assert!(matches!(result, Err(Iec104Error::TooManyUnconfirmed(1))));
+        confirm_tx.send(()).unwrap();
+
+        // Well past the retry timeout of the refused command
+        let idle = Duration::from_millis(800);
+        let _ = tokio::time::timeout(idle, async { while client.poll().await.is_ok() {} }).await;
+        drop(client);
+        assert_eq!(station.await.unwrap(), vec![10]);
+    }
+
+    #[tokio::test]
+    async fn test_watch_state_transitions() {
+        use futures::SinkExt;
+        use tokio::net::TcpListener;
+
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let (hang_up, hung_up) = tokio::sync::oneshot::channel::<()>();
+        tokio::spawn(async move {
+            let (socket, _) = listener.accept().await.unwrap();
+            let mut server = Framed::new(socket, Iec104Codec::new());
+            server.next().await.unwrap().unwrap();
+            server.send(Apdu::u_frame(UFunction::StartDtCon)).await.unwrap();
+            let _ = hung_up.await;
+        });
+
+        let mut client = Iec104Client::new(ClientConfig::new(addr.to_string()));
+        let mut first = client.watch_state();
+        let second = client.watch_state();
+        assert_eq!(*first.borrow(), ConnectionState::Disconnected);
+
+        client.connect().await.unwrap();
+        assert_eq!(*first.borrow_and_update(), ConnectionState::Connected);
+        client.start_dt().await.unwrap();
+        assert!(first.has_changed().unwrap());
+        assert_eq!(*second.borrow(), ConnectionState::Active);
+
+        let (handle, task) = client.spawn();
+        let mut third = handle.watch_state().await.unwrap();
+        hang_up.send(()).unwrap();
+        third
+            .wait_for(|state| *state == ConnectionState::Disconnected)
+            .await
+            .unwrap();
+        let _ = task.await.unwrap();
+        assert_eq!(*first.borrow(), ConnectionState::Disconnected);
+    }
+
+    #[tokio::test]
+    async fn test_run_drives_timers_until_close() {
+        let config = ClientConfig::new("test").t3_timeout(Duration::from_millis(200));
+        let (mut client, mut server) = crate::testing::pair_with(config).await.unwrap();
+        tokio::spawn(async move {
+            server.recv_apdu().await.unwrap();
+            server.send_apdu(Apdu::u_frame(UFunction::StartDtCon)).await.unwrap();
+
+            // Stay silent until the client tests the link, then hang up
+            let apdu = server.recv_apdu().await.unwrap();
+            assert!(matches!(
+                apdu.apci,
+                crate::types::Apci::UFrame {
+                    function: UFunction::TestFrAct
+                }
             ));
-        }
+            server.send_apdu(Apdu::u_frame(UFunction::TestFrCon)).await.unwrap();
+        });
 
-        // Try to parse data points
-        match crate::parser::parse_asdu(&asdu) {
-            Ok(points) if !points.is_empty() => Iec104Event::DataUpdate(points),
-            Ok(_) => {
-                // No data points (command types, etc.) - return raw ASDU
-                Iec104Event::AsduReceived(asdu)
+        client.start_dt().await.unwrap();
+
+        let result = tokio::time::timeout(Duration::from_secs(5), client.run()).await.unwrap();
+        assert!(matches!(result, Err(Iec104Error::Connection(_))));
+        assert_eq!(client.state(), ConnectionState::Disconnected);
+    }
+
+    #[tokio::test]
+    async fn test_unanswered_test_frame_closes_connection() {
+        let config = ClientConfig::new("test")
+            .t1_timeout(Duration::from_millis(200))
+            .t3_timeout(Duration::from_millis(100));
+        let (mut client, mut server) = crate::testing::pair_with(config).await.unwrap();
+        tokio::spawn(async move {
+            server.recv_apdu().await.unwrap();
+            server.send_apdu(Apdu::u_frame(UFunction::StartDtCon)).await.unwrap();
+            // Ignore TESTFR act
+            while server.recv_apdu().await.is_ok() {}
+        });
+
+        client.start_dt().await.unwrap();
+
+        let result = tokio::time::timeout(Duration::from_secs(5), client.run()).await.unwrap();
+        assert!(matches!(result, Err(Iec104Error::T3Timeout)));
+        assert_eq!(client.state(), ConnectionState::Disconnected);
+        let stats = client.stats();
+        assert_eq!(stats.test_frames_sent, 1);
+        assert_eq!(stats.test_frame_timeouts, 1);
+    }
+
+    #[tokio::test]
+    async fn test_t2_acknowledges_steady_stream() {
+        use crate::types::InitCause;
+
+        let config = ClientConfig::new("test").t2_timeout(Duration::from_millis(300));
+        let (mut client, mut server) = crate::testing::pair_with(config).await.unwrap();
+        let server = tokio::spawn(async move {
+            server.recv_apdu().await.unwrap();
+            server.send_apdu(Apdu::u_frame(UFunction::StartDtCon)).await.unwrap();
+
+            // A frame every 100 ms never leaves the link idle for T2
+            let mut tick = tokio::time::interval(Duration::from_millis(100));
+            for sent in 0..20u16 {
+                tokio::select! {
+                    apdu = server.recv_apdu() => {
+                        if let crate::types::Apci::SFrame { recv_seq } = apdu.unwrap().apci {
+                            return (sent, recv_seq);
+                        }
+                    }
+                    _ = tick.tick() => {
+                        let coi = Coi::new(InitCause::LocalPowerOn);
+                        server.end_of_init(1, coi).await.unwrap();
+                    }
+                }
             }
-            Err(e) => {
-                // Parse error - return as error event
-                Iec104Event::Error(format!("ASDU parse error: {}", e))
+            panic!("no S-frame within T2");
+        });
+
+        client.start_dt().await.unwrap();
+        let (_handle, _task) = client.spawn();
+
+        let (sent, acked) = server.await.unwrap();
+        assert!(acked > 0);
+        assert!(sent < 8, "S-frame only after {sent} frames");
+    }
+
+    #[tokio::test]
+    async fn test_send_waits_for_k_window() {
+        async fn serve(mut server: crate::testing::TestServer) {
+            // Acknowledge the first two I-frames only after a while
+            let mut received = 0;
+            while server.recv_asdu().await.is_ok() {
+                received += 1;
+                if received == 2 {
+                    tokio::time::sleep(Duration::from_millis(200)).await;
+                    server.acknowledge().await.unwrap();
+                }
             }
         }
-    }
-}
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+        let mut config = ClientConfig::new("test");
+        config.k = 2;
+        let (mut client, server) = crate::testing::pair_with(config.clone()).await.unwrap();
+        tokio::spawn(serve(server));
+        client.start_dt().await.unwrap();
+        let _ = client.general_interrogation(1).await.unwrap();
+        let _ = client.general_interrogation(2).await.unwrap();
+        let result = client.general_interrogation(3).await;
+        assert!(matches!(result, Err(Iec104Error::TooManyUnconfirmed(2))));
+        client.disconnect().await.unwrap();
+
+        let config = config.send_window_timeout(Duration::from_secs(2));
+        let (mut client, server) = crate::testing::pair_with(config).await.unwrap();
+        tokio::spawn(serve(server));
+        client.start_dt().await.unwrap();
+        let _ = client.general_interrogation(1).await.unwrap();
+        let _ = client.general_interrogation(2).await.unwrap();
+        let _ = client.general_interrogation(3).await.unwrap();
+        assert_eq!(client.unconfirmed_sends, 1);
+    }
 
-    #[test]
-    fn test_client_config() {
-        let config = ClientConfig::new("192.168.1.100:2404")
-            .connect_timeout(Duration::from_secs(5))
-            .t1_timeout(Duration::from_secs(10));
+    #[tokio::test]
+    async fn test_resend_unacknowledged_after_reconnect() {
+        use futures::SinkExt;
+        use tokio::net::TcpListener;
+
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let (resent_tx, resent_rx) = tokio::sync::oneshot::channel();
+        tokio::spawn(async move {
+            // First connection drops the interrogation without acknowledging it
+            let (socket, _) = listener.accept().await.unwrap();
+            let mut server = Framed::new(socket, Iec104Codec::new());
+            server.next().await.unwrap().unwrap();
+            server.send(Apdu::u_frame(UFunction::StartDtCon)).await.unwrap();
+            while server.next().await.unwrap().unwrap().asdu.is_none() {}
+            drop(server);
+
+            let (socket, _) = listener.accept().await.unwrap();
+            let mut server = Framed::new(socket, Iec104Codec::new());
+            server.next().await.unwrap().unwrap();
+            server.send(Apdu::u_frame(UFunction::StartDtCon)).await.unwrap();
+            let asdu = loop {
+                if let Some(asdu) = server.next().await.unwrap().unwrap().asdu {
+                    break asdu;
+                }
+            };
+            resent_tx.send(asdu.header.type_id).unwrap();
+            while server.next().await.is_some() {}
+        });
 
-        assert_eq!(config.address, "192.168.1.100:2404");
-        assert_eq!(config.connect_timeout, Duration::from_secs(5));
-        assert_eq!(config.t1_timeout, Duration::from_secs(10));
-        assert_eq!(config.t2_timeout, Duration::from_secs(DEFAULT_T2_TIMEOUT));
+        let mut client = Iec104Client::new(ClientConfig::new(addr.to_string()));
+        client.connect().await.unwrap();
+        client.start_dt().await.unwrap();
+        let _ = client.general_interrogation(1).await.unwrap();
+        while client.poll().await.is_ok() {}
+        assert_eq!(client.unacknowledged().len(), 1);
+
+        client.connect().await.unwrap();
+        client.start_dt().await.unwrap();
+        assert_eq!(client.resend_unacknowledged().await.unwrap(), 1);
+        assert!(client.unacknowledged().is_empty());
+        assert_eq!(resent_rx.await.unwrap(), TypeId::InterrogationCommand);
     }
 
-    #[test]
-    fn test_client_initial_state() {
-        let config = ClientConfig::new("localhost:2404");
-        let client = Iec104Client::new(config);
+    #[tokio::test]
+    async fn test_test_commands_verify_mirror() {
+        let (mut client, mut server) = crate::testing::pair().await.unwrap();
+        tokio::spawn(async move {
+            while let Ok(asdu) = server.recv_asdu().await {
+                let mut reply = asdu.mirror(Cot::ActivationConfirm, false);
+                // Corrupt the mirrored time tag of the second time-tagged test
+                if asdu.raw_data[3] == 1 && asdu.header.type_id == TypeId::TestCommandTime56 {
+                    let mut data = reply.raw_data.to_vec();
+                    data[5] ^= 0xFF;
+                    reply.raw_data = Bytes::from(data);
+                }
+                server.send_asdu(reply).await.unwrap();
+            }
+        });
 
-        assert_eq!(client.state(), ConnectionState::Disconnected);
+        client.start_dt().await.unwrap();
+
+        let timeout = Duration::from_secs(5);
+        client.test_command(1, timeout).await.unwrap();
+
+        let time = Cp56Time2a::from_bytes(&[0x10, 0x27, 30, 12, 0x6F, 6, 24]).unwrap();
+        client.test_command_time(1, time, timeout).await.unwrap();
+        let result = client.test_command_time(1, time, timeout).await;
+        assert!(matches!(result, Err(Iec104Error::Protocol(_))));
     }
 }
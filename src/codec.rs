@@ -1,12 +1,13 @@
 //! IEC 60870-5-104 codec for tokio.
 //!
 //! This module provides a codec implementation for encoding and decoding
-//! IEC 104 APDUs using the tokio-util codec framework.
+//! IEC 104 APDUs using the tokio-util codec framework, as well as the
+//! framework-independent [`decode_apdu`] and [`encode_apdu`] functions.
 
-use bytes::{Buf, BytesMut};
+use bytes::{Buf, Bytes, BytesMut};
 use tokio_util::codec::{Decoder, Encoder};
 
-use crate::error::Iec104Error;
+use crate::error::{Iec104Error, Result};
 use crate::types::{Apci, Asdu, MAX_APDU_LENGTH, MIN_APDU_LENGTH, START_BYTE};
 
 /// An IEC 104 APDU (Application Protocol Data Unit).
@@ -75,6 +76,91 @@ impl std::fmt::Display for Apdu {
     }
 }
 
+/// Decode a single APDU from the start of `src`.
+///
+/// Returns the APDU and the number of bytes consumed. `src` must begin with
+/// the start byte; if it does not yet hold a complete frame,
+/// [`Iec104Error::Incomplete`] reports how many more bytes are needed.
+///
+/// This is independent of tokio-util and can be used to embed the protocol
+/// in other I/O frameworks.
+pub fn decode_apdu(src: &[u8]) -> Result<(Apdu, usize)> {
+    if src.len() < 2 {
+        return Err(Iec104Error::Incomplete(2 - src.len()));
+    }
+    if src[0] != START_BYTE {
+        return Err(Iec104Error::invalid_frame_static("Missing start byte"));
+    }
+
+    let length = src[1] as usize;
+    if !(MIN_APDU_LENGTH..=MAX_APDU_LENGTH).contains(&length) {
+        return Err(Iec104Error::invalid_frame(format!("Invalid APDU length {}", length)));
+    }
+
+    let total_length = 2 + length;
+    if src.len() < total_length {
+        return Err(Iec104Error::Incomplete(total_length - src.len()));
+    }
+
+    let apdu = parse_frame(Bytes::copy_from_slice(&src[..total_length]), false)?;
+    Ok((apdu, total_length))
+}
+
+/// Encode an APDU into a new buffer, including start byte and length.
+///
+/// This is independent of tokio-util and can be used to embed the protocol
+/// in other I/O frameworks.
+pub fn encode_apdu(apdu: &Apdu) -> Result<BytesMut> {
+    let mut dst = BytesMut::new();
+    encode_apdu_to(apdu, &mut dst)?;
+    Ok(dst)
+}
+
+/// Parse a complete frame (start byte, length, control field and ASDU).
+fn parse_frame(frame: Bytes, lenient: bool) -> Result<Apdu> {
+    // Frame structure: [0x68] [length] [control1] [control2] [control3] [control4] [ASDU...]
+    let control = &frame[2..6];
+    let apci = Apci::parse(control)?;
+
+    let asdu = if apci.is_i_frame() && frame.len() > 6 {
+        let asdu = frame.slice(6..);
+        Some(if lenient {
+            Asdu::parse_bytes_lenient(asdu)?
+        } else {
+            Asdu::parse_bytes(asdu)?
+        })
+    } else {
+        None
+    };
+
+    Ok(Apdu { apci, asdu })
+}
+
+/// Encode an APDU, appending it to `dst`.
+fn encode_apdu_to(apdu: &Apdu, dst: &mut BytesMut) -> Result<()> {
+    // Calculate ASDU length without encoding yet
+    let asdu_len = apdu.asdu.as_ref().map(|a| a.encoded_len()).unwrap_or(0);
+
+    // Validate total length
+    if asdu_len > MAX_APDU_LENGTH - 4 {
+        return Err(Iec104Error::Codec(std::borrow::Cow::Borrowed("ASDU too large")));
+    }
+
+    // Reserve capacity for the entire frame
+    dst.reserve(6 + asdu_len);
+
+    // Write header
+    let header = apdu.apci.encode_header(asdu_len);
+    dst.extend_from_slice(&header);
+
+    // Write ASDU directly to dst if present (zero-copy)
+    if let Some(asdu) = &apdu.asdu {
+        asdu.encode_to(dst);
+    }
+
+    Ok(())
+}
+
 /// IEC 60870-5-104 codec.
 ///
 /// This codec handles framing and parsing of IEC 104 APDUs.
@@ -100,6 +186,8 @@ impl std::fmt::Display for Apdu {
 pub struct Iec104Codec {
     // State for handling partial frames
     state: DecodeState,
+    // Keep ASDUs of unknown type instead of failing
+    lenient: bool,
 }
 
 #[derive(Debug, Clone, Default)]
@@ -118,6 +206,18 @@ impl Iec104Codec {
     pub fn new() -> Self {
         Self::default()
     }
+
+    /// Decode ASDUs of unknown type instead of failing.
+    ///
+    /// Their type identification is kept as [`TypeId::Other`] and the
+    /// information objects are left in the raw payload, so one vendor type
+    /// does not abort the stream.
+    ///
+    /// [`TypeId::Other`]: crate::types::TypeId::Other
+    pub fn lenient(mut self, lenient: bool) -> Self {
+        self.lenient = lenient;
+        self
+    }
 }
 
 impl Decoder for Iec104Codec {
@@ -139,6 +239,11 @@ impl Decoder for Iec104Codec {
                     if src[0] != START_BYTE {
                         // Skip bytes until we find the start byte (fast-path: advance once)
                         let start_pos = src.iter().position(|&b| b == START_BYTE);
+                        #[cfg(feature = "tracing-support")]
+                        tracing::debug!(
+                            skipped = start_pos.unwrap_or(src.len()),
+                            "bytes before start byte discarded"
+                        );
                         match start_pos {
                             Some(pos) => src.advance(pos),
                             None => {
@@ -160,6 +265,10 @@ impl Decoder for Iec104Codec {
                     let length = src[1] as usize;
 
                     // Validate length
+                    #[cfg(feature = "tracing-support")]
+                    if !(MIN_APDU_LENGTH..=MAX_APDU_LENGTH).contains(&length) {
+                        tracing::debug!(length, "invalid APDU length, resynchronizing");
+                    }
                     if length < MIN_APDU_LENGTH {
                         // Invalid length, skip start byte and restart
                         src.advance(1);
@@ -188,18 +297,14 @@ impl Decoder for Iec104Codec {
                     let frame = src.split_to(total_length).freeze();
                     self.state = DecodeState::WaitingForStart;
 
-                    // Parse the frame
-                    // Frame structure: [0x68] [length] [control1] [control2] [control3] [control4] [ASDU...]
-                    let control = &frame[2..6];
-                    let apci = Apci::parse(control)?;
-
-                    let asdu = if apci.is_i_frame() && frame.len() > 6 {
-                        Some(Asdu::parse_bytes(frame.slice(6..))?)
-                    } else {
-                        None
-                    };
-
-                    return Ok(Some(Apdu { apci, asdu }));
+                    #[cfg(feature = "tracing-support")]
+                    let raw = frame.clone();
+                    let result = parse_frame(frame, self.lenient);
+                    #[cfg(feature = "tracing-support")]
+                    if let Err(error) = &result {
+                        tracing::warn!(%error, frame = ?raw.as_ref(), "frame not decoded");
+                    }
+                    return result.map(Some);
                 }
             }
         }
@@ -210,27 +315,7 @@ impl Encoder<Apdu> for Iec104Codec {
     type Error = Iec104Error;
 
     fn encode(&mut self, item: Apdu, dst: &mut BytesMut) -> std::result::Result<(), Self::Error> {
-        // Calculate ASDU length without encoding yet
-        let asdu_len = item.asdu.as_ref().map(|a| a.encoded_len()).unwrap_or(0);
-
-        // Validate total length
-        if asdu_len > MAX_APDU_LENGTH - 4 {
-            return Err(Iec104Error::Codec(std::borrow::Cow::Borrowed("ASDU too large")));
-        }
-
-        // Reserve capacity for the entire frame
-        dst.reserve(6 + asdu_len);
-
-        // Write header
-        let header = item.apci.encode_header(asdu_len);
-        dst.extend_from_slice(&header);
-
-        // Write ASDU directly to dst if present (zero-copy)
-        if let Some(asdu) = &item.asdu {
-            asdu.encode_to(dst);
-        }
-
-        Ok(())
+        encode_apdu_to(&item, dst)
     }
 }
 
@@ -239,6 +324,30 @@ mod tests {
     use super::*;
     use crate::types::{AsduHeader, Cot, TypeId, UFunction};
 
+    #[test]
+    fn test_decode_lenient_unknown_type() {
+        // I-frame with type 60 (unassigned), COT=3, CA=1, one object
+        let frame = [
+            0x68, 0x0E, 0x00, 0x00, 0x00, 0x00, 0x3C, 0x01, 0x03, 0x00, 0x01, 0x00, 0x0A, 0x00,
+            0x00, 0xAB,
+        ];
+
+        let mut strict = Iec104Codec::new();
+        let mut buf = BytesMut::from(&frame[..]);
+        assert!(matches!(strict.decode(&mut buf), Err(Iec104Error::UnknownTypeId(60))));
+
+        let mut lenient = Iec104Codec::new().lenient(true);
+        let mut buf = BytesMut::from(&frame[..]);
+        let asdu = lenient.decode(&mut buf).unwrap().unwrap().asdu.unwrap();
+        assert_eq!(asdu.header.type_id, TypeId::Other(60));
+        assert_eq!(asdu.header.common_address, 1);
+        assert_eq!(asdu.raw_data.as_ref(), &[0x0A, 0x00, 0x00, 0xAB]);
+
+        // Re-encodes byte for byte
+        let apdu = Apdu::i_frame(0, 0, asdu);
+        assert_eq!(encode_apdu(&apdu).unwrap().as_ref(), &frame[..]);
+    }
+
     #[test]
     fn test_decode_u_frame() {
         let mut codec = Iec104Codec::new();
@@ -484,19 +593,23 @@ mod tests {
         let mut codec = Iec104Codec::new();
         let mut buf = BytesMut::new();
 
-        let asdu = Asdu::new(AsduHeader::new(
+        let mut asdu = Asdu::new(AsduHeader::new(
             TypeId::MeasuredFloat,
             2,
             Cot::Spontaneous,
             100,
         ));
+        asdu.raw_data = Bytes::from_static(&[
+            0x01, 0x00, 0x00, 0x00, 0x00, 0x80, 0x3F, 0x00, // IOA 1, 1.0, QDS
+            0x02, 0x00, 0x00, 0x00, 0x00, 0x00, 0x40, 0x00, // IOA 2, 2.0, QDS
+        ]);
         let apdu = Apdu::i_frame(50, 25, asdu);
         codec.encode(apdu, &mut buf).unwrap();
 
         // Verify structure
         assert_eq!(buf[0], START_BYTE);
-        // Length should be 4 (control) + 6 (ASDU header) = 10
-        assert_eq!(buf[1], 10);
+        // Length should be 4 (control) + 6 (ASDU header) + 16 (objects) = 26
+        assert_eq!(buf[1], 26);
 
         // Decode and verify
         let decoded = codec.decode(&mut buf).unwrap().unwrap();
@@ -538,12 +651,13 @@ mod tests {
 
         for (send_seq, recv_seq) in test_cases {
             let mut buf = BytesMut::new();
-            let asdu = Asdu::new(AsduHeader::new(
+            let mut asdu = Asdu::new(AsduHeader::new(
                 TypeId::SinglePoint,
                 1,
                 Cot::Spontaneous,
                 1,
             ));
+            asdu.raw_data = Bytes::from_static(&[0x01, 0x00, 0x00, 0x01]);
             let apdu = Apdu::i_frame(send_seq, recv_seq, asdu);
             codec.encode(apdu, &mut buf).unwrap();
 
@@ -621,4 +735,58 @@ mod tests {
         // Start byte should still be in buffer
         assert_eq!(buf.len(), 1);
     }
+
+    #[test]
+    fn test_decode_apdu_pure() {
+        let buf = [
+            0x68, 0x04, 0x07, 0x00, 0x00, 0x00, // STARTDT act
+            0x68, 0x04, 0x01, 0x00, 0xC8, 0x00, // S-frame
+        ];
+
+        let (apdu, consumed) = decode_apdu(&buf).unwrap();
+        assert_eq!(consumed, 6);
+        assert_eq!(apdu.apci, Apci::u_frame(UFunction::StartDtAct));
+
+        let (apdu, consumed) = decode_apdu(&buf[consumed..]).unwrap();
+        assert_eq!(consumed, 6);
+        assert_eq!(apdu.apci, Apci::s_frame(100));
+    }
+
+    #[test]
+    fn test_decode_apdu_incomplete() {
+        assert!(matches!(decode_apdu(&[]), Err(Iec104Error::Incomplete(2))));
+        assert!(matches!(decode_apdu(&[0x68]), Err(Iec104Error::Incomplete(1))));
+        assert!(matches!(
+            decode_apdu(&[0x68, 0x04, 0x07]),
+            Err(Iec104Error::Incomplete(3))
+        ));
+    }
+
+    #[test]
+    fn test_decode_apdu_invalid() {
+        assert!(matches!(
+            decode_apdu(&[0xFF, 0x04, 0x07, 0x00, 0x00, 0x00]),
+            Err(Iec104Error::InvalidFrame(_))
+        ));
+        assert!(matches!(
+            decode_apdu(&[0x68, 0x02, 0x07, 0x00]),
+            Err(Iec104Error::InvalidFrame(_))
+        ));
+    }
+
+    #[test]
+    fn test_encode_apdu_matches_codec() {
+        let asdu = Asdu::interrogation_command(1, 20);
+        let apdu = Apdu::i_frame(3, 7, asdu);
+
+        let encoded = encode_apdu(&apdu).unwrap();
+        let mut codec_buf = BytesMut::new();
+        Iec104Codec::new().encode(apdu.clone(), &mut codec_buf).unwrap();
+        assert_eq!(encoded, codec_buf);
+
+        let (decoded, consumed) = decode_apdu(&encoded).unwrap();
+        assert_eq!(consumed, encoded.len());
+        assert_eq!(decoded.apci, apdu.apci);
+        assert_eq!(decoded.asdu.unwrap().header, apdu.asdu.unwrap().header);
+    }
 }
@@ -0,0 +1,852 @@
+//! Correlation of sent commands with their confirmations.
+//!
+//! Every command sent by the client is registered together with its type,
+//! common address and IOA. The matching activation confirmation (COT=7/9),
+//! activation termination (COT=10) or rejection (negative confirmation, or
+//! COT=44..47) completes the [`CommandCompletion`] returned to the caller.
+//! With a [`RetryPolicy`], commands not confirmed in time are re-sent and
+//! finally fail with [`Iec104Error::CommandTimeout`].
+
+use std::borrow::Cow;
+use std::time::Duration;
+
+use bytes::Bytes;
+use tokio::sync::oneshot;
+use tokio::time::Instant;
+
+use crate::error::{Iec104Error, Result};
+use crate::types::{
+    normalized_to_raw, Asdu, AsduHeader, CommandQualifier, Cot, DoubleCommandState,
+    DoubleCommandValue, InformationObject, Ioa, Qos, RegulatingStepValue, SingleCommandValue,
+    TypeId,
+};
+
+pub use crate::types::StepCommand;
+
+/// A command to a single information object that supports select-before-operate.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Command {
+    /// Single command (C_SC_NA_1)
+    Single {
+        /// Information object address
+        ioa: u32,
+        /// Command state (true = ON)
+        value: bool,
+    },
+    /// Double command (C_DC_NA_1)
+    Double {
+        /// Information object address
+        ioa: u32,
+        /// Double command state
+        value: DoubleCommandState,
+    },
+    /// Regulating step command (C_RC_NA_1)
+    RegulatingStep {
+        /// Information object address
+        ioa: u32,
+        /// Step direction
+        step: StepCommand,
+    },
+    /// Setpoint command, normalized value (C_SE_NA_1)
+    SetpointNormalized {
+        /// Information object address
+        ioa: u32,
+        /// Setpoint value in the range -1.0..=1.0
+        value: f32,
+        /// Qualifier of set-point command; the S/E bit is taken from the
+        /// qualifier the command is sent with
+        qos: Qos,
+    },
+    /// Setpoint command, scaled value (C_SE_NB_1)
+    SetpointScaled {
+        /// Information object address
+        ioa: u32,
+        /// Setpoint value
+        value: i16,
+        /// Qualifier of set-point command; the S/E bit is taken from the
+        /// qualifier the command is sent with
+        qos: Qos,
+    },
+    /// Setpoint command, short floating point (C_SE_NC_1)
+    SetpointFloat {
+        /// Information object address
+        ioa: u32,
+        /// Setpoint value
+        value: f32,
+        /// Qualifier of set-point command; the S/E bit is taken from the
+        /// qualifier the command is sent with
+        qos: Qos,
+    },
+}
+
+impl Command {
+    /// Type identification of the command.
+    pub const fn type_id(&self) -> TypeId {
+        match self {
+            Self::Single { .. } => TypeId::SingleCommand,
+            Self::Double { .. } => TypeId::DoubleCommand,
+            Self::RegulatingStep { .. } => TypeId::RegulatingStep,
+            Self::SetpointNormalized { .. } => TypeId::SetpointNormalized,
+            Self::SetpointScaled { .. } => TypeId::SetpointScaled,
+            Self::SetpointFloat { .. } => TypeId::SetpointFloat,
+        }
+    }
+
+    /// Information object address of the command.
+    pub const fn ioa(&self) -> u32 {
+        match self {
+            Self::Single { ioa, .. }
+            | Self::Double { ioa, .. }
+            | Self::RegulatingStep { ioa, .. }
+            | Self::SetpointNormalized { ioa, .. }
+            | Self::SetpointScaled { ioa, .. }
+            | Self::SetpointFloat { ioa, .. } => *ioa,
+        }
+    }
+
+    /// Check that the command value can be encoded.
+    pub fn validate(&self) -> Result<()> {
+        match *self {
+            Self::SetpointNormalized { value, .. } if !(-1.0..=1.0).contains(&value) => Err(
+                Iec104Error::invalid_asdu_static("Normalized value out of range -1.0..=1.0"),
+            ),
+            _ => Ok(()),
+        }
+    }
+
+    /// Build the command ASDU.
+    ///
+    /// Single, double and regulating step commands carry the full
+    /// `qualifier` (QOC); setpoints carry their own [`Qos`] with the S/E bit
+    /// of `qualifier`.
+    pub fn to_asdu(&self, common_address: u16, cot: Cot, qualifier: CommandQualifier) -> Asdu {
+        let data = match *self {
+            Self::Single { value, .. } => vec![SingleCommandValue::new(value, qualifier).as_u8()],
+            Self::Double { value, .. } => vec![DoubleCommandValue::new(value, qualifier).as_u8()],
+            Self::RegulatingStep { step, .. } => {
+                vec![RegulatingStepValue::new(step, qualifier).as_u8()]
+            }
+            // NVA (2 bytes) + QOS (1 byte)
+            Self::SetpointNormalized { value, qos, .. } => {
+                let mut data = normalized_to_raw(value).to_le_bytes().to_vec();
+                data.push(qos.with_select(qualifier.select).as_u8());
+                data
+            }
+            // SVA (2 bytes) + QOS (1 byte)
+            Self::SetpointScaled { value, qos, .. } => {
+                let mut data = value.to_le_bytes().to_vec();
+                data.push(qos.with_select(qualifier.select).as_u8());
+                data
+            }
+            // Value (4 bytes) + QOS (1 byte)
+            Self::SetpointFloat { value, qos, .. } => {
+                let mut data = value.to_le_bytes().to_vec();
+                data.push(qos.with_select(qualifier.select).as_u8());
+                data
+            }
+        };
+
+        let mut asdu = Asdu::new(AsduHeader::new(self.type_id(), 1, cot, common_address));
+        asdu.objects.push(InformationObject::new(
+            Ioa::new(self.ioa()),
+            Bytes::from(data),
+        ));
+        asdu
+    }
+}
+
+/// Completion of a sent command.
+///
+/// The confirmation and termination are reported by frames received by the
+/// client, so with a polled [`Iec104Client`](crate::Iec104Client) the waiting
+/// task must keep calling `poll()` concurrently. With a
+/// [`ClientHandle`](crate::ClientHandle) the background task takes care of
+/// that.
+///
+/// Dropping the completion does not cancel the command: under a
+/// [`RetryPolicy`] it is still re-sent until confirmed or out of retries.
+#[derive(Debug)]
+pub struct CommandCompletion {
+    type_id: TypeId,
+    ioa: u32,
+    confirm: oneshot::Receiver<Result<Asdu>>,
+    terminate: oneshot::Receiver<Result<Asdu>>,
+}
+
+impl CommandCompletion {
+    /// Type of the command.
+    pub fn type_id(&self) -> TypeId {
+        self.type_id
+    }
+
+    /// Information object address of the command.
+    pub fn ioa(&self) -> u32 {
+        self.ioa
+    }
+
+    /// Wait for the activation confirmation.
+    ///
+    /// Returns [`Iec104Error::CommandRejected`] for a negative confirmation.
+    pub async fn confirmed(self) -> Result<()> {
+        self.confirmation().await.map(|_| ())
+    }
+
+    /// Wait for the activation confirmation and return the confirming ASDU.
+    pub async fn confirmation(self) -> Result<Asdu> {
+        wait(self.confirm).await
+    }
+
+    /// Wait for the activation confirmation and then the activation termination.
+    ///
+    /// Only use this for commands the remote station terminates, such as
+    /// interrogations; otherwise it waits until the connection closes.
+    pub async fn terminated(self) -> Result<()> {
+        wait(self.confirm).await?;
+        wait(self.terminate).await.map(|_| ())
+    }
+
+    /// Take the confirmation if it has arrived.
+    pub(crate) fn try_confirmed(&mut self) -> Option<Result<Asdu>> {
+        try_take(&mut self.confirm)
+    }
+
+    /// Take the termination if it has arrived (call once confirmed).
+    pub(crate) fn try_terminated(&mut self) -> Option<Result<Asdu>> {
+        try_take(&mut self.terminate)
+    }
+}
+
+fn try_take(rx: &mut oneshot::Receiver<Result<Asdu>>) -> Option<Result<Asdu>> {
+    match rx.try_recv() {
+        Ok(result) => Some(result),
+        Err(oneshot::error::TryRecvError::Empty) => None,
+        Err(oneshot::error::TryRecvError::Closed) => Some(Err(closed())),
+    }
+}
+
+async fn wait(rx: oneshot::Receiver<Result<Asdu>>) -> Result<Asdu> {
+    rx.await.unwrap_or_else(|_| Err(closed()))
+}
+
+fn closed() -> Iec104Error {
+    Iec104Error::Connection(Cow::Borrowed("Connection closed before command completed"))
+}
+
+/// Confirmation timeout and retries for commands, independent of T1.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct RetryPolicy {
+    /// Time to wait for the activation confirmation of each attempt
+    pub timeout: Duration,
+    /// Number of times the activation is re-sent before giving up
+    pub retries: u8,
+}
+
+impl RetryPolicy {
+    /// Create a policy.
+    pub const fn new(timeout: Duration, retries: u8) -> Self {
+        Self { timeout, retries }
+    }
+}
+
+struct PendingCommand {
+    type_id: TypeId,
+    common_address: u16,
+    originator: u8,
+    ioa: u32,
+    confirm: Option<oneshot::Sender<Result<Asdu>>>,
+    terminate: oneshot::Sender<Result<Asdu>>,
+    /// Confirmation deadline and the command to re-send, under a policy
+    retry: Option<Retry>,
+    #[cfg(feature = "metrics")]
+    sent_at: Instant,
+}
+
+struct Retry {
+    deadline: Instant,
+    remaining: u8,
+    asdu: Asdu,
+}
+
+impl PendingCommand {
+    fn matches(&self, asdu: &Asdu, ioa: u32) -> bool {
+        self.type_id == asdu.header.type_id
+            && self.common_address == asdu.header.common_address
+            && self.originator == asdu.header.originator
+            && self.ioa == ioa
+    }
+
+    /// Nobody can observe the outcome anymore, and no retry is due.
+    fn is_abandoned(&self) -> bool {
+        let retrying = self.confirm.is_some() && self.retry.is_some();
+        !retrying
+            && self.terminate.is_closed()
+            && self.confirm.as_ref().is_none_or(|tx| tx.is_closed())
+    }
+}
+
+/// Commands awaiting confirmation or termination, oldest first.
+#[derive(Default)]
+pub(crate) struct PendingCommands {
+    entries: Vec<PendingCommand>,
+    policy: Option<RetryPolicy>,
+    /// Label of the round-trip metric
+    #[cfg(feature = "metrics")]
+    peer: String,
+}
+
+impl PendingCommands {
+    pub(crate) fn with_policy(policy: Option<RetryPolicy>) -> Self {
+        Self {
+            entries: Vec::new(),
+            policy,
+            #[cfg(feature = "metrics")]
+            peer: String::new(),
+        }
+    }
+
+    #[cfg(feature = "metrics")]
+    pub(crate) fn peer(mut self, peer: &str) -> Self {
+        self.peer = peer.to_owned();
+        self
+    }
+
+    /// Register a command once its I-frame has been sent.
+    ///
+    /// Under a policy the command is re-sent until confirmed, even when the
+    /// completion is dropped, so it must not be registered before the send
+    /// succeeded.
+    pub(crate) fn register(&mut self, asdu: &Asdu) -> CommandCompletion {
+        self.entries.retain(|entry| !entry.is_abandoned());
+
+        let ioa = first_ioa(asdu).unwrap_or(0);
+        let (confirm_tx, confirm) = oneshot::channel();
+        let (terminate_tx, terminate) = oneshot::channel();
+        self.entries.push(PendingCommand {
+            type_id: asdu.header.type_id,
+            common_address: asdu.header.common_address,
+            originator: asdu.header.originator,
+            ioa,
+            confirm: Some(confirm_tx),
+            terminate: terminate_tx,
+            retry: self.policy.map(|policy| Retry {
+                deadline: Instant::now() + policy.timeout,
+                remaining: policy.retries,
+                asdu: asdu.clone(),
+            }),
+            #[cfg(feature = "metrics")]
+            sent_at: Instant::now(),
+        });
+
+        CommandCompletion {
+            type_id: asdu.header.type_id,
+            ioa,
+            confirm,
+            terminate,
+        }
+    }
+
+    /// Complete the command a received ASDU responds to, if any.
+    ///
+    /// Returns whether a pending command matched.
+    pub(crate) fn resolve(&mut self, asdu: &Asdu) -> bool {
+        self.entries.retain(|entry| !entry.is_abandoned());
+
+        let cot = asdu.header.cot;
+        let rejected = asdu.header.negative
+            || matches!(
+                cot,
+                Cot::UnknownTypeId | Cot::UnknownCot | Cot::UnknownCommonAddress | Cot::UnknownIoa
+            );
+        let terminating = cot == Cot::ActivationTermination;
+        if !rejected
+            && !terminating
+            && !matches!(cot, Cot::ActivationConfirm | Cot::DeactivationConfirm)
+        {
+            return false;
+        }
+
+        let Some(ioa) = first_ioa(asdu) else {
+            return false;
+        };
+        // A confirmation belongs to a command awaiting one, a termination to a
+        // confirmed command; unknown type/cause/address/IOA can answer either
+        let awaits_stage = |entry: &PendingCommand| match cot {
+            Cot::ActivationConfirm | Cot::DeactivationConfirm => entry.confirm.is_some(),
+            Cot::ActivationTermination => entry.confirm.is_none(),
+            _ => true,
+        };
+        let Some(index) = self
+            .entries
+            .iter()
+            .position(|entry| entry.matches(asdu, ioa) && awaits_stage(entry))
+        else {
+            return false;
+        };
+
+        #[cfg(feature = "metrics")]
+        if self.entries[index].confirm.is_some() {
+            let entry = &self.entries[index];
+            crate::metrics::command_round_trip(&self.peer, entry.type_id, entry.sent_at.elapsed());
+        }
+
+        if rejected {
+            let entry = self.entries.remove(index);
+            let error = Iec104Error::CommandRejected {
+                type_id: asdu.header.type_id,
+                ioa,
+                cot,
+            };
+            match entry.confirm {
+                Some(tx) => {
+                    let _ = tx.send(Err(error));
+                }
+                None => {
+                    let _ = entry.terminate.send(Err(error));
+                }
+            }
+        } else if terminating {
+            let entry = self.entries.remove(index);
+            let _ = entry.terminate.send(Ok(asdu.clone()));
+        } else {
+            let entry = &mut self.entries[index];
+            if let Some(tx) = entry.confirm.take() {
+                let _ = tx.send(Ok(asdu.clone()));
+            }
+            if entry.terminate.is_closed() {
+                self.entries.remove(index);
+            }
+        }
+        true
+    }
+
+    /// When the earliest unconfirmed command times out, if any.
+    pub(crate) fn next_deadline(&self) -> Option<Instant> {
+        self.entries
+            .iter()
+            .filter(|entry| entry.confirm.is_some())
+            .filter_map(|entry| entry.retry.as_ref().map(|retry| retry.deadline))
+            .min()
+    }
+
+    /// Handle commands whose confirmation is overdue at `now`.
+    ///
+    /// Returns the commands to re-send, at most `slots` of them; the others
+    /// fail with [`Iec104Error::CommandTimeout`].
+    pub(crate) fn expire(&mut self, now: Instant, mut slots: usize) -> Vec<Asdu> {
+        let Some(policy) = self.policy else {
+            return Vec::new();
+        };
+
+        let mut resend = Vec::new();
+        self.entries.retain_mut(|entry| {
+            let (Some(retry), Some(_)) = (entry.retry.as_mut(), entry.confirm.as_ref()) else {
+                return true;
+            };
+            if retry.deadline > now {
+                return true;
+            }
+            if retry.remaining > 0 && slots > 0 {
+                retry.remaining -= 1;
+                retry.deadline = now + policy.timeout;
+                slots -= 1;
+                resend.push(retry.asdu.clone());
+                return true;
+            }
+            if let Some(tx) = entry.confirm.take() {
+                let _ = tx.send(Err(Iec104Error::CommandTimeout {
+                    type_id: entry.type_id,
+                    ioa: entry.ioa,
+                }));
+            }
+            false
+        });
+        resend
+    }
+
+    /// Fail every pending command (connection closed).
+    pub(crate) fn clear(&mut self) {
+        self.entries.clear();
+    }
+
+    /// Number of commands awaiting confirmation or termination.
+    #[cfg(test)]
+    pub(crate) fn len(&self) -> usize {
+        self.entries.len()
+    }
+}
+
+/// IOA of the first information object, whether built or received.
+pub(crate) fn first_ioa(asdu: &Asdu) -> Option<u32> {
+    match asdu.objects.first() {
+        Some(object) => Some(object.ioa.value()),
+        None => Ioa::try_from_slice(&asdu.raw_data).map(|ioa| ioa.value()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::{AsduHeader, PulseDuration};
+    use bytes::Bytes;
+
+    fn response(type_id: TypeId, cot: Cot, negative: bool, ioa: u8) -> Asdu {
+        let mut header = AsduHeader::new(type_id, 1, cot, 1);
+        header.negative = negative;
+        let mut asdu = Asdu::new(header);
+        asdu.raw_data = Bytes::copy_from_slice(&[ioa, 0x00, 0x00, 0x01]);
+        asdu
+    }
+
+    fn command(ioa: u32) -> Asdu {
+        let mut asdu = Asdu::new(AsduHeader::new(
+            TypeId::SingleCommand,
+            1,
+            Cot::Activation,
+            1,
+        ));
+        asdu.objects.push(crate::types::InformationObject::new(
+            Ioa::new(ioa),
+            Bytes::from_static(&[0x01]),
+        ));
+        asdu
+    }
+
+    #[test]
+    fn test_command_to_asdu() {
+        let asdu = Command::Single {
+            ioa: 100,
+            value: true,
+        }
+        .to_asdu(1, Cot::Activation, CommandQualifier::SELECT);
+        assert_eq!(asdu.header.type_id, TypeId::SingleCommand);
+        assert_eq!(asdu.objects[0].ioa.value(), 100);
+        assert_eq!(asdu.objects[0].data.as_ref(), &[0x81]);
+
+        let asdu = Command::Double {
+            ioa: 5,
+            value: DoubleCommandState::Off,
+        }
+        .to_asdu(
+            1,
+            Cot::Deactivation,
+            CommandQualifier::EXECUTE,
+        );
+        assert_eq!(asdu.header.cot, Cot::Deactivation);
+        assert_eq!(asdu.objects[0].data.as_ref(), &[0x01]);
+
+        let asdu = Command::RegulatingStep {
+            ioa: 9,
+            step: StepCommand::Higher,
+        }
+        .to_asdu(1, Cot::Activation, CommandQualifier::SELECT);
+        assert_eq!(asdu.header.type_id, TypeId::RegulatingStep);
+        assert_eq!(asdu.objects[0].data.as_ref(), &[0x82]);
+        let asdu = Command::RegulatingStep {
+            ioa: 9,
+            step: StepCommand::Lower,
+        }
+        .to_asdu(1, Cot::Activation, CommandQualifier::EXECUTE);
+        assert_eq!(asdu.objects[0].data.as_ref(), &[0x01]);
+
+        let qualifier = CommandQualifier::new(PulseDuration::Long, false);
+        let asdu = Command::Double {
+            ioa: 5,
+            value: DoubleCommandState::On,
+        }
+        .to_asdu(1, Cot::Activation, qualifier);
+        assert_eq!(asdu.objects[0].data.as_ref(), &[0x0A]);
+
+        let asdu = Command::SetpointFloat {
+            ioa: 7,
+            value: 1.5,
+            qos: Qos::EXECUTE,
+        }
+        .to_asdu(2, Cot::Activation, CommandQualifier::SELECT);
+        assert_eq!(asdu.header.common_address, 2);
+        assert_eq!(
+            asdu.objects[0].data.as_ref(),
+            &[0x00, 0x00, 0xC0, 0x3F, 0x80]
+        );
+
+        let asdu = Command::SetpointFloat {
+            ioa: 7,
+            value: 1.5,
+            qos: Qos::new(5, true),
+        }
+        .to_asdu(2, Cot::Activation, CommandQualifier::SELECT);
+        assert_eq!(asdu.objects[0].data[4], 0x85);
+    }
+
+    #[test]
+    fn test_setpoint_normalized() {
+        let encode = |value| {
+            let command = Command::SetpointNormalized {
+                ioa: 1,
+                value,
+                qos: Qos::EXECUTE,
+            };
+            command
+                .to_asdu(1, Cot::Activation, CommandQualifier::EXECUTE)
+                .objects[0]
+                .data
+                .clone()
+        };
+        assert_eq!(encode(0.5).as_ref(), &[0x00, 0x40, 0x00]);
+        assert_eq!(encode(-1.0).as_ref(), &[0x00, 0x80, 0x00]);
+        assert_eq!(encode(1.0).as_ref(), &[0xFF, 0x7F, 0x00]);
+
+        let validate = |value| {
+            Command::SetpointNormalized {
+                ioa: 1,
+                value,
+                qos: Qos::EXECUTE,
+            }
+            .validate()
+        };
+        assert!(validate(1.0).is_ok());
+        assert!(validate(1.5).is_err());
+        assert!(validate(f32::NAN).is_err());
+    }
+
+    #[test]
+    fn test_setpoint_scaled() {
+        let command = Command::SetpointScaled {
+            ioa: 3,
+            value: -2,
+            qos: Qos::EXECUTE,
+        };
+        let asdu = command.to_asdu(1, Cot::Activation, CommandQualifier::SELECT);
+        assert_eq!(asdu.header.type_id, TypeId::SetpointScaled);
+        assert_eq!(asdu.objects[0].data.as_ref(), &[0xFE, 0xFF, 0x80]);
+
+        let command = Command::SetpointScaled {
+            ioa: 3,
+            value: 300,
+            qos: Qos::new(5, false),
+        };
+        let asdu = command.to_asdu(1, Cot::Activation, CommandQualifier::SELECT);
+        assert_eq!(asdu.objects[0].data.as_ref(), &[0x2C, 0x01, 0x85]);
+    }
+
+    #[tokio::test]
+    async fn test_retry_then_timeout() {
+        let policy = RetryPolicy::new(Duration::from_secs(5), 1);
+        let mut pending = PendingCommands::with_policy(Some(policy));
+        let completion = pending.register(&command(5));
+        let start = Instant::now();
+        assert!(pending.next_deadline().unwrap() <= start + policy.timeout);
+
+        assert!(pending.expire(start, 1).is_empty());
+        let resend = pending.expire(start + policy.timeout, 1);
+        assert_eq!(resend, vec![command(5)]);
+
+        // The retry is exhausted: the command fails
+        let later = start + policy.timeout * 2;
+        assert!(pending.expire(later, 1).is_empty());
+        assert_eq!(pending.len(), 0);
+        assert!(matches!(
+            completion.confirmed().await,
+            Err(Iec104Error::CommandTimeout { ioa: 5, .. })
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_dropped_completion_keeps_retrying() {
+        let policy = RetryPolicy::new(Duration::from_secs(5), 1);
+        let mut pending = PendingCommands::with_policy(Some(policy));
+        drop(pending.register(&command(5)));
+        let _kept = pending.register(&command(6));
+        assert_eq!(pending.len(), 2);
+
+        let resend = pending.expire(Instant::now() + policy.timeout, 2);
+        assert_eq!(resend, vec![command(5), command(6)]);
+
+        // Once confirmed, nobody waits for its termination
+        let confirm = response(TypeId::SingleCommand, Cot::ActivationConfirm, false, 5);
+        assert!(pending.resolve(&confirm));
+        assert_eq!(pending.len(), 1);
+
+        // Without a policy there is nothing left to do for it
+        let mut pending = PendingCommands::default();
+        drop(pending.register(&command(5)));
+        let _kept = pending.register(&command(6));
+        assert_eq!(pending.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_retry_needs_window() {
+        let policy = RetryPolicy::new(Duration::from_secs(1), 3);
+        let mut pending = PendingCommands::with_policy(Some(policy));
+        let mut first = pending.register(&command(5));
+        let mut second = pending.register(&command(6));
+
+        // One free window slot: the older command is retried, the other fails
+        let resend = pending.expire(Instant::now() + policy.timeout, 1);
+        assert_eq!(resend, vec![command(5)]);
+        assert_eq!(pending.len(), 1);
+        assert!(first.try_confirmed().is_none());
+        assert!(matches!(
+            second.try_confirmed(),
+            Some(Err(Iec104Error::CommandTimeout { ioa: 6, .. }))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_confirmation_filters_originator() {
+        let mut pending = PendingCommands::default();
+        let mut sent = command(5);
+        sent.header.originator = 3;
+        let completion = pending.register(&sent);
+
+        // Mirrored to another master
+        let mut other = response(TypeId::SingleCommand, Cot::ActivationConfirm, false, 5);
+        other.header.originator = 4;
+        assert!(!pending.resolve(&other));
+
+        let mut ours = other.clone();
+        ours.header.originator = 3;
+        assert!(pending.resolve(&ours));
+        assert!(completion.confirmed().await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_confirmation_resolves_matching_command() {
+        let mut pending = PendingCommands::default();
+        let first = pending.register(&command(5));
+        let second = pending.register(&command(6));
+        assert_eq!(first.ioa(), 5);
+
+        // Wrong IOA, wrong type and unrelated cause are ignored
+        assert!(!pending.resolve(&response(
+            TypeId::SingleCommand,
+            Cot::ActivationConfirm,
+            false,
+            7
+        )));
+        assert!(!pending.resolve(&response(
+            TypeId::DoubleCommand,
+            Cot::ActivationConfirm,
+            false,
+            5
+        )));
+        assert!(!pending.resolve(&response(TypeId::SingleCommand, Cot::Spontaneous, false, 5)));
+
+        assert!(pending.resolve(&response(
+            TypeId::SingleCommand,
+            Cot::ActivationConfirm,
+            false,
+            6
+        )));
+        second.confirmed().await.unwrap();
+        assert_eq!(pending.len(), 2);
+
+        // Commands nobody waits for anymore are forgotten
+        drop(first);
+        assert!(!pending.resolve(&response(
+            TypeId::SingleCommand,
+            Cot::ActivationConfirm,
+            false,
+            5
+        )));
+        assert_eq!(pending.len(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_confirmation_skips_confirmed_command() {
+        let mut pending = PendingCommands::default();
+        let mut select = pending.register(&command(5));
+        let execute = pending.register(&command(5));
+
+        assert!(pending.resolve(&response(
+            TypeId::SingleCommand,
+            Cot::ActivationConfirm,
+            false,
+            5
+        )));
+        assert!(select.try_confirmed().unwrap().is_ok());
+        assert!(pending.resolve(&response(
+            TypeId::SingleCommand,
+            Cot::ActivationConfirm,
+            false,
+            5
+        )));
+        execute.confirmed().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_negative_confirmation_is_error() {
+        let mut pending = PendingCommands::default();
+        let completion = pending.register(&command(5));
+        assert!(pending.resolve(&response(
+            TypeId::SingleCommand,
+            Cot::ActivationConfirm,
+            true,
+            5
+        )));
+
+        match completion.confirmed().await {
+            Err(Iec104Error::CommandRejected {
+                ioa: 5,
+                cot: Cot::ActivationConfirm,
+                ..
+            }) => {}
+            other => panic!("Expected rejection, got {:?}", other),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_unknown_ioa_is_error() {
+        let mut pending = PendingCommands::default();
+        let completion = pending.register(&command(5));
+        assert!(pending.resolve(&response(TypeId::SingleCommand, Cot::UnknownIoa, false, 5)));
+        assert!(matches!(
+            completion.terminated().await,
+            Err(Iec104Error::CommandRejected {
+                cot: Cot::UnknownIoa,
+                ..
+            })
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_termination_after_confirmation() {
+        let mut pending = PendingCommands::default();
+        let completion = pending.register(&command(5));
+
+        // Termination before confirmation does not match
+        assert!(!pending.resolve(&response(
+            TypeId::SingleCommand,
+            Cot::ActivationTermination,
+            false,
+            5
+        )));
+        assert!(pending.resolve(&response(
+            TypeId::SingleCommand,
+            Cot::ActivationConfirm,
+            false,
+            5
+        )));
+        assert_eq!(pending.len(), 1);
+        assert!(pending.resolve(&response(
+            TypeId::SingleCommand,
+            Cot::ActivationTermination,
+            false,
+            5
+        )));
+        assert_eq!(pending.len(), 0);
+        completion.terminated().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_cleared_commands_fail() {
+        let mut pending = PendingCommands::default();
+        let completion = pending.register(&command(5));
+        pending.clear();
+        assert!(completion
+            .confirmed()
+            .await
+            .unwrap_err()
+            .is_connection_error());
+    }
+}
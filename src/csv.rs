@@ -0,0 +1,414 @@
+//! CSV files of received data points, e.g. for site acceptance tests.
+//!
+//! [`CsvRecorder`] appends one line per data point with the chosen
+//! [`Column`]s, under a header line, and starts a new file when the current
+//! one reaches a size or age limit:
+//!
+//! ```text
+//! received_at,ca,ioa,type,value,quality,timestamp
+//! 2024-02-29T13:45:30.312Z,1,100,Float,1.5,Good,2024-02-29T13:45:30.250
+//! 2024-02-29T13:45:30.312Z,1,101,Single,1,IV,
+//! ```
+//!
+//! Files are named `<prefix>-<UTC date and time>.csv` after the moment they
+//! were opened. Numbers use `.` as the decimal separator; choose `;` as
+//! [`delimiter`](CsvRecorder::delimiter) for spreadsheets in locales that
+//! expect it.
+
+use std::fs::{self, File, OpenOptions};
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+use tokio::sync::mpsc;
+
+use crate::client::{Iec104Event, SequencedEvent};
+use crate::error::Result;
+use crate::types::{Cp56Time2a, DataPoint, DataValue, DoublePointValue, Timestamp};
+
+/// A column of the CSV files.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Column {
+    /// When the point was recorded, UTC (`received_at`)
+    ReceivedAt,
+    /// Common address of the station (`ca`)
+    CommonAddress,
+    /// Information object address (`ioa`)
+    Ioa,
+    /// [`DataValue`] variant, as in the JSON form (`type`)
+    Type,
+    /// Value as a number; 1/0 for on/off, empty for indeterminate (`value`)
+    Value,
+    /// Quality flags set, like `OV|IV`, or `Good` (`quality`)
+    Quality,
+    /// Time tag of the point, if any (`timestamp`)
+    Timestamp,
+}
+
+impl Column {
+    /// Name in the header line.
+    pub const fn name(&self) -> &'static str {
+        match self {
+            Self::ReceivedAt => "received_at",
+            Self::CommonAddress => "ca",
+            Self::Ioa => "ioa",
+            Self::Type => "type",
+            Self::Value => "value",
+            Self::Quality => "quality",
+            Self::Timestamp => "timestamp",
+        }
+    }
+}
+
+/// Columns written unless configured otherwise: all of them.
+pub const DEFAULT_COLUMNS: [Column; 7] = [
+    Column::ReceivedAt,
+    Column::CommonAddress,
+    Column::Ioa,
+    Column::Type,
+    Column::Value,
+    Column::Quality,
+    Column::Timestamp,
+];
+
+/// Writes data points to rotating CSV files.
+#[derive(Debug)]
+pub struct CsvRecorder {
+    directory: PathBuf,
+    prefix: String,
+    columns: Vec<Column>,
+    delimiter: char,
+    max_bytes: Option<u64>,
+    max_age: Option<Duration>,
+    current: Option<CurrentFile>,
+}
+
+#[derive(Debug)]
+struct CurrentFile {
+    path: PathBuf,
+    writer: BufWriter<File>,
+    bytes: u64,
+    opened_at: SystemTime,
+}
+
+impl CsvRecorder {
+    /// Create a recorder writing to `directory`, which is created when the
+    /// first file is opened.
+    pub fn new(directory: impl Into<PathBuf>) -> Self {
+        Self {
+            directory: directory.into(),
+            prefix: "points".to_string(),
+            columns: DEFAULT_COLUMNS.to_vec(),
+            delimiter: ',',
+            max_bytes: None,
+            max_age: None,
+            current: None,
+        }
+    }
+
+    /// Start of the file names (default `points`).
+    pub fn prefix(mut self, prefix: impl Into<String>) -> Self {
+        self.prefix = prefix.into();
+        self
+    }
+
+    /// Columns to write, in order.
+    pub fn columns(mut self, columns: impl IntoIterator<Item = Column>) -> Self {
+        self.columns = columns.into_iter().collect();
+        self
+    }
+
+    /// Field separator (default `,`).
+    pub fn delimiter(mut self, delimiter: char) -> Self {
+        self.delimiter = delimiter;
+        self
+    }
+
+    /// Start a new file once the current one holds at least `bytes`.
+    pub fn rotate_size(mut self, bytes: u64) -> Self {
+        self.max_bytes = Some(bytes);
+        self
+    }
+
+    /// Start a new file once the current one is `interval` old.
+    pub fn rotate_interval(mut self, interval: Duration) -> Self {
+        self.max_age = Some(interval);
+        self
+    }
+
+    /// The file currently written, if one is open.
+    pub fn current_path(&self) -> Option<&Path> {
+        self.current.as_ref().map(|current| current.path.as_path())
+    }
+
+    /// Record points received from station `common_address` now.
+    pub fn record(&mut self, common_address: u16, points: &[DataPoint]) -> Result<()> {
+        self.record_at(common_address, points, SystemTime::now())
+    }
+
+    /// Record points received from station `common_address` at `recorded_at`.
+    pub fn record_at(
+        &mut self,
+        common_address: u16,
+        points: &[DataPoint],
+        recorded_at: SystemTime,
+    ) -> Result<()> {
+        let received_at = format!(
+            "{}Z",
+            Cp56Time2a::from_system_time(recorded_at, 0).to_iso8601()
+        );
+        for point in points {
+            let mut line = String::new();
+            for (index, column) in self.columns.iter().enumerate() {
+                if index > 0 {
+                    line.push(self.delimiter);
+                }
+                let field = match column {
+                    Column::ReceivedAt => received_at.clone(),
+                    Column::CommonAddress => common_address.to_string(),
+                    Column::Ioa => point.ioa.to_string(),
+                    Column::Type => point.value.name().to_string(),
+                    Column::Value => value(&point.value),
+                    Column::Quality => point.quality.to_string(),
+                    Column::Timestamp => timestamp(point.timestamp),
+                };
+                line.push_str(&self.escape(field));
+            }
+            line.push('\n');
+
+            let current = self.file_for(recorded_at)?;
+            current.writer.write_all(line.as_bytes())?;
+            current.bytes += line.len() as u64;
+        }
+        Ok(())
+    }
+
+    /// Write buffered lines to the file.
+    pub fn flush(&mut self) -> Result<()> {
+        if let Some(current) = self.current.as_mut() {
+            current.writer.flush()?;
+        }
+        Ok(())
+    }
+
+    /// Record the data updates of an event stream from station
+    /// `common_address` until it ends, e.g. a receiver from
+    /// [`subscribe_filtered`](crate::Iec104Client::subscribe_filtered) with
+    /// [`EventFilter::common_address`](crate::filter::EventFilter::common_address).
+    ///
+    /// Lines are flushed after every update, so the file can be opened while
+    /// recording.
+    pub async fn run(
+        mut self,
+        common_address: u16,
+        mut events: mpsc::Receiver<SequencedEvent>,
+    ) -> Result<()> {
+        while let Some(SequencedEvent { event, .. }) = events.recv().await {
+            if let Iec104Event::DataUpdate(points) = event {
+                self.record(common_address, &points)?;
+                self.flush()?;
+            }
+        }
+        self.flush()
+    }
+
+    /// The file to append to at `now`, rotating first if needed.
+    fn file_for(&mut self, now: SystemTime) -> Result<&mut CurrentFile> {
+        let expired = self.current.as_ref().is_some_and(|current| {
+            let full = self.max_bytes.is_some_and(|max| current.bytes >= max);
+            let old = self.max_age.is_some_and(|max| {
+                now.duration_since(current.opened_at)
+                    .is_ok_and(|age| age >= max)
+            });
+            full || old
+        });
+        if expired {
+            if let Some(mut current) = self.current.take() {
+                current.writer.flush()?;
+            }
+        }
+
+        if self.current.is_none() {
+            self.current = Some(self.open(now)?);
+        }
+        Ok(self.current.as_mut().expect("file opened above"))
+    }
+
+    fn open(&self, now: SystemTime) -> Result<CurrentFile> {
+        fs::create_dir_all(&self.directory)?;
+        // 2024-02-29T13:45:30.250 -> 20240229T134530
+        let stamp: String = Cp56Time2a::from_system_time(now, 0).to_iso8601()[..19]
+            .chars()
+            .filter(|c| c.is_ascii_alphanumeric())
+            .collect();
+        let mut suffix = 0;
+        let (path, file) = loop {
+            let name = match suffix {
+                0 => format!("{}-{}.csv", self.prefix, stamp),
+                n => format!("{}-{}-{}.csv", self.prefix, stamp, n),
+            };
+            let path = self.directory.join(name);
+            match OpenOptions::new().write(true).create_new(true).open(&path) {
+                Ok(file) => break (path, file),
+                Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => suffix += 1,
+                Err(e) => return Err(e.into()),
+            }
+        };
+
+        let names: Vec<String> = self
+            .columns
+            .iter()
+            .map(|column| column.name().to_string())
+            .collect();
+        let header = format!("{}\n", names.join(&self.delimiter.to_string()));
+        let mut writer = BufWriter::new(file);
+        writer.write_all(header.as_bytes())?;
+        Ok(CurrentFile {
+            path,
+            writer,
+            bytes: header.len() as u64,
+            opened_at: now,
+        })
+    }
+
+    /// Quote a field containing the delimiter, a quote or a line break.
+    fn escape(&self, field: String) -> String {
+        if field.contains([self.delimiter, '"', '\n', '\r']) {
+            format!("\"{}\"", field.replace('"', "\"\""))
+        } else {
+            field
+        }
+    }
+}
+
+fn value(value: &DataValue) -> String {
+    match value {
+        DataValue::Double(DoublePointValue::Off) => "0".to_string(),
+        DataValue::Double(DoublePointValue::On) => "1".to_string(),
+        DataValue::Double(_) => String::new(),
+        value => value.as_f64().map(|v| v.to_string()).unwrap_or_default(),
+    }
+}
+
+fn timestamp(timestamp: Option<Timestamp>) -> String {
+    match timestamp {
+        Some(Timestamp::Full(time)) => time.to_iso8601(),
+        Some(Timestamp::Partial(time)) => format!(
+            "xx:{:02}:{:02}.{:03}",
+            time.minutes,
+            time.milliseconds / 1000,
+            time.milliseconds % 1000
+        ),
+        None => String::new(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::Quality;
+
+    fn temp_directory(name: &str) -> PathBuf {
+        let directory =
+            std::env::temp_dir().join(format!("iec104-csv-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&directory);
+        directory
+    }
+
+    #[test]
+    fn test_lines_and_columns() {
+        let directory = temp_directory("lines");
+        let now = SystemTime::UNIX_EPOCH + Duration::from_millis(1_709_214_330_312);
+        let time = Cp56Time2a::from_bytes(&[0x2A, 0x76, 45, 13, 0x9D, 2, 24]).unwrap();
+        let points = [
+            DataPoint::with_timestamp(100, DataValue::Float(1.5), Quality::Good, time),
+            DataPoint::with_quality(
+                101,
+                DataValue::Single(true),
+                Quality::Good.set_invalid(true),
+            ),
+        ];
+
+        let mut recorder = CsvRecorder::new(&directory);
+        recorder.record_at(1, &points, now).unwrap();
+        recorder.flush().unwrap();
+        let path = recorder.current_path().unwrap().to_path_buf();
+        assert_eq!(path, directory.join("points-20240229T134530.csv"));
+        assert_eq!(
+            fs::read_to_string(&path).unwrap(),
+            "received_at,ca,ioa,type,value,quality,timestamp\n\
+             2024-02-29T13:45:30.312Z,1,100,Float,1.5,Good,2024-02-29T13:45:30.250\n\
+             2024-02-29T13:45:30.312Z,1,101,Single,1,IV,\n"
+        );
+
+        // Same second: a second file gets a suffix
+        let mut recorder = CsvRecorder::new(&directory)
+            .columns([Column::Ioa, Column::Value, Column::Quality])
+            .delimiter(' ');
+        recorder.record_at(1, &points[..1], now).unwrap();
+        recorder.flush().unwrap();
+        let path = recorder.current_path().unwrap();
+        assert_eq!(path, directory.join("points-20240229T134530-1.csv"));
+        assert_eq!(
+            fs::read_to_string(path).unwrap(),
+            "ioa value quality\n100 1.5 Good\n"
+        );
+
+        fs::remove_dir_all(&directory).unwrap();
+    }
+
+    #[test]
+    fn test_rotation() {
+        let directory = temp_directory("rotation");
+        let start = SystemTime::UNIX_EPOCH + Duration::from_secs(1_709_214_330);
+        let point = DataPoint::new(1, DataValue::Scaled(-7));
+
+        let mut recorder = CsvRecorder::new(&directory)
+            .columns([Column::Ioa, Column::Value])
+            .rotate_size(20)
+            .rotate_interval(Duration::from_secs(60));
+        // Header (10 bytes) and two lines (5 bytes each) fill the first file
+        for offset in [0, 1, 2] {
+            let now = start + Duration::from_secs(offset);
+            recorder
+                .record_at(1, std::slice::from_ref(&point), now)
+                .unwrap();
+        }
+        let second = recorder.current_path().unwrap().to_path_buf();
+        recorder
+            .record_at(
+                1,
+                std::slice::from_ref(&point),
+                start + Duration::from_secs(62),
+            )
+            .unwrap();
+        recorder.flush().unwrap();
+
+        let mut files: Vec<_> = fs::read_dir(&directory)
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name().into_string().unwrap())
+            .collect();
+        files.sort();
+        assert_eq!(
+            files,
+            [
+                "points-20240229T134530.csv",
+                "points-20240229T134532.csv",
+                "points-20240229T134632.csv"
+            ]
+        );
+        assert_eq!(second, directory.join("points-20240229T134532.csv"));
+        let first = fs::read_to_string(directory.join("points-20240229T134530.csv")).unwrap();
+        assert_eq!(first, "ioa,value\n1,-7\n1,-7\n");
+
+        fs::remove_dir_all(&directory).unwrap();
+    }
+
+    #[test]
+    fn test_escape() {
+        let recorder = CsvRecorder::new("unused").delimiter(' ');
+        assert_eq!(recorder.escape("OV IV".to_string()), "\"OV IV\"");
+        assert_eq!(recorder.escape("a\"b".to_string()), "\"a\"\"b\"");
+        assert_eq!(recorder.escape("Good".to_string()), "Good");
+    }
+}
@@ -0,0 +1,576 @@
+//! Monitoring ASDU encoder.
+//!
+//! The inverse of the [`parser`](crate::parser): [`encode_asdu`] serializes
+//! `DataPoint` values into the information objects of a monitoring type,
+//! for simulators, test stations and gateways that forward data.
+//! [`AsduBuilder`] does the same fluently, with sequence (SQ=1) encoding and
+//! a check of the APDU size limit.
+
+use bytes::Bytes;
+
+use crate::error::{Iec104Error, Result};
+use crate::types::{
+    normalized_to_raw, vti_to_u8, Asdu, AsduHeader, Cot, Cp24Time2a, DataPoint, DataValue,
+    InformationObject, Ioa, ParameterValue, Quality, Timestamp, TypeId, MAX_APDU_LENGTH,
+};
+
+/// Maximum number of information objects in an ASDU (7-bit VSQ count).
+pub const MAX_OBJECTS: usize = 127;
+
+/// Encode data points as an ASDU of `type_id`.
+///
+/// Every type [`parse_asdu`](crate::parser::parse_asdu) produces data points
+/// for can be encoded. Each point must carry the value variant of the type
+/// (a `Counter` or `BinaryCounter` for integrated totals); time-tagged types
+/// require a timestamp, which is ignored for the others. CP24Time2a types
+/// take the minutes and milliseconds of a full timestamp.
+///
+/// # Example
+///
+/// ```rust
+/// use voltage_iec104::{encode_asdu, Cot, DataPoint, DataValue, TypeId};
+///
+/// let points = [DataPoint::new(100, DataValue::Float(49.98))];
+/// let asdu = encode_asdu(TypeId::MeasuredFloat, Cot::Spontaneous, 1, &points).unwrap();
+/// assert_eq!(asdu.header.vsq.count, 1);
+/// ```
+pub fn encode_asdu(
+    type_id: TypeId,
+    cot: Cot,
+    common_address: u16,
+    points: &[DataPoint],
+) -> Result<Asdu> {
+    if points.is_empty() || points.len() > MAX_OBJECTS {
+        return Err(Iec104Error::invalid_asdu_static(
+            "An ASDU carries 1 to 127 information objects",
+        ));
+    }
+
+    let mut asdu = Asdu::new(AsduHeader::new(
+        type_id,
+        points.len() as u8,
+        cot,
+        common_address,
+    ));
+    for point in points {
+        asdu.objects.push(InformationObject::new(
+            Ioa::new(point.ioa),
+            Bytes::from(encode_point(type_id, point)?),
+        ));
+    }
+    Ok(asdu)
+}
+
+/// Encode the information elements of one point (without IOA).
+pub fn encode_point(type_id: TypeId, point: &DataPoint) -> Result<Vec<u8>> {
+    let mut out = Vec::with_capacity(type_id.element_size());
+    let quality = point.quality;
+
+    match (type_id, &point.value) {
+        (
+            TypeId::SinglePoint | TypeId::SinglePointTime24 | TypeId::SinglePointTime56,
+            DataValue::Single(value),
+        ) => out.push(quality.to_siq() | *value as u8),
+        (
+            TypeId::DoublePoint | TypeId::DoublePointTime24 | TypeId::DoublePointTime56,
+            DataValue::Double(value),
+        ) => out.push(quality.to_siq() | *value as u8),
+        (
+            TypeId::StepPosition | TypeId::StepPositionTime56,
+            DataValue::StepPosition { value, transient },
+        ) => {
+            if !(-64..=63).contains(value) {
+                return Err(Iec104Error::invalid_asdu_static(
+                    "Step position out of range -64..=63",
+                ));
+            }
+            out.extend_from_slice(&[vti_to_u8(*value, *transient), quality.to_qds()]);
+        }
+        (TypeId::Bitstring32 | TypeId::Bitstring32Time56, DataValue::Bitstring(value)) => {
+            out.extend_from_slice(&value.to_le_bytes());
+            out.push(quality.to_qds());
+        }
+        (
+            TypeId::MeasuredNormalized
+            | TypeId::MeasuredNormalizedTime24
+            | TypeId::MeasuredNormalizedTime56
+            | TypeId::MeasuredNormalizedNoQuality,
+            DataValue::Normalized(value),
+        ) => {
+            if !(-1.0..=1.0).contains(value) {
+                return Err(Iec104Error::invalid_asdu_static(
+                    "Normalized value out of range -1.0..=1.0",
+                ));
+            }
+            out.extend_from_slice(&normalized_to_raw(*value).to_le_bytes());
+            if type_id != TypeId::MeasuredNormalizedNoQuality {
+                out.push(quality.to_qds());
+            }
+        }
+        (
+            TypeId::MeasuredScaled | TypeId::MeasuredScaledTime24 | TypeId::MeasuredScaledTime56,
+            DataValue::Scaled(value),
+        ) => {
+            out.extend_from_slice(&value.to_le_bytes());
+            out.push(quality.to_qds());
+        }
+        (
+            TypeId::MeasuredFloat | TypeId::MeasuredFloatTime24 | TypeId::MeasuredFloatTime56,
+            DataValue::Float(value),
+        ) => {
+            out.extend_from_slice(&value.to_le_bytes());
+            out.push(quality.to_qds());
+        }
+        (TypeId::IntegratedTotals | TypeId::IntegratedTotalsTime56, DataValue::Counter(value)) => {
+            out.extend_from_slice(&value.to_le_bytes());
+            out.push(if quality.invalid() { 0x80 } else { 0 });
+        }
+        (
+            TypeId::IntegratedTotals | TypeId::IntegratedTotalsTime56,
+            DataValue::BinaryCounter {
+                value,
+                sequence,
+                carry,
+                adjusted,
+                invalid,
+            },
+        ) => {
+            out.extend_from_slice(&value.to_le_bytes());
+            out.push(
+                (sequence & 0x1F)
+                    | if *carry { 0x20 } else { 0 }
+                    | if *adjusted { 0x40 } else { 0 }
+                    | if *invalid { 0x80 } else { 0 },
+            );
+        }
+        (TypeId::PackedSinglePoint, DataValue::PackedSinglePoint { status, changes }) => {
+            out.extend_from_slice(&status.to_le_bytes());
+            out.extend_from_slice(&changes.to_le_bytes());
+            out.push(quality.to_qds());
+        }
+        (
+            TypeId::ParameterNormalized | TypeId::ParameterScaled | TypeId::ParameterFloat,
+            DataValue::Parameter { value, qpm },
+        ) if value.type_id() == type_id => {
+            value.validate()?;
+            match *value {
+                ParameterValue::Normalized(value) => {
+                    out.extend_from_slice(&normalized_to_raw(value).to_le_bytes())
+                }
+                ParameterValue::Scaled(value) => out.extend_from_slice(&value.to_le_bytes()),
+                ParameterValue::Float(value) => out.extend_from_slice(&value.to_le_bytes()),
+            }
+            out.push(qpm.as_u8());
+        }
+        _ => {
+            return Err(Iec104Error::InvalidAsdu(
+                format!(
+                    "Cannot encode {:?} as {}",
+                    point.value,
+                    type_id.standard_name()
+                )
+                .into(),
+            ))
+        }
+    }
+
+    // Time tag, sized by what the elements left of the type's element size
+    match type_id.element_size() - out.len() {
+        0 => {}
+        3 => match point.timestamp {
+            Some(Timestamp::Partial(time)) => out.extend_from_slice(&time.to_bytes()),
+            Some(Timestamp::Full(time)) => {
+                let time = Cp24Time2a {
+                    milliseconds: time.milliseconds,
+                    minutes: time.minutes,
+                    invalid: time.invalid,
+                };
+                out.extend_from_slice(&time.to_bytes());
+            }
+            None => return Err(missing_time_tag(type_id)),
+        },
+        _ => match point.timestamp {
+            Some(Timestamp::Full(time)) => out.extend_from_slice(&time.to_bytes()),
+            _ => return Err(missing_time_tag(type_id)),
+        },
+    }
+    Ok(out)
+}
+
+fn missing_time_tag(type_id: TypeId) -> Iec104Error {
+    Iec104Error::InvalidAsdu(format!("{} requires a timestamp", type_id.standard_name()).into())
+}
+
+/// Largest ASDU that fits an APDU (length field minus the control field).
+pub const MAX_ASDU_LENGTH: usize = MAX_APDU_LENGTH - 4;
+
+/// Fluent builder of monitoring ASDUs.
+///
+/// Defaults to COT=3 (spontaneous) and individually addressed objects.
+/// Values are validated by [`build`](Self::build), as for [`encode_asdu`].
+///
+/// # Example
+///
+/// ```rust
+/// use voltage_iec104::{AsduBuilder, Cot, Cp56Time2a, DataValue, Quality, TypeId};
+///
+/// let asdu = AsduBuilder::new(TypeId::MeasuredFloatTime56)
+///     .cot(Cot::Spontaneous)
+///     .ca(1)
+///     .add(100, DataValue::Float(49.98), Quality::Good)
+///     .with_time(Cp56Time2a::now())
+///     .build()
+///     .unwrap();
+/// assert_eq!(asdu.header.vsq.count, 1);
+/// ```
+#[derive(Debug, Clone)]
+pub struct AsduBuilder {
+    type_id: TypeId,
+    cot: Cot,
+    common_address: u16,
+    originator: u8,
+    test: bool,
+    sequence: bool,
+    points: Vec<DataPoint>,
+    orphan_time: bool,
+}
+
+impl AsduBuilder {
+    /// Start an ASDU of `type_id`.
+    pub fn new(type_id: TypeId) -> Self {
+        Self {
+            type_id,
+            cot: Cot::Spontaneous,
+            common_address: 0,
+            originator: 0,
+            test: false,
+            sequence: false,
+            points: Vec::new(),
+            orphan_time: false,
+        }
+    }
+
+    /// Set the cause of transmission.
+    pub fn cot(mut self, cot: Cot) -> Self {
+        self.cot = cot;
+        self
+    }
+
+    /// Set the common address of the ASDU.
+    pub fn ca(mut self, common_address: u16) -> Self {
+        self.common_address = common_address;
+        self
+    }
+
+    /// Set the originator address.
+    pub fn originator(mut self, originator: u8) -> Self {
+        self.originator = originator;
+        self
+    }
+
+    /// Mark the ASDU as a test (T bit).
+    pub fn test(mut self, test: bool) -> Self {
+        self.test = test;
+        self
+    }
+
+    /// Encode the objects as a sequence (SQ=1): one leading IOA followed by
+    /// the elements. Requires consecutive IOAs in the order added.
+    pub fn sequence(mut self, sequence: bool) -> Self {
+        self.sequence = sequence;
+        self
+    }
+
+    /// Add an information object.
+    pub fn add(self, ioa: u32, value: DataValue, quality: Quality) -> Self {
+        self.add_point(DataPoint::with_quality(ioa, value, quality))
+    }
+
+    /// Add an information object from a data point, keeping its timestamp.
+    pub fn add_point(mut self, point: DataPoint) -> Self {
+        self.points.push(point);
+        self
+    }
+
+    /// Set the time tag of the object added last.
+    pub fn with_time(mut self, time: impl Into<Timestamp>) -> Self {
+        match self.points.last_mut() {
+            Some(point) => point.timestamp = Some(time.into()),
+            None => self.orphan_time = true,
+        }
+        self
+    }
+
+    /// Validate and encode the ASDU.
+    ///
+    /// Fails if a value does not fit the type, a time tag is missing, the
+    /// object count is not 1..=127, the IOAs of a sequence are not
+    /// consecutive, or the ASDU exceeds [`MAX_ASDU_LENGTH`].
+    pub fn build(self) -> Result<Asdu> {
+        if self.orphan_time {
+            return Err(Iec104Error::invalid_asdu_static(
+                "Time tag set before any information object",
+            ));
+        }
+        if self.common_address == 0 {
+            return Err(Iec104Error::invalid_asdu_static(
+                "Common address 0 is not used",
+            ));
+        }
+
+        let mut asdu = encode_asdu(self.type_id, self.cot, self.common_address, &self.points)?;
+        asdu.header.originator = self.originator;
+        asdu.header.test = self.test;
+
+        if self.sequence {
+            if !asdu.has_consecutive_ioas() {
+                return Err(Iec104Error::invalid_asdu_static(
+                    "A sequence (SQ=1) requires consecutive IOAs",
+                ));
+            }
+            asdu.header.vsq.sequence = true;
+        }
+
+        let length = asdu.encoded_len();
+        if length > MAX_ASDU_LENGTH {
+            return Err(Iec104Error::InvalidAsdu(
+                format!(
+                    "ASDU of {} bytes exceeds the limit of {}",
+                    length, MAX_ASDU_LENGTH
+                )
+                .into(),
+            ));
+        }
+        Ok(asdu)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::parse_asdu;
+    use crate::types::{Cp56Time2a, DoublePointValue, Quality};
+
+    /// Encode, then parse the ASDU as the receiving side would.
+    fn roundtrip(type_id: TypeId, points: &[DataPoint]) -> Vec<DataPoint> {
+        let asdu = encode_asdu(type_id, Cot::Spontaneous, 1, points).unwrap();
+        parse_asdu(&Asdu::parse(&asdu.encode()).unwrap()).unwrap()
+    }
+
+    fn time() -> Cp56Time2a {
+        Cp56Time2a::from_bytes(&[0x10, 0x27, 30, 12, 0x6F, 6, 24]).unwrap()
+    }
+
+    #[test]
+    fn test_encode_roundtrip() {
+        let quality = Quality::Good.set_blocked(true).set_invalid(true);
+        let cases = [
+            (TypeId::SinglePoint, DataValue::Single(true)),
+            (TypeId::DoublePoint, DataValue::Double(DoublePointValue::On)),
+            (
+                TypeId::StepPosition,
+                DataValue::StepPosition {
+                    value: -5,
+                    transient: true,
+                },
+            ),
+            (TypeId::Bitstring32, DataValue::Bitstring(0xDEAD_BEEF)),
+            (TypeId::MeasuredNormalized, DataValue::Normalized(0.5)),
+            (TypeId::MeasuredScaled, DataValue::Scaled(-1234)),
+            (TypeId::MeasuredFloat, DataValue::Float(49.98)),
+            (
+                TypeId::PackedSinglePoint,
+                DataValue::PackedSinglePoint {
+                    status: 0x00FF,
+                    changes: 0x0101,
+                },
+            ),
+        ];
+        for (type_id, value) in cases {
+            let point = DataPoint::with_quality(7, value, quality);
+            assert_eq!(
+                roundtrip(type_id, std::slice::from_ref(&point)),
+                vec![point],
+                "{}",
+                type_id
+            );
+        }
+    }
+
+    #[test]
+    fn test_encode_time56_roundtrip() {
+        let points: Vec<DataPoint> = (0..3)
+            .map(|i| {
+                let value = DataValue::Float(i as f32);
+                DataPoint::with_timestamp(100 + i, value, Quality::Good, time())
+            })
+            .collect();
+        assert_eq!(roundtrip(TypeId::MeasuredFloatTime56, &points), points);
+
+        let counter = DataValue::BinaryCounter {
+            value: -7,
+            sequence: 3,
+            carry: true,
+            adjusted: false,
+            invalid: true,
+        };
+        let point = DataPoint::with_timestamp(1, counter, Quality::Invalid, time());
+        assert_eq!(
+            roundtrip(TypeId::IntegratedTotalsTime56, std::slice::from_ref(&point)),
+            vec![point]
+        );
+    }
+
+    #[test]
+    fn test_encode_time24_from_full_time() {
+        let point = DataPoint::with_timestamp(1, DataValue::Single(true), Quality::Good, time());
+        let parsed = roundtrip(TypeId::SinglePointTime24, &[point]);
+        let Some(Timestamp::Partial(tag)) = parsed[0].timestamp else {
+            panic!("expected a CP24Time2a tag");
+        };
+        assert_eq!((tag.minutes, tag.milliseconds), (30, 10_000));
+    }
+
+    #[test]
+    fn test_encode_measured_time24() {
+        let tag = Cp24Time2a {
+            milliseconds: 1_500,
+            minutes: 59,
+            invalid: false,
+        };
+        let cases = [
+            (TypeId::MeasuredNormalizedTime24, DataValue::Normalized(-0.5)),
+            (TypeId::MeasuredScaledTime24, DataValue::Scaled(321)),
+            (TypeId::MeasuredFloatTime24, DataValue::Float(1.25)),
+        ];
+        for (type_id, value) in cases {
+            let mut point = DataPoint::with_quality(4, value, Quality::Good);
+            point.timestamp = Some(Timestamp::Partial(tag));
+            assert_eq!(roundtrip(type_id, std::slice::from_ref(&point)), vec![point]);
+        }
+    }
+
+    #[test]
+    fn test_encode_counter_without_flags() {
+        let point = DataPoint::new(9, DataValue::Counter(123_456));
+        let asdu = encode_asdu(
+            TypeId::IntegratedTotals,
+            Cot::RequestedByGeneralCounter,
+            1,
+            &[point],
+        )
+        .unwrap();
+        assert_eq!(&asdu.objects[0].data[..], &[0x40, 0xE2, 0x01, 0x00, 0x00]);
+    }
+
+    #[test]
+    fn test_encode_step_position_wire_byte() {
+        for (value, transient, vti) in [(-1, false, 0x7F), (-64, false, 0x40), (5, true, 0x85)] {
+            let point = DataPoint::new(1, DataValue::StepPosition { value, transient });
+            let asdu = encode_asdu(TypeId::StepPosition, Cot::Spontaneous, 1, &[point]).unwrap();
+            assert_eq!(&asdu.objects[0].data[..], &[vti, 0x00]);
+        }
+    }
+
+    #[test]
+    fn test_builder() {
+        let asdu = AsduBuilder::new(TypeId::MeasuredScaledTime56)
+            .cot(Cot::Periodic)
+            .ca(7)
+            .originator(3)
+            .add(10, DataValue::Scaled(100), Quality::Good)
+            .with_time(time())
+            .add(20, DataValue::Scaled(-100), Quality::Invalid)
+            .with_time(time())
+            .build()
+            .unwrap();
+        assert_eq!(asdu.header.cot, Cot::Periodic);
+        assert_eq!(asdu.header.common_address, 7);
+        assert_eq!(asdu.header.originator, 3);
+
+        let received = Asdu::parse(&asdu.encode()).unwrap();
+        let points = parse_asdu(&received).unwrap();
+        assert_eq!(points[1].ioa, 20);
+        assert_eq!(points[1].value, DataValue::Scaled(-100));
+        assert!(points[1].quality.invalid());
+    }
+
+    #[test]
+    fn test_builder_sequence() {
+        let builder = (0..4).fold(
+            AsduBuilder::new(TypeId::SinglePoint).ca(1).sequence(true),
+            |builder, i| builder.add(0x100 + i, DataValue::Single(i % 2 == 0), Quality::Good),
+        );
+        let asdu = builder.build().unwrap();
+        assert!(asdu.header.vsq.sequence);
+        assert_eq!(asdu.encoded_len(), 6 + 3 + 4);
+
+        let received = Asdu::parse(&asdu.encode()).unwrap();
+        let points = parse_asdu(&received).unwrap();
+        let ioas: Vec<u32> = points.iter().map(|p| p.ioa).collect();
+        assert_eq!(ioas, vec![0x100, 0x101, 0x102, 0x103]);
+        assert_eq!(points[2].value, DataValue::Single(true));
+
+        let gap = AsduBuilder::new(TypeId::SinglePoint)
+            .ca(1)
+            .sequence(true)
+            .add(1, DataValue::Single(true), Quality::Good)
+            .add(3, DataValue::Single(true), Quality::Good);
+        assert!(gap.build().is_err());
+    }
+
+    #[test]
+    fn test_builder_validation() {
+        let no_ca =
+            AsduBuilder::new(TypeId::SinglePoint).add(1, DataValue::Single(true), Quality::Good);
+        assert!(no_ca.build().is_err());
+
+        let orphan = AsduBuilder::new(TypeId::SinglePointTime56)
+            .ca(1)
+            .with_time(time())
+            .add(1, DataValue::Single(true), Quality::Good);
+        assert!(orphan.build().is_err());
+
+        // 31 floats with time tags: 6 + 31 * (3 + 12) = 471 bytes
+        let oversized = (0..31).fold(
+            AsduBuilder::new(TypeId::MeasuredFloatTime56).ca(1),
+            |builder, i| {
+                builder
+                    .add(i, DataValue::Float(0.0), Quality::Good)
+                    .with_time(time())
+            },
+        );
+        assert!(matches!(
+            oversized.build(),
+            Err(Iec104Error::InvalidAsdu(_))
+        ));
+
+        // The same values fit as a sequence of plain floats: 6 + 3 + 31 * 5
+        let sequence = (0..31).fold(
+            AsduBuilder::new(TypeId::MeasuredFloat).ca(1).sequence(true),
+            |builder, i| builder.add(i, DataValue::Float(0.0), Quality::Good),
+        );
+        assert_eq!(sequence.build().unwrap().encoded_len(), 164);
+    }
+
+    #[test]
+    fn test_encode_rejects_invalid_points() {
+        let float = DataPoint::new(1, DataValue::Float(1.0));
+        assert!(encode_asdu(
+            TypeId::SinglePoint,
+            Cot::Spontaneous,
+            1,
+            std::slice::from_ref(&float)
+        )
+        .is_err());
+        assert!(encode_asdu(TypeId::MeasuredFloatTime56, Cot::Spontaneous, 1, &[float]).is_err());
+        assert!(encode_asdu(TypeId::SinglePoint, Cot::Spontaneous, 1, &[]).is_err());
+
+        let out_of_range = DataPoint::new(1, DataValue::Normalized(1.5));
+        assert!(encode_point(TypeId::MeasuredNormalized, &out_of_range).is_err());
+
+        let point = DataPoint::new(1, DataValue::Single(false));
+        let too_many = vec![point; MAX_OBJECTS + 1];
+        assert!(encode_asdu(TypeId::SinglePoint, Cot::Spontaneous, 1, &too_many).is_err());
+    }
+}
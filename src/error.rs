@@ -3,6 +3,8 @@
 use std::borrow::Cow;
 use thiserror::Error;
 
+use crate::types::{Cot, TypeId};
+
 /// Result type alias for IEC 104 operations.
 pub type Result<T> = std::result::Result<T, Iec104Error>;
 
@@ -27,6 +29,14 @@ pub enum Iec104Error {
     #[error("I/O error: {0}")]
     Io(#[from] std::io::Error),
 
+    /// Invalid TLS configuration (unreadable certificate or key)
+    #[error("TLS configuration error: {0}")]
+    TlsConfig(Cow<'static, str>),
+
+    /// TLS handshake failed (e.g. certificate rejected by either side)
+    #[error("TLS handshake failed: {0}")]
+    TlsHandshake(Cow<'static, str>),
+
     /// Protocol error
     #[error("Protocol error: {0}")]
     Protocol(Cow<'static, str>),
@@ -63,10 +73,44 @@ pub enum Iec104Error {
     #[error("Too many unconfirmed frames (K={0})")]
     TooManyUnconfirmed(u16),
 
+    /// Command rejected by the remote station (negative confirmation or unknown
+    /// type, cause, common address or IOA)
+    #[error("Command {type_id} for IOA {ioa} rejected (COT={cot})")]
+    CommandRejected {
+        /// Type of the rejected command
+        type_id: TypeId,
+        /// Information object address of the command
+        ioa: u32,
+        /// Cause of transmission of the rejection
+        cot: Cot,
+    },
+
+    /// Command not confirmed in time
+    #[error("Command {type_id} for IOA {ioa} timed out")]
+    CommandTimeout {
+        /// Type of the command
+        type_id: TypeId,
+        /// Information object address of the command
+        ioa: u32,
+    },
+
+    /// File transfer refused by the station or failed verification
+    #[error("File transfer of file {file} failed: {reason}")]
+    FileTransfer {
+        /// Name of file (NOF)
+        file: u16,
+        /// What went wrong
+        reason: Cow<'static, str>,
+    },
+
     /// Channel closed
     #[error("Channel closed")]
     ChannelClosed,
 
+    /// Incomplete frame (number of additional bytes needed)
+    #[error("Incomplete frame: {0} more bytes needed")]
+    Incomplete(usize),
+
     /// Codec error
     #[error("Codec error: {0}")]
     Codec(Cow<'static, str>),
@@ -199,7 +243,21 @@ mod tests {
             Iec104Error::T2Timeout,
             Iec104Error::T3Timeout,
             Iec104Error::TooManyUnconfirmed(100),
+            Iec104Error::CommandRejected {
+                type_id: TypeId::SingleCommand,
+                ioa: 100,
+                cot: Cot::UnknownIoa,
+            },
+            Iec104Error::CommandTimeout {
+                type_id: TypeId::SingleCommand,
+                ioa: 100,
+            },
+            Iec104Error::FileTransfer {
+                file: 1,
+                reason: Cow::Borrowed("test"),
+            },
             Iec104Error::ChannelClosed,
+            Iec104Error::Incomplete(4),
             Iec104Error::Codec(Cow::Borrowed("test")),
             Iec104Error::Internal(Cow::Borrowed("test")),
         ];
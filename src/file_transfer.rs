@@ -0,0 +1,118 @@
+//! Client side of the file transfer procedures.
+//!
+//! [`Iec104Client::file_directory`](crate::Iec104Client::file_directory)
+//! calls the directory of a station, and
+//! [`Iec104Client::download_file`](crate::Iec104Client::download_file) drives
+//! the select / call file / call section / acknowledge sequence, verifying
+//! the checksum of every section and of the whole file.
+
+use std::collections::VecDeque;
+
+use crate::error::Iec104Error;
+use crate::types::{Asdu, Cp56Time2a, FileObject, FileStatus};
+
+/// Directory entry reported by a station (F_DR_TA_1).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FileInfo {
+    /// Information object address of the file
+    pub ioa: u32,
+    /// Name of file (NOF)
+    pub file: u16,
+    /// Length of file in octets
+    pub length: u32,
+    /// Status of file
+    pub status: FileStatus,
+    /// Creation time of the file
+    pub time: Cp56Time2a,
+}
+
+/// Number of times a section failing its checksum is requested again.
+pub(crate) const SECTION_RETRIES: u8 = 2;
+
+/// File transfer objects captured from received data while a file
+/// procedure waits for the station.
+pub(crate) struct FileCapture {
+    common_address: u16,
+    ioa: Option<u32>,
+    pub(crate) objects: VecDeque<(u32, FileObject)>,
+    pub(crate) rejected: Option<Iec104Error>,
+}
+
+impl FileCapture {
+    /// Capture objects from `common_address`, only for `ioa` if given.
+    pub(crate) fn new(common_address: u16, ioa: Option<u32>) -> Self {
+        Self {
+            common_address,
+            ioa,
+            objects: VecDeque::new(),
+            rejected: None,
+        }
+    }
+
+    pub(crate) fn collect(&mut self, asdu: &Asdu) {
+        let header = &asdu.header;
+        if !header.type_id.is_file_transfer() || header.common_address != self.common_address {
+            return;
+        }
+        let Ok(objects) = FileObject::parse_asdu(asdu) else {
+            return;
+        };
+        for (ioa, object) in objects {
+            if self.ioa.is_some_and(|wanted| wanted != ioa) {
+                continue;
+            }
+            if header.negative || header.cot.is_negative() {
+                self.rejected.get_or_insert(Iec104Error::CommandRejected {
+                    type_id: header.type_id,
+                    ioa,
+                    cot: header.cot,
+                });
+            } else {
+                self.objects.push_back((ioa, object));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::{AsduHeader, CallAction, Cot, FileError, TypeId};
+
+    /// The ASDU as the client receives it, with unparsed objects.
+    fn received(asdu: Asdu) -> Asdu {
+        Asdu::parse(&asdu.encode()).unwrap()
+    }
+
+    #[test]
+    fn test_capture_filters_objects() {
+        let call = FileObject::Call {
+            file: 1,
+            section: 0,
+            action: CallAction::SelectFile,
+            error: FileError::None,
+        };
+        let mut capture = FileCapture::new(1, Some(0x10));
+
+        for (common_address, ioa) in [(1, 0x10), (1, 0x11), (2, 0x10)] {
+            let asdu = Asdu::file_transfer(common_address, ioa, Cot::FileTransfer, &call);
+            capture.collect(&received(asdu));
+        }
+        let mut other = Asdu::new(AsduHeader::new(TypeId::SinglePoint, 1, Cot::Spontaneous, 1));
+        other.raw_data = bytes::Bytes::from_static(&[0x10, 0x00, 0x00, 0x01]);
+        capture.collect(&other);
+        assert_eq!(capture.objects.len(), 1);
+        assert!(capture.rejected.is_none());
+
+        let request = Asdu::file_transfer(1, 0x10, Cot::FileTransfer, &call);
+        capture.collect(&received(request.mirror(Cot::UnknownIoa, true)));
+        assert!(matches!(
+            capture.rejected,
+            Some(Iec104Error::CommandRejected {
+                type_id: TypeId::FileCall,
+                ioa: 0x10,
+                ..
+            })
+        ));
+    }
+}
@@ -0,0 +1,241 @@
+//! Event filters for per-consumer subscriptions, and deadbands.
+//!
+//! An [`EventFilter`] is evaluated by the client before an event is queued
+//! for a subscriber created with
+//! [`Iec104Client::subscribe_filtered`](crate::Iec104Client::subscribe_filtered),
+//! so consumers interested in a few points never receive the rest.
+//!
+//! A [`Deadband`] configured on the client suppresses measured value
+//! updates that changed too little to matter, for every consumer.
+
+use std::collections::HashMap;
+use std::ops::RangeInclusive;
+
+use crate::client::Iec104Event;
+use crate::types::{AsduHeader, DataPoint, DataValue, Quality, TypeId};
+
+/// Selects the events delivered to a filtered subscriber.
+///
+/// Each criterion left empty matches everything; criteria combine with AND.
+/// Data updates are narrowed to the matching points and dropped when none
+/// remain. Events not caused by a received ASDU (connection state, errors)
+/// are always delivered.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct EventFilter {
+    common_addresses: Vec<u16>,
+    ioa_ranges: Vec<RangeInclusive<u32>>,
+    type_ids: Vec<TypeId>,
+}
+
+impl EventFilter {
+    /// Create a filter that matches every event.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Also accept ASDUs from this common address.
+    pub fn common_address(mut self, common_address: u16) -> Self {
+        self.common_addresses.push(common_address);
+        self
+    }
+
+    /// Also accept points whose IOA lies in `range`.
+    pub fn ioa_range(mut self, range: RangeInclusive<u32>) -> Self {
+        self.ioa_ranges.push(range);
+        self
+    }
+
+    /// Also accept ASDUs of this type.
+    pub fn type_id(mut self, type_id: TypeId) -> Self {
+        self.type_ids.push(type_id);
+        self
+    }
+
+    fn accepts_ioa(&self, ioa: u32) -> bool {
+        self.ioa_ranges.is_empty() || self.ioa_ranges.iter().any(|range| range.contains(&ioa))
+    }
+
+    /// The part of `event` this filter lets through, if any.
+    ///
+    /// `header` is the header of the ASDU the event was produced from.
+    pub(crate) fn apply(
+        &self,
+        event: &Iec104Event,
+        header: Option<&AsduHeader>,
+    ) -> Option<Iec104Event> {
+        let Some(header) = header else {
+            return Some(event.clone());
+        };
+        if !self.common_addresses.is_empty()
+            && !self.common_addresses.contains(&header.common_address)
+        {
+            return None;
+        }
+        if !self.type_ids.is_empty() && !self.type_ids.contains(&header.type_id) {
+            return None;
+        }
+
+        match event {
+            Iec104Event::DataUpdate(points) => {
+                let points: Vec<_> = points
+                    .iter()
+                    .filter(|point| self.accepts_ioa(point.ioa))
+                    .cloned()
+                    .collect();
+                (!points.is_empty()).then_some(Iec104Event::DataUpdate(points))
+            }
+            Iec104Event::CommandConfirm { ioa, .. } if !self.accepts_ioa(*ioa) => None,
+            _ => Some(event.clone()),
+        }
+    }
+}
+
+/// Minimum change of a measured value worth reporting.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Deadband {
+    /// Change in engineering units (raw value for scaled measurements)
+    Absolute(f64),
+    /// Change in percent of the last reported value
+    Percent(f64),
+}
+
+impl Deadband {
+    fn exceeded(self, reported: f64, value: f64) -> bool {
+        let change = (value - reported).abs();
+        match self {
+            Self::Absolute(threshold) => change >= threshold,
+            Self::Percent(percent) => change >= reported.abs() * percent / 100.0,
+        }
+    }
+}
+
+/// Deadband state: the last reported value of every measured point.
+#[derive(Debug, Default)]
+pub(crate) struct DeadbandFilter {
+    reported: HashMap<(u16, u32), (f64, Quality)>,
+}
+
+impl DeadbandFilter {
+    /// Drop the measured values of `points` that stay within their deadband.
+    ///
+    /// `deadband` gives the deadband of an IOA, if any. Quality changes and
+    /// first values are always kept.
+    pub(crate) fn retain(
+        &mut self,
+        common_address: u16,
+        points: &mut Vec<DataPoint>,
+        deadband: impl Fn(u32) -> Option<Deadband>,
+    ) {
+        points.retain(|point| {
+            let value = match point.value {
+                DataValue::Normalized(v) | DataValue::Float(v) => f64::from(v),
+                DataValue::Scaled(v) => f64::from(v),
+                _ => return true,
+            };
+            let Some(deadband) = deadband(point.ioa) else {
+                return true;
+            };
+            let key = (common_address, point.ioa);
+            let report = match self.reported.get(&key) {
+                Some(&(reported, quality)) => {
+                    quality != point.quality || deadband.exceeded(reported, value)
+                }
+                None => true,
+            };
+            if report {
+                self.reported.insert(key, (value, point.quality));
+            }
+            report
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::{Cot, DataPoint, DataValue};
+
+    fn update(ioas: &[u32]) -> Iec104Event {
+        Iec104Event::DataUpdate(
+            ioas.iter()
+                .map(|&ioa| DataPoint::new(ioa, DataValue::Single(true)))
+                .collect(),
+        )
+    }
+
+    #[test]
+    fn test_filter_narrows_data_updates() {
+        let filter = EventFilter::new().ioa_range(100..=199).ioa_range(500..=500);
+        let header = AsduHeader::new(TypeId::SinglePoint, 3, Cot::Spontaneous, 1);
+
+        match filter.apply(&update(&[99, 150, 500]), Some(&header)) {
+            Some(Iec104Event::DataUpdate(points)) => {
+                let ioas: Vec<_> = points.iter().map(|p| p.ioa).collect();
+                assert_eq!(ioas, vec![150, 500]);
+            }
+            other => panic!("unexpected {other:?}"),
+        }
+        assert!(filter.apply(&update(&[1, 2]), Some(&header)).is_none());
+    }
+
+    #[test]
+    fn test_deadband_suppresses_small_changes() {
+        let mut filter = DeadbandFilter::default();
+        let mut run = |ioa: u32, value: f32| {
+            let mut points = vec![DataPoint::new(ioa, DataValue::Float(value))];
+            filter.retain(1, &mut points, |ioa| match ioa {
+                1 => Some(Deadband::Absolute(0.5)),
+                2 => Some(Deadband::Percent(10.0)),
+                _ => None,
+            });
+            !points.is_empty()
+        };
+
+        assert!(run(1, 10.0));
+        assert!(!run(1, 10.4));
+        assert!(run(1, 10.5));
+        // Measured against the last reported value, not the last received
+        assert!(!run(1, 10.9));
+        assert!(run(1, 11.0));
+
+        assert!(run(2, 100.0));
+        assert!(!run(2, 109.0));
+        assert!(run(2, 89.0));
+
+        assert!(run(3, 1.0));
+        assert!(run(3, 1.0));
+    }
+
+    #[test]
+    fn test_deadband_reports_quality_change() {
+        let mut filter = DeadbandFilter::default();
+        let deadband = |_| Some(Deadband::Absolute(100.0));
+        let mut points = vec![DataPoint::new(1, DataValue::Scaled(5))];
+        filter.retain(1, &mut points, deadband);
+        assert_eq!(points.len(), 1);
+
+        let mut point = DataPoint::new(1, DataValue::Scaled(6));
+        point.quality = Quality::Invalid;
+        let mut points = vec![point, DataPoint::new(9, DataValue::Single(true))];
+        filter.retain(1, &mut points, deadband);
+        assert_eq!(points.len(), 2);
+    }
+
+    #[test]
+    fn test_filter_by_header() {
+        let filter = EventFilter::new()
+            .common_address(2)
+            .type_id(TypeId::MeasuredFloat);
+        let float = AsduHeader::new(TypeId::MeasuredFloat, 1, Cot::Spontaneous, 2);
+        let single = AsduHeader::new(TypeId::SinglePoint, 1, Cot::Spontaneous, 2);
+        let other_station = AsduHeader::new(TypeId::MeasuredFloat, 1, Cot::Spontaneous, 1);
+
+        assert!(filter.apply(&update(&[1]), Some(&float)).is_some());
+        assert!(filter.apply(&update(&[1]), Some(&single)).is_none());
+        assert!(filter.apply(&update(&[1]), Some(&other_station)).is_none());
+
+        // Link events carry no ASDU and always pass
+        assert!(filter.apply(&Iec104Event::Connected, None).is_some());
+    }
+}
@@ -0,0 +1,250 @@
+//! Routing of confirmations in gateways serving several downstream masters.
+//!
+//! A gateway forwards the commands of its downstream masters over a single
+//! upstream connection. The [`OriginatorRouter`] gives each downstream
+//! connection an originator address of its own, stamps it on the commands
+//! forwarded upstream and uses it to route the confirmations (ACTCON,
+//! DEACTCON, ACTTERM and negative confirmations) back to the connection that
+//! issued the command, with the originator address that master used restored.
+//!
+//! ```rust,ignore
+//! let mut router = OriginatorRouter::new();
+//! let downstream = router.attach(tx)?;
+//!
+//! // Command received from the downstream master
+//! router.forward(downstream, &mut asdu)?;
+//! upstream.forward_command(asdu).await?;
+//!
+//! // ASDU received upstream, e.g. from a frame tap
+//! if let Some(tx) = router.route(&mut asdu) {
+//!     tx.send(asdu).await?;
+//! }
+//! ```
+
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::ops::RangeInclusive;
+
+use crate::command::first_ioa;
+use crate::error::{Iec104Error, Result};
+use crate::types::{Asdu, Cot, TypeId};
+
+/// Command identity, as echoed in its confirmations.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+struct CommandKey {
+    originator: u8,
+    type_id: TypeId,
+    common_address: u16,
+    ioa: u32,
+}
+
+impl CommandKey {
+    fn of(asdu: &Asdu) -> Self {
+        Self {
+            originator: asdu.header.originator,
+            type_id: asdu.header.type_id,
+            common_address: asdu.header.common_address,
+            ioa: first_ioa(asdu).unwrap_or(0),
+        }
+    }
+}
+
+/// Originator address routing table of a gateway.
+///
+/// `D` is whatever reaches a downstream connection, such as the sending
+/// half of a channel.
+#[derive(Debug)]
+pub struct OriginatorRouter<D> {
+    addresses: RangeInclusive<u8>,
+    routes: HashMap<u8, D>,
+    /// Forwarded commands awaiting confirmation, with the originator
+    /// address the downstream master sent them with
+    pending: HashMap<CommandKey, u8>,
+}
+
+impl<D> Default for OriginatorRouter<D> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<D> OriginatorRouter<D> {
+    /// Create a router assigning originator addresses 1-255.
+    ///
+    /// Address 0 ("no originator") is left to stations that do not route.
+    pub fn new() -> Self {
+        Self::with_addresses(1..=255)
+    }
+
+    /// Create a router assigning originator addresses from `addresses`.
+    pub fn with_addresses(addresses: RangeInclusive<u8>) -> Self {
+        Self {
+            addresses,
+            routes: HashMap::new(),
+            pending: HashMap::new(),
+        }
+    }
+
+    /// Register a downstream connection and return its originator address.
+    ///
+    /// Fails when every address in the range is taken.
+    pub fn attach(&mut self, downstream: D) -> Result<u8> {
+        let originator = self
+            .addresses
+            .clone()
+            .find(|originator| !self.routes.contains_key(originator))
+            .ok_or(Iec104Error::Protocol(Cow::Borrowed("No free originator address")))?;
+        self.routes.insert(originator, downstream);
+        Ok(originator)
+    }
+
+    /// Remove a downstream connection and forget its pending commands.
+    ///
+    /// Confirmations still arriving for them are no longer routed.
+    pub fn detach(&mut self, originator: u8) -> Option<D> {
+        self.pending.retain(|key, _| key.originator != originator);
+        self.routes.remove(&originator)
+    }
+
+    /// Downstream connection with this originator address.
+    pub fn downstream(&self, originator: u8) -> Option<&D> {
+        self.routes.get(&originator)
+    }
+
+    /// Number of forwarded commands awaiting confirmation or termination.
+    pub fn pending(&self) -> usize {
+        self.pending.len()
+    }
+
+    /// Prepare a command of the downstream connection `originator` for
+    /// forwarding upstream, stamping its originator address.
+    pub fn forward(&mut self, originator: u8, asdu: &mut Asdu) -> Result<()> {
+        if !self.routes.contains_key(&originator) {
+            return Err(Iec104Error::Protocol(Cow::Borrowed(
+                "Unknown downstream originator address",
+            )));
+        }
+        let downstream_originator = asdu.header.originator;
+        asdu.header.originator = originator;
+        self.pending.insert(CommandKey::of(asdu), downstream_originator);
+        Ok(())
+    }
+
+    /// Route an ASDU received upstream to the downstream connection whose
+    /// command it confirms or terminates.
+    ///
+    /// The originator address is restored to the one the downstream master
+    /// used. A command is forgotten once terminated, deactivated or
+    /// rejected; a positive ACTCON keeps it for the ACTTERM that may follow.
+    /// Returns `None`, leaving the ASDU untouched, for anything else, such
+    /// as monitoring data.
+    pub fn route(&mut self, asdu: &mut Asdu) -> Option<&D> {
+        let cot = asdu.header.cot;
+        if !cot.is_positive() && !cot.is_negative() {
+            return None;
+        }
+        let key = CommandKey::of(asdu);
+        let downstream_originator = *self.pending.get(&key)?;
+        let done = asdu.header.negative || cot != Cot::ActivationConfirm;
+        if done {
+            self.pending.remove(&key);
+        }
+        asdu.header.originator = downstream_originator;
+        self.routes.get(&key.originator)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::AsduHeader;
+    use bytes::Bytes;
+
+    fn command(originator: u8, ioa: u8) -> Asdu {
+        let mut asdu =
+            Asdu::new(AsduHeader::new(TypeId::SingleCommand, 1, Cot::Activation, 1));
+        asdu.header.originator = originator;
+        asdu.raw_data = Bytes::copy_from_slice(&[ioa, 0, 0, 0x01]);
+        asdu
+    }
+
+    #[test]
+    fn test_attach_assigns_free_addresses() {
+        let mut router = OriginatorRouter::with_addresses(3..=4);
+        assert_eq!(router.attach("a").unwrap(), 3);
+        assert_eq!(router.attach("b").unwrap(), 4);
+        assert!(router.attach("c").is_err());
+
+        assert_eq!(router.detach(3), Some("a"));
+        assert_eq!(router.attach("c").unwrap(), 3);
+        assert_eq!(router.downstream(3), Some(&"c"));
+    }
+
+    #[test]
+    fn test_confirmations_routed_by_originator() {
+        let mut router = OriginatorRouter::new();
+        let a = router.attach("a").unwrap();
+        let b = router.attach("b").unwrap();
+
+        // Both masters command the same point with their own originator 0
+        let mut from_a = command(0, 100);
+        let mut from_b = command(0, 100);
+        router.forward(a, &mut from_a).unwrap();
+        router.forward(b, &mut from_b).unwrap();
+        assert_eq!(from_a.header.originator, a);
+        assert_eq!(from_b.header.originator, b);
+        assert_eq!(router.pending(), 2);
+
+        let mut confirm = from_b.mirror(Cot::ActivationConfirm, false);
+        assert_eq!(router.route(&mut confirm), Some(&"b"));
+        assert_eq!(confirm.header.originator, 0);
+
+        let mut terminate = from_b.mirror(Cot::ActivationTermination, false);
+        assert_eq!(router.route(&mut terminate), Some(&"b"));
+        assert_eq!(router.pending(), 1);
+
+        let mut rejected = from_a.mirror(Cot::UnknownIoa, true);
+        assert_eq!(router.route(&mut rejected), Some(&"a"));
+        assert_eq!(router.pending(), 0);
+
+        // Nothing left to confirm
+        let mut late = from_a.mirror(Cot::ActivationConfirm, false);
+        assert_eq!(router.route(&mut late), None);
+        assert_eq!(late.header.originator, a);
+    }
+
+    #[test]
+    fn test_monitoring_data_not_routed() {
+        let mut router = OriginatorRouter::new();
+        let a = router.attach("a").unwrap();
+        let mut forwarded = command(0, 100);
+        router.forward(a, &mut forwarded).unwrap();
+
+        let mut data = Asdu::new(AsduHeader::new(TypeId::SinglePoint, 1, Cot::Spontaneous, 1));
+        data.header.originator = a;
+        data.raw_data = Bytes::from_static(&[100, 0, 0, 0x01]);
+        assert_eq!(router.route(&mut data), None);
+        assert_eq!(router.pending(), 1);
+    }
+
+    #[test]
+    fn test_forward_unknown_originator_fails() {
+        let mut router = OriginatorRouter::<&str>::new();
+        let mut asdu = command(0, 100);
+        assert!(router.forward(9, &mut asdu).is_err());
+        assert_eq!(asdu.header.originator, 0);
+    }
+
+    #[test]
+    fn test_detach_forgets_pending() {
+        let mut router = OriginatorRouter::new();
+        let a = router.attach("a").unwrap();
+        let mut forwarded = command(0, 100);
+        router.forward(a, &mut forwarded).unwrap();
+
+        router.detach(a);
+        assert_eq!(router.pending(), 0);
+        let mut confirm = forwarded.mirror(Cot::ActivationConfirm, false);
+        assert_eq!(router.route(&mut confirm), None);
+    }
+}
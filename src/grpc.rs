@@ -0,0 +1,574 @@
+//! gRPC facade over a spawned client, built on [tonic](https://docs.rs/tonic).
+//!
+//! The interface is defined in `proto/iec104.proto` (package
+//! `voltage_iec104.v1`) and its Rust types are in [`proto`], so backends in
+//! any language can generate a client from the same file. [`GrpcService`]
+//! implements it on a [`ClientHandle`]:
+//!
+//! - `GetStatus` reports the connection state, session and link counters.
+//! - `GetPoints` returns the cached points, which requires
+//!   [`ClientConfig::point_cache`](crate::ClientConfig::point_cache).
+//! - `StreamUpdates` streams the points of the requested stations as they
+//!   arrive. A consumer that reads too slowly never delays the IEC 104
+//!   connection: after [`QUEUE_LENGTH`] waiting updates newer ones are
+//!   dropped and counted in the `lagged` field of the next update.
+//! - `SendCommand` issues a command and returns once the station confirmed.
+//!   Errors map to status codes: `INVALID_ARGUMENT` for a malformed request,
+//!   `PERMISSION_DENIED` for a station not added with
+//!   [`GrpcService::station`], `ABORTED` for a negative confirmation,
+//!   `DEADLINE_EXCEEDED` without confirmation in time and `UNAVAILABLE`
+//!   while disconnected.
+//!
+//! # Example
+//!
+//! ```rust,ignore
+//! let (handle, _task) = client.spawn();
+//! tonic::transport::Server::builder()
+//!     .add_service(GrpcService::new(handle).station(1).into_server())
+//!     .serve("0.0.0.0:50051".parse()?)
+//!     .await?;
+//! ```
+
+use std::pin::Pin;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+use futures::Stream;
+use tokio::sync::mpsc;
+use tonic::{Request, Response, Status};
+
+use crate::client::{ConnectionState, Iec104Event};
+use crate::command::{Command, StepCommand};
+use crate::error::Iec104Error;
+use crate::filter::EventFilter;
+use crate::handle::ClientHandle;
+use crate::types::{
+    CommandQualifier, DataPoint, DataValue, DoubleCommandState, DoublePointValue, ParameterValue,
+    Qos, Timestamp,
+};
+
+/// Types and service traits generated from `proto/iec104.proto`.
+#[allow(missing_docs, clippy::all)]
+pub mod proto {
+    tonic::include_proto!("voltage_iec104.v1");
+}
+
+use proto::iec104_server::{Iec104, Iec104Server};
+use proto::point::Value as PointValue;
+
+/// Default time to wait for the confirmation of a command.
+pub const DEFAULT_COMMAND_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Updates queued for a slow `StreamUpdates` consumer before dropping.
+pub const QUEUE_LENGTH: usize = 256;
+
+/// Implementation of the `Iec104` gRPC service on a spawned client.
+///
+/// Points of every station can be read; commands are only accepted for the
+/// stations added with [`station`](Self::station).
+#[derive(Debug, Clone)]
+pub struct GrpcService {
+    handle: ClientHandle,
+    stations: Vec<u16>,
+    command_timeout: Duration,
+}
+
+impl GrpcService {
+    /// Create a service that accepts no commands yet.
+    pub fn new(handle: ClientHandle) -> Self {
+        Self {
+            handle,
+            stations: Vec::new(),
+            command_timeout: DEFAULT_COMMAND_TIMEOUT,
+        }
+    }
+
+    /// Accept commands for a station.
+    pub fn station(mut self, common_address: u16) -> Self {
+        self.stations.push(common_address);
+        self
+    }
+
+    /// Time to wait for a station to confirm a command.
+    pub fn command_timeout(mut self, timeout: Duration) -> Self {
+        self.command_timeout = timeout;
+        self
+    }
+
+    /// Wrap the service for a tonic server.
+    pub fn into_server(self) -> Iec104Server<Self> {
+        Iec104Server::new(self)
+    }
+}
+
+type UpdateStream = Pin<Box<dyn Stream<Item = Result<proto::PointUpdate, Status>> + Send>>;
+
+#[tonic::async_trait]
+impl Iec104 for GrpcService {
+    async fn get_status(
+        &self,
+        _request: Request<proto::StatusRequest>,
+    ) -> Result<Response<proto::Status>, Status> {
+        let state = match self.handle.state().await.map_err(status)? {
+            ConnectionState::Disconnected => proto::ConnectionState::Disconnected,
+            ConnectionState::Connected => proto::ConnectionState::Connected,
+            ConnectionState::Active => proto::ConnectionState::Active,
+            ConnectionState::Stopping => proto::ConnectionState::Stopping,
+        };
+        let session = self.handle.session_info().await.map_err(status)?;
+        let stats = self.handle.stats().await.map_err(status)?;
+        Ok(Response::new(proto::Status {
+            state: state.into(),
+            session: session.map(|session| proto::Session {
+                peer_address: session.peer_address.to_string(),
+                local_address: session.local_address.to_string(),
+                k: session.k.into(),
+                w: session.w.into(),
+                t1_ms: millis(session.t1_timeout),
+                t2_ms: millis(session.t2_timeout),
+                t3_ms: millis(session.t3_timeout),
+            }),
+            i_frames_sent: stats.i_frames_sent,
+            i_frames_received: stats.i_frames_received,
+            sequence_errors: stats.sequence_errors,
+            t1_timeouts: stats.t1_timeouts,
+            unacknowledged_sends: stats.unacknowledged_sends.into(),
+            unacknowledged_receives: stats.unacknowledged_receives.into(),
+        }))
+    }
+
+    async fn get_points(
+        &self,
+        request: Request<proto::PointsRequest>,
+    ) -> Result<Response<proto::PointsReply>, Status> {
+        let stations = request.into_inner().common_addresses;
+        let mut values: Vec<_> = self
+            .handle
+            .values()
+            .await
+            .map_err(status)?
+            .into_iter()
+            .filter(|((ca, _), _)| stations.is_empty() || stations.contains(&u32::from(*ca)))
+            .collect();
+        values.sort_by_key(|(key, _)| *key);
+        let points = values
+            .iter()
+            .map(|((ca, _), point)| point_message(*ca, point))
+            .collect();
+        Ok(Response::new(proto::PointsReply { points }))
+    }
+
+    type StreamUpdatesStream = UpdateStream;
+
+    async fn stream_updates(
+        &self,
+        request: Request<proto::UpdatesRequest>,
+    ) -> Result<Response<UpdateStream>, Status> {
+        let request = request.into_inner();
+        if request.common_addresses.is_empty() {
+            return Err(Status::invalid_argument("No station requested"));
+        }
+        let (updates, mut queued) = mpsc::channel(QUEUE_LENGTH);
+        let lagged = Arc::new(AtomicU64::new(0));
+
+        // One subscription per station, as data updates do not carry it
+        for ca in request.common_addresses {
+            let ca = u16::try_from(ca)
+                .map_err(|_| Status::invalid_argument("Common address out of range"))?;
+            let mut filter = EventFilter::new().common_address(ca);
+            for range in &request.ioa_ranges {
+                filter = filter.ioa_range(range.first..=range.last);
+            }
+            let mut events = self
+                .handle
+                .subscribe_filtered(filter)
+                .await
+                .map_err(status)?;
+            let updates = updates.clone();
+            let lagged = lagged.clone();
+            tokio::spawn(async move {
+                // Drained at once; only the queue waits for the consumer
+                while let Some(event) = events.recv().await {
+                    let Iec104Event::DataUpdate(points) = event.event else {
+                        continue;
+                    };
+                    let update = proto::PointUpdate {
+                        seq: event.seq,
+                        points: points
+                            .iter()
+                            .map(|point| point_message(ca, point))
+                            .collect(),
+                        lagged: 0,
+                    };
+                    if updates.try_send(update).is_err() {
+                        if updates.is_closed() {
+                            break;
+                        }
+                        lagged.fetch_add(1, Ordering::Relaxed);
+                    }
+                }
+            });
+        }
+        drop(updates);
+
+        let stream = futures::stream::poll_fn(move |cx| {
+            queued.poll_recv(cx).map(|update| {
+                update.map(|mut update| {
+                    update.lagged = lagged.swap(0, Ordering::Relaxed);
+                    Ok(update)
+                })
+            })
+        });
+        Ok(Response::new(Box::pin(stream)))
+    }
+
+    async fn send_command(
+        &self,
+        request: Request<proto::CommandRequest>,
+    ) -> Result<Response<proto::CommandReply>, Status> {
+        let request = request.into_inner();
+        let ca = u16::try_from(request.common_address)
+            .map_err(|_| Status::invalid_argument("Common address out of range"))?;
+        if !self.stations.contains(&ca) {
+            return Err(Status::permission_denied(format!(
+                "Commands to CA {} are not allowed",
+                ca
+            )));
+        }
+        let command = command(&request)?;
+        command
+            .validate()
+            .map_err(|err| Status::invalid_argument(err.to_string()))?;
+        let qualifier = if request.select {
+            CommandQualifier::SELECT
+        } else {
+            CommandQualifier::EXECUTE
+        };
+
+        let completion = self
+            .handle
+            .command(ca, command, qualifier)
+            .await
+            .map_err(status)?;
+        tokio::time::timeout(self.command_timeout, completion.confirmed())
+            .await
+            .unwrap_or(Err(Iec104Error::CommandTimeout {
+                type_id: command.type_id(),
+                ioa: request.ioa,
+            }))
+            .map_err(status)?;
+        Ok(Response::new(proto::CommandReply {}))
+    }
+}
+
+/// The command of a request.
+fn command(request: &proto::CommandRequest) -> Result<Command, Status> {
+    use proto::command_request::Command as Requested;
+
+    let ioa = request.ioa;
+    let command = match request.command {
+        Some(Requested::Single(value)) => Command::Single { ioa, value },
+        Some(Requested::Double(value)) => Command::Double {
+            ioa,
+            value: match proto::DoublePoint::try_from(value) {
+                Ok(proto::DoublePoint::Off) => DoubleCommandState::Off,
+                Ok(proto::DoublePoint::On) => DoubleCommandState::On,
+                _ => return Err(Status::invalid_argument("Double command must be OFF or ON")),
+            },
+        },
+        Some(Requested::RegulatingStep(step)) => Command::RegulatingStep {
+            ioa,
+            step: match proto::StepDirection::try_from(step) {
+                Ok(proto::StepDirection::Lower) => StepCommand::Lower,
+                Ok(proto::StepDirection::Higher) => StepCommand::Higher,
+                Err(_) => return Err(Status::invalid_argument("Unknown step direction")),
+            },
+        },
+        Some(Requested::SetpointNormalized(value)) => Command::SetpointNormalized {
+            ioa,
+            value,
+            qos: Qos::EXECUTE,
+        },
+        Some(Requested::SetpointScaled(value)) => Command::SetpointScaled {
+            ioa,
+            value: i16::try_from(value)
+                .map_err(|_| Status::invalid_argument("Scaled setpoint out of i16 range"))?,
+            qos: Qos::EXECUTE,
+        },
+        Some(Requested::SetpointFloat(value)) => Command::SetpointFloat {
+            ioa,
+            value,
+            qos: Qos::EXECUTE,
+        },
+        None => return Err(Status::invalid_argument("No command given")),
+    };
+    Ok(command)
+}
+
+pub(crate) fn point_message(common_address: u16, point: &DataPoint) -> proto::Point {
+    let value = match point.value {
+        DataValue::Single(value) => PointValue::Single(value),
+        DataValue::Double(value) => PointValue::Double(
+            match value {
+                DoublePointValue::Indeterminate => proto::DoublePoint::Indeterminate,
+                DoublePointValue::Off => proto::DoublePoint::Off,
+                DoublePointValue::On => proto::DoublePoint::On,
+                DoublePointValue::IndeterminateOrFaulty => {
+                    proto::DoublePoint::IndeterminateOrFaulty
+                }
+            }
+            .into(),
+        ),
+        DataValue::Normalized(value) => PointValue::Normalized(value),
+        DataValue::Scaled(value) => PointValue::Scaled(value.into()),
+        DataValue::Float(value) => PointValue::Float(value),
+        DataValue::Counter(value) => PointValue::Counter(value),
+        DataValue::Bitstring(value) => PointValue::Bitstring(value),
+        DataValue::StepPosition { value, transient } => {
+            PointValue::StepPosition(proto::StepPosition {
+                value: value.into(),
+                transient,
+            })
+        }
+        DataValue::PackedSinglePoint { status, changes } => {
+            PointValue::PackedSinglePoint(proto::PackedSinglePoint {
+                status: status.into(),
+                changes: changes.into(),
+            })
+        }
+        DataValue::BinaryCounter {
+            value,
+            sequence,
+            carry,
+            adjusted,
+            invalid,
+        } => PointValue::BinaryCounter(proto::BinaryCounter {
+            value,
+            sequence: sequence.into(),
+            carry,
+            adjusted,
+            invalid,
+        }),
+        DataValue::Parameter { value, qpm } => PointValue::Parameter(proto::Parameter {
+            value: match value {
+                ParameterValue::Normalized(value) | ParameterValue::Float(value) => value.into(),
+                ParameterValue::Scaled(value) => value.into(),
+            },
+            qpm: qpm.as_u8().into(),
+        }),
+    };
+    let quality = point.quality;
+    proto::Point {
+        common_address: common_address.into(),
+        ioa: point.ioa,
+        value: Some(value),
+        quality: Some(proto::Quality {
+            overflow: quality.overflow(),
+            blocked: quality.blocked(),
+            substituted: quality.substituted(),
+            not_topical: quality.not_topical(),
+            invalid: quality.invalid(),
+            elapsed_time_invalid: quality.elapsed_time_invalid(),
+        }),
+        timestamp: point
+            .timestamp
+            .as_ref()
+            .map_or_else(String::new, crate::analyze::render_timestamp),
+        unix_millis: match point.timestamp {
+            Some(Timestamp::Full(time)) => Some(time.unix_millis()),
+            _ => None,
+        },
+    }
+}
+
+fn millis(duration: Duration) -> u32 {
+    u32::try_from(duration.as_millis()).unwrap_or(u32::MAX)
+}
+
+fn status(err: Iec104Error) -> Status {
+    let message = err.to_string();
+    match err {
+        Iec104Error::CommandRejected { .. } => Status::aborted(message),
+        Iec104Error::CommandTimeout { .. } => Status::deadline_exceeded(message),
+        Iec104Error::NotConnected | Iec104Error::ChannelClosed | Iec104Error::Connection(_) => {
+            Status::unavailable(message)
+        }
+        _ => Status::internal(message),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::{SinkExt, StreamExt};
+    use tokio::net::TcpListener;
+    use tokio::sync::oneshot;
+    use tokio_util::codec::Framed;
+
+    use crate::codec::{Apdu, Iec104Codec};
+    use crate::types::{Apci, AsduHeader, Cot, TypeId, UFunction};
+    use crate::{Asdu, ClientConfig, Iec104Client};
+
+    #[tokio::test]
+    async fn test_service() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let (send_tx, send_rx) = oneshot::channel::<()>();
+        tokio::spawn(async move {
+            let (socket, _) = listener.accept().await.unwrap();
+            let mut server = Framed::new(socket, Iec104Codec::new());
+            server.next().await.unwrap().unwrap();
+            server
+                .send(Apdu::u_frame(UFunction::StartDtCon))
+                .await
+                .unwrap();
+
+            send_rx.await.unwrap();
+            let header = AsduHeader::new(TypeId::MeasuredFloat, 1, Cot::Spontaneous, 1);
+            let mut data = Asdu::new(header);
+            data.raw_data = bytes::Bytes::from_static(&[100, 0, 0, 0x00, 0x00, 0xC0, 0x3F, 0]);
+            server.send(Apdu::i_frame(0, 0, data)).await.unwrap();
+
+            // Confirm every command
+            let mut send_seq = 1;
+            while let Some(Ok(apdu)) = server.next().await {
+                if let (Apci::IFrame { .. }, Some(mut asdu)) = (apdu.apci, apdu.asdu) {
+                    asdu.header.cot = Cot::ActivationConfirm;
+                    server.send(Apdu::i_frame(send_seq, 1, asdu)).await.unwrap();
+                    send_seq += 1;
+                }
+            }
+        });
+
+        let mut client = Iec104Client::new(ClientConfig::new(addr.to_string()).point_cache(true));
+        client.connect().await.unwrap();
+        client.start_dt().await.unwrap();
+        let (handle, _task) = client.spawn();
+        let service = GrpcService::new(handle).station(1);
+
+        let status = service
+            .get_status(Request::new(proto::StatusRequest {}))
+            .await
+            .unwrap()
+            .into_inner();
+        assert_eq!(status.state(), proto::ConnectionState::Active);
+        assert_eq!(status.session.unwrap().k, 12);
+
+        let request = proto::UpdatesRequest {
+            common_addresses: vec![1],
+            ioa_ranges: vec![proto::IoaRange {
+                first: 100,
+                last: 199,
+            }],
+        };
+        let mut updates = service
+            .stream_updates(Request::new(request))
+            .await
+            .unwrap()
+            .into_inner();
+        send_tx.send(()).unwrap();
+        let update = updates.next().await.unwrap().unwrap();
+        assert_eq!(update.lagged, 0);
+        assert_eq!(update.points.len(), 1);
+        let point = &update.points[0];
+        assert_eq!((point.common_address, point.ioa), (1, 100));
+        assert_eq!(point.value, Some(PointValue::Float(1.5)));
+        assert!(!point.quality.unwrap().invalid);
+
+        let points = service
+            .get_points(Request::new(proto::PointsRequest {
+                common_addresses: vec![1],
+            }))
+            .await
+            .unwrap()
+            .into_inner()
+            .points;
+        assert_eq!(points, update.points);
+
+        let command = |ca, command| proto::CommandRequest {
+            common_address: ca,
+            ioa: 5,
+            command: Some(command),
+            select: false,
+        };
+        use proto::command_request::Command as Requested;
+        let on = Requested::Double(proto::DoublePoint::On.into());
+        service
+            .send_command(Request::new(command(1, on)))
+            .await
+            .unwrap();
+        let err = service
+            .send_command(Request::new(command(2, on)))
+            .await
+            .unwrap_err();
+        assert_eq!(err.code(), tonic::Code::PermissionDenied);
+        let faulty = Requested::Double(proto::DoublePoint::IndeterminateOrFaulty.into());
+        let err = service
+            .send_command(Request::new(command(1, faulty)))
+            .await
+            .unwrap_err();
+        assert_eq!(err.code(), tonic::Code::InvalidArgument);
+    }
+
+    #[tokio::test]
+    async fn test_command_failures() {
+        let (mut client, mut server) = crate::testing::pair().await.unwrap();
+        server.serve_ioas([5, 7]);
+        tokio::spawn(async move {
+            // IOA 6 is rejected by the station, IOA 7 confirmed too late
+            while let Ok(asdu) = server.recv_asdu().await {
+                if asdu.raw_data.first() == Some(&7) {
+                    tokio::time::sleep(Duration::from_millis(200)).await;
+                }
+                server.respond(&asdu, Cot::ActivationConfirm).await?;
+            }
+            Ok::<_, Iec104Error>(())
+        });
+        client.start_dt().await.unwrap();
+        let (handle, task) = client.spawn();
+        let service = GrpcService::new(handle.clone())
+            .station(1)
+            .command_timeout(Duration::from_millis(100));
+
+        use proto::command_request::Command as Requested;
+        let single = |ca, ioa| proto::CommandRequest {
+            common_address: ca,
+            ioa,
+            command: Some(Requested::Single(true)),
+            select: false,
+        };
+        let code = |result: Result<Response<proto::CommandReply>, Status>| {
+            result.unwrap_err().code()
+        };
+        service.send_command(Request::new(single(1, 5))).await.unwrap();
+        let result = service.send_command(Request::new(single(1, 6))).await;
+        assert_eq!(code(result), tonic::Code::Aborted);
+        let result = service.send_command(Request::new(single(1, 7))).await;
+        assert_eq!(code(result), tonic::Code::DeadlineExceeded);
+        let result = service.send_command(Request::new(single(70_000, 5))).await;
+        assert_eq!(code(result), tonic::Code::InvalidArgument);
+        let empty = proto::CommandRequest {
+            command: None,
+            ..single(1, 5)
+        };
+        let result = service.send_command(Request::new(empty)).await;
+        assert_eq!(code(result), tonic::Code::InvalidArgument);
+        let request = proto::UpdatesRequest {
+            common_addresses: vec![],
+            ioa_ranges: vec![],
+        };
+        let err = service.stream_updates(Request::new(request)).await.err().unwrap();
+        assert_eq!(err.code(), tonic::Code::InvalidArgument);
+
+        handle.disconnect().await.unwrap();
+        task.await.unwrap().unwrap();
+        let result = service.send_command(Request::new(single(1, 5))).await;
+        assert_eq!(code(result), tonic::Code::Unavailable);
+        let err = service
+            .get_status(Request::new(proto::StatusRequest {}))
+            .await
+            .unwrap_err();
+        assert_eq!(err.code(), tonic::Code::Unavailable);
+    }
+}
@@ -0,0 +1,786 @@
+//! Handle to a client running in a background task.
+//!
+//! [`Iec104Client::spawn`] moves the client into a task that owns the socket.
+//! The task keeps receiving frames and running the protocol timers while
+//! commands are issued through one or more [`ClientHandle`]s, so sending a
+//! command never has to wait for the caller to stop polling for data.
+
+use std::collections::HashMap;
+use std::future::Future;
+use std::time::Duration;
+
+use futures::future::BoxFuture;
+use tokio::sync::{broadcast, mpsc, oneshot, watch};
+
+use crate::client::{
+    check_test_mirror, ConnectionState, Iec104Client, LinkStats, SequencedEvent, SessionInfo,
+    TappedFrame,
+};
+use crate::command::{Command, CommandCompletion, StepCommand};
+use crate::error::{Iec104Error, Result};
+use crate::file_transfer::FileInfo;
+use crate::filter::EventFilter;
+use crate::types::{
+    Asdu, CommandQualifier, Cp56Time2a, DataPoint, DoubleCommandState,
+    ParameterActivationQualifier, ParameterValue, PulseDuration, Qcc, Qos, Qpm, ResetProcessQualifier,
+    TypeId,
+};
+
+/// Work executed by the background task against the client it owns.
+pub(crate) type Request = Box<dyn for<'a> FnOnce(&'a mut Iec104Client) -> BoxFuture<'a, ()> + Send>;
+
+/// Cheap, clonable handle to a spawned [`Iec104Client`].
+///
+/// Every method forwards to the client method of the same name and waits
+/// for its result. Commands return once sent; await the returned
+/// [`CommandCompletion`] for the remote station's confirmation. Requests
+/// from all clones are executed one at a time, in the order they reach the
+/// task. Requests waiting for the station (interrogation snapshots, reads,
+/// test commands, select-before-operate) only hold the task while sending,
+/// so other clones are served while they wait; file transfers and
+/// [`shutdown`](Self::shutdown) hold it until done. Once the task has
+/// ended, every method returns [`Iec104Error::ChannelClosed`].
+#[derive(Debug, Clone)]
+pub struct ClientHandle {
+    requests: mpsc::Sender<Request>,
+}
+
+impl ClientHandle {
+    pub(crate) fn new(requests: mpsc::Sender<Request>) -> Self {
+        Self { requests }
+    }
+
+    /// Check whether the background task has ended.
+    pub fn is_closed(&self) -> bool {
+        self.requests.is_closed()
+    }
+
+    /// Get the current connection state.
+    pub async fn state(&self) -> Result<ConnectionState> {
+        self.call(|client| Box::pin(async move { Ok(client.state()) })).await
+    }
+
+    /// Subscribe to events alongside any other subscribers.
+    ///
+    /// See [`Iec104Client::subscribe_broadcast`].
+    pub async fn subscribe_broadcast(&self) -> Result<broadcast::Receiver<SequencedEvent>> {
+        self.call(|client| Box::pin(async move { Ok(client.subscribe_broadcast()) }))
+            .await
+    }
+
+    /// Copy every APDU sent or received to the returned receiver.
+    ///
+    /// See [`Iec104Client::tap`].
+    pub async fn tap(&self) -> Result<broadcast::Receiver<TappedFrame>> {
+        self.call(|client| Box::pin(async move { Ok(client.tap()) })).await
+    }
+
+    /// Subscribe to the events selected by `filter`.
+    ///
+    /// See [`Iec104Client::subscribe_filtered`].
+    pub async fn subscribe_filtered(
+        &self,
+        filter: EventFilter,
+    ) -> Result<mpsc::Receiver<SequencedEvent>> {
+        self.call(move |client| Box::pin(async move { Ok(client.subscribe_filtered(filter)) }))
+            .await
+    }
+
+    /// Latest value received for a point (requires the point cache).
+    pub async fn value(&self, common_address: u16, ioa: u32) -> Result<Option<DataPoint>> {
+        self.call(move |client| {
+            Box::pin(async move { Ok(client.value(common_address, ioa).cloned()) })
+        })
+        .await
+    }
+
+    /// Latest values of all points by common address and IOA (requires the
+    /// point cache).
+    pub async fn values(&self) -> Result<HashMap<(u16, u32), DataPoint>> {
+        self.call(|client| Box::pin(async move { Ok(client.values().clone()) }))
+            .await
+    }
+
+    /// Get the link counters.
+    pub async fn stats(&self) -> Result<LinkStats> {
+        self.call(|client| Box::pin(async move { Ok(client.stats()) })).await
+    }
+
+    /// Watch connection state transitions.
+    ///
+    /// The receiver keeps its last value once the background task has ended.
+    pub async fn watch_state(&self) -> Result<watch::Receiver<ConnectionState>> {
+        self.call(|client| Box::pin(async move { Ok(client.watch_state()) }))
+            .await
+    }
+
+    /// Get the session parameters, available once data transfer has started.
+    pub async fn session_info(&self) -> Result<Option<SessionInfo>> {
+        self.call(|client| Box::pin(async move { Ok(client.session_info().cloned()) }))
+            .await
+    }
+
+    /// Disconnect from the server, which also ends the background task.
+    pub async fn disconnect(&self) -> Result<()> {
+        self.call(|client| Box::pin(client.disconnect())).await
+    }
+
+    /// Close the connection gracefully, which also ends the background task.
+    ///
+    /// See [`Iec104Client::shutdown`].
+    pub async fn shutdown(&self, timeout: Duration) -> Result<()> {
+        self.call(move |client| Box::pin(client.shutdown(timeout))).await
+    }
+
+    /// Start data transfer (STARTDT act).
+    pub async fn start_dt(&self) -> Result<()> {
+        self.call(|client| Box::pin(client.start_dt())).await
+    }
+
+    /// Stop data transfer (STOPDT act).
+    pub async fn stop_dt(&self) -> Result<()> {
+        self.call(|client| Box::pin(client.stop_dt())).await
+    }
+
+    /// Send general interrogation command.
+    pub async fn general_interrogation(&self, common_address: u16) -> Result<CommandCompletion> {
+        self.call(move |client| Box::pin(client.general_interrogation(common_address)))
+            .await
+    }
+
+    /// Perform a general interrogation and return the points it reported.
+    ///
+    /// See [`Iec104Client::general_interrogation_snapshot`].
+    pub async fn general_interrogation_snapshot(
+        &self,
+        common_address: u16,
+        timeout: Duration,
+    ) -> Result<Vec<DataPoint>> {
+        let (reply, points) = oneshot::channel();
+        self.call(move |client| Box::pin(client.watch_interrogation(common_address, reply)))
+            .await?;
+        within(timeout, TypeId::InterrogationCommand, 0, replied(points)).await
+    }
+
+    /// Read a single information object (C_RD_NA_1).
+    ///
+    /// See [`Iec104Client::read`].
+    pub async fn read(
+        &self,
+        common_address: u16,
+        ioa: u32,
+        timeout: Duration,
+    ) -> Result<DataPoint> {
+        let (reply, points) = oneshot::channel();
+        self.call(move |client| Box::pin(client.watch_read(common_address, ioa, reply)))
+            .await?;
+        let mut points = within(timeout, TypeId::ReadCommand, ioa, replied(points)).await?;
+        points.pop().ok_or(Iec104Error::ChannelClosed)
+    }
+
+    /// Call the directory of a station.
+    ///
+    /// See [`Iec104Client::file_directory`].
+    pub async fn file_directory(
+        &self,
+        common_address: u16,
+        ioa: u32,
+        timeout: Duration,
+    ) -> Result<Vec<FileInfo>> {
+        self.call(move |client| Box::pin(client.file_directory(common_address, ioa, timeout)))
+            .await
+    }
+
+    /// Download a file from a station.
+    ///
+    /// See [`Iec104Client::download_file`].
+    pub async fn download_file(
+        &self,
+        common_address: u16,
+        ioa: u32,
+        file: u16,
+        timeout: Duration,
+    ) -> Result<Vec<u8>> {
+        self.call(move |client| {
+            Box::pin(client.download_file(common_address, ioa, file, timeout))
+        })
+        .await
+    }
+
+    /// Send a test command (C_TS_NA_1) and verify the mirrored test pattern.
+    pub async fn test_command(&self, common_address: u16, timeout: Duration) -> Result<()> {
+        let asdu = Asdu::test_command(common_address);
+        self.test(asdu, timeout).await
+    }
+
+    /// Send a test command with time tag (C_TS_TA_1) and verify the mirror.
+    pub async fn test_command_time(
+        &self,
+        common_address: u16,
+        time: Cp56Time2a,
+        timeout: Duration,
+    ) -> Result<()> {
+        let asdu = self
+            .call(move |client| {
+                Box::pin(async move { Ok(client.next_test_command_time(common_address, time)) })
+            })
+            .await?;
+        self.test(asdu, timeout).await
+    }
+
+    /// Send counter interrogation command.
+    pub async fn counter_interrogation(
+        &self,
+        common_address: u16,
+        qcc: Qcc,
+    ) -> Result<CommandCompletion> {
+        self.call(move |client| Box::pin(client.counter_interrogation(common_address, qcc)))
+            .await
+    }
+
+    /// Send clock synchronization command.
+    pub async fn clock_sync(
+        &self,
+        common_address: u16,
+        time: Cp56Time2a,
+    ) -> Result<CommandCompletion> {
+        self.call(move |client| Box::pin(client.clock_sync(common_address, time)))
+            .await
+    }
+
+    /// Send an arbitrary ASDU in an I-frame.
+    ///
+    /// See [`Iec104Client::send_asdu`].
+    pub async fn send_asdu(&self, asdu: Asdu) -> Result<()> {
+        self.call(move |client| Box::pin(client.send_asdu(asdu))).await
+    }
+
+    /// Forward a command received from another master, keeping its
+    /// originator address.
+    ///
+    /// See [`Iec104Client::forward_command`].
+    pub async fn forward_command(&self, asdu: Asdu) -> Result<CommandCompletion> {
+        self.call(move |client| Box::pin(client.forward_command(asdu))).await
+    }
+
+    /// Synchronize the station clock to the local system time.
+    pub async fn clock_sync_now(&self, common_address: u16) -> Result<CommandCompletion> {
+        self.call(move |client| Box::pin(client.clock_sync_now(common_address)))
+            .await
+    }
+
+    /// Send reset process command (C_RP_NA_1) with a non-destructive qualifier.
+    ///
+    /// See [`Iec104Client::reset_process`].
+    pub async fn reset_process(
+        &self,
+        common_address: u16,
+        qualifier: ResetProcessQualifier,
+    ) -> Result<CommandCompletion> {
+        self.call(move |client| Box::pin(client.reset_process(common_address, qualifier)))
+            .await
+    }
+
+    /// Send reset process command (C_RP_NA_1).
+    ///
+    /// See [`Iec104Client::dangerous_reset_process`].
+    pub async fn dangerous_reset_process(
+        &self,
+        common_address: u16,
+        qualifier: ResetProcessQualifier,
+    ) -> Result<CommandCompletion> {
+        self.call(move |client| {
+            Box::pin(client.dangerous_reset_process(common_address, qualifier))
+        })
+        .await
+    }
+
+    /// Load a parameter of measured values (P_ME_NA_1, P_ME_NB_1 or P_ME_NC_1).
+    ///
+    /// See [`Iec104Client::set_parameter`].
+    pub async fn set_parameter(
+        &self,
+        common_address: u16,
+        ioa: u32,
+        value: ParameterValue,
+        qpm: Qpm,
+    ) -> Result<CommandCompletion> {
+        self.call(move |client| Box::pin(client.set_parameter(common_address, ioa, value, qpm)))
+            .await
+    }
+
+    /// Activate or deactivate parameters (P_AC_NA_1).
+    ///
+    /// See [`Iec104Client::activate_parameter`].
+    pub async fn activate_parameter(
+        &self,
+        common_address: u16,
+        ioa: u32,
+        qpa: ParameterActivationQualifier,
+        activate: bool,
+    ) -> Result<CommandCompletion> {
+        self.call(move |client| {
+            Box::pin(client.activate_parameter(common_address, ioa, qpa, activate))
+        })
+        .await
+    }
+
+    /// Send single command.
+    pub async fn single_command(
+        &self,
+        common_address: u16,
+        ioa: u32,
+        value: bool,
+        qualifier: CommandQualifier,
+    ) -> Result<CommandCompletion> {
+        self.call(move |client| {
+            Box::pin(client.single_command(common_address, ioa, value, qualifier))
+        })
+        .await
+    }
+
+    /// Send double command.
+    pub async fn double_command(
+        &self,
+        common_address: u16,
+        ioa: u32,
+        value: DoubleCommandState,
+        qualifier: CommandQualifier,
+    ) -> Result<CommandCompletion> {
+        self.call(move |client| {
+            Box::pin(client.double_command(common_address, ioa, value, qualifier))
+        })
+        .await
+    }
+
+    /// Send regulating step command (e.g. transformer tap changer).
+    pub async fn regulating_step(
+        &self,
+        common_address: u16,
+        ioa: u32,
+        step: StepCommand,
+        qualifier: CommandQualifier,
+    ) -> Result<CommandCompletion> {
+        self.call(move |client| {
+            Box::pin(client.regulating_step(common_address, ioa, step, qualifier))
+        })
+        .await
+    }
+
+    /// Send setpoint command (normalized value, -1.0..=1.0) with the qualifier `qos`.
+    pub async fn setpoint_normalized(
+        &self,
+        common_address: u16,
+        ioa: u32,
+        value: f32,
+        qos: Qos,
+    ) -> Result<CommandCompletion> {
+        self.call(move |client| {
+            Box::pin(client.setpoint_normalized(common_address, ioa, value, qos))
+        })
+        .await
+    }
+
+    /// Send setpoint command (scaled value) with the qualifier `qos`.
+    pub async fn setpoint_scaled(
+        &self,
+        common_address: u16,
+        ioa: u32,
+        value: i16,
+        qos: Qos,
+    ) -> Result<CommandCompletion> {
+        self.call(move |client| {
+            Box::pin(client.setpoint_scaled(common_address, ioa, value, qos))
+        })
+        .await
+    }
+
+    /// Send setpoint command (short floating point) with the qualifier `qos`.
+    pub async fn setpoint_float(
+        &self,
+        common_address: u16,
+        ioa: u32,
+        value: f32,
+        qos: Qos,
+    ) -> Result<CommandCompletion> {
+        self.call(move |client| {
+            Box::pin(client.setpoint_float(common_address, ioa, value, qos))
+        })
+        .await
+    }
+
+    /// Send a command to the process (select or execute).
+    pub async fn command(
+        &self,
+        common_address: u16,
+        command: Command,
+        qualifier: CommandQualifier,
+    ) -> Result<CommandCompletion> {
+        self.call(move |client| Box::pin(client.command(common_address, command, qualifier)))
+            .await
+    }
+
+    /// Select a command, then execute it once the selection is confirmed.
+    ///
+    /// See [`Iec104Client::select_then_execute`].
+    pub async fn select_then_execute(
+        &self,
+        common_address: u16,
+        command: Command,
+        pulse: PulseDuration,
+        window: Duration,
+    ) -> Result<()> {
+        let qualifier = CommandQualifier::new(pulse, true);
+        let selected = self.command(common_address, command, qualifier).await?;
+        let (type_id, ioa) = (selected.type_id(), selected.ioa());
+        match within(window, type_id, ioa, selected.confirmed()).await {
+            Ok(()) => {}
+            Err(e @ Iec104Error::CommandTimeout { .. }) => {
+                self.deselect(common_address, command, qualifier).await;
+                return Err(e);
+            }
+            Err(e) => return Err(e),
+        }
+
+        let execute = qualifier.with_select(false);
+        let result = match self.command(common_address, command, execute).await {
+            Ok(executed) => within(window, type_id, ioa, executed.confirmed()).await,
+            Err(e) => Err(e),
+        };
+        if let Err(e) = result {
+            if !e.is_connection_error() {
+                self.deselect(common_address, command, qualifier).await;
+            }
+            return Err(e);
+        }
+        Ok(())
+    }
+
+    /// Send a test command and verify its confirmation mirrors it.
+    async fn test(&self, asdu: Asdu, timeout: Duration) -> Result<()> {
+        let (completion, expected) =
+            self.call(move |client| Box::pin(client.start_test_command(asdu))).await?;
+        let (type_id, ioa) = (completion.type_id(), completion.ioa());
+        let confirmation = within(timeout, type_id, ioa, completion.confirmation()).await?;
+        check_test_mirror(&expected, &confirmation)
+    }
+
+    /// Cancel a selection, ignoring failures.
+    async fn deselect(&self, common_address: u16, command: Command, qualifier: CommandQualifier) {
+        let _ = self
+            .call(move |client| {
+                Box::pin(async move {
+                    client.deselect(common_address, command, qualifier).await;
+                    Ok(())
+                })
+            })
+            .await;
+    }
+
+    /// Run `f` on the background task and wait for its result.
+    async fn call<T, F>(&self, f: F) -> Result<T>
+    where
+        T: Send + 'static,
+        F: for<'a> FnOnce(&'a mut Iec104Client) -> BoxFuture<'a, Result<T>> + Send + 'static,
+    {
+        let (reply_tx, reply_rx) = oneshot::channel();
+        let request: Request = Box::new(move |client| {
+            Box::pin(async move {
+                let _ = reply_tx.send(f(client).await);
+            })
+        });
+
+        self.requests
+            .send(request)
+            .await
+            .map_err(|_| Iec104Error::ChannelClosed)?;
+        reply_rx.await.map_err(|_| Iec104Error::ChannelClosed)?
+    }
+}
+
+/// Wait up to `timeout` for the outcome of a command sent through the task.
+async fn within<T>(
+    timeout: Duration,
+    type_id: TypeId,
+    ioa: u32,
+    outcome: impl Future<Output = Result<T>>,
+) -> Result<T> {
+    tokio::time::timeout(timeout, outcome)
+        .await
+        .unwrap_or(Err(Iec104Error::CommandTimeout { type_id, ioa }))
+}
+
+/// The result handed over by the task, which ends with the connection.
+async fn replied<T>(rx: oneshot::Receiver<Result<T>>) -> Result<T> {
+    rx.await.map_err(|_| Iec104Error::ChannelClosed)?
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::client::{ClientConfig, Iec104Event};
+    use crate::codec::Apdu;
+    use crate::testing::TestServer;
+    use crate::types::{Apci, Asdu, AsduHeader, Cot, Ioa, TypeId, UFunction};
+    use bytes::Bytes;
+
+    /// Confirm the STARTDT of the client.
+    async fn confirm_start(server: &mut TestServer) {
+        let apdu = server.recv_apdu().await.unwrap();
+        assert!(matches!(apdu.apci, Apci::UFrame { function: UFunction::StartDtAct }));
+        server.send_apdu(Apdu::u_frame(UFunction::StartDtCon)).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_handle_commands_from_clones() {
+        let (mut client, mut server) = crate::testing::pair().await.unwrap();
+        let (received_tx, received_rx) = oneshot::channel();
+        tokio::spawn(async move {
+            confirm_start(&mut server).await;
+
+            // Spontaneous data arrives while commands are being issued
+            let mut data = Asdu::new(AsduHeader::new(TypeId::SinglePoint, 1, Cot::Spontaneous, 1));
+            data.raw_data = Bytes::from_static(&[0x01, 0x00, 0x00, 0x01]);
+            server.send_asdu(data).await.unwrap();
+
+            let mut received = Vec::new();
+            for _ in 0..2 {
+                received.push(server.recv_asdu().await.unwrap().header.type_id);
+                server.acknowledge().await.unwrap();
+            }
+            received.sort_by_key(|t| t.as_u8());
+            received_tx.send(received).unwrap();
+            // Confirms the STOPDT of the disconnection
+            while server.recv_asdu().await.is_ok() {}
+        });
+
+        let mut events = client.subscribe().unwrap();
+        let (handle, task) = client.spawn();
+
+        handle.start_dt().await.unwrap();
+        assert_eq!(handle.state().await.unwrap(), ConnectionState::Active);
+        assert!(handle.session_info().await.unwrap().is_some());
+
+        let other = handle.clone();
+        let (gi, command) = tokio::join!(
+            handle.general_interrogation(1),
+            other.single_command(1, 100, true, CommandQualifier::EXECUTE)
+        );
+        gi.unwrap();
+        command.unwrap();
+
+        assert_eq!(
+            received_rx.await.unwrap(),
+            vec![TypeId::SingleCommand, TypeId::InterrogationCommand]
+        );
+        loop {
+            let event = events.recv().await.unwrap().event;
+            if let Iec104Event::DataUpdate(points) = event {
+                assert_eq!(points[0].ioa, 1);
+                break;
+            }
+        }
+
+        handle.disconnect().await.unwrap();
+        task.await.unwrap().unwrap();
+        assert!(handle.is_closed());
+        assert!(matches!(
+            handle.general_interrogation(1).await,
+            Err(Iec104Error::ChannelClosed)
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_send_asdu_passes_through() {
+        let config = ClientConfig::new("duplex").originator_address(7);
+        let (mut client, mut server) = crate::testing::pair_with(config).await.unwrap();
+        let (received_tx, received_rx) = oneshot::channel();
+        tokio::spawn(async move {
+            let asdu = server.recv_asdu().await.unwrap();
+            received_tx.send(asdu).unwrap();
+            while server.recv_asdu().await.is_ok() {}
+        });
+
+        client.start_dt().await.unwrap();
+        let (handle, _task) = client.spawn();
+
+        let header = AsduHeader::new(TypeId::Bitstring32Command, 1, Cot::Activation, 3);
+        let mut asdu = Asdu::new(header);
+        asdu.raw_data = Bytes::from_static(&[0x10, 0x00, 0x00, 0xEF, 0xBE, 0xAD, 0xDE]);
+        handle.send_asdu(asdu.clone()).await.unwrap();
+
+        let received = received_rx.await.unwrap();
+        assert_eq!(received.header.type_id, TypeId::Bitstring32Command);
+        assert_eq!(received.header.originator, 7);
+        assert_eq!(received.raw_data, asdu.raw_data);
+    }
+
+    #[tokio::test]
+    async fn test_command_resolves_on_confirmation() {
+        let (client, mut server) = crate::testing::pair().await.unwrap();
+        tokio::spawn(async move {
+            while let Ok(asdu) = server.recv_asdu().await {
+                // Reject IOA 200, confirm and terminate everything else
+                let ioa = Ioa::try_from_slice(&asdu.raw_data).unwrap().value();
+                server.send_asdu(asdu.mirror(Cot::ActivationConfirm, ioa == 200)).await?;
+                if ioa != 200 {
+                    server.respond(&asdu, Cot::ActivationTermination).await?;
+                }
+            }
+            Ok::<_, Iec104Error>(())
+        });
+
+        let (handle, _task) = client.spawn();
+        handle.start_dt().await.unwrap();
+
+        let completion = handle
+            .single_command(1, 100, true, CommandQualifier::EXECUTE)
+            .await
+            .unwrap();
+        assert_eq!(completion.ioa(), 100);
+        completion.terminated().await.unwrap();
+
+        let completion = handle
+            .double_command(1, 200, DoubleCommandState::On, CommandQualifier::EXECUTE)
+            .await
+            .unwrap();
+        match completion.confirmed().await {
+            Err(Iec104Error::CommandRejected { type_id, ioa, .. }) => {
+                assert_eq!(type_id, TypeId::DoubleCommand);
+                assert_eq!(ioa, 200);
+            }
+            other => panic!("Expected rejection, got {:?}", other),
+        }
+
+        handle.general_interrogation(1).await.unwrap().terminated().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_snapshot_does_not_hold_other_clones() {
+        let (mut client, mut server) = crate::testing::pair().await.unwrap();
+        let station = tokio::spawn(async move {
+            // The interrogation only terminates once the command got through
+            let first = server.recv_asdu().await?;
+            let second = server.recv_asdu().await?;
+            let (gi, command) = if first.header.type_id == TypeId::InterrogationCommand {
+                (first, second)
+            } else {
+                (second, first)
+            };
+            server.respond(&command, Cot::ActivationConfirm).await?;
+            server.respond(&gi, Cot::ActivationConfirm).await?;
+            let mut point =
+                Asdu::new(AsduHeader::new(TypeId::SinglePoint, 1, Cot::InterrogatedByStation, 1));
+            point.raw_data = Bytes::from_static(&[0x05, 0x00, 0x00, 0x01]);
+            server.send_asdu(point).await?;
+            server.respond(&gi, Cot::ActivationTermination).await?;
+            while server.recv_asdu().await.is_ok() {}
+            Ok::<_, Iec104Error>(())
+        });
+
+        client.start_dt().await.unwrap();
+        let (handle, task) = client.spawn();
+        let other = handle.clone();
+        let snapshot = tokio::spawn(async move {
+            handle.general_interrogation_snapshot(1, Duration::from_secs(5)).await
+        });
+
+        let completion = other
+            .single_command(1, 100, true, CommandQualifier::EXECUTE)
+            .await
+            .unwrap();
+        completion.confirmed().await.unwrap();
+        let points = snapshot.await.unwrap().unwrap();
+        assert_eq!(points.len(), 1);
+        assert_eq!(points[0].ioa, 5);
+
+        other.disconnect().await.unwrap();
+        task.await.unwrap().unwrap();
+        station.await.unwrap().unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_waiting_requests_through_handle() {
+        let (mut client, mut server) = crate::testing::pair().await.unwrap();
+        tokio::spawn(async move {
+            while let Ok(asdu) = server.recv_asdu().await {
+                match asdu.header.type_id {
+                    // Selections of IOA 2 are never confirmed
+                    TypeId::SingleCommand if asdu.raw_data[0] == 2 => {}
+                    TypeId::ReadCommand => {
+                        let mut point =
+                            Asdu::new(AsduHeader::new(TypeId::SinglePoint, 1, Cot::Request, 1));
+                        point.raw_data = Bytes::from_static(&[0x10, 0x00, 0x00, 0x01]);
+                        server.send_asdu(point).await?;
+                    }
+                    _ => server.respond(&asdu, Cot::ActivationConfirm).await?,
+                }
+            }
+            Ok::<_, Iec104Error>(())
+        });
+
+        client.start_dt().await.unwrap();
+        let (handle, _task) = client.spawn();
+        let window = Duration::from_millis(200);
+        handle.test_command(1, window).await.unwrap();
+        handle.test_command_time(1, Cp56Time2a::now(), window).await.unwrap();
+        assert_eq!(handle.read(1, 16, window).await.unwrap().ioa, 16);
+
+        let pulse = PulseDuration::Unspecified;
+        handle
+            .select_then_execute(1, Command::Single { ioa: 1, value: true }, pulse, window)
+            .await
+            .unwrap();
+        let result = handle
+            .select_then_execute(1, Command::Single { ioa: 2, value: true }, pulse, window)
+            .await;
+        assert!(matches!(result, Err(Iec104Error::CommandTimeout { ioa: 2, .. })));
+    }
+
+    #[tokio::test]
+    async fn test_task_ends_when_peer_closes() {
+        let (mut client, mut server) = crate::testing::pair().await.unwrap();
+        tokio::spawn(async move {
+            confirm_start(&mut server).await;
+            server.close().await.unwrap();
+        });
+
+        let mut events = client.subscribe().unwrap();
+        let (handle, task) = client.spawn();
+        handle.start_dt().await.unwrap();
+
+        assert!(task.await.unwrap().unwrap_err().is_connection_error());
+        assert!(handle.is_closed());
+
+        let mut last = None;
+        while let Ok(event) = events.try_recv() {
+            last = Some(event.event);
+        }
+        assert!(matches!(last, Some(Iec104Event::Disconnected)));
+    }
+
+    #[tokio::test]
+    async fn test_task_ends_on_sequence_error() {
+        let (client, mut server) = crate::testing::pair().await.unwrap();
+        let (handle, task) = client.spawn();
+        let mut state = handle.watch_state().await.unwrap();
+        tokio::spawn(async move {
+            server.recv_apdu().await.unwrap();
+            server.send_apdu(Apdu::u_frame(UFunction::StartDtCon)).await.unwrap();
+            // N(S) 3 where 0 is expected
+            let mut data = Asdu::new(AsduHeader::new(TypeId::SinglePoint, 1, Cot::Spontaneous, 1));
+            data.raw_data = Bytes::from_static(&[0x01, 0x00, 0x00, 0x01]);
+            server.send_apdu(Apdu::i_frame(3, 0, data)).await.unwrap();
+            while server.recv_apdu().await.is_ok() {}
+        });
+        handle.start_dt().await.unwrap();
+
+        let result = tokio::time::timeout(Duration::from_secs(5), task).await.unwrap();
+        assert!(matches!(
+            result.unwrap(),
+            Err(Iec104Error::SequenceMismatch { expected: 0, actual: 3 })
+        ));
+        assert_eq!(*state.borrow_and_update(), ConnectionState::Disconnected);
+        assert!(handle.is_closed());
+    }
+}
@@ -0,0 +1,392 @@
+//! Parquet archive of data points for long-term storage and offline
+//! analytics.
+//!
+//! [`ParquetRecorder`] buffers points per day and common address and writes
+//! each buffer as an Arrow record batch to a Parquet file of its own, under
+//! a Hive-style partitioned path that query engines pick up directly:
+//!
+//! ```text
+//! <directory>/date=2024-02-29/ca=1/part-1709214330250-0.parquet
+//! ```
+//!
+//! The day is that of the point's full time tag, or of the moment it was
+//! recorded (UTC) when it has none. A partition is written once it holds
+//! [`batch_rows`](ParquetRecorder::batch_rows) rows, or by
+//! [`flush_due`](ParquetRecorder::flush_due) once its oldest row waited
+//! [`flush_interval`](ParquetRecorder::flush_interval). Call
+//! [`flush`](ParquetRecorder::flush) before exiting; buffered rows are not
+//! written on drop.
+//!
+//! Every file has the [`schema`](ParquetRecorder::schema) below:
+//!
+//! | Column | Arrow type | Content |
+//! |--------|------------|---------|
+//! | `ioa` | UInt32 | Information object address |
+//! | `type` | Utf8 | [`DataValue`](crate::types::DataValue) variant, as in the JSON form |
+//! | `value` | Float64 | [`DataValue::as_f64`](crate::types::DataValue::as_f64) |
+//! | `overflow` ... `elapsed_time_invalid` | Boolean | [`Quality`](crate::types::Quality) flags |
+//! | `timestamp` | Timestamp(ms), nullable | Full time tag, in the station's time zone |
+//! | `recorded_at` | Timestamp(ms, UTC) | When the point was recorded |
+
+use std::collections::BTreeMap;
+use std::fs::{self, File};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use arrow_array::{
+    ArrayRef, BooleanArray, Float64Array, RecordBatch, StringArray, TimestampMillisecondArray,
+    UInt32Array,
+};
+use arrow_schema::{DataType, Field, Schema, SchemaRef, TimeUnit};
+use parquet::arrow::ArrowWriter;
+use parquet::basic::Compression;
+use parquet::file::properties::WriterProperties;
+
+use crate::error::{Iec104Error, Result};
+use crate::types::{Cp56Time2a, DataPoint, Timestamp};
+
+/// Rows per partition before it is written, by default.
+pub const DEFAULT_BATCH_ROWS: usize = 65_536;
+
+/// Longest time a row stays buffered, by default.
+pub const DEFAULT_FLUSH_INTERVAL: Duration = Duration::from_secs(15 * 60);
+
+/// Quality flag columns, in schema order.
+const QUALITY_COLUMNS: [&str; 6] = [
+    "overflow",
+    "blocked",
+    "substituted",
+    "not_topical",
+    "invalid",
+    "elapsed_time_invalid",
+];
+
+/// Writes data points to day- and station-partitioned Parquet files.
+#[derive(Debug)]
+pub struct ParquetRecorder {
+    directory: PathBuf,
+    batch_rows: usize,
+    flush_interval: Duration,
+    /// Buffered rows by day (`YYYY-MM-DD`) and common address
+    partitions: BTreeMap<(String, u16), Partition>,
+    /// Start of this recorder in Unix milliseconds, to keep file names unique
+    run_id: u128,
+    files_written: u64,
+}
+
+#[derive(Debug)]
+struct Partition {
+    rows: Vec<Row>,
+    opened: Instant,
+}
+
+#[derive(Debug)]
+struct Row {
+    ioa: u32,
+    kind: &'static str,
+    value: f64,
+    quality: [bool; 6],
+    timestamp: Option<i64>,
+    recorded_at: i64,
+}
+
+impl ParquetRecorder {
+    /// Create a recorder writing below `directory`, which is created when
+    /// the first file is written.
+    pub fn new(directory: impl Into<PathBuf>) -> Self {
+        Self {
+            directory: directory.into(),
+            batch_rows: DEFAULT_BATCH_ROWS,
+            flush_interval: DEFAULT_FLUSH_INTERVAL,
+            partitions: BTreeMap::new(),
+            run_id: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map_or(0, |elapsed| elapsed.as_millis()),
+            files_written: 0,
+        }
+    }
+
+    /// Write a partition once it holds this many rows (at least 1).
+    pub fn batch_rows(mut self, rows: usize) -> Self {
+        self.batch_rows = rows.max(1);
+        self
+    }
+
+    /// Longest time a row stays buffered before
+    /// [`flush_due`](Self::flush_due) writes its partition.
+    pub fn flush_interval(mut self, interval: Duration) -> Self {
+        self.flush_interval = interval;
+        self
+    }
+
+    /// Schema of the files written.
+    pub fn schema() -> SchemaRef {
+        let mut fields = vec![
+            Field::new("ioa", DataType::UInt32, false),
+            Field::new("type", DataType::Utf8, false),
+            Field::new("value", DataType::Float64, false),
+        ];
+        fields.extend(QUALITY_COLUMNS.map(|name| Field::new(name, DataType::Boolean, false)));
+        fields.push(Field::new(
+            "timestamp",
+            DataType::Timestamp(TimeUnit::Millisecond, None),
+            true,
+        ));
+        fields.push(Field::new(
+            "recorded_at",
+            DataType::Timestamp(TimeUnit::Millisecond, Some("UTC".into())),
+            false,
+        ));
+        Arc::new(Schema::new(fields))
+    }
+
+    /// Record points received from station `common_address` now.
+    ///
+    /// Returns the files written because a partition reached
+    /// [`batch_rows`](Self::batch_rows).
+    pub fn record(&mut self, common_address: u16, points: &[DataPoint]) -> Result<Vec<PathBuf>> {
+        self.record_at(common_address, points, SystemTime::now())
+    }
+
+    /// Record points received from station `common_address` at `recorded_at`.
+    pub fn record_at(
+        &mut self,
+        common_address: u16,
+        points: &[DataPoint],
+        recorded_at: SystemTime,
+    ) -> Result<Vec<PathBuf>> {
+        let received = Cp56Time2a::from_system_time(recorded_at, 0);
+        let recorded_millis = received.unix_millis();
+
+        let mut full = Vec::new();
+        for point in points {
+            let time = match point.timestamp {
+                Some(Timestamp::Full(time)) => Some(time),
+                _ => None,
+            };
+            let day = time.unwrap_or(received).to_iso8601()[..10].to_string();
+            let key = (day, common_address);
+            let partition = self
+                .partitions
+                .entry(key.clone())
+                .or_insert_with(|| Partition {
+                    rows: Vec::new(),
+                    opened: Instant::now(),
+                });
+            partition.rows.push(Row {
+                ioa: point.ioa,
+                kind: point.value.name(),
+                value: point.value.as_f64().unwrap_or(f64::NAN),
+                quality: quality_flags(point),
+                timestamp: time.map(|time| time.unix_millis()),
+                recorded_at: recorded_millis,
+            });
+            if partition.rows.len() >= self.batch_rows && !full.contains(&key) {
+                full.push(key);
+            }
+        }
+
+        full.into_iter()
+            .map(|key| self.write_partition(key))
+            .collect()
+    }
+
+    /// Write the partitions whose oldest row waited at least
+    /// [`flush_interval`](Self::flush_interval); call it periodically.
+    pub fn flush_due(&mut self) -> Result<Vec<PathBuf>> {
+        let due: Vec<_> = self
+            .partitions
+            .iter()
+            .filter(|(_, partition)| partition.opened.elapsed() >= self.flush_interval)
+            .map(|(key, _)| key.clone())
+            .collect();
+        due.into_iter()
+            .map(|key| self.write_partition(key))
+            .collect()
+    }
+
+    /// Write every buffered row.
+    pub fn flush(&mut self) -> Result<Vec<PathBuf>> {
+        let keys: Vec<_> = self.partitions.keys().cloned().collect();
+        keys.into_iter()
+            .map(|key| self.write_partition(key))
+            .collect()
+    }
+
+    /// Rows recorded but not yet written.
+    pub fn buffered_rows(&self) -> usize {
+        self.partitions
+            .values()
+            .map(|partition| partition.rows.len())
+            .sum()
+    }
+
+    /// Write a buffered partition; its rows stay buffered if that fails.
+    fn write_partition(&mut self, key: (String, u16)) -> Result<PathBuf> {
+        let (day, common_address) = &key;
+        let directory = self
+            .directory
+            .join(format!("date={}", day))
+            .join(format!("ca={}", common_address));
+        fs::create_dir_all(&directory)?;
+        let path = directory.join(format!(
+            "part-{}-{}.parquet",
+            self.run_id, self.files_written
+        ));
+        write_file(&path, &self.partitions[&key].rows)?;
+        self.partitions.remove(&key);
+        self.files_written += 1;
+        Ok(path)
+    }
+}
+
+fn write_file(path: &Path, rows: &[Row]) -> Result<()> {
+    let mut columns: Vec<ArrayRef> = vec![
+        Arc::new(UInt32Array::from_iter_values(
+            rows.iter().map(|row| row.ioa),
+        )),
+        Arc::new(StringArray::from_iter_values(
+            rows.iter().map(|row| row.kind),
+        )),
+        Arc::new(Float64Array::from_iter_values(
+            rows.iter().map(|row| row.value),
+        )),
+    ];
+    for flag in 0..QUALITY_COLUMNS.len() {
+        let values: Vec<bool> = rows.iter().map(|row| row.quality[flag]).collect();
+        columns.push(Arc::new(BooleanArray::from(values)));
+    }
+    columns.push(Arc::new(TimestampMillisecondArray::from_iter(
+        rows.iter().map(|row| row.timestamp),
+    )));
+    columns.push(Arc::new(
+        TimestampMillisecondArray::from_iter_values(rows.iter().map(|row| row.recorded_at))
+            .with_timezone("UTC"),
+    ));
+    let batch = RecordBatch::try_new(ParquetRecorder::schema(), columns).map_err(write_error)?;
+
+    let properties = WriterProperties::builder()
+        .set_compression(Compression::SNAPPY)
+        .build();
+    let file = File::create(path)?;
+    let mut writer =
+        ArrowWriter::try_new(file, batch.schema(), Some(properties)).map_err(write_error)?;
+    writer.write(&batch).map_err(write_error)?;
+    writer.close().map_err(write_error)?;
+    Ok(())
+}
+
+fn write_error(error: impl std::error::Error + Send + Sync + 'static) -> Iec104Error {
+    Iec104Error::Io(std::io::Error::other(error))
+}
+
+fn quality_flags(point: &DataPoint) -> [bool; 6] {
+    let quality = point.quality;
+    [
+        quality.overflow(),
+        quality.blocked(),
+        quality.substituted(),
+        quality.not_topical(),
+        quality.invalid(),
+        quality.elapsed_time_invalid(),
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use arrow_array::cast::AsArray;
+    use arrow_array::types::{Float64Type, TimestampMillisecondType, UInt32Type};
+    use parquet::arrow::arrow_reader::ParquetRecordBatchReaderBuilder;
+
+    use crate::types::{DataValue, Quality};
+
+    fn temp_directory(name: &str) -> PathBuf {
+        let directory =
+            std::env::temp_dir().join(format!("iec104-historian-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&directory);
+        directory
+    }
+
+    fn read(path: &Path) -> RecordBatch {
+        let file = File::open(path).unwrap();
+        let mut reader = ParquetRecordBatchReaderBuilder::try_new(file)
+            .unwrap()
+            .build()
+            .unwrap();
+        reader.next().unwrap().unwrap()
+    }
+
+    #[test]
+    fn test_partitioned_by_day_and_station() {
+        let directory = temp_directory("partitions");
+        let mut recorder = ParquetRecorder::new(&directory).batch_rows(3);
+        // 2024-03-01T00:00:00Z
+        let now = UNIX_EPOCH + Duration::from_secs(1_709_251_200);
+        let time = Cp56Time2a::from_bytes(&[0x2A, 0x76, 45, 13, 0x9D, 2, 24]).unwrap();
+
+        let points = [
+            DataPoint::with_timestamp(
+                100,
+                DataValue::Float(1.5),
+                Quality::Good.set_overflow(true),
+                time,
+            ),
+            DataPoint::new(101, DataValue::Single(true)),
+        ];
+        assert!(recorder.record_at(1, &points, now).unwrap().is_empty());
+        assert_eq!(recorder.buffered_rows(), 2);
+
+        let points: Vec<_> = (1..=3)
+            .map(|ioa| DataPoint::new(ioa, DataValue::Scaled(7)))
+            .collect();
+        let written = recorder.record_at(2, &points, now).unwrap();
+        assert_eq!(written.len(), 1);
+        assert!(written[0].starts_with(directory.join("date=2024-03-01").join("ca=2")));
+        assert_eq!(read(&written[0]).num_rows(), 3);
+
+        let written = recorder.flush().unwrap();
+        assert_eq!(written.len(), 2);
+        assert_eq!(recorder.buffered_rows(), 0);
+
+        let batch = read(&written[0]);
+        assert!(written[0].starts_with(directory.join("date=2024-02-29").join("ca=1")));
+        assert_eq!(batch.schema(), ParquetRecorder::schema());
+        assert_eq!(batch.num_rows(), 1);
+        assert_eq!(batch.column(0).as_primitive::<UInt32Type>().value(0), 100);
+        assert_eq!(batch.column(1).as_string::<i32>().value(0), "Float");
+        assert_eq!(batch.column(2).as_primitive::<Float64Type>().value(0), 1.5);
+        assert!(batch.column(3).as_boolean().value(0));
+        assert!(!batch.column(7).as_boolean().value(0));
+        let timestamp = batch.column(9).as_primitive::<TimestampMillisecondType>();
+        assert_eq!(timestamp.value(0), time.unix_millis());
+
+        let batch = read(&written[1]);
+        assert!(written[1].starts_with(directory.join("date=2024-03-01").join("ca=1")));
+        assert_eq!(batch.column(1).as_string::<i32>().value(0), "Single");
+        assert!(batch.column(9).is_null(0));
+        let recorded_at = batch.column(10).as_primitive::<TimestampMillisecondType>();
+        assert_eq!(recorded_at.value(0), 1_709_251_200_000);
+
+        fs::remove_dir_all(&directory).unwrap();
+    }
+
+    #[test]
+    fn test_flush_due() {
+        let directory = temp_directory("flush-due");
+        let point = DataPoint::new(1, DataValue::Counter(42));
+
+        let mut recorder = ParquetRecorder::new(&directory);
+        recorder.record(1, std::slice::from_ref(&point)).unwrap();
+        assert!(recorder.flush_due().unwrap().is_empty());
+        assert_eq!(recorder.buffered_rows(), 1);
+
+        let mut recorder = ParquetRecorder::new(&directory).flush_interval(Duration::ZERO);
+        recorder.record(1, std::slice::from_ref(&point)).unwrap();
+        assert_eq!(recorder.flush_due().unwrap().len(), 1);
+        assert_eq!(recorder.buffered_rows(), 0);
+
+        fs::remove_dir_all(&directory).unwrap();
+    }
+}
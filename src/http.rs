@@ -0,0 +1,400 @@
+//! REST gateway over a spawned client, built on [axum](https://docs.rs/axum).
+//!
+//! [`HttpGateway`] turns a [`ClientHandle`] into an axum [`Router`] that web
+//! dashboards can use without a service of their own in between:
+//!
+//! | Method and path | Response |
+//! |-----------------|----------|
+//! | `GET /status` | connection state, session parameters and link counters |
+//! | `GET /points` | every cached point, each with its `ca` |
+//! | `GET /points/{ca}` | the cached points of a station, sorted by IOA |
+//! | `GET /points/{ca}/{ioa}` | one cached point, 404 if none was received |
+//! | `POST /points/{ca}/{ioa}/command` | issue a command, see below |
+//! | `GET /events` | WebSocket event stream, see [`crate::websocket`] (`websocket` feature) |
+//!
+//! Points are in the canonical JSON form of [`crate::json`] and require
+//! [`ClientConfig::point_cache`](crate::ClientConfig::point_cache).
+//!
+//! The body of a command is a JSON command message as described in
+//! [`crate::json`]. The request completes once the station has confirmed,
+//! with `{"success": true}`, or fails with `{"success": false, "error": "..."}`
+//! and a status naming the reason:
+//!
+//! | Status | Reason |
+//! |--------|--------|
+//! | 400 | the body is not a valid command |
+//! | 403 | the station was not added with [`HttpGateway::station`] |
+//! | 409 | the station confirmed negatively |
+//! | 503 | the client is not connected |
+//! | 504 | no confirmation within the command timeout |
+//! | 502 | any other failure |
+//!
+//! # Example
+//!
+//! ```rust,ignore
+//! let (handle, _task) = client.spawn();
+//! let app = HttpGateway::new().station(1).router(handle);
+//! let listener = tokio::net::TcpListener::bind("0.0.0.0:8080").await?;
+//! axum::serve(listener, app).await?;
+//! ```
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use axum::body::Bytes;
+use axum::extract::{Path, State};
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+use axum::routing::{get, post};
+use axum::{Json, Router};
+use serde_json::{json, Value};
+
+use crate::client::SessionInfo;
+use crate::error::Iec104Error;
+use crate::handle::ClientHandle;
+use crate::json::{parse_command, point_value};
+
+/// Default time to wait for the confirmation of a command.
+pub const DEFAULT_COMMAND_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Builds the REST API of a spawned client.
+///
+/// Points of every station can be read; commands are only accepted for the
+/// stations added with [`station`](Self::station).
+#[derive(Debug, Clone)]
+pub struct HttpGateway {
+    stations: Vec<u16>,
+    command_timeout: Duration,
+}
+
+impl Default for HttpGateway {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl HttpGateway {
+    /// Create a gateway that accepts no commands yet.
+    pub fn new() -> Self {
+        Self {
+            stations: Vec::new(),
+            command_timeout: DEFAULT_COMMAND_TIMEOUT,
+        }
+    }
+
+    /// Accept commands for a station.
+    pub fn station(mut self, common_address: u16) -> Self {
+        self.stations.push(common_address);
+        self
+    }
+
+    /// Time to wait for a station to confirm a command.
+    pub fn command_timeout(mut self, timeout: Duration) -> Self {
+        self.command_timeout = timeout;
+        self
+    }
+
+    /// Build the router, to be served or nested in an application.
+    pub fn router(self, handle: ClientHandle) -> Router {
+        #[cfg(feature = "websocket")]
+        let events = crate::websocket::router(handle.clone());
+        let state = Arc::new(Gateway {
+            handle,
+            config: self,
+        });
+        let router = Router::new()
+            .route("/status", get(status))
+            .route("/points", get(all_points))
+            .route("/points/{ca}", get(station_points))
+            .route("/points/{ca}/{ioa}", get(point))
+            .route("/points/{ca}/{ioa}/command", post(command))
+            .with_state(state);
+        #[cfg(feature = "websocket")]
+        let router = router.merge(events);
+        router
+    }
+}
+
+struct Gateway {
+    handle: ClientHandle,
+    config: HttpGateway,
+}
+
+type Shared = State<Arc<Gateway>>;
+
+/// A failed request, answered with a status and a JSON body.
+struct Failure(StatusCode, Value);
+
+impl Failure {
+    fn new(status: StatusCode, err: impl ToString) -> Self {
+        Self(
+            status,
+            json!({ "success": false, "error": err.to_string() }),
+        )
+    }
+}
+
+impl From<Iec104Error> for Failure {
+    fn from(err: Iec104Error) -> Self {
+        let status = match err {
+            Iec104Error::CommandRejected { .. } => StatusCode::CONFLICT,
+            Iec104Error::CommandTimeout { .. } => StatusCode::GATEWAY_TIMEOUT,
+            Iec104Error::NotConnected | Iec104Error::ChannelClosed | Iec104Error::Connection(_) => {
+                StatusCode::SERVICE_UNAVAILABLE
+            }
+            _ => StatusCode::BAD_GATEWAY,
+        };
+        Self::new(status, err)
+    }
+}
+
+impl IntoResponse for Failure {
+    fn into_response(self) -> Response {
+        (self.0, Json(self.1)).into_response()
+    }
+}
+
+type Reply = std::result::Result<Json<Value>, Failure>;
+
+async fn status(State(gateway): Shared) -> Reply {
+    let state = gateway.handle.state().await?;
+    let session = gateway
+        .handle
+        .session_info()
+        .await?
+        .map(|s| session_json(&s));
+    let stats = gateway.handle.stats().await?;
+    Ok(Json(json!({
+        "state": format!("{:?}", state),
+        "session": session,
+        "stats": {
+            "i_frames_sent": stats.i_frames_sent,
+            "i_frames_received": stats.i_frames_received,
+            "s_frames_sent": stats.s_frames_sent,
+            "s_frames_received": stats.s_frames_received,
+            "u_frames_sent": stats.u_frames_sent,
+            "u_frames_received": stats.u_frames_received,
+            "bytes_sent": stats.bytes_sent,
+            "bytes_received": stats.bytes_received,
+            "sequence_errors": stats.sequence_errors,
+            "test_frames_sent": stats.test_frames_sent,
+            "test_frames_confirmed": stats.test_frames_confirmed,
+            "t1_timeouts": stats.t1_timeouts,
+            "test_frame_timeouts": stats.test_frame_timeouts,
+            "unacknowledged_sends": stats.unacknowledged_sends,
+            "unacknowledged_receives": stats.unacknowledged_receives,
+        },
+    })))
+}
+
+/// Session parameters as reported by `/status`.
+pub(crate) fn session_json(session: &SessionInfo) -> Value {
+    json!({
+        "role": format!("{:?}", session.role),
+        "peer_address": session.peer_address.to_string(),
+        "local_address": session.local_address.to_string(),
+        "k": session.k,
+        "w": session.w,
+        "t1_ms": session.t1_timeout.as_millis() as u64,
+        "t2_ms": session.t2_timeout.as_millis() as u64,
+        "t3_ms": session.t3_timeout.as_millis() as u64,
+    })
+}
+
+async fn all_points(State(gateway): Shared) -> Reply {
+    let mut values: Vec<_> = gateway.handle.values().await?.into_iter().collect();
+    values.sort_by_key(|(key, _)| *key);
+    let points = values
+        .iter()
+        .map(|((common_address, _), point)| {
+            let mut value = point_value(point);
+            value["ca"] = json!(common_address);
+            value
+        })
+        .collect();
+    Ok(Json(Value::Array(points)))
+}
+
+async fn station_points(State(gateway): Shared, Path(ca): Path<u16>) -> Reply {
+    let mut values: Vec<_> = gateway
+        .handle
+        .values()
+        .await?
+        .into_iter()
+        .filter(|((common_address, _), _)| *common_address == ca)
+        .collect();
+    values.sort_by_key(|(key, _)| *key);
+    let points = values.iter().map(|(_, point)| point_value(point)).collect();
+    Ok(Json(Value::Array(points)))
+}
+
+async fn point(State(gateway): Shared, Path((ca, ioa)): Path<(u16, u32)>) -> Reply {
+    match gateway.handle.value(ca, ioa).await? {
+        Some(point) => Ok(Json(point_value(&point))),
+        None => Err(Failure(
+            StatusCode::NOT_FOUND,
+            json!({ "error": format!("No value received for CA {} IOA {}", ca, ioa) }),
+        )),
+    }
+}
+
+async fn command(State(gateway): Shared, Path((ca, ioa)): Path<(u16, u32)>, body: Bytes) -> Reply {
+    if !gateway.config.stations.contains(&ca) {
+        return Err(Failure::new(
+            StatusCode::FORBIDDEN,
+            format!("Commands to CA {} are not allowed", ca),
+        ));
+    }
+    let (command, qualifier) =
+        parse_command(ioa, &body).map_err(|err| Failure::new(StatusCode::BAD_REQUEST, err))?;
+    let completion = gateway.handle.command(ca, command, qualifier).await?;
+    tokio::time::timeout(gateway.config.command_timeout, completion.confirmed())
+        .await
+        .unwrap_or(Err(Iec104Error::CommandTimeout {
+            type_id: command.type_id(),
+            ioa,
+        }))?;
+    Ok(Json(json!({ "success": true })))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::{SinkExt, StreamExt};
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::{TcpListener, TcpStream};
+    use tokio_util::codec::Framed;
+
+    use crate::codec::{Apdu, Iec104Codec};
+    use crate::types::{Apci, AsduHeader, Cot, TypeId, UFunction};
+    use crate::{Asdu, ClientConfig, Iec104Client};
+
+    /// Serve `router` on a local port for a single HTTP/1.1 request.
+    async fn call(router: &Router, method: &str, uri: &str, body: &str) -> (StatusCode, Value) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let router = router.clone();
+        tokio::spawn(async move { axum::serve(listener, router).await });
+
+        let mut stream = TcpStream::connect(addr).await.unwrap();
+        let request = format!(
+            "{method} {uri} HTTP/1.1\r\nHost: {addr}\r\nContent-Length: {}\r\n\
+             Connection: close\r\n\r\n{body}",
+            body.len()
+        );
+        stream.write_all(request.as_bytes()).await.unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).await.unwrap();
+        let (head, body) = response.split_once("\r\n\r\n").unwrap();
+        let status = head.split(' ').nth(1).unwrap().parse().unwrap();
+        (StatusCode::from_u16(status).unwrap(), serde_json::from_str(body).unwrap())
+    }
+
+    #[tokio::test]
+    async fn test_gateway() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            let (socket, _) = listener.accept().await.unwrap();
+            let mut server = Framed::new(socket, Iec104Codec::new());
+            server.next().await.unwrap().unwrap();
+            server
+                .send(Apdu::u_frame(UFunction::StartDtCon))
+                .await
+                .unwrap();
+
+            let header = AsduHeader::new(TypeId::MeasuredFloat, 1, Cot::Spontaneous, 1);
+            let mut data = Asdu::new(header);
+            data.raw_data = bytes::Bytes::from_static(&[100, 0, 0, 0x00, 0x00, 0xC0, 0x3F, 0]);
+            server.send(Apdu::i_frame(0, 0, data)).await.unwrap();
+
+            // Confirm every command
+            let mut send_seq = 1;
+            while let Some(Ok(apdu)) = server.next().await {
+                if let (Apci::IFrame { .. }, Some(mut asdu)) = (apdu.apci, apdu.asdu) {
+                    asdu.header.cot = Cot::ActivationConfirm;
+                    server.send(Apdu::i_frame(send_seq, 1, asdu)).await.unwrap();
+                    send_seq += 1;
+                }
+            }
+        });
+
+        let mut client = Iec104Client::new(ClientConfig::new(addr.to_string()).point_cache(true));
+        client.connect().await.unwrap();
+        client.start_dt().await.unwrap();
+        let (handle, _task) = client.spawn();
+        let router = HttpGateway::new().station(1).router(handle);
+
+        let (status, body) = call(&router, "GET", "/status", "").await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["state"], "Active");
+        assert_eq!(body["session"]["k"], 12);
+
+        let mut point = call(&router, "GET", "/points/1/100", "").await;
+        for _ in 0..100 {
+            if point.0 == StatusCode::OK {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+            point = call(&router, "GET", "/points/1/100", "").await;
+        }
+        assert_eq!(point.0, StatusCode::OK);
+        assert_eq!(point.1["value"], 1.5);
+        let (_, body) = call(&router, "GET", "/points", "").await;
+        assert_eq!(body[0]["ca"], 1);
+        assert_eq!(body[0]["ioa"], 100);
+        let (_, body) = call(&router, "GET", "/points/2", "").await;
+        assert_eq!(body, json!([]));
+        let (status, _) = call(&router, "GET", "/points/1/101", "").await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+
+        let single = r#"{"type": "Single", "value": true}"#;
+        let (status, body) = call(&router, "POST", "/points/1/5/command", single).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body, json!({ "success": true }));
+        let (status, body) = call(&router, "POST", "/points/2/5/command", single).await;
+        assert_eq!(status, StatusCode::FORBIDDEN);
+        assert_eq!(body["success"], false);
+        let (status, _) = call(&router, "POST", "/points/1/5/command", "on").await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+    }
+
+    #[tokio::test]
+    async fn test_command_failures() {
+        let (mut client, mut server) = crate::testing::pair().await.unwrap();
+        server.serve_ioas([5, 7]);
+        tokio::spawn(async move {
+            // IOA 6 is rejected by the station, IOA 7 confirmed too late
+            while let Ok(asdu) = server.recv_asdu().await {
+                if asdu.raw_data.first() == Some(&7) {
+                    tokio::time::sleep(Duration::from_millis(200)).await;
+                }
+                server.respond(&asdu, Cot::ActivationConfirm).await?;
+            }
+            Ok::<_, Iec104Error>(())
+        });
+        client.start_dt().await.unwrap();
+        let (handle, task) = client.spawn();
+        let router = HttpGateway::new()
+            .station(1)
+            .command_timeout(Duration::from_millis(100))
+            .router(handle.clone());
+
+        let single = r#"{"type": "Single", "value": true}"#;
+        let (status, _) = call(&router, "POST", "/points/1/5/command", single).await;
+        assert_eq!(status, StatusCode::OK);
+        let (status, body) = call(&router, "POST", "/points/1/6/command", single).await;
+        assert_eq!(status, StatusCode::CONFLICT);
+        assert_eq!(body["success"], false);
+        let (status, _) = call(&router, "POST", "/points/1/7/command", single).await;
+        assert_eq!(status, StatusCode::GATEWAY_TIMEOUT);
+
+        handle.disconnect().await.unwrap();
+        task.await.unwrap().unwrap();
+        let (status, body) = call(&router, "POST", "/points/1/5/command", single).await;
+        assert_eq!(status, StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(body["success"], false);
+        let (status, _) = call(&router, "GET", "/status", "").await;
+        assert_eq!(status, StatusCode::SERVICE_UNAVAILABLE);
+    }
+}
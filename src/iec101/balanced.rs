@@ -0,0 +1,365 @@
+//! Balanced link layer of IEC 60870-5-101.
+
+use std::borrow::Cow;
+use std::collections::VecDeque;
+
+use futures::{SinkExt, StreamExt};
+use tokio::time::{timeout_at, Instant};
+use tokio_util::codec::Framed;
+
+use super::frame::{Control, Ft12Codec, Ft12Frame, PrimaryFunction, SecondaryFunction};
+use super::{expect_ack, unexpected, Iec101Config};
+use crate::error::{Iec104Error, Result};
+use crate::transport::AsyncStream;
+use crate::types::Asdu;
+
+/// Balanced IEC 101 link, in which either station may send at any time.
+///
+/// Each end is primary for the frames it initiates and secondary for those
+/// of the other end. [`send_asdu`](Self::send_asdu) sends user data that
+/// the remote link layer confirms, repeating it with the same frame count
+/// bit (FCB) until it does; received user data is confirmed, and repeated
+/// frames are recognized by their FCB and delivered once. Requests of the
+/// remote station are answered whichever method is waiting.
+pub struct BalancedLink {
+    framed: Framed<Box<dyn AsyncStream>, Ft12Codec>,
+    config: Iec101Config,
+    /// FCB of the next confirmed frame sent
+    send_fcb: bool,
+    /// FCB expected on the next confirmed frame received (None before the
+    /// remote station reset the link)
+    recv_fcb: Option<bool>,
+    received: VecDeque<Result<Asdu>>,
+}
+
+impl std::fmt::Debug for BalancedLink {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("BalancedLink")
+            .field("config", &self.config)
+            .field("send_fcb", &self.send_fcb)
+            .field("recv_fcb", &self.recv_fcb)
+            .field("received", &self.received.len())
+            .finish_non_exhaustive()
+    }
+}
+
+impl BalancedLink {
+    /// Create a link over `stream`, e.g. a port from
+    /// [`serial_port`](super::serial_port).
+    pub fn new(stream: impl AsyncStream, config: Iec101Config) -> Self {
+        let codec = Ft12Codec::new(config.link_address_size);
+        Self {
+            framed: Framed::new(Box::new(stream), codec),
+            config,
+            send_fcb: false,
+            recv_fcb: None,
+            received: VecDeque::new(),
+        }
+    }
+
+    /// The parameters of the link.
+    pub fn config(&self) -> &Iec101Config {
+        &self.config
+    }
+
+    /// Set up the link: request its status until the remote station
+    /// answers, then reset it.
+    pub async fn start(&mut self) -> Result<()> {
+        let status = self
+            .request(PrimaryFunction::RequestLinkStatus, None)
+            .await?;
+        if status.secondary_function() != Some(SecondaryFunction::LinkStatus) {
+            return Err(unexpected(status));
+        }
+        self.reset().await
+    }
+
+    /// Reset the remote link, restarting its frame count.
+    pub async fn reset(&mut self) -> Result<()> {
+        let response = self.request(PrimaryFunction::ResetRemoteLink, None).await?;
+        expect_ack(response)?;
+        self.send_fcb = true;
+        Ok(())
+    }
+
+    /// Check that the remote link layer responds.
+    pub async fn test_link(&mut self) -> Result<()> {
+        let response = self.request(PrimaryFunction::TestLink, None).await?;
+        expect_ack(response)?;
+        self.send_fcb = !self.send_fcb;
+        Ok(())
+    }
+
+    /// Send an ASDU and wait for the remote link layer to confirm it.
+    pub async fn send_asdu(&mut self, asdu: &Asdu) -> Result<()> {
+        let data = self.config.encode_asdu(asdu)?;
+        let response = self
+            .request(PrimaryFunction::UserDataConfirmed, Some(data))
+            .await?;
+        expect_ack(response)?;
+        self.send_fcb = !self.send_fcb;
+        Ok(())
+    }
+
+    /// Receive the next ASDU of the remote station.
+    ///
+    /// An ASDU that cannot be read is returned as an error once; the link
+    /// stays usable.
+    pub async fn recv_asdu(&mut self) -> Result<Asdu> {
+        loop {
+            if let Some(received) = self.received.pop_front() {
+                return received;
+            }
+            let frame = self.next_frame().await?;
+            if frame.control().prm {
+                self.answer(frame).await?;
+            }
+        }
+    }
+
+    /// Send a primary frame and wait for the secondary response, repeating
+    /// the frame when none arrives in time or the remote station is busy.
+    async fn request(
+        &mut self,
+        function: PrimaryFunction,
+        asdu: Option<bytes::Bytes>,
+    ) -> Result<Control> {
+        let counted = matches!(
+            function,
+            PrimaryFunction::UserDataConfirmed | PrimaryFunction::TestLink
+        );
+        let control = Control {
+            dir: self.config.direction,
+            fcb: counted && self.send_fcb,
+            fcv: counted,
+            ..Control::primary(function)
+        };
+        let address = self.config.link_address;
+        let frame = match asdu {
+            Some(asdu) => Ft12Frame::Variable {
+                control,
+                address,
+                asdu,
+            },
+            None => Ft12Frame::Fixed { control, address },
+        };
+
+        for _ in 0..=self.config.retries {
+            self.framed.send(frame.clone()).await?;
+            let deadline = Instant::now() + self.config.response_timeout;
+            while let Ok(received) = timeout_at(deadline, self.next_frame()).await {
+                let received = received?;
+                if received.control().prm {
+                    self.answer(received).await?;
+                    continue;
+                }
+                let response = received.control();
+                if response.secondary_function() != Some(SecondaryFunction::Nack) {
+                    return Ok(response);
+                }
+                // Busy: repeat once the response time has passed
+                tokio::time::sleep_until(deadline).await;
+                break;
+            }
+        }
+        Err(Iec104Error::Connection(Cow::Owned(format!(
+            "No link layer response to {:?} after {} attempt(s)",
+            function,
+            u32::from(self.config.retries) + 1
+        ))))
+    }
+
+    /// Respond to a primary frame of the remote station.
+    async fn answer(&mut self, frame: Ft12Frame) -> Result<()> {
+        let control = frame.control();
+        let response = match control.primary_function() {
+            Some(PrimaryFunction::ResetRemoteLink) => {
+                self.recv_fcb = Some(true);
+                SecondaryFunction::Ack
+            }
+            Some(PrimaryFunction::TestLink) => {
+                self.count(control);
+                SecondaryFunction::Ack
+            }
+            Some(PrimaryFunction::UserDataConfirmed) => {
+                if self.count(control) {
+                    self.deliver(frame);
+                }
+                SecondaryFunction::Ack
+            }
+            Some(PrimaryFunction::UserDataUnconfirmed) => {
+                self.deliver(frame);
+                return Ok(());
+            }
+            Some(PrimaryFunction::RequestLinkStatus) => SecondaryFunction::LinkStatus,
+            _ => SecondaryFunction::LinkNotImplemented,
+        };
+        let control = Control {
+            dir: self.config.direction,
+            ..Control::secondary(response)
+        };
+        let address = self.config.link_address;
+        self.framed
+            .send(Ft12Frame::Fixed { control, address })
+            .await
+    }
+
+    /// Check the frame count bit of a received frame; returns whether the
+    /// frame is new rather than a repetition.
+    fn count(&mut self, control: Control) -> bool {
+        if !control.fcv {
+            return true;
+        }
+        if self.recv_fcb == Some(!control.fcb) {
+            return false;
+        }
+        self.recv_fcb = Some(!control.fcb);
+        true
+    }
+
+    fn deliver(&mut self, frame: Ft12Frame) {
+        if let Ft12Frame::Variable { asdu, .. } = frame {
+            self.received.push_back(self.config.decode_asdu(&asdu));
+        }
+    }
+
+    async fn next_frame(&mut self) -> Result<Ft12Frame> {
+        self.framed
+            .next()
+            .await
+            .unwrap_or_else(|| Err(Iec104Error::Connection(Cow::Borrowed("Link closed"))))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::{Cot, TypeId};
+
+    /// The remote station's side of a link, speaking raw frames.
+    struct Remote(Framed<tokio::io::DuplexStream, Ft12Codec>);
+
+    impl Remote {
+        async fn expect(&mut self) -> (Control, Option<bytes::Bytes>) {
+            match self.0.next().await.unwrap().unwrap() {
+                Ft12Frame::Fixed { control, .. } => (control, None),
+                Ft12Frame::Variable { control, asdu, .. } => (control, Some(asdu)),
+                Ft12Frame::Ack => panic!("single character from the link"),
+            }
+        }
+
+        async fn send(&mut self, control: Control, asdu: Option<&'static [u8]>) {
+            let frame = match asdu {
+                Some(asdu) => Ft12Frame::Variable {
+                    control,
+                    address: 1,
+                    asdu: bytes::Bytes::from_static(asdu),
+                },
+                None => Ft12Frame::Fixed {
+                    control,
+                    address: 1,
+                },
+            };
+            self.0.send(frame).await.unwrap();
+        }
+    }
+
+    fn pair(config: Iec101Config) -> (BalancedLink, Remote) {
+        let (local, remote) = tokio::io::duplex(1024);
+        let link = BalancedLink::new(local, config);
+        (link, Remote(Framed::new(remote, Ft12Codec::new(1))))
+    }
+
+    #[tokio::test]
+    async fn test_start_and_send() {
+        let config = Iec101Config::new(1).response_timeout(std::time::Duration::from_millis(50));
+        let (mut link, mut remote) = pair(config);
+        let station = tokio::spawn(async move {
+            let (control, _) = remote.expect().await;
+            assert_eq!(
+                control.primary_function(),
+                Some(PrimaryFunction::RequestLinkStatus)
+            );
+            assert!(control.dir);
+            remote
+                .send(Control::secondary(SecondaryFunction::LinkStatus), None)
+                .await;
+            let (control, _) = remote.expect().await;
+            assert_eq!(
+                control.primary_function(),
+                Some(PrimaryFunction::ResetRemoteLink)
+            );
+            remote.0.send(Ft12Frame::Ack).await.unwrap();
+
+            // The first interrogation goes unanswered and is repeated
+            let (first, asdu) = remote.expect().await;
+            assert!(first.fcv && first.fcb);
+            assert_eq!(
+                &asdu.unwrap()[..],
+                &[0x64, 0x01, 0x06, 0x01, 0x00, 0x00, 0x14]
+            );
+            let (repeated, _) = remote.expect().await;
+            assert_eq!(repeated, first);
+            remote
+                .send(Control::secondary(SecondaryFunction::Ack), None)
+                .await;
+            remote
+        });
+
+        link.start().await.unwrap();
+        link.send_asdu(&Asdu::interrogation_command(1, 20))
+            .await
+            .unwrap();
+        assert!(!link.send_fcb);
+        station.await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_receive() {
+        let (mut link, mut remote) = pair(Iec101Config::new(1).direction(false));
+        // M_SP_NA_1, spontaneous, CA 1, IOA 0x0010 on
+        const DATA: &[u8] = &[0x01, 0x01, 0x03, 0x01, 0x10, 0x00, 0x01];
+        let user_data = |fcb| Control {
+            dir: true,
+            fcb,
+            fcv: true,
+            ..Control::primary(PrimaryFunction::UserDataConfirmed)
+        };
+        let station = tokio::spawn(async move {
+            remote
+                .send(Control::primary(PrimaryFunction::ResetRemoteLink), None)
+                .await;
+            let (control, _) = remote.expect().await;
+            assert_eq!(control.secondary_function(), Some(SecondaryFunction::Ack));
+            assert!(!control.dir);
+            // Sent twice as if the first confirmation was lost, then a new one
+            for fcb in [true, true, false] {
+                remote.send(user_data(fcb), Some(DATA)).await;
+                let (control, _) = remote.expect().await;
+                assert_eq!(control.secondary_function(), Some(SecondaryFunction::Ack));
+            }
+            remote
+                .send(Control::primary(PrimaryFunction::RequestClass1), None)
+                .await;
+            let (control, _) = remote.expect().await;
+            assert_eq!(
+                control.secondary_function(),
+                Some(SecondaryFunction::LinkNotImplemented)
+            );
+            remote
+        });
+
+        for _ in 0..2 {
+            let asdu = link.recv_asdu().await.unwrap();
+            assert_eq!(asdu.header.type_id, TypeId::SinglePoint);
+            assert_eq!(asdu.header.cot, Cot::Spontaneous);
+        }
+        // Answering the last request, with no more user data to return
+        let (more, station) = tokio::join!(
+            tokio::time::timeout(std::time::Duration::from_millis(100), link.recv_asdu()),
+            station
+        );
+        assert!(more.is_err());
+        station.unwrap();
+    }
+}
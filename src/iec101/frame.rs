@@ -0,0 +1,469 @@
+//! FT1.2 frame format of IEC 60870-5-1 as used by IEC 60870-5-101.
+
+use bytes::{Buf, BufMut, Bytes, BytesMut};
+use tokio_util::codec::{Decoder, Encoder};
+
+use crate::error::{Iec104Error, Result};
+
+/// Single control character: positive acknowledgement.
+pub const SINGLE_CHAR_ACK: u8 = 0xE5;
+
+/// Start character of fixed-length frames.
+pub const FIXED_START: u8 = 0x10;
+
+/// Start character of variable-length frames.
+pub const VARIABLE_START: u8 = 0x68;
+
+/// End character of both frame formats.
+pub const END: u8 = 0x16;
+
+/// Function codes of primary (initiating) frames.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[repr(u8)]
+pub enum PrimaryFunction {
+    /// Reset of remote link
+    ResetRemoteLink = 0,
+    /// Reset of user process
+    ResetUserProcess = 1,
+    /// Test function for link (balanced mode)
+    TestLink = 2,
+    /// User data, to be confirmed
+    UserDataConfirmed = 3,
+    /// User data, no reply expected
+    UserDataUnconfirmed = 4,
+    /// Request for access demand (unbalanced mode)
+    RequestAccessDemand = 8,
+    /// Request status of link
+    RequestLinkStatus = 9,
+    /// Request user data class 1 (unbalanced mode)
+    RequestClass1 = 10,
+    /// Request user data class 2 (unbalanced mode)
+    RequestClass2 = 11,
+}
+
+impl PrimaryFunction {
+    /// Read a function code, `None` for reserved values.
+    pub const fn from_u8(value: u8) -> Option<Self> {
+        Some(match value {
+            0 => Self::ResetRemoteLink,
+            1 => Self::ResetUserProcess,
+            2 => Self::TestLink,
+            3 => Self::UserDataConfirmed,
+            4 => Self::UserDataUnconfirmed,
+            8 => Self::RequestAccessDemand,
+            9 => Self::RequestLinkStatus,
+            10 => Self::RequestClass1,
+            11 => Self::RequestClass2,
+            _ => return None,
+        })
+    }
+}
+
+/// Function codes of secondary (responding) frames.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[repr(u8)]
+pub enum SecondaryFunction {
+    /// Positive acknowledgement
+    Ack = 0,
+    /// Message not accepted, link busy
+    Nack = 1,
+    /// User data (unbalanced mode)
+    UserData = 8,
+    /// Requested data not available (unbalanced mode)
+    NoData = 9,
+    /// Status of link or access demand
+    LinkStatus = 11,
+    /// Link service not functioning
+    LinkNotFunctioning = 14,
+    /// Link service not implemented
+    LinkNotImplemented = 15,
+}
+
+impl SecondaryFunction {
+    /// Read a function code, `None` for reserved values.
+    pub const fn from_u8(value: u8) -> Option<Self> {
+        Some(match value {
+            0 => Self::Ack,
+            1 => Self::Nack,
+            8 => Self::UserData,
+            9 => Self::NoData,
+            11 => Self::LinkStatus,
+            14 => Self::LinkNotFunctioning,
+            15 => Self::LinkNotImplemented,
+            _ => return None,
+        })
+    }
+}
+
+/// Link control field.
+///
+/// Bits 5 and 4 mean FCB and FCV in primary frames, ACD and DFC in
+/// secondary frames; see [`acd`](Self::acd) and [`dfc`](Self::dfc).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Control {
+    /// Direction (balanced mode): set on frames of the controlling station
+    pub dir: bool,
+    /// Primary message: sent by the station initiating the transmission
+    pub prm: bool,
+    /// Frame count bit (primary) or access demand (secondary)
+    pub fcb: bool,
+    /// Frame count bit valid (primary) or data flow control (secondary)
+    pub fcv: bool,
+    /// Function code (0-15)
+    pub function: u8,
+}
+
+impl Control {
+    /// Control field of a primary frame, without a valid frame count bit.
+    pub const fn primary(function: PrimaryFunction) -> Self {
+        Self {
+            dir: false,
+            prm: true,
+            fcb: false,
+            fcv: false,
+            function: function as u8,
+        }
+    }
+
+    /// Control field of a secondary frame.
+    pub const fn secondary(function: SecondaryFunction) -> Self {
+        Self {
+            dir: false,
+            prm: false,
+            fcb: false,
+            fcv: false,
+            function: function as u8,
+        }
+    }
+
+    /// Read a control field octet.
+    pub const fn from_u8(value: u8) -> Self {
+        Self {
+            dir: value & 0x80 != 0,
+            prm: value & 0x40 != 0,
+            fcb: value & 0x20 != 0,
+            fcv: value & 0x10 != 0,
+            function: value & 0x0F,
+        }
+    }
+
+    /// Encode the control field octet.
+    pub const fn as_u8(&self) -> u8 {
+        (self.dir as u8) << 7
+            | (self.prm as u8) << 6
+            | (self.fcb as u8) << 5
+            | (self.fcv as u8) << 4
+            | (self.function & 0x0F)
+    }
+
+    /// Access demand: the secondary station has class 1 data waiting.
+    pub const fn acd(&self) -> bool {
+        self.fcb
+    }
+
+    /// Data flow control: the secondary station cannot take more user data.
+    pub const fn dfc(&self) -> bool {
+        self.fcv
+    }
+
+    /// Function of a primary frame.
+    pub const fn primary_function(&self) -> Option<PrimaryFunction> {
+        if self.prm {
+            PrimaryFunction::from_u8(self.function)
+        } else {
+            None
+        }
+    }
+
+    /// Function of a secondary frame.
+    pub const fn secondary_function(&self) -> Option<SecondaryFunction> {
+        if self.prm {
+            None
+        } else {
+            SecondaryFunction::from_u8(self.function)
+        }
+    }
+}
+
+/// An FT1.2 frame.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Ft12Frame {
+    /// Single control character 0xE5, a positive acknowledgement
+    Ack,
+    /// Fixed-length frame: control field and link address
+    Fixed {
+        /// Control field
+        control: Control,
+        /// Link address (0 without an address field)
+        address: u16,
+    },
+    /// Variable-length frame carrying an ASDU in the layout of the link
+    Variable {
+        /// Control field
+        control: Control,
+        /// Link address (0 without an address field)
+        address: u16,
+        /// ASDU octets
+        asdu: Bytes,
+    },
+}
+
+impl Ft12Frame {
+    /// Control field; a single character reads as a secondary ACK.
+    pub fn control(&self) -> Control {
+        match self {
+            Self::Ack => Control::secondary(SecondaryFunction::Ack),
+            Self::Fixed { control, .. } | Self::Variable { control, .. } => *control,
+        }
+    }
+}
+
+/// Codec of FT1.2 frames for tokio.
+///
+/// Frames with a wrong length, checksum or end character are discarded as
+/// IEC 60870-5-2 requires; decoding resumes at the next start character.
+#[derive(Debug, Clone)]
+pub struct Ft12Codec {
+    address_size: usize,
+}
+
+impl Ft12Codec {
+    /// Create a codec for link address fields of `address_size` octets
+    /// (0, 1 or 2).
+    pub fn new(address_size: usize) -> Self {
+        Self {
+            address_size: address_size.min(2),
+        }
+    }
+
+    fn read_address(&self, bytes: &[u8]) -> u16 {
+        match self.address_size {
+            0 => 0,
+            1 => u16::from(bytes[0]),
+            _ => u16::from_le_bytes([bytes[0], bytes[1]]),
+        }
+    }
+
+    fn write_address(&self, address: u16, dst: &mut BytesMut) -> Result<()> {
+        match self.address_size {
+            0 => {}
+            1 => dst.put_u8(
+                u8::try_from(address)
+                    .map_err(|_| Iec104Error::Codec("Link address exceeds one octet".into()))?,
+            ),
+            _ => dst.put_u16_le(address),
+        }
+        Ok(())
+    }
+}
+
+fn checksum(bytes: &[u8]) -> u8 {
+    bytes.iter().fold(0u8, |sum, &byte| sum.wrapping_add(byte))
+}
+
+impl Decoder for Ft12Codec {
+    type Item = Ft12Frame;
+    type Error = Iec104Error;
+
+    fn decode(&mut self, src: &mut BytesMut) -> Result<Option<Self::Item>> {
+        loop {
+            match src
+                .iter()
+                .position(|&byte| matches!(byte, SINGLE_CHAR_ACK | FIXED_START | VARIABLE_START))
+            {
+                Some(start) => src.advance(start),
+                None => {
+                    src.clear();
+                    return Ok(None);
+                }
+            }
+
+            match src[0] {
+                SINGLE_CHAR_ACK => {
+                    src.advance(1);
+                    return Ok(Some(Ft12Frame::Ack));
+                }
+                FIXED_START => {
+                    let length = 4 + self.address_size;
+                    if src.len() < length {
+                        return Ok(None);
+                    }
+                    let body = &src[1..length - 2];
+                    if src[length - 1] != END || src[length - 2] != checksum(body) {
+                        src.advance(1);
+                        continue;
+                    }
+                    let control = Control::from_u8(body[0]);
+                    let address = self.read_address(&body[1..]);
+                    src.advance(length);
+                    return Ok(Some(Ft12Frame::Fixed { control, address }));
+                }
+                _ => {
+                    if src.len() < 4 {
+                        return Ok(None);
+                    }
+                    let length = usize::from(src[1]);
+                    if src[2] != src[1]
+                        || src[3] != VARIABLE_START
+                        || length < 1 + self.address_size
+                    {
+                        src.advance(1);
+                        continue;
+                    }
+                    if src.len() < length + 6 {
+                        return Ok(None);
+                    }
+                    let body = &src[4..4 + length];
+                    if src[length + 5] != END || src[length + 4] != checksum(body) {
+                        src.advance(1);
+                        continue;
+                    }
+                    let control = Control::from_u8(body[0]);
+                    let address = self.read_address(&body[1..]);
+                    let mut frame = src.split_to(length + 6);
+                    frame.advance(5 + self.address_size);
+                    frame.truncate(frame.len() - 2);
+                    return Ok(Some(Ft12Frame::Variable {
+                        control,
+                        address,
+                        asdu: frame.freeze(),
+                    }));
+                }
+            }
+        }
+    }
+}
+
+impl Encoder<Ft12Frame> for Ft12Codec {
+    type Error = Iec104Error;
+
+    fn encode(&mut self, item: Ft12Frame, dst: &mut BytesMut) -> Result<()> {
+        match item {
+            Ft12Frame::Ack => dst.put_u8(SINGLE_CHAR_ACK),
+            Ft12Frame::Fixed { control, address } => {
+                let start = dst.len();
+                dst.put_u8(FIXED_START);
+                dst.put_u8(control.as_u8());
+                self.write_address(address, dst)?;
+                let sum = checksum(&dst[start + 1..]);
+                dst.put_u8(sum);
+                dst.put_u8(END);
+            }
+            Ft12Frame::Variable {
+                control,
+                address,
+                asdu,
+            } => {
+                let length = u8::try_from(1 + self.address_size + asdu.len())
+                    .map_err(|_| Iec104Error::Codec("ASDU too long for an FT1.2 frame".into()))?;
+                dst.put_slice(&[VARIABLE_START, length, length, VARIABLE_START]);
+                let start = dst.len();
+                dst.put_u8(control.as_u8());
+                self.write_address(address, dst)?;
+                dst.put_slice(&asdu);
+                let sum = checksum(&dst[start..]);
+                dst.put_u8(sum);
+                dst.put_u8(END);
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn encode(codec: &mut Ft12Codec, frame: Ft12Frame) -> BytesMut {
+        let mut buf = BytesMut::new();
+        codec.encode(frame, &mut buf).unwrap();
+        buf
+    }
+
+    #[test]
+    fn test_control() {
+        let control = Control {
+            dir: true,
+            fcb: true,
+            fcv: true,
+            ..Control::primary(PrimaryFunction::UserDataConfirmed)
+        };
+        assert_eq!(control.as_u8(), 0xF3);
+        assert_eq!(Control::from_u8(0xF3), control);
+        assert_eq!(
+            control.primary_function(),
+            Some(PrimaryFunction::UserDataConfirmed)
+        );
+        assert_eq!(control.secondary_function(), None);
+
+        // Secondary with ACD and DFC set
+        let control = Control::from_u8(0x39);
+        assert!(control.acd() && control.dfc());
+        assert_eq!(
+            control.secondary_function(),
+            Some(SecondaryFunction::NoData)
+        );
+    }
+
+    #[test]
+    fn test_frames() {
+        let mut codec = Ft12Codec::new(1);
+        let reset = Ft12Frame::Fixed {
+            control: Control::primary(PrimaryFunction::ResetRemoteLink),
+            address: 3,
+        };
+        assert_eq!(
+            &encode(&mut codec, reset.clone())[..],
+            &[0x10, 0x40, 0x03, 0x43, 0x16]
+        );
+
+        let data = Ft12Frame::Variable {
+            control: Control::primary(PrimaryFunction::UserDataConfirmed),
+            address: 3,
+            asdu: Bytes::from_static(&[0x64, 0x01, 0x06, 0x01, 0x00, 0x00, 0x14]),
+        };
+        let bytes = encode(&mut codec, data.clone());
+        assert_eq!(&bytes[..4], &[0x68, 0x09, 0x09, 0x68]);
+        assert_eq!(bytes[bytes.len() - 1], END);
+
+        // Noise, a frame with a bad checksum and the single character
+        let mut src = BytesMut::from(&[0x00, 0xFF][..]);
+        src.extend_from_slice(&[0x10, 0x40, 0x03, 0x44, 0x16]);
+        src.extend_from_slice(&encode(&mut codec, reset.clone()));
+        src.extend_from_slice(&bytes);
+        src.put_u8(SINGLE_CHAR_ACK);
+        assert_eq!(codec.decode(&mut src).unwrap(), Some(reset));
+        assert_eq!(codec.decode(&mut src).unwrap(), Some(data));
+        assert_eq!(codec.decode(&mut src).unwrap(), Some(Ft12Frame::Ack));
+        assert_eq!(codec.decode(&mut src).unwrap(), None);
+
+        // Partial frames wait for the rest
+        let mut src = BytesMut::from(&bytes[..6]);
+        assert_eq!(codec.decode(&mut src).unwrap(), None);
+        assert_eq!(src.len(), 6);
+    }
+
+    #[test]
+    fn test_address_sizes() {
+        let frame = Ft12Frame::Fixed {
+            control: Control::secondary(SecondaryFunction::Ack),
+            address: 0x1234,
+        };
+        let mut codec = Ft12Codec::new(2);
+        let mut bytes = encode(&mut codec, frame.clone());
+        assert_eq!(bytes.len(), 6);
+        assert_eq!(codec.decode(&mut bytes).unwrap(), Some(frame.clone()));
+
+        let mut buf = BytesMut::new();
+        assert!(Ft12Codec::new(1).encode(frame, &mut buf).is_err());
+
+        let frame = Ft12Frame::Fixed {
+            control: Control::secondary(SecondaryFunction::Ack),
+            address: 0,
+        };
+        let mut codec = Ft12Codec::new(0);
+        let mut bytes = encode(&mut codec, frame.clone());
+        assert_eq!(&bytes[..], &[0x10, 0x00, 0x00, 0x16]);
+        assert_eq!(codec.decode(&mut bytes).unwrap(), Some(frame));
+    }
+}
@@ -0,0 +1,338 @@
+//! IEC 60870-5-101 over serial lines.
+//!
+//! IEC 101 carries the same ASDUs as IEC 104, framed in FT1.2
+//! ([`Ft12Codec`]) and exchanged by a link layer instead of TCP and the
+//! APCI. Its ASDU fields are often shorter: [`Iec101Config`] sets the sizes
+//! of the link address, cause of transmission, common address and IOA of a
+//! line. ASDUs are converted to and from the [`Asdu`] of this crate, so
+//! [`parse_asdu`](crate::parse_asdu), the command constructors and the
+//! encoder work unchanged.
+//!
+//! [`BalancedLink`] implements the balanced link layer, in which both
+//! stations may initiate transmissions, usually point to point over RS-232.
+//! It runs over any [`AsyncStream`](crate::AsyncStream); [`serial_port`]
+//! opens a port with the customary 8E1 character format. [`UnbalancedLink`]
+//! implements the unbalanced link layer, in which this end polls one or
+//! more secondary stations, such as meters on a shared line, by their link
+//! address.
+//!
+//! # Example
+//!
+//! ```rust,ignore
+//! let config = Iec101Config::new(1).ioa_size(2);
+//! let mut link = BalancedLink::new(serial_port("/dev/ttyS0", 9600)?, config);
+//! link.start().await?;
+//! link.send_asdu(&Asdu::interrogation_command(1, 20)).await?;
+//! loop {
+//!     let asdu = link.recv_asdu().await?;
+//!     for point in parse_asdu(&asdu)? {
+//!         println!("{:?}", point);
+//!     }
+//! }
+//! ```
+
+mod balanced;
+mod frame;
+mod unbalanced;
+
+use std::time::Duration;
+
+use bytes::Bytes;
+
+use crate::error::{Iec104Error, Result};
+use crate::types::{Asdu, AsduHeader, TypeId};
+
+pub use balanced::BalancedLink;
+pub use frame::{Control, Ft12Codec, Ft12Frame, PrimaryFunction, SecondaryFunction};
+pub use unbalanced::UnbalancedLink;
+
+/// Default time to wait for the remote link layer to respond.
+pub const DEFAULT_RESPONSE_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// Default number of repetitions of an unanswered frame.
+pub const DEFAULT_RETRIES: u8 = 3;
+
+/// Link and ASDU parameters of an IEC 101 line.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Iec101Config {
+    /// Link address of the remote station
+    pub link_address: u16,
+    /// Octets of the link address field: 0 (balanced only), 1 or 2
+    pub link_address_size: usize,
+    /// Octets of the cause of transmission: 1, or 2 with originator address
+    pub cot_size: usize,
+    /// Octets of the common address of ASDU: 1 or 2
+    pub common_address_size: usize,
+    /// Octets of the information object address: 1, 2 or 3
+    pub ioa_size: usize,
+    /// DIR bit of frames sent in balanced mode, set by the controlling station
+    pub direction: bool,
+    /// Time to wait for the remote link layer to respond
+    pub response_timeout: Duration,
+    /// Repetitions of a frame left unanswered
+    pub retries: u8,
+}
+
+impl Default for Iec101Config {
+    fn default() -> Self {
+        Self::new(1)
+    }
+}
+
+impl Iec101Config {
+    /// Create a configuration for the station with this link address, with
+    /// one-octet link address, COT and common address, and two-octet IOAs.
+    pub fn new(link_address: u16) -> Self {
+        Self {
+            link_address,
+            link_address_size: 1,
+            cot_size: 1,
+            common_address_size: 1,
+            ioa_size: 2,
+            direction: true,
+            response_timeout: DEFAULT_RESPONSE_TIMEOUT,
+            retries: DEFAULT_RETRIES,
+        }
+    }
+
+    /// Set the link address of the remote station.
+    pub fn link_address(mut self, address: u16) -> Self {
+        self.link_address = address;
+        self
+    }
+
+    /// Set the size of the link address field (0 to 2 octets).
+    pub fn link_address_size(mut self, octets: usize) -> Self {
+        self.link_address_size = octets.min(2);
+        self
+    }
+
+    /// Set the size of the cause of transmission (1 or 2 octets).
+    pub fn cot_size(mut self, octets: usize) -> Self {
+        self.cot_size = octets.clamp(1, 2);
+        self
+    }
+
+    /// Set the size of the common address of ASDU (1 or 2 octets).
+    pub fn common_address_size(mut self, octets: usize) -> Self {
+        self.common_address_size = octets.clamp(1, 2);
+        self
+    }
+
+    /// Set the size of information object addresses (1 to 3 octets).
+    pub fn ioa_size(mut self, octets: usize) -> Self {
+        self.ioa_size = octets.clamp(1, 3);
+        self
+    }
+
+    /// Set the DIR bit of frames sent in balanced mode; clear it when this
+    /// end is the controlled station.
+    pub fn direction(mut self, controlling: bool) -> Self {
+        self.direction = controlling;
+        self
+    }
+
+    /// Set how long to wait for the remote link layer to respond.
+    pub fn response_timeout(mut self, timeout: Duration) -> Self {
+        self.response_timeout = timeout;
+        self
+    }
+
+    /// Set how often an unanswered frame is repeated.
+    pub fn retries(mut self, retries: u8) -> Self {
+        self.retries = retries;
+        self
+    }
+
+    /// Read an ASDU in the layout of this line.
+    ///
+    /// A one-octet global common address (255) reads as 65535, and a
+    /// missing originator address as 0.
+    pub fn decode_asdu(&self, data: &[u8]) -> Result<Asdu> {
+        let header_len = 2 + self.cot_size + self.common_address_size;
+        if data.len() < header_len {
+            return Err(Iec104Error::invalid_asdu_static("ASDU header too short"));
+        }
+        let originator = if self.cot_size == 2 { data[3] } else { 0 };
+        let ca = &data[2 + self.cot_size..header_len];
+        let common_address = match *ca {
+            [0xFF] => 0xFFFF,
+            [low] => u16::from(low),
+            [low, high] => u16::from_le_bytes([low, high]),
+            _ => unreachable!("common address of one or two octets"),
+        };
+
+        let mut asdu = Vec::with_capacity(data.len() + 4);
+        asdu.extend_from_slice(&[data[0], data[1], data[2], originator]);
+        asdu.extend_from_slice(&common_address.to_le_bytes());
+        let (header, _) = AsduHeader::parse(&asdu)?;
+        resize_ioas(&header, &data[header_len..], self.ioa_size, 3, &mut asdu)?;
+        Asdu::parse_bytes(Bytes::from(asdu))
+    }
+
+    /// Write an ASDU in the layout of this line.
+    ///
+    /// Fails when the common address or an IOA does not fit its field; the
+    /// global common address 65535 is written as 255 in one octet.
+    pub fn encode_asdu(&self, asdu: &Asdu) -> Result<Bytes> {
+        let encoded = asdu.encode();
+        let header = &asdu.header;
+        let mut data = Vec::with_capacity(encoded.len());
+        data.extend_from_slice(&encoded[..3]);
+        if self.cot_size == 2 {
+            data.push(header.originator);
+        }
+        match (self.common_address_size, header.common_address) {
+            (1, 0xFFFF) => data.push(0xFF),
+            (1, address) => data.push(u8::try_from(address).map_err(|_| {
+                Iec104Error::invalid_asdu_static("Common address exceeds one octet")
+            })?),
+            (_, address) => data.extend_from_slice(&address.to_le_bytes()),
+        }
+        resize_ioas(header, &encoded[6..], 3, self.ioa_size, &mut data)?;
+        Ok(Bytes::from(data))
+    }
+}
+
+/// Copy the information objects of an ASDU to `out`, changing the size of
+/// their IOAs from `from` to `to` octets.
+fn resize_ioas(
+    header: &AsduHeader,
+    objects: &[u8],
+    from: usize,
+    to: usize,
+    out: &mut Vec<u8>,
+) -> Result<()> {
+    let count = usize::from(header.vsq.count);
+    if from == to || count == 0 {
+        out.extend_from_slice(objects);
+        return Ok(());
+    }
+    let element_size = header.type_id.element_size();
+    // Only the first IOA can be located without knowing the element size
+    let addresses = match header.type_id {
+        TypeId::FileSegment => 1,
+        TypeId::Other(value) => {
+            return Err(Iec104Error::InvalidAsdu(
+                format!("IOAs of unknown type {} cannot be resized", value).into(),
+            ))
+        }
+        _ if header.vsq.sequence => 1,
+        _ => count,
+    };
+    let stride = if addresses == 1 {
+        objects.len().saturating_sub(from)
+    } else {
+        element_size
+    };
+    if objects.len() != addresses * (from + stride) {
+        return Err(Iec104Error::InvalidAsdu(
+            format!(
+                "{} with {} object(s) does not fit {} bytes of objects",
+                header.type_id,
+                count,
+                objects.len()
+            )
+            .into(),
+        ));
+    }
+
+    for object in objects.chunks(from + stride) {
+        let (ioa, elements) = object.split_at(from);
+        let mut bytes = [0u8; 4];
+        bytes[..from].copy_from_slice(ioa);
+        let value = u32::from_le_bytes(bytes);
+        if value >> (8 * to) != 0 {
+            return Err(Iec104Error::InvalidAsdu(
+                format!("IOA {} exceeds {} octet(s)", value, to).into(),
+            ));
+        }
+        out.extend_from_slice(&value.to_le_bytes()[..to]);
+        out.extend_from_slice(elements);
+    }
+    Ok(())
+}
+
+/// Check that a link layer response is a positive acknowledgement.
+fn expect_ack(response: Control) -> Result<()> {
+    match response.secondary_function() {
+        Some(SecondaryFunction::Ack) => Ok(()),
+        _ => Err(unexpected(response)),
+    }
+}
+
+fn unexpected(response: Control) -> Iec104Error {
+    Iec104Error::Protocol(format!("Unexpected link layer response {:?}", response).into())
+}
+
+/// Open a serial port with the character format of IEC 60870-5-101:
+/// 8 data bits, even parity, 1 stop bit.
+pub fn serial_port(path: &str, baud_rate: u32) -> Result<tokio_serial::SerialStream> {
+    use tokio_serial::{DataBits, Parity, SerialPortBuilderExt, StopBits};
+
+    tokio_serial::new(path, baud_rate)
+        .data_bits(DataBits::Eight)
+        .parity(Parity::Even)
+        .stop_bits(StopBits::One)
+        .open_native_async()
+        .map_err(|err| Iec104Error::Io(err.into()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::parse_asdu;
+    use crate::types::{Cot, DataValue};
+
+    #[test]
+    fn test_asdu_layout() {
+        // M_ME_NB_1, two objects, spontaneous, CA 7, IOAs 0x0102 and 0x0103
+        let data = [
+            0x0B, 0x02, 0x03, 0x07, 0x02, 0x01, 0x10, 0x00, 0x00, 0x03, 0x01, 0x20, 0x00, 0x00,
+        ];
+        let config = Iec101Config::new(1);
+        let asdu = config.decode_asdu(&data).unwrap();
+        assert_eq!(asdu.header.type_id, TypeId::MeasuredScaled);
+        assert_eq!(asdu.header.cot, Cot::Spontaneous);
+        assert_eq!(asdu.header.common_address, 7);
+        let points = parse_asdu(&asdu).unwrap();
+        assert_eq!(points[1].ioa, 0x0103);
+        assert_eq!(points[1].value, DataValue::Scaled(32));
+        assert_eq!(&config.encode_asdu(&asdu).unwrap()[..], &data);
+
+        // Two-octet COT and CA, three-octet IOAs: the 104 layout
+        let config = config.cot_size(2).common_address_size(2).ioa_size(3);
+        let encoded = config.encode_asdu(&asdu).unwrap();
+        assert_eq!(&encoded[..], &asdu.encode()[..]);
+        assert_eq!(config.decode_asdu(&encoded).unwrap(), asdu);
+    }
+
+    #[test]
+    fn test_asdu_limits() {
+        let config = Iec101Config::new(1).ioa_size(1);
+        let mut interrogation = Asdu::interrogation_command(0xFFFF, 20);
+        let encoded = config.encode_asdu(&interrogation).unwrap();
+        assert_eq!(&encoded[..], &[0x64, 0x01, 0x06, 0xFF, 0x00, 0x14]);
+        let decoded = config.decode_asdu(&encoded).unwrap();
+        assert_eq!(decoded.encode(), interrogation.encode());
+
+        interrogation.header.common_address = 300;
+        assert!(config.encode_asdu(&interrogation).is_err());
+
+        // IOA 0x0100 does not fit one octet
+        let mut command = Asdu::new(AsduHeader::new(
+            TypeId::SingleCommand,
+            1,
+            Cot::Activation,
+            1,
+        ));
+        command.raw_data = Bytes::from_static(&[0x00, 0x01, 0x00, 0x01]);
+        assert!(config.encode_asdu(&command).is_err());
+
+        // Object bytes not matching the VSQ
+        assert!(config
+            .decode_asdu(&[0x01, 0x02, 0x03, 0x01, 0x05, 0x01])
+            .is_err());
+    }
+}
@@ -0,0 +1,413 @@
+//! Unbalanced link layer of IEC 60870-5-101.
+
+use std::borrow::Cow;
+use std::collections::HashMap;
+
+use futures::{SinkExt, StreamExt};
+use tokio::time::{timeout_at, Instant};
+use tokio_util::codec::Framed;
+
+use super::frame::{Control, Ft12Codec, Ft12Frame, PrimaryFunction, SecondaryFunction};
+use super::{expect_ack, unexpected, Iec101Config};
+use crate::error::{Iec104Error, Result};
+use crate::transport::AsyncStream;
+use crate::types::Asdu;
+
+/// Link state of one polled station.
+#[derive(Debug, Clone, Copy)]
+struct Station {
+    /// FCB of the next counted frame sent to the station
+    fcb: bool,
+    /// ACD of the last response: class 1 data is waiting
+    access_demand: bool,
+    /// DFC of the last response: further user data would overflow
+    data_flow_control: bool,
+}
+
+/// Unbalanced IEC 101 link, in which this end is the primary station
+/// polling one or more secondary stations.
+///
+/// Secondary stations, e.g. meters sharing a multi-drop line, only transmit
+/// when asked. Each is addressed by its link address and has to be set up
+/// with [`start`](Self::start) first. [`poll`](Self::poll) requests class 1
+/// data while the station signals an access demand (ACD) and class 2 data
+/// otherwise; [`send_asdu`](Self::send_asdu) holds user data back while the
+/// station signals data flow control (DFC). Unanswered requests are repeated
+/// with the same frame count bit.
+pub struct UnbalancedLink {
+    framed: Framed<Box<dyn AsyncStream>, Ft12Codec>,
+    config: Iec101Config,
+    stations: HashMap<u16, Station>,
+}
+
+impl std::fmt::Debug for UnbalancedLink {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("UnbalancedLink")
+            .field("config", &self.config)
+            .field("stations", &self.stations)
+            .finish_non_exhaustive()
+    }
+}
+
+impl UnbalancedLink {
+    /// Create a link over `stream`, e.g. a port from
+    /// [`serial_port`](super::serial_port).
+    ///
+    /// The link address of `config` is not used: methods take the address
+    /// of the station they talk to.
+    pub fn new(stream: impl AsyncStream, config: Iec101Config) -> Self {
+        let codec = Ft12Codec::new(config.link_address_size);
+        Self {
+            framed: Framed::new(Box::new(stream), codec),
+            config,
+            stations: HashMap::new(),
+        }
+    }
+
+    /// The parameters of the link.
+    pub fn config(&self) -> &Iec101Config {
+        &self.config
+    }
+
+    /// Set up the link to a station: request its status, then reset it.
+    pub async fn start(&mut self, address: u16) -> Result<()> {
+        let status = self
+            .request(address, PrimaryFunction::RequestLinkStatus, None)
+            .await?
+            .control();
+        if status.secondary_function() != Some(SecondaryFunction::LinkStatus) {
+            return Err(unexpected(status));
+        }
+        let response = self
+            .request(address, PrimaryFunction::ResetRemoteLink, None)
+            .await?
+            .control();
+        expect_ack(response)?;
+        self.stations.insert(
+            address,
+            Station {
+                fcb: true,
+                access_demand: response.acd(),
+                data_flow_control: response.dfc(),
+            },
+        );
+        Ok(())
+    }
+
+    /// Whether the station reported class 1 data waiting in its last
+    /// response.
+    pub fn access_demand(&self, address: u16) -> bool {
+        self.stations
+            .get(&address)
+            .is_some_and(|station| station.access_demand)
+    }
+
+    /// Request data of a station: class 1 while it signals an access
+    /// demand, class 2 otherwise.
+    ///
+    /// Returns `None` when the station has no data.
+    pub async fn poll(&mut self, address: u16) -> Result<Option<Asdu>> {
+        let function = if self.station(address)?.access_demand {
+            PrimaryFunction::RequestClass1
+        } else {
+            PrimaryFunction::RequestClass2
+        };
+        let response = self.request(address, function, None).await?;
+        let control = self.counted(address, response.control());
+        match (control.secondary_function(), response) {
+            (Some(SecondaryFunction::UserData), Ft12Frame::Variable { asdu, .. }) => {
+                self.config.decode_asdu(&asdu).map(Some)
+            }
+            // A single character may stand in for "no data"
+            (Some(SecondaryFunction::NoData | SecondaryFunction::Ack), _) => Ok(None),
+            _ => Err(unexpected(control)),
+        }
+    }
+
+    /// Send an ASDU to a station and wait for it to be confirmed.
+    ///
+    /// While the station signals data flow control its status is requested
+    /// once per response timeout, up to the configured retries; the ASDU
+    /// is not sent if the station stays busy.
+    pub async fn send_asdu(&mut self, address: u16, asdu: &Asdu) -> Result<()> {
+        let data = self.config.encode_asdu(asdu)?;
+        let mut checks = 0;
+        while self.station(address)?.data_flow_control {
+            if checks == self.config.retries {
+                return Err(Iec104Error::Connection(Cow::Owned(format!(
+                    "Station {} cannot accept user data",
+                    address
+                ))));
+            }
+            checks += 1;
+            tokio::time::sleep(self.config.response_timeout).await;
+            let status = self
+                .request(address, PrimaryFunction::RequestLinkStatus, None)
+                .await?
+                .control();
+            self.update(address, status);
+        }
+
+        let response = self
+            .request(address, PrimaryFunction::UserDataConfirmed, Some(data))
+            .await?;
+        expect_ack(self.counted(address, response.control()))
+    }
+
+    fn station(&self, address: u16) -> Result<Station> {
+        self.stations.get(&address).copied().ok_or_else(|| {
+            Iec104Error::Protocol(
+                format!("Link to station {} has not been started", address).into(),
+            )
+        })
+    }
+
+    /// Record the flags of a response.
+    fn update(&mut self, address: u16, response: Control) {
+        if let Some(station) = self.stations.get_mut(&address) {
+            station.access_demand = response.acd();
+            station.data_flow_control = response.dfc();
+        }
+    }
+
+    /// Record the response to a counted frame, which toggles the FCB.
+    fn counted(&mut self, address: u16, response: Control) -> Control {
+        self.update(address, response);
+        if let Some(station) = self.stations.get_mut(&address) {
+            station.fcb = !station.fcb;
+        }
+        response
+    }
+
+    /// Send a primary frame to a station and wait for its response,
+    /// repeating the frame when none arrives in time or the station is busy.
+    async fn request(
+        &mut self,
+        address: u16,
+        function: PrimaryFunction,
+        asdu: Option<bytes::Bytes>,
+    ) -> Result<Ft12Frame> {
+        let counted = matches!(
+            function,
+            PrimaryFunction::UserDataConfirmed
+                | PrimaryFunction::RequestClass1
+                | PrimaryFunction::RequestClass2
+        );
+        let fcb = self
+            .stations
+            .get(&address)
+            .is_some_and(|station| station.fcb);
+        let control = Control {
+            fcb: counted && fcb,
+            fcv: counted,
+            ..Control::primary(function)
+        };
+        let frame = match asdu {
+            Some(asdu) => Ft12Frame::Variable {
+                control,
+                address,
+                asdu,
+            },
+            None => Ft12Frame::Fixed { control, address },
+        };
+
+        for _ in 0..=self.config.retries {
+            self.framed.send(frame.clone()).await?;
+            let deadline = Instant::now() + self.config.response_timeout;
+            while let Ok(received) = timeout_at(deadline, self.next_frame()).await {
+                let received = received?;
+                let from_station = match received {
+                    Ft12Frame::Ack => true,
+                    Ft12Frame::Fixed {
+                        control,
+                        address: from,
+                    }
+                    | Ft12Frame::Variable {
+                        control,
+                        address: from,
+                        ..
+                    } => !control.prm && from == address,
+                };
+                // Late responses of other stations are dropped
+                if !from_station {
+                    continue;
+                }
+                if received.control().secondary_function() != Some(SecondaryFunction::Nack) {
+                    return Ok(received);
+                }
+                // Busy: repeat once the response time has passed
+                tokio::time::sleep_until(deadline).await;
+                break;
+            }
+        }
+        Err(Iec104Error::Connection(Cow::Owned(format!(
+            "No response of station {} to {:?} after {} attempt(s)",
+            address,
+            function,
+            u32::from(self.config.retries) + 1
+        ))))
+    }
+
+    async fn next_frame(&mut self) -> Result<Ft12Frame> {
+        self.framed
+            .next()
+            .await
+            .unwrap_or_else(|| Err(Iec104Error::Connection(Cow::Borrowed("Link closed"))))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::TypeId;
+
+    type Remote = Framed<tokio::io::DuplexStream, Ft12Codec>;
+
+    async fn expect(remote: &mut Remote) -> (Control, u16) {
+        match remote.next().await.unwrap().unwrap() {
+            Ft12Frame::Fixed { control, address }
+            | Ft12Frame::Variable {
+                control, address, ..
+            } => (control, address),
+            Ft12Frame::Ack => panic!("single character from the primary station"),
+        }
+    }
+
+    fn respond(function: SecondaryFunction, acd: bool, dfc: bool, address: u16) -> Ft12Frame {
+        Ft12Frame::Fixed {
+            control: Control {
+                fcb: acd,
+                fcv: dfc,
+                ..Control::secondary(function)
+            },
+            address,
+        }
+    }
+
+    fn link() -> (UnbalancedLink, Remote) {
+        let (local, remote) = tokio::io::duplex(1024);
+        let config = Iec101Config::new(0).response_timeout(std::time::Duration::from_millis(50));
+        (
+            UnbalancedLink::new(local, config),
+            Framed::new(remote, Ft12Codec::new(1)),
+        )
+    }
+
+    #[tokio::test]
+    async fn test_poll() {
+        let (mut link, mut remote) = link();
+        assert!(link.poll(5).await.is_err());
+
+        let station = tokio::spawn(async move {
+            let (control, address) = expect(&mut remote).await;
+            assert_eq!(address, 5);
+            assert_eq!(
+                control.primary_function(),
+                Some(PrimaryFunction::RequestLinkStatus)
+            );
+            remote
+                .send(respond(SecondaryFunction::LinkStatus, false, false, 5))
+                .await
+                .unwrap();
+            expect(&mut remote).await;
+            remote.send(Ft12Frame::Ack).await.unwrap();
+
+            // Class 2 poll: no data, but class 1 data is waiting
+            let (control, _) = expect(&mut remote).await;
+            assert_eq!(
+                control.primary_function(),
+                Some(PrimaryFunction::RequestClass2)
+            );
+            assert!(control.fcv && control.fcb);
+            remote
+                .send(respond(SecondaryFunction::NoData, true, false, 5))
+                .await
+                .unwrap();
+
+            // Class 1 poll, answered by another station first, then repeated
+            let (control, _) = expect(&mut remote).await;
+            assert_eq!(
+                control.primary_function(),
+                Some(PrimaryFunction::RequestClass1)
+            );
+            assert!(!control.fcb);
+            remote
+                .send(respond(SecondaryFunction::NoData, false, false, 6))
+                .await
+                .unwrap();
+            let (repeated, _) = expect(&mut remote).await;
+            assert_eq!(repeated, control);
+            remote
+                .send(Ft12Frame::Variable {
+                    control: Control::secondary(SecondaryFunction::UserData),
+                    address: 5,
+                    // M_SP_NA_1, spontaneous, CA 5, IOA 0x0010 on
+                    asdu: bytes::Bytes::from_static(&[0x01, 0x01, 0x03, 0x05, 0x10, 0x00, 0x01]),
+                })
+                .await
+                .unwrap();
+            remote
+        });
+
+        link.start(5).await.unwrap();
+        assert!(link.poll(5).await.unwrap().is_none());
+        assert!(link.access_demand(5));
+        let asdu = link.poll(5).await.unwrap().unwrap();
+        assert_eq!(asdu.header.type_id, TypeId::SinglePoint);
+        assert_eq!(asdu.header.common_address, 5);
+        assert!(!link.access_demand(5));
+        station.await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_data_flow_control() {
+        let (mut link, mut remote) = link();
+        let station = tokio::spawn(async move {
+            expect(&mut remote).await;
+            remote
+                .send(respond(SecondaryFunction::LinkStatus, false, false, 1))
+                .await
+                .unwrap();
+            expect(&mut remote).await;
+            // Busy right after the reset, then free on the status request
+            remote
+                .send(respond(SecondaryFunction::Ack, false, true, 1))
+                .await
+                .unwrap();
+            let (control, _) = expect(&mut remote).await;
+            assert_eq!(
+                control.primary_function(),
+                Some(PrimaryFunction::RequestLinkStatus)
+            );
+            remote
+                .send(respond(SecondaryFunction::LinkStatus, false, false, 1))
+                .await
+                .unwrap();
+            let (control, _) = expect(&mut remote).await;
+            assert_eq!(
+                control.primary_function(),
+                Some(PrimaryFunction::UserDataConfirmed)
+            );
+            assert!(control.fcv && control.fcb);
+            remote
+                .send(respond(SecondaryFunction::Ack, false, true, 1))
+                .await
+                .unwrap();
+            // Still busy on every status request
+            for _ in 0..3 {
+                expect(&mut remote).await;
+                remote
+                    .send(respond(SecondaryFunction::LinkStatus, false, true, 1))
+                    .await
+                    .unwrap();
+            }
+            remote
+        });
+
+        link.start(1).await.unwrap();
+        let interrogation = Asdu::interrogation_command(1, 20);
+        link.send_asdu(1, &interrogation).await.unwrap();
+        assert!(link.send_asdu(1, &interrogation).await.is_err());
+        station.await.unwrap();
+    }
+}
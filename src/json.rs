@@ -0,0 +1,541 @@
+//! Canonical JSON representation of data points and ASDUs.
+//!
+//! This is the format exchanged with message buses and flow-based tools. It
+//! is kept stable across releases; fields are only ever added.
+//!
+//! # Data point
+//!
+//! ```json
+//! {
+//!   "ioa": 1001,
+//!   "type": "Float",
+//!   "value": 23.5,
+//!   "quality": {
+//!     "overflow": false, "blocked": false, "substituted": false,
+//!     "not_topical": false, "invalid": false, "elapsed_time_invalid": false
+//!   },
+//!   "timestamp": "2024-02-29T13:45:30.250",
+//!   "timestamp_invalid": false,
+//!   "summer_time": false
+//! }
+//! ```
+//!
+//! - `type` names the [`DataValue`] variant and `value` holds its content in
+//!   the shape given by [`json_schema`](crate::schema::json_schema), e.g.
+//!   `"On"` for `Double` or `{"value": -3, "transient": false}` for
+//!   `StepPosition`.
+//! - `quality` lists every flag; missing flags read as `false`.
+//! - `timestamp` is `null` without a time tag. A CP56Time2a is written as an
+//!   ISO 8601 date and time (see [`Cp56Time2a::to_iso8601`]), a CP24Time2a as
+//!   minutes and seconds of an unknown hour, e.g. `"-45:30.250"`.
+//! - `timestamp_invalid` is present with a time tag and `summer_time` with a
+//!   CP56Time2a; both read as `false` when missing.
+//!
+//! # ASDU
+//!
+//! ```json
+//! {
+//!   "type": "M_ME_TF_1",
+//!   "cot": "Spontaneous",
+//!   "ca": 1,
+//!   "originator": 0,
+//!   "test": false,
+//!   "negative": false,
+//!   "sequence": false,
+//!   "points": [ ... ]
+//! }
+//! ```
+//!
+//! `type` is the standard name of the type identification and `cot` the
+//! [`Cot`] variant name. Only ASDUs carrying data points (monitoring types
+//! and parameters of measured values) have a JSON form.
+//!
+//! # Command
+//!
+//! Control requests taken by the MQTT bridge and the HTTP gateway name the
+//! [`Command`] variant and its value; the station and object are given
+//! separately:
+//!
+//! ```json
+//! {"type": "Single", "value": true}
+//! {"type": "Double", "value": "Off", "select": true}
+//! {"type": "RegulatingStep", "value": "Higher"}
+//! {"type": "SetpointFloat", "value": 49.5}
+//! ```
+//!
+//! `select` defaults to `false` (execute).
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::command::{Command, StepCommand};
+use crate::encoder::AsduBuilder;
+use crate::error::{Iec104Error, Result};
+use crate::parser::parse_asdu;
+use crate::types::{Asdu, CommandQualifier, Cot, Cp24Time2a, Cp56Time2a, DataPoint, DataValue};
+use crate::types::{DoubleCommandState, Qos, Quality, Timestamp, TypeId};
+
+#[derive(Serialize, Deserialize)]
+struct PointJson {
+    ioa: u32,
+    #[serde(rename = "type")]
+    kind: String,
+    value: Value,
+    #[serde(default)]
+    quality: Quality,
+    #[serde(default)]
+    timestamp: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    timestamp_invalid: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    summer_time: Option<bool>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct AsduJson {
+    #[serde(rename = "type")]
+    type_id: String,
+    cot: Cot,
+    ca: u16,
+    #[serde(default)]
+    originator: u8,
+    #[serde(default)]
+    test: bool,
+    #[serde(default)]
+    negative: bool,
+    #[serde(default)]
+    sequence: bool,
+    points: Vec<PointJson>,
+}
+
+impl PointJson {
+    fn new(point: &DataPoint) -> Self {
+        // DataValue variants all carry content, so serde writes {"Variant": content}
+        let (kind, value) = match serde_json::to_value(&point.value) {
+            Ok(Value::Object(map)) => map.into_iter().next(),
+            _ => None,
+        }
+        .expect("DataValue serializes as a single-entry object");
+
+        let (timestamp, timestamp_invalid, summer_time) = match point.timestamp {
+            Some(Timestamp::Full(time)) => (
+                Some(time.to_iso8601()),
+                Some(time.invalid),
+                Some(time.summer_time),
+            ),
+            Some(Timestamp::Partial(time)) => (
+                Some(format!(
+                    "-{:02}:{:02}.{:03}",
+                    time.minutes,
+                    time.milliseconds / 1000,
+                    time.milliseconds % 1000
+                )),
+                Some(time.invalid),
+                None,
+            ),
+            None => (None, None, None),
+        };
+
+        Self {
+            ioa: point.ioa,
+            kind,
+            value,
+            quality: point.quality,
+            timestamp,
+            timestamp_invalid,
+            summer_time,
+        }
+    }
+
+    fn into_point(self) -> Result<DataPoint> {
+        let mut tagged = serde_json::Map::new();
+        tagged.insert(self.kind, self.value);
+        let value: DataValue = serde_json::from_value(Value::Object(tagged)).map_err(json_error)?;
+
+        let invalid = self.timestamp_invalid.unwrap_or(false);
+        let timestamp = match self.timestamp {
+            Some(text) if text.starts_with('-') => Some(Timestamp::Partial(Cp24Time2a {
+                invalid,
+                ..parse_partial_time(&text)?
+            })),
+            Some(text) => Some(Timestamp::Full(Cp56Time2a {
+                invalid,
+                summer_time: self.summer_time.unwrap_or(false),
+                ..Cp56Time2a::parse_iso8601(&text)?
+            })),
+            None => None,
+        };
+
+        Ok(DataPoint {
+            ioa: self.ioa,
+            value,
+            quality: self.quality,
+            timestamp,
+        })
+    }
+}
+
+/// Parse minutes and seconds written as `-MM:SS.sss`.
+fn parse_partial_time(text: &str) -> Result<Cp24Time2a> {
+    // Borrow the date of an arbitrary full time to reuse its parser
+    let full = Cp56Time2a::parse_iso8601(&format!("2000-01-01T00:{}", &text[1..]))?;
+    Ok(Cp24Time2a {
+        milliseconds: full.milliseconds,
+        minutes: full.minutes,
+        invalid: false,
+    })
+}
+
+/// Read a command message (see the module documentation) for object `ioa`.
+#[cfg_attr(not(any(feature = "http", feature = "mqtt")), allow(dead_code))]
+pub(crate) fn parse_command(ioa: u32, payload: &[u8]) -> Result<(Command, CommandQualifier)> {
+    let invalid = |reason: &str| Iec104Error::protocol(format!("Invalid command: {}", reason));
+    let message: Value =
+        serde_json::from_slice(payload).map_err(|err| invalid(&err.to_string()))?;
+    let value = &message["value"];
+    let qualifier = match message["select"] {
+        Value::Bool(true) => CommandQualifier::SELECT,
+        Value::Bool(false) | Value::Null => CommandQualifier::EXECUTE,
+        _ => return Err(invalid("select must be a boolean")),
+    };
+
+    let command = match message["type"].as_str() {
+        Some("Single") => Command::Single {
+            ioa,
+            value: value
+                .as_bool()
+                .ok_or_else(|| invalid("value must be a boolean"))?,
+        },
+        Some("Double") => Command::Double {
+            ioa,
+            value: match value.as_str() {
+                Some("On") => DoubleCommandState::On,
+                Some("Off") => DoubleCommandState::Off,
+                _ => return Err(invalid("value must be \"On\" or \"Off\"")),
+            },
+        },
+        Some("RegulatingStep") => Command::RegulatingStep {
+            ioa,
+            step: match value.as_str() {
+                Some("Higher") => StepCommand::Higher,
+                Some("Lower") => StepCommand::Lower,
+                _ => return Err(invalid("value must be \"Higher\" or \"Lower\"")),
+            },
+        },
+        Some("SetpointNormalized") => Command::SetpointNormalized {
+            ioa,
+            value: value
+                .as_f64()
+                .ok_or_else(|| invalid("value must be a number"))? as f32,
+            qos: Qos::EXECUTE,
+        },
+        Some("SetpointScaled") => Command::SetpointScaled {
+            ioa,
+            value: value
+                .as_i64()
+                .and_then(|value| i16::try_from(value).ok())
+                .ok_or_else(|| invalid("value must be an integer in i16 range"))?,
+            qos: Qos::EXECUTE,
+        },
+        Some("SetpointFloat") => Command::SetpointFloat {
+            ioa,
+            value: value
+                .as_f64()
+                .ok_or_else(|| invalid("value must be a number"))? as f32,
+            qos: Qos::EXECUTE,
+        },
+        _ => return Err(invalid("unknown type")),
+    };
+    Ok((command, qualifier))
+}
+
+fn json_error(err: serde_json::Error) -> Iec104Error {
+    Iec104Error::Codec(format!("Invalid JSON: {}", err).into())
+}
+
+/// The canonical JSON form of a point, for embedding in other documents.
+#[cfg_attr(not(any(feature = "http", feature = "nats")), allow(dead_code))]
+pub(crate) fn point_value(point: &DataPoint) -> Value {
+    serde_json::to_value(PointJson::new(point)).expect("data point serializes")
+}
+
+#[cfg_attr(docsrs, doc(cfg(feature = "json")))]
+impl DataPoint {
+    /// Write the point in the canonical JSON form (see [`crate::json`]).
+    pub fn to_json(&self) -> String {
+        serde_json::to_string(&PointJson::new(self)).expect("data point serializes")
+    }
+
+    /// Read a point from its canonical JSON form.
+    pub fn from_json(json: &str) -> Result<Self> {
+        serde_json::from_str::<PointJson>(json)
+            .map_err(json_error)?
+            .into_point()
+    }
+}
+
+#[cfg_attr(docsrs, doc(cfg(feature = "json")))]
+impl Asdu {
+    /// Write the ASDU in the canonical JSON form (see [`crate::json`]).
+    ///
+    /// Fails for ASDUs that do not carry data points, such as commands.
+    pub fn to_json(&self) -> Result<String> {
+        let type_id = self.header.type_id;
+        let parameter = matches!(
+            type_id,
+            TypeId::ParameterNormalized | TypeId::ParameterScaled | TypeId::ParameterFloat
+        );
+        if !type_id.is_monitoring() && !parameter {
+            return Err(Iec104Error::InvalidAsdu(
+                format!("{} has no JSON representation", type_id).into(),
+            ));
+        }
+
+        // Objects built locally are only parsed once encoded
+        let points = if self.raw_data.is_empty() && !self.objects.is_empty() {
+            parse_asdu(&Asdu::parse_bytes(self.encode().freeze())?)?
+        } else {
+            parse_asdu(self)?
+        };
+        let json = AsduJson {
+            type_id: type_id.standard_name().to_string(),
+            cot: self.header.cot,
+            ca: self.header.common_address,
+            originator: self.header.originator,
+            test: self.header.test,
+            negative: self.header.negative,
+            sequence: self.header.vsq.sequence,
+            points: points.iter().map(PointJson::new).collect(),
+        };
+        Ok(serde_json::to_string(&json).expect("ASDU serializes"))
+    }
+
+    /// Read an ASDU from its canonical JSON form, checking it as
+    /// [`AsduBuilder::build`] does.
+    pub fn from_json(json: &str) -> Result<Self> {
+        let json: AsduJson = serde_json::from_str(json).map_err(json_error)?;
+        let type_id = TypeId::ALL
+            .iter()
+            .copied()
+            .find(|type_id| type_id.standard_name() == json.type_id)
+            .ok_or_else(|| {
+                Iec104Error::InvalidAsdu(format!("Unknown type {:?}", json.type_id).into())
+            })?;
+
+        let mut builder = AsduBuilder::new(type_id)
+            .cot(json.cot)
+            .ca(json.ca)
+            .originator(json.originator)
+            .test(json.test)
+            .sequence(json.sequence);
+        for point in json.points {
+            builder = builder.add_point(point.into_point()?);
+        }
+        let mut asdu = builder.build()?;
+        asdu.header.negative = json.negative;
+        Ok(asdu)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::{DoublePointValue, ParameterKind, ParameterValue, Qpm};
+
+    #[test]
+    fn test_data_point_json() {
+        let time = Cp56Time2a::from_bytes(&[0x2A, 0x76, 45, 13, 0x9D, 2, 24]).unwrap();
+        let point = DataPoint::with_timestamp(1001, DataValue::Float(23.5), Quality::Good, time);
+        let json: serde_json::Value = serde_json::from_str(&point.to_json()).unwrap();
+        assert_eq!(
+            json,
+            serde_json::json!({
+                "ioa": 1001,
+                "type": "Float",
+                "value": 23.5,
+                "quality": {
+                    "overflow": false, "blocked": false, "substituted": false,
+                    "not_topical": false, "invalid": false, "elapsed_time_invalid": false
+                },
+                "timestamp": "2024-02-29T13:45:30.250",
+                "timestamp_invalid": false,
+                "summer_time": false
+            })
+        );
+        assert_eq!(DataPoint::from_json(&point.to_json()).unwrap(), point);
+
+        let points = [
+            DataPoint::with_quality(
+                1,
+                DataValue::Double(DoublePointValue::On),
+                Quality::Good.set_invalid(true),
+            ),
+            DataPoint::new(
+                2,
+                DataValue::StepPosition {
+                    value: -3,
+                    transient: true,
+                },
+            ),
+            DataPoint::new(
+                3,
+                DataValue::Parameter {
+                    value: ParameterValue::Scaled(-7),
+                    qpm: Qpm::new(ParameterKind::Other(40)),
+                },
+            ),
+            DataPoint {
+                timestamp: Some(Timestamp::Partial(Cp24Time2a {
+                    milliseconds: 30_250,
+                    minutes: 45,
+                    invalid: true,
+                })),
+                ..DataPoint::new(4, DataValue::Scaled(-12))
+            },
+        ];
+        for point in points {
+            assert_eq!(DataPoint::from_json(&point.to_json()).unwrap(), point);
+        }
+    }
+
+    #[test]
+    fn test_data_point_json_minimal() {
+        let point = DataPoint::from_json(r#"{"ioa": 7, "type": "Single", "value": true}"#);
+        assert_eq!(point.unwrap(), DataPoint::new(7, DataValue::Single(true)));
+
+        let partial = DataPoint::from_json(
+            r#"{"ioa": 7, "type": "Single", "value": true, "timestamp": "-45:30.250"}"#,
+        );
+        assert_eq!(
+            partial.unwrap().timestamp,
+            Some(Timestamp::Partial(Cp24Time2a {
+                milliseconds: 30_250,
+                minutes: 45,
+                invalid: false
+            }))
+        );
+
+        for json in [
+            r#"{"ioa": 7, "type": "Single", "value": 3}"#,
+            r#"{"ioa": 7, "type": "Unknown", "value": true}"#,
+            r#"{"ioa": 7, "value": true}"#,
+            r#"{"ioa": 7, "type": "Single", "value": true, "timestamp": "yesterday"}"#,
+        ] {
+            assert!(DataPoint::from_json(json).is_err(), "{}", json);
+        }
+    }
+
+    #[test]
+    fn test_asdu_json() {
+        let time = Cp56Time2a::from_bytes(&[0x2A, 0x76, 45, 13, 0x9D, 2, 24]).unwrap();
+        let asdu = AsduBuilder::new(TypeId::MeasuredFloatTime56)
+            .ca(1)
+            .add(100, DataValue::Float(1.5), Quality::Good)
+            .with_time(time)
+            .add(
+                101,
+                DataValue::Float(-2.0),
+                Quality::Good.set_overflow(true),
+            )
+            .with_time(time)
+            .sequence(true)
+            .build()
+            .unwrap();
+
+        let json = asdu.to_json().unwrap();
+        let value: serde_json::Value = serde_json::from_str(&json).unwrap();
+        assert_eq!(value["type"], "M_ME_TF_1");
+        assert_eq!(value["cot"], "Spontaneous");
+        assert_eq!(value["ca"], 1);
+        assert_eq!(value["sequence"], true);
+        assert_eq!(value["points"][1]["quality"]["overflow"], true);
+
+        let restored = Asdu::from_json(&json).unwrap();
+        assert_eq!(restored.encode(), asdu.encode());
+
+        // Received ASDUs give the same document
+        let received = Asdu::parse_bytes(asdu.encode().freeze()).unwrap();
+        assert_eq!(received.to_json().unwrap(), json);
+    }
+
+    #[test]
+    fn test_asdu_json_rejects() {
+        let command = Asdu::interrogation_command(1, 20);
+        assert!(command.to_json().is_err());
+
+        let wrong_value = r#"{"type": "M_SP_NA_1", "cot": "Spontaneous", "ca": 1,
+            "points": [{"ioa": 1, "type": "Float", "value": 1.0}]}"#;
+        assert!(Asdu::from_json(wrong_value).is_err());
+
+        let unknown = r#"{"type": "M_XX_NA_1", "cot": "Spontaneous", "ca": 1, "points": []}"#;
+        assert!(Asdu::from_json(unknown).is_err());
+    }
+
+    #[test]
+    fn test_parse_command() {
+        let (command, qualifier) =
+            parse_command(5, br#"{"type": "Single", "value": true}"#).unwrap();
+        assert_eq!(
+            command,
+            Command::Single {
+                ioa: 5,
+                value: true
+            }
+        );
+        assert_eq!(qualifier, CommandQualifier::EXECUTE);
+
+        let payload = br#"{"type": "Double", "value": "Off", "select": true}"#;
+        let (command, qualifier) = parse_command(6, payload).unwrap();
+        assert_eq!(
+            command,
+            Command::Double {
+                ioa: 6,
+                value: DoubleCommandState::Off
+            }
+        );
+        assert_eq!(qualifier, CommandQualifier::SELECT);
+
+        let payload = br#"{"type": "RegulatingStep", "value": "Higher"}"#;
+        let (command, _) = parse_command(7, payload).unwrap();
+        assert_eq!(
+            command,
+            Command::RegulatingStep {
+                ioa: 7,
+                step: StepCommand::Higher
+            }
+        );
+
+        let payload = br#"{"type": "SetpointScaled", "value": -300}"#;
+        let (command, _) = parse_command(8, payload).unwrap();
+        assert_eq!(
+            command,
+            Command::SetpointScaled {
+                ioa: 8,
+                value: -300,
+                qos: Qos::EXECUTE,
+            }
+        );
+
+        let payload = br#"{"type": "SetpointFloat", "value": 49.5}"#;
+        let (command, _) = parse_command(9, payload).unwrap();
+        assert_eq!(
+            command,
+            Command::SetpointFloat {
+                ioa: 9,
+                value: 49.5,
+                qos: Qos::EXECUTE,
+            }
+        );
+
+        for payload in [
+            &br#"{"type": "Single", "value": 1}"#[..],
+            br#"{"type": "Double", "value": "Indeterminate"}"#,
+            br#"{"type": "SetpointScaled", "value": 40000}"#,
+            br#"{"type": "Single", "value": true, "select": "yes"}"#,
+            br#"{"type": "Reset"}"#,
+            b"on",
+        ] {
+            assert!(parse_command(1, payload).is_err());
+        }
+    }
+}
@@ -0,0 +1,477 @@
+//! Producer publishing data updates of a spawned client to Kafka.
+//!
+//! [`KafkaSink`] sends every data point received from the configured
+//! stations as one record to a topic. Records are keyed `{ca}/{ioa}` and
+//! spread over the partitions like the default partitioner of the Java
+//! client does (murmur2 of the key), so all values of a point stay in order
+//! in one partition whichever client produces them. The value is the
+//! canonical JSON form of the point (see [`crate::json`]) unless a
+//! [`serializer`](KafkaSink::serializer) is set; the record timestamp is the
+//! point's CP56Time2a time tag, or the time of reception without one.
+//!
+//! Records are produced in batches per partition: a batch is sent once it
+//! holds [`batch_size`](KafkaSink::batch_size) records or its oldest record
+//! waited [`linger`](KafkaSink::linger), with at most one request in flight
+//! per partition. While the brokers are slow the batches grow; once
+//! [`max_buffered`](KafkaSink::max_buffered) records wait, the sink stops
+//! taking updates, which holds up the client's event delivery rather than
+//! dropping data.
+//!
+//! # Example
+//!
+//! ```rust,ignore
+//! let (handle, _task) = client.spawn();
+//! let kafka = ClientBuilder::new(vec!["broker:9092".to_string()]).build().await?;
+//! KafkaSink::new("iec104.telemetry")
+//!     .station(1)
+//!     .run(handle, kafka)
+//!     .await?;
+//! ```
+
+use std::fmt;
+use std::sync::Arc;
+use std::time::{Duration, SystemTime};
+
+use chrono::{DateTime, Utc};
+use futures::future::BoxFuture;
+use futures::stream::{FuturesUnordered, StreamExt};
+use rskafka::client::partition::{Compression, PartitionClient, UnknownTopicHandling};
+use rskafka::client::Client;
+use rskafka::record::Record;
+use tokio::sync::mpsc;
+use tokio::time::Instant;
+
+use crate::error::{Iec104Error, Result};
+use crate::filter::EventFilter;
+use crate::handle::ClientHandle;
+use crate::types::{DataPoint, Timestamp};
+use crate::Iec104Event;
+
+/// Default number of records per produce request.
+pub const DEFAULT_BATCH_SIZE: usize = 500;
+
+/// Default time a record waits for its batch to fill.
+pub const DEFAULT_LINGER: Duration = Duration::from_millis(100);
+
+/// Default number of records waiting before updates are held up.
+pub const DEFAULT_MAX_BUFFERED: usize = 50_000;
+
+/// Turns a point of a station into the value of its record.
+pub type Serializer = Arc<dyn Fn(u16, &DataPoint) -> Vec<u8> + Send + Sync>;
+
+/// Publishes data updates to a Kafka topic.
+#[derive(Clone)]
+pub struct KafkaSink {
+    topic: String,
+    stations: Vec<u16>,
+    serializer: Serializer,
+    compression: Compression,
+    batch_size: usize,
+    linger: Duration,
+    max_buffered: usize,
+}
+
+impl fmt::Debug for KafkaSink {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("KafkaSink")
+            .field("topic", &self.topic)
+            .field("stations", &self.stations)
+            .field("compression", &self.compression)
+            .field("batch_size", &self.batch_size)
+            .field("linger", &self.linger)
+            .field("max_buffered", &self.max_buffered)
+            .finish_non_exhaustive()
+    }
+}
+
+impl KafkaSink {
+    /// Create a sink producing JSON records to `topic`.
+    pub fn new(topic: impl Into<String>) -> Self {
+        Self {
+            topic: topic.into(),
+            stations: Vec::new(),
+            serializer: Arc::new(|_, point: &DataPoint| point.to_json().into_bytes()),
+            compression: Compression::NoCompression,
+            batch_size: DEFAULT_BATCH_SIZE,
+            linger: DEFAULT_LINGER,
+            max_buffered: DEFAULT_MAX_BUFFERED,
+        }
+    }
+
+    /// Publish the data of the station with this common address.
+    pub fn station(mut self, common_address: u16) -> Self {
+        self.stations.push(common_address);
+        self
+    }
+
+    /// Serialize record values with `serializer` instead of JSON.
+    pub fn serializer(
+        mut self,
+        serializer: impl Fn(u16, &DataPoint) -> Vec<u8> + Send + Sync + 'static,
+    ) -> Self {
+        self.serializer = Arc::new(serializer);
+        self
+    }
+
+    /// Compress the batches, e.g. with [`Compression::Gzip`].
+    pub fn compression(mut self, compression: Compression) -> Self {
+        self.compression = compression;
+        self
+    }
+
+    /// Set the largest number of records per produce request (at least 1).
+    pub fn batch_size(mut self, records: usize) -> Self {
+        self.batch_size = records.max(1);
+        self
+    }
+
+    /// Set how long a record waits for its batch to fill.
+    pub fn linger(mut self, linger: Duration) -> Self {
+        self.linger = linger;
+        self
+    }
+
+    /// Set how many records may wait before updates are held up.
+    pub fn max_buffered(mut self, records: usize) -> Self {
+        self.max_buffered = records.max(1);
+        self
+    }
+
+    /// Run the sink until the client task ends, then flush the remaining
+    /// records.
+    ///
+    /// Fails without stations, when the topic does not exist and when a
+    /// produce request fails (`rskafka` retries transient errors itself).
+    pub async fn run(self, handle: ClientHandle, kafka: Client) -> Result<()> {
+        if self.stations.is_empty() {
+            return Err(Iec104Error::protocol_static(
+                "Kafka sink needs at least one station",
+            ));
+        }
+        let topics = kafka.list_topics().await.map_err(kafka_error)?;
+        let partition_count = topics
+            .iter()
+            .find(|topic| topic.name == self.topic)
+            .map_or(0, |topic| topic.partitions.len());
+        if partition_count == 0 {
+            return Err(Iec104Error::Connection(
+                format!("Kafka topic {:?} not found", self.topic).into(),
+            ));
+        }
+        let mut partitions = Vec::with_capacity(partition_count);
+        for partition in 0..partition_count {
+            let client = kafka
+                .partition_client(
+                    self.topic.clone(),
+                    partition as i32,
+                    UnknownTopicHandling::Retry,
+                )
+                .await
+                .map_err(kafka_error)?;
+            partitions.push(Arc::new(client));
+        }
+
+        let (update_tx, mut updates) = mpsc::channel::<(u16, Vec<DataPoint>)>(64);
+        for &common_address in &self.stations {
+            let filter = EventFilter::new().common_address(common_address);
+            let mut events = handle.subscribe_filtered(filter).await?;
+            let update_tx = update_tx.clone();
+            tokio::spawn(async move {
+                while let Some(event) = events.recv().await {
+                    if let Iec104Event::DataUpdate(points) = event.event {
+                        if update_tx.send((common_address, points)).await.is_err() {
+                            break;
+                        }
+                    }
+                }
+            });
+        }
+        drop(update_tx);
+
+        let mut batches = Batches::new(partition_count);
+        let mut producing: FuturesUnordered<BoxFuture<'static, (usize, Result<()>)>> =
+            FuturesUnordered::new();
+        let mut closing = false;
+        loop {
+            for (partition, records) in
+                batches.ready(self.batch_size, self.linger, Instant::now(), closing)
+            {
+                let client = partitions[partition].clone();
+                producing.push(Box::pin(produce(
+                    client,
+                    partition,
+                    records,
+                    self.compression,
+                )));
+            }
+            if closing && batches.is_empty() && producing.is_empty() {
+                return Ok(());
+            }
+
+            let deadline = batches.deadline(self.linger);
+            tokio::select! {
+                update = updates.recv(), if !closing && batches.buffered < self.max_buffered => {
+                    let Some((common_address, points)) = update else {
+                        closing = true;
+                        continue;
+                    };
+                    let now = Instant::now();
+                    for point in &points {
+                        let key = record_key(common_address, point.ioa);
+                        let partition = partition_for(&key, partition_count);
+                        let record = Record {
+                            key: Some(key),
+                            value: Some((self.serializer)(common_address, point)),
+                            headers: Default::default(),
+                            timestamp: record_time(point),
+                        };
+                        batches.push(partition, record, now);
+                    }
+                }
+                Some((partition, result)) = producing.next(), if !producing.is_empty() => {
+                    result?;
+                    batches.done(partition);
+                }
+                _ = sleep_until(deadline), if deadline.is_some() => {}
+            }
+        }
+    }
+}
+
+async fn produce(
+    client: Arc<PartitionClient>,
+    partition: usize,
+    records: Vec<Record>,
+    compression: Compression,
+) -> (usize, Result<()>) {
+    let result = client
+        .produce(records, compression)
+        .await
+        .map(|_| ())
+        .map_err(kafka_error);
+    (partition, result)
+}
+
+async fn sleep_until(deadline: Option<Instant>) {
+    match deadline {
+        Some(deadline) => tokio::time::sleep_until(deadline).await,
+        None => std::future::pending().await,
+    }
+}
+
+/// Records waiting per partition.
+struct Batches {
+    pending: Vec<Vec<Record>>,
+    /// Arrival of the oldest waiting record of each partition
+    oldest: Vec<Option<Instant>>,
+    /// Partitions with a produce request in flight
+    busy: Vec<bool>,
+    buffered: usize,
+}
+
+impl Batches {
+    fn new(partitions: usize) -> Self {
+        Self {
+            pending: (0..partitions).map(|_| Vec::new()).collect(),
+            oldest: vec![None; partitions],
+            busy: vec![false; partitions],
+            buffered: 0,
+        }
+    }
+
+    fn push(&mut self, partition: usize, record: Record, now: Instant) {
+        self.pending[partition].push(record);
+        self.oldest[partition].get_or_insert(now);
+        self.buffered += 1;
+    }
+
+    /// Take the batches to send now from partitions without a request in
+    /// flight: full ones, those that lingered long enough and, when
+    /// closing, all.
+    fn ready(
+        &mut self,
+        batch_size: usize,
+        linger: Duration,
+        now: Instant,
+        closing: bool,
+    ) -> Vec<(usize, Vec<Record>)> {
+        let mut ready = Vec::new();
+        for partition in 0..self.pending.len() {
+            let pending = &mut self.pending[partition];
+            let Some(oldest) = self.oldest[partition] else {
+                continue;
+            };
+            if self.busy[partition]
+                || !(closing || pending.len() >= batch_size || now >= oldest + linger)
+            {
+                continue;
+            }
+            let count = pending.len().min(batch_size);
+            let records: Vec<Record> = pending.drain(..count).collect();
+            // What is left has waited since before this batch was sent
+            self.oldest[partition] = if pending.is_empty() {
+                None
+            } else {
+                Some(oldest)
+            };
+            self.busy[partition] = true;
+            self.buffered -= count;
+            ready.push((partition, records));
+        }
+        ready
+    }
+
+    /// Mark the request of a partition as completed.
+    fn done(&mut self, partition: usize) {
+        self.busy[partition] = false;
+    }
+
+    /// When the next idle partition has lingered long enough.
+    fn deadline(&self, linger: Duration) -> Option<Instant> {
+        (0..self.pending.len())
+            .filter(|&partition| !self.busy[partition])
+            .filter_map(|partition| self.oldest[partition])
+            .min()
+            .map(|oldest| oldest + linger)
+    }
+
+    fn is_empty(&self) -> bool {
+        self.buffered == 0
+    }
+}
+
+/// Key of the records of a point.
+fn record_key(common_address: u16, ioa: u32) -> Vec<u8> {
+    format!("{}/{}", common_address, ioa).into_bytes()
+}
+
+/// Partition of a key, as chosen by the default partitioner of the Java
+/// client.
+fn partition_for(key: &[u8], partitions: usize) -> usize {
+    (murmur2(key) & 0x7fff_ffff) as usize % partitions
+}
+
+/// The 32-bit murmur2 hash with the seed used by Kafka.
+fn murmur2(data: &[u8]) -> u32 {
+    const M: u32 = 0x5bd1_e995;
+    let mut h = 0x9747_b28c ^ data.len() as u32;
+    let mut chunks = data.chunks_exact(4);
+    for chunk in &mut chunks {
+        let mut k = u32::from_le_bytes([chunk[0], chunk[1], chunk[2], chunk[3]]);
+        k = k.wrapping_mul(M);
+        k ^= k >> 24;
+        k = k.wrapping_mul(M);
+        h = h.wrapping_mul(M) ^ k;
+    }
+    let rest = chunks.remainder();
+    if !rest.is_empty() {
+        for (i, &byte) in rest.iter().enumerate() {
+            h ^= u32::from(byte) << (8 * i);
+        }
+        h = h.wrapping_mul(M);
+    }
+    h ^= h >> 13;
+    h = h.wrapping_mul(M);
+    h ^ (h >> 15)
+}
+
+/// Timestamp of the record of a point.
+fn record_time(point: &DataPoint) -> DateTime<Utc> {
+    match point.timestamp {
+        Some(Timestamp::Full(time)) => {
+            DateTime::try_from(time).unwrap_or_else(|_| DateTime::from(SystemTime::now()))
+        }
+        _ => DateTime::from(SystemTime::now()),
+    }
+}
+
+fn kafka_error(err: rskafka::client::error::Error) -> Iec104Error {
+    Iec104Error::Connection(format!("Kafka: {}", err).into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::{Cp56Time2a, DataValue};
+
+    fn record(value: &str) -> Record {
+        Record {
+            key: None,
+            value: Some(value.as_bytes().to_vec()),
+            headers: Default::default(),
+            timestamp: DateTime::from(SystemTime::now()),
+        }
+    }
+
+    #[test]
+    fn test_partitioning() {
+        // Reference values of the Java client's Utils.murmur2
+        let cases: [(&[u8], i32); 6] = [
+            (b"21", -973932308),
+            (b"foobar", -790332482),
+            (b"a-little-bit-long-string", -985981536),
+            (b"a-little-bit-longer-string", -1486304829),
+            (
+                b"lkjh234lh9fiuh90y23oiuhsafujhadof229phr9h19h89h8",
+                -58897971,
+            ),
+            (b"abc", 479470107),
+        ];
+        for (data, expected) in cases {
+            assert_eq!(murmur2(data) as i32, expected, "{:?}", data);
+        }
+
+        assert_eq!(record_key(1, 100), b"1/100");
+        let partition = partition_for(b"1/100", 12);
+        assert!(partition < 12);
+        assert_eq!(partition_for(b"1/100", 12), partition);
+    }
+
+    #[test]
+    fn test_record_time() {
+        let time = Cp56Time2a {
+            milliseconds: 30_250,
+            minutes: 45,
+            hours: 13,
+            day: 29,
+            day_of_week: 4,
+            month: 2,
+            year: 24,
+            invalid: false,
+            summer_time: false,
+        };
+        let mut point = DataPoint::new(100, DataValue::Float(1.5));
+        point.timestamp = Some(Timestamp::Full(time));
+        assert_eq!(record_time(&point).timestamp_millis(), 1_709_214_330_250);
+    }
+
+    #[test]
+    fn test_batches() {
+        let linger = Duration::from_millis(100);
+        let start = Instant::now();
+        let mut batches = Batches::new(2);
+        for i in 0..5 {
+            batches.push(0, record(&i.to_string()), start);
+        }
+        batches.push(1, record("x"), start);
+        assert_eq!(batches.deadline(linger), Some(start + linger));
+
+        // Only the full partition goes before lingering
+        let ready = batches.ready(3, linger, start, false);
+        assert_eq!(ready.len(), 1);
+        assert_eq!((ready[0].0, ready[0].1.len()), (0, 3));
+        assert_eq!(batches.buffered, 3);
+
+        // A partition with a request in flight waits for it
+        let ready = batches.ready(3, linger, start + linger, false);
+        assert_eq!(ready.len(), 1);
+        assert_eq!((ready[0].0, ready[0].1.len()), (1, 1));
+        assert_eq!(batches.deadline(linger), None);
+
+        batches.done(0);
+        let ready = batches.ready(3, linger, start, true);
+        assert_eq!(ready.len(), 1);
+        assert_eq!(ready[0].1[1].value.as_deref(), Some(&b"4"[..]));
+        assert!(batches.is_empty());
+    }
+}
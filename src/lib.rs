@@ -8,6 +8,7 @@
 //! ## Features
 //!
 //! - **Event-driven**: Asynchronous data reception via channels
+//! - **Background task**: [`Iec104Client::spawn`] returns a clonable [`ClientHandle`]
 //! - **Full Protocol Support**: I-frames, S-frames, U-frames
 //! - **Standard Timeouts**: T1, T2, T3, K, W parameters
 //! - **Type Safe**: Strong typing for TypeID, COT, IOA
@@ -32,7 +33,7 @@
 //!     // Subscribe to events
 //!     let mut events = client.subscribe();
 //!     while let Some(event) = events.recv().await {
-//!         println!("Event: {:?}", event);
+//!         println!("Event #{}: {:?}", event.seq, event.event);
 //!     }
 //!
 //!     Ok(())
@@ -59,15 +60,82 @@
 
 #![cfg_attr(docsrs, feature(doc_cfg))]
 
+pub mod analyze;
 pub mod client;
 pub mod codec;
+pub mod command;
+pub mod csv;
+pub mod encoder;
 pub mod error;
+pub mod file_transfer;
+pub mod filter;
+pub mod gateway;
+#[cfg(feature = "grpc")]
+#[cfg_attr(docsrs, doc(cfg(feature = "grpc")))]
+pub mod grpc;
+pub mod handle;
+#[cfg(feature = "parquet")]
+#[cfg_attr(docsrs, doc(cfg(feature = "parquet")))]
+pub mod historian;
+#[cfg(feature = "http")]
+#[cfg_attr(docsrs, doc(cfg(feature = "http")))]
+pub mod http;
+#[cfg(feature = "iec101")]
+#[cfg_attr(docsrs, doc(cfg(feature = "iec101")))]
+pub mod iec101;
+#[cfg(feature = "json")]
+#[cfg_attr(docsrs, doc(cfg(feature = "json")))]
+pub mod json;
+#[cfg(feature = "kafka")]
+#[cfg_attr(docsrs, doc(cfg(feature = "kafka")))]
+pub mod kafka;
+#[cfg(feature = "metrics")]
+#[cfg_attr(docsrs, doc(cfg(feature = "metrics")))]
+pub mod metrics;
+pub mod modbus;
+#[cfg(feature = "mqtt")]
+#[cfg_attr(docsrs, doc(cfg(feature = "mqtt")))]
+pub mod mqtt;
+#[cfg(feature = "nats")]
+#[cfg_attr(docsrs, doc(cfg(feature = "nats")))]
+pub mod nats;
 pub mod parser;
+#[cfg(feature = "pcap")]
+#[cfg_attr(docsrs, doc(cfg(feature = "pcap")))]
+pub mod pcap;
+pub mod redundant;
+pub mod schema;
+#[cfg(feature = "sqlite")]
+#[cfg_attr(docsrs, doc(cfg(feature = "sqlite")))]
+pub mod sqlite;
+#[cfg(any(test, feature = "testing"))]
+#[cfg_attr(docsrs, doc(cfg(feature = "testing")))]
+pub mod testing;
+#[cfg(feature = "tls")]
+#[cfg_attr(docsrs, doc(cfg(feature = "tls")))]
+pub mod tls;
+mod transport;
 pub mod types;
+#[cfg(feature = "websocket")]
+#[cfg_attr(docsrs, doc(cfg(feature = "websocket")))]
+pub mod websocket;
 
 // Re-export main types
-pub use client::{ClientConfig, ConnectionState, Iec104Client, Iec104Event};
-pub use codec::{Apdu, Iec104Codec};
+pub use client::{
+    ClientConfig, ConnectionState, FrameDirection, Iec104Client, Iec104Event, LinkStats,
+    ProtocolRole, SequencedEvent, SessionInfo, TappedFrame,
+};
+pub use codec::{decode_apdu, encode_apdu, Apdu, Iec104Codec};
+pub use command::{Command, CommandCompletion, RetryPolicy, StepCommand};
+pub use encoder::{encode_asdu, AsduBuilder};
 pub use error::{Iec104Error, Result};
-pub use parser::parse_asdu;
+pub use file_transfer::FileInfo;
+pub use filter::{Deadband, EventFilter};
+pub use gateway::OriginatorRouter;
+pub use handle::ClientHandle;
+pub use parser::{parse_asdu, parse_asdu_iter, parse_command, CommandObject};
+pub use redundant::{RedundantClient, RedundantEvent};
+pub use transport::AsyncStream;
+#[cfg(feature = "tls")]
+pub use tls::TlsConfig;
 pub use types::*;
@@ -0,0 +1,244 @@
+//! Link metrics recorded through the [`metrics`](https://docs.rs/metrics) facade.
+//!
+//! With the `metrics` feature, every client records the series below into
+//! whatever recorder the application installed, e.g.
+//! `metrics-exporter-prometheus`. Without a recorder nothing is kept.
+//!
+//! All series carry a `peer` label with the configured
+//! [`ClientConfig::address`](crate::client::ClientConfig::address).
+//!
+//! | Name | Kind | Labels |
+//! |------|------|--------|
+//! | [`FRAMES`] | counter | `direction` (`sent`/`received`), `frame` (`I`/`S`/`U`), `type` |
+//! | [`PARSE_ERRORS`] | counter | |
+//! | [`SEQUENCE_ERRORS`] | counter | |
+//! | [`COMMAND_ROUND_TRIP`] | histogram, seconds | `type` |
+//! | [`RECONNECTS`] | counter | |
+//! | [`EVENTS_DROPPED`] | counter | |
+//! | [`CONNECTION_STATE`] | gauge | |
+//!
+//! `type` is the standard name of the ASDU type (`M_ME_NC_1`), empty for
+//! S- and U-frames.
+
+use std::time::Duration;
+
+use metrics::{counter, gauge, histogram};
+
+use crate::client::{ConnectionState, FrameDirection};
+use crate::codec::Apdu;
+use crate::error::Iec104Error;
+use crate::types::{Apci, TypeId};
+
+/// APDUs sent and received.
+pub const FRAMES: &str = "iec104_frames_total";
+/// Frames that could not be decoded.
+pub const PARSE_ERRORS: &str = "iec104_parse_errors_total";
+/// I-frames received with an unexpected send sequence number.
+pub const SEQUENCE_ERRORS: &str = "iec104_sequence_errors_total";
+/// Time from sending a command to its confirmation, positive or negative.
+pub const COMMAND_ROUND_TRIP: &str = "iec104_command_round_trip_seconds";
+/// Connections opened by a client that was connected before.
+pub const RECONNECTS: &str = "iec104_reconnects_total";
+/// Events dropped because nobody subscribed and the buffer was full.
+pub const EVENTS_DROPPED: &str = "iec104_events_dropped_total";
+/// 0 disconnected, 1 connected, 2 data transfer active, 3 stopping.
+pub const CONNECTION_STATE: &str = "iec104_connection_state";
+
+pub(crate) fn frame(peer: &str, direction: FrameDirection, apdu: &Apdu) {
+    let direction = match direction {
+        FrameDirection::Sent => "sent",
+        FrameDirection::Received => "received",
+    };
+    let frame = match apdu.apci {
+        Apci::IFrame { .. } => "I",
+        Apci::SFrame { .. } => "S",
+        Apci::UFrame { .. } => "U",
+    };
+    let type_name = apdu
+        .asdu
+        .as_ref()
+        .map_or("", |asdu| asdu.header.type_id.standard_name());
+    counter!(
+        FRAMES,
+        "peer" => peer.to_owned(),
+        "direction" => direction,
+        "frame" => frame,
+        "type" => type_name
+    )
+    .increment(1);
+}
+
+/// Count a failed read, unless the socket itself failed.
+pub(crate) fn read_error(peer: &str, error: &Iec104Error) {
+    if !matches!(error, Iec104Error::Io(_)) {
+        counter!(PARSE_ERRORS, "peer" => peer.to_owned()).increment(1);
+    }
+}
+
+pub(crate) fn sequence_error(peer: &str) {
+    counter!(SEQUENCE_ERRORS, "peer" => peer.to_owned()).increment(1);
+}
+
+pub(crate) fn command_round_trip(peer: &str, type_id: TypeId, elapsed: Duration) {
+    histogram!(
+        COMMAND_ROUND_TRIP,
+        "peer" => peer.to_owned(),
+        "type" => type_id.standard_name()
+    )
+    .record(elapsed.as_secs_f64());
+}
+
+pub(crate) fn reconnect(peer: &str) {
+    counter!(RECONNECTS, "peer" => peer.to_owned()).increment(1);
+}
+
+pub(crate) fn event_dropped(peer: &str) {
+    counter!(EVENTS_DROPPED, "peer" => peer.to_owned()).increment(1);
+}
+
+pub(crate) fn connection_state(peer: &str, state: ConnectionState) {
+    let value = match state {
+        ConnectionState::Disconnected => 0.0,
+        ConnectionState::Connected => 1.0,
+        ConnectionState::Active => 2.0,
+        ConnectionState::Stopping => 3.0,
+    };
+    gauge!(CONNECTION_STATE, "peer" => peer.to_owned()).set(value);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+    use std::sync::atomic::{AtomicU64, Ordering};
+    use std::sync::{Arc, Mutex};
+
+    use metrics::{
+        Counter, Gauge, Histogram, Key, KeyName, Metadata, Recorder, SharedString, Unit,
+    };
+
+    use crate::types::{Asdu, UFunction};
+
+    /// Keeps counters by name and sorted labels.
+    #[derive(Default)]
+    struct Counters(Mutex<HashMap<String, Arc<AtomicU64>>>);
+
+    impl Counters {
+        fn get(&self, key: &str) -> u64 {
+            self.0
+                .lock()
+                .unwrap()
+                .get(key)
+                .map_or(0, |value| value.load(Ordering::Relaxed))
+        }
+    }
+
+    impl Recorder for Counters {
+        fn describe_counter(&self, _: KeyName, _: Option<Unit>, _: SharedString) {}
+        fn describe_gauge(&self, _: KeyName, _: Option<Unit>, _: SharedString) {}
+        fn describe_histogram(&self, _: KeyName, _: Option<Unit>, _: SharedString) {}
+
+        fn register_counter(&self, key: &Key, _: &Metadata<'_>) -> Counter {
+            let mut labels: Vec<String> = key
+                .labels()
+                .map(|label| format!("{}={}", label.key(), label.value()))
+                .collect();
+            labels.sort();
+            let name = format!("{}{{{}}}", key.name(), labels.join(","));
+            let value = self.0.lock().unwrap().entry(name).or_default().clone();
+            Counter::from_arc(value)
+        }
+
+        fn register_gauge(&self, _: &Key, _: &Metadata<'_>) -> Gauge {
+            Gauge::noop()
+        }
+
+        fn register_histogram(&self, _: &Key, _: &Metadata<'_>) -> Histogram {
+            Histogram::noop()
+        }
+    }
+
+    #[test]
+    fn test_recorded_labels() {
+        let recorder = Counters::default();
+        metrics::with_local_recorder(&recorder, || {
+            let apdu = Apdu::i_frame(0, 0, Asdu::interrogation_command(1, 20));
+            frame("rtu:2404", FrameDirection::Sent, &apdu);
+            frame("rtu:2404", FrameDirection::Sent, &apdu);
+            frame(
+                "rtu:2404",
+                FrameDirection::Received,
+                &Apdu::u_frame(UFunction::TestFrCon),
+            );
+            read_error("rtu:2404", &Iec104Error::protocol_static("bad frame"));
+            read_error(
+                "rtu:2404",
+                &Iec104Error::Io(std::io::ErrorKind::BrokenPipe.into()),
+            );
+            event_dropped("rtu:2404");
+        });
+
+        assert_eq!(
+            recorder
+                .get("iec104_frames_total{direction=sent,frame=I,peer=rtu:2404,type=C_IC_NA_1}"),
+            2
+        );
+        assert_eq!(
+            recorder.get("iec104_frames_total{direction=received,frame=U,peer=rtu:2404,type=}"),
+            1
+        );
+        assert_eq!(recorder.get("iec104_parse_errors_total{peer=rtu:2404}"), 1);
+        assert_eq!(
+            recorder.get("iec104_events_dropped_total{peer=rtu:2404}"),
+            1
+        );
+    }
+
+    #[tokio::test]
+    async fn test_link_errors_counted() {
+        use bytes::Bytes;
+        use futures::{SinkExt, StreamExt};
+        use tokio::io::{AsyncWriteExt, DuplexStream};
+        use tokio_util::codec::Framed;
+
+        use crate::client::{ClientConfig, Iec104Client};
+        use crate::codec::Iec104Codec;
+        use crate::types::{AsduHeader, Cot};
+
+        // Both errors end the connection, so each gets one of its own
+        async fn start(client: &mut Iec104Client) -> Framed<DuplexStream, Iec104Codec> {
+            let (client_end, server_end) = tokio::io::duplex(1024);
+            client.connect_stream(client_end).await.unwrap();
+            let mut server = Framed::new(server_end, Iec104Codec::new());
+            let (started, _) = tokio::join!(client.start_dt(), async {
+                server.next().await.unwrap().unwrap();
+                server.send(Apdu::u_frame(UFunction::StartDtCon)).await.unwrap();
+            });
+            started.unwrap();
+            server
+        }
+
+        let recorder = Counters::default();
+        let _guard = metrics::set_default_local_recorder(&recorder);
+        let mut client = Iec104Client::new(ClientConfig::new("rtu:2404"));
+
+        // Send sequence number 5 where 0 is expected, then a truncated M_SP_NA_1
+        let mut server = start(&mut client).await;
+        let mut data = Asdu::new(AsduHeader::new(TypeId::SinglePoint, 1, Cot::Spontaneous, 1));
+        data.raw_data = Bytes::from_static(&[0x01, 0x00, 0x00, 0x01]);
+        server.send(Apdu::i_frame(5, 0, data)).await.unwrap();
+        assert!(matches!(
+            client.poll().await,
+            Err(Iec104Error::SequenceMismatch { expected: 0, actual: 5 })
+        ));
+        let mut server = start(&mut client).await;
+        let truncated = [
+            0x68, 0x0C, 0x00, 0x00, 0x00, 0x00, 0x01, 0x01, 0x03, 0x00, 0x01, 0x00, 0x01, 0x00,
+        ];
+        server.get_mut().write_all(&truncated).await.unwrap();
+        assert!(client.poll().await.is_err());
+
+        assert_eq!(recorder.get("iec104_sequence_errors_total{peer=rtu:2404}"), 1);
+        assert_eq!(recorder.get("iec104_parse_errors_total{peer=rtu:2404}"), 1);
+    }
+}
@@ -0,0 +1,687 @@
+//! Mapping between IEC 104 information objects and Modbus registers.
+//!
+//! A gateway between the two protocols mostly shuffles values from one
+//! address space to the other. [`ModbusMap`] does this from a declarative
+//! table of [`RegisterMapping`]s, independent of the Modbus crate in use:
+//!
+//! - [`encode_point`](ModbusMap::encode_point) turns a received data point
+//!   into the register values to store, e.g. in a Modbus server;
+//! - [`decode_block`](ModbusMap::decode_block) turns registers read from a
+//!   Modbus device into data points;
+//! - [`decode_write`](ModbusMap::decode_write) turns a Modbus write to coils
+//!   or holding registers into IEC 104 commands.
+//!
+//! Bits (coils and discrete inputs) are exchanged as one `u16` each, 0 or 1.
+//! Multi-register values are big-endian ("ABCD") unless the mapping swaps
+//! the words ("CDAB"). The engineering value is the raw value multiplied by
+//! the scale of the mapping. With the `serde` feature the table can be read
+//! from a configuration file.
+
+use std::collections::HashMap;
+
+use crate::command::{Command, StepCommand};
+use crate::error::{Iec104Error, Result};
+use crate::types::{DataPoint, DataValue, DoubleCommandState, DoublePointValue, Qos, TypeId};
+
+/// Modbus data table.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum RegisterKind {
+    /// Read-write bits
+    Coil,
+    /// Read-only bits
+    DiscreteInput,
+    /// Read-only 16-bit registers
+    InputRegister,
+    /// Read-write 16-bit registers
+    HoldingRegister,
+}
+
+impl RegisterKind {
+    /// Check if the table holds single bits.
+    #[inline]
+    pub const fn is_bit(self) -> bool {
+        matches!(self, Self::Coil | Self::DiscreteInput)
+    }
+
+    /// Check if a Modbus master may write the table.
+    #[inline]
+    pub const fn is_writable(self) -> bool {
+        matches!(self, Self::Coil | Self::HoldingRegister)
+    }
+}
+
+/// Encoding of a value in the Modbus table.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum RegisterFormat {
+    /// Single coil or discrete input
+    Bit,
+    /// Unsigned 16-bit register
+    U16,
+    /// Signed 16-bit register
+    I16,
+    /// Unsigned 32-bit value in two registers
+    U32,
+    /// Signed 32-bit value in two registers
+    I32,
+    /// IEEE 754 single precision in two registers
+    F32,
+}
+
+impl RegisterFormat {
+    /// Number of bits or registers taken by a value.
+    #[inline]
+    pub const fn width(self) -> u16 {
+        match self {
+            Self::Bit | Self::U16 | Self::I16 => 1,
+            Self::U32 | Self::I32 | Self::F32 => 2,
+        }
+    }
+}
+
+/// One row of the mapping table: an information object and where its value
+/// lives in the Modbus tables.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct RegisterMapping {
+    /// Common address of the station
+    pub common_address: u16,
+    /// Information object address
+    pub ioa: u32,
+    /// Type of the information object; a command type maps writes to
+    /// commands, a monitoring type maps values to data points
+    pub type_id: TypeId,
+    /// Modbus table
+    pub kind: RegisterKind,
+    /// First bit or register (0-based)
+    pub address: u16,
+    /// Encoding of the value
+    pub format: RegisterFormat,
+    /// Engineering value of one raw unit
+    #[cfg_attr(feature = "serde", serde(default = "default_scale"))]
+    pub scale: f64,
+    /// Low word first for 32-bit formats
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub swap_words: bool,
+}
+
+#[cfg(feature = "serde")]
+fn default_scale() -> f64 {
+    1.0
+}
+
+impl RegisterMapping {
+    /// Create a mapping with scale 1 and big-endian word order.
+    pub fn new(
+        common_address: u16,
+        ioa: u32,
+        type_id: TypeId,
+        kind: RegisterKind,
+        address: u16,
+        format: RegisterFormat,
+    ) -> Self {
+        Self {
+            common_address,
+            ioa,
+            type_id,
+            kind,
+            address,
+            format,
+            scale: 1.0,
+            swap_words: false,
+        }
+    }
+
+    /// Set the engineering value of one raw unit.
+    pub fn scale(mut self, scale: f64) -> Self {
+        self.scale = scale;
+        self
+    }
+
+    /// Put the low word first for 32-bit formats.
+    pub fn swap_words(mut self, swap: bool) -> Self {
+        self.swap_words = swap;
+        self
+    }
+
+    /// Bits or registers covered by the mapping.
+    fn span(&self) -> std::ops::Range<u32> {
+        let start = u32::from(self.address);
+        start..start + u32::from(self.format.width())
+    }
+
+    /// Read the engineering value from the registers of the mapping.
+    fn read(&self, words: &[u16]) -> f64 {
+        let pair = || {
+            let (high, low) = if self.swap_words {
+                (words[1], words[0])
+            } else {
+                (words[0], words[1])
+            };
+            u32::from(high) << 16 | u32::from(low)
+        };
+        let raw = match self.format {
+            RegisterFormat::Bit => f64::from(u8::from(words[0] != 0)),
+            RegisterFormat::U16 => f64::from(words[0]),
+            RegisterFormat::I16 => f64::from(words[0] as i16),
+            RegisterFormat::U32 => f64::from(pair()),
+            RegisterFormat::I32 => f64::from(pair() as i32),
+            RegisterFormat::F32 => f64::from(f32::from_bits(pair())),
+        };
+        raw * self.scale
+    }
+
+    /// Registers holding an engineering value; integers saturate.
+    fn write(&self, value: f64) -> Vec<u16> {
+        let raw = value / self.scale;
+        let pair = |bits: u32| {
+            let (high, low) = ((bits >> 16) as u16, bits as u16);
+            if self.swap_words {
+                vec![low, high]
+            } else {
+                vec![high, low]
+            }
+        };
+        match self.format {
+            RegisterFormat::Bit => vec![u16::from(raw != 0.0)],
+            RegisterFormat::U16 => vec![raw.round() as u16],
+            RegisterFormat::I16 => vec![raw.round() as i16 as u16],
+            RegisterFormat::U32 => pair(raw.round() as u32),
+            RegisterFormat::I32 => pair(raw.round() as i32 as u32),
+            RegisterFormat::F32 => pair((raw as f32).to_bits()),
+        }
+    }
+}
+
+/// Register values at consecutive addresses of one Modbus table.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RegisterBlock {
+    /// Modbus table
+    pub kind: RegisterKind,
+    /// Address of the first value
+    pub address: u16,
+    /// Register values, or 0/1 per bit
+    pub values: Vec<u16>,
+}
+
+/// What a mapped type identification turns into.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Role {
+    Single,
+    Double,
+    Normalized,
+    Scaled,
+    Float,
+    Counter,
+    Bitstring,
+    Step,
+    SingleCommand,
+    DoubleCommand,
+    StepCommand,
+    SetpointNormalized,
+    SetpointScaled,
+    SetpointFloat,
+}
+
+impl Role {
+    fn of(type_id: TypeId) -> Option<Self> {
+        Some(match type_id {
+            TypeId::SinglePoint | TypeId::SinglePointTime24 | TypeId::SinglePointTime56 => {
+                Self::Single
+            }
+            TypeId::DoublePoint | TypeId::DoublePointTime24 | TypeId::DoublePointTime56 => {
+                Self::Double
+            }
+            TypeId::StepPosition | TypeId::StepPositionTime56 => Self::Step,
+            TypeId::Bitstring32 | TypeId::Bitstring32Time56 => Self::Bitstring,
+            TypeId::MeasuredNormalized
+            | TypeId::MeasuredNormalizedTime24
+            | TypeId::MeasuredNormalizedTime56
+            | TypeId::MeasuredNormalizedNoQuality => Self::Normalized,
+            TypeId::MeasuredScaled
+            | TypeId::MeasuredScaledTime24
+            | TypeId::MeasuredScaledTime56 => Self::Scaled,
+            TypeId::MeasuredFloat | TypeId::MeasuredFloatTime24 | TypeId::MeasuredFloatTime56 => {
+                Self::Float
+            }
+            TypeId::IntegratedTotals | TypeId::IntegratedTotalsTime56 => Self::Counter,
+            // Commands are issued as [`Command`], which carries no time tag
+            TypeId::SingleCommand => Self::SingleCommand,
+            TypeId::DoubleCommand => Self::DoubleCommand,
+            TypeId::RegulatingStep => Self::StepCommand,
+            TypeId::SetpointNormalized => Self::SetpointNormalized,
+            TypeId::SetpointScaled => Self::SetpointScaled,
+            TypeId::SetpointFloat => Self::SetpointFloat,
+            _ => return None,
+        })
+    }
+
+    const fn is_command(self) -> bool {
+        matches!(
+            self,
+            Self::SingleCommand
+                | Self::DoubleCommand
+                | Self::StepCommand
+                | Self::SetpointNormalized
+                | Self::SetpointScaled
+                | Self::SetpointFloat
+        )
+    }
+
+    fn value(self, value: f64) -> Option<DataValue> {
+        Some(match self {
+            Self::Single => DataValue::Single(value != 0.0),
+            Self::Double => DataValue::Double(if value != 0.0 {
+                DoublePointValue::On
+            } else {
+                DoublePointValue::Off
+            }),
+            Self::Normalized => DataValue::Normalized(value.clamp(-1.0, 1.0) as f32),
+            Self::Scaled => DataValue::Scaled(value.round() as i16),
+            Self::Float => DataValue::Float(value as f32),
+            Self::Counter => DataValue::Counter(value.round() as i32),
+            Self::Bitstring => DataValue::Bitstring(value as u32),
+            Self::Step => DataValue::StepPosition {
+                value: value.round().clamp(-64.0, 63.0) as i8,
+                transient: false,
+            },
+            _ => return None,
+        })
+    }
+
+    fn command(self, ioa: u32, value: f64) -> Option<Command> {
+        Some(match self {
+            Self::SingleCommand => Command::Single {
+                ioa,
+                value: value != 0.0,
+            },
+            Self::DoubleCommand => Command::Double {
+                ioa,
+                value: if value != 0.0 {
+                    DoubleCommandState::On
+                } else {
+                    DoubleCommandState::Off
+                },
+            },
+            Self::StepCommand => Command::RegulatingStep {
+                ioa,
+                step: if value > 0.0 {
+                    StepCommand::Higher
+                } else if value < 0.0 {
+                    StepCommand::Lower
+                } else {
+                    return None;
+                },
+            },
+            Self::SetpointNormalized => Command::SetpointNormalized {
+                ioa,
+                value: value.clamp(-1.0, 1.0) as f32,
+                qos: Qos::EXECUTE,
+            },
+            Self::SetpointScaled => Command::SetpointScaled {
+                ioa,
+                value: value.round() as i16,
+                qos: Qos::EXECUTE,
+            },
+            Self::SetpointFloat => Command::SetpointFloat {
+                ioa,
+                value: value as f32,
+                qos: Qos::EXECUTE,
+            },
+            _ => return None,
+        })
+    }
+}
+
+/// Validated mapping table.
+#[derive(Debug, Clone, Default)]
+pub struct ModbusMap {
+    mappings: Vec<RegisterMapping>,
+    points: HashMap<(u16, u32), usize>,
+}
+
+impl ModbusMap {
+    /// Build the map from its rows.
+    ///
+    /// Fails when an information object is mapped twice, two rows overlap in
+    /// a Modbus table, a type cannot be mapped, a bit table is paired with a
+    /// register format (or the reverse), a command is mapped to a read-only
+    /// table, or a scale is zero or not finite.
+    pub fn new(mappings: impl IntoIterator<Item = RegisterMapping>) -> Result<Self> {
+        let mappings: Vec<RegisterMapping> = mappings.into_iter().collect();
+        let mut points = HashMap::with_capacity(mappings.len());
+
+        for (index, mapping) in mappings.iter().enumerate() {
+            let row = |reason: &str| {
+                Iec104Error::protocol(format!(
+                    "Modbus mapping of CA {} IOA {}: {}",
+                    mapping.common_address, mapping.ioa, reason
+                ))
+            };
+            let role = Role::of(mapping.type_id)
+                .ok_or_else(|| row(&format!("{} cannot be mapped", mapping.type_id)))?;
+            if mapping.kind.is_bit() != (mapping.format == RegisterFormat::Bit) {
+                return Err(row("bit tables take the Bit format and only they do"));
+            }
+            if role.is_command() && !mapping.kind.is_writable() {
+                return Err(row("commands need coils or holding registers"));
+            }
+            if !mapping.scale.is_finite() || mapping.scale == 0.0 {
+                return Err(row("scale must be finite and not zero"));
+            }
+            if mapping.span().end > 0x1_0000 {
+                return Err(row("value extends past address 65535"));
+            }
+            if points
+                .insert((mapping.common_address, mapping.ioa), index)
+                .is_some()
+            {
+                return Err(row("information object mapped twice"));
+            }
+            let overlap = mappings[..index].iter().find(|other| {
+                other.kind == mapping.kind
+                    && other.span().start < mapping.span().end
+                    && mapping.span().start < other.span().end
+            });
+            if let Some(other) = overlap {
+                return Err(row(&format!(
+                    "registers overlap with CA {} IOA {}",
+                    other.common_address, other.ioa
+                )));
+            }
+        }
+
+        Ok(Self { mappings, points })
+    }
+
+    /// Rows of the table, in the order given.
+    pub fn mappings(&self) -> &[RegisterMapping] {
+        &self.mappings
+    }
+
+    /// Row of an information object.
+    pub fn mapping(&self, common_address: u16, ioa: u32) -> Option<&RegisterMapping> {
+        self.points
+            .get(&(common_address, ioa))
+            .map(|&index| &self.mappings[index])
+    }
+
+    /// Register values representing a received data point.
+    ///
+    /// Returns `None` for unmapped points and values without a numeric
+    /// reading, such as an indeterminate double point.
+    pub fn encode_point(&self, common_address: u16, point: &DataPoint) -> Option<RegisterBlock> {
+        let mapping = self.mapping(common_address, point.ioa)?;
+        let value = point.value.as_f64().filter(|value| !value.is_nan())?;
+        Some(RegisterBlock {
+            kind: mapping.kind,
+            address: mapping.address,
+            values: mapping.write(value),
+        })
+    }
+
+    /// Data points of the monitoring rows lying entirely in a block read
+    /// from a Modbus device, with their common addresses.
+    pub fn decode_block(
+        &self,
+        kind: RegisterKind,
+        address: u16,
+        values: &[u16],
+    ) -> Vec<(u16, DataPoint)> {
+        self.covered(kind, address, values)
+            .filter_map(|(mapping, role, words)| {
+                let value = role.value(mapping.read(words))?;
+                Some((mapping.common_address, DataPoint::new(mapping.ioa, value)))
+            })
+            .collect()
+    }
+
+    /// Commands of the command rows lying entirely in a block written by a
+    /// Modbus master, with their common addresses.
+    ///
+    /// A regulating step is issued for a positive (higher) or negative
+    /// (lower) value; zero issues nothing.
+    pub fn decode_write(
+        &self,
+        kind: RegisterKind,
+        address: u16,
+        values: &[u16],
+    ) -> Vec<(u16, Command)> {
+        self.covered(kind, address, values)
+            .filter_map(|(mapping, role, words)| {
+                let command = role.command(mapping.ioa, mapping.read(words))?;
+                Some((mapping.common_address, command))
+            })
+            .collect()
+    }
+
+    /// Rows of `kind` within the block, with their registers.
+    fn covered<'a>(
+        &'a self,
+        kind: RegisterKind,
+        address: u16,
+        values: &'a [u16],
+    ) -> impl Iterator<Item = (&'a RegisterMapping, Role, &'a [u16])> + 'a {
+        let start = u32::from(address);
+        let end = start + values.len() as u32;
+        self.mappings.iter().filter_map(move |mapping| {
+            let span = mapping.span();
+            if mapping.kind != kind || span.start < start || span.end > end {
+                return None;
+            }
+            let offset = (span.start - start) as usize;
+            let words = &values[offset..offset + span.len()];
+            Some((mapping, Role::of(mapping.type_id)?, words))
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn map() -> ModbusMap {
+        ModbusMap::new([
+            RegisterMapping::new(
+                1,
+                100,
+                TypeId::SinglePoint,
+                RegisterKind::DiscreteInput,
+                0,
+                RegisterFormat::Bit,
+            ),
+            RegisterMapping::new(
+                1,
+                200,
+                TypeId::MeasuredFloat,
+                RegisterKind::InputRegister,
+                10,
+                RegisterFormat::F32,
+            ),
+            RegisterMapping::new(
+                1,
+                201,
+                TypeId::MeasuredScaled,
+                RegisterKind::InputRegister,
+                12,
+                RegisterFormat::I16,
+            )
+            .scale(0.1),
+            RegisterMapping::new(
+                2,
+                300,
+                TypeId::IntegratedTotals,
+                RegisterKind::InputRegister,
+                20,
+                RegisterFormat::U32,
+            )
+            .swap_words(true),
+            RegisterMapping::new(
+                1,
+                500,
+                TypeId::SingleCommand,
+                RegisterKind::Coil,
+                0,
+                RegisterFormat::Bit,
+            ),
+            RegisterMapping::new(
+                1,
+                600,
+                TypeId::SetpointFloat,
+                RegisterKind::HoldingRegister,
+                0,
+                RegisterFormat::I32,
+            )
+            .scale(0.01),
+        ])
+        .unwrap()
+    }
+
+    #[test]
+    fn test_decode_block() {
+        let map = map();
+        let mut registers = vec![0u16; 24];
+        registers[10..12].copy_from_slice(&[0x4248, 0x0000]); // 50.0
+        registers[12] = (-123i16) as u16;
+        registers[20..22].copy_from_slice(&[0x0002, 0x0001]); // 0x0001_0002
+
+        let points = map.decode_block(RegisterKind::InputRegister, 0, &registers);
+        assert_eq!(
+            points,
+            vec![
+                (1, DataPoint::new(200, DataValue::Float(50.0))),
+                (1, DataPoint::new(201, DataValue::Scaled(-12))),
+                (2, DataPoint::new(300, DataValue::Counter(0x0001_0002))),
+            ]
+        );
+
+        // Only rows entirely inside the block
+        let points = map.decode_block(RegisterKind::InputRegister, 11, &registers[11..21]);
+        assert_eq!(
+            points,
+            vec![(1, DataPoint::new(201, DataValue::Scaled(-12)))]
+        );
+
+        let bits = map.decode_block(RegisterKind::DiscreteInput, 0, &[1]);
+        assert_eq!(
+            bits,
+            vec![(1, DataPoint::new(100, DataValue::Single(true)))]
+        );
+    }
+
+    #[test]
+    fn test_encode_point() {
+        let map = map();
+        let block = map.encode_point(1, &DataPoint::new(201, DataValue::Scaled(-12)));
+        assert_eq!(
+            block,
+            Some(RegisterBlock {
+                kind: RegisterKind::InputRegister,
+                address: 12,
+                values: vec![(-120i16) as u16],
+            })
+        );
+
+        let counter = DataPoint::new(300, DataValue::Counter(0x0001_0002));
+        assert_eq!(
+            map.encode_point(2, &counter).unwrap().values,
+            vec![0x0002, 0x0001]
+        );
+
+        let float = DataPoint::new(200, DataValue::Float(50.0));
+        assert_eq!(
+            map.encode_point(1, &float).unwrap().values,
+            vec![0x4248, 0x0000]
+        );
+
+        assert!(map.encode_point(2, &float).is_none());
+    }
+
+    #[test]
+    fn test_decode_write() {
+        let map = map();
+        assert_eq!(
+            map.decode_write(RegisterKind::Coil, 0, &[1]),
+            vec![(
+                1,
+                Command::Single {
+                    ioa: 500,
+                    value: true
+                }
+            )]
+        );
+
+        let raw = 4_950i32 as u32;
+        let commands = map.decode_write(
+            RegisterKind::HoldingRegister,
+            0,
+            &[(raw >> 16) as u16, raw as u16],
+        );
+        assert_eq!(
+            commands,
+            vec![(
+                1,
+                Command::SetpointFloat {
+                    ioa: 600,
+                    value: 49.5,
+                    qos: Qos::EXECUTE,
+                }
+            )]
+        );
+
+        // Monitoring rows do not produce commands
+        assert!(map
+            .decode_write(RegisterKind::InputRegister, 10, &[0x4248, 0])
+            .is_empty());
+    }
+
+    #[test]
+    fn test_invalid_mappings() {
+        let float = |ioa, address| {
+            RegisterMapping::new(
+                1,
+                ioa,
+                TypeId::MeasuredFloat,
+                RegisterKind::InputRegister,
+                address,
+                RegisterFormat::F32,
+            )
+        };
+        assert!(ModbusMap::new([float(1, 0), float(2, 2)]).is_ok());
+        assert!(ModbusMap::new([float(1, 0), float(2, 1)]).is_err());
+        assert!(ModbusMap::new([float(1, 0), float(1, 4)]).is_err());
+        assert!(ModbusMap::new([float(1, 0xFFFF)]).is_err());
+        assert!(ModbusMap::new([float(1, 0).scale(0.0)]).is_err());
+
+        let mut bit = float(1, 0);
+        bit.format = RegisterFormat::Bit;
+        assert!(ModbusMap::new([bit]).is_err());
+
+        let mut command = float(1, 0);
+        command.type_id = TypeId::SetpointFloat;
+        assert!(ModbusMap::new([command.clone()]).is_err());
+        command.kind = RegisterKind::HoldingRegister;
+        assert!(ModbusMap::new([command]).is_ok());
+
+        let mut interrogation = float(1, 0);
+        interrogation.type_id = TypeId::InterrogationCommand;
+        assert!(ModbusMap::new([interrogation]).is_err());
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_mapping_table_serde() {
+        let table = r#"[
+            {"common_address": 1, "ioa": 201, "type_id": "MeasuredScaled",
+             "kind": "InputRegister", "address": 12, "format": "I16", "scale": 0.1},
+            {"common_address": 1, "ioa": 500, "type_id": "SingleCommand",
+             "kind": "Coil", "address": 0, "format": "Bit"}
+        ]"#;
+        let rows: Vec<RegisterMapping> = serde_json::from_str(table).unwrap();
+        let map = ModbusMap::new(rows).unwrap();
+        assert_eq!(map.mapping(1, 201).unwrap().scale, 0.1);
+        assert_eq!(map.mapping(1, 500).unwrap().scale, 1.0);
+        assert!(!map.mapping(1, 500).unwrap().swap_words);
+    }
+}
@@ -0,0 +1,424 @@
+//! Bridge between a spawned client and an MQTT broker.
+//!
+//! [`MqttBridge`] publishes every data point received from the configured
+//! stations in the canonical JSON form (see [`crate::json`]) to a topic built
+//! from a template, e.g. `iec104/{ca}/{ioa}`. With a command topic it also
+//! subscribes to control requests and issues them as IEC 104 commands.
+//!
+//! A command is a JSON object naming the [`Command`](crate::Command) variant
+//! and its value, see [`crate::json`]; the station and object come from the
+//! topic:
+//!
+//! ```json
+//! {"type": "Double", "value": "Off", "select": true}
+//! ```
+//!
+//! The outcome is published to the command topic with `/result` appended:
+//! `{"success": true}` once the station confirms, otherwise
+//! `{"success": false, "error": "..."}`.
+//!
+//! Retained command messages are ignored: the broker delivers them again on
+//! every reconnection and subscription, long after they were meant, so
+//! executing them would repeat stale controls. Commands arriving while 16
+//! others are queued are answered as failed right away.
+//!
+//! # Back pressure
+//!
+//! The IEC 104 link never waits for the broker. While publications are
+//! backed up, for instance during a broker outage, data updates beyond the
+//! 64 queued are dropped; with [`retain`](MqttBridge::retain) the broker
+//! still gets the latest value of each point once it is reachable and the
+//! point changes again.
+//!
+//! # Example
+//!
+//! ```rust,ignore
+//! let (handle, _task) = client.spawn();
+//! let (mqtt, eventloop) = AsyncClient::new(MqttOptions::new("gateway", "broker", 1883), 64);
+//! MqttBridge::new()
+//!     .station(1)
+//!     .command_topic("iec104/{ca}/{ioa}/set")
+//!     .run(handle, mqtt, eventloop)
+//!     .await?;
+//! ```
+
+use std::time::Duration;
+
+use rumqttc::{AsyncClient, Event, EventLoop, Packet, Publish, QoS};
+use serde_json::json;
+use tokio::sync::mpsc;
+use tokio::sync::mpsc::error::TrySendError;
+
+use crate::error::{Iec104Error, Result};
+use crate::filter::EventFilter;
+use crate::handle::ClientHandle;
+use crate::json::parse_command;
+use crate::types::DataPoint;
+use crate::Iec104Event;
+
+/// Default topic template of published data points.
+pub const DEFAULT_TOPIC: &str = "iec104/{ca}/{ioa}";
+
+/// Pause before the MQTT event loop reconnects after an error.
+const RECONNECT_DELAY: Duration = Duration::from_secs(1);
+
+/// Data updates queued for publication before new ones are dropped.
+const UPDATE_QUEUE: usize = 64;
+
+/// Commands queued for execution before new ones are refused.
+const COMMAND_QUEUE: usize = 16;
+
+/// Publishes data updates to MQTT and turns MQTT messages into commands.
+///
+/// Topic templates may use `{ca}` (common address) and `{ioa}`; in the
+/// command topic each of them must make up a whole topic level. Commands
+/// are only accepted for the stations added with [`station`](Self::station).
+#[derive(Debug, Clone)]
+pub struct MqttBridge {
+    topic: String,
+    command_topic: Option<String>,
+    stations: Vec<u16>,
+    qos: QoS,
+    retain: bool,
+}
+
+impl Default for MqttBridge {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl MqttBridge {
+    /// Create a bridge publishing to [`DEFAULT_TOPIC`] with QoS 1.
+    pub fn new() -> Self {
+        Self {
+            topic: DEFAULT_TOPIC.to_string(),
+            command_topic: None,
+            stations: Vec::new(),
+            qos: QoS::AtLeastOnce,
+            retain: false,
+        }
+    }
+
+    /// Set the topic template of data points.
+    pub fn topic(mut self, template: impl Into<String>) -> Self {
+        self.topic = template.into();
+        self
+    }
+
+    /// Subscribe to commands on topics matching `template`.
+    pub fn command_topic(mut self, template: impl Into<String>) -> Self {
+        self.command_topic = Some(template.into());
+        self
+    }
+
+    /// Bridge the data of the station with this common address.
+    pub fn station(mut self, common_address: u16) -> Self {
+        self.stations.push(common_address);
+        self
+    }
+
+    /// Set the quality of service of publications and subscriptions.
+    pub fn qos(mut self, qos: QoS) -> Self {
+        self.qos = qos;
+        self
+    }
+
+    /// Ask the broker to retain the latest value of each point.
+    pub fn retain(mut self, retain: bool) -> Self {
+        self.retain = retain;
+        self
+    }
+
+    /// Topic a point of a station is published to.
+    pub fn data_topic(&self, common_address: u16, ioa: u32) -> String {
+        fill(&self.topic, common_address, ioa)
+    }
+
+    /// Run the bridge until the client task ends.
+    ///
+    /// Drives `eventloop` in a task of its own, reconnecting to the broker
+    /// after errors. Fails without stations, when a template is unusable or
+    /// when `mqtt` can no longer queue requests.
+    pub async fn run(
+        self,
+        handle: ClientHandle,
+        mqtt: AsyncClient,
+        eventloop: EventLoop,
+    ) -> Result<()> {
+        if self.stations.is_empty() {
+            return Err(Iec104Error::protocol_static(
+                "MQTT bridge needs at least one station",
+            ));
+        }
+        let filter = match &self.command_topic {
+            Some(template) => Some(subscription_filter(template)?),
+            None => None,
+        };
+
+        let (update_tx, mut updates) = mpsc::channel::<(u16, Vec<DataPoint>)>(UPDATE_QUEUE);
+        for &common_address in &self.stations {
+            let filter = EventFilter::new().common_address(common_address);
+            let mut events = handle.subscribe_filtered(filter).await?;
+            let update_tx = update_tx.clone();
+            tokio::spawn(async move {
+                while let Some(event) = events.recv().await {
+                    let Iec104Event::DataUpdate(points) = event.event else {
+                        continue;
+                    };
+                    // Dropped rather than slowing down the IEC 104 link
+                    let update = (common_address, points);
+                    if let Err(TrySendError::Closed(_)) = update_tx.try_send(update) {
+                        break;
+                    }
+                }
+            });
+        }
+        drop(update_tx);
+
+        let (publish_tx, mut requests) = mpsc::channel(COMMAND_QUEUE);
+        let driver = tokio::spawn(drive(eventloop, mqtt.clone(), filter, self.qos, publish_tx));
+
+        let result = loop {
+            tokio::select! {
+                update = updates.recv() => {
+                    let Some((common_address, points)) = update else {
+                        break Ok(());
+                    };
+                    if let Err(err) = self.publish(&mqtt, common_address, &points).await {
+                        break Err(err);
+                    }
+                }
+                Some(publish) = requests.recv() => {
+                    self.handle_command(&handle, &mqtt, publish);
+                }
+            }
+        };
+        driver.abort();
+        result
+    }
+
+    async fn publish(
+        &self,
+        mqtt: &AsyncClient,
+        common_address: u16,
+        points: &[DataPoint],
+    ) -> Result<()> {
+        for point in points {
+            mqtt.publish(
+                self.data_topic(common_address, point.ioa),
+                self.qos,
+                self.retain,
+                point.to_json(),
+            )
+            .await
+            .map_err(mqtt_error)?;
+        }
+        Ok(())
+    }
+
+    /// Issue the command of a received message and report its outcome.
+    fn handle_command(&self, handle: &ClientHandle, mqtt: &AsyncClient, publish: Publish) {
+        let Some(template) = &self.command_topic else {
+            return;
+        };
+        let Some((common_address, ioa)) = match_topic(template, &publish.topic) else {
+            return;
+        };
+        // Only stations the bridge was set up for can be controlled
+        if !self.stations.contains(&common_address) {
+            return;
+        }
+
+        let handle = handle.clone();
+        let mqtt = mqtt.clone();
+        let qos = self.qos;
+        tokio::spawn(async move {
+            let outcome = match parse_command(ioa, &publish.payload) {
+                Ok((command, qualifier)) => {
+                    match handle.command(common_address, command, qualifier).await {
+                        Ok(completion) => completion.confirmed().await,
+                        Err(err) => Err(err),
+                    }
+                }
+                Err(err) => Err(err),
+            };
+            let topic = result_topic(&publish.topic);
+            let _ = mqtt.publish(topic, qos, false, outcome_json(outcome)).await;
+        });
+    }
+}
+
+/// What [`drive`] did with a received message.
+#[derive(Debug, PartialEq, Eq)]
+enum Received {
+    /// Queued for execution
+    Queued,
+    /// Retained by the broker, so possibly stale: ignored
+    Retained,
+    /// Refused as the queue is full; the outcome goes to this topic
+    Busy(String),
+    /// The bridge has stopped
+    Closed,
+}
+
+/// Queue a received message for the bridge without waiting, which would
+/// stop the event loop that publications rely on.
+fn receive(requests: &mpsc::Sender<Publish>, publish: Publish) -> Received {
+    if publish.retain {
+        return Received::Retained;
+    }
+    let topic = result_topic(&publish.topic);
+    match requests.try_send(publish) {
+        Ok(()) => Received::Queued,
+        Err(TrySendError::Full(_)) => Received::Busy(topic),
+        Err(TrySendError::Closed(_)) => Received::Closed,
+    }
+}
+
+/// Poll the MQTT event loop, forwarding received messages and subscribing
+/// to commands after every (re)connection.
+async fn drive(
+    mut eventloop: EventLoop,
+    mqtt: AsyncClient,
+    filter: Option<String>,
+    qos: QoS,
+    requests: mpsc::Sender<Publish>,
+) {
+    loop {
+        match eventloop.poll().await {
+            Ok(Event::Incoming(Packet::ConnAck(_))) => {
+                if let Some(filter) = &filter {
+                    let _ = mqtt.try_subscribe(filter.clone(), qos);
+                }
+            }
+            Ok(Event::Incoming(Packet::Publish(publish))) => match receive(&requests, publish) {
+                Received::Busy(topic) => {
+                    let busy = Err(Iec104Error::protocol_static("Too many commands queued"));
+                    let _ = mqtt.try_publish(topic, qos, false, outcome_json(busy));
+                }
+                Received::Closed => return,
+                Received::Queued | Received::Retained => {}
+            },
+            Ok(_) => {}
+            Err(_) => tokio::time::sleep(RECONNECT_DELAY).await,
+        }
+    }
+}
+
+/// Topic the outcome of a command received on `topic` is published to.
+fn result_topic(topic: &str) -> String {
+    format!("{}/result", topic)
+}
+
+/// Payload reporting the outcome of a command.
+fn outcome_json(outcome: Result<()>) -> String {
+    let result = match outcome {
+        Ok(()) => json!({ "success": true }),
+        Err(err) => json!({ "success": false, "error": err.to_string() }),
+    };
+    result.to_string()
+}
+
+/// Replace the placeholders of a topic template.
+fn fill(template: &str, common_address: u16, ioa: u32) -> String {
+    template
+        .replace("{ca}", &common_address.to_string())
+        .replace("{ioa}", &ioa.to_string())
+}
+
+/// MQTT subscription filter matching every topic of a command template.
+fn subscription_filter(template: &str) -> Result<String> {
+    let levels: Vec<&str> = template.split('/').collect();
+    if !levels.contains(&"{ca}") || !levels.contains(&"{ioa}") {
+        return Err(Iec104Error::protocol(format!(
+            "Command topic {:?} needs {{ca}} and {{ioa}} as whole levels",
+            template
+        )));
+    }
+    let filter: Vec<&str> = levels
+        .into_iter()
+        .map(|level| match level {
+            "{ca}" | "{ioa}" => "+",
+            level => level,
+        })
+        .collect();
+    Ok(filter.join("/"))
+}
+
+/// Common address and IOA of a topic matching a command template.
+fn match_topic(template: &str, topic: &str) -> Option<(u16, u32)> {
+    let mut common_address = None;
+    let mut ioa = None;
+    let mut levels = topic.split('/');
+    for expected in template.split('/') {
+        let level = levels.next()?;
+        match expected {
+            "{ca}" => common_address = Some(level.parse().ok()?),
+            "{ioa}" => ioa = Some(level.parse().ok()?),
+            _ if expected != level => return None,
+            _ => {}
+        }
+    }
+    if levels.next().is_some() {
+        return None;
+    }
+    Some((common_address?, ioa?))
+}
+
+fn mqtt_error(err: rumqttc::ClientError) -> Iec104Error {
+    Iec104Error::Connection(format!("MQTT: {}", err).into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_topics() {
+        let bridge = MqttBridge::new().topic("plant/{ca}/points/{ioa}");
+        assert_eq!(bridge.data_topic(3, 1001), "plant/3/points/1001");
+        assert_eq!(MqttBridge::new().data_topic(1, 7), "iec104/1/7");
+
+        let template = "iec104/{ca}/{ioa}/set";
+        assert_eq!(subscription_filter(template).unwrap(), "iec104/+/+/set");
+        assert!(subscription_filter("iec104/{ca}-{ioa}/set").is_err());
+
+        assert_eq!(match_topic(template, "iec104/3/1001/set"), Some((3, 1001)));
+        assert_eq!(match_topic(template, "iec104/3/1001/set/result"), None);
+        assert_eq!(match_topic(template, "iec104/3/x/set"), None);
+        assert_eq!(match_topic(template, "iec104/70000/1/set"), None);
+        assert_eq!(match_topic(template, "other/3/1001/set"), None);
+    }
+
+    #[tokio::test]
+    async fn test_received_commands() {
+        let (requests, mut queued) = mpsc::channel(1);
+        let command = |retain| {
+            let mut publish = Publish::new("iec104/1/5/set", QoS::AtLeastOnce, "{}");
+            publish.retain = retain;
+            publish
+        };
+
+        assert_eq!(receive(&requests, command(true)), Received::Retained);
+        assert_eq!(receive(&requests, command(false)), Received::Queued);
+        assert_eq!(
+            receive(&requests, command(false)),
+            Received::Busy("iec104/1/5/set/result".to_string())
+        );
+        assert!(!queued.recv().await.unwrap().retain);
+        assert!(queued.try_recv().is_err());
+
+        drop(queued);
+        assert_eq!(receive(&requests, command(false)), Received::Closed);
+    }
+
+    #[tokio::test]
+    async fn test_run_requires_station() {
+        let (handle, _task) = crate::Iec104Client::new(crate::ClientConfig::new("")).spawn();
+        let options = rumqttc::MqttOptions::new("test", "127.0.0.1", 1883);
+        let (mqtt, eventloop) = AsyncClient::new(options, 8);
+        let result = MqttBridge::new().run(handle, mqtt, eventloop).await;
+        assert!(matches!(result, Err(Iec104Error::Protocol(_))));
+    }
+}
@@ -0,0 +1,209 @@
+//! Publisher forwarding data updates of a spawned client to NATS.
+//!
+//! [`NatsPublisher`] publishes every data update received from the
+//! configured stations as one message to the subject of the station, built
+//! from a template such as `iec104.{ca}`. In JSON a message carries the
+//! points in the canonical form of [`crate::json`]:
+//!
+//! ```json
+//! {"seq": 41, "ca": 1, "points": [ ... ]}
+//! ```
+//!
+//! `seq` is the position in the client's event stream (see
+//! [`SequencedEvent`](crate::SequencedEvent)). With the `grpc` feature
+//! messages can instead be the `PointUpdate` message of
+//! `proto/iec104.proto` (see [`NatsPayload::Protobuf`]).
+//!
+//! # Example
+//!
+//! ```rust,ignore
+//! let (handle, _task) = client.spawn();
+//! let nats = async_nats::connect("nats://localhost:4222").await?;
+//! NatsPublisher::new()
+//!     .subject("site7.iec104.{ca}")
+//!     .station(1)
+//!     .run(handle, nats)
+//!     .await?;
+//! ```
+
+use bytes::Bytes;
+use serde_json::json;
+use tokio::sync::mpsc;
+
+use crate::error::{Iec104Error, Result};
+use crate::filter::EventFilter;
+use crate::handle::ClientHandle;
+use crate::json::point_value;
+use crate::types::DataPoint;
+use crate::Iec104Event;
+
+/// Default subject template of published updates.
+pub const DEFAULT_SUBJECT: &str = "iec104.{ca}";
+
+/// Encoding of published messages.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum NatsPayload {
+    /// JSON object with the points in canonical form
+    #[default]
+    Json,
+    /// `PointUpdate` message of `proto/iec104.proto`, without `lagged`
+    #[cfg(feature = "grpc")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "grpc")))]
+    Protobuf,
+}
+
+/// Publishes data updates to one NATS subject per station.
+///
+/// The subject template may use `{ca}` (common address).
+#[derive(Debug, Clone)]
+pub struct NatsPublisher {
+    subject: String,
+    stations: Vec<u16>,
+    payload: NatsPayload,
+}
+
+impl Default for NatsPublisher {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl NatsPublisher {
+    /// Create a publisher sending JSON to [`DEFAULT_SUBJECT`].
+    pub fn new() -> Self {
+        Self {
+            subject: DEFAULT_SUBJECT.to_string(),
+            stations: Vec::new(),
+            payload: NatsPayload::Json,
+        }
+    }
+
+    /// Set the subject template.
+    pub fn subject(mut self, template: impl Into<String>) -> Self {
+        self.subject = template.into();
+        self
+    }
+
+    /// Publish the data of the station with this common address.
+    pub fn station(mut self, common_address: u16) -> Self {
+        self.stations.push(common_address);
+        self
+    }
+
+    /// Set the encoding of messages.
+    pub fn payload(mut self, payload: NatsPayload) -> Self {
+        self.payload = payload;
+        self
+    }
+
+    /// Subject the updates of a station are published to.
+    pub fn station_subject(&self, common_address: u16) -> String {
+        self.subject.replace("{ca}", &common_address.to_string())
+    }
+
+    /// Run the publisher until the client task ends, then flush.
+    ///
+    /// Fails without stations and when `nats` can no longer take messages.
+    pub async fn run(self, handle: ClientHandle, nats: async_nats::Client) -> Result<()> {
+        if self.stations.is_empty() {
+            return Err(Iec104Error::protocol_static(
+                "NATS publisher needs at least one station",
+            ));
+        }
+
+        let (update_tx, mut updates) = mpsc::channel::<(u16, u64, Vec<DataPoint>)>(64);
+        for &common_address in &self.stations {
+            let filter = EventFilter::new().common_address(common_address);
+            let mut events = handle.subscribe_filtered(filter).await?;
+            let update_tx = update_tx.clone();
+            tokio::spawn(async move {
+                while let Some(event) = events.recv().await {
+                    if let Iec104Event::DataUpdate(points) = event.event {
+                        let update = (common_address, event.seq, points);
+                        if update_tx.send(update).await.is_err() {
+                            break;
+                        }
+                    }
+                }
+            });
+        }
+        drop(update_tx);
+
+        while let Some((common_address, seq, points)) = updates.recv().await {
+            let payload = self.encode(common_address, seq, &points);
+            nats.publish(self.station_subject(common_address), payload)
+                .await
+                .map_err(nats_error)?;
+        }
+        nats.flush().await.map_err(nats_error)
+    }
+
+    /// The message of an update.
+    fn encode(&self, common_address: u16, seq: u64, points: &[DataPoint]) -> Bytes {
+        match self.payload {
+            NatsPayload::Json => {
+                let points: Vec<_> = points.iter().map(point_value).collect();
+                json!({ "seq": seq, "ca": common_address, "points": points })
+                    .to_string()
+                    .into()
+            }
+            #[cfg(feature = "grpc")]
+            NatsPayload::Protobuf => {
+                use prost::Message;
+
+                crate::grpc::proto::PointUpdate {
+                    seq,
+                    points: points
+                        .iter()
+                        .map(|point| crate::grpc::point_message(common_address, point))
+                        .collect(),
+                    lagged: 0,
+                }
+                .encode_to_vec()
+                .into()
+            }
+        }
+    }
+}
+
+fn nats_error(err: impl std::fmt::Display) -> Iec104Error {
+    Iec104Error::Connection(format!("NATS: {}", err).into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::DataValue;
+
+    #[test]
+    fn test_subject() {
+        assert_eq!(NatsPublisher::new().station_subject(3), "iec104.3");
+        let publisher = NatsPublisher::new().subject("site7.{ca}.telemetry");
+        assert_eq!(publisher.station_subject(12), "site7.12.telemetry");
+    }
+
+    #[test]
+    fn test_encode() {
+        let points = [DataPoint::new(100, DataValue::Float(1.5))];
+        let message = NatsPublisher::new().encode(1, 41, &points);
+        let value: serde_json::Value = serde_json::from_slice(&message).unwrap();
+        assert_eq!(value["seq"], 41);
+        assert_eq!(value["ca"], 1);
+        assert_eq!(value["points"][0]["ioa"], 100);
+        assert_eq!(value["points"][0]["value"], 1.5);
+
+        #[cfg(feature = "grpc")]
+        {
+            use crate::grpc::proto::{point::Value as PointValue, PointUpdate};
+            use prost::Message;
+
+            let message = NatsPublisher::new()
+                .payload(NatsPayload::Protobuf)
+                .encode(1, 41, &points);
+            let update = PointUpdate::decode(message).unwrap();
+            assert_eq!(update.seq, 41);
+            assert_eq!(update.points[0].common_address, 1);
+            assert_eq!(update.points[0].value, Some(PointValue::Float(1.5)));
+        }
+    }
+}
@@ -1,15 +1,22 @@
 //! ASDU information object parser.
 //!
 //! This module provides parsing of information objects from ASDU raw data
-//! into structured `DataPoint` values.
+//! into structured `DataPoint` values, either collected with [`parse_asdu`]
+//! or lazily, one object at a time, with [`parse_asdu_iter`]. Commands in the
+//! control direction are decoded by [`parse_command`].
 
 use crate::error::{Iec104Error, Result};
-use crate::types::{Asdu, Cp56Time2a, DataPoint, DataValue, DoublePointValue, Quality, TypeId};
+use crate::types::{
+    vti_from_u8, Asdu, Cp24Time2a, Cp56Time2a, DataPoint, DataValue, DoublePointValue,
+    InfoObject, ParameterValue, Qpm, Quality, Timestamp, TypeId,
+};
 
 /// Parse an ASDU into a list of data points.
 ///
 /// This function extracts information objects from the ASDU and converts them
-/// into structured `DataPoint` values.
+/// into structured `DataPoint` values, collecting [`parse_asdu_iter`]. Types
+/// that carry no data points (commands, system and file transfer types) give
+/// an empty list.
 ///
 /// # Example
 ///
@@ -21,652 +28,270 @@ use crate::types::{Asdu, Cp56Time2a, DataPoint, DataValue, DoublePointValue, Qua
 /// }
 /// ```
 pub fn parse_asdu(asdu: &Asdu) -> Result<Vec<DataPoint>> {
-    let data = asdu.raw_data.as_ref();
-    let type_id = asdu.header.type_id;
-    let count = asdu.header.vsq.count as usize;
-    let sequence = asdu.header.vsq.sequence;
-
-    if count == 0 {
-        return Ok(Vec::new());
-    }
-
-    if data.is_empty() && count > 0 {
-        return Err(Iec104Error::invalid_asdu_static("Empty data for non-zero count"));
-    }
-
-    match type_id {
-        // Single-point information
-        TypeId::SinglePoint => parse_single_point(data, count, sequence, false),
-        TypeId::SinglePointTime56 => parse_single_point(data, count, sequence, true),
-
-        // Double-point information
-        TypeId::DoublePoint => parse_double_point(data, count, sequence, false),
-        TypeId::DoublePointTime56 => parse_double_point(data, count, sequence, true),
-
-        // Step position
-        TypeId::StepPosition => parse_step_position(data, count, sequence, false),
-
-        // Bitstring
-        TypeId::Bitstring32 => parse_bitstring(data, count, sequence, false),
-
-        // Measured values - normalized
-        TypeId::MeasuredNormalized => parse_measured_normalized(data, count, sequence, false),
-        TypeId::MeasuredNormalizedTime24 => parse_measured_normalized(data, count, sequence, false),
-
-        // Measured values - scaled
-        TypeId::MeasuredScaled => parse_measured_scaled(data, count, sequence, false),
-        TypeId::MeasuredScaledTime24 => parse_measured_scaled(data, count, sequence, false),
-
-        // Measured values - float
-        TypeId::MeasuredFloat => parse_measured_float(data, count, sequence, false),
-        TypeId::MeasuredFloatTime24 => parse_measured_float(data, count, sequence, false),
-        TypeId::MeasuredFloatTime56 => parse_measured_float(data, count, sequence, true),
-
-        // Integrated totals
-        TypeId::IntegratedTotals => parse_integrated_totals(data, count, sequence, false),
-
-        // Commands and system types - return empty (not data points)
-        TypeId::SingleCommand
-        | TypeId::DoubleCommand
-        | TypeId::RegulatingStep
-        | TypeId::SetpointNormalized
-        | TypeId::SetpointScaled
-        | TypeId::SetpointFloat
-        | TypeId::Bitstring32Command
-        | TypeId::SingleCommandTime56
-        | TypeId::DoubleCommandTime56
-        | TypeId::SetpointFloatTime56
-        | TypeId::EndOfInit
-        | TypeId::InterrogationCommand
-        | TypeId::CounterInterrogation
-        | TypeId::ReadCommand
-        | TypeId::ClockSync
-        | TypeId::TestCommand
-        | TypeId::ResetProcess
-        | TypeId::TestCommandTime56 => Ok(Vec::new()),
-
-        // Time-tagged variants without CP56Time2a
-        TypeId::SinglePointTime24 | TypeId::DoublePointTime24 => {
-            // These have 3-byte time tag (CP24Time2a), not full timestamp
-            // Parse as regular without timestamp for now
-            match type_id {
-                TypeId::SinglePointTime24 => parse_single_point_time24(data, count, sequence),
-                TypeId::DoublePointTime24 => parse_double_point_time24(data, count, sequence),
-                _ => unreachable!(),
-            }
-        }
-    }
+    parse_asdu_iter(asdu).collect()
 }
 
-/// Parse single-point information (M_SP_NA_1, M_SP_TB_1).
-fn parse_single_point(
-    data: &[u8],
-    count: usize,
-    sequence: bool,
-    with_time: bool,
-) -> Result<Vec<DataPoint>> {
-    let mut points = Vec::with_capacity(count);
-
-    // Calculate element size
-    let element_size = if with_time { 1 + 7 } else { 1 }; // SIQ + optional CP56Time2a
-
-    let required_len = if sequence {
-        3 + count * element_size
-    } else {
-        count * (3 + element_size)
-    };
-    if data.len() < required_len {
-        return Err(Iec104Error::invalid_asdu_static("Data too short"));
-    }
-    let first_ioa = read_ioa_le(data);
-    let mut offset = 3;
-
-    for i in 0..count {
-        // Get IOA
-        let ioa = if sequence {
-            first_ioa + i as u32
-        } else if i > 0 {
-            let ioa = read_ioa_le(&data[offset..]);
-            offset += 3;
-            ioa
-        } else {
-            first_ioa
-        };
-
-        // Parse SIQ (Single-point Information with Quality)
-        let siq = data[offset];
-        let value = (siq & 0x01) != 0;
-        let quality = Quality::from_siq(siq);
-        offset += 1;
-
-        // Parse timestamp if present
-        let timestamp = if with_time {
-            let ts = Cp56Time2a::from_bytes(&data[offset..offset + 7])?;
-            offset += 7;
-            Some(ts)
-        } else {
-            None
-        };
-
-        points.push(DataPoint {
-            ioa,
-            value: DataValue::Single(value),
-            quality,
-            timestamp,
-        });
-    }
-
-    Ok(points)
+/// A command information object decoded by [`parse_command`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct CommandObject {
+    /// Information object address
+    pub ioa: u32,
+    /// Decoded command, e.g. [`InfoObject::SingleCommand`]
+    pub command: InfoObject,
 }
 
-/// Parse single-point with CP24Time2a (M_SP_TA_1).
-fn parse_single_point_time24(data: &[u8], count: usize, sequence: bool) -> Result<Vec<DataPoint>> {
-    let mut points = Vec::with_capacity(count);
-
-    // Element size: SIQ (1) + CP24Time2a (3)
-    let element_size = 4;
-
-    let required_len = if sequence {
-        3 + count * element_size
-    } else {
-        count * (3 + element_size)
-    };
-    if data.len() < required_len {
-        return Err(Iec104Error::invalid_asdu_static("Data too short"));
-    }
-    let first_ioa = read_ioa_le(data);
-    let mut offset = 3;
-
-    for i in 0..count {
-        let ioa = if sequence {
-            first_ioa + i as u32
-        } else if i > 0 {
-            let ioa = read_ioa_le(&data[offset..]);
-            offset += 3;
-            ioa
-        } else {
-            first_ioa
-        };
-
-        let siq = data[offset];
-        let value = (siq & 0x01) != 0;
-        let quality = Quality::from_siq(siq);
-        offset += 4; // Skip SIQ + CP24Time2a (we don't parse short timestamp)
-
-        points.push(DataPoint {
-            ioa,
-            value: DataValue::Single(value),
-            quality,
-            timestamp: None,
-        });
-    }
-
-    Ok(points)
-}
-
-/// Parse double-point information (M_DP_NA_1, M_DP_TB_1).
-fn parse_double_point(
-    data: &[u8],
-    count: usize,
-    sequence: bool,
-    with_time: bool,
-) -> Result<Vec<DataPoint>> {
-    let mut points = Vec::with_capacity(count);
-
-    let element_size = if with_time { 1 + 7 } else { 1 };
-
-    let required_len = if sequence {
-        3 + count * element_size
-    } else {
-        count * (3 + element_size)
-    };
-    if data.len() < required_len {
-        return Err(Iec104Error::invalid_asdu_static("Data too short"));
-    }
-    let first_ioa = read_ioa_le(data);
-    let mut offset = 3;
-
-    for i in 0..count {
-        let ioa = if sequence {
-            first_ioa + i as u32
-        } else if i > 0 {
-            let ioa = read_ioa_le(&data[offset..]);
-            offset += 3;
-            ioa
-        } else {
-            first_ioa
-        };
-
-        // Parse DIQ (Double-point Information with Quality)
-        let diq = data[offset];
-        let dp_value = match diq & 0x03 {
-            0 => DoublePointValue::Indeterminate,
-            1 => DoublePointValue::Off,
-            2 => DoublePointValue::On,
-            3 => DoublePointValue::IndeterminateOrFaulty,
-            _ => unreachable!(),
-        };
-        let quality = Quality::from_diq(diq);
-        offset += 1;
-
-        let timestamp = if with_time {
-            let ts = Cp56Time2a::from_bytes(&data[offset..offset + 7])?;
-            offset += 7;
-            Some(ts)
-        } else {
-            None
-        };
-
-        points.push(DataPoint {
-            ioa,
-            value: DataValue::Double(dp_value),
-            quality,
-            timestamp,
-        });
+/// Parse a control direction ASDU into its commands.
+///
+/// The counterpart of [`parse_asdu`] for process commands (C_SC, C_DC, C_RC,
+/// C_SE, C_BO), system commands (C_IC, C_CI, C_RD, C_CS, C_TS, C_RP) and
+/// parameters (P_ME, P_AC), as received by a controlled station or seen by a
+/// protocol analyzer. Fails for monitoring and file transfer types.
+///
+/// # Example
+///
+/// ```rust,ignore
+/// for object in parse_command(&asdu)? {
+///     if let InfoObject::SingleCommand { command, .. } = object.command {
+///         println!("IOA {}: {}", object.ioa, if command.value { "ON" } else { "OFF" });
+///     }
+/// }
+/// ```
+pub fn parse_command(asdu: &Asdu) -> Result<Vec<CommandObject>> {
+    let type_id = asdu.header.type_id;
+    if !type_id.is_control() {
+        return Err(Iec104Error::InvalidAsdu(
+            format!("{} is not a command", type_id).into(),
+        ));
     }
 
-    Ok(points)
+    Ok(InfoObject::parse_asdu(asdu)?
+        .into_iter()
+        .map(|(ioa, command)| CommandObject { ioa, command })
+        .collect())
 }
 
-/// Parse double-point with CP24Time2a (M_DP_TA_1).
-fn parse_double_point_time24(data: &[u8], count: usize, sequence: bool) -> Result<Vec<DataPoint>> {
-    let mut points = Vec::with_capacity(count);
-
-    let element_size = 4; // DIQ (1) + CP24Time2a (3)
-
-    let required_len = if sequence {
-        3 + count * element_size
-    } else {
-        count * (3 + element_size)
-    };
-    if data.len() < required_len {
-        return Err(Iec104Error::invalid_asdu_static("Data too short"));
-    }
-    let first_ioa = read_ioa_le(data);
-    let mut offset = 3;
-
-    for i in 0..count {
-        let ioa = if sequence {
-            first_ioa + i as u32
-        } else if i > 0 {
-            let ioa = read_ioa_le(&data[offset..]);
-            offset += 3;
-            ioa
-        } else {
-            first_ioa
-        };
-
-        let diq = data[offset];
-        let dp_value = match diq & 0x03 {
-            0 => DoublePointValue::Indeterminate,
-            1 => DoublePointValue::Off,
-            2 => DoublePointValue::On,
-            3 => DoublePointValue::IndeterminateOrFaulty,
-            _ => unreachable!(),
-        };
-        let quality = Quality::from_diq(diq);
-        offset += 4; // Skip DIQ + CP24Time2a
-
-        points.push(DataPoint {
-            ioa,
-            value: DataValue::Double(dp_value),
-            quality,
-            timestamp: None,
-        });
+/// Parse an ASDU lazily, yielding one data point per information object.
+///
+/// Produces the same points as [`parse_asdu`] without collecting them, for
+/// gateways that forward points one by one. Nothing is allocated; an error
+/// is yielded at the first malformed object, after which the iterator ends.
+///
+/// # Example
+///
+/// ```rust,ignore
+/// for point in parse_asdu_iter(&asdu) {
+///     forward(point?);
+/// }
+/// ```
+pub fn parse_asdu_iter(asdu: &Asdu) -> DataPointIter<'_> {
+    let type_id = asdu.header.type_id;
+    DataPointIter {
+        data: asdu.raw_data.as_ref(),
+        type_id,
+        element_size: point_element_size(type_id),
+        sequence: asdu.header.vsq.sequence,
+        remaining: asdu.header.vsq.count as usize,
+        offset: 0,
+        next_ioa: 0,
     }
-
-    Ok(points)
 }
 
-/// Parse step position information (M_ST_NA_1).
-fn parse_step_position(
-    data: &[u8],
-    count: usize,
+/// Iterator over the data points of an ASDU, created by [`parse_asdu_iter`].
+#[derive(Debug, Clone)]
+pub struct DataPointIter<'a> {
+    data: &'a [u8],
+    type_id: TypeId,
+    element_size: Option<usize>,
     sequence: bool,
-    _with_time: bool,
-) -> Result<Vec<DataPoint>> {
-    let mut points = Vec::with_capacity(count);
-
-    let element_size = 2; // VTI (1) + QDS (1)
-
-    let required_len = if sequence {
-        3 + count * element_size
-    } else {
-        count * (3 + element_size)
-    };
-    if data.len() < required_len {
-        return Err(Iec104Error::invalid_asdu_static("Data too short"));
-    }
-    let first_ioa = read_ioa_le(data);
-    let mut offset = 3;
-
-    for i in 0..count {
-        let ioa = if sequence {
-            first_ioa + i as u32
-        } else if i > 0 {
-            let ioa = read_ioa_le(&data[offset..]);
-            offset += 3;
-            ioa
-        } else {
-            first_ioa
-        };
-
-        // VTI: Value with Transient Indicator
-        let vti = data[offset];
-        // Value is in bits 0-6, bit 7 is transient indicator
-        let value = ((vti & 0x7F) as i8) - 64; // Convert to -64..+63
-        offset += 1;
-
-        // QDS: Quality Descriptor
-        let qds = data[offset];
-        let quality = Quality::from_qds(qds);
-        offset += 1;
-
-        points.push(DataPoint {
-            ioa,
-            value: DataValue::StepPosition(value),
-            quality,
-            timestamp: None,
-        });
-    }
-
-    Ok(points)
+    remaining: usize,
+    offset: usize,
+    next_ioa: u32,
 }
 
-/// Parse bitstring of 32 bits (M_BO_NA_1).
-fn parse_bitstring(
-    data: &[u8],
-    count: usize,
-    sequence: bool,
-    _with_time: bool,
-) -> Result<Vec<DataPoint>> {
-    let mut points = Vec::with_capacity(count);
-
-    let element_size = 5; // BSI (4) + QDS (1)
+impl Iterator for DataPointIter<'_> {
+    type Item = Result<DataPoint>;
 
-    let required_len = if sequence {
-        3 + count * element_size
-    } else {
-        count * (3 + element_size)
-    };
-    if data.len() < required_len {
-        return Err(Iec104Error::invalid_asdu_static("Data too short"));
-    }
-    let first_ioa = read_ioa_le(data);
-    let mut offset = 3;
-
-    for i in 0..count {
-        let ioa = if sequence {
-            first_ioa + i as u32
-        } else if i > 0 {
-            let ioa = read_ioa_le(&data[offset..]);
-            offset += 3;
-            ioa
-        } else {
-            first_ioa
-        };
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.remaining == 0 {
+            return None;
+        }
+        // Types without data points yield nothing
+        let element_size = self.element_size?;
 
-        // BSI: Bitstring of 32 bit
-        let value = u32::from_le_bytes([
-            data[offset],
-            data[offset + 1],
-            data[offset + 2],
-            data[offset + 3],
-        ]);
-        offset += 4;
-
-        let qds = data[offset];
-        let quality = Quality::from_qds(qds);
-        offset += 1;
-
-        points.push(DataPoint {
-            ioa,
-            value: DataValue::Bitstring(value),
-            quality,
-            timestamp: None,
-        });
-    }
+        if self.data.is_empty() {
+            self.remaining = 0;
+            return Some(Err(Iec104Error::invalid_asdu_static("Empty data for non-zero count")));
+        }
 
-    Ok(points)
-}
+        let with_ioa = !self.sequence || self.offset == 0;
+        let needed = element_size + if with_ioa { 3 } else { 0 };
+        if self.data.len() < self.offset + needed {
+            self.remaining = 0;
+            return Some(Err(Iec104Error::invalid_asdu_static("Data too short")));
+        }
 
-/// Parse measured value, normalized (M_ME_NA_1).
-fn parse_measured_normalized(
-    data: &[u8],
-    count: usize,
-    sequence: bool,
-    _with_time: bool,
-) -> Result<Vec<DataPoint>> {
-    let mut points = Vec::with_capacity(count);
+        if with_ioa {
+            self.next_ioa = read_ioa_le(&self.data[self.offset..]);
+            self.offset += 3;
+        }
+        let ioa = self.next_ioa;
+        self.next_ioa = self.next_ioa.wrapping_add(1);
 
-    let element_size = 3; // NVA (2) + QDS (1)
+        let element = &self.data[self.offset..self.offset + element_size];
+        self.offset += element_size;
+        self.remaining -= 1;
 
-    let required_len = if sequence {
-        3 + count * element_size
-    } else {
-        count * (3 + element_size)
-    };
-    if data.len() < required_len {
-        return Err(Iec104Error::invalid_asdu_static("Data too short"));
+        let point = decode_point(self.type_id, ioa, element);
+        if point.is_err() {
+            self.remaining = 0;
+        }
+        Some(point)
     }
-    let first_ioa = read_ioa_le(data);
-    let mut offset = 3;
-
-    for i in 0..count {
-        let ioa = if sequence {
-            first_ioa + i as u32
-        } else if i > 0 {
-            let ioa = read_ioa_le(&data[offset..]);
-            offset += 3;
-            ioa
-        } else {
-            first_ioa
-        };
-
-        // NVA: Normalized Value (16-bit signed, -1.0 to ~+1.0)
-        let raw = i16::from_le_bytes([data[offset], data[offset + 1]]);
-        let value = raw as f32 / 32768.0;
-        offset += 2;
 
-        let qds = data[offset];
-        let quality = Quality::from_qds(qds);
-        offset += 1;
-
-        points.push(DataPoint {
-            ioa,
-            value: DataValue::Normalized(value),
-            quality,
-            timestamp: None,
-        });
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        match self.element_size {
+            Some(_) => (0, Some(self.remaining)),
+            None => (0, Some(0)),
+        }
     }
-
-    Ok(points)
 }
 
-/// Parse measured value, scaled (M_ME_NB_1).
-fn parse_measured_scaled(
-    data: &[u8],
-    count: usize,
-    sequence: bool,
-    _with_time: bool,
-) -> Result<Vec<DataPoint>> {
-    let mut points = Vec::with_capacity(count);
-
-    let element_size = 3; // SVA (2) + QDS (1)
-
-    let required_len = if sequence {
-        3 + count * element_size
-    } else {
-        count * (3 + element_size)
-    };
-    if data.len() < required_len {
-        return Err(Iec104Error::invalid_asdu_static("Data too short"));
-    }
-    let first_ioa = read_ioa_le(data);
-    let mut offset = 3;
-
-    for i in 0..count {
-        let ioa = if sequence {
-            first_ioa + i as u32
-        } else if i > 0 {
-            let ioa = read_ioa_le(&data[offset..]);
-            offset += 3;
-            ioa
-        } else {
-            first_ioa
-        };
-
-        // SVA: Scaled Value
-        let value = i16::from_le_bytes([data[offset], data[offset + 1]]);
-        offset += 2;
-
-        let qds = data[offset];
-        let quality = Quality::from_qds(qds);
-        offset += 1;
-
-        points.push(DataPoint {
-            ioa,
-            value: DataValue::Scaled(value),
-            quality,
-            timestamp: None,
-        });
+/// Size of an element as decoded into a data point, or `None` for types that
+/// carry no data points.
+fn point_element_size(type_id: TypeId) -> Option<usize> {
+    match type_id {
+        TypeId::SinglePoint
+        | TypeId::SinglePointTime24
+        | TypeId::SinglePointTime56
+        | TypeId::DoublePoint
+        | TypeId::DoublePointTime24
+        | TypeId::DoublePointTime56
+        | TypeId::StepPosition
+        | TypeId::StepPositionTime56
+        | TypeId::Bitstring32
+        | TypeId::Bitstring32Time56
+        | TypeId::MeasuredNormalized
+        | TypeId::MeasuredNormalizedTime24
+        | TypeId::MeasuredNormalizedTime56
+        | TypeId::MeasuredNormalizedNoQuality
+        | TypeId::MeasuredScaled
+        | TypeId::MeasuredScaledTime24
+        | TypeId::MeasuredScaledTime56
+        | TypeId::MeasuredFloat
+        | TypeId::MeasuredFloatTime24
+        | TypeId::MeasuredFloatTime56
+        | TypeId::IntegratedTotals
+        | TypeId::IntegratedTotalsTime56
+        | TypeId::PackedSinglePoint
+        | TypeId::ParameterNormalized
+        | TypeId::ParameterScaled
+        | TypeId::ParameterFloat => Some(type_id.element_size()),
+
+        _ => None,
     }
-
-    Ok(points)
 }
 
-/// Parse measured value, short floating point (M_ME_NC_1, M_ME_TF_1).
-fn parse_measured_float(
-    data: &[u8],
-    count: usize,
-    sequence: bool,
-    with_time: bool,
-) -> Result<Vec<DataPoint>> {
-    let mut points = Vec::with_capacity(count);
-
-    let element_size = if with_time { 5 + 7 } else { 5 }; // IEEE float (4) + QDS (1) + optional CP56Time2a
-
-    let required_len = if sequence {
-        3 + count * element_size
-    } else {
-        count * (3 + element_size)
+/// Decode one element (without its IOA) into a data point.
+///
+/// The element must be `point_element_size(type_id)` bytes long; a trailing
+/// 7-byte or 3-byte remainder is read as a CP56Time2a or CP24Time2a tag.
+fn decode_point(type_id: TypeId, ioa: u32, element: &[u8]) -> Result<DataPoint> {
+    let (value, quality, used) = match type_id {
+        TypeId::SinglePoint | TypeId::SinglePointTime24 | TypeId::SinglePointTime56 => {
+            let siq = element[0];
+            (DataValue::Single(siq & 0x01 != 0), Quality::from_siq(siq), 1)
+        }
+        TypeId::DoublePoint | TypeId::DoublePointTime24 | TypeId::DoublePointTime56 => {
+            let diq = element[0];
+            let value = match diq & 0x03 {
+                0 => DoublePointValue::Indeterminate,
+                1 => DoublePointValue::Off,
+                2 => DoublePointValue::On,
+                _ => DoublePointValue::IndeterminateOrFaulty,
+            };
+            (DataValue::Double(value), Quality::from_diq(diq), 1)
+        }
+        TypeId::StepPosition | TypeId::StepPositionTime56 => {
+            let (value, transient) = vti_from_u8(element[0]);
+            let value = DataValue::StepPosition { value, transient };
+            (value, Quality::from_qds(element[1]), 2)
+        }
+        TypeId::Bitstring32 | TypeId::Bitstring32Time56 => {
+            let value = u32::from_le_bytes([element[0], element[1], element[2], element[3]]);
+            (DataValue::Bitstring(value), Quality::from_qds(element[4]), 5)
+        }
+        TypeId::MeasuredNormalized
+        | TypeId::MeasuredNormalizedTime24
+        | TypeId::MeasuredNormalizedTime56 => {
+            let value = i16::from_le_bytes([element[0], element[1]]) as f32 / 32768.0;
+            (DataValue::Normalized(value), Quality::from_qds(element[2]), 3)
+        }
+        TypeId::MeasuredNormalizedNoQuality => {
+            let value = i16::from_le_bytes([element[0], element[1]]) as f32 / 32768.0;
+            (DataValue::Normalized(value), Quality::Good, 2)
+        }
+        TypeId::MeasuredScaled | TypeId::MeasuredScaledTime24 | TypeId::MeasuredScaledTime56 => {
+            let value = i16::from_le_bytes([element[0], element[1]]);
+            (DataValue::Scaled(value), Quality::from_qds(element[2]), 3)
+        }
+        TypeId::MeasuredFloat | TypeId::MeasuredFloatTime24 | TypeId::MeasuredFloatTime56 => {
+            let value = f32::from_le_bytes([element[0], element[1], element[2], element[3]]);
+            (DataValue::Float(value), Quality::from_qds(element[4]), 5)
+        }
+        TypeId::IntegratedTotals | TypeId::IntegratedTotalsTime56 => {
+            let value = i32::from_le_bytes([element[0], element[1], element[2], element[3]]);
+            let flags = element[4];
+            let invalid = flags & 0x80 != 0;
+            let counter = DataValue::BinaryCounter {
+                value,
+                sequence: flags & 0x1F,
+                carry: flags & 0x20 != 0,
+                adjusted: flags & 0x40 != 0,
+                invalid,
+            };
+            (counter, Quality::with_invalid(invalid), 5)
+        }
+        TypeId::PackedSinglePoint => {
+            let status = u16::from_le_bytes([element[0], element[1]]);
+            let changes = u16::from_le_bytes([element[2], element[3]]);
+            let value = DataValue::PackedSinglePoint { status, changes };
+            (value, Quality::from_qds(element[4]), 5)
+        }
+        TypeId::ParameterNormalized | TypeId::ParameterScaled | TypeId::ParameterFloat => {
+            let used = element.len();
+            let value = match type_id {
+                TypeId::ParameterNormalized => ParameterValue::Normalized(
+                    i16::from_le_bytes([element[0], element[1]]) as f32 / 32768.0,
+                ),
+                TypeId::ParameterScaled => {
+                    ParameterValue::Scaled(i16::from_le_bytes([element[0], element[1]]))
+                }
+                _ => ParameterValue::Float(f32::from_le_bytes([
+                    element[0], element[1], element[2], element[3],
+                ])),
+            };
+            let qpm = Qpm::from_u8(element[used - 1]);
+            (DataValue::Parameter { value, qpm }, Quality::Good, used)
+        }
+        _ => {
+            return Err(Iec104Error::InvalidAsdu(
+                format!("{} carries no data points", type_id.standard_name()).into(),
+            ))
+        }
     };
-    if data.len() < required_len {
-        return Err(Iec104Error::invalid_asdu_static("Data too short"));
-    }
-    let first_ioa = read_ioa_le(data);
-    let mut offset = 3;
-
-    for i in 0..count {
-        let ioa = if sequence {
-            first_ioa + i as u32
-        } else if i > 0 {
-            let ioa = read_ioa_le(&data[offset..]);
-            offset += 3;
-            ioa
-        } else {
-            first_ioa
-        };
-
-        // IEEE 754 short floating point
-        let value = f32::from_le_bytes([
-            data[offset],
-            data[offset + 1],
-            data[offset + 2],
-            data[offset + 3],
-        ]);
-        offset += 4;
-
-        let qds = data[offset];
-        let quality = Quality::from_qds(qds);
-        offset += 1;
-
-        let timestamp = if with_time {
-            let ts = Cp56Time2a::from_bytes(&data[offset..offset + 7])?;
-            offset += 7;
-            Some(ts)
-        } else {
-            None
-        };
 
-        points.push(DataPoint {
-            ioa,
-            value: DataValue::Float(value),
-            quality,
-            timestamp,
-        });
-    }
-
-    Ok(points)
-}
-
-/// Parse integrated totals (M_IT_NA_1).
-fn parse_integrated_totals(
-    data: &[u8],
-    count: usize,
-    sequence: bool,
-    _with_time: bool,
-) -> Result<Vec<DataPoint>> {
-    let mut points = Vec::with_capacity(count);
-
-    let element_size = 5; // BCR (4) + sequence/flags (1)
-
-    let required_len = if sequence {
-        3 + count * element_size
-    } else {
-        count * (3 + element_size)
+    let timestamp = match &element[used..] {
+        [] => None,
+        tag @ [_, _, _] => Some(Timestamp::Partial(Cp24Time2a::from_bytes(tag)?)),
+        tag => Some(Timestamp::Full(Cp56Time2a::from_bytes(tag)?)),
     };
-    if data.len() < required_len {
-        return Err(Iec104Error::invalid_asdu_static("Data too short"));
-    }
-    let first_ioa = read_ioa_le(data);
-    let mut offset = 3;
-
-    for i in 0..count {
-        let ioa = if sequence {
-            first_ioa + i as u32
-        } else if i > 0 {
-            let ioa = read_ioa_le(&data[offset..]);
-            offset += 3;
-            ioa
-        } else {
-            first_ioa
-        };
 
-        // BCR: Binary Counter Reading
-        let value = i32::from_le_bytes([
-            data[offset],
-            data[offset + 1],
-            data[offset + 2],
-            data[offset + 3],
-        ]);
-        offset += 4;
-
-        // Sequence number and flags
-        let flags = data[offset];
-        let seq_number = flags & 0x1F;
-        let carry = (flags & 0x20) != 0;
-        let adjusted = (flags & 0x40) != 0;
-        let invalid = (flags & 0x80) != 0;
-        offset += 1;
-
-        let quality = Quality::with_invalid(invalid);
-
-        points.push(DataPoint {
-            ioa,
-            value: DataValue::BinaryCounter {
-                value,
-                sequence: seq_number,
-                carry,
-                adjusted,
-                invalid,
-            },
-            quality,
-            timestamp: None,
-        });
-    }
-
-    Ok(points)
+    Ok(DataPoint {
+        ioa,
+        value,
+        quality,
+        timestamp,
+    })
 }
 
 /// Read IOA as little-endian u24 (assumes bytes.len() >= 3).
@@ -697,6 +322,135 @@ mod tests {
         }
     }
 
+    /// Collect `parse_asdu_iter` and check it against `parse_asdu`.
+    fn assert_iter_matches(asdu: &Asdu) {
+        let lazy: Result<Vec<DataPoint>> = parse_asdu_iter(asdu).collect();
+        match (parse_asdu(asdu), lazy) {
+            (Ok(expected), Ok(lazy)) => assert_eq!(expected, lazy),
+            (Err(_), Err(_)) => {}
+            (expected, lazy) => panic!("parse_asdu {:?}, parse_asdu_iter {:?}", expected, lazy),
+        }
+    }
+
+    #[test]
+    fn test_parse_asdu_iter_matches() {
+        let ts = [0x10, 0x27, 0x1E, 0x0A, 0x0F, 0x06, 0x18];
+        let mut float_time = vec![0x01, 0x00, 0x00, 0x00, 0x00, 0x48, 0x42, 0x00];
+        float_time.extend_from_slice(&ts);
+
+        let cases: Vec<(TypeId, u8, bool, Vec<u8>)> = vec![
+            (TypeId::SinglePoint, 3, true, vec![0x64, 0x00, 0x00, 0x00, 0x01, 0x80]),
+            (TypeId::SinglePointTime24, 1, false, vec![0x01, 0x00, 0x00, 0x01, 0x10, 0x27, 0x1E]),
+            (TypeId::DoublePoint, 2, false, vec![0x01, 0x00, 0x00, 0x02, 0x02, 0x00, 0x00, 0x41]),
+            (TypeId::StepPosition, 1, false, vec![0x01, 0x00, 0x00, 0xC5, 0x00]),
+            (
+                TypeId::MeasuredScaled,
+                2,
+                true,
+                vec![0x0A, 0x00, 0x00, 0xE8, 0x03, 0x00, 0x18, 0xFC, 0x10],
+            ),
+            (TypeId::MeasuredFloatTime56, 1, false, float_time),
+            (TypeId::MeasuredNormalizedNoQuality, 1, false, vec![0x01, 0x00, 0x00, 0x00, 0x40]),
+            (
+                TypeId::IntegratedTotals,
+                1,
+                false,
+                vec![0x01, 0x00, 0x00, 0x39, 0x30, 0x00, 0x00, 0xA5],
+            ),
+            (
+                TypeId::PackedSinglePoint,
+                1,
+                false,
+                vec![0x01, 0x00, 0x00, 0x0F, 0x00, 0x01, 0x00, 0x00],
+            ),
+            (TypeId::ParameterScaled, 1, false, vec![0x01, 0x00, 0x00, 0x64, 0x00, 0x01]),
+            // Truncated second object
+            (
+                TypeId::MeasuredFloat,
+                2,
+                false,
+                vec![0x01, 0x00, 0x00, 0x00, 0x00, 0x48, 0x42, 0x00, 0x02],
+            ),
+            // No data for a non-zero count
+            (TypeId::SinglePoint, 1, false, vec![]),
+        ];
+        for (type_id, count, sequence, data) in cases {
+            assert_iter_matches(&make_asdu(type_id, count, sequence, &data));
+        }
+    }
+
+    #[test]
+    fn test_parse_command() {
+        use crate::command::Command;
+        use crate::types::{CommandQualifier, Qcc, Qos, SingleCommandValue};
+
+        let asdu = Command::Single { ioa: 10, value: true }.to_asdu(
+            1,
+            crate::types::Cot::Activation,
+            CommandQualifier::SELECT,
+        );
+        let received = Asdu::parse(&asdu.encode()).unwrap();
+        assert_eq!(
+            parse_command(&received).unwrap(),
+            vec![CommandObject {
+                ioa: 10,
+                command: InfoObject::SingleCommand {
+                    command: SingleCommandValue::new(true, CommandQualifier::SELECT),
+                    time: None,
+                },
+            }]
+        );
+
+        let asdu = Command::SetpointFloat {
+            ioa: 20,
+            value: 1.5,
+            qos: Qos::new(5, false),
+        }
+        .to_asdu(1, crate::types::Cot::Activation, CommandQualifier::SELECT);
+        assert_eq!(
+            parse_command(&asdu).unwrap()[0].command,
+            InfoObject::SetpointFloat {
+                value: 1.5,
+                qos: Qos::new(5, true),
+                time: None,
+            }
+        );
+
+        let asdu = Asdu::counter_interrogation_command(1, Qcc::general_read());
+        let objects = parse_command(&asdu).unwrap();
+        assert_eq!(objects[0].ioa, 0);
+        assert_eq!(
+            objects[0].command,
+            InfoObject::CounterInterrogation {
+                qcc: Qcc::general_read()
+            }
+        );
+
+        // Monitoring types are not commands
+        let data = [0x01, 0x00, 0x00, 0x01];
+        assert!(parse_command(&make_asdu(TypeId::SinglePoint, 1, false, &data)).is_err());
+        // Neither are invalid double command states
+        let data = [0x01, 0x00, 0x00, 0x03];
+        assert!(parse_command(&make_asdu(TypeId::DoubleCommand, 1, false, &data)).is_err());
+    }
+
+    #[test]
+    fn test_parse_asdu_iter_lazy() {
+        // Two good objects, then a truncated one
+        let data = [0x01, 0x00, 0x00, 0x01, 0x02, 0x00, 0x00, 0x00, 0x03, 0x00];
+        let asdu = make_asdu(TypeId::SinglePoint, 3, false, &data);
+
+        let mut points = parse_asdu_iter(&asdu);
+        assert_eq!(points.next().unwrap().unwrap().ioa, 1);
+        assert_eq!(points.next().unwrap().unwrap().value, DataValue::Single(false));
+        assert!(points.next().unwrap().is_err());
+        assert!(points.next().is_none());
+
+        // Commands carry no data points
+        let command = make_asdu(TypeId::SingleCommand, 1, false, &[0x01, 0x00, 0x00, 0x81]);
+        assert_eq!(parse_asdu_iter(&command).count(), 0);
+    }
+
     #[test]
     fn test_parse_single_point() {
         // IOA=1001 (0xE9 0x03 0x00), SIQ=0x01 (ON, good quality)
@@ -920,11 +674,11 @@ mod tests {
 
     #[test]
     fn test_parse_single_point_time24() {
-        // IOA=700, SIQ=0x01 (ON), CP24Time2a (3 bytes - we skip it)
+        // IOA=700, SIQ=0x01 (ON), CP24Time2a 12:34.679
         let data = [
             0xBC, 0x02, 0x00, // IOA=700
             0x01, // SIQ: ON
-            0x00, 0x00, 0x00, // CP24Time2a (ignored)
+            0x77, 0x87, 0x0C, // CP24Time2a
         ];
         let asdu = make_asdu(TypeId::SinglePointTime24, 1, false, &data);
         let points = parse_asdu(&asdu).unwrap();
@@ -932,7 +686,48 @@ mod tests {
         assert_eq!(points.len(), 1);
         assert_eq!(points[0].ioa, 700);
         assert_eq!(points[0].value, DataValue::Single(true));
-        assert!(points[0].timestamp.is_none()); // CP24Time2a not parsed
+        let expected = Cp24Time2a {
+            milliseconds: 34_679,
+            minutes: 12,
+            invalid: false,
+        };
+        assert_eq!(points[0].timestamp, Some(Timestamp::Partial(expected)));
+    }
+
+    #[test]
+    fn test_parse_measured_time24() {
+        // Two M_ME_TC_1 objects: float 50.0 and 25.0 with CP24Time2a 12:34.679
+        let data = [
+            0x01, 0x00, 0x00, // IOA=1
+            0x00, 0x00, 0x48, 0x42, 0x00, // 50.0, QDS
+            0x77, 0x87, 0x0C, // CP24Time2a
+            0x02, 0x00, 0x00, // IOA=2
+            0x00, 0x00, 0xC8, 0x41, 0x80, // 25.0, QDS: invalid
+            0x77, 0x87, 0x0C, // CP24Time2a
+        ];
+        let asdu = make_asdu(TypeId::MeasuredFloatTime24, 2, false, &data);
+        let points = parse_asdu(&asdu).unwrap();
+        assert_iter_matches(&asdu);
+
+        let expected = Timestamp::Partial(Cp24Time2a {
+            milliseconds: 34_679,
+            minutes: 12,
+            invalid: false,
+        });
+        assert_eq!(points.len(), 2);
+        assert_eq!(points[1].ioa, 2);
+        assert_eq!(points[1].value, DataValue::Float(25.0));
+        assert!(points[1].quality.invalid());
+        assert_eq!(points[1].timestamp, Some(expected));
+
+        // Normalized and scaled: 2 + 1 + 3 bytes per element
+        let data = [0x01, 0x00, 0x00, 0x00, 0x40, 0x00, 0x77, 0x87, 0x0C];
+        for type_id in [TypeId::MeasuredNormalizedTime24, TypeId::MeasuredScaledTime24] {
+            let asdu = make_asdu(type_id, 1, false, &data);
+            let points = parse_asdu(&asdu).unwrap();
+            assert_eq!(points[0].timestamp, Some(expected));
+            assert_iter_matches(&asdu);
+        }
     }
 
     #[test]
@@ -941,7 +736,7 @@ mod tests {
         let data = [
             0x20, 0x03, 0x00, // IOA=800
             0x01, // DIQ: OFF
-            0x00, 0x00, 0x00, // CP24Time2a (ignored)
+            0x00, 0x00, 0x85, // CP24Time2a, invalid
         ];
         let asdu = make_asdu(TypeId::DoublePointTime24, 1, false, &data);
         let points = parse_asdu(&asdu).unwrap();
@@ -949,14 +744,17 @@ mod tests {
         assert_eq!(points.len(), 1);
         assert_eq!(points[0].ioa, 800);
         assert_eq!(points[0].value, DataValue::Double(DoublePointValue::Off));
+        let ts = points[0].timestamp.unwrap();
+        assert!(ts.full().is_none());
+        assert!(ts.is_invalid());
     }
 
     #[test]
     fn test_parse_step_position() {
-        // IOA=900, VTI=0x60 (value=-4, transient=false), QDS=0x00
+        // IOA=900, VTI=0x7C (value=-4, transient=false), QDS=0x00
         let data = [
             0x84, 0x03, 0x00, // IOA=900
-            0x3C, // VTI: 60 - 64 = -4
+            0x7C, // VTI: 7-bit two's complement -4
             0x00, // QDS
         ];
         let asdu = make_asdu(TypeId::StepPosition, 1, false, &data);
@@ -964,18 +762,44 @@ mod tests {
 
         assert_eq!(points.len(), 1);
         assert_eq!(points[0].ioa, 900);
-        assert_eq!(points[0].value, DataValue::StepPosition(-4));
+        assert_eq!(
+            points[0].value,
+            DataValue::StepPosition {
+                value: -4,
+                transient: false
+            }
+        );
+    }
+
+    #[test]
+    fn test_parse_step_position_transient() {
+        // VTI=0x85: transient, 5
+        let data = [0x01, 0x00, 0x00, 0x85, 0x00];
+        let asdu = make_asdu(TypeId::StepPosition, 1, false, &data);
+        let points = parse_asdu(&asdu).unwrap();
+        assert_eq!(
+            points[0].value,
+            DataValue::StepPosition {
+                value: 5,
+                transient: true
+            }
+        );
     }
 
     #[test]
     fn test_parse_step_position_range() {
-        // Test min/max step position values
-        // VTI=0 means value=-64, VTI=127 means value=+63
-        for (vti, expected) in [(0x00, -64), (0x40, 0), (0x7F, 63)] {
+        // VTI is a 7-bit two's complement value: 0x40 is -64, 0x7F is -1
+        for (vti, expected) in [(0x00, 0), (0x3F, 63), (0x40, -64), (0x7F, -1)] {
             let data = [0x01, 0x00, 0x00, vti, 0x00];
             let asdu = make_asdu(TypeId::StepPosition, 1, false, &data);
             let points = parse_asdu(&asdu).unwrap();
-            assert_eq!(points[0].value, DataValue::StepPosition(expected));
+            assert_eq!(
+                points[0].value,
+                DataValue::StepPosition {
+                    value: expected,
+                    transient: false
+                }
+            );
         }
     }
 
@@ -995,6 +819,49 @@ mod tests {
         assert_eq!(points[0].value, DataValue::Bitstring(0xDEADBEEF));
     }
 
+    #[test]
+    fn test_parse_packed_single_point() {
+        // IOA=1100, ST=0x8001, CD=0x0001, QDS=IV
+        let data = [
+            0x4C, 0x04, 0x00, // IOA=1100
+            0x01, 0x80, // ST (little-endian)
+            0x01, 0x00, // CD (little-endian)
+            0x80, // QDS
+        ];
+        let asdu = make_asdu(TypeId::PackedSinglePoint, 1, false, &data);
+        let points = parse_asdu(&asdu).unwrap();
+
+        assert_eq!(points.len(), 1);
+        assert_eq!(points[0].ioa, 1100);
+        assert_eq!(
+            points[0].value,
+            DataValue::PackedSinglePoint {
+                status: 0x8001,
+                changes: 0x0001
+            }
+        );
+        assert!(points[0].quality.invalid());
+    }
+
+    #[test]
+    fn test_parse_measured_normalized_no_quality_sequence() {
+        // IOA=2000.., NVA=0x4000 (0.5), 0xC000 (-0.5), no QDS
+        let data = [
+            0xD0, 0x07, 0x00, // IOA=2000
+            0x00, 0x40, // NVA
+            0x00, 0xC0, // NVA
+        ];
+        let asdu = make_asdu(TypeId::MeasuredNormalizedNoQuality, 2, true, &data);
+        let points = parse_asdu(&asdu).unwrap();
+
+        assert_eq!(points.len(), 2);
+        assert_eq!(points[0].ioa, 2000);
+        assert_eq!(points[0].value, DataValue::Normalized(0.5));
+        assert_eq!(points[1].ioa, 2001);
+        assert_eq!(points[1].value, DataValue::Normalized(-0.5));
+        assert!(points.iter().all(|p| p.is_good()));
+    }
+
     #[test]
     fn test_parse_measured_normalized_boundary() {
         // Test boundary values: -1.0, 0.0, ~+1.0
@@ -1051,6 +918,100 @@ mod tests {
         assert!(points[0].timestamp.is_some());
     }
 
+    #[test]
+    fn test_parse_measured_scaled_time56() {
+        // IOA=1200, SVA=-300, QDS=0x00, CP56Time2a
+        let mut data = vec![0xB0, 0x04, 0x00]; // IOA=1200
+        data.extend_from_slice(&(-300i16).to_le_bytes());
+        data.push(0x00); // QDS
+        data.extend_from_slice(&[0x30, 0x75, 0x1E, 0x8C, 0x6F, 0x06, 0x18]); // CP56Time2a
+
+        let asdu = make_asdu(TypeId::MeasuredScaledTime56, 1, false, &data);
+        let points = parse_asdu(&asdu).unwrap();
+
+        assert_eq!(points.len(), 1);
+        assert_eq!(points[0].ioa, 1200);
+        assert_eq!(points[0].value, DataValue::Scaled(-300));
+        assert!(points[0].timestamp.is_some());
+    }
+
+    #[test]
+    fn test_parse_step_position_time56_sequence() {
+        // SQ=1, base IOA=10, two VTI+QDS+CP56Time2a elements
+        let mut data = vec![0x0A, 0x00, 0x00];
+        for vti in [0x05u8, 0x7F] {
+            data.push(vti);
+            data.push(0x00); // QDS
+            data.extend_from_slice(&[0x30, 0x75, 0x1E, 0x8C, 0x6F, 0x06, 0x18]);
+        }
+
+        let asdu = make_asdu(TypeId::StepPositionTime56, 2, true, &data);
+        let points = parse_asdu(&asdu).unwrap();
+
+        assert_eq!(points.len(), 2);
+        assert_eq!(points[1].ioa, 11);
+        assert!(points.iter().all(|p| p.timestamp.is_some()));
+    }
+
+    #[test]
+    fn test_parse_integrated_totals_time56() {
+        let mut data = vec![
+            0x01, 0x00, 0x00, // IOA=1
+            0x10, 0x27, 0x00, 0x00, // BCR=10000
+            0x03, // seq=3
+        ];
+        data.extend_from_slice(&[0x30, 0x75, 0x1E, 0x8C, 0x6F, 0x06, 0x18]); // CP56Time2a
+
+        let asdu = make_asdu(TypeId::IntegratedTotalsTime56, 1, false, &data);
+        let points = parse_asdu(&asdu).unwrap();
+
+        assert_eq!(points.len(), 1);
+        assert!(matches!(
+            points[0].value,
+            DataValue::BinaryCounter { value: 10000, sequence: 3, .. }
+        ));
+        assert!(points[0].timestamp.is_some());
+    }
+
+    #[test]
+    fn test_parse_parameter() {
+        use crate::types::ParameterKind;
+
+        let mut data = vec![
+            0x10, 0x00, 0x00, // IOA=16
+            0x2C, 0x01, // SVA=300
+            0x41, // QPM: threshold, local change
+            0x11, 0x00, 0x00, // IOA=17
+            0xF4, 0x01, // SVA=500
+            0x04, // QPM: high limit
+        ];
+        let asdu = make_asdu(TypeId::ParameterScaled, 2, false, &data);
+        let points = parse_asdu(&asdu).unwrap();
+
+        assert_eq!(points.len(), 2);
+        let DataValue::Parameter { value, qpm } = points[0].value else {
+            panic!("Expected Parameter value");
+        };
+        assert_eq!(value, ParameterValue::Scaled(300));
+        assert_eq!(qpm.kind, ParameterKind::Threshold);
+        assert!(qpm.local_change);
+        assert_eq!(points[1].ioa, 17);
+        assert_eq!(points[1].value.as_f64(), Some(500.0));
+
+        data.truncate(3);
+        data.extend_from_slice(&2.5f32.to_le_bytes());
+        data.push(0x02); // QPM: smoothing factor
+        let asdu = make_asdu(TypeId::ParameterFloat, 1, false, &data);
+        let points = parse_asdu(&asdu).unwrap();
+        assert!(matches!(
+            points[0].value,
+            DataValue::Parameter { value: ParameterValue::Float(v), .. } if v == 2.5
+        ));
+
+        let asdu = make_asdu(TypeId::ParameterActivation, 1, false, &[0x10, 0x00, 0x00, 0x02]);
+        assert!(parse_asdu(&asdu).unwrap().is_empty());
+    }
+
     #[test]
     fn test_parse_integrated_totals_with_flags() {
         // Test with carry, adjusted, and invalid flags
@@ -0,0 +1,499 @@
+//! Replay of packet captures, e.g. taken with Wireshark or tcpdump.
+//!
+//! [`CaptureReader`] reads a pcap or pcapng file, reassembles each direction
+//! of every TCP connection on the IEC 104 port and decodes the byte streams
+//! with the same [`Iec104Codec`] the client uses. It yields the APDUs in
+//! capture order, with the time and addresses of the segment that
+//! completed them:
+//!
+//! ```rust,ignore
+//! use voltage_iec104::pcap::CaptureReader;
+//!
+//! for captured in CaptureReader::open("incident.pcapng")? {
+//!     let captured = captured?;
+//!     for point in captured.data_points()? {
+//!         println!("{} {:?}", captured.source, point);
+//!     }
+//! }
+//! ```
+//!
+//! Ethernet, Linux cooked (SLL), BSD loopback and raw IP link types are
+//! understood. Retransmitted and reordered segments are put back in order;
+//! a stream whose start was not captured is picked up from its first
+//! segment, the codec resynchronizing on the next start byte. IP fragments
+//! are skipped.
+
+use std::borrow::Cow;
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::fs::File;
+use std::io::{BufReader, Chain, Cursor, Read};
+use std::net::{IpAddr, SocketAddr};
+use std::path::Path;
+use std::time::Duration;
+
+use bytes::BytesMut;
+use etherparse::{NetSlice, SlicedPacket, TransportSlice};
+use pcap_file::pcap::PcapReader;
+use pcap_file::pcapng::{Block, PcapNgReader};
+use pcap_file::DataLink;
+use tokio_util::codec::Decoder;
+
+use crate::codec::{Apdu, Iec104Codec};
+use crate::error::{Iec104Error, Result};
+use crate::parser::parse_asdu;
+use crate::types::DataPoint;
+
+/// IANA port of IEC 60870-5-104.
+pub const DEFAULT_PORT: u16 = 2404;
+
+/// Block type of a pcapng section header, which starts every pcapng file.
+const PCAPNG_MAGIC: [u8; 4] = [0x0A, 0x0D, 0x0D, 0x0A];
+
+/// Segments held back per direction while waiting for a missing one.
+const MAX_OUT_OF_ORDER: usize = 64;
+
+/// An APDU decoded from a capture.
+#[derive(Debug, Clone)]
+pub struct CapturedApdu {
+    /// Capture time of the segment completing the APDU, since the Unix epoch
+    pub timestamp: Duration,
+    /// Sender
+    pub source: SocketAddr,
+    /// Receiver
+    pub destination: SocketAddr,
+    /// The APDU
+    pub apdu: Apdu,
+}
+
+impl CapturedApdu {
+    /// Data points carried by the APDU, none for S- and U-frames.
+    pub fn data_points(&self) -> Result<Vec<DataPoint>> {
+        match &self.apdu.asdu {
+            Some(asdu) => parse_asdu(asdu),
+            None => Ok(Vec::new()),
+        }
+    }
+}
+
+/// Iterator over the APDUs of a capture file.
+///
+/// Errors reading the file end the iteration; frames that fail to decode
+/// are yielded as errors and reading continues.
+pub struct CaptureReader<R: Read> {
+    source: Source<Chain<Cursor<[u8; 4]>, R>>,
+    port: u16,
+    lenient: bool,
+    streams: HashMap<(SocketAddr, SocketAddr), Stream>,
+    ready: VecDeque<Result<CapturedApdu>>,
+    done: bool,
+}
+
+enum Source<R: Read> {
+    Pcap(PcapReader<R>),
+    PcapNg {
+        reader: PcapNgReader<R>,
+        /// Link type per interface of the current section
+        interfaces: Vec<DataLink>,
+    },
+}
+
+/// One direction of a TCP connection.
+struct Stream {
+    /// Sequence number of the next byte expected
+    next_seq: Option<u32>,
+    buffer: BytesMut,
+    /// Segments received ahead of a gap, by sequence number
+    out_of_order: BTreeMap<u32, Vec<u8>>,
+    codec: Iec104Codec,
+}
+
+/// A TCP segment from the capture.
+struct Segment<'a> {
+    source: SocketAddr,
+    destination: SocketAddr,
+    seq: u32,
+    syn: bool,
+    fin_or_rst: bool,
+    payload: &'a [u8],
+}
+
+impl CaptureReader<BufReader<File>> {
+    /// Open a pcap or pcapng file.
+    pub fn open(path: impl AsRef<Path>) -> Result<Self> {
+        Self::new(BufReader::new(File::open(path)?))
+    }
+}
+
+impl<R: Read> CaptureReader<R> {
+    /// Read a capture in pcap or pcapng format.
+    pub fn new(mut reader: R) -> Result<Self> {
+        let mut magic = [0u8; 4];
+        reader.read_exact(&mut magic)?;
+        let reader = Cursor::new(magic).chain(reader);
+        let source = if magic == PCAPNG_MAGIC {
+            Source::PcapNg {
+                reader: PcapNgReader::new(reader).map_err(capture_error)?,
+                interfaces: Vec::new(),
+            }
+        } else {
+            Source::Pcap(PcapReader::new(reader).map_err(capture_error)?)
+        };
+        Ok(Self {
+            source,
+            port: DEFAULT_PORT,
+            lenient: false,
+            streams: HashMap::new(),
+            ready: VecDeque::new(),
+            done: false,
+        })
+    }
+
+    /// TCP port of the IEC 104 server (default 2404).
+    pub fn port(mut self, port: u16) -> Self {
+        self.port = port;
+        self
+    }
+
+    /// Decode ASDUs of unknown types as [`Iec104Codec::lenient`] does.
+    pub fn lenient(mut self, lenient: bool) -> Self {
+        self.lenient = lenient;
+        self
+    }
+
+    /// Read the next packet; `false` at the end of the file.
+    fn read_packet(&mut self) -> Result<bool> {
+        let (timestamp, link, data) = match &mut self.source {
+            Source::Pcap(reader) => {
+                let link = reader.header().datalink;
+                match reader.next_packet() {
+                    None => return Ok(false),
+                    Some(packet) => {
+                        let packet = packet.map_err(capture_error)?;
+                        (packet.timestamp, link, packet.data.into_owned())
+                    }
+                }
+            }
+            Source::PcapNg { reader, interfaces } => match reader.next_block() {
+                None => return Ok(false),
+                Some(block) => match block.map_err(capture_error)? {
+                    Block::SectionHeader(_) => {
+                        interfaces.clear();
+                        return Ok(true);
+                    }
+                    Block::InterfaceDescription(interface) => {
+                        interfaces.push(interface.linktype);
+                        return Ok(true);
+                    }
+                    Block::EnhancedPacket(packet) => {
+                        let link = interfaces.get(packet.interface_id as usize).copied();
+                        let Some(link) = link else {
+                            return Err(Iec104Error::Codec(Cow::Owned(format!(
+                                "Packet on undescribed interface {}",
+                                packet.interface_id
+                            ))));
+                        };
+                        (packet.timestamp, link, packet.data.into_owned())
+                    }
+                    // No timestamp; the interface must be the first one
+                    Block::SimplePacket(packet) => match interfaces.first() {
+                        Some(&link) => (Duration::ZERO, link, packet.data.into_owned()),
+                        None => return Ok(true),
+                    },
+                    _ => return Ok(true),
+                },
+            },
+        };
+
+        if let Some(segment) = tcp_segment(link, &data) {
+            if segment.source.port() == self.port || segment.destination.port() == self.port {
+                self.push_segment(timestamp, segment);
+            }
+        }
+        Ok(true)
+    }
+
+    fn push_segment(&mut self, timestamp: Duration, segment: Segment<'_>) {
+        let key = (segment.source, segment.destination);
+        let lenient = self.lenient;
+        let stream = self.streams.entry(key).or_insert_with(|| Stream {
+            next_seq: None,
+            buffer: BytesMut::new(),
+            out_of_order: BTreeMap::new(),
+            codec: Iec104Codec::new().lenient(lenient),
+        });
+
+        if segment.syn {
+            // A new connection reusing the addresses starts from scratch
+            stream.next_seq = Some(segment.seq.wrapping_add(1));
+            stream.buffer.clear();
+            stream.out_of_order.clear();
+            stream.codec = Iec104Codec::new().lenient(lenient);
+        } else {
+            stream.push(segment.seq, segment.payload);
+        }
+
+        loop {
+            match stream.codec.decode(&mut stream.buffer) {
+                Ok(Some(apdu)) => self.ready.push_back(Ok(CapturedApdu {
+                    timestamp,
+                    source: segment.source,
+                    destination: segment.destination,
+                    apdu,
+                })),
+                Ok(None) => break,
+                Err(e) => self.ready.push_back(Err(e)),
+            }
+        }
+
+        if segment.fin_or_rst {
+            self.streams.remove(&key);
+        }
+    }
+}
+
+impl<R: Read> Iterator for CaptureReader<R> {
+    type Item = Result<CapturedApdu>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(item) = self.ready.pop_front() {
+                return Some(item);
+            }
+            if self.done {
+                return None;
+            }
+            match self.read_packet() {
+                Ok(true) => {}
+                Ok(false) => self.done = true,
+                Err(e) => {
+                    self.done = true;
+                    return Some(Err(e));
+                }
+            }
+        }
+    }
+}
+
+impl Stream {
+    /// Append a segment's payload in sequence order.
+    fn push(&mut self, seq: u32, payload: &[u8]) {
+        if payload.is_empty() {
+            return;
+        }
+        let next = *self.next_seq.get_or_insert(seq);
+        if (seq.wrapping_sub(next) as i32) > 0 {
+            // Ahead of a gap: keep it for later
+            if self.out_of_order.len() < MAX_OUT_OF_ORDER {
+                self.out_of_order
+                    .entry(seq)
+                    .or_insert_with(|| payload.to_vec());
+            }
+            return;
+        }
+        self.append(seq, payload);
+
+        // Segments the gap was holding back
+        while let Some(next) = self.next_seq {
+            let Some(seq) = self
+                .out_of_order
+                .keys()
+                .copied()
+                .find(|&seq| (seq.wrapping_sub(next) as i32) <= 0)
+            else {
+                break;
+            };
+            let payload = self.out_of_order.remove(&seq).unwrap_or_default();
+            self.append(seq, &payload);
+        }
+    }
+
+    /// Append the part of a payload starting at `seq` not received yet.
+    fn append(&mut self, seq: u32, payload: &[u8]) {
+        let next = self.next_seq.unwrap_or(seq);
+        let seen = next.wrapping_sub(seq) as usize;
+        if seen < payload.len() {
+            self.buffer.extend_from_slice(&payload[seen..]);
+            self.next_seq = Some(seq.wrapping_add(payload.len() as u32));
+        }
+    }
+}
+
+/// The TCP segment in a captured packet, if any.
+fn tcp_segment(link: DataLink, data: &[u8]) -> Option<Segment<'_>> {
+    let packet = match link {
+        DataLink::ETHERNET => SlicedPacket::from_ethernet(data),
+        DataLink::LINUX_SLL => SlicedPacket::from_linux_sll(data),
+        DataLink::RAW | DataLink::IPV4 | DataLink::IPV6 => SlicedPacket::from_ip(data),
+        // 4-byte address family in host or network order
+        DataLink::NULL | DataLink::LOOP => SlicedPacket::from_ip(data.get(4..)?),
+        _ => return None,
+    }
+    .ok()?;
+
+    let (source, destination) = match packet.net? {
+        NetSlice::Ipv4(ip) if !ip.is_payload_fragmented() => (
+            IpAddr::V4(ip.header().source_addr()),
+            IpAddr::V4(ip.header().destination_addr()),
+        ),
+        NetSlice::Ipv6(ip) if !ip.is_payload_fragmented() => (
+            IpAddr::V6(ip.header().source_addr()),
+            IpAddr::V6(ip.header().destination_addr()),
+        ),
+        _ => return None,
+    };
+    let TransportSlice::Tcp(tcp) = packet.transport? else {
+        return None;
+    };
+    Some(Segment {
+        source: SocketAddr::new(source, tcp.source_port()),
+        destination: SocketAddr::new(destination, tcp.destination_port()),
+        seq: tcp.sequence_number(),
+        syn: tcp.syn(),
+        fin_or_rst: tcp.fin() || tcp.rst(),
+        payload: tcp.payload(),
+    })
+}
+
+fn capture_error(error: pcap_file::PcapError) -> Iec104Error {
+    Iec104Error::Io(std::io::Error::other(error))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use etherparse::PacketBuilder;
+    use pcap_file::pcap::{PcapPacket, PcapWriter};
+    use pcap_file::pcapng::blocks::enhanced_packet::EnhancedPacketBlock;
+    use pcap_file::pcapng::blocks::interface_description::InterfaceDescriptionBlock;
+    use pcap_file::pcapng::PcapNgWriter;
+
+    use crate::codec::encode_apdu;
+    use crate::encoder::AsduBuilder;
+    use crate::types::{Apci, DataValue, Quality, TypeId, UFunction};
+
+    const CLIENT: ([u8; 4], u16) = ([10, 0, 0, 1], 50_000);
+    const SERVER: ([u8; 4], u16) = ([10, 0, 0, 2], DEFAULT_PORT);
+
+    /// An Ethernet frame carrying a TCP segment.
+    fn segment(
+        from: ([u8; 4], u16),
+        to: ([u8; 4], u16),
+        seq: u32,
+        syn: bool,
+        payload: &[u8],
+    ) -> Vec<u8> {
+        let builder = PacketBuilder::ethernet2([1; 6], [2; 6])
+            .ipv4(from.0, to.0, 64)
+            .tcp(from.1, to.1, seq, 1024);
+        let builder = if syn { builder.syn() } else { builder };
+        let mut frame = Vec::new();
+        builder.write(&mut frame, payload).unwrap();
+        frame
+    }
+
+    fn pcap(packets: &[Vec<u8>]) -> Vec<u8> {
+        let mut writer = PcapWriter::new(Vec::new()).unwrap();
+        for (index, data) in packets.iter().enumerate() {
+            let timestamp = Duration::from_millis(1_000 + index as u64);
+            let packet = PcapPacket::new(timestamp, data.len() as u32, data);
+            writer.write_packet(&packet).unwrap();
+        }
+        writer.into_writer()
+    }
+
+    fn measurement() -> Vec<u8> {
+        let asdu = AsduBuilder::new(TypeId::MeasuredFloat)
+            .ca(1)
+            .add(100, DataValue::Float(1.5), Quality::Good)
+            .build()
+            .unwrap();
+        encode_apdu(&Apdu::i_frame(0, 0, asdu)).unwrap().to_vec()
+    }
+
+    #[test]
+    fn test_reassembles_streams() {
+        let start_dt = encode_apdu(&Apdu::u_frame(UFunction::StartDtAct)).unwrap();
+        let frame = measurement();
+        let (head, tail) = frame.split_at(5);
+        let capture = pcap(&[
+            segment(CLIENT, SERVER, 99, true, &[]),
+            segment(SERVER, CLIENT, 499, true, &[]),
+            segment(CLIENT, SERVER, 100, false, &start_dt),
+            // Server data arrives out of order, then partly retransmitted
+            segment(SERVER, CLIENT, 505, false, tail),
+            segment(SERVER, CLIENT, 500, false, head),
+            segment(SERVER, CLIENT, 500, false, &frame[..8]),
+            // Another protocol between the same hosts
+            segment(
+                ([10, 0, 0, 1], 50_001),
+                ([10, 0, 0, 2], 502),
+                7,
+                false,
+                &start_dt,
+            ),
+        ]);
+
+        let apdus: Vec<CapturedApdu> = CaptureReader::new(capture.as_slice())
+            .unwrap()
+            .collect::<Result<_>>()
+            .unwrap();
+        assert_eq!(apdus.len(), 2);
+
+        assert_eq!(apdus[0].source, "10.0.0.1:50000".parse().unwrap());
+        assert_eq!(apdus[0].destination, "10.0.0.2:2404".parse().unwrap());
+        assert!(matches!(
+            apdus[0].apdu.apci,
+            Apci::UFrame {
+                function: UFunction::StartDtAct
+            }
+        ));
+        assert!(apdus[0].data_points().unwrap().is_empty());
+
+        assert_eq!(apdus[1].source, "10.0.0.2:2404".parse().unwrap());
+        assert_eq!(apdus[1].timestamp, Duration::from_millis(1_004));
+        let points = apdus[1].data_points().unwrap();
+        assert_eq!(points.len(), 1);
+        assert_eq!(points[0].ioa, 100);
+        assert_eq!(points[0].value, DataValue::Float(1.5));
+    }
+
+    #[test]
+    fn test_pcapng_and_decode_errors() {
+        // A frame of an unknown type, then a valid one
+        let mut stream = vec![0x68, 0x0E, 0, 0, 0, 0, 60, 1, 3, 0, 1, 0, 1, 0, 0, 0];
+        stream.extend(measurement());
+
+        let mut writer = PcapNgWriter::new(Vec::new()).unwrap();
+        writer
+            .write_pcapng_block(InterfaceDescriptionBlock::new(DataLink::ETHERNET, 0))
+            .unwrap();
+        let data = segment(SERVER, CLIENT, 1, false, &stream);
+        writer
+            .write_pcapng_block(EnhancedPacketBlock {
+                interface_id: 0,
+                timestamp: Duration::from_secs(5),
+                original_len: data.len() as u32,
+                data: Cow::Owned(data),
+                options: Vec::new(),
+            })
+            .unwrap();
+        let capture = writer.into_inner();
+
+        let results: Vec<_> = CaptureReader::new(capture.as_slice()).unwrap().collect();
+        assert_eq!(results.len(), 2);
+        assert!(matches!(results[0], Err(Iec104Error::UnknownTypeId(60))));
+        assert_eq!(
+            results[1].as_ref().unwrap().timestamp,
+            Duration::from_secs(5)
+        );
+
+        let lenient: Vec<_> = CaptureReader::new(capture.as_slice())
+            .unwrap()
+            .lenient(true)
+            .collect::<Result<_>>()
+            .unwrap();
+        assert_eq!(lenient.len(), 2);
+
+        assert!(CaptureReader::new(&b"not a capture"[..]).is_err());
+    }
+}
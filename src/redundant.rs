@@ -0,0 +1,465 @@
+//! Client with failover between redundant controlled stations.
+//!
+//! Substations are often reachable through two (or more) front ends that
+//! serve the same data. [`RedundantClient`] keeps one of them active and
+//! switches to the next one when the active link dies, optionally keeping
+//! a standby connection open (connected but not started) so the switchover
+//! only costs a STARTDT.
+
+use std::borrow::Cow;
+
+use crate::client::{ClientConfig, ConnectionState, Iec104Client, Iec104Event, LinkInput};
+use crate::error::{Iec104Error, Result};
+
+/// Event produced by [`RedundantClient::poll`].
+#[derive(Debug, Clone)]
+pub enum RedundantEvent {
+    /// Event from the active link
+    Event(Iec104Event),
+    /// The active link changed
+    Switchover {
+        /// Address of the link that died, if one was active
+        from: Option<String>,
+        /// Address of the new active link
+        to: String,
+    },
+    /// The standby link was lost; it is replaced on the next switchover
+    StandbyLost(String),
+}
+
+/// IEC 104 client with an ordered list of redundant servers.
+///
+/// Commands are issued on [`active`](Self::active); [`poll`](Self::poll)
+/// must be called in a loop, as with [`Iec104Client::poll`], and performs
+/// the failover when the active link is lost. When no server is reachable
+/// at that moment, every further `poll` tries them again.
+///
+/// # Example
+///
+/// ```rust,ignore
+/// let config = ClientConfig::new("");
+/// let mut client = RedundantClient::new(config, ["10.0.0.1:2404", "10.0.0.2:2404"])
+///     .warm_standby(true)
+///     .interrogate_after_switchover(1);
+/// client.connect().await?;
+/// while let Some(event) = client.poll().await? {
+///     println!("{event:?}");
+/// }
+/// ```
+pub struct RedundantClient {
+    config: ClientConfig,
+    addresses: Vec<String>,
+    warm_standby: bool,
+    interrogate: Vec<u16>,
+    active: Option<Link>,
+    standby: Option<Link>,
+    /// Index of the lost active link while no server could replace it
+    lost: Option<usize>,
+}
+
+struct Link {
+    index: usize,
+    client: Iec104Client,
+}
+
+impl RedundantClient {
+    /// Create a client for `addresses`, in order of preference.
+    ///
+    /// Every connection uses `config` with its address replaced.
+    pub fn new<I, S>(config: ClientConfig, addresses: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        Self {
+            config,
+            addresses: addresses.into_iter().map(Into::into).collect(),
+            warm_standby: false,
+            interrogate: Vec::new(),
+            active: None,
+            standby: None,
+            lost: None,
+        }
+    }
+
+    /// Keep a connection to the next server open without data transfer.
+    pub fn warm_standby(mut self, enabled: bool) -> Self {
+        self.warm_standby = enabled;
+        self
+    }
+
+    /// Send a general interrogation to `common_address` after every switchover.
+    pub fn interrogate_after_switchover(mut self, common_address: u16) -> Self {
+        self.interrogate.push(common_address);
+        self
+    }
+
+    /// The client of the active link, if any.
+    pub fn active(&mut self) -> Option<&mut Iec104Client> {
+        self.active.as_mut().map(|link| &mut link.client)
+    }
+
+    /// Address of the active link, if any.
+    pub fn active_address(&self) -> Option<&str> {
+        let link = self.active.as_ref()?;
+        Some(&self.addresses[link.index])
+    }
+
+    /// Address of the standby link, if one is connected.
+    pub fn standby_address(&self) -> Option<&str> {
+        let link = self.standby.as_ref()?;
+        Some(&self.addresses[link.index])
+    }
+
+    /// Connect to the first reachable server and start data transfer.
+    pub async fn connect(&mut self) -> Result<()> {
+        if self.active.is_some() {
+            return Err(Iec104Error::Connection(Cow::Borrowed("Already connected")));
+        }
+        self.lost = None;
+        self.activate(0).await?;
+        self.connect_standby().await;
+        Ok(())
+    }
+
+    /// Disconnect every link.
+    pub async fn disconnect(&mut self) -> Result<()> {
+        self.lost = None;
+        if let Some(mut link) = self.standby.take() {
+            link.client.disconnect().await.ok();
+        }
+        match self.active.take() {
+            Some(mut link) => link.client.disconnect().await,
+            None => Ok(()),
+        }
+    }
+
+    /// Process incoming frames on every link, failing over when needed.
+    ///
+    /// Waits for the next frame or timer of either link, so the standby
+    /// answers test frames while the active link is quiet.
+    ///
+    /// Errors that close the active link, such as an I-frame received out
+    /// of sequence, cause a switchover instead of being returned; the error
+    /// is then returned only when no server can be reached. Errors that keep
+    /// the active link up are returned as by [`Iec104Client::poll`], without
+    /// failover.
+    pub async fn poll(&mut self) -> Result<Option<RedundantEvent>> {
+        let Some(active) = self.active.as_mut() else {
+            if self.lost.is_some() {
+                return self.switch_over().await.map(Some);
+            }
+            return Err(Iec104Error::NotConnected);
+        };
+
+        // Only cancel-safe futures are raced; the work happens in the handlers
+        let standby = self.standby.as_mut().map(|link| &mut link.client);
+        let result = tokio::select! {
+            input = active.client.next_input() => active.client.process_input(input).await,
+            input = next_input(standby) => {
+                let Some(standby) = self.standby.as_mut() else {
+                    return Ok(None);
+                };
+                // Errors that keep the link up are of no interest on the standby
+                let _ = standby.client.process_input(input).await;
+                if standby.client.state() != ConnectionState::Disconnected {
+                    return Ok(None);
+                }
+                let lost = self.addresses[standby.index].clone();
+                self.standby = None;
+                return Ok(Some(RedundantEvent::StandbyLost(lost)));
+            }
+        };
+        if active.client.state() == ConnectionState::Disconnected {
+            self.lost = Some(active.index);
+            self.active = None;
+            return self.switch_over().await.map(Some);
+        }
+        result.map(|event| event.map(RedundantEvent::Event))
+    }
+
+    /// Replace the lost active link.
+    ///
+    /// Nothing is left connected when this fails, and the servers are tried
+    /// again on the next call.
+    async fn switch_over(&mut self) -> Result<RedundantEvent> {
+        let failed = self.lost;
+        let from = failed.map(|index| self.addresses[index].clone());
+
+        let mut promoted = self.standby.take();
+        if let Some(link) = promoted.as_mut() {
+            if link.client.start_dt().await.is_err() {
+                promoted = None;
+            }
+        }
+        match promoted {
+            Some(link) => self.active = Some(link),
+            None => self.activate(failed.map_or(0, |index| index + 1)).await?,
+        }
+        self.lost = None;
+
+        if let Some(link) = self.active.as_mut() {
+            for &common_address in &self.interrogate {
+                let _ = link.client.general_interrogation(common_address).await;
+            }
+        }
+        self.connect_standby().await;
+
+        Ok(RedundantEvent::Switchover {
+            from,
+            to: self.active_address().unwrap_or_default().to_owned(),
+        })
+    }
+
+    /// Start data transfer with the first reachable server from `start` on,
+    /// wrapping around.
+    async fn activate(&mut self, start: usize) -> Result<()> {
+        let count = self.addresses.len();
+        for index in (start..start + count).map(|i| i % count) {
+            if let Ok(client) = self.open(index, true).await {
+                self.active = Some(Link { index, client });
+                return Ok(());
+            }
+        }
+        Err(Iec104Error::Connection(Cow::Borrowed(
+            "No redundant server reachable",
+        )))
+    }
+
+    /// Connect the next reachable server after the active one as standby.
+    async fn connect_standby(&mut self) {
+        if !self.warm_standby || self.standby.is_some() {
+            return;
+        }
+        let Some(active) = self.active.as_ref().map(|link| link.index) else {
+            return;
+        };
+        let count = self.addresses.len();
+        for index in (active + 1..active + count).map(|i| i % count) {
+            if let Ok(client) = self.open(index, false).await {
+                self.standby = Some(Link { index, client });
+                return;
+            }
+        }
+    }
+
+    async fn open(&self, index: usize, start: bool) -> Result<Iec104Client> {
+        let mut config = self.config.clone();
+        config.address = self.addresses[index].clone();
+        let mut client = Iec104Client::new(config);
+        client.connect().await?;
+        if start {
+            client.start_dt().await?;
+        }
+        Ok(client)
+    }
+}
+
+/// Next input of the standby, never ready without one.
+async fn next_input(standby: Option<&mut Iec104Client>) -> LinkInput {
+    match standby {
+        Some(client) => client.next_input().await,
+        None => std::future::pending().await,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+    use crate::codec::Apdu;
+    use crate::testing::TestServer;
+    use crate::types::{Apci, Asdu, AsduHeader, Cot, TypeId, UFunction};
+    use bytes::Bytes;
+    use tokio::net::TcpListener;
+    use tokio::sync::oneshot;
+
+    /// Accept one connection and confirm its STARTDT.
+    async fn accept_started(listener: &TcpListener) -> TestServer {
+        let mut server = TestServer::accept(listener).await.unwrap();
+        let apdu = server.recv_apdu().await.unwrap();
+        assert!(matches!(
+            apdu.apci,
+            Apci::UFrame {
+                function: UFunction::StartDtAct
+            }
+        ));
+        server
+            .send_apdu(Apdu::u_frame(UFunction::StartDtCon))
+            .await
+            .unwrap();
+        server
+    }
+
+    /// Accept one connection and serve it until the client goes away.
+    async fn serve(listener: TcpListener) {
+        let mut server = TestServer::accept(&listener).await.unwrap();
+        while server.recv_asdu().await.is_ok() {}
+    }
+
+    #[tokio::test]
+    async fn test_failover_to_warm_standby() {
+        let primary = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let backup = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addresses = [
+            primary.local_addr().unwrap().to_string(),
+            backup.local_addr().unwrap().to_string(),
+        ];
+        let (kill_primary, killed) = oneshot::channel::<()>();
+        let (interrogated_tx, interrogated) = oneshot::channel();
+
+        tokio::spawn(async move {
+            let _server = accept_started(&primary).await;
+            let _ = killed.await;
+        });
+        tokio::spawn(async move {
+            // Nothing is sent to the standby until the switchover
+            let mut server = accept_started(&backup).await;
+            let gi = server.recv_asdu().await.unwrap();
+            interrogated_tx.send(gi.header.type_id).unwrap();
+            while server.recv_asdu().await.is_ok() {}
+        });
+
+        let mut client = RedundantClient::new(ClientConfig::new(""), addresses.clone())
+            .warm_standby(true)
+            .interrogate_after_switchover(1);
+        client.connect().await.unwrap();
+        assert_eq!(client.active_address(), Some(addresses[0].as_str()));
+        assert_eq!(client.standby_address(), Some(addresses[1].as_str()));
+
+        kill_primary.send(()).unwrap();
+        loop {
+            if let Some(RedundantEvent::Switchover { from, to }) = client.poll().await.unwrap() {
+                assert_eq!(from.as_deref(), Some(addresses[0].as_str()));
+                assert_eq!(to, addresses[1]);
+                break;
+            }
+        }
+        assert_eq!(interrogated.await.unwrap(), TypeId::InterrogationCommand);
+        assert_eq!(client.active().unwrap().state(), ConnectionState::Active);
+    }
+
+    #[tokio::test]
+    async fn test_failover_on_sequence_error() {
+        let primary = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let backup = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addresses = [
+            primary.local_addr().unwrap().to_string(),
+            backup.local_addr().unwrap().to_string(),
+        ];
+        let (send_data, data_sent) = oneshot::channel::<()>();
+        tokio::spawn(async move {
+            let mut server = accept_started(&primary).await;
+            let _ = data_sent.await;
+            // N(S) 2 where 0 is expected
+            let mut data = Asdu::new(AsduHeader::new(TypeId::SinglePoint, 1, Cot::Spontaneous, 1));
+            data.raw_data = Bytes::from_static(&[0x01, 0x00, 0x00, 0x01]);
+            server.send_apdu(Apdu::i_frame(2, 0, data)).await.unwrap();
+            while server.recv_apdu().await.is_ok() {}
+        });
+        tokio::spawn(serve(backup));
+
+        let mut client = RedundantClient::new(ClientConfig::new(""), addresses.clone());
+        client.connect().await.unwrap();
+        send_data.send(()).unwrap();
+
+        let event = tokio::time::timeout(Duration::from_secs(5), client.poll())
+            .await
+            .unwrap()
+            .unwrap();
+        match event {
+            Some(RedundantEvent::Switchover { from, to }) => {
+                assert_eq!(from.as_deref(), Some(addresses[0].as_str()));
+                assert_eq!(to, addresses[1]);
+            }
+            other => panic!("Expected switchover, got {:?}", other),
+        }
+        assert_eq!(client.active().unwrap().state(), ConnectionState::Active);
+    }
+
+    #[tokio::test]
+    async fn test_connect_skips_unreachable_server() {
+        let unreachable = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let dead = unreachable.local_addr().unwrap().to_string();
+        drop(unreachable);
+
+        let backup = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let live = backup.local_addr().unwrap().to_string();
+        tokio::spawn(serve(backup));
+
+        let mut client = RedundantClient::new(ClientConfig::new(""), [dead, live.clone()]);
+        client.connect().await.unwrap();
+        assert_eq!(client.active_address(), Some(live.as_str()));
+        assert!(client.standby_address().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_standby_loss_reported() {
+        let primary = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let backup = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addresses = [
+            primary.local_addr().unwrap().to_string(),
+            backup.local_addr().unwrap().to_string(),
+        ];
+        tokio::spawn(serve(primary));
+        tokio::spawn(async move {
+            let server = TestServer::accept(&backup).await.unwrap();
+            server.close().await.unwrap();
+        });
+
+        let mut client =
+            RedundantClient::new(ClientConfig::new(""), addresses.clone()).warm_standby(true);
+        client.connect().await.unwrap();
+        assert_eq!(client.standby_address(), Some(addresses[1].as_str()));
+
+        // Noticed while the active link stays quiet
+        let event = tokio::time::timeout(Duration::from_secs(5), client.poll())
+            .await
+            .unwrap()
+            .unwrap();
+        assert!(matches!(
+            event,
+            Some(RedundantEvent::StandbyLost(address)) if address == addresses[1]
+        ));
+        assert!(client.standby_address().is_none());
+        assert_eq!(client.active_address(), Some(addresses[0].as_str()));
+    }
+
+    #[tokio::test]
+    async fn test_no_server_reachable_after_switchover() {
+        let primary = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let unreachable = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addresses = [
+            primary.local_addr().unwrap().to_string(),
+            unreachable.local_addr().unwrap().to_string(),
+        ];
+        drop(unreachable);
+        let (kill_primary, killed) = oneshot::channel::<()>();
+        tokio::spawn(async move {
+            let _server = accept_started(&primary).await;
+            drop(primary);
+            let _ = killed.await;
+        });
+
+        let mut client = RedundantClient::new(ClientConfig::new(""), addresses.clone());
+        client.connect().await.unwrap();
+        kill_primary.send(()).unwrap();
+
+        let error = client.poll().await.unwrap_err();
+        assert!(matches!(error, Iec104Error::Connection(_)));
+        assert!(client.active_address().is_none());
+        // Still trying the servers rather than giving up
+        assert!(matches!(client.poll().await, Err(Iec104Error::Connection(_))));
+
+        let primary = TcpListener::bind(&addresses[0]).await.unwrap();
+        tokio::spawn(serve(primary));
+        match client.poll().await.unwrap() {
+            Some(RedundantEvent::Switchover { from, to }) => {
+                assert_eq!(from.as_deref(), Some(addresses[0].as_str()));
+                assert_eq!(to, addresses[0]);
+            }
+            other => panic!("Expected switchover, got {:?}", other),
+        }
+        assert_eq!(client.active().unwrap().state(), ConnectionState::Active);
+    }
+}
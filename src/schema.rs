@@ -0,0 +1,331 @@
+//! Machine-readable description of the crate's data model.
+//!
+//! [`json_schema`] exports a JSON Schema (draft 2020-12) describing the JSON
+//! shape of [`DataPoint`](crate::types::DataPoint) and its value types, plus
+//! an `x-type-ids` table listing every supported type identification with its
+//! information element layout. Downstream systems can use it to generate
+//! bindings or validate serialized events.
+
+use crate::types::TypeId;
+
+/// JSON Schema dialect used by [`json_schema`].
+pub const SCHEMA_DIALECT: &str = "https://json-schema.org/draft/2020-12/schema";
+
+/// Export the data model as a JSON Schema document.
+///
+/// The root schema validates a single `DataPoint`; the other types are
+/// available under `$defs`.
+pub fn json_schema() -> String {
+    let mut out = String::with_capacity(8 * 1024);
+    out.push('{');
+    push_field(&mut out, "$schema", &quoted(SCHEMA_DIALECT));
+    out.push(',');
+    push_field(&mut out, "$id", &quoted("urn:voltage_iec104:data-model"));
+    out.push(',');
+    push_field(&mut out, "title", &quoted("voltage_iec104 DataPoint"));
+    out.push(',');
+    push_field(
+        &mut out,
+        "x-crate-version",
+        &quoted(env!("CARGO_PKG_VERSION")),
+    );
+    out.push(',');
+    push_field(&mut out, "$ref", &quoted("#/$defs/DataPoint"));
+    out.push(',');
+    push_field(&mut out, "x-type-ids", &type_id_table());
+    out.push(',');
+    push_field(&mut out, "$defs", &definitions());
+    out.push('}');
+    out
+}
+
+/// The `DataValue` variant produced for a monitoring type, if any.
+fn value_kind(type_id: TypeId) -> Option<&'static str> {
+    match type_id {
+        TypeId::SinglePoint | TypeId::SinglePointTime24 | TypeId::SinglePointTime56 => {
+            Some("Single")
+        }
+        TypeId::DoublePoint | TypeId::DoublePointTime24 | TypeId::DoublePointTime56 => {
+            Some("Double")
+        }
+        TypeId::StepPosition | TypeId::StepPositionTime56 => Some("StepPosition"),
+        TypeId::Bitstring32 | TypeId::Bitstring32Time56 => Some("Bitstring"),
+        TypeId::MeasuredNormalized
+        | TypeId::MeasuredNormalizedTime24
+        | TypeId::MeasuredNormalizedTime56
+        | TypeId::MeasuredNormalizedNoQuality => Some("Normalized"),
+        TypeId::MeasuredScaled | TypeId::MeasuredScaledTime24 | TypeId::MeasuredScaledTime56 => {
+            Some("Scaled")
+        }
+        TypeId::MeasuredFloat | TypeId::MeasuredFloatTime24 | TypeId::MeasuredFloatTime56 => {
+            Some("Float")
+        }
+        TypeId::IntegratedTotals | TypeId::IntegratedTotalsTime56 => Some("BinaryCounter"),
+        TypeId::PackedSinglePoint => Some("PackedSinglePoint"),
+        TypeId::ParameterNormalized | TypeId::ParameterScaled | TypeId::ParameterFloat => {
+            Some("Parameter")
+        }
+        _ => None,
+    }
+}
+
+fn type_id_table() -> String {
+    let entries: Vec<String> = TypeId::ALL
+        .iter()
+        .map(|type_id| {
+            let elements: Vec<String> = type_id
+                .information_elements()
+                .iter()
+                .map(|e| quoted(e))
+                .collect();
+            let direction = if type_id.is_control() {
+                "control"
+            } else if type_id.is_file_transfer() {
+                "file"
+            } else {
+                "monitoring"
+            };
+            let kind = value_kind(*type_id)
+                .map(quoted)
+                .unwrap_or_else(|| "null".to_string());
+            format!(
+                "{{\"id\":{},\"name\":{},\"variant\":{},\"direction\":{},\"element_size\":{},\
+                 \"elements\":[{}],\"time_tag\":{},\"value_kind\":{}}}",
+                type_id.as_u8(),
+                quoted(type_id.standard_name()),
+                quoted(&format!("{:?}", type_id)),
+                quoted(direction),
+                type_id.element_size(),
+                elements.join(","),
+                type_id.has_time_tag(),
+                kind
+            )
+        })
+        .collect();
+    format!("[{}]", entries.join(","))
+}
+
+fn definitions() -> String {
+    let variant = |name: &str, schema: &str| {
+        format!(
+            "{{\"type\":\"object\",\"properties\":{{{}:{}}},\"required\":[{}],\
+             \"additionalProperties\":false}}",
+            quoted(name),
+            schema,
+            quoted(name)
+        )
+    };
+    let int_range = |min: i64, max: i64| {
+        format!(
+            "{{\"type\":\"integer\",\"minimum\":{},\"maximum\":{}}}",
+            min, max
+        )
+    };
+    let boolean = "{\"type\":\"boolean\"}";
+
+    let data_value = [
+        variant("Single", boolean),
+        variant("Double", "{\"$ref\":\"#/$defs/DoublePointValue\"}"),
+        variant(
+            "Normalized",
+            "{\"type\":\"number\",\"minimum\":-1.0,\"maximum\":1.0}",
+        ),
+        variant("Scaled", &int_range(i16::MIN as i64, i16::MAX as i64)),
+        variant("Float", "{\"type\":\"number\"}"),
+        variant("Counter", &int_range(i32::MIN as i64, i32::MAX as i64)),
+        variant("Bitstring", &int_range(0, u32::MAX as i64)),
+        variant(
+            "StepPosition",
+            &object(&[
+                ("value", int_range(-64, 63)),
+                ("transient", boolean.to_string()),
+            ]),
+        ),
+        variant(
+            "PackedSinglePoint",
+            &object(&[
+                ("status", int_range(0, u16::MAX as i64)),
+                ("changes", int_range(0, u16::MAX as i64)),
+            ]),
+        ),
+        variant(
+            "BinaryCounter",
+            &object(&[
+                ("value", int_range(i32::MIN as i64, i32::MAX as i64)),
+                ("sequence", int_range(0, 31)),
+                ("carry", boolean.to_string()),
+                ("adjusted", boolean.to_string()),
+                ("invalid", boolean.to_string()),
+            ]),
+        ),
+        variant(
+            "Parameter",
+            &object(&[
+                ("value", "{\"$ref\":\"#/$defs/ParameterValue\"}".to_string()),
+                ("qpm", "{\"$ref\":\"#/$defs/Qpm\"}".to_string()),
+            ]),
+        ),
+    ];
+
+    let parameter_value = [
+        variant(
+            "Normalized",
+            "{\"type\":\"number\",\"minimum\":-1.0,\"maximum\":1.0}",
+        ),
+        variant("Scaled", &int_range(i16::MIN as i64, i16::MAX as i64)),
+        variant("Float", "{\"type\":\"number\"}"),
+    ];
+
+    let qpm = object(&[
+        (
+            "kind",
+            format!(
+                "{{\"oneOf\":[{{\"type\":\"string\",\"enum\":[\"Threshold\",\
+                 \"SmoothingFactor\",\"LowLimit\",\"HighLimit\"]}},{}]}}",
+                variant("Other", &int_range(0, 63))
+            ),
+        ),
+        ("local_change", boolean.to_string()),
+        ("out_of_operation", boolean.to_string()),
+    ]);
+
+    let quality = object(&[
+        ("overflow", boolean.to_string()),
+        ("blocked", boolean.to_string()),
+        ("substituted", boolean.to_string()),
+        ("not_topical", boolean.to_string()),
+        ("invalid", boolean.to_string()),
+        ("elapsed_time_invalid", boolean.to_string()),
+    ]);
+
+    let cp56 = object(&[
+        ("milliseconds", int_range(0, 59999)),
+        ("minutes", int_range(0, 59)),
+        ("hours", int_range(0, 23)),
+        ("day", int_range(1, 31)),
+        ("day_of_week", int_range(0, 7)),
+        ("month", int_range(1, 12)),
+        ("year", int_range(0, 99)),
+        ("invalid", boolean.to_string()),
+        ("summer_time", boolean.to_string()),
+    ]);
+
+    let cp24 = object(&[
+        ("milliseconds", int_range(0, 59999)),
+        ("minutes", int_range(0, 59)),
+        ("invalid", boolean.to_string()),
+    ]);
+
+    let timestamp = [
+        variant("Full", "{\"$ref\":\"#/$defs/Cp56Time2a\"}"),
+        variant("Partial", "{\"$ref\":\"#/$defs/Cp24Time2a\"}"),
+    ];
+
+    let data_point = object(&[
+        ("ioa", int_range(0, 0xFF_FFFF)),
+        ("value", "{\"$ref\":\"#/$defs/DataValue\"}".to_string()),
+        ("quality", "{\"$ref\":\"#/$defs/Quality\"}".to_string()),
+        (
+            "timestamp",
+            "{\"oneOf\":[{\"$ref\":\"#/$defs/Timestamp\"},{\"type\":\"null\"}]}".to_string(),
+        ),
+    ]);
+
+    let double_point = "{\"type\":\"string\",\"enum\":[\"Indeterminate\",\"Off\",\"On\",\
+                        \"IndeterminateOrFaulty\"]}";
+
+    format!(
+        "{{\"DataPoint\":{},\"DataValue\":{{\"oneOf\":[{}]}},\"DoublePointValue\":{},\
+         \"ParameterValue\":{{\"oneOf\":[{}]}},\"Qpm\":{},\"Quality\":{},\
+         \"Timestamp\":{{\"oneOf\":[{}]}},\"Cp56Time2a\":{},\"Cp24Time2a\":{}}}",
+        data_point,
+        data_value.join(","),
+        double_point,
+        parameter_value.join(","),
+        qpm,
+        quality,
+        timestamp.join(","),
+        cp56,
+        cp24
+    )
+}
+
+/// Build a closed object schema from `(name, schema)` pairs, all required.
+fn object(properties: &[(&str, String)]) -> String {
+    let props: Vec<String> = properties
+        .iter()
+        .map(|(name, schema)| format!("{}:{}", quoted(name), schema))
+        .collect();
+    let required: Vec<String> = properties.iter().map(|(name, _)| quoted(name)).collect();
+    format!(
+        "{{\"type\":\"object\",\"properties\":{{{}}},\"required\":[{}],\
+         \"additionalProperties\":false}}",
+        props.join(","),
+        required.join(",")
+    )
+}
+
+fn push_field(out: &mut String, name: &str, value: &str) {
+    out.push_str(&quoted(name));
+    out.push(':');
+    out.push_str(value);
+}
+
+/// Quote a string for JSON. Only used for the crate's own identifiers,
+/// which never contain characters that need escaping beyond `"` and `\`.
+fn quoted(value: &str) -> String {
+    format!("\"{}\"", value.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_json_schema_is_valid_json() {
+        let schema: serde_json::Value = serde_json::from_str(&json_schema()).unwrap();
+        assert_eq!(schema["$schema"], SCHEMA_DIALECT);
+        assert_eq!(schema["$ref"], "#/$defs/DataPoint");
+        assert!(schema["$defs"]["DataValue"]["oneOf"].is_array());
+        assert!(schema["$defs"]["Timestamp"]["oneOf"].is_array());
+        assert!(schema["$defs"]["Cp24Time2a"]["properties"]["minutes"].is_object());
+    }
+
+    #[test]
+    fn test_json_schema_lists_all_type_ids() {
+        let schema: serde_json::Value = serde_json::from_str(&json_schema()).unwrap();
+        let table = schema["x-type-ids"].as_array().unwrap();
+        assert_eq!(table.len(), TypeId::ALL.len());
+
+        let float = table.iter().find(|t| t["id"] == 36).unwrap();
+        assert_eq!(float["name"], "M_ME_TF_1");
+        assert_eq!(float["element_size"], 12);
+        assert_eq!(float["value_kind"], "Float");
+        assert_eq!(float["elements"][2], "CP56Time2a");
+
+        let parameter = table.iter().find(|t| t["id"] == 111).unwrap();
+        assert_eq!(parameter["direction"], "control");
+        assert_eq!(parameter["value_kind"], "Parameter");
+
+        let command = table.iter().find(|t| t["id"] == 45).unwrap();
+        assert_eq!(command["direction"], "control");
+        assert!(command["value_kind"].is_null());
+    }
+
+    #[test]
+    fn test_value_kinds_exist_in_data_value() {
+        let schema: serde_json::Value = serde_json::from_str(&json_schema()).unwrap();
+        let variants: Vec<String> = schema["$defs"]["DataValue"]["oneOf"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|v| v["required"][0].as_str().unwrap().to_string())
+            .collect();
+
+        for type_id in TypeId::ALL {
+            if let Some(kind) = value_kind(*type_id) {
+                assert!(variants.iter().any(|v| v == kind), "{} missing", kind);
+            }
+        }
+    }
+}
@@ -0,0 +1,472 @@
+//! Local historian in an embedded SQLite database.
+//!
+//! [`SqliteHistorian`] keeps received data points, the changes of their
+//! quality and the commands issued in one database file, which needs
+//! neither a server nor a SQLite installation (the library is compiled in):
+//!
+//! ```sql
+//! CREATE TABLE points (
+//!     recorded_at INTEGER NOT NULL, -- ms since the Unix epoch, UTC
+//!     ca INTEGER NOT NULL,
+//!     ioa INTEGER NOT NULL,
+//!     type TEXT NOT NULL,           -- DataValue variant, e.g. 'Float'
+//!     value REAL,                   -- 1/0 for on/off, NULL if indeterminate
+//!     quality INTEGER NOT NULL,     -- quality descriptor bits (Quality::as_raw)
+//!     timestamp INTEGER             -- time tag in ms since the epoch (CP56Time2a only)
+//! );
+//! CREATE TABLE quality_transitions (
+//!     recorded_at INTEGER NOT NULL,
+//!     ca INTEGER NOT NULL,
+//!     ioa INTEGER NOT NULL,
+//!     previous INTEGER,             -- NULL the first time the object is seen
+//!     quality INTEGER NOT NULL
+//! );
+//! CREATE TABLE commands (
+//!     issued_at INTEGER NOT NULL,
+//!     ca INTEGER NOT NULL,
+//!     ioa INTEGER NOT NULL,
+//!     type TEXT NOT NULL,           -- standard name, e.g. 'C_SC_NA_1'
+//!     value REAL NOT NULL,          -- 1/0 for on/off, 1/-1 for higher/lower
+//!     selected INTEGER NOT NULL,    -- 1 for select, 0 for execute
+//!     success INTEGER NOT NULL,     -- 1 once confirmed
+//!     error TEXT
+//! );
+//! ```
+//!
+//! With a [`retention`](SqliteHistorian::retention) period, older rows are
+//! deleted from all tables while recording, at most once an hour.
+
+use std::collections::HashMap;
+use std::path::Path;
+use std::time::{Duration, SystemTime};
+
+use rusqlite::{params, Connection};
+use tokio::sync::mpsc;
+
+use crate::client::{Iec104Event, SequencedEvent};
+use crate::command::{Command, StepCommand};
+use crate::error::{Iec104Error, Result};
+use crate::types::{DataPoint, DoubleCommandState, Timestamp};
+
+/// Minimum time between two automatic prunings.
+const PRUNE_INTERVAL: Duration = Duration::from_secs(3600);
+
+const SCHEMA: &str = "
+    CREATE TABLE IF NOT EXISTS points (
+        recorded_at INTEGER NOT NULL,
+        ca INTEGER NOT NULL,
+        ioa INTEGER NOT NULL,
+        type TEXT NOT NULL,
+        value REAL,
+        quality INTEGER NOT NULL,
+        timestamp INTEGER
+    );
+    CREATE INDEX IF NOT EXISTS points_by_object ON points (ca, ioa, recorded_at);
+    CREATE INDEX IF NOT EXISTS points_by_time ON points (recorded_at);
+    CREATE TABLE IF NOT EXISTS quality_transitions (
+        recorded_at INTEGER NOT NULL,
+        ca INTEGER NOT NULL,
+        ioa INTEGER NOT NULL,
+        previous INTEGER,
+        quality INTEGER NOT NULL
+    );
+    CREATE INDEX IF NOT EXISTS quality_transitions_by_time
+        ON quality_transitions (recorded_at);
+    CREATE TABLE IF NOT EXISTS commands (
+        issued_at INTEGER NOT NULL,
+        ca INTEGER NOT NULL,
+        ioa INTEGER NOT NULL,
+        type TEXT NOT NULL,
+        value REAL NOT NULL,
+        selected INTEGER NOT NULL,
+        success INTEGER NOT NULL,
+        error TEXT
+    );
+    CREATE INDEX IF NOT EXISTS commands_by_time ON commands (issued_at);
+";
+
+/// Writes data points, quality transitions and commands to SQLite.
+#[derive(Debug)]
+pub struct SqliteHistorian {
+    connection: Connection,
+    retention: Option<Duration>,
+    /// Last quality recorded per object, to detect transitions
+    qualities: HashMap<(u16, u32), u8>,
+    last_pruned: Option<SystemTime>,
+}
+
+impl SqliteHistorian {
+    /// Open or create the database at `path`, creating the tables as needed.
+    pub fn open(path: impl AsRef<Path>) -> Result<Self> {
+        Self::with_connection(Connection::open(path).map_err(sqlite_error)?)
+    }
+
+    /// Create a database that lives in memory, e.g. for tests.
+    pub fn open_in_memory() -> Result<Self> {
+        Self::with_connection(Connection::open_in_memory().map_err(sqlite_error)?)
+    }
+
+    fn with_connection(connection: Connection) -> Result<Self> {
+        connection.execute_batch(SCHEMA).map_err(sqlite_error)?;
+
+        // Continue the transitions of an existing database; SQLite takes the
+        // bare column from the row holding max(rowid)
+        let mut qualities = HashMap::new();
+        {
+            let mut statement = connection
+                .prepare(
+                    "SELECT ca, ioa, quality, max(rowid) FROM quality_transitions \
+                     GROUP BY ca, ioa",
+                )
+                .map_err(sqlite_error)?;
+            let rows = statement
+                .query_map([], |row| Ok(((row.get(0)?, row.get(1)?), row.get(2)?)))
+                .map_err(sqlite_error)?;
+            for row in rows {
+                let (key, quality) = row.map_err(sqlite_error)?;
+                qualities.insert(key, quality);
+            }
+        }
+
+        Ok(Self {
+            connection,
+            retention: None,
+            qualities,
+            last_pruned: None,
+        })
+    }
+
+    /// Delete rows older than `keep` while recording.
+    pub fn retention(mut self, keep: Duration) -> Self {
+        self.retention = Some(keep);
+        self
+    }
+
+    /// The database connection, for queries.
+    pub fn connection(&self) -> &Connection {
+        &self.connection
+    }
+
+    /// Record points received from station `common_address` now.
+    pub fn record(&mut self, common_address: u16, points: &[DataPoint]) -> Result<()> {
+        self.record_at(common_address, points, SystemTime::now())
+    }
+
+    /// Record points received from station `common_address` at `recorded_at`.
+    ///
+    /// All points are written in one transaction.
+    pub fn record_at(
+        &mut self,
+        common_address: u16,
+        points: &[DataPoint],
+        recorded_at: SystemTime,
+    ) -> Result<()> {
+        let at = unix_millis(recorded_at);
+        let transaction = self.connection.transaction().map_err(sqlite_error)?;
+        let mut transitions = Vec::new();
+        {
+            let mut insert_point = transaction
+                .prepare_cached(
+                    "INSERT INTO points (recorded_at, ca, ioa, type, value, quality, timestamp) \
+                     VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+                )
+                .map_err(sqlite_error)?;
+            let mut insert_transition = transaction
+                .prepare_cached(
+                    "INSERT INTO quality_transitions (recorded_at, ca, ioa, previous, quality) \
+                     VALUES (?1, ?2, ?3, ?4, ?5)",
+                )
+                .map_err(sqlite_error)?;
+            for point in points {
+                let quality = point.quality.as_raw();
+                let value = point.value.as_f64().filter(|value| !value.is_nan());
+                let timestamp = match point.timestamp {
+                    Some(Timestamp::Full(time)) => Some(time.unix_millis()),
+                    _ => None,
+                };
+                insert_point
+                    .execute(params![
+                        at,
+                        common_address,
+                        point.ioa,
+                        point.value.name(),
+                        value,
+                        quality,
+                        timestamp
+                    ])
+                    .map_err(sqlite_error)?;
+
+                let key = (common_address, point.ioa);
+                let previous = transitions
+                    .iter()
+                    .rev()
+                    .find(|(k, _)| *k == key)
+                    .map(|(_, quality)| *quality)
+                    .or_else(|| self.qualities.get(&key).copied());
+                if previous != Some(quality) {
+                    insert_transition
+                        .execute(params![at, common_address, point.ioa, previous, quality])
+                        .map_err(sqlite_error)?;
+                    transitions.push((key, quality));
+                }
+            }
+        }
+        transaction.commit().map_err(sqlite_error)?;
+        // Only committed transitions count
+        self.qualities.extend(transitions);
+
+        self.prune_due(recorded_at)
+    }
+
+    /// Record a command issued to station `common_address` now, with its
+    /// outcome: `Ok` once confirmed, otherwise the error.
+    pub fn record_command(
+        &mut self,
+        common_address: u16,
+        command: &Command,
+        select: bool,
+        outcome: &Result<()>,
+    ) -> Result<()> {
+        self.record_command_at(common_address, command, select, outcome, SystemTime::now())
+    }
+
+    /// Record a command issued to station `common_address` at `issued_at`.
+    pub fn record_command_at(
+        &mut self,
+        common_address: u16,
+        command: &Command,
+        select: bool,
+        outcome: &Result<()>,
+        issued_at: SystemTime,
+    ) -> Result<()> {
+        let value = match *command {
+            Command::Single { value, .. } => f64::from(u8::from(value)),
+            Command::Double { value, .. } => match value {
+                DoubleCommandState::Off => 0.0,
+                DoubleCommandState::On => 1.0,
+            },
+            Command::RegulatingStep { step, .. } => match step {
+                StepCommand::Lower => -1.0,
+                StepCommand::Higher => 1.0,
+            },
+            Command::SetpointNormalized { value, .. } | Command::SetpointFloat { value, .. } => {
+                f64::from(value)
+            }
+            Command::SetpointScaled { value, .. } => f64::from(value),
+        };
+        let error = outcome.as_ref().err().map(ToString::to_string);
+        self.connection
+            .execute(
+                "INSERT INTO commands (issued_at, ca, ioa, type, value, selected, success, error) \
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+                params![
+                    unix_millis(issued_at),
+                    common_address,
+                    command.ioa(),
+                    command.type_id().standard_name(),
+                    value,
+                    select,
+                    outcome.is_ok(),
+                    error
+                ],
+            )
+            .map_err(sqlite_error)?;
+        self.prune_due(issued_at)
+    }
+
+    /// Delete rows recorded before `before` from all tables and return how
+    /// many were deleted.
+    ///
+    /// Quality transitions are kept for objects whose last transition is
+    /// older, so the current quality of every object stays known.
+    pub fn prune_before(&mut self, before: SystemTime) -> Result<usize> {
+        let before = unix_millis(before);
+        let transaction = self.connection.transaction().map_err(sqlite_error)?;
+        let mut deleted = transaction
+            .execute("DELETE FROM points WHERE recorded_at < ?1", [before])
+            .map_err(sqlite_error)?;
+        deleted += transaction
+            .execute(
+                "DELETE FROM quality_transitions WHERE recorded_at < ?1 AND rowid NOT IN \
+                 (SELECT max(rowid) FROM quality_transitions GROUP BY ca, ioa)",
+                [before],
+            )
+            .map_err(sqlite_error)?;
+        deleted += transaction
+            .execute("DELETE FROM commands WHERE issued_at < ?1", [before])
+            .map_err(sqlite_error)?;
+        transaction.commit().map_err(sqlite_error)?;
+        Ok(deleted)
+    }
+
+    /// Record the data updates of an event stream from station
+    /// `common_address` until it ends, e.g. a receiver from
+    /// [`subscribe_filtered`](crate::Iec104Client::subscribe_filtered) with
+    /// [`EventFilter::common_address`](crate::filter::EventFilter::common_address).
+    pub async fn run(
+        mut self,
+        common_address: u16,
+        mut events: mpsc::Receiver<SequencedEvent>,
+    ) -> Result<()> {
+        while let Some(SequencedEvent { event, .. }) = events.recv().await {
+            if let Iec104Event::DataUpdate(points) = event {
+                self.record(common_address, &points)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Apply the retention period if the last pruning is an hour ago.
+    fn prune_due(&mut self, now: SystemTime) -> Result<()> {
+        let Some(keep) = self.retention else {
+            return Ok(());
+        };
+        let due = match self.last_pruned {
+            Some(last) => now
+                .duration_since(last)
+                .is_ok_and(|elapsed| elapsed >= PRUNE_INTERVAL),
+            None => true,
+        };
+        if due {
+            self.prune_before(now.checked_sub(keep).unwrap_or(SystemTime::UNIX_EPOCH))?;
+            self.last_pruned = Some(now);
+        }
+        Ok(())
+    }
+}
+
+fn unix_millis(time: SystemTime) -> i64 {
+    time.duration_since(SystemTime::UNIX_EPOCH)
+        .map_or(0, |since| since.as_millis() as i64)
+}
+
+fn sqlite_error(err: rusqlite::Error) -> Iec104Error {
+    Iec104Error::Io(std::io::Error::other(err))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::{DataValue, Quality};
+
+    fn at(seconds: u64) -> SystemTime {
+        SystemTime::UNIX_EPOCH + Duration::from_secs(1_709_214_330 + seconds)
+    }
+
+    fn count(historian: &SqliteHistorian, table: &str) -> i64 {
+        historian
+            .connection()
+            .query_row(&format!("SELECT count(*) FROM {}", table), [], |row| {
+                row.get(0)
+            })
+            .unwrap()
+    }
+
+    #[test]
+    fn test_points_and_transitions() {
+        let mut historian = SqliteHistorian::open_in_memory().unwrap();
+        let good = DataPoint::new(100, DataValue::Float(1.5));
+        let invalid =
+            DataPoint::with_quality(100, DataValue::Float(1.5), Quality::Good.set_invalid(true));
+        historian
+            .record_at(1, std::slice::from_ref(&good), at(0))
+            .unwrap();
+        historian
+            .record_at(1, std::slice::from_ref(&good), at(1))
+            .unwrap();
+        historian.record_at(1, &[invalid, good], at(2)).unwrap();
+
+        assert_eq!(count(&historian, "points"), 4);
+        let transitions: Vec<(i64, Option<u8>, u8)> = historian
+            .connection()
+            .prepare("SELECT recorded_at, previous, quality FROM quality_transitions")
+            .unwrap()
+            .query_map([], |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)))
+            .unwrap()
+            .collect::<std::result::Result<_, _>>()
+            .unwrap();
+        let ms = |seconds| unix_millis(at(seconds));
+        let iv = Quality::Good.set_invalid(true).as_raw();
+        assert_eq!(
+            transitions,
+            vec![(ms(0), None, 0), (ms(2), Some(0), iv), (ms(2), Some(iv), 0)]
+        );
+
+        let (kind, value): (String, f64) = historian
+            .connection()
+            .query_row("SELECT type, value FROM points LIMIT 1", [], |row| {
+                Ok((row.get(0)?, row.get(1)?))
+            })
+            .unwrap();
+        assert_eq!((kind.as_str(), value), ("Float", 1.5));
+    }
+
+    #[test]
+    fn test_commands_and_retention() {
+        let path = std::env::temp_dir().join(format!("iec104-sqlite-{}.db", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        let mut historian = SqliteHistorian::open(&path)
+            .unwrap()
+            .retention(Duration::from_secs(3600));
+
+        let point = DataPoint::new(100, DataValue::Single(true));
+        historian
+            .record_at(1, std::slice::from_ref(&point), at(0))
+            .unwrap();
+        let command = Command::Single {
+            ioa: 5,
+            value: true,
+        };
+        historian
+            .record_command_at(1, &command, false, &Ok(()), at(0))
+            .unwrap();
+        let rejected = Err(Iec104Error::protocol_static("rejected"));
+        historian
+            .record_command_at(1, &command, true, &rejected, at(1))
+            .unwrap();
+        let error: Option<String> = historian
+            .connection()
+            .query_row("SELECT error FROM commands WHERE success = 0", [], |row| {
+                row.get(0)
+            })
+            .unwrap();
+        assert_eq!(error.as_deref(), Some("Protocol error: rejected"));
+
+        // Two hours later the first rows are past retention, but the last
+        // quality transition of the object stays
+        historian
+            .record_at(1, std::slice::from_ref(&point), at(7200))
+            .unwrap();
+        assert_eq!(count(&historian, "points"), 1);
+        assert_eq!(count(&historian, "commands"), 0);
+        assert_eq!(count(&historian, "quality_transitions"), 1);
+
+        // Reopened, the known quality is not recorded as a transition again
+        drop(historian);
+        let mut historian = SqliteHistorian::open(&path).unwrap();
+        historian.record_at(1, &[point], at(7201)).unwrap();
+        assert_eq!(count(&historian, "quality_transitions"), 1);
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[test]
+    fn test_unusable_database() {
+        let name = format!("iec104-sqlite-{}-missing", std::process::id());
+        let dir = std::env::temp_dir().join(name);
+        let _ = std::fs::remove_dir_all(&dir);
+        let missing = SqliteHistorian::open(dir.join("history.db"));
+        assert!(matches!(missing, Err(Iec104Error::Io(_))));
+
+        let path = std::env::temp_dir().join(format!("iec104-sqlite-{}.txt", std::process::id()));
+        std::fs::write(&path, [b'x'; 1024]).unwrap();
+        let garbage = SqliteHistorian::open(&path);
+        let _ = std::fs::remove_file(&path);
+        assert!(matches!(garbage, Err(Iec104Error::Io(_))));
+
+        // A failed write is reported, not skipped
+        let mut historian = SqliteHistorian::open_in_memory().unwrap();
+        historian.connection().execute_batch("DROP TABLE points").unwrap();
+        let point = DataPoint::new(100, DataValue::Single(true));
+        assert!(historian.record_at(1, &[point], at(0)).is_err());
+    }
+}
@@ -0,0 +1,491 @@
+//! In-memory client and controlled station for integration tests.
+//!
+//! [`pair`] connects an [`Iec104Client`] to a [`TestServer`] over
+//! [`tokio::io::duplex`], so protocol behavior can be tested without
+//! sockets; [`TestServer::accept`] serves clients that connect by address
+//! instead. The server speaks APDUs: it confirms STARTDT, STOPDT and
+//! TESTFR, numbers the I-frames it sends, rejects what it cannot serve with
+//! a negative confirmation, reports end of initialization on request,
+//! optionally serves reset process commands and leaves everything else to
+//! the test.
+//!
+//! Enable the `testing` feature, typically in `[dev-dependencies]`, to use
+//! it outside this crate.
+//!
+//! # Example
+//!
+//! ```rust,ignore
+//! let (mut client, mut server) = testing::pair().await?;
+//! tokio::spawn(async move {
+//!     let interrogation = server.recv_asdu().await?;
+//!     server.respond(&interrogation, Cot::ActivationConfirm).await?;
+//!     server.send_asdu(measurements).await?;
+//!     server.respond(&interrogation, Cot::ActivationTermination).await
+//! });
+//! client.start_dt().await?;
+//! let points = client.general_interrogation_snapshot(1, timeout).await?;
+//! ```
+
+use std::borrow::Cow;
+use std::collections::HashSet;
+
+use futures::{SinkExt, StreamExt};
+use tokio::net::TcpListener;
+use tokio_util::codec::Framed;
+
+use crate::client::{ClientConfig, Iec104Client};
+use crate::codec::{Apdu, Iec104Codec};
+use crate::command::first_ioa;
+use crate::error::{Iec104Error, Result};
+use crate::transport::AsyncStream;
+use crate::types::{
+    Apci, Asdu, Coi, Cot, InfoObject, ResetProcessQualifier, TypeId, UFunction,
+};
+
+/// Buffer size of each direction of the in-memory connection.
+const DUPLEX_CAPACITY: usize = 64 * 1024;
+
+/// Broadcast common address, served by every station.
+const GLOBAL_ADDRESS: u16 = 0xFFFF;
+
+/// Create a client connected to a [`TestServer`], with a default
+/// configuration.
+///
+/// The connection is open but data transfer is not started.
+pub async fn pair() -> Result<(Iec104Client, TestServer)> {
+    pair_with(ClientConfig::new("duplex")).await
+}
+
+/// Create a client with `config` connected to a [`TestServer`].
+pub async fn pair_with(config: ClientConfig) -> Result<(Iec104Client, TestServer)> {
+    let (client_end, server_end) = tokio::io::duplex(DUPLEX_CAPACITY);
+    let mut client = Iec104Client::new(config);
+    client.connect_stream(client_end).await?;
+    Ok((client, TestServer::new(server_end)))
+}
+
+/// Application callback for reset process commands, see
+/// [`TestServer::on_reset_process`].
+type ResetHandler = Box<dyn FnMut(u16, ResetProcessQualifier) -> Option<Coi> + Send>;
+
+/// Controlled station end of a test connection.
+pub struct TestServer {
+    framed: Framed<Box<dyn AsyncStream>, Iec104Codec>,
+    send_seq: u16,
+    recv_seq: u16,
+    reset_handler: Option<ResetHandler>,
+    /// Common addresses served, all when `None`
+    common_addresses: Option<HashSet<u16>>,
+    /// Information object addresses served, all when `None`
+    ioas: Option<HashSet<u32>>,
+}
+
+impl std::fmt::Debug for TestServer {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("TestServer")
+            .field("send_seq", &self.send_seq)
+            .field("recv_seq", &self.recv_seq)
+            .field("reset_handler", &self.reset_handler.is_some())
+            .field("common_addresses", &self.common_addresses)
+            .field("ioas", &self.ioas)
+            .finish_non_exhaustive()
+    }
+}
+
+impl TestServer {
+    /// Accept the next connection on `listener`.
+    ///
+    /// For clients that cannot be handed a stream, such as a
+    /// [`RedundantClient`](crate::redundant::RedundantClient) failing over
+    /// between addresses.
+    pub async fn accept(listener: &TcpListener) -> Result<Self> {
+        let (socket, _) = listener.accept().await?;
+        Ok(Self::new(socket))
+    }
+
+    fn new(stream: impl AsyncStream) -> Self {
+        let stream: Box<dyn AsyncStream> = Box::new(stream);
+        Self {
+            framed: Framed::new(stream, Iec104Codec::new()),
+            send_seq: 0,
+            recv_seq: 0,
+            reset_handler: None,
+            common_addresses: None,
+            ioas: None,
+        }
+    }
+
+    /// Serve only the stations at `addresses`.
+    ///
+    /// ASDUs addressed to other stations are rejected with
+    /// [`Cot::UnknownCommonAddress`]; the global address 0xFFFF is always
+    /// served.
+    pub fn serve_common_addresses(&mut self, addresses: impl IntoIterator<Item = u16>) {
+        self.common_addresses = Some(addresses.into_iter().collect());
+    }
+
+    /// Serve only the information objects at `ioas`.
+    ///
+    /// Commands to other objects are rejected with [`Cot::UnknownIoa`];
+    /// station commands (IOA 0) are always served.
+    pub fn serve_ioas(&mut self, ioas: impl IntoIterator<Item = u32>) {
+        self.ioas = Some(ioas.into_iter().collect());
+    }
+
+    /// Serve reset process commands (C_RP_NA_1) like a station restarting.
+    ///
+    /// `handler` is called with the common address and QRP of each command,
+    /// which is then confirmed. When it returns a COI, end of initialization
+    /// (M_EI_NA_1) follows the confirmation. Such commands are no longer
+    /// returned by [`recv_asdu`](Self::recv_asdu).
+    pub fn on_reset_process<F>(&mut self, handler: F)
+    where
+        F: FnMut(u16, ResetProcessQualifier) -> Option<Coi> + Send + 'static,
+    {
+        self.reset_handler = Some(Box::new(handler));
+    }
+
+    /// Receive the next APDU of the client, as is.
+    ///
+    /// Fails once the client has closed the connection.
+    pub async fn recv_apdu(&mut self) -> Result<Apdu> {
+        let apdu = self.framed.next().await.unwrap_or_else(|| {
+            Err(Iec104Error::Connection(Cow::Borrowed(
+                "Client closed the connection",
+            )))
+        })?;
+        if let Apci::IFrame { send_seq, .. } = apdu.apci {
+            self.recv_seq = (send_seq + 1) % 32768;
+        }
+        Ok(apdu)
+    }
+
+    /// Receive the next ASDU of the client.
+    ///
+    /// STARTDT, STOPDT and TESTFR activations received meanwhile are
+    /// confirmed, and S-frames are skipped. ASDUs the station cannot serve
+    /// are mirrored back negatively with COT 44-47 (unknown type, cause of
+    /// transmission, common address or IOA), and reset process commands are
+    /// served once [`on_reset_process`](Self::on_reset_process) is set.
+    pub async fn recv_asdu(&mut self) -> Result<Asdu> {
+        loop {
+            let apdu = self.recv_apdu().await?;
+            if let Some(asdu) = apdu.asdu {
+                if let Some(cot) = self.rejection(&asdu) {
+                    self.send_asdu(asdu.mirror(cot, true)).await?;
+                    continue;
+                }
+                if asdu.header.type_id == TypeId::ResetProcess && self.reset_handler.is_some() {
+                    self.reset_process(&asdu).await?;
+                    continue;
+                }
+                return Ok(asdu);
+            }
+            let confirmation = match apdu.apci {
+                Apci::UFrame {
+                    function: UFunction::StartDtAct,
+                } => UFunction::StartDtCon,
+                Apci::UFrame {
+                    function: UFunction::StopDtAct,
+                } => UFunction::StopDtCon,
+                Apci::UFrame {
+                    function: UFunction::TestFrAct,
+                } => UFunction::TestFrCon,
+                _ => continue,
+            };
+            self.send_apdu(Apdu::u_frame(confirmation)).await?;
+        }
+    }
+
+    /// Cause of a negative confirmation for an ASDU the station cannot serve.
+    fn rejection(&self, asdu: &Asdu) -> Option<Cot> {
+        let header = &asdu.header;
+        if !header.type_id.is_control() && !header.type_id.is_file_transfer() {
+            return Some(Cot::UnknownTypeId);
+        }
+        let cot_known = match header.type_id {
+            TypeId::ReadCommand => header.cot == Cot::Request,
+            type_id if type_id.is_file_transfer() => {
+                matches!(header.cot, Cot::Request | Cot::FileTransfer)
+            }
+            _ => matches!(header.cot, Cot::Activation | Cot::Deactivation),
+        };
+        if !cot_known {
+            return Some(Cot::UnknownCot);
+        }
+        if let Some(addresses) = &self.common_addresses {
+            let address = header.common_address;
+            if address != GLOBAL_ADDRESS && !addresses.contains(&address) {
+                return Some(Cot::UnknownCommonAddress);
+            }
+        }
+        if let (Some(ioas), Some(ioa)) = (&self.ioas, first_ioa(asdu)) {
+            if ioa != 0 && !ioas.contains(&ioa) {
+                return Some(Cot::UnknownIoa);
+            }
+        }
+        None
+    }
+
+    /// Confirm a reset process command, then report end of initialization
+    /// if the handler asks for it.
+    async fn reset_process(&mut self, command: &Asdu) -> Result<()> {
+        let common_address = command.header.common_address;
+        let qrp = match command.info_objects()?.first() {
+            Some((_, InfoObject::ResetProcess { qrp })) => *qrp,
+            _ => return Err(Iec104Error::invalid_asdu_static("Reset process without QRP")),
+        };
+        let coi = match self.reset_handler.as_mut() {
+            Some(handler) => handler(common_address, qrp),
+            None => None,
+        };
+        self.respond(command, Cot::ActivationConfirm).await?;
+        match coi {
+            Some(coi) => self.end_of_init(common_address, coi).await,
+            None => Ok(()),
+        }
+    }
+
+    /// Send an APDU as is, without numbering it.
+    pub async fn send_apdu(&mut self, apdu: Apdu) -> Result<()> {
+        self.framed.send(apdu).await
+    }
+
+    /// Send an ASDU in the next I-frame, acknowledging everything received.
+    pub async fn send_asdu(&mut self, asdu: Asdu) -> Result<()> {
+        let apdu = Apdu::i_frame(self.send_seq, self.recv_seq, asdu);
+        self.send_seq = (self.send_seq + 1) % 32768;
+        self.send_apdu(apdu).await
+    }
+
+    /// Report end of initialization (M_EI_NA_1) of station `common_address`,
+    /// as after startup or a reset process command.
+    pub async fn end_of_init(&mut self, common_address: u16, coi: Coi) -> Result<()> {
+        self.send_asdu(Asdu::end_of_init(common_address, coi)).await
+    }
+
+    /// Answer a command of the client with the same ASDU and another cause
+    /// of transmission, e.g. [`Cot::ActivationConfirm`].
+    pub async fn respond(&mut self, command: &Asdu, cot: Cot) -> Result<()> {
+        let mut response = command.clone();
+        response.header.cot = cot;
+        self.send_asdu(response).await
+    }
+
+    /// Acknowledge the I-frames received so far with an S-frame.
+    pub async fn acknowledge(&mut self) -> Result<()> {
+        self.send_apdu(Apdu::s_frame(self.recv_seq)).await
+    }
+
+    /// Close the connection.
+    pub async fn close(mut self) -> Result<()> {
+        self.framed.close().await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::{AsduHeader, DataValue};
+    use bytes::Bytes;
+    use std::time::Duration;
+
+    #[tokio::test]
+    async fn test_pair() {
+        let (mut client, mut server) = pair().await.unwrap();
+        let station = tokio::spawn(async move {
+            let interrogation = server.recv_asdu().await?;
+            assert_eq!(interrogation.header.type_id, TypeId::InterrogationCommand);
+            server
+                .respond(&interrogation, Cot::ActivationConfirm)
+                .await?;
+            let header = AsduHeader::new(TypeId::SinglePoint, 1, Cot::InterrogatedByStation, 1);
+            let mut asdu = Asdu::new(header);
+            asdu.raw_data = Bytes::from_static(&[100, 0, 0, 0x01]);
+            server.send_asdu(asdu).await?;
+            server
+                .respond(&interrogation, Cot::ActivationTermination)
+                .await?;
+            // Serve until the client goes away
+            while server.recv_apdu().await.is_ok() {}
+            Ok::<_, Iec104Error>(())
+        });
+
+        client.start_dt().await.unwrap();
+        let points = client
+            .general_interrogation_snapshot(1, Duration::from_secs(5))
+            .await
+            .unwrap();
+        assert_eq!(points.len(), 1);
+        assert_eq!(points[0].ioa, 100);
+        assert_eq!(points[0].value, DataValue::Single(true));
+        drop(client);
+        station.await.unwrap().unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_reset_process() {
+        use crate::client::Iec104Event;
+        use crate::types::InitCause;
+
+        let (mut client, mut server) = pair().await.unwrap();
+        let (seen_tx, mut seen_rx) = tokio::sync::mpsc::unbounded_channel();
+        server.on_reset_process(move |common_address, qrp| {
+            seen_tx.send((common_address, qrp)).unwrap();
+            (qrp == ResetProcessQualifier::GeneralReset).then(|| Coi::new(InitCause::RemoteReset))
+        });
+        let station = tokio::spawn(async move {
+            // Reset process commands never reach the test
+            let other = server.recv_asdu().await;
+            assert!(other.is_err(), "unexpected ASDU {:?}", other);
+        });
+
+        let mut events = client.subscribe().unwrap();
+        client.start_dt().await.unwrap();
+        let (handle, _task) = client.spawn();
+        handle
+            .reset_process(1, ResetProcessQualifier::ResetEventBuffer)
+            .await
+            .unwrap()
+            .confirmed()
+            .await
+            .unwrap();
+        assert_eq!(
+            seen_rx.recv().await.unwrap(),
+            (1, ResetProcessQualifier::ResetEventBuffer)
+        );
+
+        handle
+            .dangerous_reset_process(2, ResetProcessQualifier::GeneralReset)
+            .await
+            .unwrap()
+            .confirmed()
+            .await
+            .unwrap();
+        assert_eq!(
+            seen_rx.recv().await.unwrap(),
+            (2, ResetProcessQualifier::GeneralReset)
+        );
+        loop {
+            if let Iec104Event::EndOfInitialization { common_address, coi } =
+                events.recv().await.unwrap().event
+            {
+                assert_eq!(common_address, 2);
+                assert_eq!(coi.cause, InitCause::RemoteReset);
+                break;
+            }
+        }
+
+        drop(handle);
+        station.await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_negative_confirmations() {
+        let (master_end, station_end) = tokio::io::duplex(DUPLEX_CAPACITY);
+        let mut master = Framed::new(master_end, Iec104Codec::new());
+        let mut server = TestServer::new(station_end);
+        server.serve_common_addresses([1]);
+        server.serve_ioas([100]);
+        let station = tokio::spawn(async move { server.recv_asdu().await });
+
+        let command = |type_id, cot, common_address, ioa: u8| {
+            let mut asdu = Asdu::new(AsduHeader::new(type_id, 1, cot, common_address));
+            asdu.raw_data = Bytes::copy_from_slice(&[ioa, 0, 0, 0x01]);
+            asdu
+        };
+        // C_RD_NA_1 carries the IOA only
+        let mut read = Asdu::new(AsduHeader::new(TypeId::ReadCommand, 1, Cot::Activation, 1));
+        read.raw_data = Bytes::from_static(&[100, 0, 0]);
+        let rejected = [
+            (command(TypeId::SinglePoint, Cot::Spontaneous, 1, 100), Cot::UnknownTypeId),
+            (command(TypeId::Other(200), Cot::Activation, 1, 100), Cot::UnknownTypeId),
+            (command(TypeId::SingleCommand, Cot::Spontaneous, 1, 100), Cot::UnknownCot),
+            (read, Cot::UnknownCot),
+            (command(TypeId::SingleCommand, Cot::Activation, 2, 100), Cot::UnknownCommonAddress),
+            (command(TypeId::SingleCommand, Cot::Activation, 1, 101), Cot::UnknownIoa),
+        ];
+        for (seq, (asdu, cot)) in rejected.into_iter().enumerate() {
+            master.send(Apdu::i_frame(seq as u16, 0, asdu.clone())).await.unwrap();
+            let reply = master.next().await.unwrap().unwrap().asdu.unwrap();
+            assert_eq!(reply.header.cot, cot);
+            assert!(reply.header.negative);
+            assert_eq!(reply.header.type_id, asdu.header.type_id);
+            assert_eq!(reply.raw_data, asdu.raw_data);
+        }
+
+        // Served: station-wide commands and the global address
+        let mut interrogation = Asdu::interrogation_command(0xFFFF, 20);
+        interrogation.header.originator = 0;
+        master.send(Apdu::i_frame(6, 0, interrogation.clone())).await.unwrap();
+        let served = station.await.unwrap().unwrap();
+        assert_eq!(served.header, interrogation.header);
+        assert_eq!(served.raw_data.as_ref(), &[0, 0, 0, 20]);
+    }
+
+    #[tokio::test]
+    async fn test_command_to_unknown_ioa_rejected() {
+        let (mut client, mut server) = pair().await.unwrap();
+        server.serve_ioas([100]);
+        let station = tokio::spawn(async move {
+            let served = server.recv_asdu().await?;
+            server.respond(&served, Cot::ActivationConfirm).await?;
+            while server.recv_apdu().await.is_ok() {}
+            Ok::<_, Iec104Error>(())
+        });
+
+        client.start_dt().await.unwrap();
+        let (handle, _task) = client.spawn();
+        let qualifier = crate::types::CommandQualifier::EXECUTE;
+        let result = handle.single_command(1, 101, true, qualifier).await.unwrap().confirmed().await;
+        assert!(matches!(
+            result,
+            Err(Iec104Error::CommandRejected {
+                ioa: 101,
+                cot: Cot::UnknownIoa,
+                ..
+            })
+        ));
+        let served = handle.single_command(1, 100, true, qualifier).await.unwrap();
+        served.confirmed().await.unwrap();
+        drop(handle);
+        station.await.unwrap().unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_end_of_init() {
+        use crate::client::Iec104Event;
+        use crate::types::InitCause;
+
+        let (mut client, mut server) = pair().await.unwrap();
+        let station = tokio::spawn(async move {
+            // Confirm STARTDT, then report the startup
+            server.recv_apdu().await?;
+            server.send_apdu(Apdu::u_frame(UFunction::StartDtCon)).await?;
+            let coi = Coi {
+                cause: InitCause::LocalPowerOn,
+                parameters_changed: true,
+            };
+            server.end_of_init(7, coi).await?;
+            while server.recv_apdu().await.is_ok() {}
+            Ok::<_, Iec104Error>(())
+        });
+
+        client.start_dt().await.unwrap();
+        let event = loop {
+            if let Some(event) = client.poll().await.unwrap() {
+                break event;
+            }
+        };
+        match event {
+            Iec104Event::EndOfInitialization { common_address, coi } => {
+                assert_eq!(common_address, 7);
+                assert_eq!(coi.cause, InitCause::LocalPowerOn);
+                assert!(coi.parameters_changed);
+            }
+            other => panic!("Expected EndOfInitialization, got {:?}", other),
+        }
+        drop(client);
+        station.await.unwrap().unwrap();
+    }
+}
@@ -0,0 +1,502 @@
+//! TLS for the client connection (IEC 62351-3).
+//!
+//! IEC 62351-3 requires mutual authentication against the operator's own
+//! PKI: the client presents a certificate, and the server certificate is
+//! verified against pinned CA certificates rather than a public root store.
+//! [`TlsConfig`] holds that material in PEM form and is set with
+//! [`ClientConfig::tls`](crate::ClientConfig::tls). The material is read on
+//! every connect and can be replaced at runtime with [`TlsConfig::reload`].
+
+use std::borrow::Cow;
+use std::sync::{Arc, PoisonError, RwLock};
+
+use tokio::net::TcpStream;
+use tokio_rustls::client::TlsStream;
+use tokio_rustls::rustls::client::danger::{
+    HandshakeSignatureValid, ServerCertVerified, ServerCertVerifier,
+};
+use tokio_rustls::rustls::client::WebPkiServerVerifier;
+use tokio_rustls::rustls::crypto::ring;
+use tokio_rustls::rustls::pki_types::pem::PemObject;
+use tokio_rustls::rustls::pki_types::{
+    CertificateDer, CertificateRevocationListDer, PrivateKeyDer, ServerName, UnixTime,
+};
+use tokio_rustls::rustls::{self, DigitallySignedStruct, RootCertStore, SignatureScheme};
+use tokio_rustls::TlsConnector;
+
+use crate::error::{Iec104Error, Result};
+
+/// TLS settings of a client connection.
+///
+/// # Example
+///
+/// ```rust,ignore
+/// let tls = TlsConfig::new(std::fs::read("ca.pem")?)
+///     .client_identity(std::fs::read("client.pem")?, std::fs::read("client.key")?)
+///     .accept_peer_name("rtu-17.substation.example");
+/// let config = ClientConfig::new("10.0.0.17:19998").tls(tls.clone());
+///
+/// // Later, after the certificates were rotated on disk
+/// tls.reload(&TlsConfig::new(std::fs::read("ca.pem")?)
+///     .client_identity(std::fs::read("client.pem")?, std::fs::read("client.key")?))?;
+/// ```
+///
+/// Clones share the certificates, key and CRLs, so a reload reaches every
+/// client holding a clone. The builder methods give a configuration its own
+/// copy.
+#[derive(Debug, Clone)]
+pub struct TlsConfig {
+    material: Arc<RwLock<Material>>,
+    server_name: Option<String>,
+    peer_names: Vec<String>,
+}
+
+/// Certificates, key and CRLs, PEM encoded.
+#[derive(Debug, Clone, Default)]
+struct Material {
+    ca: Vec<u8>,
+    identity: Option<(Vec<u8>, Vec<u8>)>,
+    crls: Vec<u8>,
+}
+
+impl TlsConfig {
+    /// Trust only the CA certificates in `ca_pem` to verify the server.
+    pub fn new(ca_pem: impl Into<Vec<u8>>) -> Self {
+        let material = Material {
+            ca: ca_pem.into(),
+            ..Material::default()
+        };
+        Self {
+            material: Arc::new(RwLock::new(material)),
+            server_name: None,
+            peer_names: Vec::new(),
+        }
+    }
+
+    /// Present a client certificate chain (leaf first) and its private key,
+    /// both PEM encoded.
+    pub fn client_identity(
+        self,
+        cert_chain_pem: impl Into<Vec<u8>>,
+        key_pem: impl Into<Vec<u8>>,
+    ) -> Self {
+        let identity = (cert_chain_pem.into(), key_pem.into());
+        self.with_material(|material| material.identity = Some(identity))
+    }
+
+    /// Reject server certificates revoked by the CRLs in `crl_pem`.
+    ///
+    /// Once CRLs are given, every certificate of the server chain must be
+    /// covered by one of them.
+    pub fn crl(self, crl_pem: impl AsRef<[u8]>) -> Self {
+        self.with_material(|material| material.crls.extend_from_slice(crl_pem.as_ref()))
+    }
+
+    /// Name sent with SNI and checked against the server certificate.
+    ///
+    /// Defaults to the host part of [`ClientConfig::address`](crate::ClientConfig::address).
+    pub fn server_name(mut self, name: impl Into<String>) -> Self {
+        self.server_name = Some(name.into());
+        self
+    }
+
+    /// Accept a server certificate whose subjectAltName matches `name`
+    /// (a DNS name or IP address).
+    ///
+    /// Once any name is accepted this way, the server certificate must
+    /// match one of them and the server name is no longer checked; useful
+    /// when stations are addressed by IP but certified by name.
+    ///
+    /// Only the subjectAltName is matched, never the subject common name:
+    /// like the [`server_name`](Self::server_name) check, matching is left
+    /// to webpki, which follows RFC 6125 in ignoring the CN, and a second
+    /// name parser would be one more place for certificate handling to go
+    /// wrong. Station certificates carrying their name only in the CN must
+    /// be reissued with a subjectAltName.
+    pub fn accept_peer_name(mut self, name: impl Into<String>) -> Self {
+        self.peer_names.push(name.into());
+        self
+    }
+
+    /// Replace the CA certificates, client identity and CRLs with those of
+    /// `material`; the server name settings stay as they are.
+    ///
+    /// Connections opened afterwards by any client holding a clone of this
+    /// configuration use the new material, while established sessions
+    /// continue undisturbed. Unusable material is rejected with
+    /// [`Iec104Error::TlsConfig`] and the previous material stays in effect.
+    pub fn reload(&self, material: &TlsConfig) -> Result<()> {
+        let material = material.material();
+        self.build(&material)?;
+        *self
+            .material
+            .write()
+            .unwrap_or_else(PoisonError::into_inner) = material;
+        Ok(())
+    }
+
+    fn material(&self) -> Material {
+        self.material
+            .read()
+            .unwrap_or_else(PoisonError::into_inner)
+            .clone()
+    }
+
+    fn with_material(mut self, update: impl FnOnce(&mut Material)) -> Self {
+        let mut material = self.material();
+        update(&mut material);
+        self.material = Arc::new(RwLock::new(material));
+        self
+    }
+
+    /// Perform the TLS handshake over `stream`, connected to `address`.
+    pub(crate) async fn connect(
+        &self,
+        address: &str,
+        stream: TcpStream,
+    ) -> Result<TlsStream<TcpStream>> {
+        let name = self.server_name.as_deref().unwrap_or_else(|| host(address));
+        let name = server_name(name)?;
+        let connector = TlsConnector::from(Arc::new(self.client_config()?));
+        connector
+            .connect(name, stream)
+            .await
+            .map_err(|e| Iec104Error::TlsHandshake(Cow::Owned(e.to_string())))
+    }
+
+    fn client_config(&self) -> Result<rustls::ClientConfig> {
+        self.build(&self.material())
+    }
+
+    fn build(&self, material: &Material) -> Result<rustls::ClientConfig> {
+        let provider = Arc::new(ring::default_provider());
+
+        let mut roots = RootCertStore::empty();
+        for cert in CertificateDer::pem_slice_iter(&material.ca) {
+            let cert = cert.map_err(|e| config_error(format!("CA certificate: {e}")))?;
+            roots
+                .add(cert)
+                .map_err(|e| config_error(format!("CA certificate: {e}")))?;
+        }
+        if roots.is_empty() {
+            return Err(Iec104Error::TlsConfig(Cow::Borrowed("No CA certificate")));
+        }
+        let crls = CertificateRevocationListDer::pem_slice_iter(&material.crls)
+            .collect::<std::result::Result<Vec<_>, _>>()
+            .map_err(|e| config_error(format!("CRL: {e}")))?;
+        let verifier =
+            WebPkiServerVerifier::builder_with_provider(Arc::new(roots), provider.clone())
+                .with_crls(crls)
+                .build()
+                .map_err(|e| config_error(e.to_string()))?;
+
+        let builder = rustls::ClientConfig::builder_with_provider(provider)
+            .with_safe_default_protocol_versions()
+            .map_err(|e| config_error(e.to_string()))?;
+        let builder = if self.peer_names.is_empty() {
+            builder.with_webpki_verifier(verifier)
+        } else {
+            let names = self
+                .peer_names
+                .iter()
+                .map(|name| server_name(name))
+                .collect::<Result<_>>()?;
+            builder
+                .dangerous()
+                .with_custom_certificate_verifier(Arc::new(PeerNameVerifier {
+                    inner: verifier,
+                    names,
+                }))
+        };
+
+        match &material.identity {
+            Some((chain, key)) => {
+                let chain = CertificateDer::pem_slice_iter(chain)
+                    .collect::<std::result::Result<Vec<_>, _>>()
+                    .map_err(|e| config_error(format!("Client certificate: {e}")))?;
+                let key = PrivateKeyDer::from_pem_slice(key)
+                    .map_err(|e| config_error(format!("Client key: {e}")))?;
+                builder
+                    .with_client_auth_cert(chain, key)
+                    .map_err(|e| config_error(e.to_string()))
+            }
+            None => Ok(builder.with_no_client_auth()),
+        }
+    }
+}
+
+fn config_error(message: String) -> Iec104Error {
+    Iec104Error::TlsConfig(Cow::Owned(message))
+}
+
+fn server_name(name: &str) -> Result<ServerName<'static>> {
+    ServerName::try_from(name.to_owned())
+        .map_err(|_| config_error(format!("Invalid server name: {name}")))
+}
+
+/// Host part of a `host:port` address.
+fn host(address: &str) -> &str {
+    let host = address.rsplit_once(':').map_or(address, |(host, _)| host);
+    host.trim_start_matches('[').trim_end_matches(']')
+}
+
+/// Verifies the chain as usual but matches the certificate against the
+/// accepted peer names instead of the server name.
+#[derive(Debug)]
+struct PeerNameVerifier {
+    inner: Arc<WebPkiServerVerifier>,
+    names: Vec<ServerName<'static>>,
+}
+
+impl ServerCertVerifier for PeerNameVerifier {
+    fn verify_server_cert(
+        &self,
+        end_entity: &CertificateDer<'_>,
+        intermediates: &[CertificateDer<'_>],
+        _server_name: &ServerName<'_>,
+        ocsp_response: &[u8],
+        now: UnixTime,
+    ) -> std::result::Result<ServerCertVerified, rustls::Error> {
+        let mut error = rustls::Error::General("No accepted peer name".into());
+        for name in &self.names {
+            match self
+                .inner
+                .verify_server_cert(end_entity, intermediates, name, ocsp_response, now)
+            {
+                Ok(verified) => return Ok(verified),
+                Err(e) => error = e,
+            }
+        }
+        Err(error)
+    }
+
+    fn verify_tls12_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> std::result::Result<HandshakeSignatureValid, rustls::Error> {
+        self.inner.verify_tls12_signature(message, cert, dss)
+    }
+
+    fn verify_tls13_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> std::result::Result<HandshakeSignatureValid, rustls::Error> {
+        self.inner.verify_tls13_signature(message, cert, dss)
+    }
+
+    fn supported_verify_schemes(&self) -> Vec<SignatureScheme> {
+        self.inner.supported_verify_schemes()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::client::{ClientConfig, ConnectionState, Iec104Client};
+    use crate::codec::{Apdu, Iec104Codec};
+    use crate::types::UFunction;
+    use futures::{SinkExt, StreamExt};
+    use rustls::server::WebPkiClientVerifier;
+    use tokio::net::TcpListener;
+    use tokio_rustls::TlsAcceptor;
+    use tokio_util::codec::Framed;
+
+    /// A CA with one server and one client certificate, all PEM encoded.
+    struct Pki {
+        ca: &'static str,
+        server: (&'static str, &'static str),
+        client: (&'static str, &'static str),
+    }
+
+    /// Test PKI of CA `a` or `b`, generated by `testdata/tls/generate.sh`.
+    ///
+    /// The server certificate is issued to rtu.example.
+    macro_rules! pki {
+        ($ca:literal) => {
+            Pki {
+                ca: include_str!(concat!("../testdata/tls/", $ca, "/ca.pem")),
+                server: (
+                    include_str!(concat!("../testdata/tls/", $ca, "/server.pem")),
+                    include_str!(concat!("../testdata/tls/", $ca, "/server.key")),
+                ),
+                client: (
+                    include_str!(concat!("../testdata/tls/", $ca, "/client.pem")),
+                    include_str!(concat!("../testdata/tls/", $ca, "/client.key")),
+                ),
+            }
+        };
+    }
+
+    /// CRL of CA `a` revoking its server certificate.
+    const SERVER_CRL: &str = include_str!("../testdata/tls/a/server.crl");
+
+    /// TLS acceptor requiring a client certificate issued by `pki`.
+    fn acceptor(pki: &Pki) -> TlsAcceptor {
+        let provider = Arc::new(ring::default_provider());
+        let mut roots = RootCertStore::empty();
+        roots
+            .add(CertificateDer::from_pem_slice(pki.ca.as_bytes()).unwrap())
+            .unwrap();
+        let verifier =
+            WebPkiClientVerifier::builder_with_provider(Arc::new(roots), provider.clone())
+                .build()
+                .unwrap();
+        let chain = vec![CertificateDer::from_pem_slice(pki.server.0.as_bytes()).unwrap()];
+        let key = PrivateKeyDer::from_pem_slice(pki.server.1.as_bytes()).unwrap();
+        let config = rustls::ServerConfig::builder_with_provider(provider)
+            .with_safe_default_protocol_versions()
+            .unwrap()
+            .with_client_cert_verifier(verifier)
+            .with_single_cert(chain, key)
+            .unwrap();
+        TlsAcceptor::from(Arc::new(config))
+    }
+
+    /// Accept one TLS connection and confirm STARTDT on it.
+    async fn serve(listener: TcpListener, acceptor: TlsAcceptor) {
+        let (socket, _) = listener.accept().await.unwrap();
+        let Ok(stream) = acceptor.accept(socket).await else {
+            return;
+        };
+        assert!(stream.get_ref().1.peer_certificates().is_some());
+        let mut server = Framed::new(stream, Iec104Codec::new());
+        server.next().await.unwrap().unwrap();
+        server
+            .send(Apdu::u_frame(UFunction::StartDtCon))
+            .await
+            .unwrap();
+        while let Some(Ok(_)) = server.next().await {}
+    }
+
+    #[test]
+    fn test_host() {
+        assert_eq!(host("10.0.0.1:19998"), "10.0.0.1");
+        assert_eq!(host("rtu.example:19998"), "rtu.example");
+        assert_eq!(host("[::1]:19998"), "::1");
+        assert_eq!(host("rtu.example"), "rtu.example");
+    }
+
+    #[tokio::test]
+    async fn test_mutual_tls_session() {
+        let pki = pki!("a");
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(serve(listener, acceptor(&pki)));
+
+        let tls = TlsConfig::new(pki.ca)
+            .client_identity(pki.client.0, pki.client.1)
+            .accept_peer_name("rtu.example");
+        let mut client = Iec104Client::new(ClientConfig::new(addr.to_string()).tls(tls));
+        client.connect().await.unwrap();
+        client.start_dt().await.unwrap();
+        assert_eq!(client.state(), ConnectionState::Active);
+        assert_eq!(client.session_info().unwrap().peer_address, addr);
+    }
+
+    #[tokio::test]
+    async fn test_server_identity_is_checked() {
+        let pki = pki!("a");
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let acceptor = acceptor(&pki);
+        tokio::spawn(async move {
+            for _ in 0..2 {
+                let (socket, _) = listener.accept().await.unwrap();
+                let _ = acceptor.accept(socket).await;
+            }
+        });
+
+        // Certified as rtu.example, not as 127.0.0.1
+        let tls = TlsConfig::new(pki.ca).client_identity(pki.client.0, pki.client.1);
+        let mut client = Iec104Client::new(ClientConfig::new(addr.to_string()).tls(tls));
+        assert!(matches!(
+            client.connect().await,
+            Err(Iec104Error::TlsHandshake(_))
+        ));
+        assert_eq!(client.state(), ConnectionState::Disconnected);
+
+        // Issued by a CA that is not trusted
+        let other = pki!("b");
+        let tls = TlsConfig::new(other.ca)
+            .client_identity(other.client.0, other.client.1)
+            .accept_peer_name("rtu.example");
+        let mut client = Iec104Client::new(ClientConfig::new(addr.to_string()).tls(tls));
+        assert!(matches!(
+            client.connect().await,
+            Err(Iec104Error::TlsHandshake(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_revoked_server_certificate() {
+        let pki = pki!("a");
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(serve(listener, acceptor(&pki)));
+
+        let tls = TlsConfig::new(pki.ca)
+            .client_identity(pki.client.0, pki.client.1)
+            .crl(SERVER_CRL)
+            .accept_peer_name("rtu.example");
+        let mut client = Iec104Client::new(ClientConfig::new(addr.to_string()).tls(tls));
+        assert!(matches!(
+            client.connect().await,
+            Err(Iec104Error::TlsHandshake(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_reload_keeps_sessions() {
+        let old = pki!("a");
+        let new = pki!("b");
+        let before = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let after = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let before_addr = before.local_addr().unwrap();
+        let after_addr = after.local_addr().unwrap();
+        tokio::spawn(serve(before, acceptor(&old)));
+        tokio::spawn(serve(after, acceptor(&new)));
+
+        let tls = TlsConfig::new(old.ca)
+            .client_identity(old.client.0, old.client.1)
+            .accept_peer_name("rtu.example");
+        let mut first =
+            Iec104Client::new(ClientConfig::new(before_addr.to_string()).tls(tls.clone()));
+        let mut second =
+            Iec104Client::new(ClientConfig::new(after_addr.to_string()).tls(tls.clone()));
+        first.connect().await.unwrap();
+        first.start_dt().await.unwrap();
+
+        assert!(matches!(
+            tls.reload(&TlsConfig::new("not a certificate")),
+            Err(Iec104Error::TlsConfig(_))
+        ));
+        let rotated = TlsConfig::new(new.ca).client_identity(new.client.0, new.client.1);
+        tls.reload(&rotated).unwrap();
+
+        // The rotated material reaches the clone held by the second client
+        second.connect().await.unwrap();
+        second.start_dt().await.unwrap();
+        first.poll().await.unwrap();
+        assert_eq!(first.state(), ConnectionState::Active);
+    }
+
+    #[test]
+    fn test_invalid_material() {
+        let tls = TlsConfig::new("not a certificate");
+        assert!(matches!(
+            tls.client_config(),
+            Err(Iec104Error::TlsConfig(_))
+        ));
+
+        let pki = pki!("a");
+        let tls = TlsConfig::new(pki.ca).client_identity(pki.client.0, "no key");
+        assert!(matches!(
+            tls.client_config(),
+            Err(Iec104Error::TlsConfig(_))
+        ));
+    }
+}
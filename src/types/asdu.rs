@@ -2,15 +2,20 @@
 //!
 //! ASDU contains the actual data (measurements, commands, etc.).
 
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
 use bytes::{BufMut, Bytes, BytesMut};
 
 use crate::error::{Iec104Error, Result};
-use crate::types::{Cot, TypeId};
+use crate::types::{
+    Coi, Cot, ParameterActivationQualifier, ParameterValue, Qcc, Qpm, ResetProcessQualifier, TypeId,
+};
 
 /// Variable Structure Qualifier (VSQ).
 ///
 /// Defines the structure of information objects in an ASDU.
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Vsq {
     /// Number of information objects (1-127)
     pub count: u8,
@@ -44,6 +49,9 @@ impl Vsq {
 /// IOA byte size (fixed at compile time for IEC 104)
 pub const IOA_SIZE: usize = 3;
 
+/// Fixed test bit pattern (FBP) of the test command.
+pub const TEST_BIT_PATTERN: u16 = 0x55AA;
+
 /// Information Object Address (IOA).
 ///
 /// 3-byte address identifying a specific data point.
@@ -113,6 +121,7 @@ impl std::fmt::Display for Ioa {
 
 /// ASDU header (fixed part).
 #[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct AsduHeader {
     /// Type identification
     pub type_id: TypeId,
@@ -147,14 +156,34 @@ impl AsduHeader {
 
     /// Parse ASDU header from bytes.
     ///
-    /// Returns the header and the number of bytes consumed.
+    /// Returns the header and the number of bytes consumed. Type
+    /// identifications of the private range (128-255) are accepted as
+    /// [`TypeId::Other`].
     #[inline]
     pub fn parse(data: &[u8]) -> Result<(Self, usize)> {
+        Self::parse_with(data, false)
+    }
+
+    /// Parse ASDU header from bytes, keeping an unknown type identification
+    /// as [`TypeId::Other`] instead of failing.
+    #[inline]
+    pub fn parse_lenient(data: &[u8]) -> Result<(Self, usize)> {
+        Self::parse_with(data, true)
+    }
+
+    fn parse_with(data: &[u8], lenient: bool) -> Result<(Self, usize)> {
         if data.len() < 6 {
             return Err(Iec104Error::invalid_asdu_static("ASDU header too short"));
         }
 
-        let type_id = TypeId::from_u8(data[0])?;
+        // The private range is always kept opaque; unassigned standard
+        // values only when lenient
+        let type_id = match TypeId::from_raw(data[0]) {
+            TypeId::Other(value) if !lenient && value < 128 => {
+                return Err(Iec104Error::UnknownTypeId(value));
+            }
+            type_id => type_id,
+        };
         let vsq = Vsq::from_u8(data[1]);
 
         // COT is in lower 6 bits, test flag in bit 7, negative in bit 6
@@ -202,6 +231,44 @@ impl AsduHeader {
     pub const fn encoded_size(&self) -> usize {
         6
     }
+
+    /// Length of the information objects implied by the type and VSQ.
+    ///
+    /// Returns `None` when it cannot be known from the header: for file
+    /// segments, which carry a variable number of octets, and for types
+    /// this crate does not know.
+    pub const fn expected_payload_len(&self) -> Option<usize> {
+        if matches!(self.type_id, TypeId::FileSegment | TypeId::Other(_)) {
+            return None;
+        }
+        let count = self.vsq.count as usize;
+        let element_size = self.type_id.element_size();
+        Some(if count == 0 {
+            0
+        } else if self.vsq.sequence {
+            3 + count * element_size
+        } else {
+            count * (3 + element_size)
+        })
+    }
+
+    /// Check the VSQ count against the length of the information objects.
+    fn check_payload_len(&self, actual: usize) -> Result<()> {
+        match self.expected_payload_len() {
+            Some(expected) if expected != actual => Err(Iec104Error::InvalidAsdu(
+                format!(
+                    "{} with {} object(s){} needs {} bytes of objects, got {}",
+                    self.type_id,
+                    self.vsq.count,
+                    if self.vsq.sequence { " in sequence" } else { "" },
+                    expected,
+                    actual
+                )
+                .into(),
+            )),
+            _ => Ok(()),
+        }
+    }
 }
 
 /// Single-point information value.
@@ -234,6 +301,7 @@ impl SinglePoint {
 
 /// Double-point information value.
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum DoublePointValue {
     /// Indeterminate or intermediate (00)
     Indeterminate = 0,
@@ -469,6 +537,7 @@ impl MeasuredValue {
 
 /// CP56Time2a timestamp (7 bytes).
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Cp56Time2a {
     /// Milliseconds (0-59999)
     pub milliseconds: u16,
@@ -534,6 +603,353 @@ impl Cp56Time2a {
         result[6] = self.year & 0x7F;
         result
     }
+
+    /// Build a timestamp from a system time, shifted by `utc_offset` seconds.
+    ///
+    /// Times before the Unix epoch clamp to 1970-01-01 00:00:00.000.
+    pub fn from_system_time(time: SystemTime, utc_offset: i32) -> Self {
+        let millis = time
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |elapsed| elapsed.as_millis() as i64)
+            + i64::from(utc_offset) * 1000;
+        Self::from_unix_millis(millis)
+    }
+
+    /// Current UTC time.
+    pub fn now() -> Self {
+        Self::from_system_time(SystemTime::now(), 0)
+    }
+
+    /// Format as an ISO 8601 date and time with milliseconds, e.g.
+    /// `2024-02-29T13:45:30.250`, reading the year as 20xx.
+    ///
+    /// No offset is written: the time is in whatever zone the station uses.
+    /// The invalid and summer time flags are not represented.
+    pub fn to_iso8601(&self) -> String {
+        format!(
+            "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}.{:03}",
+            2000 + u16::from(self.year),
+            self.month,
+            self.day,
+            self.hours,
+            self.minutes,
+            self.milliseconds / 1000,
+            self.milliseconds % 1000
+        )
+    }
+
+    /// Parse an ISO 8601 date and time as written by
+    /// [`to_iso8601`](Self::to_iso8601).
+    ///
+    /// Fractions of a second may have up to three digits or be left out, and
+    /// a trailing `Z` is accepted. The year must be in 2000..=2099; the day of
+    /// week is derived from the date.
+    pub fn parse_iso8601(text: &str) -> Result<Self> {
+        let invalid = || {
+            Iec104Error::InvalidAsdu(format!("Invalid ISO 8601 date and time: {:?}", text).into())
+        };
+        let number = |digits: &str| -> Result<u16> {
+            if digits.is_empty() || !digits.bytes().all(|b| b.is_ascii_digit()) {
+                return Err(invalid());
+            }
+            digits.parse().map_err(|_| invalid())
+        };
+
+        let body = text.strip_suffix('Z').unwrap_or(text);
+        let (date, time) = body.split_once('T').ok_or_else(invalid)?;
+        let mut date_parts = date.split('-');
+        let mut time_parts = time.split(':');
+        let (Some(year), Some(month), Some(day), None) = (
+            date_parts.next(),
+            date_parts.next(),
+            date_parts.next(),
+            date_parts.next(),
+        ) else {
+            return Err(invalid());
+        };
+        let (Some(hours), Some(minutes), Some(seconds), None) = (
+            time_parts.next(),
+            time_parts.next(),
+            time_parts.next(),
+            time_parts.next(),
+        ) else {
+            return Err(invalid());
+        };
+        let (seconds, fraction) = seconds.split_once('.').unwrap_or((seconds, "000"));
+        if year.len() != 4 || fraction.len() > 3 {
+            return Err(invalid());
+        }
+
+        let year = number(year)?;
+        let month = number(month)?;
+        let day = number(day)?;
+        let hours = number(hours)?;
+        let minutes = number(minutes)?;
+        let seconds = number(seconds)?;
+        let millis = number(fraction)? * 10u16.pow(3 - fraction.len() as u32);
+        if !(2000..=2099).contains(&year)
+            || !(1..=12).contains(&month)
+            || !(1..=31).contains(&day)
+            || hours > 23
+            || minutes > 59
+            || seconds > 59
+        {
+            return Err(invalid());
+        }
+        let days = days_from_civil(i64::from(year), month as u8, day as u8);
+        if civil_from_days(days) != (i64::from(year), month as u8, day as u8) {
+            return Err(invalid());
+        }
+
+        Ok(Self {
+            milliseconds: seconds * 1000 + millis,
+            minutes: minutes as u8,
+            hours: hours as u8,
+            day: day as u8,
+            // 1970-01-01 was a Thursday
+            day_of_week: ((days + 3).rem_euclid(7) + 1) as u8,
+            month: month as u8,
+            year: (year - 2000) as u8,
+            invalid: false,
+            summer_time: false,
+        })
+    }
+
+    /// Build a timestamp from milliseconds since 1970-01-01 00:00:00.
+    fn from_unix_millis(millis: i64) -> Self {
+        let days = millis.div_euclid(86_400_000);
+        let of_day = millis.rem_euclid(86_400_000);
+        let (year, month, day) = civil_from_days(days);
+
+        Self {
+            milliseconds: (of_day % 60_000) as u16,
+            minutes: (of_day / 60_000 % 60) as u8,
+            hours: (of_day / 3_600_000) as u8,
+            day,
+            // 1970-01-01 was a Thursday
+            day_of_week: ((days + 3).rem_euclid(7) + 1) as u8,
+            month,
+            year: year.rem_euclid(100) as u8,
+            invalid: false,
+            summer_time: false,
+        }
+    }
+
+    /// Milliseconds since 1970-01-01 00:00:00, reading the year as 20xx.
+    pub(crate) fn unix_millis(&self) -> i64 {
+        let days = days_from_civil(2000 + i64::from(self.year), self.month, self.day);
+        days * 86_400_000
+            + i64::from(self.hours) * 3_600_000
+            + i64::from(self.minutes) * 60_000
+            + i64::from(self.milliseconds)
+    }
+}
+
+/// CP24Time2a time tag (3 bytes): minutes and milliseconds only.
+///
+/// Carried by the M_xx_TA_1 types; the hour and date have to be inferred
+/// from a full clock, see [`complete`](Self::complete).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Cp24Time2a {
+    /// Milliseconds (0-59999)
+    pub milliseconds: u16,
+    /// Minutes (0-59)
+    pub minutes: u8,
+    /// Invalid flag
+    pub invalid: bool,
+}
+
+impl Cp24Time2a {
+    /// Parse from 3 bytes.
+    #[inline]
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self> {
+        if bytes.len() < 3 {
+            return Err(Iec104Error::invalid_asdu_static("CP24Time2a too short"));
+        }
+
+        Ok(Self {
+            milliseconds: bytes[0] as u16 | ((bytes[1] as u16) << 8),
+            minutes: bytes[2] & 0x3F,
+            invalid: (bytes[2] & 0x80) != 0,
+        })
+    }
+
+    /// Encode to 3 bytes.
+    #[inline]
+    pub const fn to_bytes(&self) -> [u8; 3] {
+        [
+            (self.milliseconds & 0xFF) as u8,
+            ((self.milliseconds >> 8) & 0xFF) as u8,
+            (self.minutes & 0x3F) | if self.invalid { 0x80 } else { 0 },
+        ]
+    }
+
+    /// Complete the tag against a full `reference` clock.
+    ///
+    /// Picks the time with these minutes and milliseconds closest to the
+    /// reference, so a tag just before or after an hour boundary lands in
+    /// the right hour (and day). The summer time flag is taken from the
+    /// reference.
+    pub fn complete(&self, reference: &Cp56Time2a) -> Cp56Time2a {
+        const HOUR: i64 = 3_600_000;
+        let reference_millis = reference.unix_millis();
+        let mut millis = reference_millis - reference_millis.rem_euclid(HOUR)
+            + i64::from(self.minutes) * 60_000
+            + i64::from(self.milliseconds);
+        if millis - reference_millis > HOUR / 2 {
+            millis -= HOUR;
+        } else if reference_millis - millis > HOUR / 2 {
+            millis += HOUR;
+        }
+
+        Cp56Time2a {
+            invalid: self.invalid,
+            summer_time: reference.summer_time,
+            ..Cp56Time2a::from_unix_millis(millis)
+        }
+    }
+}
+
+/// CP16Time2a elapsed time (2 bytes), in milliseconds.
+///
+/// Used for the relay durations of protection events and the timing of
+/// file transfer.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+pub struct Cp16Time2a {
+    /// Milliseconds (0-59999)
+    pub milliseconds: u16,
+}
+
+impl Cp16Time2a {
+    /// Largest elapsed time the format is defined for.
+    pub const MAX_MILLISECONDS: u16 = 59_999;
+
+    /// Create an elapsed time of `milliseconds`.
+    #[inline]
+    pub const fn new(milliseconds: u16) -> Self {
+        Self { milliseconds }
+    }
+
+    /// Parse from 2 bytes.
+    #[inline]
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self> {
+        if bytes.len() < 2 {
+            return Err(Iec104Error::invalid_asdu_static("CP16Time2a too short"));
+        }
+        Ok(Self::new(bytes[0] as u16 | ((bytes[1] as u16) << 8)))
+    }
+
+    /// Encode to 2 bytes.
+    #[inline]
+    pub const fn to_bytes(&self) -> [u8; 2] {
+        self.milliseconds.to_le_bytes()
+    }
+
+    /// Build from a duration, saturating at [`MAX_MILLISECONDS`](Self::MAX_MILLISECONDS).
+    pub fn from_duration(duration: Duration) -> Self {
+        let millis = duration.as_millis().min(u128::from(Self::MAX_MILLISECONDS));
+        Self::new(millis as u16)
+    }
+
+    /// The elapsed time as a duration.
+    #[inline]
+    pub const fn as_duration(&self) -> Duration {
+        Duration::from_millis(self.milliseconds as u64)
+    }
+}
+
+impl From<Cp16Time2a> for Duration {
+    fn from(time: Cp16Time2a) -> Self {
+        time.as_duration()
+    }
+}
+
+/// Read the date and time; the year is 20xx and the day of week is ignored.
+#[cfg(feature = "chrono")]
+#[cfg_attr(docsrs, doc(cfg(feature = "chrono")))]
+impl TryFrom<Cp56Time2a> for chrono::NaiveDateTime {
+    type Error = Iec104Error;
+
+    fn try_from(time: Cp56Time2a) -> Result<Self> {
+        let seconds = u32::from(time.milliseconds / 1000);
+        let millis = u32::from(time.milliseconds % 1000);
+        let year = 2000 + i32::from(time.year);
+        chrono::NaiveDate::from_ymd_opt(year, time.month.into(), time.day.into())
+            .and_then(|date| {
+                date.and_hms_milli_opt(time.hours.into(), time.minutes.into(), seconds, millis)
+            })
+            .ok_or_else(|| Iec104Error::invalid_asdu_static("CP56Time2a is not a valid date"))
+    }
+}
+
+/// Read the date and time as UTC; see the `NaiveDateTime` conversion.
+#[cfg(feature = "chrono")]
+#[cfg_attr(docsrs, doc(cfg(feature = "chrono")))]
+impl TryFrom<Cp56Time2a> for chrono::DateTime<chrono::Utc> {
+    type Error = Iec104Error;
+
+    fn try_from(time: Cp56Time2a) -> Result<Self> {
+        chrono::NaiveDateTime::try_from(time).map(|naive| naive.and_utc())
+    }
+}
+
+/// Encode the date and time; the year is kept modulo 100 and a leap second
+/// folds into the 59th second.
+#[cfg(feature = "chrono")]
+#[cfg_attr(docsrs, doc(cfg(feature = "chrono")))]
+impl From<chrono::NaiveDateTime> for Cp56Time2a {
+    fn from(time: chrono::NaiveDateTime) -> Self {
+        use chrono::{Datelike, Timelike};
+
+        let millis = time.second() * 1000 + time.nanosecond() / 1_000_000;
+        Self {
+            milliseconds: millis.min(59_999) as u16,
+            minutes: time.minute() as u8,
+            hours: time.hour() as u8,
+            day: time.day() as u8,
+            day_of_week: time.weekday().number_from_monday() as u8,
+            month: time.month() as u8,
+            year: time.year().rem_euclid(100) as u8,
+            invalid: false,
+            summer_time: false,
+        }
+    }
+}
+
+/// Encode the date and time in UTC; see the `NaiveDateTime` conversion.
+#[cfg(feature = "chrono")]
+#[cfg_attr(docsrs, doc(cfg(feature = "chrono")))]
+impl From<chrono::DateTime<chrono::Utc>> for Cp56Time2a {
+    fn from(time: chrono::DateTime<chrono::Utc>) -> Self {
+        Self::from(time.naive_utc())
+    }
+}
+
+/// Convert a (year, month, day) civil date to days since 1970-01-01.
+fn days_from_civil(year: i64, month: u8, day: u8) -> i64 {
+    let year = year - i64::from(month <= 2);
+    let era = year.div_euclid(400);
+    let yoe = year.rem_euclid(400);
+    let month = i64::from(month);
+    let mp = if month > 2 { month - 3 } else { month + 9 };
+    let doy = (153 * mp + 2) / 5 + i64::from(day) - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    era * 146_097 + doe - 719_468
+}
+
+/// Convert days since 1970-01-01 to a (year, month, day) civil date.
+fn civil_from_days(days: i64) -> (i64, u8, u8) {
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = (doy - (153 * mp + 2) / 5 + 1) as u8;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 } as u8;
+    let year = yoe + era * 400 + i64::from(month <= 2);
+    (year, month, day)
 }
 
 /// Information object (generic container).
@@ -588,6 +1004,71 @@ impl Asdu {
         asdu
     }
 
+    /// Create a counter interrogation command ASDU (C_CI_NA_1).
+    pub fn counter_interrogation_command(common_address: u16, qcc: Qcc) -> Self {
+        let mut asdu = Self::new(AsduHeader::new(
+            TypeId::CounterInterrogation,
+            1,
+            Cot::Activation,
+            common_address,
+        ));
+        asdu.objects.push(InformationObject {
+            ioa: Ioa::new(0),
+            data: Bytes::copy_from_slice(&[qcc.as_u8()]),
+        });
+        asdu
+    }
+
+    /// Create a read command ASDU (C_RD_NA_1).
+    pub fn read_command(common_address: u16, ioa: u32) -> Self {
+        let mut asdu = Self::new(AsduHeader::new(
+            TypeId::ReadCommand,
+            1,
+            Cot::Request,
+            common_address,
+        ));
+        asdu.objects.push(InformationObject {
+            ioa: Ioa::new(ioa),
+            data: Bytes::new(),
+        });
+        asdu
+    }
+
+    /// Create a test command ASDU (C_TS_NA_1) carrying the fixed test pattern.
+    pub fn test_command(common_address: u16) -> Self {
+        let mut asdu = Self::new(AsduHeader::new(
+            TypeId::TestCommand,
+            1,
+            Cot::Activation,
+            common_address,
+        ));
+        asdu.objects.push(InformationObject {
+            ioa: Ioa::new(0),
+            data: Bytes::copy_from_slice(&TEST_BIT_PATTERN.to_le_bytes()),
+        });
+        asdu
+    }
+
+    /// Create a test command ASDU with time tag (C_TS_TA_1).
+    ///
+    /// `tsc` is the test sequence counter.
+    pub fn test_command_time(common_address: u16, tsc: u16, time: Cp56Time2a) -> Self {
+        let mut asdu = Self::new(AsduHeader::new(
+            TypeId::TestCommandTime56,
+            1,
+            Cot::Activation,
+            common_address,
+        ));
+        let mut data = Vec::with_capacity(9);
+        data.extend_from_slice(&tsc.to_le_bytes());
+        data.extend_from_slice(&time.to_bytes());
+        asdu.objects.push(InformationObject {
+            ioa: Ioa::new(0),
+            data: Bytes::from(data),
+        });
+        asdu
+    }
+
     /// Create a clock synchronization command ASDU.
     pub fn clock_sync_command(common_address: u16, time: Cp56Time2a) -> Self {
         let mut asdu = Self::new(AsduHeader::new(
@@ -603,9 +1084,114 @@ impl Asdu {
         asdu
     }
 
+    /// Create a reset process command ASDU (C_RP_NA_1).
+    pub fn reset_process_command(common_address: u16, qrp: ResetProcessQualifier) -> Self {
+        let mut asdu = Self::new(AsduHeader::new(
+            TypeId::ResetProcess,
+            1,
+            Cot::Activation,
+            common_address,
+        ));
+        asdu.objects.push(InformationObject {
+            ioa: Ioa::new(0),
+            data: Bytes::copy_from_slice(&[qrp.as_u8()]),
+        });
+        asdu
+    }
+
+    /// Create a parameter of measured values ASDU (P_ME_NA_1, P_ME_NB_1 or
+    /// P_ME_NC_1, following the value variant).
+    pub fn parameter_command(
+        common_address: u16,
+        ioa: u32,
+        value: ParameterValue,
+        qpm: Qpm,
+    ) -> Self {
+        let mut asdu = Self::new(AsduHeader::new(
+            value.type_id(),
+            1,
+            Cot::Activation,
+            common_address,
+        ));
+        let mut data = Vec::with_capacity(5);
+        match value {
+            ParameterValue::Normalized(v) => {
+                data.extend_from_slice(&normalized_to_raw(v).to_le_bytes())
+            }
+            ParameterValue::Scaled(v) => data.extend_from_slice(&v.to_le_bytes()),
+            ParameterValue::Float(v) => data.extend_from_slice(&v.to_le_bytes()),
+        }
+        data.push(qpm.as_u8());
+        asdu.objects.push(InformationObject {
+            ioa: Ioa::new(ioa),
+            data: Bytes::from(data),
+        });
+        asdu
+    }
+
+    /// Create a parameter activation ASDU (P_AC_NA_1).
+    ///
+    /// `activate` selects activation (COT=6) or deactivation (COT=8).
+    pub fn parameter_activation(
+        common_address: u16,
+        ioa: u32,
+        qpa: ParameterActivationQualifier,
+        activate: bool,
+    ) -> Self {
+        let cot = if activate {
+            Cot::Activation
+        } else {
+            Cot::Deactivation
+        };
+        let mut asdu = Self::new(AsduHeader::new(
+            TypeId::ParameterActivation,
+            1,
+            cot,
+            common_address,
+        ));
+        asdu.objects.push(InformationObject {
+            ioa: Ioa::new(ioa),
+            data: Bytes::copy_from_slice(&[qpa.as_u8()]),
+        });
+        asdu
+    }
+
+    /// Create an end of initialization ASDU (M_EI_NA_1).
+    ///
+    /// Sent by a controlled station after startup or a reset process command.
+    pub fn end_of_init(common_address: u16, coi: Coi) -> Self {
+        let mut asdu = Self::new(AsduHeader::new(
+            TypeId::EndOfInit,
+            1,
+            Cot::Initialized,
+            common_address,
+        ));
+        asdu.objects.push(InformationObject {
+            ioa: Ioa::new(0),
+            data: Bytes::copy_from_slice(&[coi.as_u8()]),
+        });
+        asdu
+    }
+
+    /// Mirror this ASDU back to its sender with a new cause of transmission.
+    ///
+    /// The payload is kept intact, as required for confirmations. Use
+    /// `Cot::UnknownTypeId`..`Cot::UnknownIoa` with `negative = true` to
+    /// reject a request that cannot be served.
+    pub fn mirror(&self, cot: Cot, negative: bool) -> Self {
+        let mut asdu = self.clone();
+        asdu.header.cot = cot;
+        asdu.header.negative = negative;
+        asdu
+    }
+
     /// Parse ASDU from bytes (after APCI).
+    ///
+    /// The length of the information objects is checked against the type
+    /// and the VSQ count (see [`AsduHeader::expected_payload_len`]).
     pub fn parse(data: &[u8]) -> Result<Self> {
         let (header, header_len) = AsduHeader::parse(data)?;
+        header.check_payload_len(data.len() - header_len)?;
         let raw_data = Bytes::copy_from_slice(&data[header_len..]);
 
         Ok(Self {
@@ -618,6 +1204,21 @@ impl Asdu {
     /// Parse ASDU from bytes without copying the payload.
     pub fn parse_bytes(data: Bytes) -> Result<Self> {
         let (header, header_len) = AsduHeader::parse(data.as_ref())?;
+        header.check_payload_len(data.len() - header_len)?;
+        let raw_data = data.slice(header_len..);
+
+        Ok(Self {
+            header,
+            objects: Vec::new(),
+            raw_data,
+        })
+    }
+
+    /// Parse ASDU from bytes without copying the payload, keeping an unknown
+    /// type identification as [`TypeId::Other`] with the payload intact.
+    pub fn parse_bytes_lenient(data: Bytes) -> Result<Self> {
+        let (header, header_len) = AsduHeader::parse_lenient(data.as_ref())?;
+        header.check_payload_len(data.len() - header_len)?;
         let raw_data = data.slice(header_len..);
 
         Ok(Self {
@@ -635,14 +1236,28 @@ impl Asdu {
     }
 
     /// Encode ASDU directly into the provided buffer (zero-copy).
+    ///
+    /// With SQ=1 only the IOA of the first object is written, followed by
+    /// the elements of all objects; their IOAs must be consecutive (see
+    /// [`set_sequence_if_contiguous`](Self::set_sequence_if_contiguous)).
     #[inline]
     pub fn encode_to(&self, buf: &mut BytesMut) {
         self.header.encode(buf);
 
         // Encode information objects
-        for obj in &self.objects {
-            buf.put_slice(&obj.ioa.to_bytes());
-            buf.put_slice(&obj.data);
+        if self.header.vsq.sequence {
+            debug_assert!(self.has_consecutive_ioas(), "SQ=1 requires consecutive IOAs");
+            if let Some(first) = self.objects.first() {
+                buf.put_slice(&first.ioa.to_bytes());
+            }
+            for obj in &self.objects {
+                buf.put_slice(&obj.data);
+            }
+        } else {
+            for obj in &self.objects {
+                buf.put_slice(&obj.ioa.to_bytes());
+                buf.put_slice(&obj.data);
+            }
         }
 
         // Or raw data if no parsed objects
@@ -651,6 +1266,18 @@ impl Asdu {
         }
     }
 
+    /// Type byte and payload of an ASDU of private or unknown type.
+    ///
+    /// Returns `None` for the types this crate decodes. The payload holds
+    /// the information objects as received, for passing vendor extensions
+    /// through; the ASDU can be forwarded as is.
+    pub fn opaque(&self) -> Option<(u8, &[u8])> {
+        match self.header.type_id {
+            TypeId::Other(value) => Some((value, self.raw_data.as_ref())),
+            _ => None,
+        }
+    }
+
     /// Calculate the encoded length of this ASDU.
     #[inline]
     pub fn encoded_len(&self) -> usize {
@@ -658,11 +1285,44 @@ impl Asdu {
         for obj in &self.objects {
             len += 3 + obj.data.len(); // IOA (3 bytes) + data
         }
+        if self.header.vsq.sequence && !self.objects.is_empty() {
+            len -= 3 * (self.objects.len() - 1); // Only the first IOA
+        }
         if self.objects.is_empty() {
             len += self.raw_data.len();
         }
         len
     }
+
+    /// Check that the IOAs of the objects are consecutive, as required for
+    /// a sequence (SQ=1).
+    pub fn has_consecutive_ioas(&self) -> bool {
+        self.objects
+            .windows(2)
+            .all(|pair| pair[1].ioa.value() == pair[0].ioa.value() + 1)
+    }
+
+    /// Encode the objects as a sequence (SQ=1) when their IOAs are
+    /// consecutive, saving three bytes per object after the first, and one
+    /// IOA per object (SQ=0) otherwise.
+    ///
+    /// Returns whether SQ is set. An ASDU without objects keeps the SQ bit
+    /// describing its raw data.
+    pub fn set_sequence_if_contiguous(&mut self) -> bool {
+        if !self.objects.is_empty() {
+            self.header.vsq.sequence = self.objects.len() > 1 && self.has_consecutive_ioas();
+        }
+        self.header.vsq.sequence
+    }
+}
+
+/// Convert a normalized value to its 16-bit NVA representation.
+///
+/// 1.0 is not representable and maps to the largest value, 1 - 2^-15.
+pub(crate) fn normalized_to_raw(value: f32) -> i16 {
+    (value * 32768.0)
+        .round()
+        .clamp(i16::MIN as f32, i16::MAX as f32) as i16
 }
 
 #[cfg(test)]
@@ -948,12 +1608,159 @@ mod tests {
         assert!(parsed.summer_time);
     }
 
+    #[test]
+    fn test_cp56time2a_from_system_time() {
+        use std::time::Duration;
+
+        // 2024-02-29 13:45:30.250 UTC, a Thursday
+        let time = UNIX_EPOCH + Duration::from_millis(1_709_214_330_250);
+        let utc = Cp56Time2a::from_system_time(time, 0);
+        assert_eq!(utc.year, 24);
+        assert_eq!(utc.month, 2);
+        assert_eq!(utc.day, 29);
+        assert_eq!(utc.day_of_week, 4);
+        assert_eq!(utc.hours, 13);
+        assert_eq!(utc.minutes, 45);
+        assert_eq!(utc.milliseconds, 30_250);
+
+        // +11h crosses into Friday 1 March
+        let local = Cp56Time2a::from_system_time(time, 11 * 3600);
+        assert_eq!((local.month, local.day, local.day_of_week), (3, 1, 5));
+        assert_eq!(local.hours, 0);
+
+        let epoch = Cp56Time2a::from_system_time(UNIX_EPOCH, 0);
+        assert_eq!((epoch.year, epoch.month, epoch.day), (70, 1, 1));
+    }
+
     #[test]
     fn test_cp56time2a_too_short() {
         let result = Cp56Time2a::from_bytes(&[0, 0, 0, 0, 0, 0]); // 6 bytes, need 7
         assert!(result.is_err());
     }
 
+    #[test]
+    fn test_cp24time2a_roundtrip() {
+        let time = Cp24Time2a {
+            milliseconds: 12_345,
+            minutes: 42,
+            invalid: true,
+        };
+        let bytes = time.to_bytes();
+        assert_eq!(bytes, [0x39, 0x30, 42 | 0x80]);
+        assert_eq!(Cp24Time2a::from_bytes(&bytes).unwrap(), time);
+        assert!(Cp24Time2a::from_bytes(&bytes[..2]).is_err());
+    }
+
+    #[test]
+    fn test_cp56time2a_iso8601() {
+        let time = Cp56Time2a::from_bytes(&[0x2A, 0x76, 45, 13, 0x9D, 2, 24]).unwrap();
+        assert_eq!(time.to_iso8601(), "2024-02-29T13:45:30.250");
+        assert_eq!(Cp56Time2a::parse_iso8601("2024-02-29T13:45:30.250").unwrap(), time);
+        assert_eq!(Cp56Time2a::parse_iso8601("2024-02-29T13:45:30.25Z").unwrap(), time);
+
+        let whole = Cp56Time2a::parse_iso8601("2031-01-05T00:00:07").unwrap();
+        assert_eq!((whole.year, whole.milliseconds, whole.day_of_week), (31, 7000, 7));
+
+        for text in [
+            "2024-02-30T00:00:00",
+            "1999-12-31T23:59:59",
+            "2024-13-01T00:00:00",
+            "2024-01-01T24:00:00",
+            "2024-01-01 00:00:00",
+            "2024-01-01T00:00:00.1234",
+            "2024-01-01T00:00:00+01:00",
+            "2024-01-257T00:00:00",
+        ] {
+            assert!(Cp56Time2a::parse_iso8601(text).is_err(), "{}", text);
+        }
+    }
+
+    #[cfg(feature = "chrono")]
+    #[test]
+    fn test_cp56time2a_chrono() {
+        use chrono::{DateTime, NaiveDate, NaiveDateTime, Utc};
+
+        let time = Cp56Time2a::from_bytes(&[0x2A, 0x76, 45, 13, 0x9D, 2, 24]).unwrap();
+        let utc = DateTime::<Utc>::try_from(time).unwrap();
+        let expected = NaiveDate::from_ymd_opt(2024, 2, 29)
+            .unwrap()
+            .and_hms_milli_opt(13, 45, 30, 250)
+            .unwrap();
+        assert_eq!(utc.naive_utc(), expected);
+        assert_eq!(Cp56Time2a::from(utc), time);
+        assert_eq!(Cp56Time2a::from(expected).day_of_week, 4);
+
+        let invalid = Cp56Time2a { month: 2, day: 30, ..time };
+        assert!(NaiveDateTime::try_from(invalid).is_err());
+
+        let leap = NaiveDate::from_ymd_opt(2016, 12, 31)
+            .unwrap()
+            .and_hms_milli_opt(23, 59, 59, 1_500)
+            .unwrap();
+        assert_eq!(Cp56Time2a::from(leap).milliseconds, 59_999);
+    }
+
+    #[test]
+    fn test_cp16time2a() {
+        let time = Cp16Time2a::from_bytes(&[0x39, 0x30]).unwrap();
+        assert_eq!(time.milliseconds, 12_345);
+        assert_eq!(time.to_bytes(), [0x39, 0x30]);
+        assert_eq!(Duration::from(time), Duration::from_millis(12_345));
+        assert!(Cp16Time2a::from_bytes(&[0x39]).is_err());
+
+        assert_eq!(Cp16Time2a::from_duration(Duration::from_millis(250)).milliseconds, 250);
+        let saturated = Cp16Time2a::from_duration(Duration::from_secs(90));
+        assert_eq!(saturated.milliseconds, Cp16Time2a::MAX_MILLISECONDS);
+    }
+
+    #[test]
+    fn test_cp24time2a_complete() {
+        // 2024-02-29 13:45:30.250, Thursday
+        let reference = Cp56Time2a::from_bytes(&[0x2A, 0x76, 45, 13, 0x9D, 2, 24]).unwrap();
+
+        let same_hour = Cp24Time2a {
+            milliseconds: 5_000,
+            minutes: 40,
+            invalid: false,
+        }
+        .complete(&reference);
+        assert_eq!((same_hour.hours, same_hour.minutes), (13, 40));
+        assert_eq!(same_hour.milliseconds, 5_000);
+        assert_eq!((same_hour.day, same_hour.day_of_week), (29, 4));
+
+        // A tag just past the next hour boundary
+        let next_hour = Cp24Time2a {
+            milliseconds: 0,
+            minutes: 2,
+            invalid: false,
+        }
+        .complete(&reference);
+        assert_eq!((next_hour.hours, next_hour.minutes), (14, 2));
+
+        // Just before midnight, a tag from after it rolls into 1 March
+        let late = Cp56Time2a::from_bytes(&[0, 0, 58, 23, 0x9D, 2, 24]).unwrap();
+        let tomorrow = Cp24Time2a {
+            milliseconds: 0,
+            minutes: 1,
+            invalid: true,
+        }
+        .complete(&late);
+        assert_eq!((tomorrow.month, tomorrow.day, tomorrow.day_of_week), (3, 1, 5));
+        assert_eq!((tomorrow.hours, tomorrow.minutes), (0, 1));
+        assert!(tomorrow.invalid);
+
+        // ... and one from before midnight stays on the previous day
+        let early = Cp56Time2a::from_bytes(&[0, 0, 1, 0, 0xA1, 3, 24]).unwrap();
+        let yesterday = Cp24Time2a {
+            milliseconds: 0,
+            minutes: 59,
+            invalid: false,
+        }
+        .complete(&early);
+        assert_eq!((yesterday.month, yesterday.day), (2, 29));
+        assert_eq!(yesterday.hours, 23);
+    }
+
     #[test]
     fn test_asdu_header_with_flags() {
         let mut header = AsduHeader::new(TypeId::MeasuredFloat, 5, Cot::Spontaneous, 1);
@@ -982,6 +1789,105 @@ mod tests {
         assert!(result.is_err());
     }
 
+    #[test]
+    fn test_asdu_parse_private_type() {
+        // Type 200 (private range), VSQ=1, COT=3, CA=7, vendor payload
+        let data = [0xC8, 0x01, 0x03, 0x00, 0x07, 0x00, 0x01, 0x00, 0x00, 0xDE, 0xAD];
+        let asdu = Asdu::parse(&data).unwrap();
+        assert_eq!(asdu.header.type_id, TypeId::Other(200));
+        assert!(asdu.header.type_id.is_private());
+        assert_eq!(asdu.header.common_address, 7);
+        assert_eq!(asdu.opaque(), Some((200, &data[6..])));
+        assert_eq!(asdu.encode().as_ref(), &data);
+
+        // Unassigned standard values still need lenient parsing
+        let mut unassigned = data;
+        unassigned[0] = 60;
+        assert!(matches!(Asdu::parse(&unassigned), Err(Iec104Error::UnknownTypeId(60))));
+        let asdu = Asdu::parse_bytes_lenient(Bytes::copy_from_slice(&unassigned)).unwrap();
+        assert_eq!(asdu.opaque(), Some((60, &data[6..])));
+
+        let known = Asdu::parse(&[0x01, 0x01, 0x03, 0x00, 0x01, 0x00, 0x01, 0x00, 0x00, 0x01]);
+        assert_eq!(known.unwrap().opaque(), None);
+    }
+
+    #[test]
+    fn test_asdu_parse_payload_length() {
+        // M_ME_NC_1, VSQ=2, COT=3, CA=1, but only one object present
+        let short = [
+            0x0D, 0x02, 0x03, 0x00, 0x01, 0x00, 0x01, 0x00, 0x00, 0x00, 0x00, 0x80, 0x3F, 0x00,
+        ];
+        let err = Asdu::parse(&short).unwrap_err();
+        assert!(matches!(err, Iec104Error::InvalidAsdu(_)));
+        assert!(err.to_string().contains("M_ME_NC_1"));
+        assert!(err.to_string().contains("needs 16 bytes of objects, got 8"));
+        assert!(Asdu::parse_bytes(Bytes::copy_from_slice(&short)).is_err());
+
+        // Trailing bytes are rejected as well
+        let mut long = short.to_vec();
+        long[1] = 0x01;
+        long.push(0xFF);
+        assert!(Asdu::parse(&long).is_err());
+        long.pop();
+        assert!(Asdu::parse(&long).is_ok());
+
+        // SQ=1: one IOA followed by three elements
+        let seq = [0x01, 0x83, 0x03, 0x00, 0x01, 0x00, 0x01, 0x00, 0x00, 0x01, 0x00, 0x01];
+        assert!(Asdu::parse(&seq).is_ok());
+        assert!(Asdu::parse(&seq[..11]).is_err());
+
+        let header = AsduHeader::new(TypeId::ReadCommand, 2, Cot::Request, 1);
+        assert_eq!(header.expected_payload_len(), Some(6));
+        let header = AsduHeader::new(TypeId::FileSegment, 1, Cot::FileTransfer, 1);
+        assert_eq!(header.expected_payload_len(), None);
+    }
+
+    #[test]
+    fn test_asdu_sequence_encoding() {
+        let mut asdu = Asdu::new(AsduHeader::new(TypeId::SinglePoint, 3, Cot::Spontaneous, 1));
+        for (ioa, siq) in [(0x0100, 0x01), (0x0101, 0x00), (0x0102, 0x81)] {
+            asdu.objects.push(InformationObject::new(Ioa::new(ioa), Bytes::from(vec![siq])));
+        }
+        assert!(asdu.set_sequence_if_contiguous());
+        assert_eq!(asdu.encoded_len(), 6 + 3 + 3);
+        let encoded = asdu.encode();
+        assert_eq!(
+            &encoded[..],
+            &[0x01, 0x83, 0x03, 0x00, 0x01, 0x00, 0x00, 0x01, 0x00, 0x01, 0x00, 0x81]
+        );
+
+        let points = crate::parser::parse_asdu(&Asdu::parse(&encoded).unwrap()).unwrap();
+        let ioas: Vec<u32> = points.iter().map(|point| point.ioa).collect();
+        assert_eq!(ioas, vec![0x0100, 0x0101, 0x0102]);
+
+        // A gap keeps one IOA per object
+        let mut asdu = Asdu::new(AsduHeader::new(TypeId::SinglePoint, 2, Cot::Spontaneous, 1));
+        for ioa in [1, 3] {
+            asdu.objects.push(InformationObject::new(Ioa::new(ioa), Bytes::from_static(&[1])));
+        }
+        assert!(!asdu.set_sequence_if_contiguous());
+        assert_eq!(asdu.encoded_len(), 6 + 2 * 4);
+        assert_eq!(asdu.encode().len(), asdu.encoded_len());
+
+        // A stale SQ=1 is cleared once the IOAs are no longer consecutive
+        asdu.header.vsq.sequence = true;
+        assert!(!asdu.set_sequence_if_contiguous());
+        assert!(!asdu.header.vsq.sequence);
+        assert_eq!(&asdu.encode()[6..], &[1, 0, 0, 1, 3, 0, 0, 1]);
+    }
+
+    #[test]
+    #[cfg(debug_assertions)]
+    #[should_panic(expected = "SQ=1 requires consecutive IOAs")]
+    fn test_asdu_sequence_requires_consecutive_ioas() {
+        let mut asdu = Asdu::new(AsduHeader::new(TypeId::SinglePoint, 2, Cot::Spontaneous, 1));
+        asdu.header.vsq.sequence = true;
+        for ioa in [1, 3] {
+            asdu.objects.push(InformationObject::new(Ioa::new(ioa), Bytes::from_static(&[1])));
+        }
+        asdu.encode();
+    }
+
     #[test]
     fn test_asdu_encoded_len() {
         let mut asdu = Asdu::new(AsduHeader::new(TypeId::SinglePoint, 1, Cot::Spontaneous, 1));
@@ -1013,6 +1919,39 @@ mod tests {
         assert_eq!(&asdu.objects[0].data[..], &[20]);
     }
 
+    #[test]
+    fn test_asdu_counter_interrogation_command() {
+        use crate::types::{CounterFreeze, CounterGroup};
+
+        let qcc = Qcc::new(CounterGroup::Group(1), CounterFreeze::Freeze);
+        let asdu = Asdu::counter_interrogation_command(1, qcc);
+        assert_eq!(asdu.header.type_id, TypeId::CounterInterrogation);
+        assert_eq!(asdu.header.cot, Cot::Activation);
+        assert_eq!(asdu.objects[0].ioa.value(), 0);
+        assert_eq!(&asdu.objects[0].data[..], &[0x41]);
+    }
+
+    #[test]
+    fn test_asdu_read_command() {
+        let asdu = Asdu::read_command(1, 0x0102);
+        assert_eq!(asdu.header.type_id, TypeId::ReadCommand);
+        assert_eq!(asdu.header.cot, Cot::Request);
+        assert_eq!(&asdu.encode()[..], &[102, 0x01, 0x05, 0x00, 0x01, 0x00, 0x02, 0x01, 0x00]);
+    }
+
+    #[test]
+    fn test_asdu_test_command() {
+        let asdu = Asdu::test_command(1);
+        assert_eq!(asdu.header.type_id, TypeId::TestCommand);
+        assert_eq!(&asdu.encode()[..], &[104, 0x01, 0x06, 0x00, 0x01, 0x00, 0, 0, 0, 0xAA, 0x55]);
+
+        let time = Cp56Time2a::from_bytes(&[0x10, 0x27, 30, 12, 0x6F, 6, 24]).unwrap();
+        let asdu = Asdu::test_command_time(1, 0x0102, time);
+        assert_eq!(asdu.header.type_id, TypeId::TestCommandTime56);
+        assert_eq!(&asdu.objects[0].data[..2], &[0x02, 0x01]);
+        assert_eq!(&asdu.objects[0].data[2..], &time.to_bytes());
+    }
+
     #[test]
     fn test_asdu_clock_sync_command() {
         let time = Cp56Time2a {
@@ -1033,6 +1972,75 @@ mod tests {
         assert_eq!(asdu.objects[0].data.len(), 7);
     }
 
+    #[test]
+    fn test_asdu_reset_process_command() {
+        let asdu = Asdu::reset_process_command(1, ResetProcessQualifier::ResetEventBuffer);
+        assert_eq!(asdu.header.type_id, TypeId::ResetProcess);
+        assert_eq!(asdu.header.cot, Cot::Activation);
+        assert_eq!(asdu.objects[0].ioa.value(), 0);
+        assert_eq!(&asdu.objects[0].data[..], &[2]);
+    }
+
+    #[test]
+    fn test_asdu_parameter_command() {
+        use crate::types::ParameterKind;
+
+        let qpm = Qpm::new(ParameterKind::Threshold);
+        let asdu = Asdu::parameter_command(1, 0x0102, ParameterValue::Scaled(-2), qpm);
+        assert_eq!(asdu.header.type_id, TypeId::ParameterScaled);
+        assert_eq!(asdu.header.cot, Cot::Activation);
+        assert_eq!(
+            &asdu.encode()[..],
+            &[111, 0x01, 0x06, 0x00, 0x01, 0x00, 0x02, 0x01, 0x00, 0xFE, 0xFF, 0x01]
+        );
+
+        let qpm = Qpm::new(ParameterKind::SmoothingFactor);
+        let asdu = Asdu::parameter_command(1, 5, ParameterValue::Normalized(0.5), qpm);
+        assert_eq!(asdu.header.type_id, TypeId::ParameterNormalized);
+        assert_eq!(&asdu.objects[0].data[..], &[0x00, 0x40, 0x02]);
+
+        let asdu = Asdu::parameter_command(1, 5, ParameterValue::Float(1.5), qpm);
+        assert_eq!(asdu.header.type_id, TypeId::ParameterFloat);
+        assert_eq!(&asdu.objects[0].data[..4], &1.5f32.to_le_bytes());
+    }
+
+    #[test]
+    fn test_asdu_parameter_activation() {
+        let qpa = ParameterActivationQualifier::ObjectParameter;
+        let asdu = Asdu::parameter_activation(1, 5, qpa, true);
+        assert_eq!(asdu.header.type_id, TypeId::ParameterActivation);
+        assert_eq!(asdu.header.cot, Cot::Activation);
+        assert_eq!(&asdu.objects[0].data[..], &[2]);
+
+        let asdu = Asdu::parameter_activation(1, 5, qpa, false);
+        assert_eq!(asdu.header.cot, Cot::Deactivation);
+    }
+
+    #[test]
+    fn test_asdu_end_of_init() {
+        let coi = Coi::new(crate::types::InitCause::RemoteReset);
+        let asdu = Asdu::end_of_init(7, coi);
+        assert_eq!(asdu.header.type_id, TypeId::EndOfInit);
+        assert_eq!(asdu.header.cot, Cot::Initialized);
+        assert_eq!(asdu.header.common_address, 7);
+        assert_eq!(&asdu.encode()[..], &[70, 0x01, 0x04, 0x00, 0x07, 0x00, 0, 0, 0, 0x02]);
+    }
+
+    #[test]
+    fn test_asdu_mirror_negative() {
+        let mut request = Asdu::interrogation_command(1, 20);
+        request.header.originator = 3;
+
+        let response = request.mirror(Cot::UnknownCommonAddress, true);
+        assert_eq!(response.header.cot, Cot::UnknownCommonAddress);
+        assert!(response.header.negative);
+        assert_eq!(response.header.originator, 3);
+        assert_eq!(response.objects, request.objects);
+
+        let encoded = response.encode();
+        assert_eq!(encoded[2], 0x40 | 46);
+    }
+
     #[test]
     fn test_asdu_encode_decode_roundtrip() {
         let asdu = Asdu::interrogation_command(100, 20);
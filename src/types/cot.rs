@@ -8,6 +8,7 @@ use crate::error::{Iec104Error, Result};
 ///
 /// Defines the reason for transmission of an ASDU.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 #[repr(u8)]
 pub enum Cot {
     /// Periodic, cyclic (1)
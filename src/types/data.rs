@@ -3,10 +3,14 @@
 //! This module defines the unified data structures for representing
 //! information objects parsed from ASDUs.
 
-use super::{Cp56Time2a, DoublePointValue, MeasuredQuality, QualityDescriptor};
+use super::{
+    Cp24Time2a, Cp56Time2a, DoublePointValue, MeasuredQuality, Qpm, QualityDescriptor, TypeId,
+};
+use crate::error::{Iec104Error, Result};
 
 /// Unified data point representing an information object.
 #[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct DataPoint {
     /// Information object address (IOA)
     pub ioa: u32,
@@ -15,7 +19,59 @@ pub struct DataPoint {
     /// Quality flags
     pub quality: Quality,
     /// Timestamp (if present)
-    pub timestamp: Option<Cp56Time2a>,
+    pub timestamp: Option<Timestamp>,
+}
+
+/// Time tag of a data point.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Timestamp {
+    /// Complete date and time (CP56Time2a)
+    Full(Cp56Time2a),
+    /// Minutes and milliseconds only (CP24Time2a)
+    Partial(Cp24Time2a),
+}
+
+impl Timestamp {
+    /// The complete date and time, if known.
+    #[inline]
+    pub const fn full(self) -> Option<Cp56Time2a> {
+        match self {
+            Self::Full(time) => Some(time),
+            Self::Partial(_) => None,
+        }
+    }
+
+    /// Check if the time tag is marked invalid.
+    #[inline]
+    pub const fn is_invalid(&self) -> bool {
+        match self {
+            Self::Full(time) => time.invalid,
+            Self::Partial(time) => time.invalid,
+        }
+    }
+
+    /// Complete a partial time tag against a full `reference` clock.
+    ///
+    /// Full timestamps are returned unchanged.
+    pub fn complete(self, reference: &Cp56Time2a) -> Cp56Time2a {
+        match self {
+            Self::Full(time) => time,
+            Self::Partial(time) => time.complete(reference),
+        }
+    }
+}
+
+impl From<Cp56Time2a> for Timestamp {
+    fn from(time: Cp56Time2a) -> Self {
+        Self::Full(time)
+    }
+}
+
+impl From<Cp24Time2a> for Timestamp {
+    fn from(time: Cp24Time2a) -> Self {
+        Self::Partial(time)
+    }
 }
 
 impl DataPoint {
@@ -53,7 +109,7 @@ impl DataPoint {
             ioa,
             value,
             quality,
-            timestamp: Some(timestamp),
+            timestamp: Some(Timestamp::Full(timestamp)),
         }
     }
 
@@ -78,6 +134,7 @@ impl DataPoint {
 
 /// Data value types.
 #[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum DataValue {
     /// Single-point information (M_SP_NA_1, M_SP_TB_1)
     Single(bool),
@@ -85,7 +142,7 @@ pub enum DataValue {
     /// Double-point information (M_DP_NA_1, M_DP_TB_1)
     Double(DoublePointValue),
 
-    /// Normalized value -1.0 to +1.0 (M_ME_NA_1, M_ME_TD_1)
+    /// Normalized value -1.0 to +1.0 (M_ME_NA_1, M_ME_ND_1, M_ME_TD_1)
     Normalized(f32),
 
     /// Scaled value (M_ME_NB_1, M_ME_TE_1)
@@ -100,8 +157,22 @@ pub enum DataValue {
     /// Bitstring of 32 bits (M_BO_NA_1, M_BO_TB_1)
     Bitstring(u32),
 
-    /// Step position (-64 to +63) (M_ST_NA_1, M_ST_TB_1)
-    StepPosition(i8),
+    /// Step position (M_ST_NA_1, M_ST_TB_1)
+    StepPosition {
+        /// Position (-64 to +63)
+        value: i8,
+        /// Equipment is in transient state (e.g., a tap changer is moving)
+        transient: bool,
+    },
+
+    /// Packed single-point information with status change detection (M_PS_NA_1)
+    PackedSinglePoint {
+        /// Status of 16 single points, bit 0 first
+        status: u16,
+        /// Status change detection: bits set for points that changed at least
+        /// once since the last report
+        changes: u16,
+    },
 
     /// Binary counter reading with sequence and flags
     BinaryCounter {
@@ -111,6 +182,14 @@ pub enum DataValue {
         adjusted: bool,
         invalid: bool,
     },
+
+    /// Parameter of measured values (P_ME_NA_1, P_ME_NB_1, P_ME_NC_1)
+    Parameter {
+        /// Parameter value
+        value: ParameterValue,
+        /// Kind and state of the parameter
+        qpm: Qpm,
+    },
 }
 
 impl DataValue {
@@ -129,8 +208,10 @@ impl DataValue {
             Self::Float(v) => Some(*v as f64),
             Self::Counter(v) => Some(*v as f64),
             Self::Bitstring(v) => Some(*v as f64),
-            Self::StepPosition(v) => Some(*v as f64),
+            Self::StepPosition { value, .. } => Some(*value as f64),
+            Self::PackedSinglePoint { status, .. } => Some(*status as f64),
             Self::BinaryCounter { value, .. } => Some(*value as f64),
+            Self::Parameter { value, .. } => Some(value.as_f64()),
         }
     }
 
@@ -163,10 +244,72 @@ impl DataValue {
                 | Self::Scaled(_)
                 | Self::Float(_)
                 | Self::Counter(_)
-                | Self::StepPosition(_)
+                | Self::StepPosition { .. }
                 | Self::BinaryCounter { .. }
+                | Self::Parameter { .. }
         )
     }
+
+    /// Name of the variant, as in the JSON form (e.g. "Float").
+    pub const fn name(&self) -> &'static str {
+        match self {
+            Self::Single(_) => "Single",
+            Self::Double(_) => "Double",
+            Self::Normalized(_) => "Normalized",
+            Self::Scaled(_) => "Scaled",
+            Self::Float(_) => "Float",
+            Self::Counter(_) => "Counter",
+            Self::Bitstring(_) => "Bitstring",
+            Self::StepPosition { .. } => "StepPosition",
+            Self::PackedSinglePoint { .. } => "PackedSinglePoint",
+            Self::BinaryCounter { .. } => "BinaryCounter",
+            Self::Parameter { .. } => "Parameter",
+        }
+    }
+}
+
+/// Value of a parameter of measured values, written with P_ME_NA_1,
+/// P_ME_NB_1 or P_ME_NC_1 depending on the variant.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum ParameterValue {
+    /// Normalized value -1.0 to +1.0 (P_ME_NA_1)
+    Normalized(f32),
+    /// Scaled value (P_ME_NB_1)
+    Scaled(i16),
+    /// Short floating point (P_ME_NC_1)
+    Float(f32),
+}
+
+impl ParameterValue {
+    /// Type identification carrying this value.
+    #[inline]
+    pub const fn type_id(&self) -> TypeId {
+        match self {
+            Self::Normalized(_) => TypeId::ParameterNormalized,
+            Self::Scaled(_) => TypeId::ParameterScaled,
+            Self::Float(_) => TypeId::ParameterFloat,
+        }
+    }
+
+    /// Convert to f64.
+    #[inline]
+    pub fn as_f64(&self) -> f64 {
+        match self {
+            Self::Normalized(v) | Self::Float(v) => *v as f64,
+            Self::Scaled(v) => *v as f64,
+        }
+    }
+
+    /// Check that the value can be encoded.
+    pub fn validate(&self) -> Result<()> {
+        match *self {
+            Self::Normalized(v) if !(-1.0..=1.0).contains(&v) => Err(
+                Iec104Error::invalid_asdu_static("Normalized value out of range -1.0..=1.0"),
+            ),
+            _ => Ok(()),
+        }
+    }
 }
 
 /// Quality flags for data points.
@@ -179,6 +322,8 @@ impl DataValue {
 /// - Bit 4: invalid (IV)
 /// - Bit 5: elapsed_time_invalid (EI)
 #[derive(Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(from = "QualityFlags", into = "QualityFlags"))]
 #[repr(transparent)]
 pub struct Quality(u8);
 
@@ -411,6 +556,31 @@ impl Quality {
         Self::from_siq(byte)
     }
 
+    /// Encode the quality bits of a SIQ/DIQ byte (BL, SB, NT, IV).
+    #[inline(always)]
+    pub const fn to_siq(&self) -> u8 {
+        let mut byte = 0u8;
+        if self.blocked() {
+            byte |= 0x10;
+        }
+        if self.substituted() {
+            byte |= 0x20;
+        }
+        if self.not_topical() {
+            byte |= 0x40;
+        }
+        if self.invalid() {
+            byte |= 0x80;
+        }
+        byte
+    }
+
+    /// Encode to QDS byte (Quality Descriptor for measured values).
+    #[inline(always)]
+    pub const fn to_qds(&self) -> u8 {
+        self.to_siq() | if self.overflow() { 0x01 } else { 0 }
+    }
+
     /// Parse from BCR flags (Binary Counter Reading).
     #[inline(always)]
     pub const fn from_bcr_flags(byte: u8) -> Self {
@@ -493,6 +663,61 @@ impl std::fmt::Display for Quality {
     }
 }
 
+/// Decode a value with transient state indication (VTI) into the step
+/// position, a 7-bit two's complement value (-64..=63), and the transient
+/// flag (bit 7).
+#[inline]
+pub(crate) const fn vti_from_u8(vti: u8) -> (i8, bool) {
+    (((vti << 1) as i8) >> 1, (vti & 0x80) != 0)
+}
+
+/// Encode a step position (-64..=63) and the transient flag into a VTI byte.
+#[inline]
+pub(crate) const fn vti_to_u8(value: i8, transient: bool) -> u8 {
+    (value as u8) & 0x7F | (transient as u8) << 7
+}
+
+/// Serialized form of [`Quality`]: one boolean per flag, as described by
+/// [`json_schema`](crate::schema::json_schema).
+#[cfg(feature = "serde")]
+#[derive(Default, serde::Serialize, serde::Deserialize)]
+#[serde(default)]
+struct QualityFlags {
+    overflow: bool,
+    blocked: bool,
+    substituted: bool,
+    not_topical: bool,
+    invalid: bool,
+    elapsed_time_invalid: bool,
+}
+
+#[cfg(feature = "serde")]
+impl From<Quality> for QualityFlags {
+    fn from(quality: Quality) -> Self {
+        Self {
+            overflow: quality.overflow(),
+            blocked: quality.blocked(),
+            substituted: quality.substituted(),
+            not_topical: quality.not_topical(),
+            invalid: quality.invalid(),
+            elapsed_time_invalid: quality.elapsed_time_invalid(),
+        }
+    }
+}
+
+#[cfg(feature = "serde")]
+impl From<QualityFlags> for Quality {
+    fn from(flags: QualityFlags) -> Self {
+        Quality::Good
+            .set_overflow(flags.overflow)
+            .set_blocked(flags.blocked)
+            .set_substituted(flags.substituted)
+            .set_not_topical(flags.not_topical)
+            .set_invalid(flags.invalid)
+            .set_elapsed_time_invalid(flags.elapsed_time_invalid)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -569,7 +794,24 @@ mod tests {
         );
         assert_eq!(dp.ioa, 1002);
         assert!(dp.timestamp.is_some());
-        assert_eq!(dp.timestamp.unwrap().hours, 12);
+        assert_eq!(dp.timestamp.and_then(Timestamp::full).unwrap().hours, 12);
+    }
+
+    #[test]
+    fn test_timestamp_partial() {
+        let partial = Timestamp::from(Cp24Time2a {
+            milliseconds: 1_500,
+            minutes: 10,
+            invalid: true,
+        });
+        assert!(partial.full().is_none());
+        assert!(partial.is_invalid());
+
+        let reference = Cp56Time2a::from_bytes(&[0, 0, 5, 8, 0x2F, 6, 24]).unwrap();
+        let completed = partial.complete(&reference);
+        assert_eq!((completed.hours, completed.minutes), (8, 10));
+        assert_eq!(completed.milliseconds, 1_500);
+        assert_eq!(Timestamp::Full(reference).complete(&completed), reference);
     }
 
     #[test]
@@ -605,8 +847,12 @@ mod tests {
         assert_eq!(DataValue::Bitstring(0xDEADBEEF).as_f64(), Some(0xDEADBEEFu32 as f64));
 
         // Test StepPosition
-        assert_eq!(DataValue::StepPosition(-10).as_f64(), Some(-10.0));
-        assert_eq!(DataValue::StepPosition(63).as_f64(), Some(63.0));
+        let step = |value| DataValue::StepPosition {
+            value,
+            transient: true,
+        };
+        assert_eq!(step(-10).as_f64(), Some(-10.0));
+        assert_eq!(step(63).as_f64(), Some(63.0));
 
         // Test BinaryCounter
         let bc = DataValue::BinaryCounter {
@@ -652,7 +898,11 @@ mod tests {
         assert!(DataValue::Scaled(100).is_numeric());
         assert!(DataValue::Float(1.0).is_numeric());
         assert!(DataValue::Counter(1000).is_numeric());
-        assert!(DataValue::StepPosition(10).is_numeric());
+        assert!(DataValue::StepPosition {
+            value: 10,
+            transient: false
+        }
+        .is_numeric());
         assert!(DataValue::BinaryCounter {
             value: 1,
             sequence: 0,
@@ -669,6 +919,22 @@ mod tests {
         assert!(!DataValue::Bitstring(0).is_numeric());
     }
 
+    #[test]
+    fn test_vti_two_complement() {
+        for (vti, value, transient) in [
+            (0x00, 0, false),
+            (0x05, 5, false),
+            (0x3F, 63, false),
+            (0x40, -64, false),
+            (0x7F, -1, false),
+            (0xFF, -1, true),
+            (0x85, 5, true),
+        ] {
+            assert_eq!(vti_from_u8(vti), (value, transient));
+            assert_eq!(vti_to_u8(value, transient), vti);
+        }
+    }
+
     #[test]
     fn test_quality_from_quality_descriptor() {
         let qd = QualityDescriptor {
@@ -779,6 +1045,18 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_quality_to_qds_siq_roundtrip() {
+        for byte in [0x00, 0x01, 0x10, 0x20, 0x40, 0x80, 0xF1] {
+            assert_eq!(Quality::from_qds(byte).to_qds(), byte);
+        }
+        for byte in [0x00, 0x10, 0x20, 0x40, 0x80, 0xF0] {
+            assert_eq!(Quality::from_siq(byte).to_siq(), byte);
+        }
+        // Overflow has no place in a SIQ
+        assert_eq!(Quality::from_qds(0x81).to_siq(), 0x80);
+    }
+
     #[test]
     fn test_data_point_as_f64_method() {
         let dp = DataPoint::new(1, DataValue::Float(99.9));
@@ -861,11 +1139,8 @@ mod tests {
         // Verify Quality methods are const-evaluable
         const GOOD: Quality = Quality::Good;
         const INVALID: Quality = Quality::Invalid;
-        const IS_GOOD: bool = GOOD.is_good();
-        const IS_BAD: bool = INVALID.is_good();
-
-        assert!(IS_GOOD);
-        assert!(!IS_BAD);
+        const _: () = assert!(GOOD.is_good());
+        const _: () = assert!(!INVALID.is_good());
     }
 
     #[test]
@@ -895,4 +1170,41 @@ mod tests {
         let q = Quality::from_raw(0x20); // EI only
         assert!(q.elapsed_time_invalid());
     }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_data_point_serde() {
+        use crate::types::{AsduHeader, Cot, ParameterKind};
+
+        let time = Cp56Time2a::from_bytes(&[0x2A, 0x76, 45, 13, 0x9D, 2, 24]).unwrap();
+        let points = [
+            DataPoint::with_timestamp(
+                100,
+                DataValue::Double(DoublePointValue::On),
+                Quality::Good.set_invalid(true),
+                time,
+            ),
+            DataPoint::new(101, DataValue::StepPosition { value: -3, transient: true }),
+            DataPoint::new(
+                102,
+                DataValue::Parameter {
+                    value: ParameterValue::Scaled(-7),
+                    qpm: Qpm::new(ParameterKind::Other(40)),
+                },
+            ),
+        ];
+        for point in points {
+            let json = serde_json::to_string(&point).unwrap();
+            assert_eq!(serde_json::from_str::<DataPoint>(&json).unwrap(), point);
+        }
+
+        let json = serde_json::to_value(Quality::Good.set_overflow(true)).unwrap();
+        assert_eq!(json["overflow"], true);
+        assert_eq!(json["invalid"], false);
+        assert_eq!(json.as_object().unwrap().len(), 6);
+
+        let header = AsduHeader::new(TypeId::Other(200), 2, Cot::Spontaneous, 7);
+        let json = serde_json::to_string(&header).unwrap();
+        assert_eq!(serde_json::from_str::<AsduHeader>(&json).unwrap(), header);
+    }
 }
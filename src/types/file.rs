@@ -0,0 +1,702 @@
+//! IEC 60870-5-104 file transfer objects (F_FR_NA_1 .. F_SC_NB_1).
+//!
+//! Files are identified by a name of file (NOF) and split into sections
+//! (NOS), each transferred as one or more segments. [`FileObject`] holds one
+//! decoded information object of any of the file transfer types.
+
+use bytes::Bytes;
+
+use crate::error::{Iec104Error, Result};
+use crate::types::{Asdu, AsduHeader, Cot, Cp56Time2a, InformationObject, Ioa, TypeId};
+
+/// Error reported in the upper nibble of SCQ and AFQ.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum FileError {
+    /// No error (0)
+    #[default]
+    None,
+    /// Requested memory space not available (1)
+    NoMemory,
+    /// Checksum failed (2)
+    Checksum,
+    /// Unexpected communication service (3)
+    UnexpectedService,
+    /// Unexpected name of file (4)
+    UnexpectedFile,
+    /// Unexpected name of section (5)
+    UnexpectedSection,
+    /// Reserved for standard (6-10) or private (11-15) definitions
+    Other(u8),
+}
+
+impl FileError {
+    /// Parse from the upper nibble of an SCQ/AFQ byte.
+    #[inline]
+    pub const fn from_u8(value: u8) -> Self {
+        match value >> 4 {
+            0 => Self::None,
+            1 => Self::NoMemory,
+            2 => Self::Checksum,
+            3 => Self::UnexpectedService,
+            4 => Self::UnexpectedFile,
+            5 => Self::UnexpectedSection,
+            other => Self::Other(other),
+        }
+    }
+
+    /// Convert to the upper nibble of an SCQ/AFQ byte.
+    #[inline]
+    pub const fn as_u8(&self) -> u8 {
+        let value = match self {
+            Self::None => 0,
+            Self::NoMemory => 1,
+            Self::Checksum => 2,
+            Self::UnexpectedService => 3,
+            Self::UnexpectedFile => 4,
+            Self::UnexpectedSection => 5,
+            Self::Other(value) => *value & 0x0F,
+        };
+        value << 4
+    }
+}
+
+/// Select and call qualifier (SCQ) carried by F_SC_NA_1.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum CallAction {
+    /// Default, used to call the directory (0)
+    Default,
+    /// Select file (1)
+    SelectFile,
+    /// Request file (2)
+    RequestFile,
+    /// Deactivate file (3)
+    DeactivateFile,
+    /// Delete file (4)
+    DeleteFile,
+    /// Select section (5)
+    SelectSection,
+    /// Request section (6)
+    RequestSection,
+    /// Deactivate section (7)
+    DeactivateSection,
+    /// Reserved for standard (8-10) or private (11-15) definitions
+    Other(u8),
+}
+
+impl CallAction {
+    /// Parse from the lower nibble of an SCQ byte.
+    #[inline]
+    pub const fn from_u8(value: u8) -> Self {
+        match value & 0x0F {
+            0 => Self::Default,
+            1 => Self::SelectFile,
+            2 => Self::RequestFile,
+            3 => Self::DeactivateFile,
+            4 => Self::DeleteFile,
+            5 => Self::SelectSection,
+            6 => Self::RequestSection,
+            7 => Self::DeactivateSection,
+            other => Self::Other(other),
+        }
+    }
+
+    /// Convert to the lower nibble of an SCQ byte.
+    #[inline]
+    pub const fn as_u8(&self) -> u8 {
+        match self {
+            Self::Default => 0,
+            Self::SelectFile => 1,
+            Self::RequestFile => 2,
+            Self::DeactivateFile => 3,
+            Self::DeleteFile => 4,
+            Self::SelectSection => 5,
+            Self::RequestSection => 6,
+            Self::DeactivateSection => 7,
+            Self::Other(value) => *value & 0x0F,
+        }
+    }
+}
+
+/// Last section or segment qualifier (LSQ) carried by F_LS_NA_1.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum LastSectionQualifier {
+    /// File transfer without deactivation (1)
+    FileTransfer,
+    /// File transfer with deactivation (2)
+    FileDeactivated,
+    /// Section transfer without deactivation (3)
+    SectionTransfer,
+    /// Section transfer with deactivation (4)
+    SectionDeactivated,
+    /// Not used (0), reserved for standard (5-127) or private (128-255) definitions
+    Other(u8),
+}
+
+impl LastSectionQualifier {
+    /// Parse from LSQ byte.
+    #[inline]
+    pub const fn from_u8(value: u8) -> Self {
+        match value {
+            1 => Self::FileTransfer,
+            2 => Self::FileDeactivated,
+            3 => Self::SectionTransfer,
+            4 => Self::SectionDeactivated,
+            other => Self::Other(other),
+        }
+    }
+
+    /// Encode to LSQ byte.
+    #[inline]
+    pub const fn as_u8(&self) -> u8 {
+        match self {
+            Self::FileTransfer => 1,
+            Self::FileDeactivated => 2,
+            Self::SectionTransfer => 3,
+            Self::SectionDeactivated => 4,
+            Self::Other(value) => *value,
+        }
+    }
+}
+
+/// Acknowledge file or section qualifier (AFQ, lower nibble) carried by F_AF_NA_1.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum AckAction {
+    /// Positive acknowledge of file transfer (1)
+    FileAccepted,
+    /// Negative acknowledge of file transfer (2)
+    FileRejected,
+    /// Positive acknowledge of section transfer (3)
+    SectionAccepted,
+    /// Negative acknowledge of section transfer (4)
+    SectionRejected,
+    /// Not used (0), reserved for standard (5-10) or private (11-15) definitions
+    Other(u8),
+}
+
+impl AckAction {
+    /// Parse from the lower nibble of an AFQ byte.
+    #[inline]
+    pub const fn from_u8(value: u8) -> Self {
+        match value & 0x0F {
+            1 => Self::FileAccepted,
+            2 => Self::FileRejected,
+            3 => Self::SectionAccepted,
+            4 => Self::SectionRejected,
+            other => Self::Other(other),
+        }
+    }
+
+    /// Convert to the lower nibble of an AFQ byte.
+    #[inline]
+    pub const fn as_u8(&self) -> u8 {
+        match self {
+            Self::FileAccepted => 1,
+            Self::FileRejected => 2,
+            Self::SectionAccepted => 3,
+            Self::SectionRejected => 4,
+            Self::Other(value) => *value & 0x0F,
+        }
+    }
+}
+
+/// Status of file (SOF) of a directory entry.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct FileStatus {
+    /// Status, reserved for standard (0-15) or private (16-31) definitions
+    pub status: u8,
+    /// Last file of the directory (LFD bit)
+    pub last_file: bool,
+    /// Name defines a subdirectory (FOR bit)
+    pub directory: bool,
+    /// File transfer is active (FA bit)
+    pub transfer_active: bool,
+}
+
+impl FileStatus {
+    /// Parse from SOF byte.
+    #[inline]
+    pub const fn from_u8(value: u8) -> Self {
+        Self {
+            status: value & 0x1F,
+            last_file: (value & 0x20) != 0,
+            directory: (value & 0x40) != 0,
+            transfer_active: (value & 0x80) != 0,
+        }
+    }
+
+    /// Encode to SOF byte.
+    #[inline]
+    pub const fn as_u8(&self) -> u8 {
+        (self.status & 0x1F)
+            | if self.last_file { 0x20 } else { 0 }
+            | if self.directory { 0x40 } else { 0 }
+            | if self.transfer_active { 0x80 } else { 0 }
+    }
+}
+
+/// Checksum (CHS) of a section or file: the arithmetic sum modulo 256 of
+/// all segment octets.
+pub fn file_checksum(data: &[u8]) -> u8 {
+    data.iter().fold(0u8, |sum, byte| sum.wrapping_add(*byte))
+}
+
+/// One information object of a file transfer ASDU.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FileObject {
+    /// File ready (F_FR_NA_1)
+    FileReady {
+        /// Name of file (NOF)
+        file: u16,
+        /// Length of file (LOF, 24 bit)
+        length: u32,
+        /// File not ready, or negative confirmation (BS bit of FRQ)
+        negative: bool,
+    },
+    /// Section ready (F_SR_NA_1)
+    SectionReady {
+        /// Name of file (NOF)
+        file: u16,
+        /// Name of section (NOS)
+        section: u8,
+        /// Length of section (LOF, 24 bit)
+        length: u32,
+        /// Section not ready to load (BS bit of SRQ)
+        not_ready: bool,
+    },
+    /// Call directory, select file, call file or call section (F_SC_NA_1)
+    Call {
+        /// Name of file (NOF)
+        file: u16,
+        /// Name of section (NOS)
+        section: u8,
+        /// Requested action (SCQ, lower nibble)
+        action: CallAction,
+        /// Error (SCQ, upper nibble)
+        error: FileError,
+    },
+    /// Last section, last segment (F_LS_NA_1)
+    LastSection {
+        /// Name of file (NOF)
+        file: u16,
+        /// Name of section (NOS)
+        section: u8,
+        /// Last section or segment qualifier (LSQ)
+        qualifier: LastSectionQualifier,
+        /// Checksum of the section or file (CHS)
+        checksum: u8,
+    },
+    /// Acknowledge file, acknowledge section (F_AF_NA_1)
+    Ack {
+        /// Name of file (NOF)
+        file: u16,
+        /// Name of section (NOS)
+        section: u8,
+        /// Acknowledgement (AFQ, lower nibble)
+        action: AckAction,
+        /// Error (AFQ, upper nibble)
+        error: FileError,
+    },
+    /// Segment (F_SG_NA_1)
+    Segment {
+        /// Name of file (NOF)
+        file: u16,
+        /// Name of section (NOS)
+        section: u8,
+        /// Segment octets (length LOS, at most 255)
+        data: Bytes,
+    },
+    /// Directory entry (F_DR_TA_1)
+    Directory {
+        /// Name of file or subdirectory (NOF)
+        file: u16,
+        /// Length of file (LOF, 24 bit)
+        length: u32,
+        /// Status of file (SOF)
+        status: FileStatus,
+        /// Creation time of the file
+        time: Cp56Time2a,
+    },
+    /// Query log, request archive file (F_SC_NB_1)
+    QueryLog {
+        /// Name of file (NOF)
+        file: u16,
+        /// Start of the requested time range
+        start: Cp56Time2a,
+        /// End of the requested time range
+        stop: Cp56Time2a,
+    },
+}
+
+impl FileObject {
+    /// Type identification carrying this object.
+    pub const fn type_id(&self) -> TypeId {
+        match self {
+            Self::FileReady { .. } => TypeId::FileReady,
+            Self::SectionReady { .. } => TypeId::SectionReady,
+            Self::Call { .. } => TypeId::FileCall,
+            Self::LastSection { .. } => TypeId::LastSection,
+            Self::Ack { .. } => TypeId::FileAck,
+            Self::Segment { .. } => TypeId::FileSegment,
+            Self::Directory { .. } => TypeId::FileDirectory,
+            Self::QueryLog { .. } => TypeId::QueryLog,
+        }
+    }
+
+    /// Name of file (NOF) the object refers to.
+    pub const fn file(&self) -> u16 {
+        match self {
+            Self::FileReady { file, .. }
+            | Self::SectionReady { file, .. }
+            | Self::Call { file, .. }
+            | Self::LastSection { file, .. }
+            | Self::Ack { file, .. }
+            | Self::Segment { file, .. }
+            | Self::Directory { file, .. }
+            | Self::QueryLog { file, .. } => *file,
+        }
+    }
+
+    /// Parse one object (without IOA) of the given type.
+    ///
+    /// Returns the object and the number of bytes consumed.
+    pub fn parse(type_id: TypeId, data: &[u8]) -> Result<(Self, usize)> {
+        let size = match type_id {
+            // NOF + NOS + LOS, followed by LOS segment octets
+            TypeId::FileSegment if data.len() >= 4 => 4 + data[3] as usize,
+            TypeId::FileReady
+            | TypeId::SectionReady
+            | TypeId::FileCall
+            | TypeId::LastSection
+            | TypeId::FileAck
+            | TypeId::FileSegment
+            | TypeId::FileDirectory
+            | TypeId::QueryLog => type_id.element_size(),
+            _ => return Err(Iec104Error::invalid_asdu_static("Not a file transfer type")),
+        };
+        if data.len() < size {
+            return Err(Iec104Error::invalid_asdu_static(
+                "File transfer object too short",
+            ));
+        }
+
+        let file = u16::from_le_bytes([data[0], data[1]]);
+        let length = |at: usize| u32::from_le_bytes([data[at], data[at + 1], data[at + 2], 0]);
+        let object = match type_id {
+            TypeId::FileReady => Self::FileReady {
+                file,
+                length: length(2),
+                negative: (data[5] & 0x80) != 0,
+            },
+            TypeId::SectionReady => Self::SectionReady {
+                file,
+                section: data[2],
+                length: length(3),
+                not_ready: (data[6] & 0x80) != 0,
+            },
+            TypeId::FileCall => Self::Call {
+                file,
+                section: data[2],
+                action: CallAction::from_u8(data[3]),
+                error: FileError::from_u8(data[3]),
+            },
+            TypeId::LastSection => Self::LastSection {
+                file,
+                section: data[2],
+                qualifier: LastSectionQualifier::from_u8(data[3]),
+                checksum: data[4],
+            },
+            TypeId::FileAck => Self::Ack {
+                file,
+                section: data[2],
+                action: AckAction::from_u8(data[3]),
+                error: FileError::from_u8(data[3]),
+            },
+            TypeId::FileSegment => Self::Segment {
+                file,
+                section: data[2],
+                data: Bytes::copy_from_slice(&data[4..size]),
+            },
+            TypeId::FileDirectory => Self::Directory {
+                file,
+                length: length(2),
+                status: FileStatus::from_u8(data[5]),
+                time: Cp56Time2a::from_bytes(&data[6..13])?,
+            },
+            _ => Self::QueryLog {
+                file,
+                start: Cp56Time2a::from_bytes(&data[2..9])?,
+                stop: Cp56Time2a::from_bytes(&data[9..16])?,
+            },
+        };
+        Ok((object, size))
+    }
+
+    /// Parse all objects of a file transfer ASDU, with their IOAs.
+    pub fn parse_asdu(asdu: &Asdu) -> Result<Vec<(u32, Self)>> {
+        let type_id = asdu.header.type_id;
+        let count = asdu.header.vsq.count as usize;
+        let sequence = asdu.header.vsq.sequence;
+        let data = asdu.raw_data.as_ref();
+
+        let mut objects = Vec::with_capacity(count);
+        let mut offset = 0;
+        let mut first_ioa = 0;
+        for i in 0..count {
+            let ioa = if sequence && i > 0 {
+                first_ioa + i as u32
+            } else {
+                let ioa = Ioa::from_bytes(&data[offset.min(data.len())..])?.value();
+                offset += 3;
+                ioa
+            };
+            if i == 0 {
+                first_ioa = ioa;
+            }
+            let (object, size) = Self::parse(type_id, &data[offset..])?;
+            offset += size;
+            objects.push((ioa, object));
+        }
+        Ok(objects)
+    }
+
+    /// Encode the object (without IOA).
+    ///
+    /// Segments longer than 255 octets are truncated.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut out = Vec::with_capacity(16);
+        out.extend_from_slice(&self.file().to_le_bytes());
+        let length =
+            |out: &mut Vec<u8>, length: u32| out.extend_from_slice(&length.to_le_bytes()[..3]);
+        match self {
+            Self::FileReady {
+                length: lof,
+                negative,
+                ..
+            } => {
+                length(&mut out, *lof);
+                out.push(if *negative { 0x80 } else { 0 });
+            }
+            Self::SectionReady {
+                section,
+                length: lof,
+                not_ready,
+                ..
+            } => {
+                out.push(*section);
+                length(&mut out, *lof);
+                out.push(if *not_ready { 0x80 } else { 0 });
+            }
+            Self::Call {
+                section,
+                action,
+                error,
+                ..
+            } => {
+                out.push(*section);
+                out.push(action.as_u8() | error.as_u8());
+            }
+            Self::LastSection {
+                section,
+                qualifier,
+                checksum,
+                ..
+            } => {
+                out.extend_from_slice(&[*section, qualifier.as_u8(), *checksum]);
+            }
+            Self::Ack {
+                section,
+                action,
+                error,
+                ..
+            } => {
+                out.push(*section);
+                out.push(action.as_u8() | error.as_u8());
+            }
+            Self::Segment { section, data, .. } => {
+                let data = &data[..data.len().min(u8::MAX as usize)];
+                out.extend_from_slice(&[*section, data.len() as u8]);
+                out.extend_from_slice(data);
+            }
+            Self::Directory {
+                length: lof,
+                status,
+                time,
+                ..
+            } => {
+                length(&mut out, *lof);
+                out.push(status.as_u8());
+                out.extend_from_slice(&time.to_bytes());
+            }
+            Self::QueryLog { start, stop, .. } => {
+                out.extend_from_slice(&start.to_bytes());
+                out.extend_from_slice(&stop.to_bytes());
+            }
+        }
+        out
+    }
+}
+
+impl Asdu {
+    /// Create a file transfer ASDU carrying a single object.
+    ///
+    /// File transfer uses COT=13 (file transfer), except for calling the
+    /// directory, which is a request (COT=5).
+    pub fn file_transfer(common_address: u16, ioa: u32, cot: Cot, object: &FileObject) -> Self {
+        let mut asdu = Self::new(AsduHeader::new(object.type_id(), 1, cot, common_address));
+        asdu.objects.push(InformationObject {
+            ioa: Ioa::new(ioa),
+            data: Bytes::from(object.to_bytes()),
+        });
+        asdu
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn roundtrip(object: FileObject) {
+        let bytes = object.to_bytes();
+        let (parsed, size) = FileObject::parse(object.type_id(), &bytes).unwrap();
+        assert_eq!(size, bytes.len());
+        assert_eq!(parsed, object);
+    }
+
+    #[test]
+    fn test_file_object_roundtrip() {
+        let time = Cp56Time2a::from_bytes(&[0x10, 0x27, 30, 12, 0x6F, 6, 24]).unwrap();
+        roundtrip(FileObject::FileReady {
+            file: 2,
+            length: 0x012345,
+            negative: true,
+        });
+        roundtrip(FileObject::SectionReady {
+            file: 2,
+            section: 1,
+            length: 1000,
+            not_ready: false,
+        });
+        roundtrip(FileObject::Call {
+            file: 2,
+            section: 1,
+            action: CallAction::RequestSection,
+            error: FileError::None,
+        });
+        roundtrip(FileObject::LastSection {
+            file: 2,
+            section: 1,
+            qualifier: LastSectionQualifier::SectionTransfer,
+            checksum: 0xAB,
+        });
+        roundtrip(FileObject::Ack {
+            file: 2,
+            section: 1,
+            action: AckAction::SectionRejected,
+            error: FileError::Checksum,
+        });
+        roundtrip(FileObject::Segment {
+            file: 2,
+            section: 1,
+            data: Bytes::from_static(b"COMTRADE"),
+        });
+        roundtrip(FileObject::Directory {
+            file: 7,
+            length: 4096,
+            status: FileStatus {
+                last_file: true,
+                ..FileStatus::default()
+            },
+            time,
+        });
+        roundtrip(FileObject::QueryLog {
+            file: 7,
+            start: time,
+            stop: time,
+        });
+    }
+
+    #[test]
+    fn test_file_qualifiers() {
+        for value in 0..=u8::MAX {
+            assert_eq!(FileStatus::from_u8(value).as_u8(), value);
+            assert_eq!(
+                CallAction::from_u8(value).as_u8() | FileError::from_u8(value).as_u8(),
+                value
+            );
+            assert_eq!(
+                AckAction::from_u8(value).as_u8() | FileError::from_u8(value).as_u8(),
+                value
+            );
+            assert_eq!(LastSectionQualifier::from_u8(value).as_u8(), value);
+        }
+        assert_eq!(FileError::from_u8(0x20), FileError::Checksum);
+        assert_eq!(CallAction::from_u8(0x21), CallAction::SelectFile);
+    }
+
+    #[test]
+    fn test_checksum() {
+        assert_eq!(file_checksum(&[]), 0);
+        assert_eq!(file_checksum(&[0x80, 0x80, 0x05]), 0x05);
+    }
+
+    #[test]
+    fn test_parse_file_asdu() {
+        // F_DR_TA_1 with SQ=1 and two entries
+        let time = [0x10, 0x27, 30, 12, 0x6F, 6, 24];
+        let mut asdu = Asdu::new(AsduHeader::new(TypeId::FileDirectory, 2, Cot::Request, 1));
+        asdu.header.vsq.sequence = true;
+        let mut data = vec![0x00, 0x00, 0x00];
+        for (file, sof) in [(1u8, 0x00u8), (2, 0x20)] {
+            data.extend_from_slice(&[file, 0x00, 0x10, 0x00, 0x00, sof]);
+            data.extend_from_slice(&time);
+        }
+        asdu.raw_data = Bytes::from(data);
+
+        let objects = FileObject::parse_asdu(&asdu).unwrap();
+        assert_eq!(objects.len(), 2);
+        assert_eq!(objects[1].0, 1);
+        let FileObject::Directory {
+            file,
+            length,
+            status,
+            ..
+        } = &objects[1].1
+        else {
+            panic!("Expected directory entry");
+        };
+        assert_eq!((*file, *length), (2, 16));
+        assert!(status.last_file);
+
+        // Segment followed by a truncated object
+        let mut asdu = Asdu::new(AsduHeader::new(
+            TypeId::FileSegment,
+            1,
+            Cot::FileTransfer,
+            1,
+        ));
+        asdu.raw_data = Bytes::from_static(&[0x05, 0x00, 0x00, 0x02, 0x00, 0x01, 0x03, 1, 2, 3]);
+        let objects = FileObject::parse_asdu(&asdu).unwrap();
+        assert!(matches!(
+            &objects[0].1,
+            FileObject::Segment { file: 2, section: 1, data } if data.as_ref() == [1, 2, 3]
+        ));
+        asdu.raw_data = Bytes::from_static(&[0x05, 0x00, 0x00, 0x02, 0x00, 0x01, 0x03, 1, 2]);
+        assert!(FileObject::parse_asdu(&asdu).is_err());
+    }
+
+    #[test]
+    fn test_file_transfer_asdu() {
+        let call = FileObject::Call {
+            file: 3,
+            section: 0,
+            action: CallAction::SelectFile,
+            error: FileError::None,
+        };
+        let asdu = Asdu::file_transfer(1, 0x10, Cot::FileTransfer, &call);
+        assert_eq!(asdu.header.type_id, TypeId::FileCall);
+        assert_eq!(
+            &asdu.encode()[..],
+            &[122, 0x01, 13, 0x00, 0x01, 0x00, 0x10, 0x00, 0x00, 0x03, 0x00, 0x00, 0x01]
+        );
+    }
+}
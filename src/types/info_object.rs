@@ -0,0 +1,624 @@
+//! Typed information objects.
+//!
+//! [`InfoObject`] decodes the information elements of every supported type
+//! identification, keeping the type-specific fields that the flattened
+//! [`DataPoint`](crate::types::DataPoint) drops, such as the qualifier of a
+//! command or the transient bit of a step position.
+
+use crate::error::{Iec104Error, Result};
+use crate::types::{
+    vti_from_u8, Asdu, Coi, Cp24Time2a, Cp56Time2a, DoubleCommandValue, DoublePointValue,
+    FileObject, Ioa, MeasuredQuality, ParameterActivationQualifier, ParameterValue, Qcc, Qos, Qpm,
+    QualityDescriptor, RegulatingStepValue, ResetProcessQualifier, SingleCommandValue, Timestamp,
+    TypeId,
+};
+
+/// Information object decoded according to its type identification.
+///
+/// Time-tagged types share the variant of their untagged counterpart, with
+/// the tag in `time`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum InfoObject {
+    /// Single-point information (M_SP_NA_1, M_SP_TA_1, M_SP_TB_1)
+    SinglePoint {
+        /// Value (true = ON)
+        value: bool,
+        /// Quality descriptor
+        quality: QualityDescriptor,
+        /// Time tag
+        time: Option<Timestamp>,
+    },
+    /// Double-point information (M_DP_NA_1, M_DP_TA_1, M_DP_TB_1)
+    DoublePoint {
+        /// Value
+        value: DoublePointValue,
+        /// Quality descriptor
+        quality: QualityDescriptor,
+        /// Time tag
+        time: Option<Timestamp>,
+    },
+    /// Step position information (M_ST_NA_1, M_ST_TB_1)
+    StepPosition {
+        /// Position (-64..=63)
+        value: i8,
+        /// Equipment is in transient state
+        transient: bool,
+        /// Quality descriptor
+        quality: MeasuredQuality,
+        /// Time tag
+        time: Option<Timestamp>,
+    },
+    /// Bitstring of 32 bits (M_BO_NA_1, M_BO_TB_1)
+    Bitstring {
+        /// Bitstring
+        value: u32,
+        /// Quality descriptor
+        quality: MeasuredQuality,
+        /// Time tag
+        time: Option<Timestamp>,
+    },
+    /// Measured value, normalized (M_ME_NA_1, M_ME_TA_1, M_ME_ND_1, M_ME_TD_1)
+    ///
+    /// M_ME_ND_1 carries no quality descriptor; it decodes as good quality.
+    MeasuredNormalized {
+        /// Value (-1.0..1.0)
+        value: f32,
+        /// Quality descriptor
+        quality: MeasuredQuality,
+        /// Time tag
+        time: Option<Timestamp>,
+    },
+    /// Measured value, scaled (M_ME_NB_1, M_ME_TB_1, M_ME_TE_1)
+    MeasuredScaled {
+        /// Value
+        value: i16,
+        /// Quality descriptor
+        quality: MeasuredQuality,
+        /// Time tag
+        time: Option<Timestamp>,
+    },
+    /// Measured value, short floating point (M_ME_NC_1, M_ME_TC_1, M_ME_TF_1)
+    MeasuredFloat {
+        /// Value
+        value: f32,
+        /// Quality descriptor
+        quality: MeasuredQuality,
+        /// Time tag
+        time: Option<Timestamp>,
+    },
+    /// Integrated totals (M_IT_NA_1, M_IT_TB_1)
+    IntegratedTotals {
+        /// Counter reading
+        value: i32,
+        /// Sequence number (0-31)
+        sequence: u8,
+        /// Counter overflowed in the integration period (CY)
+        carry: bool,
+        /// Counter was adjusted (CA)
+        adjusted: bool,
+        /// Invalid (IV)
+        invalid: bool,
+        /// Time tag
+        time: Option<Timestamp>,
+    },
+    /// Packed single-point information with status change detection (M_PS_NA_1)
+    PackedSinglePoint {
+        /// Status bits (ST)
+        status: u16,
+        /// Status change detection bits (CD)
+        changes: u16,
+        /// Quality descriptor
+        quality: MeasuredQuality,
+    },
+    /// End of initialization (M_EI_NA_1)
+    EndOfInit {
+        /// Cause of initialization
+        coi: Coi,
+    },
+    /// Single command (C_SC_NA_1, C_SC_TA_1)
+    SingleCommand {
+        /// Single command (SCO)
+        command: SingleCommandValue,
+        /// Time tag
+        time: Option<Cp56Time2a>,
+    },
+    /// Double command (C_DC_NA_1, C_DC_TA_1)
+    DoubleCommand {
+        /// Double command (DCO)
+        command: DoubleCommandValue,
+        /// Time tag
+        time: Option<Cp56Time2a>,
+    },
+    /// Regulating step command (C_RC_NA_1)
+    RegulatingStep {
+        /// Regulating step command (RCO)
+        command: RegulatingStepValue,
+    },
+    /// Setpoint command, normalized value (C_SE_NA_1)
+    SetpointNormalized {
+        /// Value (-1.0..1.0)
+        value: f32,
+        /// Qualifier of setpoint command (QOS)
+        qos: Qos,
+    },
+    /// Setpoint command, scaled value (C_SE_NB_1)
+    SetpointScaled {
+        /// Value
+        value: i16,
+        /// Qualifier of setpoint command (QOS)
+        qos: Qos,
+    },
+    /// Setpoint command, short floating point (C_SE_NC_1, C_SE_TC_1)
+    SetpointFloat {
+        /// Value
+        value: f32,
+        /// Qualifier of setpoint command (QOS)
+        qos: Qos,
+        /// Time tag
+        time: Option<Cp56Time2a>,
+    },
+    /// Bitstring of 32 bits command (C_BO_NA_1)
+    BitstringCommand {
+        /// Bitstring
+        value: u32,
+    },
+    /// Interrogation command (C_IC_NA_1)
+    Interrogation {
+        /// Qualifier of interrogation (QOI)
+        qoi: u8,
+    },
+    /// Counter interrogation command (C_CI_NA_1)
+    CounterInterrogation {
+        /// Qualifier of counter interrogation
+        qcc: Qcc,
+    },
+    /// Read command (C_RD_NA_1)
+    Read,
+    /// Clock synchronization command (C_CS_NA_1)
+    ClockSync {
+        /// Time
+        time: Cp56Time2a,
+    },
+    /// Test command (C_TS_NA_1, C_TS_TA_1)
+    ///
+    /// C_TS_NA_1 carries the fixed test bit pattern, C_TS_TA_1 a test
+    /// sequence counter.
+    TestCommand {
+        /// Fixed test bit pattern or test sequence counter
+        pattern: u16,
+        /// Time tag
+        time: Option<Cp56Time2a>,
+    },
+    /// Reset process command (C_RP_NA_1)
+    ResetProcess {
+        /// Qualifier of reset process
+        qrp: ResetProcessQualifier,
+    },
+    /// Parameter of measured values (P_ME_NA_1, P_ME_NB_1, P_ME_NC_1)
+    Parameter {
+        /// Parameter value
+        value: ParameterValue,
+        /// Kind and state of the parameter
+        qpm: Qpm,
+    },
+    /// Parameter activation (P_AC_NA_1)
+    ParameterActivation {
+        /// Qualifier of parameter activation
+        qpa: ParameterActivationQualifier,
+    },
+    /// File transfer object (F_xx_xx_1)
+    File(FileObject),
+}
+
+impl InfoObject {
+    /// Parse one object (without IOA) of the given type.
+    ///
+    /// Returns the object and the number of bytes consumed.
+    pub fn parse(type_id: TypeId, data: &[u8]) -> Result<(Self, usize)> {
+        if let TypeId::Other(value) = type_id {
+            return Err(Iec104Error::UnknownTypeId(value));
+        }
+        if type_id.is_file_transfer() {
+            let (object, size) = FileObject::parse(type_id, data)?;
+            return Ok((Self::File(object), size));
+        }
+
+        let size = type_id.element_size();
+        if data.len() < size {
+            return Err(Iec104Error::invalid_asdu_static(
+                "Information object too short",
+            ));
+        }
+
+        let u16_at = |at: usize| u16::from_le_bytes([data[at], data[at + 1]]);
+        let u32_at =
+            |at: usize| u32::from_le_bytes([data[at], data[at + 1], data[at + 2], data[at + 3]]);
+        let normalized_at = |at: usize| u16_at(at) as i16 as f32 / 32768.0;
+        let float_at = |at: usize| f32::from_bits(u32_at(at));
+        let qds_at = |at: usize| MeasuredQuality::from_u8(data[at]);
+        // Monitoring time tags follow the elements: CP24Time2a for the
+        // M_xx_TA_1 types, CP56Time2a for the M_xx_TB_1 types
+        let tag = |at: usize| -> Result<Option<Timestamp>> {
+            match size - at {
+                3 => Cp24Time2a::from_bytes(&data[at..]).map(|t| Some(t.into())),
+                7 => Cp56Time2a::from_bytes(&data[at..]).map(|t| Some(t.into())),
+                _ => Ok(None),
+            }
+        };
+        let command_tag = |at: usize| -> Result<Option<Cp56Time2a>> {
+            if size - at == 7 {
+                Cp56Time2a::from_bytes(&data[at..]).map(Some)
+            } else {
+                Ok(None)
+            }
+        };
+
+        let object = match type_id {
+            TypeId::SinglePoint | TypeId::SinglePointTime24 | TypeId::SinglePointTime56 => {
+                Self::SinglePoint {
+                    value: (data[0] & 0x01) != 0,
+                    quality: QualityDescriptor::from_siq(data[0]),
+                    time: tag(1)?,
+                }
+            }
+            TypeId::DoublePoint | TypeId::DoublePointTime24 | TypeId::DoublePointTime56 => {
+                Self::DoublePoint {
+                    value: DoublePointValue::from_u8(data[0]),
+                    quality: QualityDescriptor::from_diq(data[0]),
+                    time: tag(1)?,
+                }
+            }
+            TypeId::StepPosition | TypeId::StepPositionTime56 => {
+                let (value, transient) = vti_from_u8(data[0]);
+                Self::StepPosition {
+                    value,
+                    transient,
+                    quality: qds_at(1),
+                    time: tag(2)?,
+                }
+            }
+            TypeId::Bitstring32 | TypeId::Bitstring32Time56 => Self::Bitstring {
+                value: u32_at(0),
+                quality: qds_at(4),
+                time: tag(5)?,
+            },
+            TypeId::MeasuredNormalized
+            | TypeId::MeasuredNormalizedTime24
+            | TypeId::MeasuredNormalizedTime56 => Self::MeasuredNormalized {
+                value: normalized_at(0),
+                quality: qds_at(2),
+                time: tag(3)?,
+            },
+            TypeId::MeasuredNormalizedNoQuality => Self::MeasuredNormalized {
+                value: normalized_at(0),
+                quality: MeasuredQuality::new(),
+                time: None,
+            },
+            TypeId::MeasuredScaled
+            | TypeId::MeasuredScaledTime24
+            | TypeId::MeasuredScaledTime56 => Self::MeasuredScaled {
+                value: u16_at(0) as i16,
+                quality: qds_at(2),
+                time: tag(3)?,
+            },
+            TypeId::MeasuredFloat | TypeId::MeasuredFloatTime24 | TypeId::MeasuredFloatTime56 => {
+                Self::MeasuredFloat {
+                    value: float_at(0),
+                    quality: qds_at(4),
+                    time: tag(5)?,
+                }
+            }
+            TypeId::IntegratedTotals | TypeId::IntegratedTotalsTime56 => Self::IntegratedTotals {
+                value: u32_at(0) as i32,
+                sequence: data[4] & 0x1F,
+                carry: (data[4] & 0x20) != 0,
+                adjusted: (data[4] & 0x40) != 0,
+                invalid: (data[4] & 0x80) != 0,
+                time: tag(5)?,
+            },
+            TypeId::PackedSinglePoint => Self::PackedSinglePoint {
+                status: u16_at(0),
+                changes: u16_at(2),
+                quality: qds_at(4),
+            },
+            TypeId::EndOfInit => Self::EndOfInit {
+                coi: Coi::from_u8(data[0]),
+            },
+            TypeId::SingleCommand | TypeId::SingleCommandTime56 => Self::SingleCommand {
+                command: SingleCommandValue::from_u8(data[0]),
+                time: command_tag(1)?,
+            },
+            TypeId::DoubleCommand | TypeId::DoubleCommandTime56 => Self::DoubleCommand {
+                command: DoubleCommandValue::from_u8(data[0])?,
+                time: command_tag(1)?,
+            },
+            TypeId::RegulatingStep => Self::RegulatingStep {
+                command: RegulatingStepValue::from_u8(data[0])?,
+            },
+            TypeId::SetpointNormalized => Self::SetpointNormalized {
+                value: normalized_at(0),
+                qos: Qos::from_u8(data[2]),
+            },
+            TypeId::SetpointScaled => Self::SetpointScaled {
+                value: u16_at(0) as i16,
+                qos: Qos::from_u8(data[2]),
+            },
+            TypeId::SetpointFloat | TypeId::SetpointFloatTime56 => Self::SetpointFloat {
+                value: float_at(0),
+                qos: Qos::from_u8(data[4]),
+                time: command_tag(5)?,
+            },
+            TypeId::Bitstring32Command => Self::BitstringCommand { value: u32_at(0) },
+            TypeId::InterrogationCommand => Self::Interrogation { qoi: data[0] },
+            TypeId::CounterInterrogation => Self::CounterInterrogation {
+                qcc: Qcc::from_u8(data[0]),
+            },
+            TypeId::ReadCommand => Self::Read,
+            TypeId::ClockSync => Self::ClockSync {
+                time: Cp56Time2a::from_bytes(data)?,
+            },
+            TypeId::TestCommand | TypeId::TestCommandTime56 => Self::TestCommand {
+                pattern: u16_at(0),
+                time: command_tag(2)?,
+            },
+            TypeId::ResetProcess => Self::ResetProcess {
+                qrp: ResetProcessQualifier::from_u8(data[0]),
+            },
+            TypeId::ParameterNormalized => Self::Parameter {
+                value: ParameterValue::Normalized(normalized_at(0)),
+                qpm: Qpm::from_u8(data[2]),
+            },
+            TypeId::ParameterScaled => Self::Parameter {
+                value: ParameterValue::Scaled(u16_at(0) as i16),
+                qpm: Qpm::from_u8(data[2]),
+            },
+            TypeId::ParameterFloat => Self::Parameter {
+                value: ParameterValue::Float(float_at(0)),
+                qpm: Qpm::from_u8(data[4]),
+            },
+            TypeId::ParameterActivation => Self::ParameterActivation {
+                qpa: ParameterActivationQualifier::from_u8(data[0]),
+            },
+            TypeId::FileReady
+            | TypeId::SectionReady
+            | TypeId::FileCall
+            | TypeId::LastSection
+            | TypeId::FileAck
+            | TypeId::FileSegment
+            | TypeId::FileDirectory
+            | TypeId::QueryLog => unreachable!("file transfer types are parsed above"),
+            TypeId::Other(_) => unreachable!("unknown types are rejected above"),
+        };
+        Ok((object, size))
+    }
+
+    /// Parse all objects of an ASDU, with their IOAs.
+    ///
+    /// Reads the encoded objects of a received ASDU, or the objects of one
+    /// built locally.
+    pub fn parse_asdu(asdu: &Asdu) -> Result<Vec<(u32, Self)>> {
+        let type_id = asdu.header.type_id;
+        if !asdu.objects.is_empty() {
+            return asdu
+                .objects
+                .iter()
+                .map(|object| Ok((object.ioa.value(), Self::parse(type_id, &object.data)?.0)))
+                .collect();
+        }
+
+        let count = asdu.header.vsq.count as usize;
+        let sequence = asdu.header.vsq.sequence;
+        let data = asdu.raw_data.as_ref();
+
+        let mut objects = Vec::with_capacity(count);
+        let mut offset = 0;
+        let mut first_ioa = 0;
+        for i in 0..count {
+            let ioa = if sequence && i > 0 {
+                first_ioa + i as u32
+            } else {
+                let ioa = Ioa::from_bytes(&data[offset.min(data.len())..])?.value();
+                offset += 3;
+                ioa
+            };
+            if i == 0 {
+                first_ioa = ioa;
+            }
+            let (object, size) = Self::parse(type_id, &data[offset.min(data.len())..])?;
+            offset += size;
+            objects.push((ioa, object));
+        }
+        Ok(objects)
+    }
+}
+
+impl Asdu {
+    /// Decode the information objects as typed values, with their IOAs.
+    ///
+    /// See [`InfoObject::parse_asdu`].
+    pub fn info_objects(&self) -> Result<Vec<(u32, InfoObject)>> {
+        InfoObject::parse_asdu(self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::{AsduHeader, CallAction, CommandQualifier, Cot, FileError, PulseDuration};
+    use bytes::Bytes;
+
+    fn received(type_id: TypeId, count: u8, sequence: bool, data: &[u8]) -> Asdu {
+        let mut header = AsduHeader::new(type_id, count, Cot::Spontaneous, 1);
+        header.vsq.sequence = sequence;
+        let mut asdu = Asdu::new(header);
+        asdu.raw_data = Bytes::copy_from_slice(data);
+        asdu
+    }
+
+    #[test]
+    fn test_parse_single_point_time24() {
+        let data = [0x10, 0x00, 0x00, 0x81, 0x10, 0x27, 0x05];
+        let objects = received(TypeId::SinglePointTime24, 1, false, &data)
+            .info_objects()
+            .unwrap();
+        let expected = Cp24Time2a {
+            milliseconds: 10_000,
+            minutes: 5,
+            invalid: false,
+        };
+        assert_eq!(
+            objects,
+            vec![(
+                0x10,
+                InfoObject::SinglePoint {
+                    value: true,
+                    quality: QualityDescriptor::invalid(),
+                    time: Some(Timestamp::Partial(expected)),
+                }
+            )]
+        );
+    }
+
+    #[test]
+    fn test_parse_step_position_keeps_transient() {
+        // VTI 0x85: transient, position 5
+        let data = [0x01, 0x00, 0x00, 0x85, 0x00];
+        let objects = received(TypeId::StepPosition, 1, false, &data)
+            .info_objects()
+            .unwrap();
+        assert!(matches!(
+            objects[0].1,
+            InfoObject::StepPosition {
+                value: 5,
+                transient: true,
+                time: None,
+                ..
+            }
+        ));
+    }
+
+    #[test]
+    fn test_parse_measured_float_sequence() {
+        let mut data = vec![0x64, 0x00, 0x00];
+        for value in [1.5f32, -2.0] {
+            data.extend_from_slice(&value.to_le_bytes());
+            data.push(0x00);
+        }
+        let objects = received(TypeId::MeasuredFloat, 2, true, &data)
+            .info_objects()
+            .unwrap();
+        let values: Vec<(u32, f32)> = objects
+            .iter()
+            .map(|(ioa, object)| match object {
+                InfoObject::MeasuredFloat { value, .. } => (*ioa, *value),
+                other => panic!("unexpected {:?}", other),
+            })
+            .collect();
+        assert_eq!(values, vec![(100, 1.5), (101, -2.0)]);
+    }
+
+    #[test]
+    fn test_parse_measured_time24_sizes() {
+        // Scaled 1000, QDS overflow, CP24Time2a, then a second object
+        let data = [
+            0x01, 0x00, 0x00, 0xE8, 0x03, 0x01, 0x00, 0x00, 0x02, //
+            0x02, 0x00, 0x00, 0x18, 0xFC, 0x00, 0x00, 0x00, 0x03,
+        ];
+        let objects = received(TypeId::MeasuredScaledTime24, 2, false, &data)
+            .info_objects()
+            .unwrap();
+        assert_eq!(objects.len(), 2);
+        assert!(matches!(
+            objects[1],
+            (
+                2,
+                InfoObject::MeasuredScaled {
+                    value: -1000,
+                    time: Some(Timestamp::Partial(Cp24Time2a { minutes: 3, .. })),
+                    ..
+                }
+            )
+        ));
+    }
+
+    #[test]
+    fn test_parse_command_qualifier() {
+        let command = crate::command::Command::Single {
+            ioa: 0x20,
+            value: true,
+        };
+        let asdu = command.to_asdu(1, Cot::Activation, CommandQualifier::SELECT);
+        let objects = asdu.info_objects().unwrap();
+        assert_eq!(
+            objects,
+            vec![(
+                0x20,
+                InfoObject::SingleCommand {
+                    command: SingleCommandValue::new(
+                        true,
+                        CommandQualifier::new(PulseDuration::Unspecified, true),
+                    ),
+                    time: None,
+                }
+            )]
+        );
+
+        let received = Asdu::parse(&asdu.encode()).unwrap();
+        assert_eq!(received.info_objects().unwrap(), objects);
+    }
+
+    #[test]
+    fn test_parse_setpoint_qualifier() {
+        let command = crate::command::Command::SetpointScaled {
+            ioa: 3,
+            value: -2,
+            qos: Qos::EXECUTE,
+        };
+        let asdu = command.to_asdu(1, Cot::Activation, CommandQualifier::SELECT);
+        assert_eq!(
+            asdu.info_objects().unwrap(),
+            vec![(
+                3,
+                InfoObject::SetpointScaled {
+                    value: -2,
+                    qos: Qos::SELECT,
+                }
+            )]
+        );
+    }
+
+    #[test]
+    fn test_parse_clock_sync_and_file() {
+        let time = Cp56Time2a::from_bytes(&[0x10, 0x27, 30, 12, 0x6F, 6, 24]).unwrap();
+        let asdu = Asdu::clock_sync_command(1, time);
+        assert_eq!(
+            asdu.info_objects().unwrap(),
+            vec![(0, InfoObject::ClockSync { time })]
+        );
+
+        let call = FileObject::Call {
+            file: 2,
+            section: 1,
+            action: CallAction::RequestSection,
+            error: FileError::None,
+        };
+        let asdu = Asdu::file_transfer(1, 0x300, Cot::FileTransfer, &call);
+        assert_eq!(
+            asdu.info_objects().unwrap(),
+            vec![(0x300, InfoObject::File(call))]
+        );
+    }
+
+    #[test]
+    fn test_parse_too_short() {
+        let data = [0x01, 0x00, 0x00, 0x00, 0x00];
+        assert!(received(TypeId::MeasuredFloat, 1, false, &data)
+            .info_objects()
+            .is_err());
+        assert!(
+            received(TypeId::SinglePoint, 2, false, &[0x01, 0x00, 0x00, 0x01])
+                .info_objects()
+                .is_err()
+        );
+    }
+}
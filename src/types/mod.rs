@@ -8,15 +8,24 @@
 //! - `Asdu` - Application Service Data Unit
 //! - `DataPoint` - Unified data point structure
 //! - `DataValue` - Data value variants
+//! - `Coi` - Command and system qualifiers
+//! - `FileObject` - File transfer objects
+//! - `InfoObject` - Typed information objects
 
 mod apci;
 mod asdu;
 mod cot;
 mod data;
+mod file;
+mod info_object;
+mod qualifier;
 mod type_id;
 
 pub use apci::*;
 pub use asdu::*;
 pub use cot::*;
 pub use data::*;
+pub use file::*;
+pub use info_object::*;
+pub use qualifier::*;
 pub use type_id::*;
@@ -0,0 +1,850 @@
+//! IEC 60870-5-104 qualifiers.
+//!
+//! Qualifiers are the single-byte parameters carried by system and
+//! command information objects (COI, QRP, QCC, QPM, QOC, QOS, ...).
+
+use crate::error::{Iec104Error, Result};
+
+/// Cause of initialization (bits 0-6 of COI).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum InitCause {
+    /// Local power switched on (0)
+    LocalPowerOn,
+    /// Local manual reset (1)
+    LocalManualReset,
+    /// Remote reset (2)
+    RemoteReset,
+    /// Reserved for standard (3-31) or private (32-127) definitions
+    Other(u8),
+}
+
+impl InitCause {
+    /// Parse from the lower 7 bits of a COI byte.
+    #[inline]
+    pub const fn from_u8(value: u8) -> Self {
+        match value & 0x7F {
+            0 => Self::LocalPowerOn,
+            1 => Self::LocalManualReset,
+            2 => Self::RemoteReset,
+            other => Self::Other(other),
+        }
+    }
+
+    /// Convert to raw 7-bit value.
+    #[inline]
+    pub const fn as_u8(&self) -> u8 {
+        match self {
+            Self::LocalPowerOn => 0,
+            Self::LocalManualReset => 1,
+            Self::RemoteReset => 2,
+            Self::Other(value) => *value & 0x7F,
+        }
+    }
+}
+
+impl std::fmt::Display for InitCause {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::LocalPowerOn => write!(f, "LocalPowerOn"),
+            Self::LocalManualReset => write!(f, "LocalManualReset"),
+            Self::RemoteReset => write!(f, "RemoteReset"),
+            Self::Other(value) => write!(f, "Other({})", value),
+        }
+    }
+}
+
+/// Cause of initialization (COI) carried by M_EI_NA_1.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Coi {
+    /// Cause of initialization
+    pub cause: InitCause,
+    /// Initialization after change of local parameters (BS bit)
+    pub parameters_changed: bool,
+}
+
+impl Coi {
+    /// Create a new COI with unchanged local parameters.
+    #[inline]
+    pub const fn new(cause: InitCause) -> Self {
+        Self {
+            cause,
+            parameters_changed: false,
+        }
+    }
+
+    /// Parse from COI byte.
+    #[inline]
+    pub const fn from_u8(value: u8) -> Self {
+        Self {
+            cause: InitCause::from_u8(value),
+            parameters_changed: (value & 0x80) != 0,
+        }
+    }
+
+    /// Encode to COI byte.
+    #[inline]
+    pub const fn as_u8(&self) -> u8 {
+        self.cause.as_u8() | if self.parameters_changed { 0x80 } else { 0 }
+    }
+}
+
+impl std::fmt::Display for Coi {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.parameters_changed {
+            write!(f, "{} (parameters changed)", self.cause)
+        } else {
+            write!(f, "{}", self.cause)
+        }
+    }
+}
+
+/// Qualifier of reset process command (QRP) carried by C_RP_NA_1.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ResetProcessQualifier {
+    /// General reset of process (1)
+    GeneralReset,
+    /// Reset of pending information with time tag of the event buffer (2)
+    ResetEventBuffer,
+    /// Not used (0), reserved for standard (3-127) or private (128-255) definitions
+    Other(u8),
+}
+
+impl ResetProcessQualifier {
+    /// Parse from QRP byte.
+    #[inline]
+    pub const fn from_u8(value: u8) -> Self {
+        match value {
+            1 => Self::GeneralReset,
+            2 => Self::ResetEventBuffer,
+            other => Self::Other(other),
+        }
+    }
+
+    /// Encode to QRP byte.
+    #[inline]
+    pub const fn as_u8(&self) -> u8 {
+        match self {
+            Self::GeneralReset => 1,
+            Self::ResetEventBuffer => 2,
+            Self::Other(value) => *value,
+        }
+    }
+}
+
+impl std::fmt::Display for ResetProcessQualifier {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::GeneralReset => write!(f, "GeneralReset"),
+            Self::ResetEventBuffer => write!(f, "ResetEventBuffer"),
+            Self::Other(value) => write!(f, "Other({})", value),
+        }
+    }
+}
+
+/// Counter group requested by a counter interrogation (RQT, bits 0-5 of QCC).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum CounterGroup {
+    /// No counter requested (0)
+    None,
+    /// Counter group 1-4 (1-4)
+    Group(u8),
+    /// General request counter (5)
+    General,
+    /// Reserved for standard (6-31) or private (32-63) definitions
+    Other(u8),
+}
+
+impl CounterGroup {
+    /// Parse from the lower 6 bits of a QCC byte.
+    #[inline]
+    pub const fn from_u8(value: u8) -> Self {
+        match value & 0x3F {
+            0 => Self::None,
+            group @ 1..=4 => Self::Group(group),
+            5 => Self::General,
+            other => Self::Other(other),
+        }
+    }
+
+    /// Convert to raw 6-bit value.
+    #[inline]
+    pub const fn as_u8(&self) -> u8 {
+        match self {
+            Self::None => 0,
+            Self::Group(group) | Self::Other(group) => *group & 0x3F,
+            Self::General => 5,
+        }
+    }
+}
+
+/// Freeze/reset operation of a counter interrogation (FRZ, bits 6-7 of QCC).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum CounterFreeze {
+    /// Read without freeze or reset (0)
+    Read,
+    /// Counter freeze without reset (1)
+    Freeze,
+    /// Counter freeze with reset (2)
+    FreezeAndReset,
+    /// Counter reset (3)
+    Reset,
+}
+
+impl CounterFreeze {
+    /// Parse from the upper 2 bits of a QCC byte.
+    #[inline]
+    pub const fn from_u8(value: u8) -> Self {
+        match (value >> 6) & 0x03 {
+            0 => Self::Read,
+            1 => Self::Freeze,
+            2 => Self::FreezeAndReset,
+            _ => Self::Reset,
+        }
+    }
+
+    /// Convert to the upper 2 bits of a QCC byte.
+    #[inline]
+    pub const fn as_u8(&self) -> u8 {
+        match self {
+            Self::Read => 0x00,
+            Self::Freeze => 0x40,
+            Self::FreezeAndReset => 0x80,
+            Self::Reset => 0xC0,
+        }
+    }
+}
+
+/// Qualifier of counter interrogation command (QCC) carried by C_CI_NA_1.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Qcc {
+    /// Requested counter group
+    pub group: CounterGroup,
+    /// Freeze/reset operation
+    pub freeze: CounterFreeze,
+}
+
+impl Qcc {
+    /// Create a new QCC.
+    #[inline]
+    pub const fn new(group: CounterGroup, freeze: CounterFreeze) -> Self {
+        Self { group, freeze }
+    }
+
+    /// Read all counters without freeze or reset.
+    #[inline]
+    pub const fn general_read() -> Self {
+        Self::new(CounterGroup::General, CounterFreeze::Read)
+    }
+
+    /// Parse from QCC byte.
+    #[inline]
+    pub const fn from_u8(value: u8) -> Self {
+        Self {
+            group: CounterGroup::from_u8(value),
+            freeze: CounterFreeze::from_u8(value),
+        }
+    }
+
+    /// Encode to QCC byte.
+    #[inline]
+    pub const fn as_u8(&self) -> u8 {
+        self.group.as_u8() | self.freeze.as_u8()
+    }
+}
+
+impl std::fmt::Display for Qcc {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.group {
+            CounterGroup::None => write!(f, "none")?,
+            CounterGroup::Group(group) => write!(f, "group {}", group)?,
+            CounterGroup::General => write!(f, "general")?,
+            CounterGroup::Other(value) => write!(f, "Other({})", value)?,
+        }
+        write!(f, " {:?}", self.freeze)
+    }
+}
+
+/// Kind of parameter (KPA, bits 0-5 of QPM).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum ParameterKind {
+    /// Threshold value (1)
+    Threshold,
+    /// Smoothing factor (filter time constant) (2)
+    SmoothingFactor,
+    /// Low limit for transmission of measured values (3)
+    LowLimit,
+    /// High limit for transmission of measured values (4)
+    HighLimit,
+    /// Not used (0), reserved for standard (5-31) or private (32-63) definitions
+    Other(u8),
+}
+
+impl ParameterKind {
+    /// Parse from the lower 6 bits of a QPM byte.
+    #[inline]
+    pub const fn from_u8(value: u8) -> Self {
+        match value & 0x3F {
+            1 => Self::Threshold,
+            2 => Self::SmoothingFactor,
+            3 => Self::LowLimit,
+            4 => Self::HighLimit,
+            other => Self::Other(other),
+        }
+    }
+
+    /// Convert to raw 6-bit value.
+    #[inline]
+    pub const fn as_u8(&self) -> u8 {
+        match self {
+            Self::Threshold => 1,
+            Self::SmoothingFactor => 2,
+            Self::LowLimit => 3,
+            Self::HighLimit => 4,
+            Self::Other(value) => *value & 0x3F,
+        }
+    }
+}
+
+/// Qualifier of parameter of measured values (QPM) carried by P_ME_NA_1,
+/// P_ME_NB_1 and P_ME_NC_1.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Qpm {
+    /// Kind of parameter
+    pub kind: ParameterKind,
+    /// Local parameter change (LPC bit)
+    pub local_change: bool,
+    /// Parameter not in operation (POP bit)
+    pub out_of_operation: bool,
+}
+
+impl Qpm {
+    /// Create a new QPM for a parameter in operation without local change.
+    #[inline]
+    pub const fn new(kind: ParameterKind) -> Self {
+        Self {
+            kind,
+            local_change: false,
+            out_of_operation: false,
+        }
+    }
+
+    /// Parse from QPM byte.
+    #[inline]
+    pub const fn from_u8(value: u8) -> Self {
+        Self {
+            kind: ParameterKind::from_u8(value),
+            local_change: (value & 0x40) != 0,
+            out_of_operation: (value & 0x80) != 0,
+        }
+    }
+
+    /// Encode to QPM byte.
+    #[inline]
+    pub const fn as_u8(&self) -> u8 {
+        self.kind.as_u8()
+            | if self.local_change { 0x40 } else { 0 }
+            | if self.out_of_operation { 0x80 } else { 0 }
+    }
+}
+
+impl std::fmt::Display for Qpm {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:?}", self.kind)?;
+        if self.local_change {
+            write!(f, " (local change)")?;
+        }
+        if self.out_of_operation {
+            write!(f, " (not in operation)")?;
+        }
+        Ok(())
+    }
+}
+
+/// Qualifier of parameter activation (QPA) carried by P_AC_NA_1.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ParameterActivationQualifier {
+    /// Act/deact of the previously loaded parameters, IOA 0 (1)
+    LoadedParameters,
+    /// Act/deact of the parameter of the addressed object (2)
+    ObjectParameter,
+    /// Act/deact of persistent cyclic or periodic transmission of the
+    /// addressed object (3)
+    CyclicTransmission,
+    /// Not used (0), reserved for standard (4-127) or private (128-255) definitions
+    Other(u8),
+}
+
+impl ParameterActivationQualifier {
+    /// Parse from QPA byte.
+    #[inline]
+    pub const fn from_u8(value: u8) -> Self {
+        match value {
+            1 => Self::LoadedParameters,
+            2 => Self::ObjectParameter,
+            3 => Self::CyclicTransmission,
+            other => Self::Other(other),
+        }
+    }
+
+    /// Encode to QPA byte.
+    #[inline]
+    pub const fn as_u8(&self) -> u8 {
+        match self {
+            Self::LoadedParameters => 1,
+            Self::ObjectParameter => 2,
+            Self::CyclicTransmission => 3,
+            Self::Other(value) => *value,
+        }
+    }
+}
+
+/// Qualifier of command output duration (QU, bits 2-6 of QOC).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum PulseDuration {
+    /// No additional definition (0)
+    #[default]
+    Unspecified,
+    /// Short pulse duration (1)
+    Short,
+    /// Long pulse duration (2)
+    Long,
+    /// Persistent output (3)
+    Persistent,
+    /// Reserved for standard (4-15) or private (16-31) definitions
+    Other(u8),
+}
+
+impl PulseDuration {
+    /// Parse from a 5-bit QU value.
+    #[inline]
+    pub const fn from_u8(value: u8) -> Self {
+        match value & 0x1F {
+            0 => Self::Unspecified,
+            1 => Self::Short,
+            2 => Self::Long,
+            3 => Self::Persistent,
+            other => Self::Other(other),
+        }
+    }
+
+    /// Convert to raw 5-bit QU value.
+    #[inline]
+    pub const fn as_u8(&self) -> u8 {
+        match self {
+            Self::Unspecified => 0,
+            Self::Short => 1,
+            Self::Long => 2,
+            Self::Persistent => 3,
+            Self::Other(value) => *value & 0x1F,
+        }
+    }
+}
+
+/// Qualifier of command (QOC): output duration and select/execute.
+///
+/// Occupies bits 2-7 of the SCO, DCO and RCO bytes. The default executes
+/// with no additional definition.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct CommandQualifier {
+    /// Output duration (QU)
+    pub pulse: PulseDuration,
+    /// Select (true) or execute (false) (S/E)
+    pub select: bool,
+}
+
+impl CommandQualifier {
+    /// Execute with no additional definition.
+    pub const EXECUTE: Self = Self::new(PulseDuration::Unspecified, false);
+
+    /// Select with no additional definition.
+    pub const SELECT: Self = Self::new(PulseDuration::Unspecified, true);
+
+    /// Create a new command qualifier.
+    #[inline]
+    pub const fn new(pulse: PulseDuration, select: bool) -> Self {
+        Self { pulse, select }
+    }
+
+    /// Same qualifier with the given S/E bit.
+    #[inline]
+    pub const fn with_select(self, select: bool) -> Self {
+        Self::new(self.pulse, select)
+    }
+
+    /// Parse from an SCO/DCO/RCO byte (bits 0-1 are ignored).
+    #[inline]
+    pub const fn from_u8(value: u8) -> Self {
+        Self {
+            pulse: PulseDuration::from_u8(value >> 2),
+            select: (value & 0x80) != 0,
+        }
+    }
+
+    /// Encode to bits 2-7 of an SCO/DCO/RCO byte.
+    #[inline]
+    pub const fn as_u8(&self) -> u8 {
+        (self.pulse.as_u8() << 2) | if self.select { 0x80 } else { 0 }
+    }
+}
+
+/// Qualifier of command, under its abbreviation in IEC 60870-5-101.
+pub type Qoc = CommandQualifier;
+
+/// Single command (SCO) carried by C_SC_NA_1 and C_SC_TA_1.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct SingleCommandValue {
+    /// Command state (SCS, bit 0): true = ON
+    pub value: bool,
+    /// Qualifier of command (bits 2-7)
+    pub qualifier: CommandQualifier,
+}
+
+impl SingleCommandValue {
+    /// Create a new SCO.
+    #[inline]
+    pub const fn new(value: bool, qualifier: CommandQualifier) -> Self {
+        Self { value, qualifier }
+    }
+
+    /// Parse from SCO byte (bit 1 is reserved and ignored).
+    #[inline]
+    pub const fn from_u8(value: u8) -> Self {
+        Self::new((value & 0x01) != 0, CommandQualifier::from_u8(value))
+    }
+
+    /// Encode to SCO byte.
+    #[inline]
+    pub const fn as_u8(&self) -> u8 {
+        self.value as u8 | self.qualifier.as_u8()
+    }
+}
+
+/// Double command state (DCS, bits 0-1 of DCO).
+///
+/// The encodings 0 and 3 are not permitted.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum DoubleCommandState {
+    /// OFF (1)
+    Off,
+    /// ON (2)
+    On,
+}
+
+impl DoubleCommandState {
+    /// Parse from the lower 2 bits of a DCO byte.
+    #[inline]
+    pub fn from_u8(value: u8) -> Result<Self> {
+        match value & 0x03 {
+            1 => Ok(Self::Off),
+            2 => Ok(Self::On),
+            other => Err(Iec104Error::InvalidAsdu(
+                format!("Double command state {} is not permitted", other).into(),
+            )),
+        }
+    }
+
+    /// Convert to raw DCS value.
+    #[inline]
+    pub const fn as_u8(&self) -> u8 {
+        match self {
+            Self::Off => 1,
+            Self::On => 2,
+        }
+    }
+}
+
+/// Double command (DCO) carried by C_DC_NA_1 and C_DC_TA_1.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct DoubleCommandValue {
+    /// Command state (DCS, bits 0-1)
+    pub state: DoubleCommandState,
+    /// Qualifier of command (bits 2-7)
+    pub qualifier: CommandQualifier,
+}
+
+impl DoubleCommandValue {
+    /// Create a new DCO.
+    #[inline]
+    pub const fn new(state: DoubleCommandState, qualifier: CommandQualifier) -> Self {
+        Self { state, qualifier }
+    }
+
+    /// Parse from DCO byte, rejecting the states 0 and 3.
+    #[inline]
+    pub fn from_u8(value: u8) -> Result<Self> {
+        Ok(Self::new(DoubleCommandState::from_u8(value)?, CommandQualifier::from_u8(value)))
+    }
+
+    /// Encode to DCO byte.
+    #[inline]
+    pub const fn as_u8(&self) -> u8 {
+        self.state.as_u8() | self.qualifier.as_u8()
+    }
+}
+
+/// Regulating step command state (RCS, bits 0-1 of RCO).
+///
+/// The encodings 0 and 3 are not permitted.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum StepCommand {
+    /// Next step lower (1)
+    Lower,
+    /// Next step higher (2)
+    Higher,
+}
+
+impl StepCommand {
+    /// Parse from the lower 2 bits of an RCO byte.
+    #[inline]
+    pub fn from_u8(value: u8) -> Result<Self> {
+        match value & 0x03 {
+            1 => Ok(Self::Lower),
+            2 => Ok(Self::Higher),
+            other => Err(Iec104Error::InvalidAsdu(
+                format!("Regulating step state {} is not permitted", other).into(),
+            )),
+        }
+    }
+
+    /// Convert to raw RCS value.
+    #[inline]
+    pub const fn as_u8(&self) -> u8 {
+        match self {
+            Self::Lower => 1,
+            Self::Higher => 2,
+        }
+    }
+}
+
+/// Regulating step command (RCO) carried by C_RC_NA_1.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct RegulatingStepValue {
+    /// Step direction (RCS, bits 0-1)
+    pub step: StepCommand,
+    /// Qualifier of command (bits 2-7)
+    pub qualifier: CommandQualifier,
+}
+
+impl RegulatingStepValue {
+    /// Create a new RCO.
+    #[inline]
+    pub const fn new(step: StepCommand, qualifier: CommandQualifier) -> Self {
+        Self { step, qualifier }
+    }
+
+    /// Parse from RCO byte, rejecting the states 0 and 3.
+    #[inline]
+    pub fn from_u8(value: u8) -> Result<Self> {
+        Ok(Self::new(StepCommand::from_u8(value)?, CommandQualifier::from_u8(value)))
+    }
+
+    /// Encode to RCO byte.
+    #[inline]
+    pub const fn as_u8(&self) -> u8 {
+        self.step.as_u8() | self.qualifier.as_u8()
+    }
+}
+
+/// Qualifier of set-point command (QOS) carried by C_SE_NA_1, C_SE_NB_1,
+/// C_SE_NC_1 and C_SE_TC_1.
+///
+/// The default executes with the default qualifier.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct Qos {
+    /// Qualifier (QL, bits 0-6): default (0), reserved for standard (1-63)
+    /// or private (64-127) definitions
+    pub ql: u8,
+    /// Select (true) or execute (false) (S/E)
+    pub select: bool,
+}
+
+impl Qos {
+    /// Execute with the default qualifier.
+    pub const EXECUTE: Self = Self::new(0, false);
+
+    /// Select with the default qualifier.
+    pub const SELECT: Self = Self::new(0, true);
+
+    /// Create a new QOS.
+    #[inline]
+    pub const fn new(ql: u8, select: bool) -> Self {
+        Self {
+            ql: ql & 0x7F,
+            select,
+        }
+    }
+
+    /// Same qualifier with the given S/E bit.
+    #[inline]
+    pub const fn with_select(self, select: bool) -> Self {
+        Self::new(self.ql, select)
+    }
+
+    /// Parse from QOS byte.
+    #[inline]
+    pub const fn from_u8(value: u8) -> Self {
+        Self::new(value, (value & 0x80) != 0)
+    }
+
+    /// Encode to QOS byte.
+    #[inline]
+    pub const fn as_u8(&self) -> u8 {
+        (self.ql & 0x7F) | if self.select { 0x80 } else { 0 }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_coi_roundtrip() {
+        for value in [0x00, 0x01, 0x02, 0x05, 0x7F, 0x80, 0x81, 0x82, 0xFF] {
+            assert_eq!(Coi::from_u8(value).as_u8(), value);
+        }
+    }
+
+    #[test]
+    fn test_coi_fields() {
+        let coi = Coi::from_u8(0x82);
+        assert_eq!(coi.cause, InitCause::RemoteReset);
+        assert!(coi.parameters_changed);
+
+        let coi = Coi::from_u8(0x00);
+        assert_eq!(coi.cause, InitCause::LocalPowerOn);
+        assert!(!coi.parameters_changed);
+
+        assert_eq!(Coi::from_u8(0x21).cause, InitCause::Other(33));
+    }
+
+    #[test]
+    fn test_coi_display() {
+        assert_eq!(Coi::new(InitCause::LocalManualReset).to_string(), "LocalManualReset");
+        assert_eq!(Coi::from_u8(0x82).to_string(), "RemoteReset (parameters changed)");
+    }
+
+    #[test]
+    fn test_qrp_roundtrip() {
+        assert_eq!(ResetProcessQualifier::from_u8(1), ResetProcessQualifier::GeneralReset);
+        assert_eq!(ResetProcessQualifier::from_u8(2), ResetProcessQualifier::ResetEventBuffer);
+        assert_eq!(ResetProcessQualifier::from_u8(0), ResetProcessQualifier::Other(0));
+        for value in [0u8, 1, 2, 3, 127, 128, 255] {
+            assert_eq!(ResetProcessQualifier::from_u8(value).as_u8(), value);
+        }
+    }
+
+    #[test]
+    fn test_qcc_roundtrip() {
+        for value in 0..=u8::MAX {
+            assert_eq!(Qcc::from_u8(value).as_u8(), value);
+        }
+    }
+
+    #[test]
+    fn test_qcc_fields() {
+        let qcc = Qcc::from_u8(0x45);
+        assert_eq!(qcc.group, CounterGroup::General);
+        assert_eq!(qcc.freeze, CounterFreeze::Freeze);
+
+        let qcc = Qcc::new(CounterGroup::Group(2), CounterFreeze::FreezeAndReset);
+        assert_eq!(qcc.as_u8(), 0x82);
+        assert_eq!(qcc.to_string(), "group 2 FreezeAndReset");
+
+        assert_eq!(Qcc::general_read().as_u8(), 0x05);
+        assert_eq!(Qcc::from_u8(0xC0).freeze, CounterFreeze::Reset);
+        assert_eq!(Qcc::from_u8(0x06).group, CounterGroup::Other(6));
+    }
+
+    #[test]
+    fn test_qpm_roundtrip() {
+        for value in 0..=u8::MAX {
+            assert_eq!(Qpm::from_u8(value).as_u8(), value);
+        }
+    }
+
+    #[test]
+    fn test_qpm_fields() {
+        let qpm = Qpm::from_u8(0xC2);
+        assert_eq!(qpm.kind, ParameterKind::SmoothingFactor);
+        assert!(qpm.local_change);
+        assert!(qpm.out_of_operation);
+        assert_eq!(qpm.to_string(), "SmoothingFactor (local change) (not in operation)");
+
+        assert_eq!(Qpm::new(ParameterKind::Threshold).as_u8(), 0x01);
+        assert_eq!(Qpm::new(ParameterKind::HighLimit).to_string(), "HighLimit");
+        assert_eq!(Qpm::from_u8(0x00).kind, ParameterKind::Other(0));
+    }
+
+    #[test]
+    fn test_qpa_roundtrip() {
+        assert_eq!(
+            ParameterActivationQualifier::from_u8(3),
+            ParameterActivationQualifier::CyclicTransmission
+        );
+        for value in [0u8, 1, 2, 3, 4, 127, 128, 255] {
+            assert_eq!(ParameterActivationQualifier::from_u8(value).as_u8(), value);
+        }
+    }
+
+    #[test]
+    fn test_command_qualifier() {
+        assert_eq!(CommandQualifier::default(), CommandQualifier::EXECUTE);
+        assert_eq!(CommandQualifier::SELECT.as_u8(), 0x80);
+
+        let qoc = CommandQualifier::new(PulseDuration::Short, true);
+        assert_eq!(qoc.as_u8(), 0x84);
+        assert_eq!(CommandQualifier::new(PulseDuration::Persistent, false).as_u8(), 0x0C);
+        assert_eq!(CommandQualifier::new(PulseDuration::Other(31), false).as_u8(), 0x7C);
+
+        // State bits are ignored when parsing
+        assert_eq!(CommandQualifier::from_u8(0x86), qoc);
+        assert_eq!(qoc.with_select(false).as_u8(), 0x04);
+        assert_eq!(Qoc::from_u8(0x84), qoc);
+        for value in (0..=u8::MAX).step_by(4) {
+            assert_eq!(CommandQualifier::from_u8(value).as_u8(), value);
+        }
+    }
+
+    #[test]
+    fn test_command_values() {
+        let sco = SingleCommandValue::from_u8(0x85);
+        assert_eq!(sco, SingleCommandValue::new(true, Qoc::new(PulseDuration::Short, true)));
+        assert_eq!(sco.as_u8(), 0x85);
+
+        let dco = DoubleCommandValue::from_u8(0x0E).unwrap();
+        assert_eq!(dco.state, DoubleCommandState::On);
+        assert_eq!(dco.qualifier.pulse, PulseDuration::Persistent);
+        assert_eq!(dco.as_u8(), 0x0E);
+        assert!(DoubleCommandValue::from_u8(0x80).is_err());
+        assert!(DoubleCommandValue::from_u8(0x03).is_err());
+
+        let rco = RegulatingStepValue::from_u8(0x01).unwrap();
+        assert_eq!(rco, RegulatingStepValue::new(StepCommand::Lower, Qoc::EXECUTE));
+        assert!(RegulatingStepValue::from_u8(0x00).is_err());
+
+        for value in 0..=u8::MAX {
+            // Bit 1 of the SCO is reserved
+            assert_eq!(SingleCommandValue::from_u8(value).as_u8(), value & !0x02);
+            if let Ok(dco) = DoubleCommandValue::from_u8(value) {
+                assert_eq!(dco.as_u8(), value);
+            }
+            if let Ok(rco) = RegulatingStepValue::from_u8(value) {
+                assert_eq!(rco.as_u8(), value);
+            }
+        }
+    }
+
+    #[test]
+    fn test_qos() {
+        assert_eq!(Qos::default(), Qos::EXECUTE);
+        assert_eq!(Qos::SELECT.as_u8(), 0x80);
+        assert_eq!(Qos::new(64, true).as_u8(), 0xC0);
+        assert_eq!(Qos::from_u8(0x85), Qos::new(5, true));
+        assert_eq!(Qos::from_u8(0x85).with_select(false).as_u8(), 0x05);
+        for value in 0..=u8::MAX {
+            assert_eq!(Qos::from_u8(value).as_u8(), value);
+        }
+    }
+}
@@ -8,6 +8,7 @@ use crate::error::{Iec104Error, Result};
 ///
 /// Defines the type of information contained in an ASDU.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 #[repr(u8)]
 pub enum TypeId {
     // ============================================
@@ -52,15 +53,36 @@ pub enum TypeId {
     /// Integrated totals (M_IT_NA_1)
     IntegratedTotals = 15,
 
+    /// Packed single-point information with status change detection (M_PS_NA_1)
+    PackedSinglePoint = 20,
+
+    /// Measured value, normalized without quality descriptor (M_ME_ND_1)
+    MeasuredNormalizedNoQuality = 21,
+
     /// Single-point information with time tag CP56Time2a (M_SP_TB_1)
     SinglePointTime56 = 30,
 
     /// Double-point information with time tag CP56Time2a (M_DP_TB_1)
     DoublePointTime56 = 31,
 
+    /// Step position information with time tag CP56Time2a (M_ST_TB_1)
+    StepPositionTime56 = 32,
+
+    /// Bitstring of 32 bit with time tag CP56Time2a (M_BO_TB_1)
+    Bitstring32Time56 = 33,
+
+    /// Measured value, normalized with time tag CP56Time2a (M_ME_TD_1)
+    MeasuredNormalizedTime56 = 34,
+
+    /// Measured value, scaled with time tag CP56Time2a (M_ME_TE_1)
+    MeasuredScaledTime56 = 35,
+
     /// Measured value, short floating point with time tag CP56Time2a (M_ME_TF_1)
     MeasuredFloatTime56 = 36,
 
+    /// Integrated totals with time tag CP56Time2a (M_IT_TB_1)
+    IntegratedTotalsTime56 = 37,
+
     // ============================================
     // Process information in control direction
     // ============================================
@@ -123,13 +145,62 @@ pub enum TypeId {
 
     /// Test command with time tag CP56Time2a (C_TS_TA_1)
     TestCommandTime56 = 107,
+
+    // ============================================
+    // Parameter in control direction
+    // ============================================
+    /// Parameter of measured value, normalized value (P_ME_NA_1)
+    ParameterNormalized = 110,
+
+    /// Parameter of measured value, scaled value (P_ME_NB_1)
+    ParameterScaled = 111,
+
+    /// Parameter of measured value, short floating point (P_ME_NC_1)
+    ParameterFloat = 112,
+
+    /// Parameter activation (P_AC_NA_1)
+    ParameterActivation = 113,
+
+    // ============================================
+    // File transfer
+    // ============================================
+    /// File ready (F_FR_NA_1)
+    FileReady = 120,
+
+    /// Section ready (F_SR_NA_1)
+    SectionReady = 121,
+
+    /// Call directory, select file, call file, call section (F_SC_NA_1)
+    FileCall = 122,
+
+    /// Last section, last segment (F_LS_NA_1)
+    LastSection = 123,
+
+    /// Acknowledge file, acknowledge section (F_AF_NA_1)
+    FileAck = 124,
+
+    /// Segment (F_SG_NA_1)
+    FileSegment = 125,
+
+    /// Directory (F_DR_TA_1)
+    FileDirectory = 126,
+
+    /// Query log, request archive file (F_SC_NB_1)
+    QueryLog = 127,
+
+    /// Type identification not known to this crate, kept as its raw value.
+    ///
+    /// Decoded for the private range (128-255) of vendor extensions, and for
+    /// unassigned standard values with lenient decoding. The information
+    /// objects are left in the ASDU's raw payload (see `Asdu::opaque`).
+    Other(u8),
 }
 
 /// Compile-time element size lookup table.
 /// Maps TypeId raw value to element size (without IOA).
 /// Unknown types map to 0.
-const ELEMENT_SIZE_TABLE: [usize; 128] = {
-    let mut table = [0usize; 128];
+const ELEMENT_SIZE_TABLE: [usize; 256] = {
+    let mut table = [0usize; 256];
 
     // Process information in monitoring direction
     table[1] = 1;   // SinglePoint: SIQ (1)
@@ -145,9 +216,16 @@ const ELEMENT_SIZE_TABLE: [usize; 128] = {
     table[13] = 5;  // MeasuredFloat: IEEE + QDS (4+1)
     table[14] = 8;  // MeasuredFloatTime24: IEEE + QDS + CP24Time2a (4+1+3)
     table[15] = 5;  // IntegratedTotals: BCR (5)
+    table[20] = 5;  // PackedSinglePoint: SCD + QDS (4+1)
+    table[21] = 2;  // MeasuredNormalizedNoQuality: NVA (2)
     table[30] = 8;  // SinglePointTime56: SIQ + CP56Time2a (1+7)
     table[31] = 8;  // DoublePointTime56: DIQ + CP56Time2a (1+7)
+    table[32] = 9;  // StepPositionTime56: VTI + QDS + CP56Time2a (1+1+7)
+    table[33] = 12; // Bitstring32Time56: BSI + QDS + CP56Time2a (4+1+7)
+    table[34] = 10; // MeasuredNormalizedTime56: NVA + QDS + CP56Time2a (2+1+7)
+    table[35] = 10; // MeasuredScaledTime56: SVA + QDS + CP56Time2a (2+1+7)
     table[36] = 12; // MeasuredFloatTime56: IEEE + QDS + CP56Time2a (4+1+7)
+    table[37] = 12; // IntegratedTotalsTime56: BCR + CP56Time2a (5+7)
 
     // Process information in control direction
     table[45] = 1;  // SingleCommand: SCO (1)
@@ -171,10 +249,83 @@ const ELEMENT_SIZE_TABLE: [usize; 128] = {
     table[105] = 1; // ResetProcess: QRP (1)
     table[107] = 9; // TestCommandTime56: FBP + CP56Time2a (2+7)
 
+    // Parameters
+    table[110] = 3; // ParameterNormalized: NVA + QPM (2+1)
+    table[111] = 3; // ParameterScaled: SVA + QPM (2+1)
+    table[112] = 5; // ParameterFloat: IEEE + QPM (4+1)
+    table[113] = 1; // ParameterActivation: QPA (1)
+
+    // File transfer
+    table[120] = 6;  // FileReady: NOF + LOF + FRQ (2+3+1)
+    table[121] = 7;  // SectionReady: NOF + NOS + LOF + SRQ (2+1+3+1)
+    table[122] = 4;  // FileCall: NOF + NOS + SCQ (2+1+1)
+    table[123] = 5;  // LastSection: NOF + NOS + LSQ + CHS (2+1+1+1)
+    table[124] = 4;  // FileAck: NOF + NOS + AFQ (2+1+1)
+    table[125] = 4;  // FileSegment: NOF + NOS + LOS (2+1+1), followed by LOS octets
+    table[126] = 13; // FileDirectory: NOF + LOF + SOF + CP56Time2a (2+3+1+7)
+    table[127] = 16; // QueryLog: NOF + 2 x CP56Time2a (2+7+7)
+
     table
 };
 
 impl TypeId {
+    /// All supported type identifications, in ascending order.
+    pub const ALL: &[Self] = &[
+        Self::SinglePoint,
+        Self::SinglePointTime24,
+        Self::DoublePoint,
+        Self::DoublePointTime24,
+        Self::StepPosition,
+        Self::Bitstring32,
+        Self::MeasuredNormalized,
+        Self::MeasuredNormalizedTime24,
+        Self::MeasuredScaled,
+        Self::MeasuredScaledTime24,
+        Self::MeasuredFloat,
+        Self::MeasuredFloatTime24,
+        Self::IntegratedTotals,
+        Self::PackedSinglePoint,
+        Self::MeasuredNormalizedNoQuality,
+        Self::SinglePointTime56,
+        Self::DoublePointTime56,
+        Self::StepPositionTime56,
+        Self::Bitstring32Time56,
+        Self::MeasuredNormalizedTime56,
+        Self::MeasuredScaledTime56,
+        Self::MeasuredFloatTime56,
+        Self::IntegratedTotalsTime56,
+        Self::SingleCommand,
+        Self::DoubleCommand,
+        Self::RegulatingStep,
+        Self::SetpointNormalized,
+        Self::SetpointScaled,
+        Self::SetpointFloat,
+        Self::Bitstring32Command,
+        Self::SingleCommandTime56,
+        Self::DoubleCommandTime56,
+        Self::SetpointFloatTime56,
+        Self::EndOfInit,
+        Self::InterrogationCommand,
+        Self::CounterInterrogation,
+        Self::ReadCommand,
+        Self::ClockSync,
+        Self::TestCommand,
+        Self::ResetProcess,
+        Self::TestCommandTime56,
+        Self::ParameterNormalized,
+        Self::ParameterScaled,
+        Self::ParameterFloat,
+        Self::ParameterActivation,
+        Self::FileReady,
+        Self::SectionReady,
+        Self::FileCall,
+        Self::LastSection,
+        Self::FileAck,
+        Self::FileSegment,
+        Self::FileDirectory,
+        Self::QueryLog,
+    ];
+
     /// Get the element size for this TypeId (without IOA).
     /// Returns 0 for unknown types.
     /// This is a compile-time constant lookup.
@@ -200,9 +351,16 @@ impl TypeId {
             13 => Ok(Self::MeasuredFloat),
             14 => Ok(Self::MeasuredFloatTime24),
             15 => Ok(Self::IntegratedTotals),
+            20 => Ok(Self::PackedSinglePoint),
+            21 => Ok(Self::MeasuredNormalizedNoQuality),
             30 => Ok(Self::SinglePointTime56),
             31 => Ok(Self::DoublePointTime56),
+            32 => Ok(Self::StepPositionTime56),
+            33 => Ok(Self::Bitstring32Time56),
+            34 => Ok(Self::MeasuredNormalizedTime56),
+            35 => Ok(Self::MeasuredScaledTime56),
             36 => Ok(Self::MeasuredFloatTime56),
+            37 => Ok(Self::IntegratedTotalsTime56),
             45 => Ok(Self::SingleCommand),
             46 => Ok(Self::DoubleCommand),
             47 => Ok(Self::RegulatingStep),
@@ -221,26 +379,114 @@ impl TypeId {
             104 => Ok(Self::TestCommand),
             105 => Ok(Self::ResetProcess),
             107 => Ok(Self::TestCommandTime56),
+            110 => Ok(Self::ParameterNormalized),
+            111 => Ok(Self::ParameterScaled),
+            112 => Ok(Self::ParameterFloat),
+            113 => Ok(Self::ParameterActivation),
+            120 => Ok(Self::FileReady),
+            121 => Ok(Self::SectionReady),
+            122 => Ok(Self::FileCall),
+            123 => Ok(Self::LastSection),
+            124 => Ok(Self::FileAck),
+            125 => Ok(Self::FileSegment),
+            126 => Ok(Self::FileDirectory),
+            127 => Ok(Self::QueryLog),
             _ => Err(Iec104Error::UnknownTypeId(value)),
         }
     }
 
+    /// Create TypeId from raw byte value, mapping unknown values to
+    /// [`TypeId::Other`] instead of failing.
+    #[inline]
+    pub fn from_raw(value: u8) -> Self {
+        Self::from_u8(value).unwrap_or(Self::Other(value))
+    }
+
     /// Convert to raw byte value.
     #[inline]
     pub const fn as_u8(self) -> u8 {
-        self as u8
+        match self {
+            Self::SinglePoint => 1,
+            Self::SinglePointTime24 => 2,
+            Self::DoublePoint => 3,
+            Self::DoublePointTime24 => 4,
+            Self::StepPosition => 5,
+            Self::Bitstring32 => 7,
+            Self::MeasuredNormalized => 9,
+            Self::MeasuredNormalizedTime24 => 10,
+            Self::MeasuredScaled => 11,
+            Self::MeasuredScaledTime24 => 12,
+            Self::MeasuredFloat => 13,
+            Self::MeasuredFloatTime24 => 14,
+            Self::IntegratedTotals => 15,
+            Self::PackedSinglePoint => 20,
+            Self::MeasuredNormalizedNoQuality => 21,
+            Self::SinglePointTime56 => 30,
+            Self::DoublePointTime56 => 31,
+            Self::StepPositionTime56 => 32,
+            Self::Bitstring32Time56 => 33,
+            Self::MeasuredNormalizedTime56 => 34,
+            Self::MeasuredScaledTime56 => 35,
+            Self::MeasuredFloatTime56 => 36,
+            Self::IntegratedTotalsTime56 => 37,
+            Self::SingleCommand => 45,
+            Self::DoubleCommand => 46,
+            Self::RegulatingStep => 47,
+            Self::SetpointNormalized => 48,
+            Self::SetpointScaled => 49,
+            Self::SetpointFloat => 50,
+            Self::Bitstring32Command => 51,
+            Self::SingleCommandTime56 => 58,
+            Self::DoubleCommandTime56 => 59,
+            Self::SetpointFloatTime56 => 63,
+            Self::EndOfInit => 70,
+            Self::InterrogationCommand => 100,
+            Self::CounterInterrogation => 101,
+            Self::ReadCommand => 102,
+            Self::ClockSync => 103,
+            Self::TestCommand => 104,
+            Self::ResetProcess => 105,
+            Self::TestCommandTime56 => 107,
+            Self::ParameterNormalized => 110,
+            Self::ParameterScaled => 111,
+            Self::ParameterFloat => 112,
+            Self::ParameterActivation => 113,
+            Self::FileReady => 120,
+            Self::SectionReady => 121,
+            Self::FileCall => 122,
+            Self::LastSection => 123,
+            Self::FileAck => 124,
+            Self::FileSegment => 125,
+            Self::FileDirectory => 126,
+            Self::QueryLog => 127,
+            Self::Other(value) => value,
+        }
     }
 
     /// Check if this type is in the monitoring direction (from RTU to master).
     #[inline]
     pub const fn is_monitoring(&self) -> bool {
-        matches!(self.as_u8(), 1..=70)
+        !matches!(self, Self::Other(_)) && matches!(self.as_u8(), 1..=70)
     }
 
     /// Check if this type is in the control direction (from master to RTU).
     #[inline]
     pub const fn is_control(&self) -> bool {
-        matches!(self.as_u8(), 45..=51 | 58..=63 | 100..=107)
+        !matches!(self, Self::Other(_))
+            && matches!(self.as_u8(), 45..=51 | 58..=63 | 100..=113)
+    }
+
+    /// Check if this type belongs to file transfer (used in both directions).
+    #[inline]
+    pub const fn is_file_transfer(&self) -> bool {
+        !matches!(self, Self::Other(_)) && matches!(self.as_u8(), 120..=127)
+    }
+
+    /// Check if this type is in the private range (128-255) reserved for
+    /// vendor extensions.
+    #[inline]
+    pub const fn is_private(&self) -> bool {
+        self.as_u8() >= 128
     }
 
     /// Check if this type contains a time tag.
@@ -255,14 +501,82 @@ impl TypeId {
                 | Self::MeasuredFloatTime24
                 | Self::SinglePointTime56
                 | Self::DoublePointTime56
+                | Self::StepPositionTime56
+                | Self::Bitstring32Time56
+                | Self::MeasuredNormalizedTime56
+                | Self::MeasuredScaledTime56
                 | Self::MeasuredFloatTime56
+                | Self::IntegratedTotalsTime56
                 | Self::SingleCommandTime56
                 | Self::DoubleCommandTime56
                 | Self::SetpointFloatTime56
                 | Self::TestCommandTime56
+                | Self::FileDirectory
         )
     }
 
+    /// Get the information elements of one object, in wire order
+    /// (e.g., `["SIQ", "CP56Time2a"]` for M_SP_TB_1).
+    #[inline]
+    pub const fn information_elements(&self) -> &'static [&'static str] {
+        match self {
+            Self::SinglePoint => &["SIQ"],
+            Self::SinglePointTime24 => &["SIQ", "CP24Time2a"],
+            Self::DoublePoint => &["DIQ"],
+            Self::DoublePointTime24 => &["DIQ", "CP24Time2a"],
+            Self::StepPosition => &["VTI", "QDS"],
+            Self::Bitstring32 => &["BSI", "QDS"],
+            Self::MeasuredNormalized => &["NVA", "QDS"],
+            Self::MeasuredNormalizedTime24 => &["NVA", "QDS", "CP24Time2a"],
+            Self::MeasuredScaled => &["SVA", "QDS"],
+            Self::MeasuredScaledTime24 => &["SVA", "QDS", "CP24Time2a"],
+            Self::MeasuredFloat => &["IEEE STD 754", "QDS"],
+            Self::MeasuredFloatTime24 => &["IEEE STD 754", "QDS", "CP24Time2a"],
+            Self::IntegratedTotals => &["BCR"],
+            Self::PackedSinglePoint => &["SCD", "QDS"],
+            Self::MeasuredNormalizedNoQuality => &["NVA"],
+            Self::SinglePointTime56 => &["SIQ", "CP56Time2a"],
+            Self::DoublePointTime56 => &["DIQ", "CP56Time2a"],
+            Self::StepPositionTime56 => &["VTI", "QDS", "CP56Time2a"],
+            Self::Bitstring32Time56 => &["BSI", "QDS", "CP56Time2a"],
+            Self::MeasuredNormalizedTime56 => &["NVA", "QDS", "CP56Time2a"],
+            Self::MeasuredScaledTime56 => &["SVA", "QDS", "CP56Time2a"],
+            Self::MeasuredFloatTime56 => &["IEEE STD 754", "QDS", "CP56Time2a"],
+            Self::IntegratedTotalsTime56 => &["BCR", "CP56Time2a"],
+            Self::SingleCommand => &["SCO"],
+            Self::DoubleCommand => &["DCO"],
+            Self::RegulatingStep => &["RCO"],
+            Self::SetpointNormalized => &["NVA", "QOS"],
+            Self::SetpointScaled => &["SVA", "QOS"],
+            Self::SetpointFloat => &["IEEE STD 754", "QOS"],
+            Self::Bitstring32Command => &["BSI"],
+            Self::SingleCommandTime56 => &["SCO", "CP56Time2a"],
+            Self::DoubleCommandTime56 => &["DCO", "CP56Time2a"],
+            Self::SetpointFloatTime56 => &["IEEE STD 754", "QOS", "CP56Time2a"],
+            Self::EndOfInit => &["COI"],
+            Self::InterrogationCommand => &["QOI"],
+            Self::CounterInterrogation => &["QCC"],
+            Self::ReadCommand => &[],
+            Self::ClockSync => &["CP56Time2a"],
+            Self::TestCommand => &["FBP"],
+            Self::ResetProcess => &["QRP"],
+            Self::TestCommandTime56 => &["TSC", "CP56Time2a"],
+            Self::ParameterNormalized => &["NVA", "QPM"],
+            Self::ParameterScaled => &["SVA", "QPM"],
+            Self::ParameterFloat => &["IEEE STD 754", "QPM"],
+            Self::ParameterActivation => &["QPA"],
+            Self::FileReady => &["NOF", "LOF", "FRQ"],
+            Self::SectionReady => &["NOF", "NOS", "LOF", "SRQ"],
+            Self::FileCall => &["NOF", "NOS", "SCQ"],
+            Self::LastSection => &["NOF", "NOS", "LSQ", "CHS"],
+            Self::FileAck => &["NOF", "NOS", "AFQ"],
+            Self::FileSegment => &["NOF", "NOS", "LOS", "segment"],
+            Self::FileDirectory => &["NOF", "LOF", "SOF", "CP56Time2a"],
+            Self::QueryLog => &["NOF", "CP56Time2a", "CP56Time2a"],
+            Self::Other(_) => &[],
+        }
+    }
+
     /// Get the IEC standard name (e.g., "M_SP_NA_1").
     #[inline]
     pub const fn standard_name(&self) -> &'static str {
@@ -280,9 +594,16 @@ impl TypeId {
             Self::MeasuredFloat => "M_ME_NC_1",
             Self::MeasuredFloatTime24 => "M_ME_TC_1",
             Self::IntegratedTotals => "M_IT_NA_1",
+            Self::PackedSinglePoint => "M_PS_NA_1",
+            Self::MeasuredNormalizedNoQuality => "M_ME_ND_1",
             Self::SinglePointTime56 => "M_SP_TB_1",
             Self::DoublePointTime56 => "M_DP_TB_1",
+            Self::StepPositionTime56 => "M_ST_TB_1",
+            Self::Bitstring32Time56 => "M_BO_TB_1",
+            Self::MeasuredNormalizedTime56 => "M_ME_TD_1",
+            Self::MeasuredScaledTime56 => "M_ME_TE_1",
             Self::MeasuredFloatTime56 => "M_ME_TF_1",
+            Self::IntegratedTotalsTime56 => "M_IT_TB_1",
             Self::SingleCommand => "C_SC_NA_1",
             Self::DoubleCommand => "C_DC_NA_1",
             Self::RegulatingStep => "C_RC_NA_1",
@@ -301,13 +622,29 @@ impl TypeId {
             Self::TestCommand => "C_TS_NA_1",
             Self::ResetProcess => "C_RP_NA_1",
             Self::TestCommandTime56 => "C_TS_TA_1",
+            Self::ParameterNormalized => "P_ME_NA_1",
+            Self::ParameterScaled => "P_ME_NB_1",
+            Self::ParameterFloat => "P_ME_NC_1",
+            Self::ParameterActivation => "P_AC_NA_1",
+            Self::FileReady => "F_FR_NA_1",
+            Self::SectionReady => "F_SR_NA_1",
+            Self::FileCall => "F_SC_NA_1",
+            Self::LastSection => "F_LS_NA_1",
+            Self::FileAck => "F_AF_NA_1",
+            Self::FileSegment => "F_SG_NA_1",
+            Self::FileDirectory => "F_DR_TA_1",
+            Self::QueryLog => "F_SC_NB_1",
+            Self::Other(_) => "unknown",
         }
     }
 }
 
 impl std::fmt::Display for TypeId {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "{}", self.standard_name())
+        match self {
+            Self::Other(value) => write!(f, "TypeId({})", value),
+            _ => write!(f, "{}", self.standard_name()),
+        }
     }
 }
 
@@ -323,6 +660,22 @@ mod tests {
         assert!(TypeId::from_u8(255).is_err());
     }
 
+    #[test]
+    fn test_type_id_from_raw() {
+        assert_eq!(TypeId::from_raw(13), TypeId::MeasuredFloat);
+        for value in [0u8, 6, 60, 128, 255] {
+            let type_id = TypeId::from_raw(value);
+            assert_eq!(type_id, TypeId::Other(value));
+            assert_eq!(type_id.as_u8(), value);
+            assert_eq!(type_id.element_size(), 0);
+            assert!(!type_id.is_monitoring() && !type_id.is_control());
+        }
+        assert_eq!(TypeId::Other(200).to_string(), "TypeId(200)");
+        for &type_id in TypeId::ALL {
+            assert_eq!(TypeId::from_raw(type_id.as_u8()), type_id);
+        }
+    }
+
     #[test]
     fn test_type_id_direction() {
         assert!(TypeId::SinglePoint.is_monitoring());
@@ -351,12 +704,14 @@ mod tests {
     #[test]
     fn test_type_id_all_values_roundtrip() {
         let valid_values = [
-            1, 2, 3, 4, 5, 7, 9, 10, 11, 12, 13, 14, 15,
-            30, 31, 36,
+            1, 2, 3, 4, 5, 7, 9, 10, 11, 12, 13, 14, 15, 20, 21,
+            30, 31, 32, 33, 34, 35, 36, 37,
             45, 46, 47, 48, 49, 50, 51,
             58, 59, 63,
             70,
             100, 101, 102, 103, 104, 105, 107,
+            110, 111, 112, 113,
+            120, 121, 122, 123, 124, 125, 126, 127,
         ];
 
         for val in valid_values {
@@ -365,10 +720,34 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_type_id_all_matches_from_u8() {
+        let parsed: Vec<TypeId> = (0..=255u8).filter_map(|v| TypeId::from_u8(v).ok()).collect();
+        assert_eq!(parsed, TypeId::ALL);
+    }
+
+    #[test]
+    fn test_type_id_information_elements() {
+        assert_eq!(TypeId::SinglePointTime56.information_elements(), &["SIQ", "CP56Time2a"]);
+        assert!(TypeId::ReadCommand.information_elements().is_empty());
+
+        // The clock synchronization time and the query log range are the
+        // payload itself, not a time tag
+        let payload_times = [TypeId::ClockSync, TypeId::QueryLog];
+        for type_id in TypeId::ALL.iter().filter(|t| !payload_times.contains(t)) {
+            assert_eq!(
+                type_id.has_time_tag(),
+                type_id.information_elements().iter().any(|e| e.starts_with("CP")),
+                "{:?}",
+                type_id
+            );
+        }
+    }
+
     #[test]
     fn test_type_id_invalid_values() {
         // Test some invalid type IDs
-        let invalid_values = [0, 6, 8, 16, 17, 29, 32, 44, 52, 60, 71, 99, 106, 108, 200, 255];
+        let invalid_values = [0, 6, 8, 16, 17, 29, 38, 44, 52, 60, 71, 99, 106, 108, 114, 200, 255];
 
         for val in invalid_values {
             let result = TypeId::from_u8(val);
@@ -393,9 +772,16 @@ mod tests {
             TypeId::MeasuredFloat,
             TypeId::MeasuredFloatTime24,
             TypeId::IntegratedTotals,
+            TypeId::PackedSinglePoint,
+            TypeId::MeasuredNormalizedNoQuality,
             TypeId::SinglePointTime56,
             TypeId::DoublePointTime56,
+            TypeId::StepPositionTime56,
+            TypeId::Bitstring32Time56,
+            TypeId::MeasuredNormalizedTime56,
+            TypeId::MeasuredScaledTime56,
             TypeId::MeasuredFloatTime56,
+            TypeId::IntegratedTotalsTime56,
             TypeId::EndOfInit,
         ];
 
@@ -425,6 +811,10 @@ mod tests {
             TypeId::TestCommand,
             TypeId::ResetProcess,
             TypeId::TestCommandTime56,
+            TypeId::ParameterNormalized,
+            TypeId::ParameterScaled,
+            TypeId::ParameterFloat,
+            TypeId::ParameterActivation,
         ];
 
         for type_id in control_types {
@@ -443,11 +833,17 @@ mod tests {
             TypeId::MeasuredFloatTime24,
             TypeId::SinglePointTime56,
             TypeId::DoublePointTime56,
+            TypeId::StepPositionTime56,
+            TypeId::Bitstring32Time56,
+            TypeId::MeasuredNormalizedTime56,
+            TypeId::MeasuredScaledTime56,
             TypeId::MeasuredFloatTime56,
+            TypeId::IntegratedTotalsTime56,
             TypeId::SingleCommandTime56,
             TypeId::DoubleCommandTime56,
             TypeId::SetpointFloatTime56,
             TypeId::TestCommandTime56,
+            TypeId::FileDirectory,
         ];
 
         for type_id in time_tagged {
@@ -493,9 +889,16 @@ mod tests {
             (TypeId::MeasuredFloat, "M_ME_NC_1"),
             (TypeId::MeasuredFloatTime24, "M_ME_TC_1"),
             (TypeId::IntegratedTotals, "M_IT_NA_1"),
+            (TypeId::PackedSinglePoint, "M_PS_NA_1"),
+            (TypeId::MeasuredNormalizedNoQuality, "M_ME_ND_1"),
             (TypeId::SinglePointTime56, "M_SP_TB_1"),
             (TypeId::DoublePointTime56, "M_DP_TB_1"),
+            (TypeId::StepPositionTime56, "M_ST_TB_1"),
+            (TypeId::Bitstring32Time56, "M_BO_TB_1"),
+            (TypeId::MeasuredNormalizedTime56, "M_ME_TD_1"),
+            (TypeId::MeasuredScaledTime56, "M_ME_TE_1"),
             (TypeId::MeasuredFloatTime56, "M_ME_TF_1"),
+            (TypeId::IntegratedTotalsTime56, "M_IT_TB_1"),
             (TypeId::SingleCommand, "C_SC_NA_1"),
             (TypeId::DoubleCommand, "C_DC_NA_1"),
             (TypeId::RegulatingStep, "C_RC_NA_1"),
@@ -514,6 +917,18 @@ mod tests {
             (TypeId::TestCommand, "C_TS_NA_1"),
             (TypeId::ResetProcess, "C_RP_NA_1"),
             (TypeId::TestCommandTime56, "C_TS_TA_1"),
+            (TypeId::ParameterNormalized, "P_ME_NA_1"),
+            (TypeId::ParameterScaled, "P_ME_NB_1"),
+            (TypeId::ParameterFloat, "P_ME_NC_1"),
+            (TypeId::ParameterActivation, "P_AC_NA_1"),
+            (TypeId::FileReady, "F_FR_NA_1"),
+            (TypeId::SectionReady, "F_SR_NA_1"),
+            (TypeId::FileCall, "F_SC_NA_1"),
+            (TypeId::LastSection, "F_LS_NA_1"),
+            (TypeId::FileAck, "F_AF_NA_1"),
+            (TypeId::FileSegment, "F_SG_NA_1"),
+            (TypeId::FileDirectory, "F_DR_TA_1"),
+            (TypeId::QueryLog, "F_SC_NB_1"),
         ];
 
         for (type_id, expected_name) in types_and_names {
@@ -532,6 +947,7 @@ mod tests {
         assert_eq!(TypeId::EndOfInit.as_u8(), 70);
         assert_eq!(TypeId::InterrogationCommand.as_u8(), 100);
         assert_eq!(TypeId::TestCommandTime56.as_u8(), 107);
+        assert_eq!(TypeId::ParameterActivation.as_u8(), 113);
     }
 
     // ============ Element Size Lookup Table Tests ============
@@ -552,9 +968,16 @@ mod tests {
         assert_eq!(TypeId::MeasuredFloat.element_size(), 5);
         assert_eq!(TypeId::MeasuredFloatTime24.element_size(), 8);
         assert_eq!(TypeId::IntegratedTotals.element_size(), 5);
+        assert_eq!(TypeId::PackedSinglePoint.element_size(), 5);
+        assert_eq!(TypeId::MeasuredNormalizedNoQuality.element_size(), 2);
         assert_eq!(TypeId::SinglePointTime56.element_size(), 8);
         assert_eq!(TypeId::DoublePointTime56.element_size(), 8);
+        assert_eq!(TypeId::StepPositionTime56.element_size(), 9);
+        assert_eq!(TypeId::Bitstring32Time56.element_size(), 12);
+        assert_eq!(TypeId::MeasuredNormalizedTime56.element_size(), 10);
+        assert_eq!(TypeId::MeasuredScaledTime56.element_size(), 10);
         assert_eq!(TypeId::MeasuredFloatTime56.element_size(), 12);
+        assert_eq!(TypeId::IntegratedTotalsTime56.element_size(), 12);
     }
 
     #[test]
@@ -583,6 +1006,26 @@ mod tests {
         assert_eq!(TypeId::TestCommand.element_size(), 2);
         assert_eq!(TypeId::ResetProcess.element_size(), 1);
         assert_eq!(TypeId::TestCommandTime56.element_size(), 9);
+        assert_eq!(TypeId::ParameterNormalized.element_size(), 3);
+        assert_eq!(TypeId::ParameterScaled.element_size(), 3);
+        assert_eq!(TypeId::ParameterFloat.element_size(), 5);
+        assert_eq!(TypeId::ParameterActivation.element_size(), 1);
+    }
+
+    #[test]
+    fn test_file_transfer_types() {
+        for value in 120..=127 {
+            let type_id = TypeId::from_u8(value).unwrap();
+            assert!(type_id.is_file_transfer());
+            assert!(!type_id.is_control());
+            assert!(!type_id.is_monitoring());
+        }
+        assert!(!TypeId::ParameterActivation.is_file_transfer());
+        assert_eq!(TypeId::FileReady.element_size(), 6);
+        assert_eq!(TypeId::SectionReady.element_size(), 7);
+        assert_eq!(TypeId::FileSegment.element_size(), 4);
+        assert_eq!(TypeId::FileDirectory.element_size(), 13);
+        assert_eq!(TypeId::QueryLog.element_size(), 16);
     }
 
     #[test]
@@ -0,0 +1,360 @@
+//! Event stream of a spawned client served over WebSocket.
+//!
+//! [`router`] answers WebSocket upgrades at `/events`; the
+//! [`HttpGateway`](crate::http::HttpGateway) router includes it. Each
+//! connection receives the client's events as JSON text frames:
+//!
+//! ```json
+//! {"seq": 41, "event": "DataUpdate", "points": [ ... ]}
+//! {"seq": 42, "event": "CommandConfirm", "ioa": 5, "success": true}
+//! {"seq": 43, "event": "Disconnected"}
+//! ```
+//!
+//! `seq` is the position in the client's event stream (see
+//! [`SequencedEvent`]) and `event` the [`Iec104Event`] variant; points are
+//! in the canonical form of [`crate::json`]. Further fields depend on the
+//! variant and are only ever added.
+//!
+//! The query string selects the events of a connection, as an
+//! [`EventFilter`] does; each parameter takes a comma-separated list:
+//!
+//! ```text
+//! /events?ca=1,2&ioa=100-199,300&type=M_ME_NC_1
+//! ```
+//!
+//! A browser that reads too slowly never delays the IEC 104 connection.
+//! Once [`QUEUE_LENGTH`] frames are waiting, newer events are dropped and
+//! the next frame sent is `{"lagged": n}` with the number lost.
+
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+
+use axum::extract::ws::{Message, WebSocket, WebSocketUpgrade};
+use axum::extract::{RawQuery, State};
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+use axum::routing::get;
+use axum::Router;
+use futures::{SinkExt, StreamExt};
+use serde_json::{json, Value};
+use tokio::sync::mpsc;
+
+use crate::client::{Iec104Event, SequencedEvent};
+use crate::error::{Iec104Error, Result};
+use crate::filter::EventFilter;
+use crate::handle::ClientHandle;
+use crate::http::session_json;
+use crate::json::point_value;
+use crate::types::TypeId;
+
+/// Frames queued for a slow connection before events are dropped.
+pub const QUEUE_LENGTH: usize = 256;
+
+/// Router serving the event stream of `handle` at `/events`.
+pub fn router(handle: ClientHandle) -> Router {
+    Router::new()
+        .route("/events", get(upgrade))
+        .with_state(handle)
+}
+
+async fn upgrade(
+    State(handle): State<ClientHandle>,
+    RawQuery(query): RawQuery,
+    upgrade: WebSocketUpgrade,
+) -> Response {
+    let filter = match parse_filter(query.as_deref().unwrap_or("")) {
+        Ok(filter) => filter,
+        Err(err) => return (StatusCode::BAD_REQUEST, err.to_string()).into_response(),
+    };
+    // Subscribe before upgrading so a stopped client is reported as an error
+    let events = match handle.subscribe_filtered(filter).await {
+        Ok(events) => events,
+        Err(err) => return (StatusCode::SERVICE_UNAVAILABLE, err.to_string()).into_response(),
+    };
+    upgrade.on_upgrade(move |socket| stream(socket, events))
+}
+
+/// Forward events until the client task ends or the browser goes away.
+async fn stream(socket: WebSocket, mut events: mpsc::Receiver<SequencedEvent>) {
+    let (mut sink, mut incoming) = socket.split();
+    let (frames, mut queued) = mpsc::channel::<Value>(QUEUE_LENGTH);
+    let lagged = Arc::new(AtomicU64::new(0));
+
+    // The subscription is drained at once; only the queue waits for the socket
+    let dropped = lagged.clone();
+    let pump = tokio::spawn(async move {
+        while let Some(event) = events.recv().await {
+            if frames.try_send(event_json(&event)).is_err() {
+                if frames.is_closed() {
+                    break;
+                }
+                dropped.fetch_add(1, Ordering::Relaxed);
+            }
+        }
+    });
+
+    loop {
+        tokio::select! {
+            frame = queued.recv() => {
+                let Some(frame) = frame else {
+                    break;
+                };
+                let lost = lagged.swap(0, Ordering::Relaxed);
+                if lost > 0 {
+                    let notice = json!({ "lagged": lost }).to_string();
+                    if sink.send(Message::Text(notice.into())).await.is_err() {
+                        break;
+                    }
+                }
+                if sink.send(Message::Text(frame.to_string().into())).await.is_err() {
+                    break;
+                }
+            }
+            // Nothing is expected from the browser but its close
+            message = incoming.next() => match message {
+                Some(Ok(Message::Close(_))) | Some(Err(_)) | None => break,
+                Some(Ok(_)) => {}
+            },
+        }
+    }
+    pump.abort();
+    let _ = sink.close().await;
+}
+
+/// The JSON frame of an event (see the module documentation).
+fn event_json(event: &SequencedEvent) -> Value {
+    let (name, mut fields) = match &event.event {
+        Iec104Event::Connected => ("Connected", json!({})),
+        Iec104Event::Disconnected => ("Disconnected", json!({})),
+        Iec104Event::DataTransferStarted => ("DataTransferStarted", json!({})),
+        Iec104Event::SessionEstablished(session) => (
+            "SessionEstablished",
+            json!({ "session": session_json(session) }),
+        ),
+        Iec104Event::DataTransferStopped => ("DataTransferStopped", json!({})),
+        Iec104Event::DataUpdate(points) => (
+            "DataUpdate",
+            json!({ "points": points.iter().map(point_value).collect::<Vec<_>>() }),
+        ),
+        Iec104Event::AsduReceived(asdu) => (
+            "AsduReceived",
+            json!({
+                "type": asdu.header.type_id.standard_name(),
+                "cot": format!("{:?}", asdu.header.cot),
+                "ca": asdu.header.common_address,
+            }),
+        ),
+        Iec104Event::CommandConfirm { ioa, success } => {
+            ("CommandConfirm", json!({ "ioa": ioa, "success": success }))
+        }
+        Iec104Event::ResetProcessIssued {
+            common_address,
+            qualifier,
+        } => (
+            "ResetProcessIssued",
+            json!({ "ca": common_address, "qualifier": format!("{:?}", qualifier) }),
+        ),
+        Iec104Event::EndOfInitialization {
+            common_address,
+            coi,
+        } => (
+            "EndOfInitialization",
+            json!({ "ca": common_address, "coi": coi.to_string() }),
+        ),
+        Iec104Event::UnsolicitedUFrame(function) => (
+            "UnsolicitedUFrame",
+            json!({ "function": format!("{:?}", function) }),
+        ),
+        Iec104Event::Unacknowledged(asdus) => ("Unacknowledged", json!({ "count": asdus.len() })),
+        Iec104Event::InterrogationComplete { common_address } => {
+            ("InterrogationComplete", json!({ "ca": common_address }))
+        }
+        Iec104Event::CounterInterrogationComplete {
+            common_address,
+            qcc,
+        } => (
+            "CounterInterrogationComplete",
+            json!({ "ca": common_address, "qcc": qcc.to_string() }),
+        ),
+        Iec104Event::FileProgress {
+            common_address,
+            file,
+            received,
+            total,
+        } => (
+            "FileProgress",
+            json!({ "ca": common_address, "file": file, "received": received, "total": total }),
+        ),
+        Iec104Event::Error(message) => ("Error", json!({ "message": message })),
+    };
+    fields["seq"] = json!(event.seq);
+    fields["event"] = json!(name);
+    fields
+}
+
+/// Read the filter of a connection from its query string.
+fn parse_filter(query: &str) -> Result<EventFilter> {
+    let invalid = |part: &str| Iec104Error::protocol(format!("Invalid filter {:?}", part));
+    let mut filter = EventFilter::new();
+    for part in query.split('&').filter(|part| !part.is_empty()) {
+        let (key, values) = part.split_once('=').ok_or_else(|| invalid(part))?;
+        for value in values.split(',') {
+            filter = match key {
+                "ca" => filter.common_address(value.parse().map_err(|_| invalid(part))?),
+                "ioa" => {
+                    let (start, end) = value.split_once('-').unwrap_or((value, value));
+                    let start = start.parse().map_err(|_| invalid(part))?;
+                    let end = end.parse().map_err(|_| invalid(part))?;
+                    filter.ioa_range(start..=end)
+                }
+                "type" => filter.type_id(
+                    TypeId::ALL
+                        .iter()
+                        .copied()
+                        .find(|type_id| type_id.standard_name() == value)
+                        .ok_or_else(|| invalid(part))?,
+                ),
+                _ => return Err(invalid(part)),
+            };
+        }
+    }
+    Ok(filter)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::net::SocketAddr;
+    use tokio::io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader};
+    use tokio::net::{TcpListener, TcpStream};
+    use tokio::sync::oneshot;
+    use tokio_util::codec::Framed;
+
+    use crate::codec::{Apdu, Iec104Codec};
+    use crate::types::{AsduHeader, Cot, UFunction};
+    use crate::{Asdu, ClientConfig, Iec104Client};
+
+    /// Open a WebSocket to `path`, skipping the handshake response.
+    async fn connect(addr: SocketAddr, path: &str) -> BufReader<TcpStream> {
+        let mut stream = BufReader::new(TcpStream::connect(addr).await.unwrap());
+        let request = format!(
+            "GET {path} HTTP/1.1\r\nHost: {addr}\r\nUpgrade: websocket\r\n\
+             Connection: Upgrade\r\nSec-WebSocket-Key: dGhlIHNhbXBsZSBub25jZQ==\r\n\
+             Sec-WebSocket-Version: 13\r\n\r\n"
+        );
+        stream.write_all(request.as_bytes()).await.unwrap();
+        let mut line = String::new();
+        stream.read_line(&mut line).await.unwrap();
+        assert!(line.starts_with("HTTP/1.1 101"), "{}", line);
+        while line != "\r\n" {
+            line.clear();
+            stream.read_line(&mut line).await.unwrap();
+        }
+        stream
+    }
+
+    /// Next text message of the server, whose frames are unmasked and
+    /// unfragmented.
+    async fn recv_text(stream: &mut BufReader<TcpStream>) -> String {
+        loop {
+            let first = stream.read_u8().await.unwrap();
+            let second = stream.read_u8().await.unwrap();
+            let len = match second & 0x7F {
+                126 => stream.read_u16().await.unwrap() as usize,
+                127 => stream.read_u64().await.unwrap() as usize,
+                len => len as usize,
+            };
+            let mut payload = vec![0; len];
+            stream.read_exact(&mut payload).await.unwrap();
+            if first & 0x0F == 0x1 {
+                return String::from_utf8(payload).unwrap();
+            }
+        }
+    }
+
+    #[test]
+    fn test_parse_filter() {
+        assert_eq!(parse_filter("").unwrap(), EventFilter::new());
+        assert_eq!(
+            parse_filter("ca=1,2&ioa=100-199,300&type=M_ME_NC_1").unwrap(),
+            EventFilter::new()
+                .common_address(1)
+                .common_address(2)
+                .ioa_range(100..=199)
+                .ioa_range(300..=300)
+                .type_id(TypeId::MeasuredFloat)
+        );
+        for query in ["ca=x", "ioa=5-", "type=M_XX_NA_1", "station=1", "ca"] {
+            assert!(parse_filter(query).is_err(), "{}", query);
+        }
+    }
+
+    #[test]
+    fn test_event_json() {
+        let event = SequencedEvent {
+            seq: 7,
+            event: Iec104Event::CommandConfirm {
+                ioa: 5,
+                success: true,
+            },
+        };
+        assert_eq!(
+            event_json(&event),
+            json!({ "seq": 7, "event": "CommandConfirm", "ioa": 5, "success": true })
+        );
+        let event = SequencedEvent {
+            seq: 8,
+            event: Iec104Event::Disconnected,
+        };
+        assert_eq!(
+            event_json(&event),
+            json!({ "seq": 8, "event": "Disconnected" })
+        );
+    }
+
+    #[tokio::test]
+    async fn test_filtered_stream() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let (send_tx, send_rx) = oneshot::channel::<()>();
+        tokio::spawn(async move {
+            let (socket, _) = listener.accept().await.unwrap();
+            let mut server = Framed::new(socket, Iec104Codec::new());
+            server.next().await.unwrap().unwrap();
+            server
+                .send(Apdu::u_frame(UFunction::StartDtCon))
+                .await
+                .unwrap();
+
+            send_rx.await.unwrap();
+            let header = AsduHeader::new(TypeId::MeasuredFloat, 2, Cot::Spontaneous, 1);
+            let mut data = Asdu::new(header);
+            data.raw_data = bytes::Bytes::from_static(&[
+                100, 0, 0, 0x00, 0x00, 0xC0, 0x3F, 0, // IOA 100: 1.5
+                101, 0, 0, 0x00, 0x00, 0x00, 0x40, 0, // IOA 101: 2.0
+            ]);
+            server.send(Apdu::i_frame(0, 0, data)).await.unwrap();
+            while server.next().await.is_some() {}
+        });
+
+        let mut client = Iec104Client::new(ClientConfig::new(addr.to_string()));
+        client.connect().await.unwrap();
+        client.start_dt().await.unwrap();
+        let (handle, _task) = client.spawn();
+
+        let http = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let http_addr = http.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(http, router(handle)).await });
+
+        let mut socket = connect(http_addr, "/events?ca=1&ioa=100").await;
+        send_tx.send(()).unwrap();
+
+        let message = recv_text(&mut socket).await;
+        let frame: Value = serde_json::from_str(&message).unwrap();
+        assert_eq!(frame["event"], "DataUpdate");
+        let points = frame["points"].as_array().unwrap();
+        assert_eq!(points.len(), 1);
+        assert_eq!(points[0]["ioa"], 100);
+        assert_eq!(points[0]["value"], 1.5);
+    }
+}
@@ -0,0 +1,45 @@
+#!/bin/sh
+# Regenerate the test PKIs of the tls feature: CAs `a` and `b`, each with a
+# server certificate for rtu.example and a client certificate, plus a CRL of
+# CA `a` revoking its server certificate. Everything is valid until 2100.
+set -e
+cd "$(dirname "$0")"
+work=$(mktemp -d)
+trap 'rm -rf "$work"' EXIT
+
+key() {
+    openssl genpkey -algorithm EC -pkeyopt ec_paramgen_curve:P-256 -out "$1"
+}
+
+for ca in a b; do
+    mkdir -p "$ca"
+    key "$work/$ca.key"
+    openssl req -x509 -new -key "$work/$ca.key" -subj "/CN=IEC 104 test CA $ca" \
+        -days 27000 -set_serial 1 -out "$ca/ca.pem" \
+        -addext "basicConstraints=critical,CA:TRUE" \
+        -addext "keyUsage=critical,keyCertSign,cRLSign"
+    for leaf in "server rtu.example 2 serverAuth" "client scada-frontend 3 clientAuth"; do
+        set -- $leaf
+        key "$ca/$1.key"
+        openssl req -new -key "$ca/$1.key" -subj "/CN=$2" -out "$work/$1.csr"
+        printf 'subjectAltName=DNS:%s\nextendedKeyUsage=%s\nbasicConstraints=CA:FALSE\n' \
+            "$2" "$4" > "$work/$1.ext"
+        printf 'keyUsage=critical,digitalSignature\n' >> "$work/$1.ext"
+        openssl x509 -req -in "$work/$1.csr" -CA "$ca/ca.pem" -CAkey "$work/$ca.key" \
+            -set_serial "$3" -days 27000 -extfile "$work/$1.ext" -out "$ca/$1.pem"
+    done
+done
+
+touch "$work/index.txt"
+echo 01 > "$work/crlnumber"
+cat > "$work/ca.cnf" <<CNF
+[ca]
+default_ca = test
+[test]
+database = $work/index.txt
+crlnumber = $work/crlnumber
+default_md = sha256
+default_crl_days = 27000
+CNF
+openssl ca -config "$work/ca.cnf" -keyfile "$work/a.key" -cert a/ca.pem -revoke a/server.pem
+openssl ca -config "$work/ca.cnf" -keyfile "$work/a.key" -cert a/ca.pem -gencrl -out a/server.crl